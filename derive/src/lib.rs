@@ -0,0 +1,123 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Derive macros implementing RGB contract state traits for custom storage
+//! types. Use them through the `derive` feature of `rgb-core`, which
+//! re-exports the macros next to the traits they implement.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span};
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Result};
+
+const ATTR_NAME: &str = "contract_state";
+
+/// Implements `DynContractState` for a struct with named fields, forwarding
+/// all calls to its `ContractHistory` field marked with
+/// `#[contract_state(history)]`.
+///
+/// If the struct also has a field keeping the set of known operation ids,
+/// marked with `#[contract_state(ops)]` (like `BTreeSet<OpId>` or
+/// `HashSet<OpId>`), and a field mapping the spent outputs to the spending
+/// operation ids, marked with `#[contract_state(spends)]` (like
+/// `BTreeMap<Opout, OpId>` or `HashMap<Opout, OpId>`), `ContractStateMut` is
+/// implemented as well. See the `rgbcore::contract` facade documentation for
+/// an example.
+#[proc_macro_derive(DynContractState, attributes(contract_state))]
+pub fn derive_dyn_contract_state(input: TokenStream) -> TokenStream {
+    let derive_input = parse_macro_input!(input as DeriveInput);
+    contract_state_inner(derive_input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+fn contract_state_inner(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "DynContractState can't be derived for generic types",
+        ));
+    }
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "DynContractState can be derived only for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "DynContractState can be derived only for structs with named fields",
+        ));
+    };
+
+    let mut history = None;
+    let mut ops = None;
+    let mut spends = None;
+    for field in &fields.named {
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident(ATTR_NAME))
+        {
+            let role = attr.parse_args::<Ident>()?;
+            let slot = match role.to_string().as_str() {
+                "history" => &mut history,
+                "ops" => &mut ops,
+                "spends" => &mut spends,
+                _ => {
+                    return Err(Error::new_spanned(
+                        &role,
+                        "unknown contract state field role; expected `history`, `ops` or `spends`",
+                    ))
+                }
+            };
+            if slot.is_some() {
+                return Err(Error::new_spanned(&role, format!("duplicated `{role}` field")));
+            }
+            *slot = field.ident.clone();
+        }
+    }
+
+    let ty = &input.ident;
+    let Some(history) = history else {
+        return Err(Error::new(
+            Span::call_site(),
+            "DynContractState requires a field marked with `#[contract_state(history)]`",
+        ));
+    };
+    Ok(match (ops, spends) {
+        (None, None) => quote! {
+            ::rgbcore::impl_contract_state!(#ty { history: #history });
+        },
+        (Some(ops), Some(spends)) => quote! {
+            ::rgbcore::impl_contract_state!(#ty { history: #history, ops: #ops, spends: #spends });
+        },
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "fields marked with `#[contract_state(ops)]` and `#[contract_state(spends)]` must \
+                 be provided together",
+            ))
+        }
+    })
+}
@@ -0,0 +1,267 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Machine-readable changelog of the consensus rules.
+//!
+//! Each consensus rule enforced by the validator is described by a
+//! [`ConsensusRule`] constant, and each validation failure caused by a
+//! consensus rule violation references the rule (see
+//! [`crate::validation::Failure::rule`]). Auditors may compare [`RULES`]
+//! exported by different releases of the library to find out which rules
+//! were introduced, changed or retired.
+//!
+//! Rules which are not a part of a published release yet have no version:
+//! the version is assigned to them when the release is cut. Rules enforced
+//! before the changelog was introduced are reported as introduced by the
+//! first release containing the changelog.
+
+use core::fmt::{self, Display, Formatter};
+
+/// Description of a consensus rule enforced by the validator.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct ConsensusRule {
+    /// Stable identifier of the rule, which is never reused for other rules.
+    pub id: &'static str,
+    /// Human-readable description of the rule.
+    pub description: &'static str,
+    /// Version of the library which started to enforce the rule, or `None`
+    /// if the rule is not a part of a published release yet.
+    pub since: Option<&'static str>,
+    /// Version of the library which stopped to enforce the rule, if the rule
+    /// was retired.
+    pub until: Option<&'static str>,
+}
+
+impl ConsensusRule {
+    const fn new(id: &'static str, description: &'static str) -> Self {
+        ConsensusRule {
+            id,
+            description,
+            since: None,
+            until: None,
+        }
+    }
+
+    /// Detects whether the rule is enforced by this build of the library.
+    pub const fn is_active(&self) -> bool { self.until.is_none() }
+}
+
+impl Display for ConsensusRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.since {
+            Some(since) => write!(f, "{} (since {since}", self.id)?,
+            None => write!(f, "{} (unreleased", self.id)?,
+        }
+        if let Some(until) = self.until {
+            write!(f, ", until {until}")?;
+        }
+        write!(f, "): {}", self.description)
+    }
+}
+
+pub const NETWORK: ConsensusRule =
+    ConsensusRule::new("network", "contract is valid only on the network it was issued for");
+pub const NETWORK_SALT: ConsensusRule = ConsensusRule::new(
+    "network.chain_salt",
+    "contract id is salted for the chain network the contract is validated on",
+);
+pub const SCHEMA_VALIDITY: ConsensusRule = ConsensusRule::new(
+    "schema.validity",
+    "schema is internally consistent and references only known types and libraries",
+);
+pub const SCHEMA_MATCH: ConsensusRule = ConsensusRule::new(
+    "schema.match",
+    "contract is validated against the schema its genesis commits to",
+);
+pub const SCHEMA_CONFORMANCE: ConsensusRule = ConsensusRule::new(
+    "schema.conformance",
+    "operation metadata, global and owned state match the types and occurrences defined by the \
+     schema",
+);
+pub const SCHEMA_UPGRADE: ConsensusRule = ConsensusRule::new(
+    "schema.upgrade",
+    "schema upgrades are performed by well-formed state extensions and apply to the descending \
+     transitions mined after their activation, while unmined transitions must be valid under all \
+     schemata in their history",
+);
+pub const GRAPH_INTEGRITY: ConsensusRule = ConsensusRule::new(
+    "graph.integrity",
+    "operation graph is acyclic, complete and belongs to a single contract",
+);
+pub const GENESIS_METADATA: ConsensusRule = ConsensusRule::new(
+    "genesis.metadata",
+    "contract-wide parameters declared by the genesis metadata are well-formed",
+);
+pub const GENESIS_TEMPLATE: ConsensusRule = ConsensusRule::new(
+    "genesis.template",
+    "genesis template id declared by the genesis matches the genesis data",
+);
+pub const TERMS_IMMUTABLE: ConsensusRule =
+    ConsensusRule::new("terms.immutable", "contract terms are defined only by the genesis");
+pub const DEPENDENCIES: ConsensusRule =
+    ConsensusRule::new("dependencies", "contracts the contract depends on are validated before it");
+pub const BUNDLE_COMMITMENT: ConsensusRule = ConsensusRule::new(
+    "bundle.commitment",
+    "transition bundle commits to each transition it contains under the witness inputs closing \
+     the transition seals",
+);
+pub const SEAL_CLOSING: ConsensusRule = ConsensusRule::new(
+    "seal.closing",
+    "each spent seal is defined by an ancestor operation, unspent before, and closed by the \
+     witness committing to the spending operation",
+);
+pub const SEAL_LAYER1: ConsensusRule = ConsensusRule::new(
+    "seal.layer1",
+    "seals are defined only on the layers 1 allowed by the genesis",
+);
+pub const CLOSE_METHODS: ConsensusRule = ConsensusRule::new(
+    "seal.close_methods",
+    "bundles use only the seal close methods allowed by the genesis",
+);
+pub const WITNESS_CONSTRAINTS: ConsensusRule = ConsensusRule::new(
+    "witness.constraints",
+    "witness transactions satisfy the sequence and locktime constraints of the schema",
+);
+pub const WITNESS_SUBSTITUTION: ConsensusRule = ConsensusRule::new(
+    "witness.substitution",
+    "replaced and alternative witnesses preserve the seals and at most one alternative is mined",
+);
+pub const STATE_RANGE_PROOFS: ConsensusRule = ConsensusRule::new(
+    "state.range_proofs",
+    "concealed fungible state is accompanied by valid range proofs",
+);
+pub const SCRIPTS: ConsensusRule = ConsensusRule::new(
+    "script",
+    "validation scripts defined by the schema succeed for each operation",
+);
+pub const LOCK_SCRIPTS: ConsensusRule = ConsensusRule::new(
+    "script.locks",
+    "assignment locks are well-formed and succeed for the operations spending the assignments",
+);
+pub const OP_FEATURES: ConsensusRule = ConsensusRule::new(
+    "op.features",
+    "operation features are well-formed and supported by the validating software",
+);
+pub const SUPPLY_CAPS: ConsensusRule = ConsensusRule::new(
+    "state.supply_caps",
+    "issued fungible state doesn't exceed the supply caps declared by the genesis",
+);
+pub const ORACLE_STATE: ConsensusRule = ConsensusRule::new(
+    "state.oracle",
+    "oracle feed attestations and rotations are signed by the oracle set current at their \
+     position in the consensus order",
+);
+pub const VALENCY_LIMITS: ConsensusRule = ConsensusRule::new(
+    "state.valency_limits",
+    "valencies are redeemed within the limits and expiry declared by the genesis",
+);
+pub const ATTACHMENTS: ConsensusRule = ConsensusRule::new(
+    "attachment.integrity",
+    "available attachment content matches the digest, media type and size declared by the \
+     operation",
+);
+
+/// All consensus rules known to this build of the library, including the
+/// retired ones.
+pub const RULES: &[ConsensusRule] = &[
+    NETWORK,
+    NETWORK_SALT,
+    SCHEMA_VALIDITY,
+    SCHEMA_MATCH,
+    SCHEMA_CONFORMANCE,
+    SCHEMA_UPGRADE,
+    GRAPH_INTEGRITY,
+    GENESIS_METADATA,
+    GENESIS_TEMPLATE,
+    TERMS_IMMUTABLE,
+    DEPENDENCIES,
+    BUNDLE_COMMITMENT,
+    SEAL_CLOSING,
+    SEAL_LAYER1,
+    CLOSE_METHODS,
+    WITNESS_CONSTRAINTS,
+    WITNESS_SUBSTITUTION,
+    STATE_RANGE_PROOFS,
+    SCRIPTS,
+    LOCK_SCRIPTS,
+    OP_FEATURES,
+    SUPPLY_CAPS,
+    ORACLE_STATE,
+    VALENCY_LIMITS,
+    ATTACHMENTS,
+];
+
+/// Lists consensus rules enforced by this build of the library.
+pub fn active_rules() -> impl Iterator<Item = &'static ConsensusRule> {
+    RULES.iter().filter(|rule| rule.is_active())
+}
+
+/// Finds consensus rule by its identifier.
+pub fn rule(id: &str) -> Option<&'static ConsensusRule> { RULES.iter().find(|rule| rule.id == id) }
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::validation::Failure;
+    use crate::OpId;
+
+    #[test]
+    fn rule_ids_unique() {
+        let ids = RULES.iter().map(|rule| rule.id).collect::<BTreeSet<_>>();
+        assert_eq!(ids.len(), RULES.len());
+        assert_eq!(active_rules().count(), RULES.len());
+        assert_eq!(rule("seal.closing"), Some(&SEAL_CLOSING));
+    }
+
+    #[test]
+    fn rule_versions() {
+        assert!(RULES.iter().all(|rule| rule.since.is_none()));
+        assert_eq!(
+            SUPPLY_CAPS.to_string(),
+            "state.supply_caps (unreleased): issued fungible state doesn't exceed the supply caps \
+             declared by the genesis"
+        );
+        let released = ConsensusRule {
+            since: Some("0.11.0"),
+            until: Some("0.12.0"),
+            ..SUPPLY_CAPS
+        };
+        assert!(!released.is_active());
+        assert_eq!(
+            released.to_string(),
+            "state.supply_caps (since 0.11.0, until 0.12.0): issued fungible state doesn't exceed \
+             the supply caps declared by the genesis"
+        );
+    }
+
+    #[test]
+    fn failure_rules() {
+        let opid = OpId::from([0u8; 32]);
+        assert_eq!(Failure::TermsRedefined(opid).rule(), Some(TERMS_IMMUTABLE));
+        assert_eq!(Failure::Custom(s!("custom")).rule(), None);
+        assert!(Failure::CyclicGraph(opid)
+            .rule()
+            .is_some_and(|rule| RULES.contains(&rule)));
+    }
+}
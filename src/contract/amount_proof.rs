@@ -0,0 +1,197 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Selective disclosure of a single amount.
+//!
+//! Holder of an allocation may need to prove its amount to a third party
+//! (like an auditor) without revealing amounts of the other allocations
+//! created by the same operation. [`AmountDisclosure`] opens the Pedersen
+//! commitment of one fungible assignment and contains the concealed form of
+//! all other assignments of the operation, which is enough to recompute the
+//! operation id: the concealed siblings disclose neither their amounts nor
+//! their seals.
+
+use std::{iter, slice};
+
+use commit_verify::{CommitId, Conceal, MerkleHash, MerkleLeaves};
+
+use crate::{
+    AssignmentCommitment, AssignmentIndex, AssignmentsRef, ConcealedState, OpCommitment, OpId,
+    OpRef, Operation, RevealedValue,
+};
+
+/// Errors producing or verifying amount disclosure.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum AmountDisclosureError {
+    /// operation {0} has no assignment {1:?}.
+    NoAssignment(OpId, AssignmentIndex),
+
+    /// assignment {1:?} of operation {0} is not fungible.
+    NotFungible(OpId, AssignmentIndex),
+
+    /// amount of assignment {1:?} of operation {0} is not known.
+    Concealed(OpId, AssignmentIndex),
+
+    /// disclosed commitments produce operation id {1} instead of {0}.
+    OpIdMismatch(OpId, OpId),
+
+    /// disclosed assignments don't match assignments committed by operation
+    /// {0}.
+    AssignmentsMismatch(OpId),
+
+    /// disclosed amount doesn't match commitment of assignment {1:?} of
+    /// operation {0}.
+    AmountMismatch(OpId, AssignmentIndex),
+}
+
+/// Proof revealing amount of a single fungible assignment of an operation.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct AmountDisclosure {
+    pub opid: OpId,
+    pub op_commitment: OpCommitment,
+    /// Commitments to all assignments of the operation, in the order they are
+    /// committed into the operation id.
+    pub assignments: Vec<AssignmentCommitment>,
+    pub index: AssignmentIndex,
+    pub revealed: RevealedValue,
+}
+
+struct AssignmentLeaves<'proof>(&'proof [AssignmentCommitment]);
+
+impl<'proof> MerkleLeaves for AssignmentLeaves<'proof> {
+    type Leaf = AssignmentCommitment;
+    type LeafIter<'tmp>
+        = iter::Copied<slice::Iter<'tmp, AssignmentCommitment>>
+    where Self: 'tmp;
+
+    fn merkle_leaves(&self) -> Self::LeafIter<'_> { self.0.iter().copied() }
+}
+
+impl AmountDisclosure {
+    /// Produces disclosure of the amount of the fungible assignment `index`
+    /// of the operation. The amount of the assignment must be known.
+    pub fn prove(op: OpRef, index: AssignmentIndex) -> Result<Self, AmountDisclosureError> {
+        let opid = op.id();
+        let assignments = op.assignments();
+        let typed = assignments
+            .get(index.ty)
+            .ok_or(AmountDisclosureError::NoAssignment(opid, index))?;
+        if !typed.is_fungible() {
+            return Err(AmountDisclosureError::NotFungible(opid, index));
+        }
+        let revealed = *typed
+            .as_fungible()
+            .get(index.pos as usize)
+            .ok_or(AmountDisclosureError::NoAssignment(opid, index))?
+            .as_revealed_state()
+            .ok_or(AmountDisclosureError::Concealed(opid, index))?;
+        let op_commitment = match op {
+            OpRef::Genesis(genesis) => genesis.commit(),
+            OpRef::Transition(transition) => transition.commit(),
+            OpRef::Extension(extension) => extension.commit(),
+        };
+        let assignments = match assignments {
+            AssignmentsRef::Genesis(assignments) => assignments.merkle_leaves().collect(),
+            AssignmentsRef::Graph(assignments) => assignments.merkle_leaves().collect(),
+        };
+        Ok(AmountDisclosure {
+            opid,
+            op_commitment,
+            assignments,
+            index,
+            revealed,
+        })
+    }
+
+    /// Verifies that the disclosed amount is committed by the operation.
+    pub fn verify(&self) -> Result<(), AmountDisclosureError> {
+        let opid = self.op_commitment.commit_id();
+        if opid != self.opid {
+            return Err(AmountDisclosureError::OpIdMismatch(self.opid, opid));
+        }
+        let root = MerkleHash::merklize(&AssignmentLeaves(&self.assignments));
+        if root != self.op_commitment.assignments {
+            return Err(AmountDisclosureError::AssignmentsMismatch(self.opid));
+        }
+        let commitment = self
+            .assignments
+            .iter()
+            .filter(|leaf| leaf.ty == self.index.ty)
+            .nth(self.index.pos as usize)
+            .ok_or(AmountDisclosureError::NoAssignment(self.opid, self.index))?;
+        let ConcealedState::Fungible(concealed) = commitment.state else {
+            return Err(AmountDisclosureError::NotFungible(self.opid, self.index));
+        };
+        if concealed != self.revealed.conceal() {
+            return Err(AmountDisclosureError::AmountMismatch(self.opid, self.index));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+    use amplify::Wrapper;
+    use bp::seals::txout::CloseMethod;
+    use bp::Vout;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{
+        AssetTag, Assign, AssignmentType, Assignments, BlindingSeed, Layer1, Transition,
+        TypedAssigns,
+    };
+
+    #[test]
+    fn disclose_single_amount() {
+        let seed = BlindingSeed::from([0x33; 32]);
+        let tag = AssetTag::strict_dumb();
+        let ty = AssignmentType::with(4000);
+        let allocations = [100u64, 250]
+            .into_iter()
+            .zip(0u32..)
+            .map(|(amount, no)| {
+                let method = CloseMethod::OpretFirst;
+                let seal = seed.graph_seal(Layer1::Bitcoin, method, None, Vout::from(no), no);
+                Assign::revealed(seal, RevealedValue::new_random_blinding(amount, tag))
+            })
+            .collect::<Vec<_>>();
+        let mut transition = Transition::strict_dumb();
+        transition.assignments = Assignments::from_inner(
+            Confined::try_from(bmap! {
+                ty => TypedAssigns::Fungible(Confined::try_from(allocations).unwrap())
+            })
+            .unwrap(),
+        );
+
+        let index = AssignmentIndex::new(ty, 1);
+        let mut proof = AmountDisclosure::prove(OpRef::Transition(&transition), index).unwrap();
+        assert_eq!(proof.opid, transition.id());
+        assert_eq!(proof.verify(), Ok(()));
+
+        proof.revealed = RevealedValue::new_random_blinding(250u64, tag);
+        let opid = proof.opid;
+        assert_eq!(proof.verify(), Err(AmountDisclosureError::AmountMismatch(opid, index)));
+    }
+}
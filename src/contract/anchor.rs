@@ -20,8 +20,6 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::cmp::Ordering;
-
 use bp::dbc::opret::{OpretError, OpretProof};
 use bp::dbc::tapret::TapretProof;
 use bp::dbc::Method;
@@ -130,23 +128,6 @@ pub struct WitnessAnchor {
     pub witness_id: XWitnessId,
 }
 
-impl PartialOrd for WitnessAnchor {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
-}
-
-impl Ord for WitnessAnchor {
-    fn cmp(&self, other: &Self) -> Ordering {
-        if self == other {
-            return Ordering::Equal;
-        }
-        match self.witness_ord.cmp(&other.witness_ord) {
-            Ordering::Less => Ordering::Less,
-            Ordering::Greater => Ordering::Greater,
-            Ordering::Equal => self.witness_id.cmp(&other.witness_id),
-        }
-    }
-}
-
 impl WitnessAnchor {
     pub fn from_mempool(witness_id: XWitnessId) -> Self {
         WitnessAnchor {
@@ -154,6 +135,9 @@ impl WitnessAnchor {
             witness_id,
         }
     }
+
+    /// Returns mining timestamp of the witness block, if the witness is mined.
+    pub fn timestamp(&self) -> Option<i64> { self.witness_ord.timestamp() }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
@@ -0,0 +1,267 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Idempotent application of operations to a mutable contract state.
+//!
+//! Changes made by each applied operation may be reported to a
+//! [`StateObserver`] as a [`StateDiff`], allowing wallets and indexers to
+//! follow the contract state without re-scanning it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    ContractHistory, DataState, GlobalStateType, OpId, OpRef, Operation, Opout, WitnessAnchor,
+};
+
+/// Outcome of applying an operation to the contract state with
+/// [`ContractStateMut::apply`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ApplyOutcome {
+    /// Operation was not known before and was added to the state.
+    Added,
+
+    /// Operation was already present in the state; the state was not changed.
+    AlreadyKnown,
+
+    /// Operation spends outputs which were already spent by other operations.
+    /// The map lists each of such outputs with the id of the operation which
+    /// has spent it before. The state was not changed.
+    Conflict(BTreeMap<Opout, OpId>),
+}
+
+/// Changes made to the contract state by an applied operation.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct StateDiff {
+    pub opid: OpId,
+    pub witness_anchor: Option<WitnessAnchor>,
+    /// Assignments created by the operation.
+    pub created: BTreeSet<Opout>,
+    /// Assignments spent by the operation.
+    pub spent: BTreeSet<Opout>,
+    /// Global state items added by the operation, in their order within the
+    /// operation.
+    pub globals: Vec<(GlobalStateType, DataState)>,
+}
+
+impl StateDiff {
+    /// Computes changes the operation makes to the contract state.
+    pub fn with(op: &OpRef, witness_anchor: Option<WitnessAnchor>) -> Self {
+        let opid = op.id();
+        let created = op
+            .assignments()
+            .types()
+            .into_iter()
+            .flat_map(|ty| {
+                let len = op
+                    .assignments_by_type(ty)
+                    .map(|a| a.len_u16())
+                    .unwrap_or_default();
+                (0..len).map(move |no| Opout::new(opid, ty, no))
+            })
+            .collect();
+        let spent = op.inputs().iter().map(|input| input.prev_out).collect();
+        let globals = op
+            .globals()
+            .iter()
+            .flat_map(|(ty, values)| values.iter().map(|value| (*ty, value.clone())))
+            .collect();
+        StateDiff {
+            opid,
+            witness_anchor,
+            created,
+            spent,
+            globals,
+        }
+    }
+}
+
+/// Subscriber to the contract state changes.
+pub trait StateObserver {
+    /// Called after each operation added to the contract state.
+    fn on_diff(&mut self, diff: &StateDiff);
+}
+
+impl<F: FnMut(&StateDiff)> StateObserver for F {
+    fn on_diff(&mut self, diff: &StateDiff) { self(diff) }
+}
+
+/// Mutable contract state which can be updated with new operations.
+///
+/// Implementors provide storage primitives, while the [`Self::apply`] method
+/// implements the update procedure, which is the same for all storage layers.
+pub trait ContractStateMut {
+    /// Checks whether the operation was already added to the state.
+    fn is_known_op(&self, opid: OpId) -> bool;
+
+    /// Returns id of the operation known to spend the given output.
+    fn spending_op(&self, opout: Opout) -> Option<OpId>;
+
+    /// Saves operation into the state, registering all outputs it spends.
+    ///
+    /// The method is called only for operations which are not known and do
+    /// not conflict with already known operations.
+    fn add_op(&mut self, op: OpRef, witness_anchor: Option<WitnessAnchor>);
+
+    /// Applies operation to the contract state.
+    ///
+    /// The method is idempotent: applying an already known operation doesn't
+    /// change the state. Operations spending outputs already spent by other
+    /// known operations are not applied and are reported as conflicts.
+    ///
+    /// # Panics
+    ///
+    /// If the operation violates RGB consensus rules and wasn't checked
+    /// against the schema before applying.
+    fn apply(&mut self, op: OpRef, witness_anchor: Option<WitnessAnchor>) -> ApplyOutcome {
+        let opid = op.id();
+        if self.is_known_op(opid) {
+            return ApplyOutcome::AlreadyKnown;
+        }
+        let conflicts = op
+            .inputs()
+            .iter()
+            .filter_map(|input| {
+                self.spending_op(input.prev_out)
+                    .map(|spender| (input.prev_out, spender))
+            })
+            .collect::<BTreeMap<_, _>>();
+        if !conflicts.is_empty() {
+            return ApplyOutcome::Conflict(conflicts);
+        }
+        self.add_op(op, witness_anchor);
+        ApplyOutcome::Added
+    }
+
+    /// Applies operation to the contract state like [`Self::apply`], and
+    /// reports the changes to the observer if the operation was added.
+    fn apply_observed(
+        &mut self,
+        op: OpRef,
+        witness_anchor: Option<WitnessAnchor>,
+        observer: &mut dyn StateObserver,
+    ) -> ApplyOutcome {
+        let diff = StateDiff::with(&op, witness_anchor);
+        let outcome = self.apply(op, witness_anchor);
+        if outcome == ApplyOutcome::Added {
+            observer.on_diff(&diff);
+        }
+        outcome
+    }
+}
+
+/// In-memory implementation of [`ContractStateMut`] over the
+/// [`ContractHistory`].
+#[derive(Getters, Clone, Eq, PartialEq, Debug)]
+pub struct MemContractState {
+    history: ContractHistory,
+    #[getter(skip)]
+    ops: BTreeSet<OpId>,
+    #[getter(skip)]
+    spends: BTreeMap<Opout, OpId>,
+}
+
+impl MemContractState {
+    /// Constructs state from the contract history, which must include
+    /// contract genesis.
+    ///
+    /// Operations and spent outputs already present in the history are
+    /// registered, such that applying them again is detected. State
+    /// transitions which neither assign revealed state nor spend any outputs
+    /// are not recorded by the history and will be applied once more.
+    pub fn with(history: ContractHistory, genesis_id: OpId) -> Self {
+        let mut ops = history.op_ids();
+        ops.insert(genesis_id);
+        let spends = history.spends().collect();
+        MemContractState {
+            history,
+            ops,
+            spends,
+        }
+    }
+
+    pub fn into_history(self) -> ContractHistory { self.history }
+}
+
+impl ContractStateMut for MemContractState {
+    fn is_known_op(&self, opid: OpId) -> bool { self.ops.contains(&opid) }
+
+    fn spending_op(&self, opout: Opout) -> Option<OpId> { self.spends.get(&opout).copied() }
+
+    fn add_op(&mut self, op: OpRef, witness_anchor: Option<WitnessAnchor>) {
+        let opid = op.id();
+        for input in &op.inputs() {
+            self.spends.insert(input.prev_out, opid);
+        }
+        self.history.add_operation(&op, witness_anchor);
+        self.ops.insert(opid);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+    use amplify::Wrapper;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{
+        Assign, AssignmentType, Assignments, Genesis, Input, Inputs, Transition, TransitionType,
+        TypedAssigns, VoidState, WitnessOrd, XGraphSeal, XWitnessId,
+    };
+
+    #[test]
+    fn seeded_from_history() {
+        let ty = AssignmentType::with(1);
+        let genesis = Genesis::strict_dumb();
+        let mut history = ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis);
+
+        let spent = Opout::new(genesis.id(), ty, 0);
+        let mut transition = Transition::strict_dumb();
+        transition.contract_id = genesis.contract_id();
+        transition.inputs = Inputs::try_from_inputs([Input::with(spent)]).unwrap();
+        transition.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            ty,
+            TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                XGraphSeal::strict_dumb(),
+                VoidState::default(),
+            )])),
+        )]));
+        let witness_anchor = WitnessAnchor {
+            witness_ord: WitnessOrd::OffChain,
+            witness_id: XWitnessId::strict_dumb(),
+        };
+        history.add_operation(&transition, Some(witness_anchor));
+
+        let mut state = MemContractState::with(history, genesis.id());
+        assert_eq!(
+            state.apply(OpRef::Transition(&transition), Some(witness_anchor)),
+            ApplyOutcome::AlreadyKnown
+        );
+
+        let mut double_spend = transition.clone();
+        double_spend.transition_type = TransitionType::with(10);
+        assert_eq!(
+            state.apply(OpRef::Transition(&double_spend), Some(witness_anchor)),
+            ApplyOutcome::Conflict(bmap! { spent => transition.id() })
+        );
+    }
+}
@@ -20,20 +20,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::cmp::Ordering;
 use core::fmt::Debug;
 use std::collections::{btree_map, BTreeSet};
 use std::hash::Hash;
 
 use amplify::confinement::{Confined, SmallVec, TinyOrdMap};
 use commit_verify::{Conceal, ReservedBytes};
-use strict_encoding::{StrictDumb, StrictEncode};
+use strict_encoding::{DecodeError, StrictDecode, StrictDumb, StrictEncode, TypedRead};
 
 use super::ExposedState;
 use crate::contract::seal::GenesisSeal;
 use crate::{
-    AssignmentType, ExposedSeal, GraphSeal, RevealedAttach, RevealedData, RevealedValue,
-    SecretSeal, StateType, VoidState, XChain, LIB_NAME_RGB,
+    AssignmentType, DecodeLimitError, ExposedSeal, GraphSeal, RevealedAttach, RevealedData,
+    RevealedValue, SecretSeal, StateType, VoidState, XChain, LIB_NAME_RGB, OP_MAX_ASSIGNMENTS,
 };
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error)]
@@ -95,21 +94,6 @@ pub enum Assign<State: ExposedState, Seal: ExposedSeal> {
     },
 }
 
-// Consensus-critical!
-// Assignment indexes are part of the transition ancestor's commitment, so
-// here we use deterministic ordering based on hash values of the concealed
-// seal data contained within the assignment
-impl<State: ExposedState, Seal: ExposedSeal> PartialOrd for Assign<State, Seal> {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
-}
-
-impl<State: ExposedState, Seal: ExposedSeal> Ord for Assign<State, Seal> {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.to_confidential_seal()
-            .cmp(&other.to_confidential_seal())
-    }
-}
-
 impl<State: ExposedState, Seal: ExposedSeal> PartialEq for Assign<State, Seal> {
     fn eq(&self, other: &Self) -> bool {
         self.to_confidential_seal() == other.to_confidential_seal() &&
@@ -227,6 +211,40 @@ impl<State: ExposedState, Seal: ExposedSeal> Assign<State, Seal> {
             _ => None,
         }
     }
+
+    /// Conceals state of the assignment, keeping the seal as is.
+    pub fn conceal_state(&self) -> Self {
+        match self {
+            Assign::Revealed { seal, state, lock } => Assign::ConfidentialState {
+                seal: *seal,
+                state: state.conceal(),
+                lock: *lock,
+            },
+            Assign::ConfidentialSeal { seal, state, lock } => Assign::Confidential {
+                seal: *seal,
+                state: state.conceal(),
+                lock: *lock,
+            },
+            Assign::Confidential { .. } | Assign::ConfidentialState { .. } => self.clone(),
+        }
+    }
+
+    /// Conceals seal of the assignment, keeping the state as is.
+    pub fn conceal_seal(&self) -> Self {
+        match self {
+            Assign::Revealed { seal, state, lock } => Assign::ConfidentialSeal {
+                seal: seal.conceal(),
+                state: state.clone(),
+                lock: *lock,
+            },
+            Assign::ConfidentialState { seal, state, lock } => Assign::Confidential {
+                seal: seal.conceal(),
+                state: *state,
+                lock: *lock,
+            },
+            Assign::Confidential { .. } | Assign::ConfidentialSeal { .. } => self.clone(),
+        }
+    }
 }
 
 impl<State: ExposedState, Seal: ExposedSeal> Conceal for Assign<State, Seal>
@@ -576,7 +594,7 @@ impl TypedAssigns<GenesisSeal> {
 #[derive(Wrapper, WrapperMut, Clone, PartialEq, Eq, Debug, From)]
 #[wrapper(Deref)]
 #[wrapper_mut(DerefMut)]
-#[derive(StrictType, StrictEncode, StrictDecode)]
+#[derive(StrictType, StrictEncode)]
 #[strict_type(lib = LIB_NAME_RGB)]
 #[cfg_attr(
     feature = "serde",
@@ -594,6 +612,33 @@ impl<Seal: ExposedSeal> Default for Assignments<Seal> {
     fn default() -> Self { Self(empty!()) }
 }
 
+impl<Seal: ExposedSeal> StrictDecode for Assignments<Seal> {
+    fn strict_decode(reader: &mut impl TypedRead) -> Result<Self, DecodeError> {
+        // The map is decoded item by item, such that the number of assignments is
+        // checked before decoding assignments of the next type
+        let len = u8::strict_decode(reader)?;
+        let mut assignments: TinyOrdMap<AssignmentType, TypedAssigns<Seal>> = empty!();
+        let mut count = 0usize;
+        for _ in 0..len {
+            let ty = AssignmentType::strict_decode(reader)?;
+            if matches!(assignments.last_key_value(), Some((last, _)) if *last >= ty) {
+                return Err(DecodeError::DataIntegrityError(format!(
+                    "assignment type {ty} is repeated or not sorted"
+                )));
+            }
+            let typed_assigns = TypedAssigns::<Seal>::strict_decode(reader)?;
+            count += typed_assigns.len_u16() as usize;
+            if count > OP_MAX_ASSIGNMENTS {
+                return Err(DecodeLimitError::AssignmentCount(count).into());
+            }
+            assignments
+                .insert(ty, typed_assigns)
+                .expect("the number of items is read as u8");
+        }
+        Ok(Self(assignments))
+    }
+}
+
 impl Assignments<GenesisSeal> {
     pub fn transmutate_seals(&self) -> Assignments<GraphSeal> {
         Assignments(
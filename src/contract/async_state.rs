@@ -0,0 +1,371 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Asynchronous access to the contract state.
+//!
+//! State backends living behind a network or an asynchronous database (like
+//! indexers or remote nodes) implement [`AsyncContractState`] and
+//! [`AsyncGlobalStateIter`] instead of providing an in-memory
+//! [`ContractState`]. The in-memory state implements both traits with futures
+//! which are immediately ready, and [`BlockingState`] bridges asynchronous
+//! backends to the synchronous code by blocking the current thread on each
+//! request.
+
+use std::collections::BTreeSet;
+use std::convert::Infallible;
+use std::future::{ready, Future};
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use crate::{
+    AssignmentType, ContractId, ContractState, DataState, GlobalOrd, GlobalStateIter,
+    GlobalStateType, OutputAssignment, RevealedAttach, RevealedData, RevealedValue, SchemaId,
+    VoidState, WitnessOrd, XOutpoint, XWitnessId,
+};
+
+/// Asynchronous iterator over the global state items in the consensus order.
+pub trait AsyncGlobalStateIter {
+    type Error: std::error::Error;
+
+    /// Fetches the next item, returning `None` once all items are iterated.
+    fn next_item(
+        &mut self,
+    ) -> impl Future<Output = Result<Option<(GlobalOrd, DataState)>, Self::Error>>;
+}
+
+/// Asynchronous read access to the contract state.
+pub trait AsyncContractState {
+    type Error: std::error::Error;
+    type GlobalIter<'state>: AsyncGlobalStateIter<Error = Self::Error>
+    where Self: 'state;
+
+    fn schema_id(&self) -> SchemaId;
+
+    fn contract_id(&self) -> ContractId;
+
+    /// Returns iterator over the global state of the given type, or `None` if
+    /// the contract has no global state of this type.
+    fn global(
+        &self,
+        ty: GlobalStateType,
+    ) -> impl Future<Output = Result<Option<Self::GlobalIter<'_>>, Self::Error>>;
+
+    fn rights(&self)
+        -> impl Future<Output = Result<Vec<OutputAssignment<VoidState>>, Self::Error>>;
+
+    fn fungibles(
+        &self,
+    ) -> impl Future<Output = Result<Vec<OutputAssignment<RevealedValue>>, Self::Error>>;
+
+    fn data(
+        &self,
+    ) -> impl Future<Output = Result<Vec<OutputAssignment<RevealedData>>, Self::Error>>;
+
+    fn attach(
+        &self,
+    ) -> impl Future<Output = Result<Vec<OutputAssignment<RevealedAttach>>, Self::Error>>;
+
+    /// Lists outpoints holding state of the given assignment type.
+    fn outpoints(
+        &self,
+        ty: AssignmentType,
+    ) -> impl Future<Output = Result<BTreeSet<XOutpoint>, Self::Error>>;
+
+    /// Returns position of the witness as it is known to the backend.
+    fn witness_status(
+        &self,
+        witness_id: XWitnessId,
+    ) -> impl Future<Output = Result<Option<WitnessOrd>, Self::Error>>;
+}
+
+impl<'state> AsyncGlobalStateIter for GlobalStateIter<'state> {
+    type Error = Infallible;
+
+    fn next_item(
+        &mut self,
+    ) -> impl Future<Output = Result<Option<(GlobalOrd, DataState)>, Self::Error>> {
+        let item = self.next().map(|(ord, state)| (ord, state.clone()));
+        ready(Ok(item))
+    }
+}
+
+impl AsyncContractState for ContractState {
+    type Error = Infallible;
+    type GlobalIter<'state> = GlobalStateIter<'state>;
+
+    fn schema_id(&self) -> SchemaId { self.history.schema_id() }
+
+    fn contract_id(&self) -> ContractId { self.history.contract_id() }
+
+    fn global(
+        &self,
+        ty: GlobalStateType,
+    ) -> impl Future<Output = Result<Option<Self::GlobalIter<'_>>, Self::Error>> {
+        ready(Ok(self.history.global_state(ty).map(|state| state.iter())))
+    }
+
+    fn rights(
+        &self,
+    ) -> impl Future<Output = Result<Vec<OutputAssignment<VoidState>>, Self::Error>> {
+        ready(Ok(self.history.rights().iter().cloned().collect()))
+    }
+
+    fn fungibles(
+        &self,
+    ) -> impl Future<Output = Result<Vec<OutputAssignment<RevealedValue>>, Self::Error>> {
+        ready(Ok(self.history.fungibles().iter().cloned().collect()))
+    }
+
+    fn data(
+        &self,
+    ) -> impl Future<Output = Result<Vec<OutputAssignment<RevealedData>>, Self::Error>> {
+        ready(Ok(self.history.data().iter().cloned().collect()))
+    }
+
+    fn attach(
+        &self,
+    ) -> impl Future<Output = Result<Vec<OutputAssignment<RevealedAttach>>, Self::Error>> {
+        ready(Ok(self.history.attach().iter().cloned().collect()))
+    }
+
+    fn outpoints(
+        &self,
+        ty: AssignmentType,
+    ) -> impl Future<Output = Result<BTreeSet<XOutpoint>, Self::Error>> {
+        ready(Ok(self.history.outpoints(ty).collect()))
+    }
+
+    fn witness_status(
+        &self,
+        witness_id: XWitnessId,
+    ) -> impl Future<Output = Result<Option<WitnessOrd>, Self::Error>> {
+        ready(Ok(self.history.witness_status(witness_id)))
+    }
+}
+
+/// Runs the future to completion, blocking the current thread.
+///
+/// Intended for bridging asynchronous state backends to the synchronous code
+/// outside of an asynchronous runtime; must not be called from inside the
+/// runtime threads.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) { self.0.unpark() }
+    }
+
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// Synchronous adapter over an asynchronous global state iterator.
+#[derive(Debug)]
+pub struct BlockingGlobalIter<I: AsyncGlobalStateIter>(I);
+
+impl<I: AsyncGlobalStateIter> Iterator for BlockingGlobalIter<I> {
+    type Item = Result<(GlobalOrd, DataState), I::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> { block_on(self.0.next_item()).transpose() }
+}
+
+/// Synchronous adapter over an asynchronous contract state backend, blocking
+/// the current thread on each request (see [`block_on`]).
+#[derive(Clone, Debug, From)]
+pub struct BlockingState<S: AsyncContractState>(S);
+
+impl<S: AsyncContractState> BlockingState<S> {
+    /// Returns the wrapped asynchronous contract state.
+    pub fn into_inner(self) -> S { self.0 }
+
+    pub fn schema_id(&self) -> SchemaId { self.0.schema_id() }
+
+    pub fn contract_id(&self) -> ContractId { self.0.contract_id() }
+
+    pub fn global(
+        &self,
+        ty: GlobalStateType,
+    ) -> Result<Option<BlockingGlobalIter<S::GlobalIter<'_>>>, S::Error> {
+        block_on(self.0.global(ty)).map(|iter| iter.map(BlockingGlobalIter))
+    }
+
+    pub fn rights(&self) -> Result<Vec<OutputAssignment<VoidState>>, S::Error> {
+        block_on(self.0.rights())
+    }
+
+    pub fn fungibles(&self) -> Result<Vec<OutputAssignment<RevealedValue>>, S::Error> {
+        block_on(self.0.fungibles())
+    }
+
+    pub fn data(&self) -> Result<Vec<OutputAssignment<RevealedData>>, S::Error> {
+        block_on(self.0.data())
+    }
+
+    pub fn attach(&self) -> Result<Vec<OutputAssignment<RevealedAttach>>, S::Error> {
+        block_on(self.0.attach())
+    }
+
+    pub fn outpoints(&self, ty: AssignmentType) -> Result<BTreeSet<XOutpoint>, S::Error> {
+        block_on(self.0.outpoints(ty))
+    }
+
+    pub fn witness_status(&self, witness_id: XWitnessId) -> Result<Option<WitnessOrd>, S::Error> {
+        block_on(self.0.witness_status(witness_id))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    use amplify::confinement::{Confined, SmallBlob};
+    use amplify::Wrapper;
+    use bp::Txid;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{
+        Assign, Assignments, ContractHistory, Extension, Genesis, Operation, Schema, TypedAssigns,
+        WitnessAnchor, WitnessPos, XChain, XGenesisSeal,
+    };
+
+    /// Future which gets ready only once it is woken from another thread.
+    struct Delayed {
+        ready: Arc<AtomicBool>,
+        spawned: bool,
+    }
+
+    impl Future for Delayed {
+        type Output = u8;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<u8> {
+            if self.ready.load(Ordering::SeqCst) {
+                return Poll::Ready(42);
+            }
+            if !self.spawned {
+                self.spawned = true;
+                let ready = self.ready.clone();
+                let waker = cx.waker().clone();
+                thread::spawn(move || {
+                    thread::sleep(Duration::from_millis(10));
+                    ready.store(true, Ordering::SeqCst);
+                    waker.wake();
+                });
+            }
+            Poll::Pending
+        }
+    }
+
+    fn state() -> ContractState {
+        let ty = GlobalStateType::with(1);
+        let mut genesis = Genesis::strict_dumb();
+        genesis
+            .globals
+            .add_state(ty, DataState::from(SmallBlob::from_iter_unsafe([0])))
+            .unwrap();
+        genesis.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            AssignmentType::with(1),
+            TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                XGenesisSeal::strict_dumb(),
+                VoidState::default(),
+            )])),
+        )]));
+        let mut history = ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis);
+
+        let mut extension = Extension::strict_dumb();
+        extension
+            .globals
+            .add_state(ty, DataState::from(SmallBlob::from_iter_unsafe([1])))
+            .unwrap();
+        history.add_extension(&extension, WitnessAnchor {
+            witness_ord: WitnessOrd::OnChain(WitnessPos::new(100, 1700000000).unwrap()),
+            witness_id: XChain::Bitcoin(Txid::from([1; 32])),
+        });
+        ContractState {
+            schema: Schema::strict_dumb(),
+            history,
+        }
+    }
+
+    #[test]
+    fn block_on_pending() {
+        assert_eq!(block_on(ready(7)), 7);
+        let delayed = Delayed {
+            ready: default!(),
+            spawned: false,
+        };
+        assert_eq!(block_on(delayed), 42);
+    }
+
+    #[test]
+    fn blocking_state() {
+        let ty = GlobalStateType::with(1);
+        let state = state();
+        let blocking = BlockingState::from(state.clone());
+        assert_eq!(blocking.schema_id(), state.history.schema_id());
+        assert_eq!(blocking.contract_id(), state.history.contract_id());
+
+        let global = blocking
+            .global(ty)
+            .unwrap()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let expected = state
+            .history
+            .global_state(ty)
+            .unwrap()
+            .iter()
+            .map(|(ord, state)| (ord, state.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(global.len(), 2);
+        assert_eq!(global, expected);
+        assert!(blocking.global(GlobalStateType::with(2)).unwrap().is_none());
+
+        let rights = blocking.rights().unwrap();
+        assert_eq!(rights.len(), 1);
+        assert_eq!(rights, state.history.rights().iter().cloned().collect::<Vec<_>>());
+        assert!(blocking.fungibles().unwrap().is_empty());
+        assert_eq!(
+            blocking.outpoints(AssignmentType::with(1)).unwrap(),
+            state.history.outpoints(AssignmentType::with(1)).collect()
+        );
+
+        let witness_id = XChain::Bitcoin(Txid::from([1; 32]));
+        let witness_status = blocking.witness_status(witness_id).unwrap();
+        assert!(witness_status.is_some());
+        assert_eq!(witness_status, state.history.witness_status(witness_id));
+        let unknown_id = XChain::Bitcoin(Txid::from([2; 32]));
+        assert_eq!(blocking.witness_status(unknown_id).unwrap(), None);
+    }
+}
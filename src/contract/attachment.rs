@@ -44,6 +44,15 @@ pub struct AttachId(
     Bytes32,
 );
 
+impl AttachId {
+    /// Computes attachment id as a SHA256 digest of the attachment content.
+    pub fn with_content(data: impl AsRef<[u8]>) -> Self {
+        let mut hasher = Sha256::default();
+        hasher.input_raw(data.as_ref());
+        AttachId::from(hasher.finish())
+    }
+}
+
 impl DisplayBaid64 for AttachId {
     const HRI: &'static str = "rgb:fs";
     const CHUNKING: bool = true;
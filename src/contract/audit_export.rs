@@ -0,0 +1,331 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export of the contract state for audit.
+//!
+//! Contract state is written as rows of delimiter-separated values (CSV or
+//! TSV) with a stable layout, one row per global state item or allocation,
+//! in the consensus order of [`ContractHistory::state_leaves`]. The columns
+//! are:
+//!
+//! | Column          | Value                                                  |
+//! |-----------------|--------------------------------------------------------|
+//! | `kind`          | `global`, `rights`, `fungible`, `data` or `attach`     |
+//! | `type`          | global state or assignment type number                 |
+//! | `opout`         | operation output of an allocation; empty for global    |
+//! | `index`         | index of the global state item or the assignment       |
+//! | `seal`          | allocation seal; empty if concealed or for global      |
+//! | `seal_revealed` | `1` if the seal is revealed, `0` otherwise             |
+//! | `state`         | hex data, amount or attachment id; empty if concealed  |
+//! | `state_revealed`| `1` if the state is revealed, `0` otherwise            |
+//! | `witness_id`    | witness transaction id; empty for genesis state        |
+//! | `witness_ord`   | known position of the witness; empty if unknown        |
+//!
+//! Exported rows can be parsed back and cross-checked against the contract
+//! history.
+
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::str::FromStr;
+
+use amplify::Wrapper;
+
+use crate::{
+    AssignmentWitness, ContractHistory, DisclosureLevel, KnownState, OutputAssignment, StateLeaf,
+    WitnessOrd, XWitnessId,
+};
+
+/// Column names of the audit export.
+pub const AUDIT_COLUMNS: [&str; 10] = [
+    "kind",
+    "type",
+    "opout",
+    "index",
+    "seal",
+    "seal_revealed",
+    "state",
+    "state_revealed",
+    "witness_id",
+    "witness_ord",
+];
+
+/// Delimiter of the audit export values.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum AuditFormat {
+    /// Comma-separated values.
+    #[default]
+    Csv,
+    /// Tab-separated values.
+    Tsv,
+}
+
+impl AuditFormat {
+    pub fn delimiter(self) -> char {
+        match self {
+            AuditFormat::Csv => ',',
+            AuditFormat::Tsv => '\t',
+        }
+    }
+}
+
+/// Kind of the exported state item.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display(lowercase)]
+pub enum AuditKind {
+    Global,
+    Rights,
+    Fungible,
+    Data,
+    Attach,
+}
+
+impl FromStr for AuditKind {
+    type Err = AuditParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "global" => Ok(AuditKind::Global),
+            "rights" => Ok(AuditKind::Rights),
+            "fungible" => Ok(AuditKind::Fungible),
+            "data" => Ok(AuditKind::Data),
+            "attach" => Ok(AuditKind::Attach),
+            _ => Err(AuditParseError::InvalidKind(s.to_owned())),
+        }
+    }
+}
+
+/// Errors parsing audit export.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum AuditParseError {
+    /// audit export has invalid header.
+    InvalidHeader,
+
+    /// row {0} has {1} columns instead of 10.
+    ColumnCount(usize, usize),
+
+    /// unknown kind of state '{0}'.
+    InvalidKind(String),
+
+    /// row {0} has invalid value '{1}' in the column '{2}'.
+    InvalidValue(usize, String, &'static str),
+}
+
+/// Single row of the audit export.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct AuditRow {
+    pub kind: AuditKind,
+    pub ty: u16,
+    pub opout: Option<String>,
+    pub index: u16,
+    pub seal: Option<String>,
+    pub state: Option<String>,
+    pub witness_id: Option<String>,
+    pub witness_ord: Option<String>,
+}
+
+impl AuditRow {
+    pub fn seal_revealed(&self) -> bool { self.seal.is_some() }
+
+    pub fn state_revealed(&self) -> bool { self.state.is_some() }
+
+    fn allocation<State: KnownState>(
+        kind: AuditKind,
+        assignment: &OutputAssignment<State>,
+        state: Option<String>,
+        level: DisclosureLevel,
+        witness_ord: impl Fn(XWitnessId) -> Option<WitnessOrd>,
+    ) -> Self {
+        let witness_id = match assignment.witness {
+            AssignmentWitness::Absent => None,
+            AssignmentWitness::Present(witness_id) => Some(witness_id),
+        };
+        AuditRow {
+            kind,
+            ty: assignment.opout.ty.to_inner(),
+            opout: Some(assignment.opout.to_string()),
+            index: assignment.opout.no,
+            seal: (!level.conceals_seals()).then(|| assignment.seal.to_string()),
+            state,
+            witness_id: witness_id.map(|id| id.to_string()),
+            witness_ord: witness_id.and_then(witness_ord).map(|ord| ord.to_string()),
+        }
+    }
+
+    fn cells(&self) -> [String; 10] {
+        let flag = |revealed: bool| if revealed { s!("1") } else { s!("0") };
+        [
+            self.kind.to_string(),
+            self.ty.to_string(),
+            self.opout.clone().unwrap_or_default(),
+            self.index.to_string(),
+            self.seal.clone().unwrap_or_default(),
+            flag(self.seal_revealed()),
+            self.state.clone().unwrap_or_default(),
+            flag(self.state_revealed()),
+            self.witness_id.clone().unwrap_or_default(),
+            self.witness_ord.clone().unwrap_or_default(),
+        ]
+    }
+
+    /// Parses row from the values split by the delimiter; `row` is the row
+    /// number used in errors.
+    fn parse(row: usize, cells: &[&str]) -> Result<Self, AuditParseError> {
+        if cells.len() != AUDIT_COLUMNS.len() {
+            return Err(AuditParseError::ColumnCount(row, cells.len()));
+        }
+        let invalid =
+            |no: usize| AuditParseError::InvalidValue(row, cells[no].to_owned(), AUDIT_COLUMNS[no]);
+        let optional = |no: usize| (!cells[no].is_empty()).then(|| cells[no].to_owned());
+        let flagged = |no: usize, flag: usize| match (cells[flag], cells[no].is_empty()) {
+            ("1", _) => Ok(Some(cells[no].to_owned())),
+            ("0", true) => Ok(None),
+            _ => Err(invalid(flag)),
+        };
+        Ok(AuditRow {
+            kind: cells[0].parse()?,
+            ty: cells[1].parse().map_err(|_| invalid(1))?,
+            opout: optional(2),
+            index: cells[3].parse().map_err(|_| invalid(3))?,
+            seal: flagged(4, 5)?,
+            state: flagged(6, 7)?,
+            witness_id: optional(8),
+            witness_ord: optional(9),
+        })
+    }
+}
+
+impl Display for AuditRow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { f.write_str(&self.cells().join(",")) }
+}
+
+impl ContractHistory {
+    /// Lists contract state as audit export rows at the given disclosure
+    /// level: amounts are omitted for [`DisclosureLevel::AmountsConcealed`]
+    /// and seals for [`DisclosureLevel::SealsConcealed`].
+    pub fn audit_rows(&self, level: DisclosureLevel) -> Vec<AuditRow> {
+        let witness_ord = |witness_id| self.witness_status(witness_id);
+        self.state_leaves()
+            .into_iter()
+            .map(|leaf| match leaf {
+                StateLeaf::Global { ty, ord, state } => AuditRow {
+                    kind: AuditKind::Global,
+                    ty: ty.to_inner(),
+                    opout: None,
+                    index: ord.idx,
+                    seal: None,
+                    state: Some(state.to_string()),
+                    witness_id: ord.witness_anchor.map(|a| a.witness_id.to_string()),
+                    witness_ord: ord.witness_anchor.map(|a| a.witness_ord.to_string()),
+                },
+                StateLeaf::Rights(a) => {
+                    AuditRow::allocation(AuditKind::Rights, &a, Some(s!("")), level, witness_ord)
+                }
+                StateLeaf::Fungible(a) => {
+                    let amount =
+                        (!level.conceals_amounts()).then(|| u64::from(a.state.value).to_string());
+                    AuditRow::allocation(AuditKind::Fungible, &a, amount, level, witness_ord)
+                }
+                StateLeaf::Data(a) => {
+                    let data = Some(a.state.value.to_string());
+                    AuditRow::allocation(AuditKind::Data, &a, data, level, witness_ord)
+                }
+                StateLeaf::Attach(a) => {
+                    let id = Some(a.state.id.to_string());
+                    AuditRow::allocation(AuditKind::Attach, &a, id, level, witness_ord)
+                }
+            })
+            .collect()
+    }
+
+    /// Writes contract state audit export, including the header row.
+    pub fn write_audit(
+        &self,
+        mut writer: impl io::Write,
+        level: DisclosureLevel,
+        format: AuditFormat,
+    ) -> io::Result<()> {
+        let delimiter = format.delimiter().to_string();
+        writeln!(writer, "{}", AUDIT_COLUMNS.join(&delimiter))?;
+        for row in self.audit_rows(level) {
+            writeln!(writer, "{}", row.cells().join(&delimiter))?;
+        }
+        Ok(())
+    }
+
+    /// Cross-checks audit export rows against the contract history, returning
+    /// numbers of the rows (starting from zero) which do not match it. Rows
+    /// missed from the export are reported with the numbers following the
+    /// last exported row.
+    pub fn audit_mismatches(&self, level: DisclosureLevel, rows: &[AuditRow]) -> Vec<usize> {
+        let expected = self.audit_rows(level);
+        let len = expected.len().max(rows.len());
+        (0..len)
+            .filter(|no| expected.get(*no) != rows.get(*no))
+            .collect()
+    }
+}
+
+/// Parses audit export, checking the header row.
+pub fn parse_audit(data: &str, format: AuditFormat) -> Result<Vec<AuditRow>, AuditParseError> {
+    let delimiter = format.delimiter();
+    let mut lines = data.lines();
+    let header = lines.next().ok_or(AuditParseError::InvalidHeader)?;
+    if !header.split(delimiter).eq(AUDIT_COLUMNS) {
+        return Err(AuditParseError::InvalidHeader);
+    }
+    lines
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .map(|(row, line)| AuditRow::parse(row, &line.split(delimiter).collect::<Vec<_>>()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn row_roundtrip() {
+        let row = AuditRow {
+            kind: AuditKind::Fungible,
+            ty: 4000,
+            opout: Some(s!("op/0x0FA0/1")),
+            index: 1,
+            seal: None,
+            state: Some(s!("100")),
+            witness_id: None,
+            witness_ord: None,
+        };
+        for format in [AuditFormat::Csv, AuditFormat::Tsv] {
+            let delimiter = format.delimiter().to_string();
+            let data =
+                format!("{}\n{}\n", AUDIT_COLUMNS.join(&delimiter), row.cells().join(&delimiter));
+            assert_eq!(parse_audit(&data, format), Ok(vec![row.clone()]));
+        }
+        let data = format!("{}\nfungible,4000,,1,,1,100,0,,\n", AUDIT_COLUMNS.join(","));
+        assert_eq!(
+            parse_audit(&data, AuditFormat::Csv),
+            Err(AuditParseError::InvalidValue(0, s!("0"), "state_revealed"))
+        );
+    }
+}
@@ -0,0 +1,200 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Batch concealment of seal definitions.
+//!
+//! Issuers distributing large number of allocations (for instance, in
+//! airdrops) conceal seals in batches. Each seal in a batch gets an
+//! independent blinding factor derived from the [`BlindingSeed`] and the seal
+//! position in the batch, which avoids blinding factor reuse caused by a
+//! poor randomness source. The derivation is recorded in a [`SealBatchRecord`],
+//! which can be published; once the seed is disclosed to an auditor, the
+//! record allows to verify that all concealed seals were produced from the
+//! declared outpoints.
+
+use std::collections::BTreeSet;
+
+use amplify::{Bytes32, Wrapper};
+use bp::seals::txout::CloseMethod;
+use bp::Outpoint;
+use commit_verify::{DigestExt, Sha256};
+
+use crate::{BlindingSeed, Layer1, SecretSeal, XChain, XGraphSeal};
+
+pub const SEED_COMMITMENT_TAG: &[u8] = b"urn:lnp-bp:rgb:seal-blinding-seed#2024-05-20";
+
+impl BlindingSeed {
+    /// Commitment to the seed, which can be published without revealing the
+    /// seed.
+    pub fn commitment(&self) -> Bytes32 {
+        let mut hasher = Sha256::default();
+        hasher.input_raw(SEED_COMMITMENT_TAG);
+        hasher.input_raw(self.as_inner());
+        Bytes32::from_byte_array(hasher.finish())
+    }
+
+    /// Creates seals for all the outpoints, assigning each of them an index
+    /// starting from `first_index`, and conceals them.
+    pub fn conceal_batch(
+        &self,
+        layer1: Layer1,
+        method: CloseMethod,
+        first_index: u32,
+        outpoints: impl IntoIterator<Item = Outpoint>,
+    ) -> SealBatch {
+        let seals = outpoints
+            .into_iter()
+            .zip(first_index..)
+            .map(|(outpoint, index)| {
+                self.graph_seal(layer1, method, Some(outpoint.txid), outpoint.vout, index)
+            })
+            .collect::<Vec<_>>();
+        let secrets = seals.iter().map(XGraphSeal::to_secret_seal).collect();
+        SealBatch {
+            record: SealBatchRecord {
+                seed_commitment: self.commitment(),
+                layer1,
+                method,
+                first_index,
+                count: seals.len() as u32,
+            },
+            seals,
+            secrets,
+        }
+    }
+}
+
+/// Auditable record of a batch seal concealment.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct SealBatchRecord {
+    pub seed_commitment: Bytes32,
+    pub layer1: Layer1,
+    pub method: CloseMethod,
+    pub first_index: u32,
+    pub count: u32,
+}
+
+/// Seals produced by batch concealment.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SealBatch {
+    pub record: SealBatchRecord,
+    /// Revealed seals, kept by the issuer.
+    pub seals: Vec<XGraphSeal>,
+    /// Concealed seals, in the same order as the revealed ones.
+    pub secrets: Vec<XChain<SecretSeal>>,
+}
+
+/// Errors verifying batch seal concealment.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum SealBatchError {
+    /// provided seed doesn't match the seed commitment of the batch record.
+    SeedMismatch,
+
+    /// batch record declares {0} seals, while {1} seals were provided.
+    CountMismatch(u32, u32),
+
+    /// concealed seal #{0} doesn't match the declared outpoint.
+    SealMismatch(u32),
+
+    /// concealed seal #{0} is a duplicate of a previous seal from the batch.
+    DuplicateSeal(u32),
+}
+
+impl SealBatchRecord {
+    /// Checks that the seed matches the record commitment.
+    pub fn verify_seed(&self, seed: &BlindingSeed) -> bool {
+        seed.commitment() == self.seed_commitment
+    }
+
+    /// Verifies that the concealed seals were produced from the outpoints
+    /// using the seed, following the record, and that none of them repeats.
+    pub fn verify(
+        &self,
+        seed: &BlindingSeed,
+        outpoints: impl IntoIterator<Item = Outpoint>,
+        secrets: &[XChain<SecretSeal>],
+    ) -> Result<(), SealBatchError> {
+        if !self.verify_seed(seed) {
+            return Err(SealBatchError::SeedMismatch);
+        }
+        let outpoints = outpoints.into_iter().collect::<Vec<_>>();
+        if outpoints.len() != secrets.len() || secrets.len() != self.count as usize {
+            return Err(SealBatchError::CountMismatch(self.count, secrets.len() as u32));
+        }
+        let batch = seed.conceal_batch(self.layer1, self.method, self.first_index, outpoints);
+        let mut known = BTreeSet::new();
+        for (no, (expected, secret)) in batch.secrets.iter().zip(secrets).enumerate() {
+            if expected != secret {
+                return Err(SealBatchError::SealMismatch(no as u32));
+            }
+            if !known.insert(*secret) {
+                return Err(SealBatchError::DuplicateSeal(no as u32));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bp::{Txid, Vout};
+
+    use super::*;
+
+    #[test]
+    fn batch_audit() {
+        let seed = BlindingSeed::from([0x5A; 32]);
+        let outpoint = Outpoint::new(Txid::from([7u8; 32]), Vout::from(0));
+        let outpoints =
+            vec![outpoint, outpoint, Outpoint::new(Txid::from([8u8; 32]), Vout::from(1))];
+        let batch =
+            seed.conceal_batch(Layer1::Bitcoin, CloseMethod::TapretFirst, 0, outpoints.clone());
+        assert_eq!(batch.record.count, 3);
+        assert_ne!(batch.secrets[0], batch.secrets[1]);
+        assert_eq!(
+            batch
+                .record
+                .verify(&seed, outpoints.clone(), &batch.secrets),
+            Ok(())
+        );
+
+        let other = BlindingSeed::from([0xA5; 32]);
+        assert_eq!(
+            batch
+                .record
+                .verify(&other, outpoints.clone(), &batch.secrets),
+            Err(SealBatchError::SeedMismatch)
+        );
+        let mut secrets = batch.secrets.clone();
+        secrets.swap(0, 2);
+        assert_eq!(
+            batch.record.verify(&seed, outpoints, &secrets),
+            Err(SealBatchError::SealMismatch(0))
+        );
+    }
+}
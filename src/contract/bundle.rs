@@ -20,16 +20,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::{btree_map, BTreeMap};
+use std::collections::btree_map;
 
-use amplify::confinement::{Confined, U16 as U16MAX};
+use amplify::confinement::Confined;
 use amplify::{Bytes32, Wrapper};
 use bp::seals::txout::CloseMethod;
 use bp::Vout;
 use commit_verify::{mpc, CommitEncode, CommitEngine, CommitId, CommitmentId, DigestExt, Sha256};
-use strict_encoding::{StrictDumb, StrictEncode};
+use strict_encoding::{StrictDeserialize, StrictDumb, StrictEncode, StrictSerialize};
 
-use crate::{OpId, Transition, LIB_NAME_RGB};
+use crate::{confine, BundleInputMap, BundleTransitions, CollectionLimitError, OpId, LIB_NAME_RGB};
 
 pub type Vin = Vout;
 
@@ -76,7 +76,7 @@ impl From<mpc::Message> for BundleId {
     derive(Serialize, Deserialize),
     serde(crate = "serde_crate", transparent)
 )]
-pub struct InputMap(Confined<BTreeMap<Vin, OpId>, 1, U16MAX>);
+pub struct InputMap(BundleInputMap);
 
 impl StrictDumb for InputMap {
     fn strict_dumb() -> Self { Self(confined_bmap!(strict_dumb!() => strict_dumb!())) }
@@ -84,6 +84,13 @@ impl StrictDumb for InputMap {
 
 impl InputMap {
     pub fn with(input: Vin, id: OpId) -> Self { InputMap(Confined::with((input, id))) }
+
+    /// Constructs input map, checking its size against the consensus bounds.
+    pub fn try_from_map(
+        map: impl IntoIterator<Item = (Vin, OpId)>,
+    ) -> Result<Self, CollectionLimitError> {
+        confine("bundle input map", map.into_iter().collect()).map(Self)
+    }
 }
 
 impl IntoIterator for InputMap {
@@ -111,7 +118,7 @@ impl<'a> IntoIterator for &'a InputMap {
 pub struct TransitionBundle {
     pub close_method: CloseMethod,
     pub input_map: InputMap,
-    pub known_transitions: Confined<BTreeMap<OpId, Transition>, 1, U16MAX>,
+    pub known_transitions: BundleTransitions,
 }
 
 impl CommitEncode for TransitionBundle {
@@ -123,6 +130,9 @@ impl CommitEncode for TransitionBundle {
     }
 }
 
+impl StrictSerialize for TransitionBundle {}
+impl StrictDeserialize for TransitionBundle {}
+
 impl StrictDumb for TransitionBundle {
     fn strict_dumb() -> Self {
         Self {
@@ -0,0 +1,281 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Canonical comparison of contract states.
+//!
+//! Two replicas of the same contract may hold the same state while having
+//! different representations of it: allocations may be added in a different
+//! order, and the revealed state data may come with different (but equally
+//! valid) auxiliary information. Canonical form of the contract state commits
+//! to each state item with the concealed form of its state, which is fully
+//! defined by the consensus, and ignores the order in which the items are
+//! stored, such that replication and synchronization layers can detect true
+//! divergence of the state.
+//!
+//! Canonical form commits to the ids of the witnesses and not to their
+//! mining status: replicas which observed different (or no) chain
+//! reorganizations hold the same state. At the same time, it commits to the
+//! operation spending each of the allocations, since spent and unspent
+//! allocations represent a different contract state.
+
+use std::collections::BTreeSet;
+use std::hash::{Hash, Hasher};
+
+use amplify::{ByteArray, Bytes32};
+use commit_verify::{CommitEncode, CommitEngine, CommitId, DigestExt, Sha256, StrictHash};
+
+use crate::{ContractHistory, ContractState, ExposedState, OpId, OutputAssignment, StateLeaf};
+
+pub const CANONICAL_STATE_TAG: &[u8] = b"urn:lnp-bp:rgb:state-canonical#2024-05-20";
+
+struct CanonicalLeaf<'leaf> {
+    leaf: &'leaf StateLeaf,
+    spender: Option<OpId>,
+}
+
+impl CommitEncode for CanonicalLeaf<'_> {
+    type CommitmentId = StrictHash;
+
+    fn commit_encode(&self, e: &mut CommitEngine) {
+        fn assignment<State: ExposedState>(
+            e: &mut CommitEngine,
+            tag: u8,
+            assignment: &OutputAssignment<State>,
+            spender: Option<OpId>,
+        ) {
+            e.commit_to_serialized(&tag);
+            e.commit_to_serialized(&assignment.opout);
+            e.commit_to_serialized(&assignment.seal);
+            e.commit_to_serialized(&assignment.state.conceal());
+            e.commit_to_serialized(&assignment.witness);
+            match spender {
+                None => e.commit_to_serialized(&0u8),
+                Some(opid) => {
+                    e.commit_to_serialized(&1u8);
+                    e.commit_to_serialized(&opid);
+                }
+            }
+        }
+
+        match self.leaf {
+            StateLeaf::Global { ty, ord, state } => {
+                e.commit_to_serialized(&0u8);
+                e.commit_to_serialized(ty);
+                match ord.witness_anchor {
+                    None => e.commit_to_serialized(&0u8),
+                    Some(anchor) => {
+                        e.commit_to_serialized(&1u8);
+                        e.commit_to_serialized(&anchor.witness_id);
+                    }
+                }
+                e.commit_to_serialized(&ord.idx);
+                e.commit_to_serialized(state);
+            }
+            StateLeaf::Rights(a) => assignment(e, 1, a, self.spender),
+            StateLeaf::Fungible(a) => assignment(e, 2, a, self.spender),
+            StateLeaf::Data(a) => assignment(e, 3, a, self.spender),
+            StateLeaf::Attach(a) => assignment(e, 4, a, self.spender),
+        }
+        e.set_finished();
+    }
+}
+
+impl ContractHistory {
+    /// Hash of the canonical form of the state item, committing to the
+    /// concealed form of the allocated state, the id of its witness and the
+    /// operation spending it, if any.
+    pub fn canonical_leaf_hash(&self, leaf: &StateLeaf) -> Bytes32 {
+        let spender = match leaf {
+            StateLeaf::Global { .. } => None,
+            StateLeaf::Rights(a) => self.spent_by(a.opout),
+            StateLeaf::Fungible(a) => self.spent_by(a.opout),
+            StateLeaf::Data(a) => self.spent_by(a.opout),
+            StateLeaf::Attach(a) => self.spent_by(a.opout),
+        };
+        let leaf = CanonicalLeaf {
+            leaf,
+            spender: spender.map(|spender| spender.opid),
+        };
+        Bytes32::from_byte_array(leaf.commit_id().to_byte_array())
+    }
+}
+
+impl ContractState {
+    /// Returns hashes of the canonical forms of all state items.
+    pub fn canonical_leaves(&self) -> BTreeSet<Bytes32> {
+        self.state_leaves()
+            .iter()
+            .map(|leaf| self.canonical_leaf_hash(leaf))
+            .collect()
+    }
+
+    /// Compares contract states by their canonical form, ignoring the
+    /// storage order of the state items, differences in the revealed data
+    /// representing the same state and the mining status of the witnesses.
+    pub fn canonical_eq(&self, other: &Self) -> bool {
+        self.schema_id() == other.schema_id() &&
+            self.contract_id() == other.contract_id() &&
+            self.canonical_leaves() == other.canonical_leaves()
+    }
+
+    /// Computes hash of the canonical form of the contract state, which is
+    /// equal for all states which are [`ContractState::canonical_eq`].
+    pub fn canonical_hash(&self) -> Bytes32 {
+        let mut hasher = Sha256::default();
+        hasher.input_raw(CANONICAL_STATE_TAG);
+        hasher.input_raw(&self.schema_id()[..]);
+        hasher.input_raw(&self.contract_id()[..]);
+        for leaf in self.canonical_leaves() {
+            hasher.input_raw(&leaf.to_byte_array());
+        }
+        Bytes32::from_byte_array(hasher.finish())
+    }
+
+    /// Returns view of the contract state which is compared and hashed by
+    /// its canonical form, allowing use of the state as a key in hash maps
+    /// and sets.
+    pub fn canonical(&self) -> CanonicalState<'_> {
+        CanonicalState {
+            state: self,
+            hash: self.canonical_hash(),
+        }
+    }
+}
+
+/// View of the contract state with equality and hashing defined by its
+/// canonical form.
+#[derive(Copy, Clone, Debug)]
+pub struct CanonicalState<'state> {
+    state: &'state ContractState,
+    hash: Bytes32,
+}
+
+impl<'state> CanonicalState<'state> {
+    pub fn state(&self) -> &'state ContractState { self.state }
+
+    pub fn canonical_hash(&self) -> Bytes32 { self.hash }
+}
+
+impl PartialEq for CanonicalState<'_> {
+    fn eq(&self, other: &Self) -> bool { self.hash == other.hash }
+}
+
+impl Eq for CanonicalState<'_> {}
+
+impl Hash for CanonicalState<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.hash.hash(state) }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use amplify::confinement::{Confined, SmallBlob};
+    use amplify::Wrapper;
+    use bp::Txid;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{
+        Assign, AssignmentType, Assignments, DataState, Extension, ExtensionType, Genesis,
+        GlobalStateType, Input, Inputs, Operation, Opout, Schema, Transition, TypedAssigns,
+        VoidState, WitnessAnchor, WitnessOrd, WitnessPos, XChain, XGenesisSeal,
+    };
+
+    fn extension(no: u8) -> Extension {
+        let mut extension = Extension::strict_dumb();
+        extension.extension_type = ExtensionType::with(no as u16);
+        extension
+            .globals
+            .add_state(GlobalStateType::with(1), DataState::from(SmallBlob::from_iter_unsafe([no])))
+            .unwrap();
+        extension.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            AssignmentType::with(1),
+            TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                XGenesisSeal::strict_dumb(),
+                VoidState::default(),
+            )])),
+        )]));
+        extension
+    }
+
+    fn anchor(no: u8, witness_ord: WitnessOrd) -> WitnessAnchor {
+        WitnessAnchor {
+            witness_ord,
+            witness_id: XChain::Bitcoin(Txid::from([no; 32])),
+        }
+    }
+
+    fn state(extensions: &[(u8, WitnessOrd)]) -> ContractState {
+        let genesis = Genesis::strict_dumb();
+        let mut history = ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis);
+        for (no, witness_ord) in extensions {
+            history.add_extension(&extension(*no), anchor(*no, *witness_ord));
+        }
+        ContractState {
+            schema: Schema::strict_dumb(),
+            history,
+        }
+    }
+
+    #[test]
+    fn insertion_order() {
+        let offchain = WitnessOrd::OffChain;
+        let state1 = state(&[(1, offchain), (2, offchain)]);
+        let state2 = state(&[(2, offchain), (1, offchain)]);
+        assert_eq!(state1.canonical_leaves().len(), 4);
+        assert!(state1.canonical_eq(&state2));
+        assert_eq!(state1.canonical_hash(), state2.canonical_hash());
+        let set = HashSet::from([state1.canonical(), state2.canonical()]);
+        assert_eq!(set.len(), 1);
+
+        let partial = state(&[(1, offchain)]);
+        assert!(!partial.canonical_eq(&state1));
+        assert_ne!(partial.canonical_hash(), state1.canonical_hash());
+    }
+
+    #[test]
+    fn witness_status() {
+        let mined = WitnessOrd::OnChain(WitnessPos::new(100, 1700000000).unwrap());
+        let pending = state(&[(1, WitnessOrd::OffChain)]);
+        let confirmed = state(&[(1, mined)]);
+        assert!(pending.canonical_eq(&confirmed));
+        assert_eq!(pending.canonical_hash(), confirmed.canonical_hash());
+    }
+
+    #[test]
+    fn spent_allocations() {
+        let unspent = state(&[(1, WitnessOrd::OffChain)]);
+        let mut spent = state(&[(1, WitnessOrd::OffChain)]);
+
+        let prev_out = Opout::new(extension(1).id(), AssignmentType::with(1), 0);
+        let mut transition = Transition::strict_dumb();
+        transition.inputs = Inputs::try_from_inputs([Input::with(prev_out)]).unwrap();
+        spent
+            .history
+            .add_transition(&transition, anchor(2, WitnessOrd::OffChain));
+
+        assert_eq!(spent.state_leaves(), unspent.state_leaves());
+        assert!(!spent.canonical_eq(&unspent));
+        assert_ne!(spent.canonical_hash(), unspent.canonical_hash());
+    }
+}
@@ -0,0 +1,172 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Chain-specific salt of the contract id.
+//!
+//! Contract id is derived from the genesis operation id, and two geneses with
+//! identical data issued for different networks (for instance, testnet and
+//! mainnet) would produce the same contract id. Starting from
+//! [`ContractIdVersion::V1`] genesis commits to the network it is issued for
+//! with the [`schema::MetaType::CHAIN_SALT`] metadata, which makes the
+//! contract id specific to that network. Geneses without the metadata keep
+//! the legacy [`ContractIdVersion::V0`] ids.
+
+use amplify::confinement::Confined;
+
+use crate::{schema, Genesis, Layer1, MetaValue};
+
+/// Network a contract is issued for, used as a salt of the contract id.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[repr(u8)]
+pub enum ChainNet {
+    #[display("bitcoin-mainnet")]
+    BitcoinMainnet = 0x00,
+    #[display("bitcoin-testnet3")]
+    BitcoinTestnet3 = 0x01,
+    #[display("bitcoin-testnet4")]
+    BitcoinTestnet4 = 0x02,
+    #[display("bitcoin-signet")]
+    BitcoinSignet = 0x03,
+    #[display("bitcoin-regtest")]
+    BitcoinRegtest = 0x04,
+    #[display("liquid-mainnet")]
+    LiquidMainnet = 0x10,
+    #[display("liquid-testnet")]
+    LiquidTestnet = 0x11,
+}
+
+impl ChainNet {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0x00 => ChainNet::BitcoinMainnet,
+            0x01 => ChainNet::BitcoinTestnet3,
+            0x02 => ChainNet::BitcoinTestnet4,
+            0x03 => ChainNet::BitcoinSignet,
+            0x04 => ChainNet::BitcoinRegtest,
+            0x10 => ChainNet::LiquidMainnet,
+            0x11 => ChainNet::LiquidTestnet,
+            _ => return None,
+        })
+    }
+
+    pub fn layer1(self) -> Layer1 {
+        match self {
+            ChainNet::BitcoinMainnet |
+            ChainNet::BitcoinTestnet3 |
+            ChainNet::BitcoinTestnet4 |
+            ChainNet::BitcoinSignet |
+            ChainNet::BitcoinRegtest => Layer1::Bitcoin,
+            ChainNet::LiquidMainnet | ChainNet::LiquidTestnet => Layer1::Liquid,
+        }
+    }
+
+    pub fn is_testnet(self) -> bool {
+        !matches!(self, ChainNet::BitcoinMainnet | ChainNet::LiquidMainnet)
+    }
+
+    /// Constructs value for the [`schema::MetaType::CHAIN_SALT`] genesis
+    /// metadata.
+    pub fn to_meta(self) -> MetaValue {
+        MetaValue::from(Confined::try_from(vec![self as u8]).expect("fixed size"))
+    }
+}
+
+/// Version of the contract id derivation procedure.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum ContractIdVersion {
+    /// Legacy contract id, which is not specific to a network.
+    #[display("v0")]
+    V0,
+
+    /// Contract id salted with the network the contract is issued for.
+    #[display("v1({0})")]
+    V1(ChainNet),
+}
+
+impl ContractIdVersion {
+    /// Returns network the contract id is specific to, if any.
+    pub fn chain_net(self) -> Option<ChainNet> {
+        match self {
+            ContractIdVersion::V0 => None,
+            ContractIdVersion::V1(chain_net) => Some(chain_net),
+        }
+    }
+}
+
+impl Genesis {
+    /// Returns version of the contract id derivation, as determined by the
+    /// [`schema::MetaType::CHAIN_SALT`] metadata. If the metadata is absent,
+    /// the contract uses legacy [`ContractIdVersion::V0`] id.
+    ///
+    /// Returns `None` if the metadata value is malformed or doesn't match the
+    /// network and layers 1 of the genesis.
+    pub fn contract_id_version(&self) -> Option<ContractIdVersion> {
+        let Some(meta) = self.metadata.get(&schema::MetaType::CHAIN_SALT) else {
+            return Some(ContractIdVersion::V0);
+        };
+        let [code] = meta.as_slice() else {
+            return None;
+        };
+        let chain_net = ChainNet::from_u8(*code)?;
+        if chain_net.is_testnet() != self.testnet {
+            return None;
+        }
+        if chain_net.layer1() != Layer1::Bitcoin &&
+            !self
+                .alt_layers1
+                .iter()
+                .any(|alt| alt.layer1() == chain_net.layer1())
+        {
+            return None;
+        }
+        Some(ContractIdVersion::V1(chain_net))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chain_salt_meta() {
+        for code in 0..=u8::MAX {
+            let Some(chain_net) = ChainNet::from_u8(code) else {
+                continue;
+            };
+            assert_eq!(chain_net as u8, code);
+            assert_eq!(chain_net.to_meta().as_slice(), &[code]);
+        }
+        assert!(!ChainNet::BitcoinMainnet.is_testnet());
+        assert!(ChainNet::LiquidTestnet.is_testnet());
+        assert_eq!(ChainNet::LiquidMainnet.layer1(), Layer1::Liquid);
+    }
+}
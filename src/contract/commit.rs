@@ -121,6 +121,58 @@ impl CommitmentId for OpId {
     const TAG: &'static str = "urn:lnp-bp:rgb:operation#2024-02-03";
 }
 
+/// Digest algorithm used for computing commitment ids of contract operations.
+///
+/// All ids under the current consensus rules are produced with tagged SHA-256,
+/// which is the version 1 of the algorithm. New algorithms may be added here
+/// without the need of redefining id types. Each algorithm hashes operation
+/// commitments under its own tag (see [`DigestAlgo::op_id_tag`]), such that
+/// ids produced by different algorithms never collide.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, Default)]
+#[repr(u8)]
+#[non_exhaustive]
+pub enum DigestAlgo {
+    #[default]
+    #[display("sha256t")]
+    Sha256Tagged = 1,
+}
+
+impl DigestAlgo {
+    /// All digest algorithms supported by this version of the library,
+    /// starting from the most recent.
+    pub const ALL: [DigestAlgo; 1] = [DigestAlgo::Sha256Tagged];
+
+    pub const fn version(self) -> u8 { self as u8 }
+
+    pub fn from_version(version: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|algo| algo.version() == version)
+    }
+
+    /// Tag of the hash producing operation ids with the algorithm. The
+    /// version 1 tag is the [`OpId`] commitment tag, thus the ids computed
+    /// with it match [`Operation::id`].
+    pub const fn op_id_tag(self) -> &'static str {
+        match self {
+            DigestAlgo::Sha256Tagged => OpId::TAG,
+        }
+    }
+}
+
+impl OpCommitment {
+    /// Computes operation id from the commitment using a specific digest
+    /// algorithm.
+    pub fn id_with(&self, algo: DigestAlgo) -> OpId {
+        match algo {
+            DigestAlgo::Sha256Tagged => {
+                let mut engine = CommitEngine::new(algo.op_id_tag());
+                self.commit_encode(&mut engine);
+                engine.set_finished();
+                OpId::from(engine.finish())
+            }
+        }
+    }
+}
+
 impl FromStr for OpId {
     type Err = hex::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> { Self::from_hex(s) }
@@ -440,3 +492,38 @@ impl MerkleLeaves for GlobalState {
             .into_iter()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::OpRef;
+
+    #[test]
+    fn digest_algo_versions() {
+        assert_eq!(DigestAlgo::default().version(), 1);
+        assert_eq!(DigestAlgo::from_version(1), Some(DigestAlgo::Sha256Tagged));
+        assert_eq!(DigestAlgo::from_version(0), None);
+        assert_eq!(DigestAlgo::from_version(2), None);
+        assert_eq!(DigestAlgo::Sha256Tagged.op_id_tag(), OpId::TAG);
+    }
+
+    #[test]
+    fn ids_with_algo() {
+        let genesis = Genesis::strict_dumb();
+        let transition = Transition::strict_dumb();
+        let extension = Extension::strict_dumb();
+        let ops = [
+            OpRef::Genesis(&genesis),
+            OpRef::Transition(&transition),
+            OpRef::Extension(&extension),
+        ];
+        for op in ops {
+            let opid = op.id();
+            assert_eq!(op.id_with(DigestAlgo::Sha256Tagged), opid);
+            assert_eq!(op.verify_id(opid), Some(DigestAlgo::Sha256Tagged));
+            assert_eq!(op.verify_id(OpId::from([0xA1; 32])), None);
+        }
+        assert_eq!(genesis.commit().id_with(DigestAlgo::Sha256Tagged), genesis.id());
+        assert_eq!(transition.commit().id_with(DigestAlgo::Sha256Tagged), transition.id());
+    }
+}
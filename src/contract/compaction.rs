@@ -0,0 +1,336 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compaction of replaceable global state.
+//!
+//! Some contracts (like price oracles) regularly publish new values of a
+//! global state type, where each new value replaces the previous one. Such
+//! types are declared replaceable in the contract genesis with the
+//! [`schema::MetaType::REPLACEABLE_GLOBALS`] metadata. Only the latest value
+//! of a replaceable type is meaningful, so the stored contract history may be
+//! compacted with [`ContractHistory::compact_globals`], which keeps only the
+//! latest value of each replaceable type. Each compaction produces a
+//! [`GlobalSupersession`], which proves that the removed values were
+//! superseded by the kept one, and allows to audit the removed values if they
+//! are provided by a third party.
+//!
+//! Compaction applies to the stored history only: consignments always carry
+//! complete operations, since the global state values are committed into the
+//! operation ids.
+
+use std::collections::BTreeSet;
+use std::vec;
+
+use amplify::confinement::Confined;
+use amplify::Wrapper;
+use commit_verify::{CommitEncode, CommitEngine, MerkleHash, MerkleLeaves};
+
+use crate::{
+    schema, ContractHistory, ContractId, DataState, Genesis, GlobalOrd, GlobalStateType, MetaValue,
+    Operation,
+};
+
+impl Genesis {
+    /// Returns global state types declared replaceable by the
+    /// [`schema::MetaType::REPLACEABLE_GLOBALS`] metadata. If the metadata is
+    /// absent, no types are replaceable.
+    ///
+    /// Returns `None` if the metadata value is malformed.
+    pub fn replaceable_globals(&self) -> Option<BTreeSet<GlobalStateType>> {
+        let Some(meta) = self.metadata.get(&schema::MetaType::REPLACEABLE_GLOBALS) else {
+            return Some(empty!());
+        };
+        let data = meta.as_slice();
+        if data.is_empty() || data.len() % 2 != 0 {
+            return None;
+        }
+        let types = data
+            .chunks(2)
+            .map(|chunk| GlobalStateType::with(u16::from_le_bytes([chunk[0], chunk[1]])))
+            .collect::<BTreeSet<_>>();
+        if types.len() * 2 != data.len() {
+            return None;
+        }
+        Some(types)
+    }
+}
+
+/// Constructs value for the [`schema::MetaType::REPLACEABLE_GLOBALS`] genesis
+/// metadata.
+///
+/// Returns `None` if there are no replaceable types or too many of them to fit
+/// the metadata.
+pub fn replaceable_globals_meta(types: &BTreeSet<GlobalStateType>) -> Option<MetaValue> {
+    if types.is_empty() {
+        return None;
+    }
+    let data = types
+        .iter()
+        .flat_map(|ty| ty.to_inner().to_le_bytes())
+        .collect::<Vec<_>>();
+    Confined::try_from(data).ok().map(MetaValue::from)
+}
+
+/// Errors verifying global state compaction.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum CompactionError {
+    /// genesis of contract {0} is used to compact history of a different
+    /// contract.
+    ContractMismatch(ContractId),
+
+    /// genesis declares replaceable global state types with a malformed
+    /// metadata value.
+    ReplaceableInvalid,
+
+    /// compacted history has no value of the global state type {0}.
+    NoLatest(GlobalStateType),
+
+    /// compacted history keeps values of the global state type {0} other than
+    /// the latest one.
+    NotCompacted(GlobalStateType),
+
+    /// value {1:?} of the global state type {0} is not superseded by the
+    /// latest value.
+    NotSuperseded(GlobalStateType, GlobalOrd),
+
+    /// superseded values of the global state type {0} do not match the
+    /// supersession commitment.
+    CommitmentMismatch(GlobalStateType),
+}
+
+/// Proof that values of a replaceable global state type removed by the
+/// compaction were superseded by the kept latest value.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct GlobalSupersession {
+    pub ty: GlobalStateType,
+    /// Position of the latest value kept by the compaction.
+    pub latest: GlobalOrd,
+    /// Positions of the removed values, in the consensus order.
+    pub superseded: Vec<GlobalOrd>,
+    /// Merkle commitment to the removed values and their positions.
+    pub commitment: MerkleHash,
+}
+
+/// Removed value committed together with its position, such that the values
+/// provided for an audit can't be reordered or attributed to other
+/// operations.
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct SupersededValue {
+    ty: GlobalStateType,
+    ord: GlobalOrd,
+    state: DataState,
+}
+
+impl CommitEncode for SupersededValue {
+    type CommitmentId = MerkleHash;
+
+    fn commit_encode(&self, e: &mut CommitEngine) {
+        e.commit_to_serialized(&self.ty);
+        e.commit_to_serialized(&self.ord);
+        e.commit_to_serialized(&self.state);
+        e.set_finished();
+    }
+}
+
+struct Superseded(Vec<SupersededValue>);
+
+impl MerkleLeaves for Superseded {
+    type Leaf = SupersededValue;
+    type LeafIter<'tmp> = vec::IntoIter<SupersededValue>;
+
+    fn merkle_leaves(&self) -> Self::LeafIter<'_> { self.0.clone().into_iter() }
+}
+
+fn superseded_commitment<'a>(
+    ty: GlobalStateType,
+    values: impl IntoIterator<Item = (&'a GlobalOrd, &'a DataState)>,
+) -> MerkleHash {
+    let leaves = values
+        .into_iter()
+        .map(|(ord, state)| SupersededValue {
+            ty,
+            ord: *ord,
+            state: state.clone(),
+        })
+        .collect();
+    MerkleHash::merklize(&Superseded(leaves))
+}
+
+impl GlobalSupersession {
+    /// Verifies that the compacted history keeps only the latest value of the
+    /// global state type, and that all the removed values are ordered before
+    /// it.
+    pub fn verify(&self, history: &ContractHistory) -> Result<(), CompactionError> {
+        let state = history
+            .global_state(self.ty)
+            .ok_or(CompactionError::NoLatest(self.ty))?;
+        let (latest, _) = state.last().ok_or(CompactionError::NoLatest(self.ty))?;
+        if state.len() > 1 || latest != self.latest {
+            return Err(CompactionError::NotCompacted(self.ty));
+        }
+        if let Some(ord) = self.superseded.iter().find(|ord| **ord >= self.latest) {
+            return Err(CompactionError::NotSuperseded(self.ty, *ord));
+        }
+        Ok(())
+    }
+
+    /// Verifies that the removed values, provided in the consensus order,
+    /// match the supersession commitment to the values at the
+    /// [`GlobalSupersession::superseded`] positions.
+    pub fn verify_values<'a>(
+        &self,
+        values: impl IntoIterator<Item = &'a DataState>,
+    ) -> Result<(), CompactionError> {
+        let values = values.into_iter().collect::<Vec<_>>();
+        if values.len() != self.superseded.len() ||
+            superseded_commitment(self.ty, self.superseded.iter().zip(values)) != self.commitment
+        {
+            return Err(CompactionError::CommitmentMismatch(self.ty));
+        }
+        Ok(())
+    }
+}
+
+impl ContractHistory {
+    /// Removes all but the latest values of the global state types declared
+    /// replaceable by the contract genesis, returning proofs of supersession
+    /// for each of the types which had values removed.
+    ///
+    /// Types which are not replaceable are never compacted, since all of
+    /// their values are a part of the contract state.
+    pub fn compact_globals(
+        &mut self,
+        genesis: &Genesis,
+    ) -> Result<Vec<GlobalSupersession>, CompactionError> {
+        if genesis.contract_id() != self.contract_id() {
+            return Err(CompactionError::ContractMismatch(genesis.contract_id()));
+        }
+        let replaceable = genesis
+            .replaceable_globals()
+            .ok_or(CompactionError::ReplaceableInvalid)?;
+        let mut proofs = vec![];
+        for ty in &replaceable {
+            let Some(map) = self.global_mut(*ty) else {
+                continue;
+            };
+            let Some((latest, value)) = map.last_key_value() else {
+                continue;
+            };
+            if map.len() == 1 {
+                continue;
+            }
+            let latest = *latest;
+            let value = value.clone();
+            let superseded = map.keys().filter(|ord| **ord != latest).copied().collect();
+            let values = map.iter().filter(|(ord, _)| **ord != latest);
+            let commitment = superseded_commitment(*ty, values);
+            *map = Confined::try_from(bmap! { latest => value }).expect("single item");
+            proofs.push(GlobalSupersession {
+                ty: *ty,
+                latest,
+                superseded,
+                commitment,
+            });
+        }
+        Ok(proofs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::SmallBlob;
+    use bp::Txid;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{Extension, Operation, WitnessAnchor, WitnessOrd, WitnessPos, XChain};
+
+    fn price(value: u8) -> DataState { DataState::from(SmallBlob::from_iter_unsafe([value])) }
+
+    #[test]
+    fn compact_oracle_prices() {
+        let ty = GlobalStateType::with(1);
+        let mut genesis = Genesis::strict_dumb();
+        let meta = replaceable_globals_meta(&bset! { ty }).unwrap();
+        genesis
+            .metadata
+            .add_value(schema::MetaType::REPLACEABLE_GLOBALS, meta)
+            .unwrap();
+        genesis.globals.add_state(ty, price(1)).unwrap();
+        assert_eq!(genesis.replaceable_globals(), Some(bset! { ty }));
+
+        let mut history = ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis);
+        for (no, value) in [2u8, 3].into_iter().enumerate() {
+            let mut extension = Extension::strict_dumb();
+            extension.globals.add_state(ty, price(value)).unwrap();
+            let pos = WitnessPos::new(100 + no as u32, 1700000000 + no as i64).unwrap();
+            let anchor = WitnessAnchor {
+                witness_ord: WitnessOrd::OnChain(pos),
+                witness_id: XChain::Bitcoin(Txid::from([value; 32])),
+            };
+            history.add_extension(&extension, anchor);
+        }
+
+        let mut other = genesis.clone();
+        other.timestamp += 1;
+        assert_eq!(
+            history.compact_globals(&other).err(),
+            Some(CompactionError::ContractMismatch(other.contract_id()))
+        );
+
+        let proofs = history.compact_globals(&genesis).unwrap();
+        assert_eq!(proofs.len(), 1);
+        let proof = &proofs[0];
+        assert_eq!(proof.superseded.len(), 2);
+        assert_eq!(proof.verify(&history), Ok(()));
+        assert_eq!(history.global_state(ty).unwrap().last().unwrap().1, &price(3));
+        assert_eq!(proof.verify_values([&price(1), &price(2)]), Ok(()));
+        assert_eq!(
+            proof.verify_values([&price(2), &price(1)]),
+            Err(CompactionError::CommitmentMismatch(ty))
+        );
+        assert_eq!(proof.verify_values([&price(1)]), Err(CompactionError::CommitmentMismatch(ty)));
+
+        let mut reordered = proof.clone();
+        reordered.superseded.reverse();
+        assert_eq!(
+            reordered.verify_values([&price(2), &price(1)]),
+            Err(CompactionError::CommitmentMismatch(ty))
+        );
+        assert_eq!(history.compact_globals(&genesis), Ok(vec![]));
+    }
+
+    #[test]
+    fn meta_overflow() {
+        let types = (0..=u16::MAX / 2)
+            .map(GlobalStateType::with)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(replaceable_globals_meta(&types), None);
+        assert_eq!(replaceable_globals_meta(&empty!()), None);
+    }
+}
@@ -0,0 +1,315 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Consensus-critical ordering of contract data.
+//!
+//! All [`Ord`] implementations which affect consensus are defined in this
+//! module. Changing any of them changes either commitments or the order in
+//! which the contract state is applied, and thus is a consensus change.
+//!
+//! | Type                 | Ordering                                          |
+//! |----------------------|---------------------------------------------------|
+//! | [`WitnessPos`]       | by block timestamp; height is not taken into      |
+//! |                      | account                                           |
+//! | [`WitnessOrd`]       | mined witnesses go before off-chain ones (derived |
+//! |                      | from the variant order)                           |
+//! | [`WitnessAnchor`]    | by [`WitnessOrd`], then by witness id             |
+//! | [`GlobalOrd`]        | genesis state goes first; then by the witness     |
+//! |                      | anchor and by the index within the operation      |
+//! | [`Assign`]           | by the concealed seal, defining the assignment    |
+//! |                      | indexes committed into operation id               |
+//! | [`OutputAssignment`] | by the operation output                           |
+//! | [`Transition`]       | by operation id                                   |
+//! | [`Extension`]        | by operation id                                   |
+//!
+//! Transition bundles keep transitions in a map ordered by the operation id,
+//! which is covered by the [`Transition`] ordering.
+//!
+//! The rules are checked against test vectors returned by [`ord_vectors`].
+
+use std::cmp::Ordering;
+
+use bp::Txid;
+
+use crate::{
+    Assign, ExposedSeal, ExposedState, Extension, GlobalOrd, KnownState, Operation,
+    OutputAssignment, Transition, WitnessAnchor, WitnessOrd, WitnessPos, XChain,
+};
+
+impl PartialOrd for WitnessPos {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl Ord for WitnessPos {
+    fn cmp(&self, other: &Self) -> Ordering { self.timestamp().cmp(&other.timestamp()) }
+}
+
+impl PartialOrd for WitnessAnchor {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl Ord for WitnessAnchor {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other {
+            return Ordering::Equal;
+        }
+        match self.witness_ord.cmp(&other.witness_ord) {
+            Ordering::Less => Ordering::Less,
+            Ordering::Greater => Ordering::Greater,
+            Ordering::Equal => self.witness_id.cmp(&other.witness_id),
+        }
+    }
+}
+
+impl PartialOrd for GlobalOrd {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl Ord for GlobalOrd {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other {
+            return Ordering::Equal;
+        }
+        match (self.witness_anchor, &other.witness_anchor) {
+            (None, None) => self.idx.cmp(&other.idx),
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(ord1), Some(ord2)) if ord1 == *ord2 => self.idx.cmp(&other.idx),
+            (Some(ord1), Some(ord2)) => ord1.cmp(ord2),
+        }
+    }
+}
+
+// Assignment indexes are part of the transition ancestor's commitment, so
+// here we use deterministic ordering based on hash values of the concealed
+// seal data contained within the assignment
+impl<State: ExposedState, Seal: ExposedSeal> PartialOrd for Assign<State, Seal> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl<State: ExposedState, Seal: ExposedSeal> Ord for Assign<State, Seal> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_confidential_seal()
+            .cmp(&other.to_confidential_seal())
+    }
+}
+
+impl<State: KnownState> PartialOrd for OutputAssignment<State> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl<State: KnownState> Ord for OutputAssignment<State> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other {
+            return Ordering::Equal;
+        }
+        self.opout.cmp(&other.opout)
+    }
+}
+
+impl Ord for Transition {
+    fn cmp(&self, other: &Self) -> Ordering { self.id().cmp(&other.id()) }
+}
+
+impl PartialOrd for Transition {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl Ord for Extension {
+    fn cmp(&self, other: &Self) -> Ordering { self.id().cmp(&other.id()) }
+}
+
+impl PartialOrd for Extension {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+/// Test vector for the consensus ordering: the `lesser` value must be ordered
+/// before the `greater` one.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct OrdVector<T: Ord> {
+    pub name: &'static str,
+    pub lesser: T,
+    pub greater: T,
+}
+
+impl<T: Ord> OrdVector<T> {
+    fn new(name: &'static str, lesser: T, greater: T) -> Self {
+        OrdVector {
+            name,
+            lesser,
+            greater,
+        }
+    }
+
+    /// Checks that the ordering of the vector values matches the consensus
+    /// and is antisymmetric.
+    pub fn check(&self) -> bool {
+        self.lesser.cmp(&self.greater) == Ordering::Less &&
+            self.greater.cmp(&self.lesser) == Ordering::Greater &&
+            self.lesser.cmp(&self.lesser) == Ordering::Equal
+    }
+}
+
+/// Test vectors for the consensus ordering of witness and global state
+/// positions.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct OrdVectors {
+    pub witness_pos: Vec<OrdVector<WitnessPos>>,
+    pub witness_ord: Vec<OrdVector<WitnessOrd>>,
+    pub witness_anchor: Vec<OrdVector<WitnessAnchor>>,
+    pub global_ord: Vec<OrdVector<GlobalOrd>>,
+}
+
+impl OrdVectors {
+    /// Checks all the test vectors, returning names of the failed ones.
+    pub fn failures(&self) -> Vec<&'static str> {
+        let witness_pos = self
+            .witness_pos
+            .iter()
+            .filter(|v| !v.check())
+            .map(|v| v.name);
+        let witness_ord = self
+            .witness_ord
+            .iter()
+            .filter(|v| !v.check())
+            .map(|v| v.name);
+        let witness_anchor = self
+            .witness_anchor
+            .iter()
+            .filter(|v| !v.check())
+            .map(|v| v.name);
+        let global_ord = self
+            .global_ord
+            .iter()
+            .filter(|v| !v.check())
+            .map(|v| v.name);
+        witness_pos
+            .chain(witness_ord)
+            .chain(witness_anchor)
+            .chain(global_ord)
+            .collect()
+    }
+}
+
+/// Returns test vectors for the consensus ordering.
+pub fn ord_vectors() -> OrdVectors {
+    let pos_early = WitnessPos::new(100, 1700000000).expect("valid position");
+    let pos_late = WitnessPos::new(90, 1700000600).expect("valid position");
+    let witness_a = XChain::Bitcoin(Txid::from([0x01; 32]));
+    let witness_b = XChain::Bitcoin(Txid::from([0x02; 32]));
+    let mined_a = WitnessAnchor {
+        witness_ord: WitnessOrd::OnChain(pos_early),
+        witness_id: witness_a,
+    };
+    let mined_b = WitnessAnchor {
+        witness_ord: WitnessOrd::OnChain(pos_early),
+        witness_id: witness_b,
+    };
+    let mined_late = WitnessAnchor {
+        witness_ord: WitnessOrd::OnChain(pos_late),
+        witness_id: witness_a,
+    };
+    let offchain = WitnessAnchor::from_mempool(witness_a);
+
+    OrdVectors {
+        witness_pos: vec![OrdVector::new("timestamp over height", pos_early, pos_late)],
+        witness_ord: vec![
+            OrdVector::new(
+                "mined by position",
+                WitnessOrd::OnChain(pos_early),
+                WitnessOrd::OnChain(pos_late),
+            ),
+            OrdVector::new(
+                "mined before offchain",
+                WitnessOrd::OnChain(pos_late),
+                WitnessOrd::OffChain,
+            ),
+        ],
+        witness_anchor: vec![
+            OrdVector::new("same position by witness id", mined_a, mined_b),
+            OrdVector::new("position over witness id", mined_b, mined_late),
+            OrdVector::new("mined before offchain", mined_late, offchain),
+        ],
+        global_ord: vec![
+            OrdVector::new("genesis by index", GlobalOrd::genesis(0), GlobalOrd::genesis(1)),
+            OrdVector::new(
+                "genesis before witness",
+                GlobalOrd::genesis(u16::MAX),
+                GlobalOrd::with_anchor(mined_a, 0),
+            ),
+            OrdVector::new(
+                "same witness by index",
+                GlobalOrd::with_anchor(mined_a, 0),
+                GlobalOrd::with_anchor(mined_a, 1),
+            ),
+            OrdVector::new(
+                "witness over index",
+                GlobalOrd::with_anchor(mined_a, u16::MAX),
+                GlobalOrd::with_anchor(mined_b, 0),
+            ),
+            OrdVector::new(
+                "mined before offchain",
+                GlobalOrd::with_anchor(mined_late, u16::MAX),
+                GlobalOrd::with_anchor(offchain, 0),
+            ),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The derived ordering of [`WitnessOrd`] must put mined witnesses before
+    /// off-chain ones and order mined witnesses by their position.
+    #[test]
+    fn witness_ord_canonical() {
+        let early = WitnessPos::new(1, 1231006505).expect("valid position");
+        let late = WitnessPos::new(1, 1231006506).expect("valid position");
+        assert!(WitnessOrd::OnChain(early) < WitnessOrd::OnChain(late));
+        assert!(WitnessOrd::OnChain(late) < WitnessOrd::OffChain);
+    }
+
+    #[test]
+    fn vectors() {
+        assert_eq!(ord_vectors().failures(), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn differential_global_ord() {
+        let vectors = ord_vectors();
+        let mut ords = vectors
+            .global_ord
+            .iter()
+            .flat_map(|v| [v.lesser, v.greater])
+            .collect::<Vec<_>>();
+        ords.sort();
+        ords.dedup();
+        // Ordering must be total and transitive across all vector values
+        for (i, a) in ords.iter().enumerate() {
+            for b in &ords[i + 1..] {
+                assert_eq!(a.cmp(b), Ordering::Less);
+                assert_eq!(b.cmp(a), Ordering::Greater);
+            }
+        }
+    }
+}
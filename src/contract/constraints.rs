@@ -0,0 +1,136 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Constraints on the witness transactions closing contract seals.
+//!
+//! A schema may require witness transactions of its contracts to signal RBF
+//! or to use anti-fee-sniping locktime, which is declared by the
+//! [`crate::Schema::witness_constraints`] field and committed into the schema
+//! id. The constraints are verified against the resolved witness transactions
+//! during anchor validation.
+
+use std::ops::RangeInclusive;
+
+use bp::Tx;
+
+use crate::{Vin, LIB_NAME_RGB};
+
+/// Constraints on the witness transactions of a contract.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct WitnessConstraints {
+    /// Minimal `nSequence` of the witness inputs spending contract seals.
+    pub sequence_min: u32,
+    /// Maximal `nSequence` of the witness inputs spending contract seals.
+    pub sequence_max: u32,
+    /// Whether witness transaction must have non-zero locktime.
+    pub locktime: bool,
+}
+
+impl Default for WitnessConstraints {
+    fn default() -> Self { Self::NONE }
+}
+
+impl WitnessConstraints {
+    /// No constraints on the witness transactions.
+    pub const NONE: Self = WitnessConstraints {
+        sequence_min: 0,
+        sequence_max: u32::MAX,
+        locktime: false,
+    };
+
+    /// Requires witness inputs to signal replace-by-fee (BIP125).
+    pub const RBF: Self = WitnessConstraints {
+        sequence_min: 0,
+        sequence_max: 0xFFFF_FFFD,
+        locktime: false,
+    };
+
+    /// Constructs constraints from the range of acceptable `nSequence`
+    /// values.
+    pub fn with_sequence(range: RangeInclusive<u32>, locktime: bool) -> Self {
+        WitnessConstraints {
+            sequence_min: *range.start(),
+            sequence_max: *range.end(),
+            locktime,
+        }
+    }
+
+    /// Checks whether the constraints are well-formed, i.e. the range of
+    /// acceptable `nSequence` values is not empty.
+    pub fn is_valid(&self) -> bool { self.sequence_min <= self.sequence_max }
+
+    /// Checks whether `nSequence` value satisfies the constraints.
+    pub fn accepts_sequence(&self, sequence: u32) -> bool {
+        (self.sequence_min..=self.sequence_max).contains(&sequence)
+    }
+
+    /// Checks whether locktime value satisfies the constraints.
+    pub fn accepts_locktime(&self, locktime: u32) -> bool { !self.locktime || locktime != 0 }
+
+    /// Returns inputs of the witness transaction from the provided set which
+    /// violate the `nSequence` constraint.
+    pub fn violating_inputs<'a>(
+        &'a self,
+        tx: &'a Tx,
+        inputs: impl IntoIterator<Item = Vin> + 'a,
+    ) -> impl Iterator<Item = Vin> + 'a {
+        inputs.into_iter().filter(|vin| {
+            tx.inputs
+                .get(vin.to_usize())
+                .map(|input| !self.accepts_sequence(input.sequence.to_consensus_u32()))
+                .unwrap_or_default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use strict_encoding::{StrictDeserialize, StrictDumb, StrictSerialize};
+
+    use super::*;
+
+    impl StrictSerialize for WitnessConstraints {}
+    impl StrictDeserialize for WitnessConstraints {}
+
+    #[test]
+    fn constraints() {
+        let constraints = WitnessConstraints::with_sequence(0..=0xFFFF_FFFD, true);
+        assert!(constraints.is_valid());
+        assert!(constraints.accepts_sequence(0xFFFF_FFFD));
+        assert!(!constraints.accepts_sequence(0xFFFF_FFFE));
+        assert!(!constraints.accepts_locktime(0));
+        assert!(constraints.accepts_locktime(1));
+        assert!(!WitnessConstraints::with_sequence(RangeInclusive::new(2, 1), false).is_valid());
+        assert_eq!(WitnessConstraints::strict_dumb(), WitnessConstraints::NONE);
+
+        let data = constraints.to_strict_serialized::<16>().unwrap();
+        assert_eq!(data.len(), 9);
+        assert_eq!(WitnessConstraints::from_strict_serialized::<16>(data).unwrap(), constraints);
+    }
+}
@@ -22,11 +22,13 @@
 
 //! Extraction of contract state.
 
-use std::cmp::Ordering;
+use std::cell::OnceCell;
+use std::collections::{btree_map, BTreeSet};
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::iter;
 use std::num::ParseIntError;
-use std::ops::{Deref, DerefMut};
+use std::ops::{Bound, Deref, DerefMut, RangeBounds};
 use std::str::FromStr;
 
 use amplify::confinement::{LargeOrdMap, LargeOrdSet, SmallVec, TinyOrdMap};
@@ -34,10 +36,11 @@ use amplify::hex;
 use strict_encoding::{StrictDecode, StrictDumb, StrictEncode};
 
 use crate::{
-    Assign, AssignmentType, Assignments, AssignmentsRef, ContractId, DataState, ExposedSeal,
-    ExposedState, Extension, Genesis, GlobalStateType, OpId, Operation, RevealedAttach,
-    RevealedData, RevealedValue, Schema, SchemaId, Transition, TypedAssigns, VoidState,
-    WitnessAnchor, XChain, XOutputSeal, XWitnessId, LIB_NAME_RGB,
+    Assign, AssignmentType, Assignments, AssignmentsRef, AttachId, ContractId, DataState,
+    ExposedSeal, ExposedState, Extension, FungibleState, Genesis, GlobalStateType, MediaType, OpId,
+    OpRef, OpType, Operation, RevealedAttach, RevealedData, RevealedValue, Schema, SchemaId,
+    StateLeaf, Transition, TypedAssigns, VoidState, WitnessAnchor, WitnessOrd, XChain, XOutpoint,
+    XOutputSeal, XWitnessId, LIB_NAME_RGB,
 };
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
@@ -164,19 +167,6 @@ impl<State: KnownState> PartialEq for OutputAssignment<State> {
     }
 }
 
-impl<State: KnownState> PartialOrd for OutputAssignment<State> {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
-}
-
-impl<State: KnownState> Ord for OutputAssignment<State> {
-    fn cmp(&self, other: &Self) -> Ordering {
-        if self == other {
-            return Ordering::Equal;
-        }
-        self.opout.cmp(&other.opout)
-    }
-}
-
 impl<State: KnownState> OutputAssignment<State> {
     /// # Panics
     ///
@@ -223,8 +213,8 @@ impl<State: KnownState> OutputAssignment<State> {
         }
     }
 
-    pub fn transmute<S: KnownState>(self) -> OutputAssignment<S>
-    where S: From<State> {
+    pub fn transmute<S>(self) -> OutputAssignment<S>
+    where S: KnownState + From<State> {
         OutputAssignment {
             opout: self.opout,
             seal: self.seal,
@@ -244,28 +234,12 @@ impl<State: KnownState> OutputAssignment<State> {
 )]
 pub struct GlobalOrd {
     pub witness_anchor: Option<WitnessAnchor>,
+    /// Index of the item among the global state of the same type defined by
+    /// the operation. Operations define at most 65535 items of each type (see
+    /// [`crate::GlobalValueVec`]), so the index always fits 16 bits.
     pub idx: u16,
 }
 
-impl PartialOrd for GlobalOrd {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
-}
-
-impl Ord for GlobalOrd {
-    fn cmp(&self, other: &Self) -> Ordering {
-        if self == other {
-            return Ordering::Equal;
-        }
-        match (self.witness_anchor, &other.witness_anchor) {
-            (None, None) => self.idx.cmp(&other.idx),
-            (None, Some(_)) => Ordering::Less,
-            (Some(_), None) => Ordering::Greater,
-            (Some(ord1), Some(ord2)) if ord1 == *ord2 => self.idx.cmp(&other.idx),
-            (Some(ord1), Some(ord2)) => ord1.cmp(ord2),
-        }
-    }
-}
-
 impl GlobalOrd {
     pub fn with_anchor(ord_txid: WitnessAnchor, idx: u16) -> Self {
         GlobalOrd {
@@ -279,6 +253,31 @@ impl GlobalOrd {
             idx,
         }
     }
+
+    /// Returns mining timestamp of the witness which had defined the global
+    /// state. The value is `None` for genesis state, state extensions and
+    /// not yet mined witnesses.
+    pub fn timestamp(&self) -> Option<i64> {
+        self.witness_anchor
+            .as_ref()
+            .and_then(WitnessAnchor::timestamp)
+    }
+}
+
+/// State transition which has spent an output, together with the anchor of
+/// its witness.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display("{opid}@{witness_anchor}")]
+pub struct SpendingOp {
+    pub opid: OpId,
+    pub witness_anchor: WitnessAnchor,
 }
 
 /// Contract history accumulates raw data from the contract history, extracted
@@ -306,6 +305,15 @@ pub struct ContractHistory {
     fungibles: LargeOrdSet<OutputAssignment<RevealedValue>>,
     data: LargeOrdSet<OutputAssignment<RevealedData>>,
     attach: LargeOrdSet<OutputAssignment<RevealedAttach>>,
+    /// Outputs spent by state transitions.
+    #[getter(skip)]
+    spent: LargeOrdMap<Opout, SpendingOp>,
+    /// Positions of the witnesses of all operations added to the history.
+    #[getter(skip)]
+    witnesses: LargeOrdMap<XWitnessId, WitnessOrd>,
+    /// State extensions added to the history.
+    #[getter(skip)]
+    extensions: LargeOrdSet<OpId>,
 }
 
 impl ContractHistory {
@@ -322,6 +330,9 @@ impl ContractHistory {
             fungibles: empty!(),
             data: empty!(),
             attach: empty!(),
+            spent: empty!(),
+            witnesses: empty!(),
+            extensions: empty!(),
         };
         state.update_genesis(genesis);
         state
@@ -349,9 +360,213 @@ impl ContractHistory {
         self.add_operation(extension, Some(witness_anchor));
     }
 
-    fn add_operation(&mut self, op: &impl Operation, witness_anchor: Option<WitnessAnchor>) {
+    /// Adds operation of any type to the history. Genesis and state
+    /// extensions may not have a witness anchor.
+    ///
+    /// # Panics
+    ///
+    /// If the operation violates RGB consensus rules and wasn't checked
+    /// against the schema before adding to the history.
+    pub fn add_op(&mut self, op: OpRef, witness_anchor: Option<WitnessAnchor>) {
+        self.add_operation(&op, witness_anchor);
+    }
+
+    /// Lists all known state items in their consensus order: global state
+    /// items ordered by their type and [`GlobalOrd`], followed by rights,
+    /// fungible, structured and attachment allocations.
+    pub fn state_leaves(&self) -> Vec<StateLeaf> {
+        let global = self.global.iter().flat_map(|(ty, map)| {
+            map.iter().map(|(ord, state)| StateLeaf::Global {
+                ty: *ty,
+                ord: *ord,
+                state: state.clone(),
+            })
+        });
+        global
+            .chain(self.rights.iter().cloned().map(StateLeaf::Rights))
+            .chain(self.fungibles.iter().cloned().map(StateLeaf::Fungible))
+            .chain(self.data.iter().cloned().map(StateLeaf::Data))
+            .chain(self.attach.iter().cloned().map(StateLeaf::Attach))
+            .collect()
+    }
+
+    /// Returns all witnesses which the contract state depends on.
+    pub fn witness_ids(&self) -> BTreeSet<XWitnessId> {
+        fn witness<State: KnownState>(a: &OutputAssignment<State>) -> Option<XWitnessId> {
+            match a.witness {
+                AssignmentWitness::Absent => None,
+                AssignmentWitness::Present(witness_id) => Some(witness_id),
+            }
+        }
+        self.global
+            .values()
+            .flat_map(|map| map.keys())
+            .filter_map(|ord| ord.witness_anchor.map(|anchor| anchor.witness_id))
+            .chain(self.rights.iter().filter_map(witness))
+            .chain(self.fungibles.iter().filter_map(witness))
+            .chain(self.data.iter().filter_map(witness))
+            .chain(self.attach.iter().filter_map(witness))
+            .collect()
+    }
+
+    /// Returns position of the witness as it is known to the contract
+    /// history, or `None` if the witness is unknown to the contract.
+    pub fn witness_status(&self, witness_id: XWitnessId) -> Option<WitnessOrd> {
+        self.witnesses.get(&witness_id).copied()
+    }
+
+    pub(crate) fn witness_anchor(&self, witness_id: XWitnessId) -> Option<WitnessAnchor> {
+        self.witness_status(witness_id)
+            .map(|witness_ord| WitnessAnchor {
+                witness_ord,
+                witness_id,
+            })
+    }
+
+    /// Returns state transition which had spent the output, if the
+    /// transition is known to the history.
+    pub fn spent_by(&self, opout: Opout) -> Option<SpendingOp> { self.spent.get(&opout).copied() }
+
+    /// Returns outputs spent by state transitions together with the ids of
+    /// the spending transitions.
+    pub(crate) fn spends(&self) -> impl Iterator<Item = (Opout, OpId)> + '_ {
+        self.spent
+            .iter()
+            .map(|(opout, spender)| (*opout, spender.opid))
+    }
+
+    /// Returns ids of the operations known to the history: state extensions
+    /// and the operations which have assigned revealed state or spent
+    /// outputs.
+    pub(crate) fn op_ids(&self) -> BTreeSet<OpId> {
+        self.rights
+            .iter()
+            .map(|a| a.opout.op)
+            .chain(self.fungibles.iter().map(|a| a.opout.op))
+            .chain(self.data.iter().map(|a| a.opout.op))
+            .chain(self.attach.iter().map(|a| a.opout.op))
+            .chain(self.spent.values().map(|spender| spender.opid))
+            .chain(self.extensions.iter().copied())
+            .collect()
+    }
+
+    /// Checks whether the operation is a state extension known to the
+    /// history.
+    pub fn is_extension(&self, opid: OpId) -> bool { self.extensions.contains(&opid) }
+
+    /// Lists owned state assigned by state extensions, in the same order as
+    /// [`ContractHistory::state_leaves`].
+    ///
+    /// Global state is not included, since the history doesn't keep track of
+    /// the operations defining global state items.
+    pub fn extension_allocations(&self) -> Vec<StateLeaf> {
+        self.state_leaves()
+            .into_iter()
+            .filter(|leaf| match leaf {
+                StateLeaf::Global { .. } => false,
+                StateLeaf::Rights(a) => self.is_extension(a.opout.op),
+                StateLeaf::Fungible(a) => self.is_extension(a.opout.op),
+                StateLeaf::Data(a) => self.is_extension(a.opout.op),
+                StateLeaf::Attach(a) => self.is_extension(a.opout.op),
+            })
+            .collect()
+    }
+
+    /// Lists witnesses which the contract state depends on and which are not
+    /// known to be mined, i.e. either have [`WitnessOrd::OffChain`] status or
+    /// their position is unknown (see [`ContractHistory::witness_status`]).
+    pub fn pending_witnesses(&self) -> BTreeSet<XWitnessId> {
+        self.witness_ids()
+            .into_iter()
+            .filter(|witness_id| {
+                !matches!(self.witness_status(*witness_id), Some(WitnessOrd::OnChain(_)))
+            })
+            .collect()
+    }
+
+    /// Lists outpoints holding state of the given assignment type, whatever
+    /// the state type is. Each outpoint is listed once, in the lexicographic
+    /// order.
+    pub fn outpoints(&self, ty: AssignmentType) -> impl Iterator<Item = XOutpoint> {
+        fn outpoints<State: KnownState>(
+            assignments: &LargeOrdSet<OutputAssignment<State>>,
+            ty: AssignmentType,
+        ) -> impl Iterator<Item = XOutpoint> + '_ {
+            assignments
+                .iter()
+                .filter(move |a| a.opout.ty == ty)
+                .map(|a| a.seal.to_outpoint())
+        }
+        outpoints(&self.rights, ty)
+            .chain(outpoints(&self.fungibles, ty))
+            .chain(outpoints(&self.data, ty))
+            .chain(outpoints(&self.attach, ty))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+    }
+
+    /// Lists attachment allocations matching the filter, optionally limited
+    /// to the ones assigned to a specific outpoint.
+    pub fn attach_filtered<'a>(
+        &'a self,
+        outpoint: Option<XOutpoint>,
+        filter: impl Fn(&RevealedAttach) -> bool + 'a,
+    ) -> impl Iterator<Item = &'a OutputAssignment<RevealedAttach>> + 'a {
+        self.attach.iter().filter(move |a| {
+            outpoint.map_or(true, |o| a.seal.to_outpoint() == o) && filter(&a.state)
+        })
+    }
+
+    /// Lists attachment allocations with the given content id, optionally
+    /// limited to the ones assigned to a specific outpoint.
+    pub fn attach_by_id(
+        &self,
+        outpoint: Option<XOutpoint>,
+        id: AttachId,
+    ) -> impl Iterator<Item = &OutputAssignment<RevealedAttach>> {
+        self.attach_filtered(outpoint, move |state| state.id == id)
+    }
+
+    /// Lists attachment allocations whose media type matches the predicate,
+    /// optionally limited to the ones assigned to a specific outpoint.
+    pub fn attach_by_media<'a>(
+        &'a self,
+        outpoint: Option<XOutpoint>,
+        filter: impl Fn(&MediaType) -> bool + 'a,
+    ) -> impl Iterator<Item = &'a OutputAssignment<RevealedAttach>> + 'a {
+        self.attach_filtered(outpoint, move |state| filter(&state.media_type))
+    }
+
+    /// Returns consensus-ordered view of the global state of the given type,
+    /// or `None` if the contract has no global state of this type.
+    pub fn global_state(&self, ty: GlobalStateType) -> Option<GlobalContractState<'_>> {
+        self.global.get(&ty).map(|items| GlobalContractState {
+            ty,
+            items,
+            cache: None,
+        })
+    }
+
+    pub(crate) fn global_mut(
+        &mut self,
+        ty: GlobalStateType,
+    ) -> Option<&mut LargeOrdMap<GlobalOrd, DataState>> {
+        self.global.get_mut(&ty)
+    }
+
+    pub(crate) fn add_operation(
+        &mut self,
+        op: &impl Operation,
+        witness_anchor: Option<WitnessAnchor>,
+    ) {
         let opid = op.id();
 
+        if op.op_type() == OpType::StateExtension {
+            self.extensions
+                .push(opid)
+                .expect("contract state exceeded 2^32 items, which is unrealistic");
+        }
+
         for (ty, state) in op.globals() {
             let map = match self.global.get_mut(ty) {
                 Some(map) => map,
@@ -408,6 +623,21 @@ impl ContractHistory {
         }
          */
 
+        if let Some(witness_anchor) = witness_anchor {
+            self.witnesses
+                .insert(witness_anchor.witness_id, witness_anchor.witness_ord)
+                .expect("contract state exceeded 2^32 items, which is unrealistic");
+            let spender = SpendingOp {
+                opid,
+                witness_anchor,
+            };
+            for input in &op.inputs() {
+                self.spent
+                    .insert(input.prev_out, spender)
+                    .expect("contract state exceeded 2^32 items, which is unrealistic");
+            }
+        }
+
         let witness_id = witness_anchor.map(|wa| wa.witness_id);
         match op.assignments() {
             AssignmentsRef::Genesis(assignments) => {
@@ -468,6 +698,303 @@ impl ContractHistory {
     }
 }
 
+/// View of the global state of a single type in the consensus order: items
+/// defined by genesis go first, followed by items defined by the operations
+/// ordered by their witness positions (see [`GlobalOrd`]).
+///
+/// Accessing items by their depth requires walking from the latest item; for
+/// repeated accesses the view may cache positions of the items (see
+/// [`GlobalContractState::cached`]).
+#[derive(Clone, Debug)]
+pub struct GlobalContractState<'state> {
+    ty: GlobalStateType,
+    items: &'state LargeOrdMap<GlobalOrd, DataState>,
+    cache: Option<OnceCell<GlobalPositions<'state>>>,
+}
+
+type GlobalPositions<'state> = Result<Vec<(GlobalOrd, &'state DataState)>, GlobalStateError>;
+
+impl PartialEq for GlobalContractState<'_> {
+    fn eq(&self, other: &Self) -> bool { self.ty == other.ty && self.items == other.items }
+}
+
+impl Eq for GlobalContractState<'_> {}
+
+impl<'state> GlobalContractState<'state> {
+    /// Enables caching of the item positions, making access to the items by
+    /// their depth ([`GlobalContractState::try_nth`]) take constant time
+    /// after the first access, which walks all the items once.
+    pub fn cached(mut self) -> Self {
+        self.cache = Some(OnceCell::new());
+        self
+    }
+
+    pub fn is_cached(&self) -> bool { self.cache.is_some() }
+
+    pub fn state_type(&self) -> GlobalStateType { self.ty }
+
+    pub fn len(&self) -> usize { self.items.len() }
+
+    pub fn is_empty(&self) -> bool { self.items.is_empty() }
+
+    /// Iterates over all items in the consensus order.
+    pub fn iter(&self) -> GlobalStateIter<'state> {
+        GlobalStateIter {
+            items: self.items,
+            range: self.items.range(..),
+            front: None,
+            back: None,
+        }
+    }
+
+    /// Replays all items chronologically, starting from the ones defined by
+    /// genesis, and checking that the items follow the consensus order.
+    ///
+    /// Iteration yields an error and stops at the first item stored out of
+    /// the consensus order, which may happen only if the underlying storage
+    /// is corrupted.
+    pub fn iter_from_genesis(
+        &self,
+    ) -> impl Iterator<Item = Result<(GlobalOrd, &'state DataState), GlobalStateError>> {
+        let mut iter = self.iter();
+        let mut failed = false;
+        iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            let item = iter.try_next()?;
+            failed = item.is_err();
+            Some(item)
+        })
+    }
+
+    /// Iterates over items defined by the operations with the given witness
+    /// anchor or later ones, in the consensus order.
+    ///
+    /// Unlike walking the items one by one, seeking takes logarithmic time.
+    pub fn seek(&self, witness_anchor: WitnessAnchor) -> GlobalStateIter<'state> {
+        let mut iter = self.iter();
+        iter.seek(witness_anchor);
+        iter
+    }
+
+    /// Returns item with the given ordering key, in logarithmic time.
+    pub fn get(&self, ord: GlobalOrd) -> Option<&'state DataState> { self.items.get(&ord) }
+
+    /// Iterates over items starting from the one with the given ordering key
+    /// (or the next one, if there is no such item), in the consensus order.
+    pub fn seek_ord(&self, ord: GlobalOrd) -> GlobalStateIter<'state> {
+        let mut iter = self.iter();
+        iter.seek_ord(ord);
+        iter
+    }
+
+    /// Returns the latest item, if any.
+    pub fn last(&self) -> Option<(GlobalOrd, &'state DataState)> { self.iter().next_back() }
+
+    /// Returns item at the given depth from the latest item (the latest item
+    /// has depth zero), or `None` if there are not enough items.
+    ///
+    /// # Errors
+    ///
+    /// If the items are not in the consensus order, which may happen only if
+    /// the underlying storage is corrupted.
+    pub fn try_nth(
+        &self,
+        depth: usize,
+    ) -> Result<Option<(GlobalOrd, &'state DataState)>, GlobalStateError> {
+        if let Some(cache) = &self.cache {
+            let positions = cache
+                .get_or_init(|| self.iter_from_genesis().collect())
+                .as_ref()
+                .map_err(GlobalStateError::clone)?;
+            return Ok(positions.iter().rev().nth(depth).copied());
+        }
+        let mut iter = self.iter();
+        for _ in 0..depth {
+            if iter.try_next_back().transpose()?.is_none() {
+                return Ok(None);
+            }
+        }
+        iter.try_next_back().transpose()
+    }
+
+    /// Returns up to `n` latest items, in the consensus order (i.e. the
+    /// latest item goes last). If there are less than `n` items, all of them
+    /// are returned.
+    pub fn take_last(&self, n: usize) -> impl Iterator<Item = (GlobalOrd, &'state DataState)> {
+        self.iter().skip(self.len().saturating_sub(n))
+    }
+
+    /// Returns items defined by the operations with witnesses at the given
+    /// position or later, in the consensus order.
+    ///
+    /// Items defined by genesis and extensions without witness positions
+    /// precede all mined witnesses and are never returned; off-chain
+    /// witnesses follow all the mined ones.
+    pub fn take_since(
+        &self,
+        witness_ord: WitnessOrd,
+    ) -> impl Iterator<Item = (GlobalOrd, &'state DataState)> {
+        // Items with anchors at or after the position form a suffix of the consensus
+        // order
+        self.iter().skip_while(move |(ord, _)| {
+            ord.witness_anchor
+                .map_or(true, |anchor| anchor.witness_ord < witness_ord)
+        })
+    }
+
+    /// Returns items defined by the operations with witness positions within
+    /// the given range, in the consensus order.
+    ///
+    /// Items defined by genesis and extensions without witness positions are
+    /// never returned.
+    pub fn range_by_ord(
+        &self,
+        range: impl RangeBounds<WitnessOrd>,
+    ) -> impl Iterator<Item = (GlobalOrd, &'state DataState)> {
+        // Since positions are ordered, the matching items form a contiguous part of the
+        // consensus order
+        let before = |bound: Bound<&WitnessOrd>, witness_ord: WitnessOrd| match bound {
+            Bound::Included(start) => witness_ord < *start,
+            Bound::Excluded(start) => witness_ord <= *start,
+            Bound::Unbounded => false,
+        };
+        let after = |bound: Bound<&WitnessOrd>, witness_ord: WitnessOrd| match bound {
+            Bound::Included(end) => witness_ord > *end,
+            Bound::Excluded(end) => witness_ord >= *end,
+            Bound::Unbounded => false,
+        };
+        let start = range.start_bound().cloned();
+        self.iter()
+            .skip_while(move |(ord, _)| {
+                ord.witness_anchor
+                    .map_or(true, |anchor| before(start.as_ref(), anchor.witness_ord))
+            })
+            .take_while(move |(ord, _)| {
+                ord.witness_anchor
+                    .is_some_and(|anchor| !after(range.end_bound(), anchor.witness_ord))
+            })
+    }
+
+    /// Returns items defined by the operations with witnesses mined at the
+    /// block heights within the given range, in the consensus order.
+    ///
+    /// Items defined by genesis, extensions and operations with off-chain
+    /// witnesses are never returned. Since witnesses are ordered by their
+    /// timestamps, the heights of the returned items may be not monotonic.
+    pub fn range_by_height(
+        &self,
+        range: impl RangeBounds<u32>,
+    ) -> impl Iterator<Item = (GlobalOrd, &'state DataState)> {
+        self.iter().filter(move |(ord, _)| {
+            matches!(
+                ord.witness_anchor.map(|anchor| anchor.witness_ord),
+                Some(WitnessOrd::OnChain(pos)) if range.contains(&pos.height().get())
+            )
+        })
+    }
+}
+
+/// Iterator over the global state of a single type in the consensus order,
+/// supporting seeking to a witness anchor.
+#[derive(Clone, Debug)]
+pub struct GlobalStateIter<'state> {
+    items: &'state LargeOrdMap<GlobalOrd, DataState>,
+    range: btree_map::Range<'state, GlobalOrd, DataState>,
+    front: Option<GlobalOrd>,
+    back: Option<GlobalOrd>,
+}
+
+/// Errors accessing global state.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum GlobalStateError {
+    /// global state item {0:?} is stored before item {1:?} while not preceding
+    /// it in the consensus order; the state storage is corrupted.
+    Unordered(Box<GlobalOrd>, Box<GlobalOrd>),
+}
+
+impl<'state> GlobalStateIter<'state> {
+    /// Moves the iterator to the first item defined by an operation with the
+    /// given witness anchor or a later one, in logarithmic time.
+    ///
+    /// The iterator is positioned independently of the items it has already
+    /// returned, and iterates till the last item.
+    pub fn seek(&mut self, witness_anchor: WitnessAnchor) {
+        self.seek_ord(GlobalOrd::with_anchor(witness_anchor, 0));
+    }
+
+    /// Moves the iterator to the item with the given ordering key, or the
+    /// next one if there is no such item, in logarithmic time.
+    ///
+    /// The iterator is positioned independently of the items it has already
+    /// returned, and iterates till the last item.
+    pub fn seek_ord(&mut self, ord: GlobalOrd) {
+        self.range = self.items.range(ord..);
+        self.front = None;
+        self.back = None;
+    }
+
+    /// Returns the next item walking forward from the oldest one, checking
+    /// that the items follow the consensus order.
+    pub fn try_next(&mut self) -> Option<Result<(GlobalOrd, &'state DataState), GlobalStateError>> {
+        let (ord, state) = self.next()?;
+        match self.front.replace(ord) {
+            Some(earlier) if earlier >= ord => {
+                Some(Err(GlobalStateError::Unordered(Box::new(earlier), Box::new(ord))))
+            }
+            _ => Some(Ok((ord, state))),
+        }
+    }
+
+    /// Returns the previous item walking back from the latest one, checking
+    /// that the items follow the consensus order.
+    pub fn try_next_back(
+        &mut self,
+    ) -> Option<Result<(GlobalOrd, &'state DataState), GlobalStateError>> {
+        let (ord, state) = self.next_back()?;
+        match self.back.replace(ord) {
+            Some(later) if later <= ord => {
+                Some(Err(GlobalStateError::Unordered(Box::new(ord), Box::new(later))))
+            }
+            _ => Some(Ok((ord, state))),
+        }
+    }
+
+    /// Returns up to `n` previous items walking back from the latest one in a
+    /// single call, checking that the items follow the consensus order. The
+    /// items are returned starting from the latest one; fewer than `n` items
+    /// are returned once the iterator is exhausted.
+    pub fn prev_n(
+        &mut self,
+        n: u32,
+    ) -> Result<Vec<(GlobalOrd, &'state DataState)>, GlobalStateError> {
+        let mut chunk = Vec::with_capacity(n.min(self.items.len() as u32) as usize);
+        for _ in 0..n {
+            match self.try_next_back() {
+                Some(item) => chunk.push(item?),
+                None => break,
+            }
+        }
+        Ok(chunk)
+    }
+}
+
+impl<'state> Iterator for GlobalStateIter<'state> {
+    type Item = (GlobalOrd, &'state DataState);
+
+    fn next(&mut self) -> Option<Self::Item> { self.range.next().map(|(ord, state)| (*ord, state)) }
+
+    fn size_hint(&self) -> (usize, Option<usize>) { self.range.size_hint() }
+}
+
+impl<'state> DoubleEndedIterator for GlobalStateIter<'state> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.range.next_back().map(|(ord, state)| (*ord, state))
+    }
+}
+
 /// Contract state provides API to read consensus-valid data from the
 /// [`ContractHistory`].
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -508,4 +1035,187 @@ impl ContractState {
         let iter = state.values().take(schema.max_items as usize);
         SmallVec::try_from_iter(iter).expect("same size as previous confined collection")
     }
+
+    /// Returns global state of the type with the given name, or `None` if
+    /// the contract schema doesn't name such type.
+    pub fn global_by_name(&self, name: &str) -> Option<SmallVec<&DataState>> {
+        let ty = self.schema.global_type_by_name(name)?;
+        // SAFETY: the type is a part of the schema, since the name lookup checks it
+        Some(unsafe { self.global_unchecked(ty) })
+    }
+
+    /// Returns fungible allocations of the assignment type with the given
+    /// name, or `None` if the contract schema doesn't name such type.
+    pub fn fungibles_by_name<'a>(
+        &'a self,
+        name: &str,
+    ) -> Option<impl Iterator<Item = &'a OutputAssignment<RevealedValue>> + 'a> {
+        let ty = self.schema.assignment_type_by_name(name)?;
+        Some(self.fungibles().iter().filter(move |a| a.opout.ty == ty))
+    }
+
+    /// Computes total amount of the fungible state of the given type assigned
+    /// to the outpoints. Outpoints listed multiple times are counted once.
+    ///
+    /// Returns `None` if the total amount overflows.
+    pub fn balance(
+        &self,
+        ty: AssignmentType,
+        outpoints: impl IntoIterator<Item = impl Into<XOutpoint>>,
+    ) -> Option<FungibleState> {
+        let outpoints = outpoints
+            .into_iter()
+            .map(Into::into)
+            .collect::<BTreeSet<_>>();
+        self.fungibles()
+            .iter()
+            .filter(|a| a.opout.ty == ty && outpoints.contains(&a.seal.to_outpoint()))
+            .try_fold(0u64, |sum, a| sum.checked_add(a.state.value.as_u64()))
+            .map(FungibleState::Bits64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::{Confined, SmallBlob};
+    use amplify::Wrapper;
+    use bp::Txid;
+
+    use super::*;
+    use crate::{ExtensionType, WitnessPos, XGenesisSeal, XGraphSeal};
+
+    fn data(values: &[u8]) -> Vec<DataState> {
+        values
+            .iter()
+            .map(|val| DataState::from(SmallBlob::from_iter_unsafe([*val])))
+            .collect()
+    }
+
+    fn values<'a>(iter: impl Iterator<Item = (GlobalOrd, &'a DataState)>) -> Vec<DataState> {
+        iter.map(|(_, state)| state.clone()).collect()
+    }
+
+    #[test]
+    fn pending_witnesses() {
+        let ty = AssignmentType::with(1);
+        let genesis = Genesis::strict_dumb();
+        let mut history = ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis);
+
+        let mined = WitnessOrd::OnChain(WitnessPos::new(100, 1700000000).unwrap());
+        let witness_ords = [(1u8, mined), (2, WitnessOrd::OffChain)];
+        for (no, witness_ord) in witness_ords {
+            // Extension without global state
+            let mut extension = Extension::strict_dumb();
+            extension.extension_type = ExtensionType::with(no as u16);
+            extension.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+                ty,
+                TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                    XGenesisSeal::strict_dumb(),
+                    VoidState::default(),
+                )])),
+            )]));
+            let witness_id = XChain::Bitcoin(Txid::from([no; 32]));
+            history.add_extension(&extension, WitnessAnchor {
+                witness_ord,
+                witness_id,
+            });
+        }
+
+        let mined_id = XChain::Bitcoin(Txid::from([1; 32]));
+        let pending_id = XChain::Bitcoin(Txid::from([2; 32]));
+        assert_eq!(history.witness_status(mined_id), Some(mined));
+        assert_eq!(history.witness_status(pending_id), Some(WitnessOrd::OffChain));
+        assert_eq!(history.pending_witnesses(), bset! { pending_id });
+    }
+
+    #[test]
+    fn extension_allocations() {
+        let ty = AssignmentType::with(1);
+        let genesis = Genesis::strict_dumb();
+        let mut history = ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis);
+        let witness_anchor = WitnessAnchor {
+            witness_ord: WitnessOrd::OffChain,
+            witness_id: XChain::Bitcoin(Txid::from([1; 32])),
+        };
+
+        let mut extension = Extension::strict_dumb();
+        extension.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            ty,
+            TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                XGenesisSeal::strict_dumb(),
+                VoidState::default(),
+            )])),
+        )]));
+        let mut transition = Transition::strict_dumb();
+        transition.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            ty,
+            TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                XGraphSeal::strict_dumb(),
+                VoidState::default(),
+            )])),
+        )]));
+        history.add_extension(&extension, witness_anchor);
+        history.add_transition(&transition, witness_anchor);
+
+        assert!(history.is_extension(extension.id()));
+        assert!(!history.is_extension(transition.id()));
+        assert!(!history.is_extension(genesis.id()));
+        assert_eq!(history.state_leaves().len(), 2);
+        let allocations = history.extension_allocations();
+        assert_eq!(allocations.len(), 1);
+        assert!(matches!(&allocations[0], StateLeaf::Rights(a) if a.opout.op == extension.id()));
+    }
+
+    #[test]
+    fn global_state_view() {
+        let ty = GlobalStateType::with(1);
+        let mut genesis = Genesis::strict_dumb();
+        for val in [0, 1] {
+            genesis
+                .globals
+                .add_state(ty, DataState::from(SmallBlob::from_iter_unsafe([val])))
+                .unwrap();
+        }
+        let mut history = ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis);
+
+        let early = WitnessOrd::OnChain(WitnessPos::new(100, 1700000000).unwrap());
+        let late = WitnessOrd::OnChain(WitnessPos::new(200, 1700060000).unwrap());
+        // Extensions are added out of their consensus order
+        for (no, witness_ord) in [(30u8, WitnessOrd::OffChain), (10, early), (20, late)] {
+            let mut extension = Extension::strict_dumb();
+            extension.extension_type = ExtensionType::with(no as u16);
+            extension
+                .globals
+                .add_state(ty, DataState::from(SmallBlob::from_iter_unsafe([no])))
+                .unwrap();
+            history.add_extension(&extension, WitnessAnchor {
+                witness_ord,
+                witness_id: XChain::Bitcoin(Txid::from([no; 32])),
+            });
+        }
+
+        assert!(history.global_state(GlobalStateType::with(2)).is_none());
+        let global = history.global_state(ty).unwrap();
+        assert_eq!(global.state_type(), ty);
+        assert_eq!(global.len(), 5);
+        assert_eq!(values(global.iter()), data(&[0, 1, 10, 20, 30]));
+        assert_eq!(global.last().map(|(_, state)| state.clone()), data(&[30]).pop());
+
+        assert_eq!(values(global.take_last(2)), data(&[20, 30]));
+        assert_eq!(values(global.take_last(10)), data(&[0, 1, 10, 20, 30]));
+        assert_eq!(values(global.take_since(late)), data(&[20, 30]));
+        assert_eq!(values(global.take_since(WitnessOrd::OffChain)), data(&[30]));
+
+        for global in [global.clone(), global.cached()] {
+            let nth = |depth| {
+                global
+                    .try_nth(depth)
+                    .unwrap()
+                    .map(|(_, state)| state.clone())
+            };
+            assert_eq!(nth(0), data(&[30]).pop());
+            assert_eq!(nth(4), data(&[0]).pop());
+            assert_eq!(nth(5), None);
+        }
+    }
 }
@@ -0,0 +1,194 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dependencies between contracts.
+//!
+//! A contract may depend on other contracts (for instance, a DEX contract
+//! depends on the contracts of the traded assets), which is declared in its
+//! genesis with [`schema::MetaType::DEPENDENCIES`] metadata. Contracts must
+//! be validated after all the contracts they depend on, and the
+//! [`DependencyGraph`] provides the order in which multi-contract
+//! consignments have to be validated.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use amplify::confinement::Confined;
+use amplify::ByteArray;
+
+use crate::{schema, ContractId, Genesis, MetaValue, Operation};
+
+impl Genesis {
+    /// Returns ids of the contracts this contract depends on, as declared by
+    /// the [`schema::MetaType::DEPENDENCIES`] metadata. If the metadata is
+    /// absent, the contract has no dependencies.
+    ///
+    /// Returns `None` if the metadata value is malformed.
+    pub fn dependencies(&self) -> Option<BTreeSet<ContractId>> {
+        let Some(meta) = self.metadata.get(&schema::MetaType::DEPENDENCIES) else {
+            return Some(empty!());
+        };
+        let data = meta.as_slice();
+        if data.is_empty() || data.len() % 32 != 0 {
+            return None;
+        }
+        let deps = data
+            .chunks(32)
+            .map(|chunk| ContractId::copy_from_slice(chunk).expect("fixed size"))
+            .collect::<BTreeSet<_>>();
+        if deps.len() * 32 != data.len() {
+            return None;
+        }
+        Some(deps)
+    }
+}
+
+/// Constructs value for the [`schema::MetaType::DEPENDENCIES`] genesis
+/// metadata.
+///
+/// Returns `None` if there are no dependencies or too many of them to fit
+/// into a metadata value.
+pub fn dependencies_meta(deps: &BTreeSet<ContractId>) -> Option<MetaValue> {
+    if deps.is_empty() {
+        return None;
+    }
+    let data = deps
+        .iter()
+        .flat_map(|id| id.to_byte_array())
+        .collect::<Vec<_>>();
+    Confined::try_from(data).ok().map(MetaValue::from)
+}
+
+/// Errors resolving contract dependencies.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum DependencyError {
+    /// genesis of contract {0} declares malformed dependencies.
+    Malformed(ContractId),
+
+    /// contract {0} depends on contract {1}, which is not known.
+    Missing(ContractId, ContractId),
+
+    /// contract {0} depends on itself through the chain of dependencies.
+    Cycle(ContractId),
+}
+
+/// Graph of dependencies between contracts.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct DependencyGraph(BTreeMap<ContractId, BTreeSet<ContractId>>);
+
+impl DependencyGraph {
+    pub fn new() -> Self { Self::default() }
+
+    /// Adds contract to the graph using dependencies declared by its genesis.
+    pub fn add_genesis(&mut self, genesis: &Genesis) -> Result<(), DependencyError> {
+        let contract_id = genesis.contract_id();
+        let deps = genesis
+            .dependencies()
+            .ok_or(DependencyError::Malformed(contract_id))?;
+        self.0.insert(contract_id, deps);
+        Ok(())
+    }
+
+    /// Adds contract with an explicit set of dependencies.
+    pub fn add(&mut self, contract_id: ContractId, deps: BTreeSet<ContractId>) {
+        self.0.insert(contract_id, deps);
+    }
+
+    /// Returns set of the direct dependencies of a contract.
+    pub fn dependencies(&self, contract_id: ContractId) -> Option<&BTreeSet<ContractId>> {
+        self.0.get(&contract_id)
+    }
+
+    /// Returns set of the contracts directly depending on a contract.
+    pub fn dependents(&self, contract_id: ContractId) -> BTreeSet<ContractId> {
+        self.0
+            .iter()
+            .filter(|(_, deps)| deps.contains(&contract_id))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    pub fn contracts(&self) -> impl Iterator<Item = ContractId> + '_ { self.0.keys().copied() }
+
+    /// Returns order in which contracts must be validated, such that each
+    /// contract comes after all of its dependencies.
+    pub fn resolution_order(&self) -> Result<Vec<ContractId>, DependencyError> {
+        for (id, deps) in &self.0 {
+            if let Some(dep) = deps.iter().find(|dep| !self.0.contains_key(*dep)) {
+                return Err(DependencyError::Missing(*id, *dep));
+            }
+        }
+        let mut order = Vec::with_capacity(self.0.len());
+        let mut resolved = BTreeSet::new();
+        while resolved.len() < self.0.len() {
+            let ready = self
+                .0
+                .iter()
+                .filter(|(id, deps)| !resolved.contains(*id) && deps.is_subset(&resolved))
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>();
+            if ready.is_empty() {
+                let id = self
+                    .0
+                    .keys()
+                    .find(|id| !resolved.contains(*id))
+                    .expect("unresolved contracts present");
+                return Err(DependencyError::Cycle(*id));
+            }
+            resolved.extend(ready.iter().copied());
+            order.extend(ready);
+        }
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolution_order() {
+        let a = ContractId::from([1u8; 32]);
+        let b = ContractId::from([2u8; 32]);
+        let dex = ContractId::from([3u8; 32]);
+
+        let mut graph = DependencyGraph::new();
+        graph.add(dex, bset! { a, b });
+        graph.add(a, empty!());
+        assert_eq!(graph.resolution_order(), Err(DependencyError::Missing(dex, b)));
+
+        graph.add(b, empty!());
+        assert_eq!(graph.resolution_order(), Ok(vec![a, b, dex]));
+        assert_eq!(graph.dependents(a), bset! { dex });
+
+        graph.add(a, bset! { dex });
+        assert!(matches!(graph.resolution_order(), Err(DependencyError::Cycle(_))));
+    }
+
+    #[test]
+    fn dependencies_meta_value() {
+        let deps = bset! { ContractId::from([1u8; 32]), ContractId::from([2u8; 32]) };
+        let meta = dependencies_meta(&deps).unwrap();
+        assert_eq!(meta.len(), 64);
+        assert_eq!(dependencies_meta(&empty!()), None);
+    }
+}
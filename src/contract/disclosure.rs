@@ -0,0 +1,179 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Construction of contract operations at different disclosure levels.
+//!
+//! Operation ids commit to the concealed form of all assignments, thus
+//! concealing any part of the state never changes the operation id and the
+//! operations can be put into consignments at the level of disclosure
+//! required by the recipient: full reveal for payments, concealed amounts for
+//! audits, and concealed seals for third parties like escrow agents.
+
+use std::collections::BTreeSet;
+
+use amplify::confinement::{Confined, SmallVec};
+
+use crate::{
+    Assign, Assignments, ExposedSeal, ExposedState, Extension, Genesis, OpId, Operation, Opout,
+    Transition, TransitionBundle, TypedAssigns,
+};
+
+/// Level of disclosure of the owned state in operations included into a
+/// consignment.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum DisclosureLevel {
+    /// All known state and seals are revealed.
+    #[default]
+    #[display("full")]
+    Full,
+
+    /// Fungible state (amounts) is concealed; seals and other state types
+    /// are kept revealed.
+    #[display("amounts-concealed")]
+    AmountsConcealed,
+
+    /// Seals are concealed, except the ones which are required to be
+    /// revealed for the consignment validation; state is kept revealed.
+    #[display("seals-concealed")]
+    SealsConcealed,
+}
+
+impl DisclosureLevel {
+    pub fn conceals_amounts(self) -> bool { self == DisclosureLevel::AmountsConcealed }
+
+    pub fn conceals_seals(self) -> bool { self == DisclosureLevel::SealsConcealed }
+}
+
+fn disclose_list<State: ExposedState, Seal: ExposedSeal>(
+    list: &SmallVec<Assign<State, Seal>>,
+    conceal_state: bool,
+    conceal_seal: impl Fn(u16) -> bool,
+) -> SmallVec<Assign<State, Seal>> {
+    let iter = list.iter().enumerate().map(|(no, assign)| {
+        let mut assign = assign.clone();
+        if conceal_state {
+            assign = assign.conceal_state();
+        }
+        if conceal_seal(no as u16) {
+            assign = assign.conceal_seal();
+        }
+        assign
+    });
+    Confined::try_from_iter(iter).expect("same size")
+}
+
+impl<Seal: ExposedSeal> TypedAssigns<Seal> {
+    /// Returns copy of the assignments at the given disclosure level.
+    ///
+    /// Seals of the assignments for which `keep_seal` returns `true` are never
+    /// concealed.
+    pub fn disclosed(&self, level: DisclosureLevel, keep_seal: impl Fn(u16) -> bool) -> Self {
+        let conceal_seal = |no| level.conceals_seals() && !keep_seal(no);
+        match self {
+            TypedAssigns::Declarative(list) => {
+                TypedAssigns::Declarative(disclose_list(list, false, conceal_seal))
+            }
+            TypedAssigns::Fungible(list) => {
+                TypedAssigns::Fungible(disclose_list(list, level.conceals_amounts(), conceal_seal))
+            }
+            TypedAssigns::Structured(list) => {
+                TypedAssigns::Structured(disclose_list(list, false, conceal_seal))
+            }
+            TypedAssigns::Attachment(list) => {
+                TypedAssigns::Attachment(disclose_list(list, false, conceal_seal))
+            }
+        }
+    }
+}
+
+impl<Seal: ExposedSeal> Assignments<Seal> {
+    /// Returns copy of the operation assignments at the given disclosure
+    /// level.
+    ///
+    /// Seals of the outputs listed in `revealed` are never concealed. These
+    /// must include all outputs spent by other operations in the consignment,
+    /// since the validation requires their seals to be known.
+    pub fn disclosed(
+        &self,
+        opid: OpId,
+        level: DisclosureLevel,
+        revealed: &BTreeSet<Opout>,
+    ) -> Self {
+        let iter = self.iter().map(|(ty, assigns)| {
+            let assigns =
+                assigns.disclosed(level, |no| revealed.contains(&Opout::new(opid, *ty, no)));
+            (*ty, assigns)
+        });
+        Assignments::from(Confined::try_from_iter(iter).expect("same size"))
+    }
+}
+
+impl Genesis {
+    /// Returns copy of the genesis at the given disclosure level; see
+    /// [`Assignments::disclosed`] for the details.
+    pub fn disclosed(&self, level: DisclosureLevel, revealed: &BTreeSet<Opout>) -> Self {
+        let mut genesis = self.clone();
+        genesis.assignments = self.assignments.disclosed(self.id(), level, revealed);
+        genesis
+    }
+}
+
+impl Extension {
+    /// Returns copy of the state extension at the given disclosure level; see
+    /// [`Assignments::disclosed`] for the details.
+    pub fn disclosed(&self, level: DisclosureLevel, revealed: &BTreeSet<Opout>) -> Self {
+        let mut extension = self.clone();
+        extension.assignments = self.assignments.disclosed(self.id(), level, revealed);
+        extension
+    }
+}
+
+impl Transition {
+    /// Returns copy of the state transition at the given disclosure level; see
+    /// [`Assignments::disclosed`] for the details.
+    pub fn disclosed(&self, level: DisclosureLevel, revealed: &BTreeSet<Opout>) -> Self {
+        let mut transition = self.clone();
+        transition.assignments = self.assignments.disclosed(self.id(), level, revealed);
+        transition
+    }
+}
+
+impl TransitionBundle {
+    /// Returns copy of the bundle with all known transitions put at the given
+    /// disclosure level; see [`Assignments::disclosed`] for the details.
+    pub fn disclosed(&self, level: DisclosureLevel, revealed: &BTreeSet<Opout>) -> Self {
+        let known_transitions = self
+            .known_transitions
+            .iter()
+            .map(|(opid, transition)| (*opid, transition.disclosed(level, revealed)));
+        TransitionBundle {
+            close_method: self.close_method,
+            input_map: self.input_map.clone(),
+            known_transitions: Confined::try_from_iter(known_transitions).expect("same size"),
+        }
+    }
+}
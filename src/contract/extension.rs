@@ -0,0 +1,413 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Construction of state extensions checked against the contract schema.
+//!
+//! [`ExtensionBuilder`] accepts only the metadata, global state, owned state
+//! and valencies which the schema allows for the state extension type, and
+//! checks the number of state items when the extension is completed. The
+//! checks match the ones performed by the validator, thus the completed
+//! extension fails the validation only due to the state and logic of the
+//! contract (e.g. the validity of the redeemed valencies or the VM scripts).
+
+use amplify::confinement;
+use strict_encoding::StrictDumb;
+
+use crate::schema::{
+    AssignmentType, ExtensionSchema, ExtensionType, GlobalStateType, MetaType, OccurrencesMismatch,
+    ValencyType,
+};
+use crate::{
+    Assign, ContractId, DataState, Extension, GenesisSeal, MetaValue, MetadataError, OpId,
+    RevealedAttach, RevealedData, RevealedValue, Schema, StateType, TypedAssigns, VoidState,
+    XChain,
+};
+
+/// Errors constructing state extension.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum ExtensionBuilderError {
+    /// schema doesn't define state extension type {0}.
+    UnknownExtension(ExtensionType),
+
+    /// schema doesn't define state extension named '{0}'.
+    UnknownName(String),
+
+    /// state extension can't redeem valency {0}.
+    ValencyNotRedeemable(ValencyType),
+
+    /// valency {0} is already redeemed by the state extension.
+    ValencyRedeemed(ValencyType),
+
+    /// state extension can't define valency {0}.
+    ValencyNotAllowed(ValencyType),
+
+    /// metadata type {0} is not allowed in the state extension.
+    MetaNotAllowed(MetaType),
+
+    /// global state type {0} is not allowed in the state extension.
+    GlobalNotAllowed(GlobalStateType),
+
+    /// assignment type {0} is not allowed in the state extension.
+    AssignmentNotAllowed(AssignmentType),
+
+    /// assignment type {0} has {1} state, while {2} state was provided.
+    StateTypeMismatch(AssignmentType, StateType, StateType),
+
+    /// state extension doesn't redeem any valency.
+    NothingRedeemed,
+
+    /// state extension doesn't have value of the required metadata type {0}.
+    MetaMissing(MetaType),
+
+    /// invalid number of global state values of type {0}: {1}.
+    GlobalOccurrences(GlobalStateType, OccurrencesMismatch),
+
+    /// invalid number of assignments of type {0}: {1}.
+    AssignmentOccurrences(AssignmentType, OccurrencesMismatch),
+
+    /// {0}
+    #[from]
+    Metadata(MetadataError),
+
+    /// too many state items in the state extension.
+    #[from(confinement::Error)]
+    TooManyItems,
+}
+
+/// Builder of state extensions checking the added data against the extension
+/// schema.
+#[derive(Clone, Debug)]
+pub struct ExtensionBuilder<'schema> {
+    schema: &'schema Schema,
+    extension_schema: &'schema ExtensionSchema,
+    extension: Extension,
+}
+
+impl<'schema> ExtensionBuilder<'schema> {
+    /// Starts construction of the state extension of the given type.
+    pub fn new(
+        schema: &'schema Schema,
+        contract_id: ContractId,
+        extension_type: ExtensionType,
+    ) -> Result<Self, ExtensionBuilderError> {
+        let extension_schema = schema
+            .extensions
+            .get(&extension_type)
+            .ok_or(ExtensionBuilderError::UnknownExtension(extension_type))?;
+        let mut extension = Extension::strict_dumb();
+        extension.contract_id = contract_id;
+        extension.extension_type = extension_type;
+        Ok(ExtensionBuilder {
+            schema,
+            extension_schema,
+            extension,
+        })
+    }
+
+    /// Starts construction of the state extension with the type named `name`
+    /// in the schema.
+    pub fn named(
+        schema: &'schema Schema,
+        contract_id: ContractId,
+        name: &str,
+    ) -> Result<Self, ExtensionBuilderError> {
+        let extension_type = schema
+            .extension_type_by_name(name)
+            .ok_or_else(|| ExtensionBuilderError::UnknownName(name.to_owned()))?;
+        Self::new(schema, contract_id, extension_type)
+    }
+
+    /// Redeems valency defined by the operation `opid`.
+    pub fn redeem(
+        mut self,
+        valency: ValencyType,
+        opid: OpId,
+    ) -> Result<Self, ExtensionBuilderError> {
+        if !self.extension_schema.redeems.contains(&valency) {
+            return Err(ExtensionBuilderError::ValencyNotRedeemable(valency));
+        }
+        if self.extension.redeemed.contains_key(&valency) {
+            return Err(ExtensionBuilderError::ValencyRedeemed(valency));
+        }
+        self.extension.redeemed.insert(valency, opid)?;
+        Ok(self)
+    }
+
+    /// Defines valency which can be redeemed by other state extensions.
+    pub fn add_valency(mut self, valency: ValencyType) -> Result<Self, ExtensionBuilderError> {
+        if !self.extension_schema.valencies.contains(&valency) {
+            return Err(ExtensionBuilderError::ValencyNotAllowed(valency));
+        }
+        self.extension.valencies.push(valency)?;
+        Ok(self)
+    }
+
+    pub fn add_metadata(
+        mut self,
+        ty: MetaType,
+        value: MetaValue,
+    ) -> Result<Self, ExtensionBuilderError> {
+        if !self.extension_schema.metadata.contains(&ty) {
+            return Err(ExtensionBuilderError::MetaNotAllowed(ty));
+        }
+        self.extension.metadata.add_value(ty, value)?;
+        Ok(self)
+    }
+
+    pub fn add_global_state(
+        mut self,
+        ty: GlobalStateType,
+        state: DataState,
+    ) -> Result<Self, ExtensionBuilderError> {
+        if !self.extension_schema.globals.contains_key(&ty) {
+            return Err(ExtensionBuilderError::GlobalNotAllowed(ty));
+        }
+        self.extension.globals.add_state(ty, state)?;
+        Ok(self)
+    }
+
+    pub fn add_rights(
+        mut self,
+        ty: AssignmentType,
+        seal: XChain<GenesisSeal>,
+    ) -> Result<Self, ExtensionBuilderError> {
+        self.assignments_mut(ty, StateType::Void)?
+            .as_declarative_mut()
+            .expect("state type is checked")
+            .push(Assign::revealed(seal, VoidState::default()))?;
+        Ok(self)
+    }
+
+    pub fn add_fungible(
+        mut self,
+        ty: AssignmentType,
+        seal: XChain<GenesisSeal>,
+        value: RevealedValue,
+    ) -> Result<Self, ExtensionBuilderError> {
+        self.assignments_mut(ty, StateType::Fungible)?
+            .as_fungible_mut()
+            .expect("state type is checked")
+            .push(Assign::revealed(seal, value))?;
+        Ok(self)
+    }
+
+    pub fn add_data(
+        mut self,
+        ty: AssignmentType,
+        seal: XChain<GenesisSeal>,
+        data: RevealedData,
+    ) -> Result<Self, ExtensionBuilderError> {
+        self.assignments_mut(ty, StateType::Structured)?
+            .as_structured_mut()
+            .expect("state type is checked")
+            .push(Assign::revealed(seal, data))?;
+        Ok(self)
+    }
+
+    pub fn add_attachment(
+        mut self,
+        ty: AssignmentType,
+        seal: XChain<GenesisSeal>,
+        attach: RevealedAttach,
+    ) -> Result<Self, ExtensionBuilderError> {
+        self.assignments_mut(ty, StateType::Attachment)?
+            .as_attachment_mut()
+            .expect("state type is checked")
+            .push(Assign::revealed(seal, attach))?;
+        Ok(self)
+    }
+
+    fn assignments_mut(
+        &mut self,
+        ty: AssignmentType,
+        state_type: StateType,
+    ) -> Result<&mut TypedAssigns<GenesisSeal>, ExtensionBuilderError> {
+        let expected = match self.schema.owned_types.get(&ty) {
+            Some(state_schema) if self.extension_schema.assignments.contains_key(&ty) => {
+                state_schema.state_type()
+            }
+            _ => return Err(ExtensionBuilderError::AssignmentNotAllowed(ty)),
+        };
+        if expected != state_type {
+            return Err(ExtensionBuilderError::StateTypeMismatch(ty, expected, state_type));
+        }
+        if !self.extension.assignments.contains_key(&ty) {
+            let assigns = match state_type {
+                StateType::Void => TypedAssigns::Declarative(empty!()),
+                StateType::Fungible => TypedAssigns::Fungible(empty!()),
+                StateType::Structured => TypedAssigns::Structured(empty!()),
+                StateType::Attachment => TypedAssigns::Attachment(empty!()),
+            };
+            self.extension.assignments.insert(ty, assigns)?;
+        }
+        Ok(self
+            .extension
+            .assignments
+            .get_mut(&ty)
+            .expect("just inserted"))
+    }
+
+    /// Completes the state extension, checking that it redeems valencies and
+    /// that it has all the metadata and the number of global and owned state
+    /// items required by the schema.
+    pub fn complete(self) -> Result<Extension, ExtensionBuilderError> {
+        let extension_schema = self.extension_schema;
+        let extension = self.extension;
+        if extension.redeemed.is_empty() {
+            return Err(ExtensionBuilderError::NothingRedeemed);
+        }
+        if let Some(ty) = extension_schema
+            .metadata
+            .iter()
+            .find(|ty| !extension.metadata.contains_key(*ty))
+        {
+            return Err(ExtensionBuilderError::MetaMissing(*ty));
+        }
+        for (ty, occurrences) in &extension_schema.globals {
+            let count = extension
+                .globals
+                .get(ty)
+                .map(|v| v.len_u16())
+                .unwrap_or_default();
+            occurrences
+                .check(count)
+                .map_err(|err| ExtensionBuilderError::GlobalOccurrences(*ty, err))?;
+        }
+        for (ty, occurrences) in &extension_schema.assignments {
+            let count = extension
+                .assignments
+                .get(ty)
+                .map(TypedAssigns::len_u16)
+                .unwrap_or_default();
+            occurrences
+                .check(count)
+                .map_err(|err| ExtensionBuilderError::AssignmentOccurrences(*ty, err))?;
+        }
+        Ok(extension)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+
+    use super::*;
+    use crate::schema::{Occurrences, OwnedStateSchema};
+    use crate::AssetTag;
+
+    fn schema() -> Schema {
+        let mut schema = Schema::strict_dumb();
+        schema.owned_types = Confined::try_from(bmap! {
+            AssignmentType::with(1) => OwnedStateSchema::Declarative,
+            AssignmentType::with(2) => OwnedStateSchema::Fungible(default!()),
+        })
+        .unwrap();
+        let extension_schema = ExtensionSchema {
+            redeems: Confined::from_iter_unsafe([ValencyType::with(1)]),
+            valencies: Confined::from_iter_unsafe([ValencyType::with(2)]),
+            assignments: Confined::from_iter_unsafe([(AssignmentType::with(2), Occurrences::Once)]),
+            ..default!()
+        };
+        schema.extensions =
+            Confined::from_iter_unsafe([(ExtensionType::with(1), extension_schema)]);
+        schema
+            .names
+            .name_extension("issue", ExtensionType::with(1))
+            .unwrap();
+        schema
+    }
+
+    #[test]
+    fn build() {
+        let schema = schema();
+        let contract_id = ContractId::from([1; 32]);
+        let opid = OpId::from([2; 32]);
+        let seal = XChain::<GenesisSeal>::strict_dumb();
+        let value = RevealedValue::new_random_blinding(100, AssetTag::strict_dumb());
+
+        assert_eq!(
+            ExtensionBuilder::new(&schema, contract_id, ExtensionType::with(2)).unwrap_err(),
+            ExtensionBuilderError::UnknownExtension(ExtensionType::with(2))
+        );
+        let builder = ExtensionBuilder::named(&schema, contract_id, "issue").unwrap();
+        assert_eq!(
+            builder
+                .clone()
+                .redeem(ValencyType::with(2), opid)
+                .unwrap_err(),
+            ExtensionBuilderError::ValencyNotRedeemable(ValencyType::with(2))
+        );
+        assert_eq!(
+            builder
+                .clone()
+                .add_rights(AssignmentType::with(1), seal)
+                .unwrap_err(),
+            ExtensionBuilderError::AssignmentNotAllowed(AssignmentType::with(1))
+        );
+        assert_eq!(
+            builder
+                .clone()
+                .add_rights(AssignmentType::with(2), seal)
+                .unwrap_err(),
+            ExtensionBuilderError::StateTypeMismatch(
+                AssignmentType::with(2),
+                StateType::Fungible,
+                StateType::Void
+            )
+        );
+        assert_eq!(builder.clone().complete().unwrap_err(), ExtensionBuilderError::NothingRedeemed);
+
+        let builder = builder
+            .redeem(ValencyType::with(1), opid)
+            .unwrap()
+            .add_valency(ValencyType::with(2))
+            .unwrap();
+        assert_eq!(
+            builder.clone().complete().unwrap_err(),
+            ExtensionBuilderError::AssignmentOccurrences(
+                AssignmentType::with(2),
+                OccurrencesMismatch {
+                    min: 1,
+                    max: 1,
+                    found: 0
+                }
+            )
+        );
+
+        let extension = builder
+            .add_fungible(AssignmentType::with(2), seal, value)
+            .unwrap()
+            .complete()
+            .unwrap();
+        assert_eq!(extension.contract_id, contract_id);
+        assert_eq!(extension.redeemed.get(&ValencyType::with(1)), Some(&opid));
+        assert_eq!(extension.valencies.len(), 1);
+        assert_eq!(
+            extension
+                .assignments
+                .get(&AssignmentType::with(2))
+                .map(TypedAssigns::len_u16),
+            Some(1)
+        );
+    }
+}
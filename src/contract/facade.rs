@@ -0,0 +1,380 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Object-safe facade over the contract state.
+//!
+//! [`ContractState`] APIs return `impl Iterator` values, which makes them
+//! unusable behind trait objects, FFI boundaries and plugin interfaces.
+//! [`DynContractState`] provides the same read access with boxed iterators,
+//! such that hosts can hold `Box<dyn DynContractState>` values.
+//!
+//! Custom storage backends wrapping a [`ContractHistory`] get both the facade
+//! and [`crate::ContractStateMut`] implemented with the
+//! [`impl_contract_state`] macro, or with `#[derive(DynContractState)]` when
+//! the `derive` feature is enabled:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! use std::collections::{BTreeMap, BTreeSet};
+//!
+//! use rgbcore::{ContractHistory, ContractStateMut, DynContractState, OpId, Opout};
+//!
+//! #[derive(DynContractState)]
+//! struct Store {
+//!     #[contract_state(history)]
+//!     history: ContractHistory,
+//!     #[contract_state(ops)]
+//!     known: BTreeSet<OpId>,
+//!     #[contract_state(spends)]
+//!     spent_by: BTreeMap<Opout, OpId>,
+//! }
+//!
+//! fn is_known(store: &Store, opid: OpId) -> bool { store.is_known_op(opid) }
+//! # }
+//! ```
+
+use std::collections::BTreeSet;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::{
+    AssignmentType, ContractHistory, ContractId, ContractState, DataState, FungibleState,
+    GlobalOrd, GlobalStateType, OutputAssignment, RevealedAttach, RevealedData, RevealedValue,
+    Schema, SchemaId, VoidState, WitnessOrd, XOutpoint, XWitnessId,
+};
+
+/// Boxed iterator returned by [`DynContractState`] methods.
+pub type DynIter<'state, T> = Box<dyn Iterator<Item = T> + 'state>;
+
+/// Object-safe read access to the contract state.
+pub trait DynContractState {
+    fn schema_id(&self) -> SchemaId;
+
+    fn contract_id(&self) -> ContractId;
+
+    /// Iterates over global state of the given type in the consensus order.
+    fn global(&self, ty: GlobalStateType) -> DynIter<'_, (GlobalOrd, &DataState)>;
+
+    fn rights(&self) -> DynIter<'_, &OutputAssignment<VoidState>>;
+
+    fn fungibles(&self) -> DynIter<'_, &OutputAssignment<RevealedValue>>;
+
+    fn data(&self) -> DynIter<'_, &OutputAssignment<RevealedData>>;
+
+    fn attach(&self) -> DynIter<'_, &OutputAssignment<RevealedAttach>>;
+
+    /// Lists outpoints holding state of the given assignment type.
+    fn outpoints(&self, ty: AssignmentType) -> DynIter<'_, XOutpoint>;
+
+    /// Lists attachment allocations matching the filter, optionally limited
+    /// to the ones assigned to a specific outpoint.
+    fn attach_filtered<'state>(
+        &'state self,
+        outpoint: Option<XOutpoint>,
+        filter: &'state dyn Fn(&RevealedAttach) -> bool,
+    ) -> DynIter<'state, &'state OutputAssignment<RevealedAttach>>;
+
+    /// Returns fungible allocations of the assignment type with the given
+    /// name, or `None` if the schema is not the contract schema or the named
+    /// type is unknown.
+    fn fungibles_by_name(
+        &self,
+        schema: &Schema,
+        name: &str,
+    ) -> Option<DynIter<'_, &OutputAssignment<RevealedValue>>>;
+
+    /// Computes total amount of the fungible state of the given type assigned
+    /// to the outpoints, returning `None` on overflow.
+    fn balance(&self, ty: AssignmentType, outpoints: &[XOutpoint]) -> Option<FungibleState>;
+
+    fn witness_ids(&self) -> BTreeSet<XWitnessId>;
+
+    fn witness_status(&self, witness_id: XWitnessId) -> Option<WitnessOrd>;
+}
+
+// Calls are made through the explicit paths, since inherent methods of
+// `ContractHistory` would be shadowed by the trait methods otherwise.
+impl DynContractState for ContractHistory {
+    fn schema_id(&self) -> SchemaId { ContractHistory::schema_id(self) }
+
+    fn contract_id(&self) -> ContractId { ContractHistory::contract_id(self) }
+
+    fn global(&self, ty: GlobalStateType) -> DynIter<'_, (GlobalOrd, &DataState)> {
+        match self.global_state(ty) {
+            Some(state) => Box::new(state.iter()),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+
+    fn rights(&self) -> DynIter<'_, &OutputAssignment<VoidState>> {
+        Box::new(ContractHistory::rights(self).iter())
+    }
+
+    fn fungibles(&self) -> DynIter<'_, &OutputAssignment<RevealedValue>> {
+        Box::new(ContractHistory::fungibles(self).iter())
+    }
+
+    fn data(&self) -> DynIter<'_, &OutputAssignment<RevealedData>> {
+        Box::new(ContractHistory::data(self).iter())
+    }
+
+    fn attach(&self) -> DynIter<'_, &OutputAssignment<RevealedAttach>> {
+        Box::new(ContractHistory::attach(self).iter())
+    }
+
+    fn outpoints(&self, ty: AssignmentType) -> DynIter<'_, XOutpoint> {
+        Box::new(ContractHistory::outpoints(self, ty))
+    }
+
+    fn attach_filtered<'state>(
+        &'state self,
+        outpoint: Option<XOutpoint>,
+        filter: &'state dyn Fn(&RevealedAttach) -> bool,
+    ) -> DynIter<'state, &'state OutputAssignment<RevealedAttach>> {
+        Box::new(ContractHistory::attach_filtered(self, outpoint, filter))
+    }
+
+    fn fungibles_by_name(
+        &self,
+        schema: &Schema,
+        name: &str,
+    ) -> Option<DynIter<'_, &OutputAssignment<RevealedValue>>> {
+        if schema.schema_id() != ContractHistory::schema_id(self) {
+            return None;
+        }
+        let ty = schema.assignment_type_by_name(name)?;
+        let iter = ContractHistory::fungibles(self)
+            .iter()
+            .filter(move |a| a.opout.ty == ty);
+        Some(Box::new(iter))
+    }
+
+    fn balance(&self, ty: AssignmentType, outpoints: &[XOutpoint]) -> Option<FungibleState> {
+        ContractHistory::fungibles(self)
+            .iter()
+            .filter(|a| a.opout.ty == ty && outpoints.contains(&a.seal.to_outpoint()))
+            .try_fold(0u64, |sum, a| sum.checked_add(a.state.value.as_u64()))
+            .map(FungibleState::Bits64)
+    }
+
+    fn witness_ids(&self) -> BTreeSet<XWitnessId> { ContractHistory::witness_ids(self) }
+
+    fn witness_status(&self, witness_id: XWitnessId) -> Option<WitnessOrd> {
+        ContractHistory::witness_status(self, witness_id)
+    }
+}
+
+/// Implements [`DynContractState`] for a custom storage type by forwarding
+/// all calls to its [`ContractHistory`] field. If the type also has fields
+/// keeping the set of known operation ids (like `BTreeSet<OpId>` or
+/// `HashSet<OpId>`) and the map of the spent outputs to the spending
+/// operation ids (like `BTreeMap<Opout, OpId>` or `HashMap<Opout, OpId>`),
+/// [`ContractStateMut`] is implemented as well. With the `derive` feature
+/// the same is achieved by `#[derive(DynContractState)]` with the fields
+/// marked by `#[contract_state(history | ops | spends)]` attributes.
+///
+/// ```
+/// use std::collections::{HashMap, HashSet};
+///
+/// use rgbcore::{impl_contract_state, ContractHistory, ContractStateMut, OpId, Opout};
+///
+/// struct Store {
+///     history: ContractHistory,
+///     known: HashSet<OpId>,
+///     spent_by: HashMap<Opout, OpId>,
+/// }
+///
+/// impl_contract_state!(Store {
+///     history,
+///     ops: known,
+///     spends: spent_by
+/// });
+///
+/// fn is_known(store: &Store, opid: OpId) -> bool { store.is_known_op(opid) }
+/// ```
+///
+/// [`ContractStateMut`]: crate::ContractStateMut
+#[macro_export]
+macro_rules! impl_contract_state {
+    ($ty:ty { history $(, $($rest:tt)*)? }) => {
+        $crate::impl_contract_state!($ty { history: history $(, $($rest)*)? });
+    };
+    ($ty:ty { history: $history:ident $(, ops: $ops:ident, spends: $spends:ident)? $(,)? }) => {
+        impl $crate::DynContractState for $ty {
+            fn schema_id(&self) -> $crate::SchemaId {
+                $crate::DynContractState::schema_id(&self.$history)
+            }
+
+            fn contract_id(&self) -> $crate::ContractId {
+                $crate::DynContractState::contract_id(&self.$history)
+            }
+
+            fn global(
+                &self,
+                ty: $crate::GlobalStateType,
+            ) -> $crate::DynIter<'_, ($crate::GlobalOrd, &$crate::DataState)> {
+                $crate::DynContractState::global(&self.$history, ty)
+            }
+
+            fn rights(&self) -> $crate::DynIter<'_, &$crate::OutputAssignment<$crate::VoidState>> {
+                $crate::DynContractState::rights(&self.$history)
+            }
+
+            fn fungibles(
+                &self,
+            ) -> $crate::DynIter<'_, &$crate::OutputAssignment<$crate::RevealedValue>> {
+                $crate::DynContractState::fungibles(&self.$history)
+            }
+
+            fn data(&self) -> $crate::DynIter<'_, &$crate::OutputAssignment<$crate::RevealedData>> {
+                $crate::DynContractState::data(&self.$history)
+            }
+
+            fn attach(
+                &self,
+            ) -> $crate::DynIter<'_, &$crate::OutputAssignment<$crate::RevealedAttach>> {
+                $crate::DynContractState::attach(&self.$history)
+            }
+
+            fn outpoints(
+                &self,
+                ty: $crate::AssignmentType,
+            ) -> $crate::DynIter<'_, $crate::XOutpoint> {
+                $crate::DynContractState::outpoints(&self.$history, ty)
+            }
+
+            fn attach_filtered<'state>(
+                &'state self,
+                outpoint: Option<$crate::XOutpoint>,
+                filter: &'state dyn Fn(&$crate::RevealedAttach) -> bool,
+            ) -> $crate::DynIter<'state, &'state $crate::OutputAssignment<$crate::RevealedAttach>>
+            {
+                $crate::DynContractState::attach_filtered(&self.$history, outpoint, filter)
+            }
+
+            fn fungibles_by_name(
+                &self,
+                schema: &$crate::Schema,
+                name: &str,
+            ) -> Option<$crate::DynIter<'_, &$crate::OutputAssignment<$crate::RevealedValue>>> {
+                $crate::DynContractState::fungibles_by_name(&self.$history, schema, name)
+            }
+
+            fn balance(
+                &self,
+                ty: $crate::AssignmentType,
+                outpoints: &[$crate::XOutpoint],
+            ) -> Option<$crate::FungibleState> {
+                $crate::DynContractState::balance(&self.$history, ty, outpoints)
+            }
+
+            fn witness_ids(&self) -> ::std::collections::BTreeSet<$crate::XWitnessId> {
+                $crate::DynContractState::witness_ids(&self.$history)
+            }
+
+            fn witness_status(
+                &self,
+                witness_id: $crate::XWitnessId,
+            ) -> Option<$crate::WitnessOrd> {
+                $crate::DynContractState::witness_status(&self.$history, witness_id)
+            }
+        }
+
+        $(
+        impl $crate::ContractStateMut for $ty {
+            fn is_known_op(&self, opid: $crate::OpId) -> bool { self.$ops.contains(&opid) }
+
+            fn spending_op(&self, opout: $crate::Opout) -> Option<$crate::OpId> {
+                self.$spends.get(&opout).copied()
+            }
+
+            fn add_op(
+                &mut self,
+                op: $crate::OpRef,
+                witness_anchor: Option<$crate::WitnessAnchor>,
+            ) {
+                let opid = $crate::Operation::id(&op);
+                for input in &$crate::Operation::inputs(&op) {
+                    self.$spends.insert(input.prev_out, opid);
+                }
+                self.$history.add_op(op, witness_anchor);
+                self.$ops.insert(opid);
+            }
+        }
+        )?
+    };
+}
+
+impl_contract_state!(ContractState { history });
+
+impl From<ContractState> for Box<dyn DynContractState> {
+    fn from(state: ContractState) -> Self { Box::new(state) }
+}
+
+impl From<ContractState> for Rc<dyn DynContractState> {
+    fn from(state: ContractState) -> Self { Rc::new(state) }
+}
+
+impl From<ContractState> for Arc<dyn DynContractState + Send + Sync> {
+    fn from(state: ContractState) -> Self { Arc::new(state) }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap, HashSet};
+
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{
+        ApplyOutcome, ContractStateMut, Extension, Genesis, OpId, OpRef, Operation, Opout,
+    };
+
+    struct Store {
+        history: ContractHistory,
+        known: HashSet<OpId>,
+        spent_by: HashMap<Opout, OpId>,
+    }
+
+    impl_contract_state!(Store {
+        history,
+        ops: known,
+        spends: spent_by
+    });
+
+    #[test]
+    fn custom_store() {
+        let genesis = Genesis::strict_dumb();
+        let mut store = Store {
+            history: ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis),
+            known: HashSet::new(),
+            spent_by: HashMap::new(),
+        };
+        let extension = Extension::strict_dumb();
+        assert_eq!(store.apply(OpRef::from(&extension), None), ApplyOutcome::Added);
+        assert_eq!(store.apply(OpRef::from(&extension), None), ApplyOutcome::AlreadyKnown);
+
+        let state: Box<dyn DynContractState> = Box::new(store);
+        assert_eq!(state.contract_id(), genesis.contract_id());
+        assert_eq!(state.schema_id(), genesis.schema_id);
+    }
+}
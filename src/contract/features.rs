@@ -0,0 +1,110 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Operation-level feature bits.
+//!
+//! An operation may opt into new semantics by setting feature bits in its
+//! [`schema::MetaType::OP_FEATURES`] metadata. Since metadata are committed
+//! into the operation id, the features can't be altered without changing the
+//! id. Schema permits the features for an operation type by declaring the
+//! metadata type in the operation schema, and the validator rejects operations
+//! using features it doesn't support. This allows to introduce new
+//! per-operation semantics gradually, without breaking the validation of the
+//! existing contracts.
+
+use amplify::confinement::Confined;
+
+use crate::{schema, MetaValue, Metadata};
+
+/// Set of features used by an operation, encoded as a 16-bit mask.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Display)]
+#[display("{0:#06x}")]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", transparent)
+)]
+pub struct OpFeatures(u16);
+
+impl OpFeatures {
+    /// No features are used.
+    pub const NONE: Self = OpFeatures(0);
+
+    /// Features supported by this version of the library.
+    pub const KNOWN: Self = Self::NONE;
+
+    pub const fn from_bits(bits: u16) -> Self { Self(bits) }
+
+    pub const fn bits(self) -> u16 { self.0 }
+
+    pub const fn is_empty(self) -> bool { self.0 == 0 }
+
+    pub const fn contains(self, other: Self) -> bool { self.0 & other.0 == other.0 }
+
+    pub const fn union(self, other: Self) -> Self { Self(self.0 | other.0) }
+
+    /// Returns features from this set which are not in the `supported` set.
+    pub const fn unsupported(self, supported: Self) -> Self { Self(self.0 & !supported.0) }
+
+    /// Converts features into a value for the
+    /// [`schema::MetaType::OP_FEATURES`] metadata.
+    pub fn to_meta(self) -> MetaValue {
+        MetaValue::from(Confined::try_from(self.0.to_le_bytes().to_vec()).expect("fixed size"))
+    }
+
+    /// Parses features from the [`schema::MetaType::OP_FEATURES`] metadata
+    /// value. Returns `None` if the value is malformed.
+    pub fn from_meta(meta: &MetaValue) -> Option<Self> {
+        let [lo, hi] = meta.as_slice() else {
+            return None;
+        };
+        Some(Self(u16::from_le_bytes([*lo, *hi])))
+    }
+}
+
+impl Metadata {
+    /// Returns features used by the operation, as declared by the operation
+    /// metadata; no features if the metadata are absent.
+    ///
+    /// Returns `None` if the metadata value is malformed.
+    pub fn op_features(&self) -> Option<OpFeatures> {
+        match self.get(&schema::MetaType::OP_FEATURES) {
+            None => Some(OpFeatures::NONE),
+            Some(meta) => OpFeatures::from_meta(meta),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unsupported() {
+        let features = OpFeatures::from_bits(0b1011);
+        let supported = OpFeatures::from_bits(0b0011);
+        assert_eq!(features.unsupported(supported), OpFeatures::from_bits(0b1000));
+        assert!(features.contains(supported));
+        assert!(features.unsupported(features).is_empty());
+        assert_eq!(OpFeatures::from_meta(&features.to_meta()), Some(features));
+    }
+}
@@ -0,0 +1,471 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compact filters for matching transactions relevant to contract seals.
+//!
+//! Filters are Golomb-coded sets following the construction of BIP-158. Items
+//! of a filter are byte strings: outpoints of the contract seals (which are
+//! matched against the outpoints spent by transactions), output scripts of the
+//! seals and script patterns of the outputs carrying seal-closing commitments
+//! (`OP_RETURN` outputs for opret and P2TR outputs for tapret). Light clients
+//! build a filter for the contract with [`ContractHistory::filter_items`] and
+//! match it against the items extracted from block transactions with
+//! [`tx_filter_items`], or vice versa.
+
+use std::collections::BTreeSet;
+
+use amplify::ByteArray;
+use bp::dbc::Method;
+use bp::{Outpoint, ScriptPubkey, Tx};
+
+use crate::{ContractHistory, KnownState, OutputAssignment};
+
+/// Golomb-Rice coding parameter, matching the one of BIP-158 basic filters.
+pub const FILTER_P: u8 = 19;
+/// Inverse false positive rate, matching the one of BIP-158 basic filters.
+pub const FILTER_M: u64 = 784_931;
+/// Filter item representing presence of an opret commitment output
+/// (`OP_RETURN OP_PUSHBYTES_32`) in a transaction.
+pub const OPRET_FILTER_ITEM: [u8; 2] = [0x6a, 0x20];
+/// Filter item representing presence of an output which may carry a tapret
+/// commitment (P2TR output, `OP_1 OP_PUSHBYTES_32`) in a transaction.
+pub const TAPRET_FILTER_ITEM: [u8; 2] = [0x51, 0x20];
+
+/// Returns filter item for the script pattern of the outputs which may carry
+/// commitments with the given close method.
+pub fn pattern_filter_item(method: Method) -> [u8; 2] {
+    match method {
+        Method::OpretFirst => OPRET_FILTER_ITEM,
+        Method::TapretFirst => TAPRET_FILTER_ITEM,
+    }
+}
+
+/// Converts transaction outpoint into a filter item.
+pub fn outpoint_filter_item(outpoint: Outpoint) -> Vec<u8> {
+    let mut item = outpoint.txid.to_byte_array().to_vec();
+    item.extend(outpoint.vout.to_u32().to_le_bytes());
+    item
+}
+
+/// Extracts filter items from a transaction: the outpoints it spends, its
+/// output scripts and the script patterns of its outputs which may carry
+/// commitments. Opret outputs are represented by the pattern only, since their
+/// scripts contain commitments and are never known in advance.
+pub fn tx_filter_items(tx: &Tx) -> BTreeSet<Vec<u8>> {
+    let mut items = tx
+        .inputs
+        .iter()
+        .map(|input| outpoint_filter_item(input.prev_output))
+        .collect::<BTreeSet<_>>();
+    for output in &tx.outputs {
+        let script = &output.script_pubkey[..];
+        if script.len() == 34 && script.starts_with(&OPRET_FILTER_ITEM) {
+            items.insert(OPRET_FILTER_ITEM.to_vec());
+            continue;
+        }
+        if script.len() == 34 && script.starts_with(&TAPRET_FILTER_ITEM) {
+            items.insert(TAPRET_FILTER_ITEM.to_vec());
+        }
+        items.insert(script.to_vec());
+    }
+    items
+}
+
+impl ContractHistory {
+    /// Returns filter items for all seals of the known contract allocations:
+    /// seal outpoints and script patterns of the outputs which may carry
+    /// commitments closing the seals.
+    ///
+    /// Pattern items match any transaction having an output of the pattern,
+    /// so they are useful only for filters with few other items; use
+    /// [`ContractHistory::filter_items_with`] to add output scripts of the
+    /// seals instead.
+    pub fn filter_items(&self) -> BTreeSet<Vec<u8>> { self.filter_items_with(|_| None) }
+
+    /// Returns filter items for all seals of the known contract allocations,
+    /// like [`ContractHistory::filter_items`], adding output scripts of the
+    /// seals which are known to the `script` lookup (for instance, the wallet
+    /// scripts).
+    pub fn filter_items_with(
+        &self,
+        script: impl Fn(Outpoint) -> Option<ScriptPubkey>,
+    ) -> BTreeSet<Vec<u8>> {
+        fn seal<State: KnownState>(a: &OutputAssignment<State>) -> (Outpoint, Method) {
+            (*a.seal.to_outpoint().as_reduced_unsafe(), a.seal.as_reduced_unsafe().method)
+        }
+        let mut items = BTreeSet::new();
+        for (outpoint, method) in self
+            .rights()
+            .iter()
+            .map(seal)
+            .chain(self.fungibles().iter().map(seal))
+            .chain(self.data().iter().map(seal))
+            .chain(self.attach().iter().map(seal))
+        {
+            items.insert(outpoint_filter_item(outpoint));
+            items.insert(pattern_filter_item(method).to_vec());
+            if let Some(script) = script(outpoint) {
+                items.insert(script[..].to_vec());
+            }
+        }
+        items
+    }
+}
+
+/// Golomb-coded set filter over byte string items.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SealFilter {
+    key: [u8; 16],
+    p: u8,
+    m: u64,
+    n: u32,
+    data: Vec<u8>,
+}
+
+impl SealFilter {
+    /// Constructs filter with the BIP-158 parameters.
+    pub fn with(key: [u8; 16], items: impl IntoIterator<Item = impl AsRef<[u8]>>) -> Self {
+        Self::with_params(key, FILTER_P, FILTER_M, items)
+    }
+
+    /// Constructs filter with custom Golomb-Rice parameter `p` and inverse
+    /// false positive rate `m`.
+    pub fn with_params(
+        key: [u8; 16],
+        p: u8,
+        m: u64,
+        items: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    ) -> Self {
+        let items = items
+            .into_iter()
+            .map(|item| item.as_ref().to_vec())
+            .collect::<BTreeSet<_>>();
+        let n = items.len() as u32;
+        let range = n as u64 * m;
+        let mut values = items
+            .iter()
+            .map(|item| hash_to_range(key, item, range))
+            .collect::<Vec<_>>();
+        values.sort_unstable();
+
+        let mut writer = BitWriter::default();
+        let mut last = 0u64;
+        for value in values {
+            let delta = value - last;
+            last = value;
+            writer.write_unary(delta >> p);
+            writer.write_bits(delta, p);
+        }
+        SealFilter {
+            key,
+            p,
+            m,
+            n,
+            data: writer.finish(),
+        }
+    }
+
+    pub fn key(&self) -> [u8; 16] { self.key }
+
+    /// Number of items in the filter.
+    pub fn len(&self) -> u32 { self.n }
+
+    pub fn is_empty(&self) -> bool { self.n == 0 }
+
+    /// Golomb-coded filter data.
+    pub fn as_slice(&self) -> &[u8] { &self.data }
+
+    /// Checks whether the filter (probably) contains the item.
+    pub fn contains(&self, item: impl AsRef<[u8]>) -> bool { self.match_any([item]) }
+
+    /// Checks whether the filter (probably) contains any of the items.
+    pub fn match_any(&self, items: impl IntoIterator<Item = impl AsRef<[u8]>>) -> bool {
+        if self.n == 0 {
+            return false;
+        }
+        let range = self.n as u64 * self.m;
+        let mut query = items
+            .into_iter()
+            .map(|item| hash_to_range(self.key, item.as_ref(), range))
+            .collect::<Vec<_>>();
+        query.sort_unstable();
+        query.dedup();
+        let mut query = query.into_iter().peekable();
+
+        let mut reader = BitReader::new(&self.data);
+        let mut value = 0u64;
+        for _ in 0..self.n {
+            let Some(delta) = reader
+                .read_unary()
+                .and_then(|q| Some((q << self.p) | reader.read_bits(self.p)?))
+            else {
+                return false;
+            };
+            value += delta;
+            while let Some(next) = query.peek() {
+                match (*next).cmp(&value) {
+                    std::cmp::Ordering::Less => {
+                        query.next();
+                    }
+                    std::cmp::Ordering::Equal => return true,
+                    std::cmp::Ordering::Greater => break,
+                }
+            }
+            if query.peek().is_none() {
+                return false;
+            }
+        }
+        false
+    }
+}
+
+fn hash_to_range(key: [u8; 16], item: &[u8], range: u64) -> u64 {
+    let k0 = u64::from_le_bytes(key[..8].try_into().expect("fixed size"));
+    let k1 = u64::from_le_bytes(key[8..].try_into().expect("fixed size"));
+    ((siphash24(k0, k1, item) as u128 * range as u128) >> 64) as u64
+}
+
+/// SipHash-2-4, as required by BIP-158.
+fn siphash24(k0: u64, k1: u64, data: &[u8]) -> u64 {
+    fn round(v: &mut [u64; 4]) {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+    fn compress(v: &mut [u64; 4], m: u64) {
+        v[3] ^= m;
+        round(v);
+        round(v);
+        v[0] ^= m;
+    }
+
+    let mut v = [
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573,
+    ];
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        compress(&mut v, u64::from_le_bytes(chunk.try_into().expect("fixed size")));
+    }
+    let mut last = [0u8; 8];
+    let tail = chunks.remainder();
+    last[..tail.len()].copy_from_slice(tail);
+    last[7] = data.len() as u8;
+    compress(&mut v, u64::from_le_bytes(last));
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+#[derive(Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    byte: u8,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn write_bit(&mut self, bit: bool) {
+        self.byte = (self.byte << 1) | bit as u8;
+        self.bits += 1;
+        if self.bits == 8 {
+            self.data.push(self.byte);
+            self.byte = 0;
+            self.bits = 0;
+        }
+    }
+
+    fn write_unary(&mut self, value: u64) {
+        for _ in 0..value {
+            self.write_bit(true);
+        }
+        self.write_bit(false);
+    }
+
+    fn write_bits(&mut self, value: u64, count: u8) {
+        for i in (0..count).rev() {
+            self.write_bit((value >> i) & 1 == 1);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.data.push(self.byte << (8 - self.bits));
+        }
+        self.data
+    }
+}
+
+struct BitReader<'data> {
+    data: &'data [u8],
+    pos: usize,
+}
+
+impl<'data> BitReader<'data> {
+    fn new(data: &'data [u8]) -> Self { BitReader { data, pos: 0 } }
+
+    fn read_bit(&mut self) -> Option<bool> {
+        let byte = self.data.get(self.pos / 8)?;
+        let bit = (byte >> (7 - self.pos % 8)) & 1 == 1;
+        self.pos += 1;
+        Some(bit)
+    }
+
+    fn read_unary(&mut self) -> Option<u64> {
+        let mut value = 0;
+        while self.read_bit()? {
+            value += 1;
+        }
+        Some(value)
+    }
+
+    fn read_bits(&mut self, count: u8) -> Option<u64> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.read_bit()? as u64;
+        }
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+    use amplify::Wrapper;
+    use bp::{LockTime, Sats, SeqNo, TxIn, TxOut, TxVer};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::validation::fixtures::TestChain;
+    use crate::{Assign, AssignmentType, Assignments, Genesis, Operation, TypedAssigns, VoidState};
+
+    fn tx(spent: Outpoint, scripts: impl IntoIterator<Item = ScriptPubkey>) -> Tx {
+        let input = TxIn {
+            prev_output: spent,
+            sig_script: default!(),
+            sequence: SeqNo::from_consensus_u32(0xFFFF_FFFF),
+            witness: default!(),
+        };
+        let outputs = scripts.into_iter().map(|script_pubkey| TxOut {
+            value: Sats::from(1000u64),
+            script_pubkey,
+        });
+        Tx {
+            version: TxVer::V2,
+            inputs: Confined::from_iter_unsafe([input]),
+            outputs: Confined::try_from_iter(outputs).unwrap(),
+            lock_time: LockTime::ZERO,
+        }
+    }
+
+    #[test]
+    fn siphash() {
+        // Reference test vectors of SipHash-2-4
+        let (k0, k1) = (0x0706_0504_0302_0100, 0x0f0e_0d0c_0b0a_0908);
+        let data = (0u8..15).collect::<Vec<_>>();
+        assert_eq!(siphash24(k0, k1, &data[..0]), 0x726f_db47_dd0e_0e31);
+        assert_eq!(siphash24(k0, k1, &data[..8]), 0x93f5_f579_9a93_2462);
+        assert_eq!(siphash24(k0, k1, &data), 0xa129_ca61_49be_45e5);
+    }
+
+    #[test]
+    fn tx_items() {
+        let spent = Outpoint::new(strict_dumb!(), 1);
+        let opret = ScriptPubkey::op_return(&[0x11; 32]);
+        let mut p2tr = vec![0x51, 0x20];
+        p2tr.extend([0x22; 32]);
+        let p2tr = ScriptPubkey::from_unsafe(p2tr);
+        let p2wpkh =
+            ScriptPubkey::from_unsafe([0x00, 0x14].into_iter().chain([0x33; 20]).collect());
+        let items = tx_filter_items(&tx(spent, [opret.clone(), p2tr.clone(), p2wpkh.clone()]));
+        assert_eq!(items, bset! {
+            outpoint_filter_item(spent),
+            OPRET_FILTER_ITEM.to_vec(),
+            TAPRET_FILTER_ITEM.to_vec(),
+            p2tr[..].to_vec(),
+            p2wpkh[..].to_vec(),
+        });
+        assert!(!items.contains(&opret[..]));
+    }
+
+    #[test]
+    fn contract_items() {
+        let mut chain = TestChain::regtest();
+        let seal = chain.genesis_seal();
+        let outpoint = *seal.to_outpoint().as_reduced_unsafe();
+        let mut genesis = Genesis::strict_dumb();
+        genesis.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            AssignmentType::with(1),
+            TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                seal,
+                VoidState::default(),
+            )])),
+        )]));
+        let history = ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis);
+
+        let items = history.filter_items();
+        assert_eq!(items, bset! { outpoint_filter_item(outpoint), OPRET_FILTER_ITEM.to_vec() });
+        let script = ScriptPubkey::from_unsafe(vec![0x51]);
+        let items = history.filter_items_with(|op| (op == outpoint).then(|| script.clone()));
+        assert!(items.contains(&script[..]));
+
+        // Block containing a transaction spending the seal matches the contract
+        let key = [3u8; 16];
+        let block = SealFilter::with(key, tx_filter_items(&tx(outpoint, [])));
+        assert!(block.match_any(history.filter_items()));
+        let other = Outpoint::new(strict_dumb!(), 7);
+        let block = SealFilter::with(key, tx_filter_items(&tx(other, [])));
+        assert!(!block.contains(outpoint_filter_item(outpoint)));
+    }
+
+    #[test]
+    fn gcs_roundtrip() {
+        let key = [7u8; 16];
+        let items = (0u32..100)
+            .map(|i| i.to_le_bytes().to_vec())
+            .collect::<Vec<_>>();
+        let filter = SealFilter::with(key, &items);
+        assert_eq!(filter.len(), 100);
+        assert!(items.iter().all(|item| filter.contains(item)));
+        assert!(filter.match_any([b"absent".as_slice(), items[42].as_slice()]));
+        assert!(!filter.contains(b"absent"));
+    }
+
+    #[test]
+    fn empty_filter() {
+        let filter = SealFilter::with([0u8; 16], Vec::<Vec<u8>>::new());
+        assert!(filter.is_empty());
+        assert!(!filter.contains(b"item"));
+    }
+}
@@ -0,0 +1,310 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Finality assumptions of a contract.
+//!
+//! Contract genesis may declare the maximum reorg depth it assumes in its
+//! [`schema::MetaType::FINALITY_DEPTH`] metadata. State defined by witnesses
+//! which are not buried under more blocks than the declared depth is
+//! provisional: the validator reports such witnesses with
+//! [`crate::validation::Info::WitnessProvisional`] and [`ContractHistory`]
+//! allows to tell provisional state from the settled one, while
+//! [`ContractState::provisional_state`] applies the depth declared by the
+//! contract itself. Contracts which do not declare the depth treat all mined
+//! state as settled.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use amplify::confinement::Confined;
+
+use crate::{
+    schema, AssignmentWitness, ContractHistory, ContractState, DataState, Genesis, GlobalOrd,
+    GlobalStateType, Layer1, MetaValue, Operation, Opout, WitnessOrd, XWitnessId,
+};
+
+/// Maximum reorg depth assumed by a contract.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default, Display)]
+#[display("{0} blocks")]
+pub struct FinalityDepth(u32);
+
+impl FinalityDepth {
+    pub const fn new(depth: u32) -> Self { FinalityDepth(depth) }
+
+    pub const fn depth(self) -> u32 { self.0 }
+
+    /// Detects whether the witness at the given position is settled, i.e. is
+    /// mined and has more confirmations than the finality depth. Off-chain
+    /// witnesses are never final; if the height of the chain tip is not
+    /// known, only contracts which do not declare the depth treat mined
+    /// witnesses as final.
+    pub fn is_final(self, witness_ord: WitnessOrd, tip_height: Option<u32>) -> bool {
+        let WitnessOrd::OnChain(pos) = witness_ord else {
+            return false;
+        };
+        if self.0 == 0 {
+            return true;
+        }
+        let Some(tip_height) = tip_height else {
+            return false;
+        };
+        let confirmations = tip_height
+            .saturating_sub(pos.height().get())
+            .saturating_add(1);
+        confirmations > self.0
+    }
+}
+
+impl Genesis {
+    /// Returns finality depth declared by the
+    /// [`schema::MetaType::FINALITY_DEPTH`] metadata. If the metadata is
+    /// absent, the depth is zero.
+    ///
+    /// Returns `None` if the metadata value is malformed or declares zero
+    /// depth.
+    pub fn finality_depth(&self) -> Option<FinalityDepth> {
+        let Some(meta) = self.metadata.get(&schema::MetaType::FINALITY_DEPTH) else {
+            return Some(FinalityDepth::default());
+        };
+        let bytes = <[u8; 4]>::try_from(meta.as_slice()).ok()?;
+        match u32::from_le_bytes(bytes) {
+            0 => None,
+            depth => Some(FinalityDepth(depth)),
+        }
+    }
+}
+
+/// Constructs value for the [`schema::MetaType::FINALITY_DEPTH`] genesis
+/// metadata.
+///
+/// Returns `None` for the zero depth, which must not be declared.
+pub fn finality_depth_meta(depth: FinalityDepth) -> Option<MetaValue> {
+    if depth.0 == 0 {
+        return None;
+    }
+    let data = depth.0.to_le_bytes().to_vec();
+    Some(MetaValue::from(Confined::try_from(data).expect("4 bytes")))
+}
+
+impl ContractHistory {
+    /// Detects whether the global state item is settled under the finality
+    /// depth. Genesis state and state of extensions without witnesses is
+    /// always final.
+    pub fn is_global_final(
+        &self,
+        ord: &GlobalOrd,
+        depth: FinalityDepth,
+        tip_height: impl Fn(Layer1) -> Option<u32>,
+    ) -> bool {
+        match ord.witness_anchor {
+            None => true,
+            Some(anchor) => {
+                depth.is_final(anchor.witness_ord, tip_height(anchor.witness_id.layer1()))
+            }
+        }
+    }
+
+    /// Returns items of the global state which are provisional under the
+    /// finality depth, in the consensus order.
+    pub fn provisional_global(
+        &self,
+        ty: GlobalStateType,
+        depth: FinalityDepth,
+        tip_height: impl Fn(Layer1) -> Option<u32>,
+    ) -> Vec<(GlobalOrd, &DataState)> {
+        let Some(state) = self.global_state(ty) else {
+            return vec![];
+        };
+        state
+            .iter()
+            .filter(|(ord, _)| !self.is_global_final(ord, depth, &tip_height))
+            .collect()
+    }
+
+    /// Returns outputs of the owned state which are provisional under the
+    /// finality depth. Since the history doesn't keep positions of the
+    /// witnesses defining owned state, they are provided by the caller;
+    /// outputs with witnesses of unknown position are provisional.
+    pub fn provisional_outputs(
+        &self,
+        depth: FinalityDepth,
+        witness_ord: impl Fn(XWitnessId) -> Option<WitnessOrd>,
+        tip_height: impl Fn(Layer1) -> Option<u32>,
+    ) -> BTreeSet<Opout> {
+        let is_provisional = |witness: &AssignmentWitness| {
+            let AssignmentWitness::Present(witness_id) = *witness else {
+                return false;
+            };
+            !witness_ord(witness_id)
+                .is_some_and(|ord| depth.is_final(ord, tip_height(witness_id.layer1())))
+        };
+        let rights = self.rights().iter().filter(|a| is_provisional(&a.witness));
+        let rights = rights.map(|a| a.opout);
+        let fungibles = self
+            .fungibles()
+            .iter()
+            .filter(|a| is_provisional(&a.witness));
+        let fungibles = fungibles.map(|a| a.opout);
+        let data = self.data().iter().filter(|a| is_provisional(&a.witness));
+        let data = data.map(|a| a.opout);
+        let attach = self.attach().iter().filter(|a| is_provisional(&a.witness));
+        let attach = attach.map(|a| a.opout);
+        rights.chain(fungibles).chain(data).chain(attach).collect()
+    }
+}
+
+/// State of a contract which is provisional under the finality depth declared
+/// by the contract genesis.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ProvisionalState {
+    /// Provisional items of the global state, by the global state type.
+    pub global: BTreeMap<GlobalStateType, BTreeSet<GlobalOrd>>,
+    /// Provisional outputs of the owned state.
+    pub outputs: BTreeSet<Opout>,
+}
+
+impl ProvisionalState {
+    /// Detects whether all the contract state is settled.
+    pub fn is_empty(&self) -> bool { self.global.is_empty() && self.outputs.is_empty() }
+}
+
+impl ContractState {
+    /// Returns state of the contract which is provisional under the finality
+    /// depth declared by the contract genesis, the same way as the validator
+    /// reports it. Positions of the witnesses defining owned state are
+    /// provided by the caller (see [`ContractHistory::provisional_outputs`]).
+    ///
+    /// Returns `None` if the genesis doesn't belong to the contract or
+    /// declares malformed finality depth.
+    pub fn provisional_state(
+        &self,
+        genesis: &Genesis,
+        witness_ord: impl Fn(XWitnessId) -> Option<WitnessOrd>,
+        tip_height: impl Fn(Layer1) -> Option<u32>,
+    ) -> Option<ProvisionalState> {
+        if genesis.contract_id() != self.contract_id() {
+            return None;
+        }
+        // The depth is a consensus-reserved metadata type, which is ignored by the
+        // validator unless the schema opts into it
+        let depth = if self.schema.reserves_types() {
+            genesis.finality_depth()?
+        } else {
+            FinalityDepth::default()
+        };
+        let mut global = BTreeMap::new();
+        for ty in self.schema.global_types.keys() {
+            let ords = self
+                .provisional_global(*ty, depth, &tip_height)
+                .into_iter()
+                .map(|(ord, _)| ord)
+                .collect::<BTreeSet<_>>();
+            if !ords.is_empty() {
+                global.insert(*ty, ords);
+            }
+        }
+        let outputs = self.provisional_outputs(depth, witness_ord, &tip_height);
+        Some(ProvisionalState { global, outputs })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::Wrapper;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{
+        Assign, AssignmentType, Assignments, Schema, SchemaFlags, Transition, TypedAssigns,
+        VoidState, WitnessAnchor, WitnessPos, XGraphSeal,
+    };
+
+    #[test]
+    fn finality_window() {
+        let depth = FinalityDepth::new(6);
+        let mined = WitnessOrd::OnChain(WitnessPos::new(100, 1700000000).unwrap());
+        assert!(!depth.is_final(mined, Some(105)));
+        assert!(depth.is_final(mined, Some(106)));
+        assert!(!depth.is_final(mined, None));
+        assert!(!depth.is_final(WitnessOrd::OffChain, Some(1000)));
+        assert!(FinalityDepth::default().is_final(mined, None));
+
+        let meta = finality_depth_meta(depth).unwrap();
+        assert_eq!(meta.as_slice(), &[6, 0, 0, 0]);
+        assert_eq!(finality_depth_meta(FinalityDepth::default()), None);
+    }
+
+    #[test]
+    fn provisional_state() {
+        let ty = AssignmentType::with(1);
+        let mut schema = Schema::strict_dumb();
+        schema.flags = SchemaFlags::RESERVED_TYPES;
+        let mut genesis = Genesis::strict_dumb();
+        genesis.schema_id = schema.schema_id();
+        genesis
+            .metadata
+            .add_value(
+                schema::MetaType::FINALITY_DEPTH,
+                finality_depth_meta(FinalityDepth::new(6)).unwrap(),
+            )
+            .unwrap();
+        let history = ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis);
+        let mut state = ContractState { schema, history };
+
+        let mut transition = Transition::strict_dumb();
+        transition.contract_id = genesis.contract_id();
+        transition.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            ty,
+            TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                XGraphSeal::strict_dumb(),
+                VoidState::default(),
+            )])),
+        )]));
+        let mined = WitnessOrd::OnChain(WitnessPos::new(100, 1700000000).unwrap());
+        let witness_anchor = WitnessAnchor {
+            witness_ord: mined,
+            witness_id: XWitnessId::strict_dumb(),
+        };
+        state.add_transition(&transition, witness_anchor);
+        let opout = Opout::new(transition.id(), ty, 0);
+
+        let ord = |_| Some(mined);
+        let provisional = state
+            .provisional_state(&genesis, ord, |_| Some(105))
+            .unwrap();
+        assert_eq!(provisional.outputs, bset! { opout });
+        assert!(provisional.global.is_empty());
+        let provisional = state
+            .provisional_state(&genesis, ord, |_| Some(106))
+            .unwrap();
+        assert!(provisional.is_empty());
+
+        // Depth is ignored unless the schema opts into the reserved types
+        state.schema.flags = SchemaFlags::default();
+        let provisional = state
+            .provisional_state(&genesis, ord, |_| Some(105))
+            .unwrap();
+        assert!(provisional.is_empty());
+
+        let foreign = Genesis::strict_dumb();
+        assert_eq!(state.provisional_state(&foreign, ord, |_| None), None);
+    }
+}
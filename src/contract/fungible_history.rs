@@ -0,0 +1,235 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Balance history of fungible state.
+//!
+//! Portfolio and accounting software needs to know not only the current
+//! balance of a wallet, but also how it evolved over time.
+//! [`ContractState::fungible_history`] reconstructs this history from the
+//! allocations known to the contract, ordering changes of the balance
+//! according to the consensus order of the witnesses which made them.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{AssignmentType, AssignmentWitness, ContractState, WitnessAnchor, XOutpoint};
+
+/// Change of the fungible balance made by a single witness.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct FungibleHistoryEntry {
+    /// Anchor of the witness which has changed the balance, or `None` for
+    /// the state assigned by genesis and state extensions.
+    pub witness_anchor: Option<WitnessAnchor>,
+    /// Amount received by the outpoints minus the amount spent from them.
+    pub delta: i128,
+    /// Balance after the change.
+    ///
+    /// Relative order of unmined witnesses is not defined by the consensus,
+    /// thus spending an unmined allocation by another unmined witness may
+    /// temporarily make the balance negative.
+    pub balance: i128,
+}
+
+impl ContractState {
+    /// Lists changes of the balance of the fungible state of the given type
+    /// assigned to the outpoints, in the consensus order of the witnesses.
+    /// Outpoints listed multiple times are counted once; witnesses which do
+    /// not change the balance (like transfers between the listed outpoints)
+    /// are not included.
+    pub fn fungible_history(
+        &self,
+        outpoints: impl IntoIterator<Item = impl Into<XOutpoint>>,
+        ty: AssignmentType,
+    ) -> Vec<FungibleHistoryEntry> {
+        let outpoints = outpoints
+            .into_iter()
+            .map(Into::into)
+            .collect::<BTreeSet<_>>();
+        let mut deltas = BTreeMap::<Option<WitnessAnchor>, i128>::new();
+        for assignment in self
+            .fungibles()
+            .iter()
+            .filter(|a| a.opout.ty == ty && outpoints.contains(&a.seal.to_outpoint()))
+        {
+            let value = assignment.state.value.as_u64() as i128;
+            let received = match assignment.witness {
+                AssignmentWitness::Absent => None,
+                AssignmentWitness::Present(witness_id) => Some(
+                    self.witness_anchor(witness_id)
+                        .unwrap_or_else(|| WitnessAnchor::from_mempool(witness_id)),
+                ),
+            };
+            *deltas.entry(received).or_default() += value;
+            if let Some(spender) = self.spent_by(assignment.opout) {
+                *deltas.entry(Some(spender.witness_anchor)).or_default() -= value;
+            }
+        }
+
+        let mut balance = 0i128;
+        deltas
+            .into_iter()
+            .filter(|(_, delta)| *delta != 0)
+            .map(|(witness_anchor, delta)| {
+                balance += delta;
+                FungibleHistoryEntry {
+                    witness_anchor,
+                    delta,
+                    balance,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+    use amplify::Wrapper;
+    use bp::seals::txout::{BlindSeal, CloseMethod, TxPtr};
+    use bp::{Outpoint, Txid, Vout};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{
+        AssetTag, Assign, Assignments, ContractHistory, Genesis, Input, Inputs, Operation, Opout,
+        RevealedValue, Schema, Transition, TypedAssigns, WitnessOrd, WitnessPos, XChain,
+        XGenesisSeal, XGraphSeal,
+    };
+
+    const ASSET: AssignmentType = AssignmentType::with(1);
+
+    fn genesis_seal(no: u8) -> XGenesisSeal {
+        XChain::Bitcoin(BlindSeal {
+            method: CloseMethod::OpretFirst,
+            blinding: no as u64,
+            txid: Txid::from([no; 32]),
+            vout: Vout::from(0u32),
+        })
+    }
+
+    fn witness_seal(vout: u32) -> XGraphSeal {
+        XChain::Bitcoin(BlindSeal {
+            method: CloseMethod::OpretFirst,
+            blinding: vout as u64,
+            txid: TxPtr::WitnessTx,
+            vout: Vout::from(vout),
+        })
+    }
+
+    fn outpoint(txid: u8, vout: u32) -> XOutpoint {
+        XOutpoint::from(XChain::Bitcoin(Outpoint::new(Txid::from([txid; 32]), Vout::from(vout))))
+    }
+
+    fn anchor(txid: u8, witness_ord: WitnessOrd) -> WitnessAnchor {
+        WitnessAnchor {
+            witness_ord,
+            witness_id: XChain::Bitcoin(Txid::from([txid; 32])),
+        }
+    }
+
+    fn transfer(prev_out: Opout, outputs: &[(u32, u64)]) -> Transition {
+        let tag = AssetTag::strict_dumb();
+        let assigns = outputs
+            .iter()
+            .map(|(vout, amount)| {
+                Assign::revealed(
+                    witness_seal(*vout),
+                    RevealedValue::new_random_blinding(*amount, tag),
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut transition = Transition::strict_dumb();
+        transition.inputs = Inputs::try_from_inputs([Input::with(prev_out)]).unwrap();
+        transition.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            ASSET,
+            TypedAssigns::Fungible(Confined::try_from(assigns).unwrap()),
+        )]));
+        transition
+    }
+
+    #[test]
+    fn balance_history() {
+        let tag = AssetTag::strict_dumb();
+        let mut genesis = Genesis::strict_dumb();
+        genesis.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            ASSET,
+            TypedAssigns::Fungible(
+                Confined::try_from(vec![
+                    Assign::revealed(genesis_seal(1), RevealedValue::new_random_blinding(100, tag)),
+                    Assign::revealed(genesis_seal(2), RevealedValue::new_random_blinding(50, tag)),
+                ])
+                .unwrap(),
+            ),
+        )]));
+        let genesis_id = genesis.id();
+        let mut history = ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis);
+
+        // Sends 30 out of 100 to a foreign output
+        let t1 = transfer(Opout::new(genesis_id, ASSET, 0), &[(1, 70), (2, 30)]);
+        // Receives 50 from a foreign output
+        let t2 = transfer(Opout::new(genesis_id, ASSET, 1), &[(1, 50)]);
+        // Moves 70 between the wallet outputs
+        let t3 = transfer(Opout::new(t1.id(), ASSET, 0), &[(1, 70)]);
+        let early = WitnessOrd::OnChain(WitnessPos::new(100, 1700000000).unwrap());
+        let late = WitnessOrd::OnChain(WitnessPos::new(200, 1700060000).unwrap());
+        history.add_transition(&t2, anchor(12, late));
+        history.add_transition(&t3, anchor(13, WitnessOrd::OffChain));
+        history.add_transition(&t1, anchor(11, early));
+        let state = ContractState {
+            schema: Schema::strict_dumb(),
+            history,
+        };
+
+        let wallet =
+            [outpoint(1, 0), outpoint(11, 1), outpoint(12, 1), outpoint(13, 1), outpoint(1, 0)];
+        assert_eq!(state.fungible_history(wallet, ASSET), vec![
+            FungibleHistoryEntry {
+                witness_anchor: None,
+                delta: 100,
+                balance: 100,
+            },
+            FungibleHistoryEntry {
+                witness_anchor: Some(anchor(11, early)),
+                delta: -30,
+                balance: 70,
+            },
+            FungibleHistoryEntry {
+                witness_anchor: Some(anchor(12, late)),
+                delta: 50,
+                balance: 120,
+            },
+        ]);
+
+        assert_eq!(state.fungible_history([outpoint(12, 1)], ASSET), vec![FungibleHistoryEntry {
+            witness_anchor: Some(anchor(12, late)),
+            delta: 50,
+            balance: 50,
+        }]);
+        assert!(state
+            .fungible_history(wallet, AssignmentType::with(2))
+            .is_empty());
+    }
+}
@@ -23,30 +23,66 @@
 use std::collections::btree_map;
 use std::vec;
 
-use amplify::confinement::{Confined, TinyOrdMap, U16};
+use amplify::confinement::{Confined, SmallBlob, TinyOrdMap};
 use amplify::{confinement, Wrapper};
-use strict_encoding::StrictDumb;
+use strict_encoding::{DecodeError, StrictDecode, StrictDumb, TypedRead};
 
-use crate::{schema, DataState, LIB_NAME_RGB};
+use crate::{
+    confine, schema, CollectionLimitError, DataState, DecodeLimitError, GlobalValueVec,
+    GLOBAL_VALUES_MAX_LEN, LIB_NAME_RGB,
+};
 
 #[derive(Wrapper, WrapperMut, Clone, PartialEq, Eq, Hash, Debug, From)]
 #[wrapper(Deref)]
 #[wrapper_mut(DerefMut)]
-#[derive(StrictType, StrictEncode, StrictDecode)]
+#[derive(StrictType, StrictEncode)]
 #[strict_type(lib = LIB_NAME_RGB)]
 #[cfg_attr(
     feature = "serde",
     derive(Serialize, Deserialize),
     serde(crate = "serde_crate", transparent)
 )]
-pub struct GlobalValues(Confined<Vec<DataState>, 1, U16>);
+pub struct GlobalValues(GlobalValueVec);
 
 impl StrictDumb for GlobalValues {
     fn strict_dumb() -> Self { Self(confined_vec!(DataState::strict_dumb())) }
 }
 
+impl StrictDecode for GlobalValues {
+    fn strict_decode(reader: &mut impl TypedRead) -> Result<Self, DecodeError> {
+        // The length of each value is read before its data, such that the total size of
+        // the values is checked before the data are allocated
+        let len = u16::strict_decode(reader)?;
+        let mut values = vec![];
+        let mut size = 0usize;
+        for _ in 0..len {
+            let value_len = u16::strict_decode(reader)?;
+            size += value_len as usize;
+            if size > GLOBAL_VALUES_MAX_LEN {
+                return Err(DecodeLimitError::GlobalValuesSize(size).into());
+            }
+            let data = (0..value_len)
+                .map(|_| u8::strict_decode(reader))
+                .collect::<Result<Vec<_>, _>>()?;
+            let data = SmallBlob::try_from(data).expect("the length is read as u16");
+            values.push(DataState::from(data));
+        }
+        confine("global state values", values)
+            .map(Self)
+            .map_err(|err| DecodeError::DataIntegrityError(err.to_string()))
+    }
+}
+
 impl GlobalValues {
     pub fn with(state: DataState) -> Self { GlobalValues(Confined::with(state)) }
+
+    /// Constructs global state values, checking their number against the
+    /// consensus bounds.
+    pub fn try_from_values(
+        values: impl IntoIterator<Item = DataState>,
+    ) -> Result<Self, CollectionLimitError> {
+        confine("global state values", values.into_iter().collect()).map(Self)
+    }
 }
 
 impl IntoIterator for GlobalValues {
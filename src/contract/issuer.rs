@@ -0,0 +1,120 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Issuer identity signatures over the contract genesis.
+//!
+//! Issuer may sign the contract genesis with a BIP-340 key, proving that the
+//! contract was issued by the holder of the key. The signature commits to
+//! both the contract id and the contract schema id, and is distributed next
+//! to the genesis, since it can't be a part of the data committed into the
+//! contract id. Once verified, the signer key is reported in the validation
+//! status, allowing wallets to display the contract provenance.
+
+use amplify::ByteArray;
+use bp::secp256k1::{schnorr, Keypair, Message, Secp256k1, XOnlyPublicKey};
+use commit_verify::{DigestExt, Sha256};
+
+use crate::{ContractId, Genesis, Operation};
+
+pub const ISSUER_SIGNATURE_TAG: &[u8] = b"urn:lnp-bp:rgb:issuer-signature#2024-05-20";
+
+/// BIP-340 signature of the contract issuer over the genesis.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct IssuerSignature {
+    pub signer: XOnlyPublicKey,
+    pub signature: schnorr::Signature,
+}
+
+/// Errors verifying issuer signature.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(doc_comments)]
+pub enum IssuerSignatureError {
+    /// issuer signature by key {1} doesn't match genesis of contract {0}.
+    Invalid(ContractId, XOnlyPublicKey),
+}
+
+impl Genesis {
+    /// Message signed by the contract issuer.
+    pub fn issuer_message(&self) -> Message {
+        let mut hasher = Sha256::default();
+        hasher.input_raw(ISSUER_SIGNATURE_TAG);
+        hasher.input_raw(&self.contract_id().to_byte_array());
+        hasher.input_raw(&self.schema_id.to_byte_array());
+        Message::from_digest(hasher.finish())
+    }
+
+    /// Signs the genesis with the issuer key.
+    pub fn sign_issuer(&self, keypair: &Keypair) -> IssuerSignature {
+        let secp = Secp256k1::signing_only();
+        let signature = secp.sign_schnorr_no_aux_rand(&self.issuer_message(), keypair);
+        IssuerSignature {
+            signer: keypair.x_only_public_key().0,
+            signature,
+        }
+    }
+
+    /// Verifies issuer signature over the genesis, returning the signer key.
+    pub fn verify_issuer(
+        &self,
+        sig: &IssuerSignature,
+    ) -> Result<XOnlyPublicKey, IssuerSignatureError> {
+        Secp256k1::verification_only()
+            .verify_schnorr(&sig.signature, &self.issuer_message(), &sig.signer)
+            .map_err(|_| IssuerSignatureError::Invalid(self.contract_id(), sig.signer))?;
+        Ok(sig.signer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bp::secp256k1::SecretKey;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn sign_verify() {
+        let secp = Secp256k1::new();
+        let sk = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let keypair = Keypair::from_secret_key(&secp, &sk);
+        let genesis = Genesis::strict_dumb();
+        let sig = genesis.sign_issuer(&keypair);
+        assert_eq!(genesis.verify_issuer(&sig), Ok(keypair.x_only_public_key().0));
+
+        let mut other = genesis.clone();
+        other.testnet = !other.testnet;
+        assert_eq!(
+            other.verify_issuer(&sig),
+            Err(IssuerSignatureError::Invalid(other.contract_id(), sig.signer))
+        );
+    }
+}
@@ -0,0 +1,344 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Consensus ceilings on the size of contract operations.
+//!
+//! The limits are enforced by the strict decoders of the respective data
+//! structures while reading the data, such that oversized data are rejected
+//! before they are allocated and before they reach the validation procedures.
+//! The strict decoders of genesis, state extensions and state transitions
+//! count the bytes they read and fail once [`OP_MAX_SERIALIZED_LEN`] is
+//! exceeded; transition bundles and consignments decode their operations
+//! with these decoders and thus can't contain oversized operations.
+//!
+//! The limits on the assignments and the global state are implied by the
+//! limit on the operation size: no operation fitting
+//! [`OP_MAX_SERIALIZED_LEN`] may exceed them. Thus, they don't reject any
+//! operation fitting the operation size limit, and just stop reading
+//! oversized collections early.
+//!
+//! The module also exposes the bounded collections used by the contract data
+//! structures as type aliases, such that the code constructing operations can
+//! check the same bounds with [`confine`] and get [`CollectionLimitError`]
+//! naming the violated bound.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use amplify::confinement::{Collection, Confined, SmallOrdSet, SmallVec, TinyOrdMap, U16};
+use strict_encoding::{
+    DecodeError, DeserializeError, ReadRaw, StrictDeserialize, StrictReader, TypedRead,
+};
+
+use crate::schema::{AssignmentType, GlobalStateType, MetaType};
+use crate::{DataState, Input, MetaValue, OpId, Operation, Transition, Vin};
+
+/// Maximum size of a strict-serialized contract operation, in bytes.
+pub const OP_MAX_SERIALIZED_LEN: usize = 0x10_0000;
+
+/// Maximum number of assignments of all types in a single operation.
+///
+/// The smallest assignment, which is a declarative one with a concealed seal,
+/// takes 36 bytes when serialized, thus an operation of
+/// [`OP_MAX_SERIALIZED_LEN`] contains less than 30 000 assignments. The limit
+/// applies to all types together, since it is implied by the operation size
+/// and not by the size of the assignments of a single type.
+pub const OP_MAX_ASSIGNMENTS: usize = U16;
+
+/// Maximum total size of the values of a single global state type within an
+/// operation, in bytes.
+pub const GLOBAL_VALUES_MAX_LEN: usize = OP_MAX_SERIALIZED_LEN;
+
+/// Errors of the strict decoding caused by the data exceeding consensus
+/// limits.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum DecodeLimitError {
+    /// serialized operation has size of at least {0} bytes, exceeding the
+    /// consensus limit.
+    OperationSize(usize),
+
+    /// operation contains at least {0} assignments, exceeding the consensus
+    /// limit.
+    AssignmentCount(usize),
+
+    /// global state values have total size of at least {0} bytes, exceeding
+    /// the consensus limit.
+    GlobalValuesSize(usize),
+}
+
+impl From<DecodeLimitError> for DecodeError {
+    fn from(err: DecodeLimitError) -> Self { DecodeError::DataIntegrityError(err.to_string()) }
+}
+
+/// Raw reader counting the bytes read from the underlying reader and failing
+/// once their number exceeds the limit.
+pub(crate) struct LimitedReader<'reader, R: ReadRaw> {
+    reader: &'reader mut R,
+    count: usize,
+    limit: usize,
+}
+
+impl<R: ReadRaw> LimitedReader<'_, R> {
+    fn consume(&mut self, len: usize) -> io::Result<()> {
+        self.count = self.count.saturating_add(len);
+        if self.count > self.limit {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        Ok(())
+    }
+}
+
+impl<R: ReadRaw> ReadRaw for LimitedReader<'_, R> {
+    fn read_raw<const MAX_LEN: usize>(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        // The length is checked before the data are allocated by the underlying reader
+        self.consume(len)?;
+        self.reader.read_raw::<MAX_LEN>(len)
+    }
+
+    fn read_raw_array<const LEN: usize>(&mut self) -> io::Result<[u8; LEN]> {
+        self.consume(LEN)?;
+        self.reader.read_raw_array::<LEN>()
+    }
+}
+
+/// Decodes contract operation with the `decode` procedure, failing with
+/// [`DecodeLimitError::OperationSize`] once it reads more than
+/// [`OP_MAX_SERIALIZED_LEN`] bytes.
+pub(crate) fn decode_operation<R: TypedRead, T>(
+    reader: &mut R,
+    decode: impl FnOnce(&mut StrictReader<LimitedReader<'_, R::RawReader>>) -> Result<T, DecodeError>,
+) -> Result<T, DecodeError> {
+    // The raw reader is used only through the strict reader wrapping it, which
+    // reads the operation in the same way as the original typed reader does
+    let raw = unsafe { reader.raw_reader() };
+    let mut limited = StrictReader::with(LimitedReader {
+        reader: raw,
+        count: 0,
+        limit: OP_MAX_SERIALIZED_LEN,
+    });
+    let res = decode(&mut limited);
+    let count = limited.unbox().count;
+    res.map_err(|err| match count > OP_MAX_SERIALIZED_LEN {
+        true => DecodeLimitError::OperationSize(count).into(),
+        false => err,
+    })
+}
+
+/// Deserializes contract operation from the strict-encoded data, rejecting
+/// data exceeding [`OP_MAX_SERIALIZED_LEN`] before decoding.
+pub fn deserialize_operation<Op: Operation + StrictDeserialize>(
+    data: impl AsRef<[u8]>,
+) -> Result<Op, DeserializeError> {
+    let data = data.as_ref();
+    let data = Confined::<Vec<u8>, 0, OP_MAX_SERIALIZED_LEN>::try_from(data.to_vec())
+        .map_err(|_| DecodeError::from(DecodeLimitError::OperationSize(data.len())))?;
+    Op::from_strict_serialized::<OP_MAX_SERIALIZED_LEN>(data)
+}
+
+/// Assignments of a single type within an operation: up to 65535 items.
+pub type AssignmentVec<T> = SmallVec<T>;
+
+/// Assignments of an operation by their type: up to 255 types.
+pub type AssignmentMap<T> = TinyOrdMap<AssignmentType, T>;
+
+/// Values of a single global state type within an operation: from 1 to 65535
+/// items.
+pub type GlobalValueVec = Confined<Vec<DataState>, 1, U16>;
+
+/// Global state of an operation by its type: up to 255 types.
+pub type GlobalStateMap<T> = TinyOrdMap<GlobalStateType, T>;
+
+/// Metadata of an operation: up to 255 types.
+pub type MetadataMap = TinyOrdMap<MetaType, MetaValue>;
+
+/// Inputs of a state transition: up to 65535 items.
+pub type InputSet = SmallOrdSet<Input>;
+
+/// Witness transaction inputs mapped to the transitions of a bundle: from 1 to
+/// 65535 items.
+pub type BundleInputMap = Confined<BTreeMap<Vin, OpId>, 1, U16>;
+
+/// Transitions of a bundle: from 1 to 65535 items.
+pub type BundleTransitions = Confined<BTreeMap<OpId, Transition>, 1, U16>;
+
+/// Errors constructing bounded collections.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum CollectionLimitError {
+    /// {collection} must contain at least {min} items, while {len} were
+    /// provided.
+    Undersize {
+        collection: &'static str,
+        len: usize,
+        min: usize,
+    },
+
+    /// {collection} may contain at most {max} items, while {len} were
+    /// provided.
+    Oversize {
+        collection: &'static str,
+        len: usize,
+        max: usize,
+    },
+}
+
+/// Checks the collection against the bounds of the confined collection type,
+/// reporting the violated bound with the collection name used in the error.
+pub fn confine<C: Collection, const MIN: usize, const MAX: usize>(
+    collection: &'static str,
+    items: C,
+) -> Result<Confined<C, MIN, MAX>, CollectionLimitError> {
+    let len = items.len();
+    if len < MIN {
+        return Err(CollectionLimitError::Undersize {
+            collection,
+            len,
+            min: MIN,
+        });
+    }
+    if len > MAX {
+        return Err(CollectionLimitError::Oversize {
+            collection,
+            len,
+            max: MAX,
+        });
+    }
+    Ok(Confined::from_collection_unsafe(items))
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::SmallBlob;
+    use amplify::Wrapper;
+    use strict_encoding::{StrictDecode, StrictDumb, StrictEncode, StrictSerialize, StrictWriter};
+
+    use super::*;
+    use crate::{
+        Assign, Assignments, GlobalValues, GraphSeal, SecretSeal, TransitionBundle, TypedAssigns,
+        VoidState, XChain, XGraphSeal,
+    };
+
+    const TEST_MAX_LEN: usize = 0x40_0000;
+
+    fn serialize(value: &impl StrictEncode) -> Vec<u8> {
+        let writer = StrictWriter::in_memory::<TEST_MAX_LEN>();
+        value.strict_encode(writer).unwrap().unbox().unconfine()
+    }
+
+    fn decode_error<T: StrictDecode>(data: Vec<u8>) -> String {
+        let mut reader = StrictReader::in_memory::<TEST_MAX_LEN>(data);
+        match T::strict_decode(&mut reader) {
+            Err(DecodeError::DataIntegrityError(msg)) => msg,
+            Err(err) => panic!("unexpected error: {err}"),
+            Ok(_) => panic!("oversized data are decoded"),
+        }
+    }
+
+    fn global_values(size: usize) -> GlobalValues {
+        let value = DataState::from(SmallBlob::try_from(vec![0u8; 0xFFFF]).unwrap());
+        GlobalValues::try_from_values(vec![value; size / 0xFFFF + 1]).unwrap()
+    }
+
+    #[test]
+    fn operation_size() {
+        let mut transition = Transition::strict_dumb();
+        for ty in 1..=2 {
+            let values = global_values(OP_MAX_SERIALIZED_LEN / 2);
+            transition
+                .globals
+                .insert(GlobalStateType::with(ty), values)
+                .unwrap();
+        }
+        let err = decode_error::<Transition>(serialize(&transition));
+        assert!(err.starts_with("serialized operation has size of at least"));
+
+        let mut bundle = TransitionBundle::strict_dumb();
+        bundle.known_transitions = Confined::with((transition.id(), transition));
+        assert_eq!(decode_error::<TransitionBundle>(serialize(&bundle)), err);
+
+        transition = Transition::strict_dumb();
+        let data = transition.to_strict_serialized::<TEST_MAX_LEN>().unwrap();
+        assert_eq!(Transition::from_strict_serialized::<TEST_MAX_LEN>(data).unwrap(), transition);
+    }
+
+    #[test]
+    fn assignment_count() {
+        let assign = Assign::revealed(XGraphSeal::strict_dumb(), VoidState::default());
+        let typed_assigns = |count: usize| {
+            TypedAssigns::Declarative(Confined::try_from(vec![assign.clone(); count]).unwrap())
+        };
+        let mut assignments = Assignments::<GraphSeal>::from_inner(
+            Confined::try_from(bmap! {
+                AssignmentType::with(1) => typed_assigns(U16),
+            })
+            .unwrap(),
+        );
+        let mut reader = StrictReader::in_memory::<TEST_MAX_LEN>(serialize(&assignments));
+        assert_eq!(Assignments::strict_decode(&mut reader).unwrap(), assignments);
+
+        assignments
+            .insert(AssignmentType::with(2), typed_assigns(1))
+            .unwrap();
+        let err = DecodeLimitError::AssignmentCount(OP_MAX_ASSIGNMENTS + 1);
+        assert_eq!(
+            decode_error::<Assignments<GraphSeal>>(serialize(&assignments)),
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn assignment_count_implied() {
+        let assign = Assign::<VoidState, GraphSeal>::ConfidentialSeal {
+            seal: XChain::Bitcoin(SecretSeal::strict_dumb()),
+            state: VoidState::default(),
+            lock: default!(),
+        };
+        let len = serialize(&assign).len();
+        assert_eq!(len, 36);
+        assert!(len * OP_MAX_ASSIGNMENTS > OP_MAX_SERIALIZED_LEN);
+    }
+
+    #[test]
+    fn global_values_size() {
+        let values = global_values(GLOBAL_VALUES_MAX_LEN);
+        let err = DecodeLimitError::GlobalValuesSize(values.len() * 0xFFFF);
+        assert_eq!(decode_error::<GlobalValues>(serialize(&values)), err.to_string());
+    }
+
+    #[test]
+    fn confine_bounds() {
+        let err = confine::<_, 1, { U16 }>("global values", Vec::<DataState>::new()).unwrap_err();
+        assert_eq!(err, CollectionLimitError::Undersize {
+            collection: "global values",
+            len: 0,
+            min: 1
+        });
+        let items = vec![0u8; 0x100];
+        let err = confine::<_, 0, 0xFF>("metadata", items).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "metadata may contain at most 255 items, while 256 were provided."
+        );
+        assert!(confine::<_, 1, { U16 }>("inputs", vec![0u8]).is_ok());
+    }
+}
@@ -0,0 +1,98 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lock scripts attached to individual assignments.
+//!
+//! An operation may lock some of its assignments with AluVM scripts by
+//! putting [`AssignmentLocks`] into its [`schema::MetaType::ASSIGNMENT_LOCKS`]
+//! metadata. Since metadata are committed into the operation id, locks are
+//! committed together with the assignments. Lock script is evaluated when the
+//! assignment is spent, in the context of the spending operation, which may
+//! provide the data required by the script in its
+//! [`schema::MetaType::LOCK_WITNESS`] metadata.
+
+use aluvm::library::LibSite;
+use amplify::confinement::{TinyOrdMap, U16};
+use amplify::Wrapper;
+use strict_encoding::{StrictDeserialize, StrictSerialize};
+
+use crate::{schema, AssignmentType, MetaValue, Metadata, LIB_NAME_RGB};
+
+/// Assignment of an operation which is locked with a script.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display("{ty}/{no}")]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+pub struct LockedAssignment {
+    pub ty: AssignmentType,
+    pub no: u16,
+}
+
+impl LockedAssignment {
+    pub fn new(ty: AssignmentType, no: u16) -> Self { LockedAssignment { ty, no } }
+}
+
+/// Lock scripts for the assignments of an operation.
+#[derive(Wrapper, WrapperMut, Clone, PartialEq, Eq, Hash, Debug, Default, From)]
+#[wrapper(Deref)]
+#[wrapper_mut(DerefMut)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+pub struct AssignmentLocks(TinyOrdMap<LockedAssignment, LibSite>);
+
+impl StrictSerialize for AssignmentLocks {}
+impl StrictDeserialize for AssignmentLocks {}
+
+impl AssignmentLocks {
+    /// Returns lock script for the assignment, if any.
+    pub fn lock(&self, ty: AssignmentType, no: u16) -> Option<LibSite> {
+        self.0.get(&LockedAssignment::new(ty, no)).copied()
+    }
+
+    /// Converts locks into a value for the
+    /// [`schema::MetaType::ASSIGNMENT_LOCKS`] metadata.
+    pub fn to_meta(&self) -> MetaValue {
+        let data = self
+            .to_strict_serialized::<U16>()
+            .expect("tiny map always fits into a small blob");
+        MetaValue::from(data)
+    }
+
+    /// Parses locks from the [`schema::MetaType::ASSIGNMENT_LOCKS`] metadata
+    /// value.
+    pub fn from_meta(meta: &MetaValue) -> Option<Self> {
+        Self::from_strict_serialized::<U16>(meta.clone().into_inner()).ok()
+    }
+}
+
+impl Metadata {
+    /// Returns assignment locks declared by the operation metadata; empty
+    /// locks if the metadata are absent.
+    ///
+    /// Returns `None` if the metadata value is malformed.
+    pub fn assignment_locks(&self) -> Option<AssignmentLocks> {
+        match self.get(&schema::MetaType::ASSIGNMENT_LOCKS) {
+            None => Some(AssignmentLocks::default()),
+            Some(meta) => AssignmentLocks::from_meta(meta),
+        }
+    }
+}
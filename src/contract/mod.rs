@@ -30,43 +30,154 @@ mod anchor;
 pub mod seal;
 pub mod assignments;
 mod operations;
+mod extension;
 mod bundle;
 #[allow(clippy::module_inception)]
 mod contract;
 mod xchain;
 mod commit;
+mod limits;
+mod proof;
+mod disclosure;
+mod dependency;
+mod substitution;
+mod apply;
+mod filter;
+mod timestamp;
+mod lock;
+mod blinding;
+mod consensus_ord;
+mod constraints;
+mod audit_export;
+mod refined;
+mod issuer;
+mod amount_proof;
+mod upgrade;
+mod seal_usage;
+mod restricted;
+mod reveal;
+mod compaction;
+mod chain_salt;
+mod features;
+mod supply;
+mod snapshot;
+mod patch;
+mod watch;
+mod canonical;
+mod facade;
+mod oracle;
+mod finality;
+mod terms;
+mod fungible_history;
+mod valency;
+mod seal_status;
+mod split;
+mod replace;
+mod template;
+mod transfer;
+#[cfg(feature = "async")]
+mod async_state;
 
+pub use amount_proof::{AmountDisclosure, AmountDisclosureError};
 pub use anchor::{DbcError, DbcProof, EAnchor, Layer1, WitnessAnchor};
+pub use apply::{ApplyOutcome, ContractStateMut, MemContractState, StateDiff, StateObserver};
 pub use assignments::{
     Assign, AssignAttach, AssignData, AssignFungible, AssignRights, Assignments, AssignmentsRef,
     TypedAssigns,
 };
+#[cfg(feature = "async")]
+pub use async_state::{
+    block_on, AsyncContractState, AsyncGlobalStateIter, BlockingGlobalIter, BlockingState,
+};
 pub use attachment::{AttachId, ConcealedAttach, RevealedAttach};
+pub use audit_export::{
+    parse_audit, AuditFormat, AuditKind, AuditParseError, AuditRow, AUDIT_COLUMNS,
+};
+pub use blinding::{SealBatch, SealBatchError, SealBatchRecord, SEED_COMMITMENT_TAG};
 pub use bundle::{BundleId, InputMap, TransitionBundle, Vin};
+pub use canonical::{CanonicalState, CANONICAL_STATE_TAG};
+pub use chain_salt::{ChainNet, ContractIdVersion};
 pub use commit::{
     AssignmentCommitment, AssignmentIndex, BaseCommitment, BundleDisclosure, ContractId,
-    DiscloseHash, GlobalCommitment, OpCommitment, OpDisclose, OpId, TypeCommitment,
+    DigestAlgo, DiscloseHash, GlobalCommitment, OpCommitment, OpDisclose, OpId, TypeCommitment,
 };
+pub use compaction::{replaceable_globals_meta, CompactionError, GlobalSupersession};
+pub use consensus_ord::{ord_vectors, OrdVector, OrdVectors};
+pub use constraints::WitnessConstraints;
 pub use contract::{
-    AssignmentWitness, ContractHistory, ContractState, GlobalOrd, KnownState, Opout,
-    OpoutParseError, OutputAssignment,
+    AssignmentWitness, ContractHistory, ContractState, GlobalContractState, GlobalOrd,
+    GlobalStateError, GlobalStateIter, KnownState, Opout, OpoutParseError, OutputAssignment,
+    SpendingOp,
 };
 pub use data::{ConcealedData, DataState, RevealedData, VoidState};
+pub use dependency::{dependencies_meta, DependencyError, DependencyGraph};
+pub use disclosure::DisclosureLevel;
+pub use extension::{ExtensionBuilder, ExtensionBuilderError};
+pub use facade::{DynContractState, DynIter};
+pub use features::OpFeatures;
+pub use filter::{
+    outpoint_filter_item, pattern_filter_item, tx_filter_items, SealFilter, FILTER_M, FILTER_P,
+    OPRET_FILTER_ITEM, TAPRET_FILTER_ITEM,
+};
+pub use finality::{finality_depth_meta, FinalityDepth, ProvisionalState};
 pub use fungible::{
     AssetTag, BlindingFactor, BlindingParseError, ConcealedValue, FungibleState,
     InvalidFieldElement, NoiseDumb, PedersenCommitment, RangeProof, RangeProofError, RevealedValue,
 };
+pub use fungible_history::FungibleHistoryEntry;
 pub use global::{GlobalState, GlobalValues};
+pub use issuer::{IssuerSignature, IssuerSignatureError, ISSUER_SIGNATURE_TAG};
+pub use limits::{
+    confine, deserialize_operation, AssignmentMap, AssignmentVec, BundleInputMap,
+    BundleTransitions, CollectionLimitError, DecodeLimitError, GlobalStateMap, GlobalValueVec,
+    InputSet, MetadataMap, GLOBAL_VALUES_MAX_LEN, OP_MAX_ASSIGNMENTS, OP_MAX_SERIALIZED_LEN,
+};
+pub use lock::{AssignmentLocks, LockedAssignment};
 pub use meta::{MetaValue, Metadata, MetadataError};
 pub use operations::{
     AssetTags, Extension, Genesis, Identity, Input, Inputs, OpRef, Operation, Redeemed, Transition,
     Valencies,
 };
+pub use oracle::{
+    oracle_feeds_meta, OracleAttestation, OracleError, OracleFeed, OracleSet, OracleSignature,
+    OracleTracker, ORACLE_ATTESTATION_TAG, ORACLE_ROTATION_TAG, ORACLE_SET_MAX_KEYS,
+};
+pub use patch::{PatchError, PatchOp, TransitionPatch};
+pub use proof::{
+    StateLeaf, StateProof, StateProofError, StateRoot, STATE_ROOT_EMPTY_TAG, STATE_ROOT_NODE_TAG,
+};
+pub use refined::{
+    NonEmptyAssignments, NonEmptyInputs, NonEmptyRedeemed, NonEmptyValencies, OpOutputs,
+    RefinementError,
+};
+pub use replace::{CurrentGlobal, GlobalProvenance};
+pub use restricted::RestrictedState;
+pub use reveal::{FromStateData, RevealError, SecretRegistry};
+#[cfg(feature = "derive")]
+pub use rgb_core_derive::DynContractState;
 pub use seal::{
-    ExposedSeal, GenesisSeal, GraphSeal, OutputSeal, SecretSeal, TxoSeal, WitnessOrd, WitnessPos,
-    XGenesisSeal, XGraphSeal, XOutputSeal, XWitnessId, XWitnessTx,
+    BlindingSeed, CloseMethodSet, ExposedSeal, GenesisSeal, GraphSeal, OutputSeal, SecretSeal,
+    TxoSeal, WitnessOrd, WitnessPos, XGenesisSeal, XGraphSeal, XOutputSeal, XWitnessId, XWitnessTx,
+    SEAL_BLINDING_TAG,
 };
+pub use seal_status::{ContractSeal, SealStatus};
+pub use seal_usage::SealUsage;
+pub use snapshot::StateSnapshot;
+pub use split::{FungibleInput, RebalanceError, Rebalanced};
 pub use state::{ConcealedState, ConfidentialState, ExposedState, RevealedState, StateType};
+pub use substitution::{SubstitutionError, WitnessSubstitution};
+pub use supply::{supply_caps_meta, SupplyError, SupplyTracker};
+pub use template::{template_id_meta, GenesisTemplateId, TemplateError, GENESIS_TEMPLATE_TAG};
+pub use terms::{ContractTerms, TermsError};
+pub use timestamp::{
+    DocumentId, DocumentTimestamp, TimestampError, DOCUMENT_ID_TAG, DOCUMENT_PROTOCOL_TAG,
+};
+pub use transfer::{
+    StateDelta, Transfer, TransferAssembler, TransferConsignment, TransferError, TransferState,
+};
+pub use upgrade::SchemaUpgrade;
+pub use valency::{valency_limits_meta, Redemptions, ValencyError, ValencyLimit, ValencyTracker};
+pub use watch::{AlertError, ScriptPattern, SealClosing, SpendAlert, WatchList};
 pub use xchain::{
     AltLayer1, AltLayer1Set, XChain, XChainParseError, XOutpoint, XCHAIN_BITCOIN_PREFIX,
     XCHAIN_LIQUID_PREFIX,
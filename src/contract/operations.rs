@@ -20,25 +20,29 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::cmp::Ordering;
 use std::collections::{btree_map, btree_set, BTreeMap};
 use std::iter;
 
-use amplify::confinement::{Confined, SmallOrdSet, TinyOrdMap, TinyOrdSet};
+use amplify::confinement::{Confined, TinyOrdMap, TinyOrdSet};
 use amplify::Wrapper;
 use commit_verify::{
     CommitEncode, CommitEngine, CommitId, Conceal, MerkleHash, MerkleLeaves, ReservedBytes,
     StrictHash,
 };
 use strict_encoding::stl::AsciiPrintable;
-use strict_encoding::{RString, StrictDeserialize, StrictEncode, StrictSerialize};
+use strict_encoding::{
+    DecodeError, RString, ReadStruct, StrictDecode, StrictDeserialize, StrictEncode,
+    StrictSerialize, TypedRead,
+};
 
+use super::limits::decode_operation;
 use crate::schema::{self, ExtensionType, OpFullType, OpType, SchemaId, TransitionType};
 use crate::{
-    AltLayer1Set, AssetTag, Assign, AssignmentIndex, AssignmentType, Assignments, AssignmentsRef,
-    ConcealedAttach, ConcealedData, ConcealedValue, ContractId, DiscloseHash, ExposedState, Ffv,
-    GenesisSeal, GlobalState, GraphSeal, Metadata, OpDisclose, OpId, Opout, SecretSeal,
-    TypedAssigns, VoidState, XChain, LIB_NAME_RGB,
+    confine, AltLayer1Set, AssetTag, Assign, AssignmentIndex, AssignmentType, Assignments,
+    AssignmentsRef, CloseMethodSet, CollectionLimitError, ConcealedAttach, ConcealedData,
+    ConcealedValue, ContractId, DigestAlgo, DiscloseHash, ExposedState, Ffv, GenesisSeal,
+    GlobalState, GraphSeal, InputSet, Metadata, OpDisclose, OpId, Opout, SecretSeal, TypedAssigns,
+    VoidState, XChain, LIB_NAME_RGB,
 };
 
 #[derive(Wrapper, WrapperMut, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default, From)]
@@ -107,7 +111,17 @@ impl<'a> IntoIterator for &'a Redeemed {
     derive(Serialize, Deserialize),
     serde(crate = "serde_crate", transparent)
 )]
-pub struct Inputs(SmallOrdSet<Input>);
+pub struct Inputs(InputSet);
+
+impl Inputs {
+    /// Constructs transition inputs, checking their number against the
+    /// consensus bounds.
+    pub fn try_from_inputs(
+        inputs: impl IntoIterator<Item = Input>,
+    ) -> Result<Self, CollectionLimitError> {
+        confine("transition inputs", inputs.into_iter().collect()).map(Self)
+    }
+}
 
 impl<'a> IntoIterator for &'a Inputs {
     type Item = Input;
@@ -169,6 +183,18 @@ pub trait Operation {
     /// serialization
     fn id(&self) -> OpId;
 
+    /// Returns [`OpId`] computed with a specific digest algorithm (see
+    /// [`crate::OpCommitment::id_with`]).
+    fn id_with(&self, algo: DigestAlgo) -> OpId;
+
+    /// Checks whether the provided id commits to this operation under any of
+    /// the supported digest algorithms, returning the matching algorithm.
+    fn verify_id(&self, opid: OpId) -> Option<DigestAlgo> {
+        DigestAlgo::ALL
+            .into_iter()
+            .find(|algo| self.id_with(*algo) == opid)
+    }
+
     /// Returns [`ContractId`] this operation belongs to.
     fn contract_id(&self) -> ContractId;
 
@@ -188,7 +214,7 @@ pub trait Operation {
     fn globals(&self) -> &GlobalState;
     fn valencies(&self) -> &Valencies;
 
-    fn assignments(&self) -> AssignmentsRef;
+    fn assignments(&self) -> AssignmentsRef<'_>;
 
     fn assignments_by_type(&self, t: AssignmentType) -> Option<TypedAssigns<GraphSeal>>;
 
@@ -287,7 +313,7 @@ impl Identity {
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
-#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[derive(StrictType, StrictDumb, StrictEncode)]
 #[strict_type(lib = LIB_NAME_RGB)]
 #[cfg_attr(
     feature = "serde",
@@ -310,11 +336,52 @@ pub struct Genesis {
     pub validator: ReservedBytes<1, 0>,
 }
 
+impl StrictDecode for Genesis {
+    fn strict_decode(reader: &mut impl TypedRead) -> Result<Self, DecodeError> {
+        decode_operation(reader, |reader| {
+            reader.read_struct(|r| {
+                Ok(Genesis {
+                    ffv: r.read_field(fname!("ffv"))?,
+                    schema_id: r.read_field(fname!("schemaId"))?,
+                    flags: r.read_field(fname!("flags"))?,
+                    timestamp: r.read_field(fname!("timestamp"))?,
+                    issuer: r.read_field(fname!("issuer"))?,
+                    testnet: r.read_field(fname!("testnet"))?,
+                    alt_layers1: r.read_field(fname!("altLayers1"))?,
+                    asset_tags: r.read_field(fname!("assetTags"))?,
+                    metadata: r.read_field(fname!("metadata"))?,
+                    globals: r.read_field(fname!("globals"))?,
+                    assignments: r.read_field(fname!("assignments"))?,
+                    valencies: r.read_field(fname!("valencies"))?,
+                    validator: r.read_field(fname!("validator"))?,
+                })
+            })
+        })
+    }
+}
+
 impl StrictSerialize for Genesis {}
 impl StrictDeserialize for Genesis {}
 
+impl Genesis {
+    /// Returns set of close methods acceptable for the contract witnesses, as
+    /// declared by [`schema::MetaType::CLOSE_METHODS`] metadata. If the
+    /// metadata is absent, all close methods are accepted.
+    ///
+    /// Returns `None` if the metadata value is malformed.
+    pub fn close_methods(&self) -> Option<CloseMethodSet> {
+        match self.metadata.get(&schema::MetaType::CLOSE_METHODS) {
+            None => Some(CloseMethodSet::ALL),
+            Some(meta) => match meta.as_slice() {
+                [bits] => CloseMethodSet::from_bits(*bits),
+                _ => None,
+            },
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug)]
-#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[derive(StrictType, StrictDumb, StrictEncode)]
 #[strict_type(lib = LIB_NAME_RGB)]
 #[cfg_attr(
     feature = "serde",
@@ -334,19 +401,32 @@ pub struct Extension {
     pub witness: ReservedBytes<2, 0>,
 }
 
-impl StrictSerialize for Extension {}
-impl StrictDeserialize for Extension {}
-
-impl Ord for Extension {
-    fn cmp(&self, other: &Self) -> Ordering { self.id().cmp(&other.id()) }
+impl StrictDecode for Extension {
+    fn strict_decode(reader: &mut impl TypedRead) -> Result<Self, DecodeError> {
+        decode_operation(reader, |reader| {
+            reader.read_struct(|r| {
+                Ok(Extension {
+                    ffv: r.read_field(fname!("ffv"))?,
+                    contract_id: r.read_field(fname!("contractId"))?,
+                    extension_type: r.read_field(fname!("extensionType"))?,
+                    metadata: r.read_field(fname!("metadata"))?,
+                    globals: r.read_field(fname!("globals"))?,
+                    assignments: r.read_field(fname!("assignments"))?,
+                    redeemed: r.read_field(fname!("redeemed"))?,
+                    valencies: r.read_field(fname!("valencies"))?,
+                    validator: r.read_field(fname!("validator"))?,
+                    witness: r.read_field(fname!("witness"))?,
+                })
+            })
+        })
+    }
 }
 
-impl PartialOrd for Extension {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
-}
+impl StrictSerialize for Extension {}
+impl StrictDeserialize for Extension {}
 
 #[derive(Clone, PartialEq, Eq, Debug)]
-#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[derive(StrictType, StrictDumb, StrictEncode)]
 #[strict_type(lib = LIB_NAME_RGB)]
 #[cfg_attr(
     feature = "serde",
@@ -366,16 +446,29 @@ pub struct Transition {
     pub witness: ReservedBytes<2, 0>,
 }
 
-impl StrictSerialize for Transition {}
-impl StrictDeserialize for Transition {}
-
-impl Ord for Transition {
-    fn cmp(&self, other: &Self) -> Ordering { self.id().cmp(&other.id()) }
+impl StrictDecode for Transition {
+    fn strict_decode(reader: &mut impl TypedRead) -> Result<Self, DecodeError> {
+        decode_operation(reader, |reader| {
+            reader.read_struct(|r| {
+                Ok(Transition {
+                    ffv: r.read_field(fname!("ffv"))?,
+                    contract_id: r.read_field(fname!("contractId"))?,
+                    transition_type: r.read_field(fname!("transitionType"))?,
+                    metadata: r.read_field(fname!("metadata"))?,
+                    globals: r.read_field(fname!("globals"))?,
+                    inputs: r.read_field(fname!("inputs"))?,
+                    assignments: r.read_field(fname!("assignments"))?,
+                    valencies: r.read_field(fname!("valencies"))?,
+                    validator: r.read_field(fname!("validator"))?,
+                    witness: r.read_field(fname!("witness"))?,
+                })
+            })
+        })
+    }
 }
 
-impl PartialOrd for Transition {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
-}
+impl StrictSerialize for Transition {}
+impl StrictDeserialize for Transition {}
 
 impl Conceal for Genesis {
     type Concealed = Self;
@@ -454,6 +547,9 @@ impl Operation for Genesis {
     #[inline]
     fn id(&self) -> OpId { self.commit_id() }
 
+    #[inline]
+    fn id_with(&self, algo: DigestAlgo) -> OpId { self.commit().id_with(algo) }
+
     #[inline]
     fn contract_id(&self) -> ContractId { ContractId::from_inner(self.id().into_inner()) }
 
@@ -473,7 +569,7 @@ impl Operation for Genesis {
     fn valencies(&self) -> &Valencies { &self.valencies }
 
     #[inline]
-    fn assignments(&self) -> AssignmentsRef { (&self.assignments).into() }
+    fn assignments(&self) -> AssignmentsRef<'_> { (&self.assignments).into() }
 
     #[inline]
     fn assignments_by_type(&self, t: AssignmentType) -> Option<TypedAssigns<GraphSeal>> {
@@ -496,6 +592,9 @@ impl Operation for Extension {
     #[inline]
     fn id(&self) -> OpId { self.commit_id() }
 
+    #[inline]
+    fn id_with(&self, algo: DigestAlgo) -> OpId { self.commit().id_with(algo) }
+
     #[inline]
     fn contract_id(&self) -> ContractId { self.contract_id }
 
@@ -515,7 +614,7 @@ impl Operation for Extension {
     fn valencies(&self) -> &Valencies { &self.valencies }
 
     #[inline]
-    fn assignments(&self) -> AssignmentsRef { (&self.assignments).into() }
+    fn assignments(&self) -> AssignmentsRef<'_> { (&self.assignments).into() }
 
     #[inline]
     fn assignments_by_type(&self, t: AssignmentType) -> Option<TypedAssigns<GraphSeal>> {
@@ -538,6 +637,9 @@ impl Operation for Transition {
     #[inline]
     fn id(&self) -> OpId { self.commit_id() }
 
+    #[inline]
+    fn id_with(&self, algo: DigestAlgo) -> OpId { self.commit().id_with(algo) }
+
     #[inline]
     fn contract_id(&self) -> ContractId { self.contract_id }
 
@@ -557,7 +659,7 @@ impl Operation for Transition {
     fn valencies(&self) -> &Valencies { &self.valencies }
 
     #[inline]
-    fn assignments(&self) -> AssignmentsRef { (&self.assignments).into() }
+    fn assignments(&self) -> AssignmentsRef<'_> { (&self.assignments).into() }
 
     #[inline]
     fn assignments_by_type(&self, t: AssignmentType) -> Option<TypedAssigns<GraphSeal>> {
@@ -602,6 +704,14 @@ impl<'op> Operation for OpRef<'op> {
         }
     }
 
+    fn id_with(&self, algo: DigestAlgo) -> OpId {
+        match self {
+            OpRef::Genesis(op) => op.id_with(algo),
+            OpRef::Transition(op) => op.id_with(algo),
+            OpRef::Extension(op) => op.id_with(algo),
+        }
+    }
+
     fn contract_id(&self) -> ContractId {
         match self {
             OpRef::Genesis(op) => op.contract_id(),
@@ -679,10 +789,14 @@ impl<'op> Operation for OpRef<'op> {
 mod test {
     use std::str::FromStr;
 
+    use amplify::confinement::SmallBlob;
     use amplify::ByteArray;
     use baid64::DisplayBaid64;
+    use bp::seals::txout::CloseMethod;
+    use strict_encoding::StrictDumb;
 
     use super::*;
+    use crate::MetaValue;
 
     #[test]
     fn contract_id_display() {
@@ -722,4 +836,32 @@ mod test {
             ContractId::from_str("rgb:bGxs-bGxs-bGxsbGx-sbGxsbG-xsbGxsb-GxsbGxs-bGxsbGw").is_ok()
         );
     }
+
+    fn genesis_with_close_methods(meta: &[u8]) -> Genesis {
+        let mut genesis = Genesis::strict_dumb();
+        let value = MetaValue::from(SmallBlob::try_from(meta.to_vec()).unwrap());
+        genesis
+            .metadata
+            .add_value(schema::MetaType::CLOSE_METHODS, value)
+            .unwrap();
+        genesis
+    }
+
+    #[test]
+    fn genesis_close_methods() {
+        assert_eq!(Genesis::strict_dumb().close_methods(), Some(CloseMethodSet::ALL));
+        assert_eq!(
+            genesis_with_close_methods(&[CloseMethodSet::OPRET]).close_methods(),
+            Some(CloseMethodSet::with([CloseMethod::OpretFirst]))
+        );
+        assert_eq!(
+            genesis_with_close_methods(&[CloseMethodSet::TAPRET]).close_methods(),
+            Some(CloseMethodSet::with([CloseMethod::TapretFirst]))
+        );
+        // Malformed metadata
+        assert_eq!(genesis_with_close_methods(&[]).close_methods(), None);
+        assert_eq!(genesis_with_close_methods(&[0x00]).close_methods(), None);
+        assert_eq!(genesis_with_close_methods(&[0x08]).close_methods(), None);
+        assert_eq!(genesis_with_close_methods(&[0x01, 0x02]).close_methods(), None);
+    }
 }
@@ -0,0 +1,691 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Oracle feeds: global state attested by a rotating set of oracle keys.
+//!
+//! Contract genesis may declare oracle feeds in its
+//! [`schema::MetaType::ORACLE_FEEDS`] metadata. Each feed pairs a global
+//! state type holding attestations with a global state type holding the
+//! oracle set, i.e. the BIP-340 keys which are allowed to sign the
+//! attestations.
+//!
+//! The oracle set is encoded as a single-byte number of keys, followed by the
+//! serialized x-only keys in the lexicographic order. The initial oracle set
+//! is defined by the genesis; each subsequent set is a rotation and must be
+//! followed by a signature of a key from the preceding set (see
+//! [`OracleSet::rotate`]). Attestation is an arbitrary payload followed by the
+//! signer key and its signature over the payload (see
+//! [`OracleAttestation::sign`]).
+//!
+//! Each operation is checked by the validator to carry well-formed and
+//! correctly signed attestations and rotations with [`OracleFeed::check_op`].
+//! The validator then tracks the current oracle set over the operations of the
+//! consignment in their consensus order with [`OracleTracker`], requiring each
+//! attestation and rotation to be signed by a key from the set current at its
+//! position. Since the order is defined by the witness positions, attestations
+//! and rotations may be defined only by state transitions with a known witness
+//! position.
+//!
+//! Rotations which are not a part of the consignment are not known to the
+//! validator, so contracts must place rotations into the history of the
+//! operations carrying attestations, for instance by requiring both to spend
+//! a right controlled by the oracles. The whole history of the feed known to
+//! the wallet is verified with [`ContractHistory::verify_oracle_feed`].
+//!
+//! Contract scripts can verify attestations and rotations with the `ora` and
+//! `ors` VM instructions.
+
+use std::collections::BTreeSet;
+
+use amplify::confinement::{Confined, SmallBlob};
+use amplify::{ByteArray, Wrapper};
+use bp::secp256k1::{schnorr, Keypair, Message, Secp256k1, XOnlyPublicKey};
+use commit_verify::{DigestExt, Sha256};
+
+use crate::{
+    schema, ContractHistory, ContractId, DataState, Genesis, GlobalOrd, GlobalState,
+    GlobalStateType, MetaValue,
+};
+
+pub const ORACLE_ATTESTATION_TAG: &[u8] = b"urn:lnp-bp:rgb:oracle-attestation#2024-05-20";
+pub const ORACLE_ROTATION_TAG: &[u8] = b"urn:lnp-bp:rgb:oracle-rotation#2024-05-20";
+
+/// Maximum number of keys in an oracle set.
+pub const ORACLE_SET_MAX_KEYS: usize = 16;
+
+const SIGNATURE_LEN: usize = 32 + 64;
+
+/// Oracle feed declared by the contract genesis.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display("{attestations}:{oracle_set}")]
+pub struct OracleFeed {
+    /// Global state type holding attestations.
+    pub attestations: GlobalStateType,
+    /// Global state type holding the oracle set.
+    pub oracle_set: GlobalStateType,
+}
+
+/// Errors of the oracle feed state.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(doc_comments)]
+pub enum OracleError {
+    /// malformed oracle set of the global state type {0}.
+    InvalidSet(GlobalStateType),
+
+    /// malformed attestation of the global state type {0}.
+    InvalidAttestation(GlobalStateType),
+
+    /// invalid signature by key {1} in the global state type {0}.
+    InvalidSignature(GlobalStateType, XOnlyPublicKey),
+
+    /// genesis defines multiple or signed oracle sets of the global state type
+    /// {0}.
+    InvalidInitialSet(GlobalStateType),
+
+    /// oracle set of the global state type {0} is rotated without a signature.
+    UnsignedRotation(GlobalStateType),
+
+    /// attestation of the global state type {0} precedes the oracle set.
+    NoOracleSet(GlobalStateType),
+
+    /// key {1} signing an item of the global state type {0} is not a part of
+    /// the current oracle set.
+    UnknownSigner(GlobalStateType, XOnlyPublicKey),
+
+    /// item of the global state type {0} is defined by an operation which
+    /// position in the consensus order is not known.
+    UnknownPosition(GlobalStateType),
+}
+
+/// Signature made by an oracle key.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct OracleSignature {
+    pub signer: XOnlyPublicKey,
+    pub signature: schnorr::Signature,
+}
+
+impl OracleSignature {
+    fn sign(msg: &Message, keypair: &Keypair) -> Self {
+        let secp = Secp256k1::signing_only();
+        OracleSignature {
+            signer: keypair.x_only_public_key().0,
+            signature: secp.sign_schnorr_no_aux_rand(msg, keypair),
+        }
+    }
+
+    fn verify(&self, msg: &Message, ty: GlobalStateType) -> Result<(), OracleError> {
+        Secp256k1::verification_only()
+            .verify_schnorr(&self.signature, msg, &self.signer)
+            .map_err(|_| OracleError::InvalidSignature(ty, self.signer))
+    }
+
+    fn parse(data: &[u8], err: OracleError) -> Result<Self, OracleError> {
+        let (signer, signature) = data.split_at(32);
+        Ok(OracleSignature {
+            signer: XOnlyPublicKey::from_slice(signer).map_err(|_| err)?,
+            signature: schnorr::Signature::from_slice(signature).map_err(|_| err)?,
+        })
+    }
+
+    fn serialize(&self) -> [u8; SIGNATURE_LEN] {
+        let mut data = [0u8; SIGNATURE_LEN];
+        data[..32].copy_from_slice(&self.signer.serialize());
+        data[32..].copy_from_slice(&self.signature.serialize());
+        data
+    }
+}
+
+fn message(tag: &[u8], contract_id: ContractId, ty: GlobalStateType, data: &[u8]) -> Message {
+    let mut hasher = Sha256::default();
+    hasher.input_raw(tag);
+    hasher.input_raw(&contract_id.to_byte_array());
+    hasher.input_raw(&ty.to_inner().to_le_bytes());
+    hasher.input_raw(data);
+    Message::from_digest(hasher.finish())
+}
+
+/// Set of keys allowed to sign attestations.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct OracleSet {
+    keys: Vec<XOnlyPublicKey>,
+    rotation: Option<OracleSignature>,
+}
+
+impl OracleSet {
+    /// Constructs initial oracle set, which is defined by the genesis.
+    ///
+    /// Returns `None` if there are no keys or more than
+    /// [`ORACLE_SET_MAX_KEYS`] keys.
+    pub fn initial(keys: impl IntoIterator<Item = XOnlyPublicKey>) -> Option<Self> {
+        let mut keys = keys.into_iter().collect::<Vec<_>>();
+        keys.sort_by_key(XOnlyPublicKey::serialize);
+        keys.dedup();
+        if keys.is_empty() || keys.len() > ORACLE_SET_MAX_KEYS {
+            return None;
+        }
+        Some(OracleSet {
+            keys,
+            rotation: None,
+        })
+    }
+
+    /// Constructs oracle set replacing the current one, signed by a key from
+    /// the current set.
+    ///
+    /// Returns `None` if there are no keys or more than
+    /// [`ORACLE_SET_MAX_KEYS`] keys.
+    pub fn rotate(
+        keys: impl IntoIterator<Item = XOnlyPublicKey>,
+        contract_id: ContractId,
+        ty: GlobalStateType,
+        keypair: &Keypair,
+    ) -> Option<Self> {
+        let mut set = Self::initial(keys)?;
+        let msg = message(ORACLE_ROTATION_TAG, contract_id, ty, &set.keys_data());
+        set.rotation = Some(OracleSignature::sign(&msg, keypair));
+        Some(set)
+    }
+
+    pub fn keys(&self) -> &[XOnlyPublicKey] { &self.keys }
+
+    pub fn contains(&self, key: XOnlyPublicKey) -> bool { self.keys.contains(&key) }
+
+    /// Returns signature of the rotation, which is absent for the initial
+    /// set.
+    pub fn rotation(&self) -> Option<OracleSignature> { self.rotation }
+
+    fn keys_data(&self) -> Vec<u8> {
+        let mut data = vec![self.keys.len() as u8];
+        data.extend(self.keys.iter().flat_map(XOnlyPublicKey::serialize));
+        data
+    }
+
+    /// Verifies signature of the rotation, if present.
+    pub fn verify(&self, contract_id: ContractId, ty: GlobalStateType) -> Result<(), OracleError> {
+        let Some(rotation) = self.rotation else {
+            return Ok(());
+        };
+        let msg = message(ORACLE_ROTATION_TAG, contract_id, ty, &self.keys_data());
+        rotation.verify(&msg, ty)
+    }
+
+    /// Verifies that the attestation of the given global state type is
+    /// correctly signed by a key from the set.
+    pub fn verify_attestation(
+        &self,
+        attestation: &OracleAttestation,
+        contract_id: ContractId,
+        ty: GlobalStateType,
+    ) -> Result<(), OracleError> {
+        if !self.contains(attestation.signer()) {
+            return Err(OracleError::UnknownSigner(ty, attestation.signer()));
+        }
+        attestation.verify(contract_id, ty)
+    }
+
+    /// Verifies that the next oracle set of the given global state type is a
+    /// rotation correctly signed by a key from the set.
+    pub fn verify_rotation(
+        &self,
+        next: &OracleSet,
+        contract_id: ContractId,
+        ty: GlobalStateType,
+    ) -> Result<(), OracleError> {
+        let Some(rotation) = next.rotation else {
+            return Err(OracleError::UnsignedRotation(ty));
+        };
+        if !self.contains(rotation.signer) {
+            return Err(OracleError::UnknownSigner(ty, rotation.signer));
+        }
+        next.verify(contract_id, ty)
+    }
+
+    /// Parses oracle set from the global state of the given type.
+    pub fn from_data(data: &DataState, ty: GlobalStateType) -> Result<Self, OracleError> {
+        Self::from_slice(data.as_slice(), ty)
+    }
+
+    /// Parses oracle set of the given global state type from its serialized
+    /// data.
+    pub fn from_slice(data: &[u8], ty: GlobalStateType) -> Result<Self, OracleError> {
+        let err = OracleError::InvalidSet(ty);
+        let Some((&len, rest)) = data.split_first() else {
+            return Err(err);
+        };
+        let len = len as usize;
+        if len == 0 || len > ORACLE_SET_MAX_KEYS || rest.len() < len * 32 {
+            return Err(err);
+        }
+        let (keys, rotation) = rest.split_at(len * 32);
+        let keys = keys
+            .chunks(32)
+            .map(XOnlyPublicKey::from_slice)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| err)?;
+        if keys
+            .windows(2)
+            .any(|pair| pair[0].serialize() >= pair[1].serialize())
+        {
+            return Err(err);
+        }
+        let rotation = match rotation.len() {
+            0 => None,
+            SIGNATURE_LEN => Some(OracleSignature::parse(rotation, err)?),
+            _ => return Err(err),
+        };
+        Ok(OracleSet { keys, rotation })
+    }
+
+    /// Serializes oracle set into the global state data.
+    pub fn to_data(&self) -> DataState {
+        let mut data = self.keys_data();
+        if let Some(rotation) = self.rotation {
+            data.extend(rotation.serialize());
+        }
+        DataState::from(SmallBlob::try_from(data).expect("at most 1 + 16 * 32 + 96 bytes"))
+    }
+}
+
+/// Attestation signed by an oracle key.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct OracleAttestation {
+    pub payload: Vec<u8>,
+    pub signature: OracleSignature,
+}
+
+impl OracleAttestation {
+    /// Signs the payload with the oracle key.
+    pub fn sign(
+        payload: impl Into<Vec<u8>>,
+        contract_id: ContractId,
+        ty: GlobalStateType,
+        keypair: &Keypair,
+    ) -> Self {
+        let payload = payload.into();
+        let msg = message(ORACLE_ATTESTATION_TAG, contract_id, ty, &payload);
+        OracleAttestation {
+            signature: OracleSignature::sign(&msg, keypair),
+            payload,
+        }
+    }
+
+    pub fn signer(&self) -> XOnlyPublicKey { self.signature.signer }
+
+    /// Verifies the attestation signature, without checking whether the
+    /// signer is a part of the oracle set.
+    pub fn verify(&self, contract_id: ContractId, ty: GlobalStateType) -> Result<(), OracleError> {
+        let msg = message(ORACLE_ATTESTATION_TAG, contract_id, ty, &self.payload);
+        self.signature.verify(&msg, ty)
+    }
+
+    /// Parses attestation from the global state of the given type.
+    pub fn from_data(data: &DataState, ty: GlobalStateType) -> Result<Self, OracleError> {
+        Self::from_slice(data.as_slice(), ty)
+    }
+
+    /// Parses attestation of the given global state type from its serialized
+    /// data.
+    pub fn from_slice(data: &[u8], ty: GlobalStateType) -> Result<Self, OracleError> {
+        let err = OracleError::InvalidAttestation(ty);
+        if data.len() < SIGNATURE_LEN {
+            return Err(err);
+        }
+        let (payload, signature) = data.split_at(data.len() - SIGNATURE_LEN);
+        Ok(OracleAttestation {
+            payload: payload.to_vec(),
+            signature: OracleSignature::parse(signature, err)?,
+        })
+    }
+
+    /// Serializes attestation into the global state data.
+    ///
+    /// Returns `None` if the payload is too large to fit the global state.
+    pub fn to_data(&self) -> Option<DataState> {
+        let mut data = self.payload.clone();
+        data.extend(self.signature.serialize());
+        SmallBlob::try_from(data).ok().map(DataState::from)
+    }
+}
+
+impl OracleFeed {
+    /// Checks that the global state of an operation contains well-formed and
+    /// correctly signed attestations and oracle sets. Genesis must define a
+    /// single unsigned oracle set, while other operations may only rotate the
+    /// set with a signature.
+    ///
+    /// Membership of the signers in the oracle set is checked over the
+    /// operations in their consensus order (see [`OracleTracker`]).
+    pub fn check_op(
+        &self,
+        contract_id: ContractId,
+        is_genesis: bool,
+        globals: &GlobalState,
+    ) -> Result<(), OracleError> {
+        let sets = globals
+            .get(&self.oracle_set)
+            .into_iter()
+            .flat_map(|v| v.iter());
+        let mut count = 0usize;
+        for data in sets {
+            let set = OracleSet::from_data(data, self.oracle_set)?;
+            count += 1;
+            match (is_genesis, set.rotation.is_some()) {
+                (true, true) => return Err(OracleError::InvalidInitialSet(self.oracle_set)),
+                (false, false) => return Err(OracleError::UnsignedRotation(self.oracle_set)),
+                _ => set.verify(contract_id, self.oracle_set)?,
+            }
+        }
+        if is_genesis && count > 1 {
+            return Err(OracleError::InvalidInitialSet(self.oracle_set));
+        }
+        let attestations = globals
+            .get(&self.attestations)
+            .into_iter()
+            .flat_map(|v| v.iter());
+        for data in attestations {
+            OracleAttestation::from_data(data, self.attestations)?
+                .verify(contract_id, self.attestations)?;
+        }
+        Ok(())
+    }
+}
+
+impl Genesis {
+    /// Returns oracle feeds declared by the [`schema::MetaType::ORACLE_FEEDS`]
+    /// metadata. If the metadata is absent, the contract has no oracle feeds.
+    ///
+    /// Returns `None` if the metadata value is malformed or a global state
+    /// type is used by more than one feed.
+    pub fn oracle_feeds(&self) -> Option<BTreeSet<OracleFeed>> {
+        let Some(meta) = self.metadata.get(&schema::MetaType::ORACLE_FEEDS) else {
+            return Some(empty!());
+        };
+        let data = meta.as_slice();
+        if data.is_empty() || data.len() % 4 != 0 {
+            return None;
+        }
+        let types = data
+            .chunks(2)
+            .map(|chunk| GlobalStateType::with(u16::from_le_bytes([chunk[0], chunk[1]])))
+            .collect::<Vec<_>>();
+        if types.iter().collect::<BTreeSet<_>>().len() != types.len() {
+            return None;
+        }
+        let feeds = types
+            .chunks(2)
+            .map(|pair| OracleFeed {
+                attestations: pair[0],
+                oracle_set: pair[1],
+            })
+            .collect();
+        Some(feeds)
+    }
+}
+
+/// Constructs value for the [`schema::MetaType::ORACLE_FEEDS`] genesis
+/// metadata.
+///
+/// Returns `None` if there are no feeds.
+pub fn oracle_feeds_meta(feeds: &BTreeSet<OracleFeed>) -> Option<MetaValue> {
+    if feeds.is_empty() {
+        return None;
+    }
+    let data = feeds
+        .iter()
+        .flat_map(|feed| [feed.attestations, feed.oracle_set])
+        .flat_map(|ty| ty.to_inner().to_le_bytes())
+        .collect::<Vec<_>>();
+    Some(MetaValue::from(Confined::try_from(data).expect("at most 2^18 bytes")))
+}
+
+/// Tracker of the oracle set current for a feed, which must be provided with
+/// the feed items in their consensus order.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct OracleTracker {
+    feed: OracleFeed,
+    contract_id: ContractId,
+    current: Option<OracleSet>,
+}
+
+impl OracleTracker {
+    pub fn new(feed: OracleFeed, contract_id: ContractId) -> Self {
+        OracleTracker {
+            feed,
+            contract_id,
+            current: None,
+        }
+    }
+
+    pub fn feed(&self) -> OracleFeed { self.feed }
+
+    /// Returns the oracle set current at the position of the last processed
+    /// item.
+    pub fn current(&self) -> Option<&OracleSet> { self.current.as_ref() }
+
+    /// Processes an attestation, checking that it is signed by a key from
+    /// the current oracle set.
+    pub fn push_attestation(&mut self, data: &DataState) -> Result<(), OracleError> {
+        let ty = self.feed.attestations;
+        let attestation = OracleAttestation::from_data(data, ty)?;
+        let Some(set) = &self.current else {
+            return Err(OracleError::NoOracleSet(ty));
+        };
+        set.verify_attestation(&attestation, self.contract_id, ty)
+    }
+
+    /// Processes an oracle set, which must be the initial set if there is no
+    /// current set, or a rotation signed by a key from the current set
+    /// otherwise.
+    pub fn push_set(&mut self, data: &DataState) -> Result<(), OracleError> {
+        let ty = self.feed.oracle_set;
+        let set = OracleSet::from_data(data, ty)?;
+        match &self.current {
+            None if set.rotation.is_some() => return Err(OracleError::InvalidInitialSet(ty)),
+            None => {}
+            Some(prev) => prev.verify_rotation(&set, self.contract_id, ty)?,
+        }
+        self.current = Some(set);
+        Ok(())
+    }
+
+    /// Processes global state of the feed defined by a single operation. Its
+    /// attestations are checked against the oracle set current before the
+    /// operation.
+    pub fn push_op(&mut self, globals: &GlobalState) -> Result<(), OracleError> {
+        for data in globals
+            .get(&self.feed.attestations)
+            .into_iter()
+            .flat_map(|v| v.iter())
+        {
+            self.push_attestation(data)?;
+        }
+        for data in globals
+            .get(&self.feed.oracle_set)
+            .into_iter()
+            .flat_map(|v| v.iter())
+        {
+            self.push_set(data)?;
+        }
+        Ok(())
+    }
+}
+
+impl ContractHistory {
+    /// Returns the latest oracle set of the feed, if any.
+    pub fn oracle_set(&self, feed: OracleFeed) -> Option<Result<OracleSet, OracleError>> {
+        let (_, data) = self.global_state(feed.oracle_set)?.last()?;
+        Some(OracleSet::from_data(data, feed.oracle_set))
+    }
+
+    /// Verifies that each attestation of the feed is signed by a key from
+    /// the oracle set current at the attestation position, and that each
+    /// rotation of the oracle set is signed by a key from the preceding set.
+    ///
+    /// Items are processed in the consensus order; an attestation at the same
+    /// position as an oracle set is checked against the preceding set.
+    pub fn verify_oracle_feed(&self, feed: OracleFeed) -> Result<(), OracleError> {
+        let mut items = Vec::<(GlobalOrd, bool, &DataState)>::new();
+        if let Some(state) = self.global_state(feed.attestations) {
+            items.extend(state.iter().map(|(ord, data)| (ord, false, data)));
+        }
+        if let Some(state) = self.global_state(feed.oracle_set) {
+            items.extend(state.iter().map(|(ord, data)| (ord, true, data)));
+        }
+        items.sort_by_key(|(ord, is_set, _)| (*ord, *is_set));
+
+        let mut tracker = OracleTracker::new(feed, self.contract_id());
+        for (_, is_set, data) in items {
+            match is_set {
+                true => tracker.push_set(data)?,
+                false => tracker.push_attestation(data)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bp::secp256k1::SecretKey;
+    use bp::Txid;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{Extension, WitnessAnchor, WitnessOrd, WitnessPos, XChain};
+
+    #[test]
+    fn rotation_and_attestation() {
+        let secp = Secp256k1::new();
+        let keypair = |byte: u8| {
+            Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[byte; 32]).unwrap())
+        };
+        let (first, second) = (keypair(0x11), keypair(0x22));
+        let contract_id = ContractId::from([0xA1; 32]);
+        let feed = OracleFeed {
+            attestations: GlobalStateType::with(10),
+            oracle_set: GlobalStateType::with(11),
+        };
+
+        let initial = OracleSet::initial([first.x_only_public_key().0]).unwrap();
+        let decoded = OracleSet::from_data(&initial.to_data(), feed.oracle_set).unwrap();
+        assert_eq!(decoded, initial);
+        assert_eq!(decoded.verify(contract_id, feed.oracle_set), Ok(()));
+
+        let keys = [second.x_only_public_key().0, first.x_only_public_key().0];
+        let rotated = OracleSet::rotate(keys, contract_id, feed.oracle_set, &first).unwrap();
+        let decoded = OracleSet::from_data(&rotated.to_data(), feed.oracle_set).unwrap();
+        assert_eq!(decoded, rotated);
+        assert!(initial.contains(decoded.rotation().unwrap().signer));
+        assert_eq!(decoded.verify(contract_id, feed.oracle_set), Ok(()));
+        assert!(decoded
+            .verify(ContractId::from([0xB2; 32]), feed.oracle_set)
+            .is_err());
+
+        let attestation =
+            OracleAttestation::sign(b"price:42".to_vec(), contract_id, feed.attestations, &second);
+        let data = attestation.to_data().unwrap();
+        let decoded = OracleAttestation::from_data(&data, feed.attestations).unwrap();
+        assert_eq!(decoded, attestation);
+        assert_eq!(decoded.verify(contract_id, feed.attestations), Ok(()));
+        assert!(!initial.contains(decoded.signer()));
+        assert!(rotated.contains(decoded.signer()));
+
+        let feeds = bset! { feed };
+        let mut genesis = Genesis::strict_dumb();
+        genesis
+            .metadata
+            .add_value(schema::MetaType::ORACLE_FEEDS, oracle_feeds_meta(&feeds).unwrap())
+            .unwrap();
+        genesis
+            .globals
+            .add_state(feed.oracle_set, initial.to_data())
+            .unwrap();
+        assert_eq!(genesis.oracle_feeds(), Some(feeds));
+        assert_eq!(feed.check_op(contract_id, true, &genesis.globals), Ok(()));
+        assert_eq!(
+            feed.check_op(contract_id, false, &genesis.globals),
+            Err(OracleError::UnsignedRotation(feed.oracle_set))
+        );
+
+        let mut history = ContractHistory::with(genesis.schema_id, contract_id, &genesis);
+        let mut extension = Extension::strict_dumb();
+        extension
+            .globals
+            .add_state(feed.attestations, data)
+            .unwrap();
+        assert_eq!(feed.check_op(contract_id, false, &extension.globals), Ok(()));
+        let anchor = |height: u32| WitnessAnchor {
+            witness_ord: WitnessOrd::OnChain(
+                WitnessPos::new(height, 1700000000 + height as i64).unwrap(),
+            ),
+            witness_id: XChain::Bitcoin(Txid::from([height as u8; 32])),
+        };
+        history.add_extension(&extension, anchor(100));
+        assert_eq!(
+            history.verify_oracle_feed(feed),
+            Err(OracleError::UnknownSigner(feed.attestations, second.x_only_public_key().0))
+        );
+
+        let mut extension = Extension::strict_dumb();
+        extension
+            .globals
+            .add_state(feed.oracle_set, rotated.to_data())
+            .unwrap();
+        history.add_extension(&extension, anchor(90));
+        assert_eq!(history.verify_oracle_feed(feed), Ok(()));
+        assert_eq!(history.oracle_set(feed), Some(Ok(rotated.clone())));
+
+        let data = attestation.to_data().unwrap();
+        let mut tracker = OracleTracker::new(feed, contract_id);
+        assert_eq!(
+            tracker.push_attestation(&data),
+            Err(OracleError::NoOracleSet(feed.attestations))
+        );
+        assert_eq!(tracker.push_op(&genesis.globals), Ok(()));
+        assert_eq!(
+            tracker.push_attestation(&data),
+            Err(OracleError::UnknownSigner(feed.attestations, second.x_only_public_key().0))
+        );
+        assert_eq!(tracker.push_op(&extension.globals), Ok(()));
+        assert_eq!(tracker.current(), Some(&rotated));
+        assert_eq!(tracker.push_attestation(&data), Ok(()));
+        assert_eq!(
+            tracker.push_set(&initial.to_data()),
+            Err(OracleError::UnsignedRotation(feed.oracle_set))
+        );
+    }
+}
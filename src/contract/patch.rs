@@ -0,0 +1,286 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Patches for the collaborative construction of state transitions.
+//!
+//! When several wallets construct a single transition together (for
+//! instance, in payjoin-like protocols), each party contributes its inputs,
+//! assignments, metadata and global state to the unsigned transition. The
+//! contributions are exchanged as strict-encoded [`TransitionPatch`]es, each
+//! of which refers to the id of the transition it edits, such that parties
+//! apply the patches in the same order and detect concurrent edits.
+
+use amplify::confinement::{self, TinyVec};
+use strict_encoding::{StrictDeserialize, StrictSerialize};
+
+use crate::schema::{MetaType, TransitionType};
+use crate::{
+    AssignmentType, ContractId, DataState, GlobalStateType, GraphSeal, Input, MetaValue, OpId,
+    Operation, Opout, Schema, Transition, TypedAssigns, LIB_NAME_RGB,
+};
+
+/// Single edit of a transition.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB, tags = custom, dumb = Self::AddInput(strict_dumb!()))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum PatchOp {
+    /// Adds input spending the given output.
+    #[strict_type(tag = 0x00)]
+    AddInput(Input),
+
+    /// Appends assignments of the given type.
+    #[strict_type(tag = 0x01)]
+    AddAssignments(AssignmentType, TypedAssigns<GraphSeal>),
+
+    /// Sets metadata value. Setting a value already set to a different one
+    /// is a conflict.
+    #[strict_type(tag = 0x02)]
+    SetMetadata(MetaType, MetaValue),
+
+    /// Appends global state value of the given type.
+    #[strict_type(tag = 0x03)]
+    AddGlobal(GlobalStateType, DataState),
+}
+
+/// Errors applying transition patches.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum PatchError {
+    /// patch is made for contract {expected}, while the transition belongs to
+    /// contract {actual}.
+    ContractMismatch {
+        expected: ContractId,
+        actual: ContractId,
+    },
+
+    /// patch is made for transition type {expected}, while the transition
+    /// has type {actual}.
+    TypeMismatch {
+        expected: TransitionType,
+        actual: TransitionType,
+    },
+
+    /// patch edits transition {expected}, while it is applied to transition
+    /// {actual}; patches must be applied in order.
+    BaseMismatch { expected: OpId, actual: OpId },
+
+    /// transition type {0} is not defined by the schema.
+    UnknownTransitionType(TransitionType),
+
+    /// input {0} is already present in the transition.
+    InputDuplicate(Opout),
+
+    /// assignment type {0} is not defined for the transition by the schema.
+    UnknownAssignmentType(AssignmentType),
+
+    /// assignments of type {0} contain state of a wrong type.
+    StateTypeMismatch(AssignmentType),
+
+    /// metadata type {0} is not defined for the transition by the schema.
+    UnknownMetaType(MetaType),
+
+    /// metadata value of type {0} is already set to a different value.
+    MetadataConflict(MetaType),
+
+    /// global state type {0} is not defined for the transition by the schema.
+    UnknownGlobalType(GlobalStateType),
+
+    /// patch makes the transition exceed the consensus limits on the number of
+    /// items.
+    TooManyItems,
+}
+
+impl From<confinement::Error> for PatchError {
+    fn from(_: confinement::Error) -> Self { PatchError::TooManyItems }
+}
+
+/// Patch editing an unsigned transition.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct TransitionPatch {
+    pub contract_id: ContractId,
+    pub transition_type: TransitionType,
+    /// Id of the transition the patch edits.
+    pub base: OpId,
+    pub ops: TinyVec<PatchOp>,
+}
+
+impl StrictSerialize for TransitionPatch {}
+impl StrictDeserialize for TransitionPatch {}
+
+impl TransitionPatch {
+    /// Constructs empty patch editing the transition.
+    pub fn with(transition: &Transition) -> Self {
+        TransitionPatch {
+            contract_id: transition.contract_id,
+            transition_type: transition.transition_type,
+            base: transition.id(),
+            ops: empty!(),
+        }
+    }
+
+    pub fn push(&mut self, op: PatchOp) -> Result<(), PatchError> {
+        self.ops.push(op).map_err(PatchError::from)
+    }
+
+    /// Checks the patch against the contract schema, without applying it.
+    pub fn validate(&self, schema: &Schema) -> Result<(), PatchError> {
+        let transition_schema = schema
+            .transitions
+            .get(&self.transition_type)
+            .ok_or(PatchError::UnknownTransitionType(self.transition_type))?;
+        for op in &self.ops {
+            match op {
+                PatchOp::AddInput(_) => {}
+                PatchOp::AddAssignments(ty, assigns) => {
+                    if !transition_schema.assignments.contains_key(ty) {
+                        return Err(PatchError::UnknownAssignmentType(*ty));
+                    }
+                    let state_type = schema.owned_types.get(ty).map(|ss| ss.state_type());
+                    if state_type != Some(assigns.state_type()) {
+                        return Err(PatchError::StateTypeMismatch(*ty));
+                    }
+                }
+                PatchOp::SetMetadata(ty, _) if !transition_schema.metadata.contains(ty) => {
+                    return Err(PatchError::UnknownMetaType(*ty));
+                }
+                PatchOp::SetMetadata(..) => {}
+                PatchOp::AddGlobal(ty, _) if !transition_schema.globals.contains_key(ty) => {
+                    return Err(PatchError::UnknownGlobalType(*ty));
+                }
+                PatchOp::AddGlobal(..) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the patch to the transition.
+    ///
+    /// The transition is left unchanged if the patch can't be applied.
+    pub fn apply(&self, transition: &mut Transition) -> Result<(), PatchError> {
+        if transition.contract_id != self.contract_id {
+            return Err(PatchError::ContractMismatch {
+                expected: self.contract_id,
+                actual: transition.contract_id,
+            });
+        }
+        if transition.transition_type != self.transition_type {
+            return Err(PatchError::TypeMismatch {
+                expected: self.transition_type,
+                actual: transition.transition_type,
+            });
+        }
+        let actual = transition.id();
+        if actual != self.base {
+            return Err(PatchError::BaseMismatch {
+                expected: self.base,
+                actual,
+            });
+        }
+        let mut patched = transition.clone();
+        for op in &self.ops {
+            apply_op(&mut patched, op)?;
+        }
+        *transition = patched;
+        Ok(())
+    }
+}
+
+fn apply_op(transition: &mut Transition, op: &PatchOp) -> Result<(), PatchError> {
+    match op {
+        PatchOp::AddInput(input) => {
+            if transition.inputs.contains(input) {
+                return Err(PatchError::InputDuplicate(input.prev_out));
+            }
+            transition.inputs.push(*input)?;
+        }
+        PatchOp::AddAssignments(ty, assigns) => {
+            let Some(existing) = transition.assignments.get_mut(ty) else {
+                transition.assignments.insert(*ty, assigns.clone())?;
+                return Ok(());
+            };
+            match (existing, assigns) {
+                (TypedAssigns::Declarative(a), TypedAssigns::Declarative(b)) => {
+                    a.extend(b.iter().cloned())?
+                }
+                (TypedAssigns::Fungible(a), TypedAssigns::Fungible(b)) => {
+                    a.extend(b.iter().cloned())?
+                }
+                (TypedAssigns::Structured(a), TypedAssigns::Structured(b)) => {
+                    a.extend(b.iter().cloned())?
+                }
+                (TypedAssigns::Attachment(a), TypedAssigns::Attachment(b)) => {
+                    a.extend(b.iter().cloned())?
+                }
+                _ => return Err(PatchError::StateTypeMismatch(*ty)),
+            }
+        }
+        PatchOp::SetMetadata(ty, value) => match transition.metadata.get(ty) {
+            Some(existing) if existing == value => {}
+            Some(_) => return Err(PatchError::MetadataConflict(*ty)),
+            None => {
+                transition.metadata.insert(*ty, value.clone())?;
+            }
+        },
+        PatchOp::AddGlobal(ty, state) => transition.globals.add_state(*ty, state.clone())?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn metadata_conflict() {
+        let mut transition = Transition::strict_dumb();
+        let ty = MetaType::with(1);
+        let value = MetaValue::from(confinement::Confined::try_from(vec![1u8]).unwrap());
+        let mut patch = TransitionPatch::with(&transition);
+        patch.push(PatchOp::SetMetadata(ty, value.clone())).unwrap();
+        patch.apply(&mut transition).unwrap();
+        assert_eq!(transition.metadata.get(&ty), Some(&value));
+
+        // The patch was made for the transition before it was changed
+        assert!(matches!(patch.apply(&mut transition), Err(PatchError::BaseMismatch { .. })));
+
+        let other = MetaValue::from(confinement::Confined::try_from(vec![2u8]).unwrap());
+        let mut patch = TransitionPatch::with(&transition);
+        patch.push(PatchOp::SetMetadata(ty, other)).unwrap();
+        let before = transition.clone();
+        assert_eq!(patch.apply(&mut transition), Err(PatchError::MetadataConflict(ty)));
+        assert_eq!(transition, before);
+    }
+}
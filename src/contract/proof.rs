@@ -0,0 +1,326 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contract state root and inclusion proofs for individual state items.
+//!
+//! State root is a merkle root over all allocations and global state items
+//! known to the [`ContractHistory`], taken in their consensus order. It can
+//! be published by an indexer, such that light clients are able to verify
+//! presence of a specific allocation or global state item with a compact
+//! [`StateProof`] without holding the full contract state.
+
+use amplify::{ByteArray, Bytes32};
+use commit_verify::{CommitEncode, CommitEngine, CommitId, DigestExt, Sha256, StrictHash};
+
+use crate::{
+    ContractHistory, DataState, GlobalOrd, GlobalStateType, OutputAssignment, RevealedAttach,
+    RevealedData, RevealedValue, VoidState,
+};
+
+pub const STATE_ROOT_NODE_TAG: &[u8] = b"urn:lnp-bp:rgb:state-node#2024-05-20";
+pub const STATE_ROOT_EMPTY_TAG: &[u8] = b"urn:lnp-bp:rgb:state-empty#2024-05-20";
+
+/// Merkle root committing to all state items of a contract.
+#[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From)]
+#[wrapper(Deref, BorrowSlice, Display, Hex, Index, RangeOps)]
+pub struct StateRoot(
+    #[from]
+    #[from([u8; 32])]
+    Bytes32,
+);
+
+/// Individual item of the contract state, which is a leaf of the state merkle
+/// tree.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum StateLeaf {
+    Global {
+        ty: GlobalStateType,
+        ord: GlobalOrd,
+        state: DataState,
+    },
+    Rights(OutputAssignment<VoidState>),
+    Fungible(OutputAssignment<RevealedValue>),
+    Data(OutputAssignment<RevealedData>),
+    Attach(OutputAssignment<RevealedAttach>),
+}
+
+impl CommitEncode for StateLeaf {
+    type CommitmentId = StrictHash;
+
+    fn commit_encode(&self, e: &mut CommitEngine) {
+        match self {
+            StateLeaf::Global { ty, ord, state } => {
+                e.commit_to_serialized(&0u8);
+                e.commit_to_serialized(ty);
+                e.commit_to_serialized(ord);
+                e.commit_to_serialized(state);
+            }
+            StateLeaf::Rights(assignment) => {
+                e.commit_to_serialized(&1u8);
+                e.commit_to_serialized(assignment);
+            }
+            StateLeaf::Fungible(assignment) => {
+                e.commit_to_serialized(&2u8);
+                e.commit_to_serialized(assignment);
+            }
+            StateLeaf::Data(assignment) => {
+                e.commit_to_serialized(&3u8);
+                e.commit_to_serialized(assignment);
+            }
+            StateLeaf::Attach(assignment) => {
+                e.commit_to_serialized(&4u8);
+                e.commit_to_serialized(assignment);
+            }
+        }
+        e.set_finished();
+    }
+}
+
+impl StateLeaf {
+    pub fn leaf_hash(&self) -> Bytes32 {
+        Bytes32::from_byte_array(self.commit_id().to_byte_array())
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum StateProofError {
+    /// leaf index {0} is out of range of the state tree with {1} leaves.
+    IndexOutOfRange(u32, u32),
+
+    /// state proof has invalid length for the leaf {0} in the tree with {1}
+    /// leaves.
+    InvalidPathLen(u32, u32),
+
+    /// state proof doesn't match the state root {expected}; the computed root
+    /// is {actual}.
+    RootMismatch {
+        expected: StateRoot,
+        actual: StateRoot,
+    },
+}
+
+/// Merkle inclusion proof for a single [`StateLeaf`] against [`StateRoot`].
+///
+/// Nodes without a sibling at some level of the tree are promoted to the next
+/// level unchanged, thus the shape of the tree (and the path length) is fully
+/// defined by the number of leaves.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct StateProof {
+    pub leaf_index: u32,
+    pub leaf_count: u32,
+    pub path: Vec<Bytes32>,
+}
+
+impl StateProof {
+    /// Computes state root from the leaf and the proof.
+    pub fn root_for(&self, leaf: &StateLeaf) -> Result<StateRoot, StateProofError> {
+        if self.leaf_index >= self.leaf_count {
+            return Err(StateProofError::IndexOutOfRange(self.leaf_index, self.leaf_count));
+        }
+        let mut path = self.path.iter();
+        let mut hash = leaf.leaf_hash();
+        let mut index = self.leaf_index;
+        let mut width = self.leaf_count;
+        while width > 1 {
+            let sibling = index ^ 1;
+            if sibling < width {
+                let Some(node) = path.next() else {
+                    return Err(StateProofError::InvalidPathLen(self.leaf_index, self.leaf_count));
+                };
+                hash = if index & 1 == 0 {
+                    node_hash(hash, *node)
+                } else {
+                    node_hash(*node, hash)
+                };
+            }
+            index >>= 1;
+            width = (width + 1) >> 1;
+        }
+        if path.next().is_some() {
+            return Err(StateProofError::InvalidPathLen(self.leaf_index, self.leaf_count));
+        }
+        Ok(StateRoot::from(hash))
+    }
+
+    /// Verifies that the leaf is a part of the state with the given root.
+    pub fn verify(&self, root: StateRoot, leaf: &StateLeaf) -> Result<(), StateProofError> {
+        let actual = self.root_for(leaf)?;
+        if actual != root {
+            return Err(StateProofError::RootMismatch {
+                expected: root,
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn node_hash(left: Bytes32, right: Bytes32) -> Bytes32 {
+    let mut hasher = Sha256::default();
+    hasher.input_raw(STATE_ROOT_NODE_TAG);
+    hasher.input_raw(&left.to_byte_array());
+    hasher.input_raw(&right.to_byte_array());
+    Bytes32::from_byte_array(hasher.finish())
+}
+
+fn empty_root() -> StateRoot {
+    let mut hasher = Sha256::default();
+    hasher.input_raw(STATE_ROOT_EMPTY_TAG);
+    StateRoot::from(hasher.finish())
+}
+
+fn next_level(level: &[Bytes32]) -> Vec<Bytes32> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(*left, *right),
+            [single] => *single,
+            _ => unreachable!("chunks of two"),
+        })
+        .collect()
+}
+
+impl ContractHistory {
+    /// Computes merkle root over all known contract state items.
+    pub fn state_root(&self) -> StateRoot {
+        let mut level = self
+            .state_leaves()
+            .iter()
+            .map(StateLeaf::leaf_hash)
+            .collect::<Vec<_>>();
+        if level.is_empty() {
+            return empty_root();
+        }
+        while level.len() > 1 {
+            level = next_level(&level);
+        }
+        StateRoot::from(level[0])
+    }
+
+    /// Constructs merkle inclusion proof for the given state item against
+    /// [`ContractHistory::state_root`]. Returns `None` if the item is not a
+    /// part of the contract state, or if the state has more items than a
+    /// proof can address.
+    pub fn prove_leaf(&self, leaf: &StateLeaf) -> Option<StateProof> {
+        let leaves = self.state_leaves();
+        let leaf_count = u32::try_from(leaves.len()).ok()?;
+        let leaf_index = leaves.iter().position(|l| l == leaf)?;
+
+        let mut level = leaves.iter().map(StateLeaf::leaf_hash).collect::<Vec<_>>();
+        let mut index = leaf_index;
+        let mut path = vec![];
+        while level.len() > 1 {
+            if let Some(sibling) = level.get(index ^ 1) {
+                path.push(*sibling);
+            }
+            level = next_level(&level);
+            index >>= 1;
+        }
+        Some(StateProof {
+            leaf_index: leaf_index as u32,
+            leaf_count,
+            path,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::SmallBlob;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{Genesis, Operation};
+
+    fn history(items: u8) -> ContractHistory {
+        let mut genesis = Genesis::strict_dumb();
+        for val in 0..items {
+            genesis
+                .globals
+                .add_state(
+                    GlobalStateType::with(1),
+                    DataState::from(SmallBlob::from_iter_unsafe([val])),
+                )
+                .unwrap();
+        }
+        ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis)
+    }
+
+    #[test]
+    fn inclusion() {
+        for items in 1..=7 {
+            let history = history(items);
+            let root = history.state_root();
+            let leaves = history.state_leaves();
+            assert_eq!(leaves.len(), items as usize);
+            for leaf in &leaves {
+                let proof = history.prove_leaf(leaf).unwrap();
+                assert_eq!(proof.leaf_count, items as u32);
+                assert_eq!(proof.verify(root, leaf), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn empty_state() {
+        let history = history(0);
+        assert_eq!(history.state_root(), empty_root());
+        assert_ne!(history.state_root(), self::history(1).state_root());
+    }
+
+    #[test]
+    fn invalid_proofs() {
+        let history = history(5);
+        let root = history.state_root();
+        let leaves = history.state_leaves();
+        let proof = history.prove_leaf(&leaves[2]).unwrap();
+
+        let actual = proof.root_for(&leaves[3]).unwrap();
+        assert_eq!(
+            proof.verify(root, &leaves[3]),
+            Err(StateProofError::RootMismatch {
+                expected: root,
+                actual
+            })
+        );
+
+        let mut out_of_range = proof.clone();
+        out_of_range.leaf_index = 5;
+        assert_eq!(
+            out_of_range.verify(root, &leaves[2]),
+            Err(StateProofError::IndexOutOfRange(5, 5))
+        );
+
+        let mut short = proof.clone();
+        short.path.pop();
+        assert_eq!(short.verify(root, &leaves[2]), Err(StateProofError::InvalidPathLen(2, 5)));
+
+        let mut long = proof;
+        long.path.push(Bytes32::from_byte_array([0; 32]));
+        assert_eq!(long.verify(root, &leaves[2]), Err(StateProofError::InvalidPathLen(2, 5)));
+
+        // Leaves of another state are not a part of this one
+        let other = self::history(6).state_leaves();
+        assert_eq!(history.prove_leaf(&other[5]), None);
+    }
+}
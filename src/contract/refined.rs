@@ -0,0 +1,248 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Refinement types for the construction of valid operations.
+//!
+//! Some operations can never be valid: state transitions without inputs or
+//! operations which neither assign state nor define valencies. The types
+//! provided here make it impossible to construct such operations with
+//! [`Transition::with`] and [`Extension::with`], moving these failures from
+//! the validation to the construction time.
+
+use crate::schema::{ExtensionType, TransitionType};
+use crate::{
+    Assignments, ContractId, ExposedSeal, Extension, GenesisSeal, GraphSeal, Inputs, Redeemed,
+    Transition, Valencies,
+};
+
+/// Errors constructing refinement types.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum RefinementError {
+    /// operation must have at least one input.
+    NoInputs,
+
+    /// operation must have at least one assignment.
+    NoAssignments,
+
+    /// operation must define at least one valency.
+    NoValencies,
+
+    /// operation must either assign some state or define some valencies.
+    NoOutputs,
+
+    /// state extension must redeem at least one valency.
+    NoRedeemed,
+}
+
+/// Assignments containing at least one assigned state.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct NonEmptyAssignments<Seal: ExposedSeal>(Assignments<Seal>);
+
+impl<Seal: ExposedSeal> TryFrom<Assignments<Seal>> for NonEmptyAssignments<Seal> {
+    type Error = RefinementError;
+
+    fn try_from(assignments: Assignments<Seal>) -> Result<Self, Self::Error> {
+        if assignments.values().all(|a| a.is_empty()) {
+            return Err(RefinementError::NoAssignments);
+        }
+        Ok(Self(assignments))
+    }
+}
+
+impl<Seal: ExposedSeal> NonEmptyAssignments<Seal> {
+    pub fn as_assignments(&self) -> &Assignments<Seal> { &self.0 }
+
+    pub fn into_assignments(self) -> Assignments<Seal> { self.0 }
+}
+
+/// Valencies containing at least one valency.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct NonEmptyValencies(Valencies);
+
+impl TryFrom<Valencies> for NonEmptyValencies {
+    type Error = RefinementError;
+
+    fn try_from(valencies: Valencies) -> Result<Self, Self::Error> {
+        if valencies.is_empty() {
+            return Err(RefinementError::NoValencies);
+        }
+        Ok(Self(valencies))
+    }
+}
+
+impl NonEmptyValencies {
+    pub fn as_valencies(&self) -> &Valencies { &self.0 }
+
+    pub fn into_valencies(self) -> Valencies { self.0 }
+}
+
+/// Transition inputs containing at least one input.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct NonEmptyInputs(Inputs);
+
+impl TryFrom<Inputs> for NonEmptyInputs {
+    type Error = RefinementError;
+
+    fn try_from(inputs: Inputs) -> Result<Self, Self::Error> {
+        if inputs.is_empty() {
+            return Err(RefinementError::NoInputs);
+        }
+        Ok(Self(inputs))
+    }
+}
+
+impl NonEmptyInputs {
+    pub fn as_inputs(&self) -> &Inputs { &self.0 }
+
+    pub fn into_inputs(self) -> Inputs { self.0 }
+}
+
+/// Redeemed valencies containing at least one valency.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct NonEmptyRedeemed(Redeemed);
+
+impl TryFrom<Redeemed> for NonEmptyRedeemed {
+    type Error = RefinementError;
+
+    fn try_from(redeemed: Redeemed) -> Result<Self, Self::Error> {
+        if redeemed.is_empty() {
+            return Err(RefinementError::NoRedeemed);
+        }
+        Ok(Self(redeemed))
+    }
+}
+
+/// Outputs of an operation: assignments and valencies, at least one of which
+/// is not empty.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct OpOutputs<Seal: ExposedSeal> {
+    assignments: Assignments<Seal>,
+    valencies: Valencies,
+}
+
+impl<Seal: ExposedSeal> OpOutputs<Seal> {
+    /// Constructs outputs, checking that there is at least one assignment or
+    /// valency.
+    pub fn new(
+        assignments: Assignments<Seal>,
+        valencies: Valencies,
+    ) -> Result<Self, RefinementError> {
+        match NonEmptyAssignments::try_from(assignments) {
+            Ok(assignments) => Ok(Self::with_assignments(assignments, valencies)),
+            Err(_) if valencies.is_empty() => Err(RefinementError::NoOutputs),
+            Err(_) => Ok(OpOutputs {
+                assignments: empty!(),
+                valencies,
+            }),
+        }
+    }
+
+    pub fn with_assignments(assignments: NonEmptyAssignments<Seal>, valencies: Valencies) -> Self {
+        OpOutputs {
+            assignments: assignments.into_assignments(),
+            valencies,
+        }
+    }
+
+    pub fn with_valencies(assignments: Assignments<Seal>, valencies: NonEmptyValencies) -> Self {
+        OpOutputs {
+            assignments,
+            valencies: valencies.into_valencies(),
+        }
+    }
+
+    pub fn assignments(&self) -> &Assignments<Seal> { &self.assignments }
+
+    pub fn valencies(&self) -> &Valencies { &self.valencies }
+}
+
+impl Transition {
+    /// Constructs state transition which has inputs and outputs, with no
+    /// metadata and global state.
+    pub fn with(
+        contract_id: ContractId,
+        transition_type: TransitionType,
+        inputs: NonEmptyInputs,
+        outputs: OpOutputs<GraphSeal>,
+    ) -> Self {
+        Transition {
+            ffv: default!(),
+            contract_id,
+            transition_type,
+            metadata: default!(),
+            globals: default!(),
+            inputs: inputs.into_inputs(),
+            assignments: outputs.assignments,
+            valencies: outputs.valencies,
+            validator: default!(),
+            witness: default!(),
+        }
+    }
+}
+
+impl Extension {
+    /// Constructs state extension which redeems valencies and has outputs,
+    /// with no metadata and global state.
+    pub fn with(
+        contract_id: ContractId,
+        extension_type: ExtensionType,
+        redeemed: NonEmptyRedeemed,
+        outputs: OpOutputs<GenesisSeal>,
+    ) -> Self {
+        Extension {
+            ffv: default!(),
+            contract_id,
+            extension_type,
+            metadata: default!(),
+            globals: default!(),
+            assignments: outputs.assignments,
+            redeemed: redeemed.0,
+            valencies: outputs.valencies,
+            validator: default!(),
+            witness: default!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::ValencyType;
+
+    #[test]
+    fn empty_outputs() {
+        let assignments = Assignments::<GraphSeal>::default();
+        assert_eq!(
+            OpOutputs::new(assignments.clone(), Valencies::default()),
+            Err(RefinementError::NoOutputs)
+        );
+        assert_eq!(
+            NonEmptyAssignments::try_from(assignments.clone()),
+            Err(RefinementError::NoAssignments)
+        );
+        let valencies = Valencies::from(tiny_bset![ValencyType::with(1)]);
+        let outputs = OpOutputs::new(assignments, valencies.clone()).unwrap();
+        assert_eq!(outputs.valencies(), &valencies);
+        assert_eq!(NonEmptyInputs::try_from(Inputs::default()), Err(RefinementError::NoInputs));
+    }
+}
@@ -0,0 +1,180 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Consensus selection of the current value of replaceable global state.
+//!
+//! When several operations under different witnesses update a replaceable
+//! global state type (see [`crate::Genesis::replaceable_globals`]), all
+//! implementations must agree on which value is the current one. The winner
+//! is the value which goes last in the consensus order of [`GlobalOrd`]:
+//!
+//! 1. values defined by genesis are superseded by any value defined under a
+//!    witness;
+//! 2. values under mined witnesses are ordered by the block timestamp of the
+//!    witness, and values under witnesses which are not mined yet go after all
+//!    mined ones;
+//! 3. values under different witnesses with the same position are ordered by
+//!    the witness id;
+//! 4. values defined by the same operation are ordered by their index.
+//!
+//! Values from witnesses which are not mined yet may be replaced by a mined
+//! value once their witness gets mined or is dropped, so the winner is
+//! reported together with its provenance, and
+//! [`GlobalContractState::current_mined`] allows to ignore such values.
+
+use crate::{DataState, GlobalContractState, GlobalOrd, WitnessAnchor, WitnessOrd};
+
+/// Origin of the current value of a global state type.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum GlobalProvenance {
+    /// The value is defined by the contract genesis.
+    #[display("genesis")]
+    Genesis,
+
+    /// The value is defined by an operation with a mined witness.
+    #[display("mined {0}")]
+    Mined(WitnessAnchor),
+
+    /// The value is defined by an operation with a witness which is not
+    /// mined yet.
+    #[display("pending {0}")]
+    Pending(WitnessAnchor),
+}
+
+impl GlobalProvenance {
+    pub fn with(ord: GlobalOrd) -> Self {
+        match ord.witness_anchor {
+            None => GlobalProvenance::Genesis,
+            Some(anchor) if matches!(anchor.witness_ord, WitnessOrd::OnChain(_)) => {
+                GlobalProvenance::Mined(anchor)
+            }
+            Some(anchor) => GlobalProvenance::Pending(anchor),
+        }
+    }
+
+    pub fn is_pending(&self) -> bool { matches!(self, GlobalProvenance::Pending(_)) }
+}
+
+/// Current value of a global state type selected by the consensus rules.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct CurrentGlobal<'state> {
+    pub value: &'state DataState,
+    pub ord: GlobalOrd,
+    pub provenance: GlobalProvenance,
+    /// Number of values of the same type superseded by the current one.
+    pub superseded: usize,
+}
+
+impl<'state> GlobalContractState<'state> {
+    /// Returns the current value of the global state, which is the value
+    /// going last in the consensus order, with its provenance.
+    ///
+    /// The returned value may come from a witness which is not mined yet; use
+    /// [`GlobalContractState::current_mined`] to ignore such values.
+    pub fn current(&self) -> Option<CurrentGlobal<'state>> {
+        let (ord, value) = self.last()?;
+        Some(CurrentGlobal {
+            value,
+            ord,
+            provenance: GlobalProvenance::with(ord),
+            superseded: self.len() - 1,
+        })
+    }
+
+    /// Returns the current value of the global state ignoring values defined
+    /// under the witnesses which are not mined yet.
+    pub fn current_mined(&self) -> Option<CurrentGlobal<'state>> {
+        // Values under unmined witnesses always go after the mined ones
+        let mut pending = 0;
+        for (ord, value) in self.iter().rev() {
+            let provenance = GlobalProvenance::with(ord);
+            if provenance.is_pending() {
+                pending += 1;
+                continue;
+            }
+            return Some(CurrentGlobal {
+                value,
+                ord,
+                provenance,
+                superseded: self.len() - pending - 1,
+            });
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::SmallBlob;
+    use bp::Txid;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{
+        ContractHistory, Extension, Genesis, GlobalStateType, Operation, WitnessPos, XChain,
+    };
+
+    fn value(val: u8) -> DataState { DataState::from(SmallBlob::from_iter_unsafe([val])) }
+
+    #[test]
+    fn competing_updates() {
+        let ty = GlobalStateType::with(1);
+        let mut genesis = Genesis::strict_dumb();
+        genesis.globals.add_state(ty, value(1)).unwrap();
+        let mut history = ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis);
+        let current = history.global_state(ty).unwrap().current().unwrap();
+        assert_eq!(current.provenance, GlobalProvenance::Genesis);
+
+        let pos = WitnessPos::new(100, 1700000000).unwrap();
+        let witness_ords = [
+            (2u8, WitnessOrd::OnChain(pos)),
+            (3, WitnessOrd::OnChain(pos)),
+            (4, WitnessOrd::OffChain),
+        ];
+        for (val, witness_ord) in witness_ords {
+            let mut extension = Extension::strict_dumb();
+            extension.globals.add_state(ty, value(val)).unwrap();
+            let anchor = WitnessAnchor {
+                witness_ord,
+                witness_id: XChain::Bitcoin(Txid::from([5 - val; 32])),
+            };
+            history.add_extension(&extension, anchor);
+        }
+
+        let state = history.global_state(ty).unwrap();
+        let current = state.current().unwrap();
+        assert_eq!(current.value, &value(4));
+        assert!(current.provenance.is_pending());
+        assert_eq!(current.superseded, 3);
+
+        // Both mined witnesses have the same position: the one with the
+        // greater witness id wins
+        let mined = state.current_mined().unwrap();
+        assert_eq!(mined.value, &value(2));
+        assert_eq!(mined.superseded, 2);
+    }
+}
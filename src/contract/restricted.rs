@@ -0,0 +1,196 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contract state restricted to a set of outpoints.
+//!
+//! Services acting on behalf of a single wallet or account (for instance, RPC
+//! servers) must not expose allocations belonging to other users. Instead of
+//! filtering the state at each call site, such services work with
+//! [`RestrictedState`], which doesn't provide access to the allocations on
+//! seals outside of the given set of outpoints. Global state is public for all
+//! contract participants and is exposed without restrictions.
+
+use std::collections::BTreeSet;
+
+use amplify::confinement::SmallVec;
+
+use crate::{
+    AssignmentType, ContractId, ContractState, DataState, FungibleState, GlobalContractState,
+    GlobalStateType, KnownState, OutputAssignment, RevealedAttach, RevealedData, RevealedValue,
+    Schema, SchemaId, VoidState, XOutpoint,
+};
+
+impl ContractState {
+    /// Returns view of the contract state which exposes only allocations
+    /// assigned to the given outpoints.
+    pub fn restricted_view(
+        &self,
+        outpoints: impl IntoIterator<Item = impl Into<XOutpoint>>,
+    ) -> RestrictedState<'_> {
+        RestrictedState {
+            state: self,
+            outpoints: outpoints.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// View of the [`ContractState`] restricted to allocations on a set of
+/// outpoints.
+///
+/// Global state is not restricted.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct RestrictedState<'state> {
+    state: &'state ContractState,
+    outpoints: BTreeSet<XOutpoint>,
+}
+
+impl<'state> RestrictedState<'state> {
+    /// Outpoints to which the view is restricted.
+    pub fn outpoints(&self) -> &BTreeSet<XOutpoint> { &self.outpoints }
+
+    pub fn schema(&self) -> &'state Schema { &self.state.schema }
+
+    pub fn schema_id(&self) -> SchemaId { self.state.schema_id() }
+
+    pub fn contract_id(&self) -> ContractId { self.state.contract_id() }
+
+    /// Returns consensus-ordered view of the global state of the given type,
+    /// or `None` if the contract has no global state of this type.
+    pub fn global_state(&self, ty: GlobalStateType) -> Option<GlobalContractState<'state>> {
+        self.state.global_state(ty)
+    }
+
+    /// Returns global state of the type with the given name.
+    ///
+    /// See [`ContractState::global_by_name`] for the details.
+    pub fn global_by_name(&self, name: &str) -> Option<SmallVec<&'state DataState>> {
+        self.state.global_by_name(name)
+    }
+
+    /// Lists outpoints of the view holding state of the given assignment
+    /// type.
+    ///
+    /// See [`crate::ContractHistory::outpoints`] for the details.
+    pub fn outpoints_by_type(&self, ty: AssignmentType) -> impl Iterator<Item = XOutpoint> + '_ {
+        self.state
+            .outpoints(ty)
+            .filter(|outpoint| self.outpoints.contains(outpoint))
+    }
+
+    pub fn rights(&self) -> impl Iterator<Item = &'state OutputAssignment<VoidState>> + '_ {
+        self.filter(self.state.rights())
+    }
+
+    pub fn fungibles(&self) -> impl Iterator<Item = &'state OutputAssignment<RevealedValue>> + '_ {
+        self.filter(self.state.fungibles())
+    }
+
+    pub fn data(&self) -> impl Iterator<Item = &'state OutputAssignment<RevealedData>> + '_ {
+        self.filter(self.state.data())
+    }
+
+    pub fn attach(&self) -> impl Iterator<Item = &'state OutputAssignment<RevealedAttach>> + '_ {
+        self.filter(self.state.attach())
+    }
+
+    /// Returns fungible allocations of the assignment type with the given
+    /// name, or `None` if the contract schema doesn't name such type.
+    pub fn fungibles_by_name<'a>(
+        &'a self,
+        name: &str,
+    ) -> Option<impl Iterator<Item = &'state OutputAssignment<RevealedValue>> + 'a> {
+        let ty = self.state.schema.assignment_type_by_name(name)?;
+        Some(self.fungibles().filter(move |a| a.opout.ty == ty))
+    }
+
+    /// Computes total amount of the fungible state of the given type assigned
+    /// to the outpoints of the view.
+    ///
+    /// Returns `None` if the total amount overflows.
+    pub fn balance(&self, ty: AssignmentType) -> Option<FungibleState> {
+        self.state.balance(ty, self.outpoints.iter().copied())
+    }
+
+    fn filter<'a, State: KnownState + 'state>(
+        &'a self,
+        assignments: impl IntoIterator<Item = &'state OutputAssignment<State>> + 'a,
+    ) -> impl Iterator<Item = &'state OutputAssignment<State>> + 'a {
+        assignments
+            .into_iter()
+            .filter(move |a| self.outpoints.contains(&a.seal.to_outpoint()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+    use amplify::Wrapper;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::validation::fixtures::TestChain;
+    use crate::{
+        AssetTag, Assign, Assignments, ContractHistory, Genesis, Operation, TypedAssigns,
+        XGenesisSeal,
+    };
+
+    #[test]
+    fn restricted_allocations() {
+        let ty = AssignmentType::with(1);
+        let mut chain = TestChain::regtest();
+        let seals = [chain.genesis_seal(), chain.genesis_seal(), chain.genesis_seal()];
+        let tag = AssetTag::strict_dumb();
+        let allocations = seals
+            .iter()
+            .zip([100u64, 200, 300])
+            .map(|(seal, amount)| {
+                Assign::revealed(*seal, RevealedValue::new_random_blinding(amount, tag))
+            })
+            .collect::<Vec<_>>();
+        let mut genesis = Genesis::strict_dumb();
+        genesis.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            ty,
+            TypedAssigns::Fungible(Confined::try_from(allocations).unwrap()),
+        )]));
+        let state = ContractState {
+            schema: Schema::strict_dumb(),
+            history: ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis),
+        };
+
+        let outpoints = [seals[0], seals[2]].map(|seal: XGenesisSeal| seal.to_outpoint());
+        let view = state.restricted_view(outpoints);
+        assert_eq!(view.outpoints(), &BTreeSet::from(outpoints));
+        assert_eq!(view.fungibles().count(), 2);
+        assert!(view
+            .fungibles()
+            .all(|a| outpoints.contains(&a.seal.to_outpoint())));
+        assert_eq!(view.outpoints_by_type(ty).collect::<BTreeSet<_>>(), BTreeSet::from(outpoints));
+        assert_eq!(view.outpoints_by_type(AssignmentType::with(2)).count(), 0);
+        assert_eq!(view.balance(ty), Some(FungibleState::from(400u64)));
+        assert_eq!(view.rights().count(), 0);
+        assert!(view.fungibles_by_name("amount").is_none());
+
+        let empty = state.restricted_view(Vec::<XOutpoint>::new());
+        assert_eq!(empty.fungibles().count(), 0);
+        assert_eq!(empty.balance(ty), Some(FungibleState::from(0u64)));
+    }
+}
@@ -0,0 +1,262 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conversion between concealed and revealed forms of assignments.
+//!
+//! Wallets keep secrets (blinded seals and the state data) of the assignments
+//! they own or receive in a [`SecretRegistry`]. Assignments received in a
+//! concealed form are revealed with [`Assign::reveal_with`] (and the same
+//! methods of [`TypedAssigns`] and [`Assignments`]), which checks that the
+//! secrets match the commitments of the concealed assignment. Concealment is
+//! always possible and is provided by [`Assign::conceal_seal`],
+//! [`Assign::conceal_state`] and [`commit_verify::Conceal`].
+
+use std::collections::BTreeMap;
+
+use amplify::confinement::{Confined, SmallVec};
+use commit_verify::Conceal;
+
+use crate::{
+    Assign, Assignments, ConcealedState, ConfidentialState, ExposedSeal, ExposedState,
+    RevealedAttach, RevealedData, RevealedState, RevealedValue, SecretSeal, StateType,
+    TypedAssigns, VoidState, XChain,
+};
+
+/// Errors revealing concealed assignments.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum RevealError {
+    /// secret for the concealed seal {0} is not known.
+    UnknownSeal(XChain<SecretSeal>),
+
+    /// {0} state matching the concealed state commitment is not known.
+    UnknownState(StateType),
+}
+
+/// State types which can be restored from the [`RevealedState`].
+pub trait FromStateData: ExposedState {
+    /// Extracts state from the revealed state data, if the data are of the
+    /// matching type.
+    fn from_state_data(data: &RevealedState) -> Option<Self>;
+}
+
+impl FromStateData for VoidState {
+    fn from_state_data(data: &RevealedState) -> Option<Self> {
+        match data {
+            RevealedState::Void => Some(VoidState::default()),
+            _ => None,
+        }
+    }
+}
+
+impl FromStateData for RevealedValue {
+    fn from_state_data(data: &RevealedState) -> Option<Self> {
+        match data {
+            RevealedState::Fungible(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromStateData for RevealedData {
+    fn from_state_data(data: &RevealedState) -> Option<Self> {
+        match data {
+            RevealedState::Structured(data) => Some(data.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromStateData for RevealedAttach {
+    fn from_state_data(data: &RevealedState) -> Option<Self> {
+        match data {
+            RevealedState::Attachment(attach) => Some(attach.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Registry of the seal and state secrets used to reveal concealed
+/// assignments.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SecretRegistry<Seal: ExposedSeal> {
+    seals: BTreeMap<XChain<SecretSeal>, XChain<Seal>>,
+    states: Vec<(ConcealedState, RevealedState)>,
+}
+
+impl<Seal: ExposedSeal> Default for SecretRegistry<Seal> {
+    fn default() -> Self {
+        SecretRegistry {
+            seals: empty!(),
+            states: empty!(),
+        }
+    }
+}
+
+impl<Seal: ExposedSeal> SecretRegistry<Seal> {
+    pub fn new() -> Self { Self::default() }
+
+    /// Adds revealed seal to the registry.
+    pub fn add_seal(&mut self, seal: XChain<Seal>) { self.seals.insert(seal.conceal(), seal); }
+
+    /// Adds revealed state to the registry.
+    pub fn add_state<State: ExposedState>(&mut self, state: &State) {
+        let concealed = state.conceal().state_commitment();
+        if !self.states.iter().any(|(known, _)| *known == concealed) {
+            self.states.push((concealed, state.state_data()));
+        }
+    }
+
+    /// Returns revealed seal matching the concealed one.
+    pub fn reveal_seal(&self, secret: XChain<SecretSeal>) -> Option<XChain<Seal>> {
+        self.seals.get(&secret).copied()
+    }
+
+    /// Returns revealed state matching the concealed one.
+    pub fn reveal_state<State: FromStateData>(
+        &self,
+        concealed: &State::Confidential,
+    ) -> Option<State> {
+        let commitment = concealed.state_commitment();
+        self.states
+            .iter()
+            .find(|(known, _)| *known == commitment)
+            .and_then(|(_, data)| State::from_state_data(data))
+    }
+}
+
+impl<State: ExposedState, Seal: ExposedSeal> From<(XChain<Seal>, State)> for Assign<State, Seal> {
+    fn from((seal, state): (XChain<Seal>, State)) -> Self { Assign::revealed(seal, state) }
+}
+
+impl<State: ExposedState, Seal: ExposedSeal> Assign<State, Seal> {
+    /// Checks whether both seal and state of the assignment are revealed.
+    pub fn is_revealed(&self) -> bool { matches!(self, Assign::Revealed { .. }) }
+
+    /// Checks whether both seal and state of the assignment are concealed.
+    pub fn is_concealed(&self) -> bool { matches!(self, Assign::Confidential { .. }) }
+}
+
+impl<State: FromStateData, Seal: ExposedSeal> Assign<State, Seal> {
+    /// Reveals concealed seal and state of the assignment using secrets from
+    /// the registry.
+    ///
+    /// Already revealed data are kept as is.
+    pub fn reveal_with(&self, registry: &SecretRegistry<Seal>) -> Result<Self, RevealError> {
+        let reveal_seal = |secret: &XChain<SecretSeal>| {
+            registry
+                .reveal_seal(*secret)
+                .ok_or(RevealError::UnknownSeal(*secret))
+        };
+        let reveal_state = |concealed: &State::Confidential| {
+            registry
+                .reveal_state::<State>(concealed)
+                .ok_or(RevealError::UnknownState(concealed.state_type()))
+        };
+        Ok(match self {
+            Assign::Revealed { .. } => self.clone(),
+            Assign::Confidential { seal, state, lock } => Assign::Revealed {
+                seal: reveal_seal(seal)?,
+                state: reveal_state(state)?,
+                lock: *lock,
+            },
+            Assign::ConfidentialSeal { seal, state, lock } => Assign::Revealed {
+                seal: reveal_seal(seal)?,
+                state: state.clone(),
+                lock: *lock,
+            },
+            Assign::ConfidentialState { seal, state, lock } => Assign::Revealed {
+                seal: *seal,
+                state: reveal_state(state)?,
+                lock: *lock,
+            },
+        })
+    }
+}
+
+fn reveal_all<State: FromStateData, Seal: ExposedSeal>(
+    assignments: &SmallVec<Assign<State, Seal>>,
+    registry: &SecretRegistry<Seal>,
+) -> Result<SmallVec<Assign<State, Seal>>, RevealError> {
+    let revealed = assignments
+        .iter()
+        .map(|a| a.reveal_with(registry))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Confined::try_from(revealed).expect("same size"))
+}
+
+impl<Seal: ExposedSeal> TypedAssigns<Seal> {
+    /// Reveals all concealed assignments using secrets from the registry.
+    pub fn reveal_with(&self, registry: &SecretRegistry<Seal>) -> Result<Self, RevealError> {
+        Ok(match self {
+            TypedAssigns::Declarative(a) => TypedAssigns::Declarative(reveal_all(a, registry)?),
+            TypedAssigns::Fungible(a) => TypedAssigns::Fungible(reveal_all(a, registry)?),
+            TypedAssigns::Structured(a) => TypedAssigns::Structured(reveal_all(a, registry)?),
+            TypedAssigns::Attachment(a) => TypedAssigns::Attachment(reveal_all(a, registry)?),
+        })
+    }
+}
+
+impl<Seal: ExposedSeal> Assignments<Seal> {
+    /// Reveals all concealed assignments using secrets from the registry.
+    pub fn reveal_with(&self, registry: &SecretRegistry<Seal>) -> Result<Self, RevealError> {
+        let revealed = self
+            .iter()
+            .map(|(ty, typed)| typed.reveal_with(registry).map(|typed| (*ty, typed)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Assignments::from(Confined::try_from_iter(revealed).expect("same size")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bp::seals::txout::CloseMethod;
+    use bp::Vout;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{AssetTag, BlindingSeed, Layer1};
+
+    #[test]
+    fn reveal_roundtrip() {
+        let seed = BlindingSeed::from([0x44; 32]);
+        let method = CloseMethod::OpretFirst;
+        let seal = seed.graph_seal(Layer1::Bitcoin, method, None, Vout::from(1), 0);
+        let state = RevealedValue::new_random_blinding(100u64, AssetTag::strict_dumb());
+        let revealed = Assign::from((seal, state));
+        assert!(revealed.is_revealed());
+        let concealed = revealed.conceal();
+        assert!(concealed.is_concealed());
+
+        let mut registry = SecretRegistry::new();
+        assert_eq!(concealed.reveal_with(&registry), Err(RevealError::UnknownSeal(seal.conceal())));
+        registry.add_seal(seal);
+        assert_eq!(
+            concealed.reveal_with(&registry),
+            Err(RevealError::UnknownState(StateType::Fungible))
+        );
+        registry.add_state(&state);
+        let restored = concealed.reveal_with(&registry).unwrap();
+        assert!(restored.is_revealed());
+        assert_eq!(restored.to_revealed(), Some((seal, state)));
+    }
+}
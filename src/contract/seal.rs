@@ -20,23 +20,25 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::fmt::Debug;
-use std::cmp::Ordering;
+use core::fmt::{self, Debug, Formatter};
 use std::hash::Hash;
 use std::num::NonZeroU32;
 
+use amplify::ByteArray;
 use bp::dbc::Method;
 pub use bp::seals::txout::blind::{ChainBlindSeal, ParseError, SingleBlindSeal};
 pub use bp::seals::txout::TxoSeal;
-use bp::seals::txout::{BlindSeal, CloseMethod, ExplicitSeal, SealTxid, VerifyError, Witness};
+use bp::seals::txout::{
+    BlindSeal, CloseMethod, ExplicitSeal, SealTxid, TxPtr, VerifyError, Witness,
+};
 pub use bp::seals::SecretSeal;
 use bp::{dbc, Outpoint, Tx, Txid, Vout};
-use commit_verify::{mpc, Conceal};
+use commit_verify::{mpc, Conceal, DigestExt, Sha256};
 use single_use_seals::SealWitness;
 use strict_encoding::{StrictDecode, StrictDumb, StrictEncode, StrictType};
 
 use crate::contract::xchain::Impossible;
-use crate::{XChain, XOutpoint, LIB_NAME_RGB};
+use crate::{Layer1, XChain, XOutpoint, LIB_NAME_RGB};
 
 pub type GenesisSeal = SingleBlindSeal<Method>;
 pub type GraphSeal = ChainBlindSeal<Method>;
@@ -201,14 +203,8 @@ impl WitnessPos {
     }
 
     pub fn height(&self) -> NonZeroU32 { NonZeroU32::new(self.height).expect("invariant") }
-}
-
-impl PartialOrd for WitnessPos {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
-}
 
-impl Ord for WitnessPos {
-    fn cmp(&self, other: &Self) -> Ordering { self.timestamp.cmp(&other.timestamp) }
+    pub fn timestamp(&self) -> i64 { self.timestamp }
 }
 
 /// RGB consensus information about the current mined height of a witness
@@ -237,6 +233,71 @@ impl WitnessOrd {
             .map(WitnessOrd::OnChain)
             .unwrap_or(WitnessOrd::OffChain)
     }
+
+    /// Returns block height of the mined witness, or `None` for off-chain
+    /// witnesses.
+    pub fn height(&self) -> Option<NonZeroU32> {
+        match self {
+            WitnessOrd::OnChain(pos) => Some(pos.height()),
+            WitnessOrd::OffChain => None,
+        }
+    }
+
+    /// Returns mining timestamp of the block containing the witness, or `None`
+    /// for off-chain witnesses.
+    pub fn timestamp(&self) -> Option<i64> {
+        match self {
+            WitnessOrd::OnChain(pos) => Some(pos.timestamp()),
+            WitnessOrd::OffChain => None,
+        }
+    }
+}
+
+/// Set of seal close methods which are acceptable for the contract witnesses.
+///
+/// The set is declared by the contract genesis with
+/// [`crate::schema::MetaType::CLOSE_METHODS`] metadata, holding a single byte
+/// bit mask. Contracts which do not declare it accept all close methods.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct CloseMethodSet(u8);
+
+impl Default for CloseMethodSet {
+    fn default() -> Self { Self::ALL }
+}
+
+impl CloseMethodSet {
+    pub const OPRET: u8 = 0x01;
+    pub const TAPRET: u8 = 0x02;
+    pub const ALL: Self = CloseMethodSet(Self::OPRET | Self::TAPRET);
+
+    /// Constructs the set from a bit mask. Returns `None` if the mask is empty
+    /// or contains unknown bits.
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        if bits == 0 || bits & !Self::ALL.0 != 0 {
+            return None;
+        }
+        Some(Self(bits))
+    }
+
+    pub fn with(methods: impl IntoIterator<Item = CloseMethod>) -> Self {
+        Self(
+            methods
+                .into_iter()
+                .map(Self::bit)
+                .fold(0, |acc, bit| acc | bit),
+        )
+    }
+
+    pub const fn bits(self) -> u8 { self.0 }
+
+    pub fn contains(self, method: CloseMethod) -> bool { self.0 & Self::bit(method) != 0 }
+
+    fn bit(method: CloseMethod) -> u8 {
+        match method {
+            CloseMethod::OpretFirst => Self::OPRET,
+            CloseMethod::TapretFirst => Self::TAPRET,
+        }
+    }
 }
 
 pub type XWitnessTx<X = Impossible> = XChain<Tx, X>;
@@ -293,10 +354,114 @@ impl<Id: SealTxid> XChain<BlindSeal<Id>> {
     pub fn to_secret_seal(&self) -> XChain<SecretSeal> { self.conceal() }
 }
 
+pub const SEAL_BLINDING_TAG: &[u8] = b"urn:lnp-bp:rgb:seal-blinding#2024-05-20";
+
+/// Wallet-provided secret seed for deterministic generation of seal blinding
+/// factors.
+///
+/// Blinding factors derived from the seed can be re-created from the wallet
+/// backup, such that blinded seals can be restored without storing each of
+/// the blinding factors. The way the blinding factor is produced is not
+/// visible to the validation, which works with any blinding factor.
+///
+/// # Derivation
+///
+/// Blinding factor is the first 8 bytes, read as a little-endian integer, of
+/// the SHA256 hash of the concatenation of:
+/// - [`SEAL_BLINDING_TAG`];
+/// - 32-byte seed;
+/// - single byte of the layer 1 (`0` for Bitcoin, `1` for Liquid);
+/// - single byte of the seal closing method (`0` for opret, `1` for tapret);
+/// - 32 bytes of the seal txid, or 32 zero bytes for the seals defined relative
+///   to the witness transaction;
+/// - seal output number as a 4-byte little-endian integer;
+/// - seal index as a 4-byte little-endian integer, allowing to create multiple
+///   seals over the same output.
+#[derive(Wrapper, Copy, Clone, Eq, PartialEq, Hash, From)]
+#[wrapper(Deref, BorrowSlice)]
+pub struct BlindingSeed([u8; 32]);
+
+impl Debug for BlindingSeed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { f.write_str("BlindingSeed(..)") }
+}
+
+impl BlindingSeed {
+    /// Derives blinding factor for a seal; see the type documentation for the
+    /// derivation procedure.
+    pub fn blinding(
+        &self,
+        layer1: Layer1,
+        method: CloseMethod,
+        txid: Option<Txid>,
+        vout: Vout,
+        index: u32,
+    ) -> u64 {
+        let mut hasher = Sha256::default();
+        hasher.input_raw(SEAL_BLINDING_TAG);
+        hasher.input_raw(&self.0);
+        hasher.input_raw(&[layer1 as u8, method as u8]);
+        hasher.input_raw(&txid.map(|txid| txid.to_byte_array()).unwrap_or_default());
+        hasher.input_raw(&vout.to_u32().to_le_bytes());
+        hasher.input_raw(&index.to_le_bytes());
+        let hash = hasher.finish();
+        u64::from_le_bytes(hash[..8].try_into().expect("fixed size"))
+    }
+
+    /// Constructs seal with the deterministic blinding factor. If `txid` is
+    /// not provided, the seal is defined relative to the witness transaction.
+    pub fn graph_seal(
+        &self,
+        layer1: Layer1,
+        method: CloseMethod,
+        txid: Option<Txid>,
+        vout: Vout,
+        index: u32,
+    ) -> XGraphSeal {
+        let seal = GraphSeal {
+            method,
+            txid: txid.map(TxPtr::Txid).unwrap_or(TxPtr::WitnessTx),
+            vout,
+            blinding: self.blinding(layer1, method, txid, vout, index),
+        };
+        XChain::with(layer1, seal)
+    }
+
+    /// Constructs genesis seal with the deterministic blinding factor.
+    pub fn genesis_seal(
+        &self,
+        layer1: Layer1,
+        method: CloseMethod,
+        outpoint: Outpoint,
+        index: u32,
+    ) -> XGenesisSeal {
+        let seal = GenesisSeal {
+            method,
+            txid: outpoint.txid,
+            vout: outpoint.vout,
+            blinding: self.blinding(layer1, method, Some(outpoint.txid), outpoint.vout, index),
+        };
+        XChain::with(layer1, seal)
+    }
+
+    /// Restores seal from its concealed form by searching through the seal
+    /// indexes below `max_index`.
+    pub fn restore_seal(
+        &self,
+        secret: XChain<SecretSeal>,
+        method: CloseMethod,
+        txid: Option<Txid>,
+        vout: Vout,
+        max_index: u32,
+    ) -> Option<XGraphSeal> {
+        (0..max_index)
+            .map(|index| self.graph_seal(secret.layer1(), method, txid, vout, index))
+            .find(|seal| seal.to_secret_seal() == secret)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use amplify::hex::FromHex;
-    use bp::seals::txout::TxPtr;
 
     use super::*;
 
@@ -318,4 +483,43 @@ mod test {
         );
         assert_eq!(reveal.to_secret_seal(), reveal.conceal())
     }
+
+    #[test]
+    fn deterministic_blinding() {
+        let seed = BlindingSeed::from([0xA5; 32]);
+        let vout = Vout::from(1);
+        let seal = seed.graph_seal(Layer1::Bitcoin, CloseMethod::OpretFirst, None, vout, 3);
+        assert_eq!(seal, seed.graph_seal(Layer1::Bitcoin, CloseMethod::OpretFirst, None, vout, 3));
+        assert_ne!(seal, seed.graph_seal(Layer1::Bitcoin, CloseMethod::OpretFirst, None, vout, 4));
+        assert_eq!(
+            seed.restore_seal(seal.to_secret_seal(), CloseMethod::OpretFirst, None, vout, 10),
+            Some(seal)
+        );
+        assert_eq!(
+            seed.restore_seal(seal.to_secret_seal(), CloseMethod::OpretFirst, None, vout, 3),
+            None
+        );
+    }
+
+    #[test]
+    fn close_method_set() {
+        assert_eq!(CloseMethodSet::default(), CloseMethodSet::ALL);
+        assert!(CloseMethodSet::ALL.contains(CloseMethod::OpretFirst));
+        assert!(CloseMethodSet::ALL.contains(CloseMethod::TapretFirst));
+
+        let opret = CloseMethodSet::with([CloseMethod::OpretFirst]);
+        assert_eq!(opret.bits(), CloseMethodSet::OPRET);
+        assert!(opret.contains(CloseMethod::OpretFirst));
+        assert!(!opret.contains(CloseMethod::TapretFirst));
+        assert_eq!(
+            CloseMethodSet::with([CloseMethod::OpretFirst, CloseMethod::TapretFirst]),
+            CloseMethodSet::ALL
+        );
+
+        assert_eq!(CloseMethodSet::from_bits(CloseMethodSet::OPRET), Some(opret));
+        assert_eq!(CloseMethodSet::from_bits(0x03), Some(CloseMethodSet::ALL));
+        assert_eq!(CloseMethodSet::from_bits(0x00), None);
+        assert_eq!(CloseMethodSet::from_bits(0x04), None);
+        assert_eq!(CloseMethodSet::from_bits(0x05), None);
+    }
 }
@@ -0,0 +1,186 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Status of the contract seals.
+//!
+//! Wallets and watch services need to know which seals of a contract are
+//! still open and have to be monitored on-chain, and which were already
+//! closed and by which operation, for instance to reconcile their state after
+//! some downtime. [`ContractState::seals`] provides this information from the
+//! allocations and spent outputs known to the contract history.
+
+use amplify::confinement::LargeOrdSet;
+
+use crate::{ContractState, KnownState, Opout, OutputAssignment, SpendingOp, XOutputSeal};
+
+/// Status of a contract seal.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum SealStatus {
+    /// Seal is not closed by any state transition known to the contract.
+    #[display("open")]
+    Open,
+
+    /// Seal is closed by the state transition.
+    #[display("closed by {0}")]
+    Closed(SpendingOp),
+}
+
+impl SealStatus {
+    pub fn is_open(self) -> bool { self == SealStatus::Open }
+}
+
+/// Seal of the contract with the state it holds.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display("{seal} ({opout}): {status}")]
+pub struct ContractSeal {
+    pub seal: XOutputSeal,
+    /// Output of the operation which has assigned state to the seal.
+    pub opout: Opout,
+    pub status: SealStatus,
+}
+
+impl ContractState {
+    /// Lists seals of all known contract allocations, each with its status.
+    /// A seal holding several allocations is listed once per allocation.
+    ///
+    /// Seals closed by state transitions which are not known to the contract
+    /// history are reported as open.
+    pub fn seals(&self) -> impl Iterator<Item = ContractSeal> + '_ {
+        fn seals<State: KnownState>(
+            assignments: &LargeOrdSet<OutputAssignment<State>>,
+        ) -> impl Iterator<Item = (XOutputSeal, Opout)> + '_ {
+            assignments.iter().map(|a| (a.seal, a.opout))
+        }
+        seals(self.rights())
+            .chain(seals(self.fungibles()))
+            .chain(seals(self.data()))
+            .chain(seals(self.attach()))
+            .map(|(seal, opout)| ContractSeal {
+                seal,
+                opout,
+                status: match self.spent_by(opout) {
+                    Some(spender) => SealStatus::Closed(spender),
+                    None => SealStatus::Open,
+                },
+            })
+    }
+
+    /// Lists seals which are not closed by any state transition known to the
+    /// contract.
+    pub fn open_seals(&self) -> impl Iterator<Item = ContractSeal> + '_ {
+        self.seals().filter(|seal| seal.status.is_open())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+    use amplify::Wrapper;
+    use bp::Txid;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::validation::fixtures::TestChain;
+    use crate::{
+        AssetTag, Assign, AssignmentType, Assignments, ContractHistory, Genesis, Input, Inputs,
+        Operation, RevealedValue, Schema, Transition, TypedAssigns, VoidState, WitnessAnchor,
+        WitnessOrd, XChain,
+    };
+
+    const RIGHT: AssignmentType = AssignmentType::with(1);
+    const ASSET: AssignmentType = AssignmentType::with(2);
+
+    #[test]
+    fn open_and_closed() {
+        let mut chain = TestChain::regtest();
+        let mut genesis = Genesis::strict_dumb();
+        genesis.assignments = Assignments::from_inner(
+            Confined::try_from(bmap! {
+                RIGHT => TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                    chain.genesis_seal(),
+                    VoidState::default(),
+                )])),
+                ASSET => TypedAssigns::Fungible(Confined::from_iter_unsafe([Assign::revealed(
+                    chain.genesis_seal(),
+                    RevealedValue::new_random_blinding(100, AssetTag::strict_dumb()),
+                )])),
+            })
+            .unwrap(),
+        );
+        let genesis_id = genesis.id();
+        let mut history = ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis);
+
+        let mut transition = Transition::strict_dumb();
+        transition.inputs =
+            Inputs::try_from_inputs([Input::with(Opout::new(genesis_id, ASSET, 0))]).unwrap();
+        transition.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            RIGHT,
+            TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                chain.graph_seal(1),
+                VoidState::default(),
+            )])),
+        )]));
+        let witness_anchor = WitnessAnchor {
+            witness_ord: WitnessOrd::OffChain,
+            witness_id: XChain::Bitcoin(Txid::from([1; 32])),
+        };
+        history.add_transition(&transition, witness_anchor);
+        let state = ContractState {
+            schema: Schema::strict_dumb(),
+            history,
+        };
+
+        let seals = state.seals().collect::<Vec<_>>();
+        assert_eq!(seals.len(), 3);
+        let status = |opout| {
+            seals
+                .iter()
+                .find(|seal| seal.opout == opout)
+                .map(|seal| seal.status)
+        };
+        assert_eq!(status(Opout::new(genesis_id, RIGHT, 0)), Some(SealStatus::Open));
+        assert_eq!(
+            status(Opout::new(genesis_id, ASSET, 0)),
+            Some(SealStatus::Closed(SpendingOp {
+                opid: transition.id(),
+                witness_anchor,
+            }))
+        );
+        assert_eq!(status(Opout::new(transition.id(), RIGHT, 0)), Some(SealStatus::Open));
+
+        let open = state.open_seals().collect::<Vec<_>>();
+        assert_eq!(open.len(), 2);
+        assert!(open.iter().all(|seal| seal.status.is_open()));
+        assert!(open.iter().all(|seal| seal.opout.ty == RIGHT));
+        assert_eq!(SealStatus::Open.to_string(), "open");
+    }
+}
@@ -0,0 +1,139 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detection of seals reused across contracts.
+//!
+//! The same outpoint may hold allocations of multiple contracts. This is
+//! valid under the consensus rules, but spending such an outpoint requires
+//! all of the contracts to be transferred (or the allocations of the
+//! contracts not transferred are lost), thus wallets should warn the user
+//! before spending it. [`SealUsage`] collects outpoints used by the known
+//! contracts, and can be passed to the validator to report reuse of the
+//! seals by the validated contract.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{ContractHistory, ContractId, KnownState, OutputAssignment, XOutpoint};
+
+/// Index of the contracts having allocations on each outpoint.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct SealUsage(BTreeMap<XOutpoint, BTreeSet<ContractId>>);
+
+impl SealUsage {
+    pub fn new() -> Self { Self::default() }
+
+    /// Constructs index from the contract histories.
+    pub fn with<'history>(histories: impl IntoIterator<Item = &'history ContractHistory>) -> Self {
+        let mut usage = Self::new();
+        for history in histories {
+            usage.add_contract(history);
+        }
+        usage
+    }
+
+    /// Adds all outpoints with allocations of the contract to the index.
+    pub fn add_contract(&mut self, history: &ContractHistory) {
+        let contract_id = history.contract_id();
+        let outpoints = outpoints(history.rights().iter())
+            .chain(outpoints(history.fungibles().iter()))
+            .chain(outpoints(history.data().iter()))
+            .chain(outpoints(history.attach().iter()));
+        for outpoint in outpoints {
+            self.add(outpoint, contract_id);
+        }
+    }
+
+    /// Registers usage of the outpoint by the contract.
+    pub fn add(&mut self, outpoint: XOutpoint, contract_id: ContractId) {
+        self.0.entry(outpoint).or_default().insert(contract_id);
+    }
+
+    /// Returns contracts having allocations on the outpoint.
+    pub fn contracts_on(&self, outpoint: XOutpoint) -> impl Iterator<Item = ContractId> + '_ {
+        self.0.get(&outpoint).into_iter().flatten().copied()
+    }
+
+    /// Returns contracts other than `contract_id` having allocations on the
+    /// outpoint.
+    pub fn other_contracts_on(
+        &self,
+        outpoint: XOutpoint,
+        contract_id: ContractId,
+    ) -> impl Iterator<Item = ContractId> + '_ {
+        self.contracts_on(outpoint)
+            .filter(move |id| *id != contract_id)
+    }
+
+    /// Returns outpoints holding allocations of more than one contract.
+    pub fn reused(&self) -> impl Iterator<Item = (XOutpoint, &BTreeSet<ContractId>)> {
+        self.0
+            .iter()
+            .filter(|(_, contracts)| contracts.len() > 1)
+            .map(|(outpoint, contracts)| (*outpoint, contracts))
+    }
+}
+
+fn outpoints<'history, State: KnownState + 'history, I>(
+    assignments: I,
+) -> impl Iterator<Item = XOutpoint> + 'history
+where
+    I: IntoIterator<Item = &'history OutputAssignment<State>>,
+    I::IntoIter: 'history,
+{
+    assignments
+        .into_iter()
+        .map(|assignment| XOutpoint::from(assignment.seal))
+}
+
+#[cfg(test)]
+mod test {
+    use bp::{Outpoint, Txid, Vout};
+
+    use super::*;
+    use crate::XChain;
+
+    #[test]
+    fn reused_outpoints() {
+        let outpoint = |txid: u8, vout: u32| {
+            let outpoint = Outpoint::new(Txid::from([txid; 32]), Vout::from(vout));
+            XOutpoint::from(XChain::Bitcoin(outpoint))
+        };
+        let shared = outpoint(1, 0);
+        let single = outpoint(2, 1);
+        let first = ContractId::from([0xA1; 32]);
+        let second = ContractId::from([0xB2; 32]);
+
+        let mut usage = SealUsage::new();
+        usage.add(shared, first);
+        usage.add(shared, second);
+        usage.add(single, first);
+        assert_eq!(
+            usage
+                .reused()
+                .map(|(outpoint, _)| outpoint)
+                .collect::<Vec<_>>(),
+            vec![shared]
+        );
+        assert_eq!(usage.other_contracts_on(shared, first).collect::<Vec<_>>(), vec![second]);
+        assert_eq!(usage.other_contracts_on(single, first).count(), 0);
+    }
+}
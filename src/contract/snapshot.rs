@@ -0,0 +1,256 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Historical snapshots of the contract state.
+//!
+//! Contract history keeps all known allocations and global state items
+//! together with the witnesses of the operations defining and spending them.
+//! This allows to reconstruct the state as it existed at some past block
+//! height without re-validating the contract: [`StateSnapshot`] exposes only
+//! the state defined by genesis, extensions without witnesses and operations
+//! with witnesses mined at or below the height, and which was not spent by
+//! an operation with a witness mined at or below the height.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    AssignmentWitness, ContractId, ContractState, DataState, GlobalOrd, GlobalStateType,
+    KnownState, OutputAssignment, RevealedAttach, RevealedData, RevealedValue, SchemaId, VoidState,
+    WitnessOrd, XWitnessId,
+};
+
+impl ContractState {
+    /// Returns view of the contract state as it existed at the given block
+    /// height.
+    ///
+    /// Positions of the witnesses are taken from the contract history (see
+    /// [`crate::ContractHistory::witness_status`]); allocations created by
+    /// operations with witnesses of unknown position are not exposed. Use
+    /// [`ContractState::at_height_with`] to provide the positions explicitly.
+    pub fn at_height(&self, height: u32) -> StateSnapshot<'_> {
+        let positions = self
+            .witness_ids()
+            .into_iter()
+            .filter_map(|witness_id| Some((witness_id, self.witness_status(witness_id)?)))
+            .collect();
+        self.at_height_with(height, positions)
+    }
+
+    /// Returns view of the contract state as it existed at the given block
+    /// height, using the provided positions of the witness transactions.
+    pub fn at_height_with(
+        &self,
+        height: u32,
+        positions: BTreeMap<XWitnessId, WitnessOrd>,
+    ) -> StateSnapshot<'_> {
+        StateSnapshot {
+            state: self,
+            height,
+            positions,
+        }
+    }
+}
+
+/// View of the [`ContractState`] at some past block height.
+///
+/// Unlike the contract state itself, which lists all known allocations, the
+/// snapshot lists only allocations unspent at the height, such that it can be
+/// used to compute historical balances.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct StateSnapshot<'state> {
+    state: &'state ContractState,
+    height: u32,
+    positions: BTreeMap<XWitnessId, WitnessOrd>,
+}
+
+impl<'state> StateSnapshot<'state> {
+    pub fn height(&self) -> u32 { self.height }
+
+    pub fn schema_id(&self) -> SchemaId { self.state.schema_id() }
+
+    pub fn contract_id(&self) -> ContractId { self.state.contract_id() }
+
+    /// Detects whether the witness was mined at or below the snapshot height.
+    pub fn is_witness_included(&self, witness_id: XWitnessId) -> bool {
+        self.positions
+            .get(&witness_id)
+            .is_some_and(|witness_ord| self.includes(*witness_ord))
+    }
+
+    fn includes(&self, witness_ord: WitnessOrd) -> bool {
+        matches!(witness_ord, WitnessOrd::OnChain(pos) if pos.height().get() <= self.height)
+    }
+
+    /// Returns global state items of the given type defined at the snapshot
+    /// height, in the consensus order.
+    pub fn global_state(
+        &self,
+        ty: GlobalStateType,
+    ) -> impl Iterator<Item = (GlobalOrd, &'state DataState)> + '_ {
+        self.state
+            .global_state(ty)
+            .into_iter()
+            .flat_map(move |global| {
+                global.iter().filter(move |(ord, _)| {
+                    ord.witness_anchor
+                        .map_or(true, |anchor| self.is_witness_included(anchor.witness_id))
+                })
+            })
+    }
+
+    pub fn rights(&self) -> impl Iterator<Item = &'state OutputAssignment<VoidState>> + '_ {
+        self.filter(self.state.rights())
+    }
+
+    pub fn fungibles(&self) -> impl Iterator<Item = &'state OutputAssignment<RevealedValue>> + '_ {
+        self.filter(self.state.fungibles())
+    }
+
+    pub fn data(&self) -> impl Iterator<Item = &'state OutputAssignment<RevealedData>> + '_ {
+        self.filter(self.state.data())
+    }
+
+    pub fn attach(&self) -> impl Iterator<Item = &'state OutputAssignment<RevealedAttach>> + '_ {
+        self.filter(self.state.attach())
+    }
+
+    fn filter<'a, State: KnownState + 'state>(
+        &'a self,
+        assignments: impl IntoIterator<Item = &'state OutputAssignment<State>> + 'a,
+    ) -> impl Iterator<Item = &'state OutputAssignment<State>> + 'a {
+        assignments
+            .into_iter()
+            .filter(move |a| match a.witness {
+                AssignmentWitness::Absent => true,
+                AssignmentWitness::Present(witness_id) => self.is_witness_included(witness_id),
+            })
+            .filter(move |a| {
+                self.state.spent_by(a.opout).map_or(true, |spender| {
+                    !self.is_witness_included(spender.witness_anchor.witness_id)
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::{Confined, SmallBlob};
+    use amplify::Wrapper;
+    use bp::Txid;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{
+        Assign, AssignmentType, Assignments, ContractHistory, Extension, Genesis, Input, Inputs,
+        Operation, Opout, Schema, Transition, TypedAssigns, WitnessAnchor, WitnessPos, XChain,
+        XGenesisSeal, XGraphSeal,
+    };
+
+    fn anchor(no: u8, height: u32) -> WitnessAnchor {
+        WitnessAnchor {
+            witness_ord: WitnessOrd::OnChain(WitnessPos::new(height, 1700000000).unwrap()),
+            witness_id: XChain::Bitcoin(Txid::from([no; 32])),
+        }
+    }
+
+    // Extension mined at height 100 allocates a right which is spent by a
+    // transition mined at height 200.
+    fn state() -> (ContractState, Extension, Transition) {
+        let ty = AssignmentType::with(1);
+        let genesis = Genesis::strict_dumb();
+        let mut history = ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis);
+
+        let mut extension = Extension::strict_dumb();
+        extension
+            .globals
+            .add_state(GlobalStateType::with(1), DataState::from(SmallBlob::from_iter_unsafe([1])))
+            .unwrap();
+        extension.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            ty,
+            TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                XGenesisSeal::strict_dumb(),
+                VoidState::default(),
+            )])),
+        )]));
+        history.add_extension(&extension, anchor(1, 100));
+
+        let mut transition = Transition::strict_dumb();
+        transition.inputs =
+            Inputs::try_from_inputs([Input::with(Opout::new(extension.id(), ty, 0))]).unwrap();
+        transition.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            ty,
+            TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                XGraphSeal::strict_dumb(),
+                VoidState::default(),
+            )])),
+        )]));
+        history.add_transition(&transition, anchor(2, 200));
+
+        let state = ContractState {
+            schema: Schema::strict_dumb(),
+            history,
+        };
+        (state, extension, transition)
+    }
+
+    #[test]
+    fn historical_allocations() {
+        let (state, extension, transition) = state();
+        assert_eq!(state.rights().len(), 2);
+
+        let snapshot = state.at_height(50);
+        assert_eq!(snapshot.rights().count(), 0);
+        assert_eq!(snapshot.global_state(GlobalStateType::with(1)).count(), 0);
+
+        let snapshot = state.at_height(150);
+        let rights = snapshot.rights().collect::<Vec<_>>();
+        assert_eq!(rights.len(), 1);
+        assert_eq!(rights[0].opout.op, extension.id());
+        assert_eq!(snapshot.global_state(GlobalStateType::with(1)).count(), 1);
+
+        let snapshot = state.at_height(250);
+        let rights = snapshot.rights().collect::<Vec<_>>();
+        assert_eq!(rights.len(), 1);
+        assert_eq!(rights[0].opout.op, transition.id());
+        assert_eq!(snapshot.global_state(GlobalStateType::with(1)).count(), 1);
+    }
+
+    #[test]
+    fn explicit_positions() {
+        let (state, extension, _) = state();
+        let witness_id = XChain::Bitcoin(Txid::from([1; 32]));
+        let pending_id = XChain::Bitcoin(Txid::from([2; 32]));
+
+        // The spending transaction got reorged out of the chain
+        let positions = bmap! { witness_id => anchor(1, 100).witness_ord };
+        let snapshot = state.at_height_with(250, positions);
+        assert!(snapshot.is_witness_included(witness_id));
+        assert!(!snapshot.is_witness_included(pending_id));
+        let rights = snapshot.rights().collect::<Vec<_>>();
+        assert_eq!(rights.len(), 1);
+        assert_eq!(rights[0].opout.op, extension.id());
+
+        let snapshot = state.at_height_with(250, empty!());
+        assert_eq!(snapshot.rights().count(), 0);
+        assert_eq!(snapshot.global_state(GlobalStateType::with(1)).count(), 0);
+    }
+}
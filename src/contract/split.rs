@@ -0,0 +1,290 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Splitting and merging of fungible allocations.
+//!
+//! Wallets often need to split a single allocation into several (for
+//! instance, to pay and to get change) or to merge several allocations into
+//! one. Constructing such transitions by hand is error-prone: the amounts
+//! must be conserved, and the blinding factors of the outputs must balance
+//! the blinding factors of the inputs, or the Pedersen commitment sums won't
+//! match. The helpers here produce the transition, pick the blinding
+//! factors and check the commitment balance in a single step.
+
+use std::collections::BTreeSet;
+
+use amplify::Wrapper;
+use commit_verify::{CommitVerify, Conceal};
+use secp256k1_zkp::SECP256K1;
+
+use crate::schema::TransitionType;
+use crate::{
+    confine, Assign, Assignments, BlindingFactor, CollectionLimitError, ConcealedValue, ContractId,
+    GraphSeal, Input, Inputs, NonEmptyAssignments, NonEmptyInputs, OpOutputs, Opout,
+    PedersenCommitment, RevealedValue, Transition, TypedAssigns, XChain,
+};
+
+/// Revealed fungible allocation spent by a split or merge.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct FungibleInput {
+    pub prev_out: Opout,
+    pub state: RevealedValue,
+}
+
+impl FungibleInput {
+    pub fn new(prev_out: Opout, state: RevealedValue) -> Self { FungibleInput { prev_out, state } }
+}
+
+/// Transition produced by a split or merge, together with the concealed form
+/// of its outputs, in the order the outputs were provided.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Rebalanced {
+    pub transition: Transition,
+    pub concealed: Vec<ConcealedValue>,
+}
+
+/// Errors splitting or merging fungible allocations.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum RebalanceError {
+    /// no input allocations were provided.
+    NoInputs,
+
+    /// no output allocations were provided.
+    NoOutputs,
+
+    /// input {0} is provided more than once.
+    DuplicateInput(Opout),
+
+    /// input {0} has state type different from the state type of the first
+    /// input.
+    TypeMismatch(Opout),
+
+    /// input {0} has asset tag different from the asset tag of the first
+    /// input.
+    TagMismatch(Opout),
+
+    /// merged inputs sum up to {0}, which exceeds 64-bit range.
+    AmountOverflow(u128),
+
+    /// inputs sum up to {inputs}, while outputs sum up to {outputs}.
+    SumMismatch { inputs: u128, outputs: u128 },
+
+    /// {0}
+    #[from]
+    Limits(CollectionLimitError),
+
+    /// unable to find blinding factors balancing the inputs; retry with other
+    /// output blinding factors.
+    Blinding,
+
+    /// commitments of the produced outputs don't sum up to the commitments of
+    /// the inputs.
+    Unbalanced,
+}
+
+impl Transition {
+    /// Splits a single fungible allocation into several allocations with the
+    /// given amounts.
+    pub fn split(
+        contract_id: ContractId,
+        transition_type: TransitionType,
+        input: FungibleInput,
+        outputs: impl IntoIterator<Item = (XChain<GraphSeal>, u64)>,
+    ) -> Result<Rebalanced, RebalanceError> {
+        Self::rebalance(contract_id, transition_type, [input], outputs)
+    }
+
+    /// Merges several fungible allocations of the same asset into a single
+    /// allocation assigned to the seal.
+    pub fn merge(
+        contract_id: ContractId,
+        transition_type: TransitionType,
+        inputs: impl IntoIterator<Item = FungibleInput>,
+        seal: XChain<GraphSeal>,
+    ) -> Result<Rebalanced, RebalanceError> {
+        let inputs = inputs.into_iter().collect::<Vec<_>>();
+        let sum = inputs
+            .iter()
+            .map(|i| i.state.value.as_u64() as u128)
+            .sum::<u128>();
+        let value = u64::try_from(sum).map_err(|_| RebalanceError::AmountOverflow(sum))?;
+        Self::rebalance(contract_id, transition_type, inputs, [(seal, value)])
+    }
+
+    /// Constructs transition spending fungible allocations of a single asset
+    /// and assigning the same total amount to the new seals.
+    ///
+    /// The last output gets a blinding factor balancing all other blinding
+    /// factors, such that the transition satisfies the Pedersen commitment
+    /// conservation rule. The balance is verified before the transition is
+    /// returned.
+    pub fn rebalance(
+        contract_id: ContractId,
+        transition_type: TransitionType,
+        inputs: impl IntoIterator<Item = FungibleInput>,
+        outputs: impl IntoIterator<Item = (XChain<GraphSeal>, u64)>,
+    ) -> Result<Rebalanced, RebalanceError> {
+        let inputs = inputs.into_iter().collect::<Vec<_>>();
+        let outputs = outputs.into_iter().collect::<Vec<_>>();
+        let (first, last) = match (inputs.first(), outputs.last()) {
+            (None, _) => return Err(RebalanceError::NoInputs),
+            (_, None) => return Err(RebalanceError::NoOutputs),
+            (Some(first), Some(last)) => (*first, *last),
+        };
+        let ty = first.prev_out.ty;
+        let tag = first.state.tag;
+
+        let mut seen = BTreeSet::new();
+        for input in &inputs {
+            if !seen.insert(input.prev_out) {
+                return Err(RebalanceError::DuplicateInput(input.prev_out));
+            }
+            if input.prev_out.ty != ty {
+                return Err(RebalanceError::TypeMismatch(input.prev_out));
+            }
+            if input.state.tag != tag {
+                return Err(RebalanceError::TagMismatch(input.prev_out));
+            }
+        }
+        let input_sum = inputs.iter().map(|i| i.state.value.as_u64() as u128).sum();
+        let output_sum = outputs.iter().map(|(_, value)| *value as u128).sum();
+        if input_sum != output_sum {
+            return Err(RebalanceError::SumMismatch {
+                inputs: input_sum,
+                outputs: output_sum,
+            });
+        }
+
+        let mut states = outputs[..outputs.len() - 1]
+            .iter()
+            .map(|(_, value)| RevealedValue::new_random_blinding(*value, tag))
+            .collect::<Vec<_>>();
+        let balancing = BlindingFactor::zero_balanced(
+            inputs.iter().map(|i| i.state.blinding),
+            states.iter().map(|s| s.blinding),
+        )
+        .map_err(|_| RebalanceError::Blinding)?;
+        states.push(RevealedValue::with_blinding(last.1, balancing, tag));
+
+        let commit = |state: &RevealedValue| PedersenCommitment::commit(state).into_inner();
+        let input_commitments = inputs.iter().map(|i| commit(&i.state)).collect::<Vec<_>>();
+        let output_commitments = states.iter().map(commit).collect::<Vec<_>>();
+        if !secp256k1_zkp::verify_commitments_sum_to_equal(
+            SECP256K1,
+            &input_commitments,
+            &output_commitments,
+        ) {
+            return Err(RebalanceError::Unbalanced);
+        }
+
+        let assigns = outputs
+            .iter()
+            .zip(&states)
+            .map(|((seal, _), state)| Assign::revealed(*seal, *state))
+            .collect::<Vec<_>>();
+        let concealed = assigns
+            .iter()
+            .map(|assign| assign.to_confidential_state())
+            .collect();
+        let assignments = Assignments::from_inner(confine(
+            "transition assignment types",
+            bmap! { ty => TypedAssigns::Fungible(confine("fungible assignments", assigns)?) },
+        )?);
+        let inputs = Inputs::try_from_inputs(inputs.iter().map(|i| Input::with(i.prev_out)))?;
+
+        let transition = Transition::with(
+            contract_id,
+            transition_type,
+            NonEmptyInputs::try_from(inputs).expect("inputs are checked to be non-empty"),
+            OpOutputs::with_assignments(
+                NonEmptyAssignments::try_from(assignments)
+                    .expect("outputs are checked to be non-empty"),
+                default!(),
+            ),
+        );
+        Ok(Rebalanced {
+            transition,
+            concealed,
+        })
+    }
+}
+
+impl Rebalanced {
+    /// Returns the transition with all its output seals and amounts
+    /// concealed, ready to be shared with parties which must not learn them.
+    pub fn concealed_transition(&self) -> Transition { self.transition.conceal() }
+}
+
+#[cfg(test)]
+mod test {
+    use bp::seals::txout::CloseMethod;
+    use bp::Vout;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{AssetTag, AssignmentType, BlindingSeed, Layer1, OpId, Operation};
+
+    #[test]
+    fn split_merge() {
+        let seed = BlindingSeed::from([0x42; 32]);
+        let tag = AssetTag::strict_dumb();
+        let ty = AssignmentType::with(4000);
+        let contract_id = ContractId::strict_dumb();
+        let seal = |no: u32| {
+            seed.graph_seal(Layer1::Bitcoin, CloseMethod::OpretFirst, None, Vout::from(no), no)
+        };
+        let input = FungibleInput::new(
+            Opout::new(OpId::from([1u8; 32]), ty, 0),
+            RevealedValue::new_random_blinding(100u64, tag),
+        );
+
+        let split = Transition::split(contract_id, TransitionType::with(1), input, [
+            (seal(0), 30),
+            (seal(1), 70),
+        ])
+        .unwrap();
+        assert_eq!(split.concealed.len(), 2);
+        assert_eq!(
+            Transition::split(contract_id, TransitionType::with(1), input, [(seal(0), 99)]),
+            Err(RebalanceError::SumMismatch {
+                inputs: 100,
+                outputs: 99
+            })
+        );
+
+        let opid = split.transition.id();
+        let assigns = split.transition.assignments.get(&ty).unwrap();
+        let inputs = assigns
+            .as_fungible()
+            .iter()
+            .zip(0u16..)
+            .map(|(assign, no)| {
+                FungibleInput::new(Opout::new(opid, ty, no), *assign.as_revealed_state().unwrap())
+            });
+        let merged =
+            Transition::merge(contract_id, TransitionType::with(1), inputs, seal(2)).unwrap();
+        let assigns = merged.transition.assignments.get(&ty).unwrap();
+        let state = assigns.as_fungible()[0].as_revealed_state().unwrap();
+        assert_eq!(state.value.as_u64(), 100);
+    }
+}
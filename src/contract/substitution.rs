@@ -0,0 +1,140 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Substitution of witness transactions for already anchored bundles.
+//!
+//! State transitions do not commit to their witness transaction, thus a
+//! bundle can be re-anchored to a different transaction when the original
+//! one gets stuck and is replaced (for instance, fee-bumped with RBF). The
+//! substitution proof links the replaced witness to its replacement, such that
+//! the validator can check that the replacement preserves all the outputs
+//! which may be referenced by the seals of the bundle transitions.
+
+use std::collections::BTreeSet;
+
+use crate::{BundleId, XWitnessId, XWitnessTx, LIB_NAME_RGB};
+
+/// Errors of the witness substitution verification.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum SubstitutionError {
+    /// transaction {0} is provided instead of the replaced witness {1}.
+    ReplacedMismatch(XWitnessId, XWitnessId),
+
+    /// transaction {0} is provided instead of the replacement witness {1}.
+    ReplacementMismatch(XWitnessId, XWitnessId),
+
+    /// replacement witness {0} is the same as the replaced one.
+    SameWitness(XWitnessId),
+
+    /// replacement witness {0} doesn't conflict with the replaced witness {1},
+    /// i.e. they do not spend a common input.
+    NoConflict(XWitnessId, XWitnessId),
+
+    /// replacement witness {0} doesn't preserve output #{1} of the replaced
+    /// witness.
+    OutputNotPreserved(XWitnessId, u32),
+}
+
+/// Proof that a transition bundle was re-anchored from one witness
+/// transaction to another one, replacing it.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct WitnessSubstitution {
+    pub bundle_id: BundleId,
+    pub replaced: XWitnessId,
+    pub replacement: XWitnessId,
+}
+
+impl WitnessSubstitution {
+    pub fn new(bundle_id: BundleId, replaced: XWitnessId, replacement: XWitnessId) -> Self {
+        WitnessSubstitution {
+            bundle_id,
+            replaced,
+            replacement,
+        }
+    }
+
+    /// Verifies substitution against the replaced and replacement witness
+    /// transactions.
+    ///
+    /// The replacement must be a transaction on the same layer 1, spending at
+    /// least one of the inputs of the replaced transaction (such that both of
+    /// them can't be mined), and keeping all the outputs of the replaced
+    /// transaction at the same positions with the same scripts. Output values
+    /// may differ, allowing fee bumping.
+    pub fn verify(
+        &self,
+        replaced_tx: &XWitnessTx,
+        replacement_tx: &XWitnessTx,
+    ) -> Result<(), SubstitutionError> {
+        let replaced_id = replaced_tx.witness_id();
+        if replaced_id != self.replaced {
+            return Err(SubstitutionError::ReplacedMismatch(replaced_id, self.replaced));
+        }
+        let replacement_id = replacement_tx.witness_id();
+        if replacement_id != self.replacement {
+            return Err(SubstitutionError::ReplacementMismatch(replacement_id, self.replacement));
+        }
+        if self.replaced == self.replacement {
+            return Err(SubstitutionError::SameWitness(self.replacement));
+        }
+        if replaced_tx.layer1() != replacement_tx.layer1() {
+            return Err(SubstitutionError::NoConflict(self.replacement, self.replaced));
+        }
+
+        let replaced_tx = replaced_tx.as_reduced_unsafe();
+        let replacement_tx = replacement_tx.as_reduced_unsafe();
+
+        let spent = replaced_tx
+            .inputs
+            .iter()
+            .map(|input| input.prev_output)
+            .collect::<BTreeSet<_>>();
+        if !replacement_tx
+            .inputs
+            .iter()
+            .any(|input| spent.contains(&input.prev_output))
+        {
+            return Err(SubstitutionError::NoConflict(self.replacement, self.replaced));
+        }
+
+        for (vout, output) in replaced_tx.outputs.iter().enumerate() {
+            match replacement_tx.outputs.get(vout) {
+                Some(o) if o.script_pubkey == output.script_pubkey => {}
+                _ => {
+                    return Err(SubstitutionError::OutputNotPreserved(
+                        self.replacement,
+                        vout as u32,
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Supply caps of fungible state.
+//!
+//! Contract genesis may declare the maximum total supply for each fungible
+//! assignment type in its [`schema::MetaType::SUPPLY_CAPS`] metadata, which
+//! is permitted by the schema declaring the metadata type for the genesis.
+//! Unlike caps implemented by the schema scripts, the declared caps are
+//! enforced by the validator itself: [`SupplyTracker`] accumulates the amount
+//! issued by each operation (the excess of the revealed outputs over the
+//! spent inputs) and reports operations exceeding the cap. Since the issued
+//! amount can't be computed from concealed state, operations concealing
+//! capped state are reported as uncheckable.
+
+use std::collections::BTreeMap;
+
+use amplify::confinement::Confined;
+
+use crate::{schema, AssignmentType, Genesis, MetaValue, OpId, OpRef, Operation, Opout};
+
+impl Genesis {
+    /// Returns supply caps declared by the [`schema::MetaType::SUPPLY_CAPS`]
+    /// metadata. If the metadata is absent, the supply is not capped.
+    ///
+    /// Returns `None` if the metadata value is malformed.
+    pub fn supply_caps(&self) -> Option<BTreeMap<AssignmentType, u64>> {
+        let Some(meta) = self.metadata.get(&schema::MetaType::SUPPLY_CAPS) else {
+            return Some(empty!());
+        };
+        let data = meta.as_slice();
+        if data.is_empty() || data.len() % 10 != 0 {
+            return None;
+        }
+        let caps = data
+            .chunks(10)
+            .map(|chunk| {
+                let ty = AssignmentType::with(u16::from_le_bytes([chunk[0], chunk[1]]));
+                let cap = u64::from_le_bytes(chunk[2..].try_into().expect("fixed size"));
+                (ty, cap)
+            })
+            .collect::<BTreeMap<_, _>>();
+        if caps.len() * 10 != data.len() {
+            return None;
+        }
+        Some(caps)
+    }
+}
+
+/// Constructs value for the [`schema::MetaType::SUPPLY_CAPS`] genesis
+/// metadata.
+///
+/// Returns `None` if there are no caps or they don't fit the metadata size
+/// limit.
+pub fn supply_caps_meta(caps: &BTreeMap<AssignmentType, u64>) -> Option<MetaValue> {
+    if caps.is_empty() {
+        return None;
+    }
+    let data = caps
+        .iter()
+        .flat_map(|(ty, cap)| {
+            let mut entry = ty.to_le_bytes().to_vec();
+            entry.extend(cap.to_le_bytes());
+            entry
+        })
+        .collect::<Vec<_>>();
+    Confined::try_from(data).ok().map(MetaValue::from)
+}
+
+/// Error tracking the issued supply.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum SupplyError {
+    /// operation {opid} issues {issued} units of {ty}, exceeding the supply cap
+    /// of {cap} units.
+    CapExceeded {
+        opid: OpId,
+        ty: AssignmentType,
+        cap: u64,
+        issued: u128,
+    },
+
+    /// operation {opid} conceals state of {ty} or spends unknown state of
+    /// this type, so the issued supply can't be checked against the cap.
+    Uncheckable { opid: OpId, ty: AssignmentType },
+}
+
+/// Tracker of the supply issued by contract operations.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct SupplyTracker {
+    caps: BTreeMap<AssignmentType, u64>,
+    issued: BTreeMap<AssignmentType, u128>,
+}
+
+impl SupplyTracker {
+    pub fn with(caps: BTreeMap<AssignmentType, u64>) -> Self {
+        SupplyTracker {
+            caps,
+            issued: empty!(),
+        }
+    }
+
+    /// Constructs tracker with the caps declared by the contract genesis.
+    ///
+    /// Returns `None` if the declaration is malformed.
+    pub fn from_genesis(genesis: &Genesis) -> Option<Self> { genesis.supply_caps().map(Self::with) }
+
+    pub fn cap(&self, ty: AssignmentType) -> Option<u64> { self.caps.get(&ty).copied() }
+
+    /// Returns amount of the state of the given type issued by the registered
+    /// operations.
+    pub fn issued(&self, ty: AssignmentType) -> u128 {
+        self.issued.get(&ty).copied().unwrap_or_default()
+    }
+
+    /// Returns amount of the state of the given type which still can be
+    /// issued, or `None` if the supply is not capped.
+    pub fn remaining(&self, ty: AssignmentType) -> Option<u64> {
+        let cap = self.cap(ty)?;
+        Some((cap as u128).saturating_sub(self.issued(ty)) as u64)
+    }
+
+    /// Returns amount which still can be issued for each of the capped state
+    /// types.
+    pub fn remaining_supply(&self) -> BTreeMap<AssignmentType, u64> {
+        self.caps
+            .keys()
+            .filter_map(|ty| Some((*ty, self.remaining(*ty)?)))
+            .collect()
+    }
+
+    /// Registers supply issued by the operation, which is the excess of its
+    /// fungible outputs over the spent inputs, as provided by the `prev_value`
+    /// function.
+    ///
+    /// Operations are accounted for only once by the caller, but may be
+    /// registered in any order.
+    ///
+    /// # Errors
+    ///
+    /// - [`SupplyError::Uncheckable`] if the operation conceals outputs of a
+    ///   capped type or `prev_value` can't provide value of some of its inputs;
+    ///   nothing is registered for this type;
+    /// - [`SupplyError::CapExceeded`] if the total issued supply of some type
+    ///   exceeds its cap. The issued amount is registered nevertheless.
+    ///
+    /// Only the first error is returned if there are several of them.
+    pub fn register(
+        &mut self,
+        op: OpRef,
+        prev_value: impl Fn(Opout) -> Option<u64>,
+    ) -> Result<(), SupplyError> {
+        let opid = op.id();
+        let mut result = Ok(());
+        for ty in op.assignments().types() {
+            if !self.caps.contains_key(&ty) {
+                continue;
+            }
+            let Some(outputs) = op.assignments_by_type(ty).and_then(|assigns| {
+                assigns
+                    .as_fungible()
+                    .iter()
+                    .map(|assign| assign.as_revealed_state().map(|state| state.value.as_u64()))
+                    .try_fold(0u128, |sum, value| Some(sum + value? as u128))
+            }) else {
+                result = result.and(Err(SupplyError::Uncheckable { opid, ty }));
+                continue;
+            };
+            let Some(inputs) = op
+                .inputs()
+                .iter()
+                .filter(|input| input.prev_out.ty == ty)
+                .try_fold(0u128, |sum, input| Some(sum + prev_value(input.prev_out)? as u128))
+            else {
+                result = result.and(Err(SupplyError::Uncheckable { opid, ty }));
+                continue;
+            };
+            let issued = self.issued.entry(ty).or_default();
+            *issued += outputs.saturating_sub(inputs);
+            let cap = self.caps[&ty];
+            if *issued > cap as u128 {
+                result = result.and(Err(SupplyError::CapExceeded {
+                    opid,
+                    ty,
+                    cap,
+                    issued: *issued,
+                }));
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::Wrapper;
+    use bp::seals::txout::CloseMethod;
+    use bp::Vout;
+    use commit_verify::Conceal;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{
+        AssetTag, Assign, Assignments, BlindingSeed, Input, Inputs, Layer1, RevealedValue,
+        Transition, TypedAssigns,
+    };
+
+    #[test]
+    fn caps_meta() {
+        let caps = bmap! { AssignmentType::with(1) => 21_000_000, AssignmentType::with(4) => 0 };
+        let meta = supply_caps_meta(&caps).unwrap();
+        assert_eq!(meta.len(), 20);
+        let tracker = SupplyTracker::with(caps);
+        assert_eq!(tracker.remaining(AssignmentType::with(1)), Some(21_000_000));
+        assert_eq!(tracker.remaining(AssignmentType::with(2)), None);
+    }
+
+    #[test]
+    fn concealed_uncheckable() {
+        let ty = AssignmentType::with(4000);
+        let tag = AssetTag::strict_dumb();
+        let seed = BlindingSeed::from([0x33; 32]);
+        let seal = |no: u32| {
+            seed.graph_seal(Layer1::Bitcoin, CloseMethod::OpretFirst, None, Vout::from(no), no)
+        };
+        let prev_out = Opout::new(OpId::strict_dumb(), ty, 0);
+        let mut transition = Transition::strict_dumb();
+        transition.inputs = Inputs::try_from_inputs([Input::with(prev_out)]).unwrap();
+        transition.assignments = Assignments::from_inner(
+            Confined::try_from(bmap! {
+                ty => TypedAssigns::Fungible(Confined::try_from(vec![
+                    Assign::revealed(seal(1), RevealedValue::new_random_blinding(60u64, tag)),
+                    Assign::revealed(seal(2), RevealedValue::new_random_blinding(50u64, tag)),
+                ]).unwrap())
+            })
+            .unwrap(),
+        );
+        let opid = transition.id();
+
+        let mut tracker = SupplyTracker::with(bmap! { ty => 100 });
+        let op = OpRef::Transition(&transition);
+        assert_eq!(tracker.register(op, |_| None), Err(SupplyError::Uncheckable { opid, ty }));
+        assert_eq!(tracker.issued(ty), 0);
+        tracker.register(op, |_| Some(20)).unwrap();
+        assert_eq!(tracker.remaining(ty), Some(10));
+        assert_eq!(tracker.register(op, |_| Some(100)), Ok(()));
+        assert_eq!(tracker.remaining(ty), Some(0));
+
+        let concealed = transition.assignments.get(&ty).unwrap().conceal();
+        transition.assignments =
+            Assignments::from_inner(Confined::try_from(bmap! { ty => concealed }).unwrap());
+        let opid = transition.id();
+        let op = OpRef::Transition(&transition);
+        assert_eq!(tracker.register(op, |_| Some(0)), Err(SupplyError::Uncheckable { opid, ty }));
+    }
+}
@@ -0,0 +1,228 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Genesis templates for issuance before the seals are known.
+//!
+//! Issuance often requires a legal sign-off of the contract terms, amounts
+//! and allocation structure, which may happen before the UTXOs receiving the
+//! issued state exist. The issuer constructs the genesis with placeholder
+//! seals and signs off the [`GenesisTemplateId`], which commits to all the
+//! genesis data except the seals. Once the UTXOs are known, the template is
+//! bound to them with [`Genesis::bind_template`], which replaces the
+//! placeholders in place and records the template id in the
+//! [`schema::MetaType::GENESIS_TEMPLATE`] metadata. The validator checks that
+//! the recorded template id matches the genesis, linking the final contract
+//! to the signed-off template.
+
+use amplify::confinement::{Confined, SmallVec};
+use amplify::{ByteArray, Bytes32};
+use commit_verify::{CommitId, DigestExt, Sha256};
+use strict_encoding::StrictDumb;
+
+use crate::{
+    schema, Assign, ExposedState, Genesis, GenesisSeal, MetaValue, MetadataError, TypedAssigns,
+    XChain,
+};
+
+pub const GENESIS_TEMPLATE_TAG: &[u8] = b"urn:lnp-bp:rgb:genesis-template#2024-05-20";
+
+/// Identifier of a genesis template, committing to all genesis data except
+/// the seal definitions.
+#[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From)]
+#[wrapper(Deref, BorrowSlice, Display, Hex, Index, RangeOps)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", transparent)
+)]
+pub struct GenesisTemplateId(
+    #[from]
+    #[from([u8; 32])]
+    Bytes32,
+);
+
+/// Errors binding genesis template to the seals.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum TemplateError {
+    /// genesis template has {0} seal placeholders, while {1} seals were
+    /// provided.
+    SealCountMismatch(usize, usize),
+
+    /// {0}
+    #[from]
+    Metadata(MetadataError),
+}
+
+fn replace_seals<State: ExposedState>(
+    assigns: &SmallVec<Assign<State, GenesisSeal>>,
+    next_seal: &mut impl FnMut() -> XChain<GenesisSeal>,
+) -> SmallVec<Assign<State, GenesisSeal>> {
+    Confined::try_from_iter(
+        assigns
+            .iter()
+            .map(|a| Assign::with_seal_replaced(a, next_seal())),
+    )
+    .expect("same size")
+}
+
+impl Genesis {
+    fn with_seals_replaced(&self, mut next_seal: impl FnMut() -> XChain<GenesisSeal>) -> Genesis {
+        let mut genesis = self.clone();
+        genesis.assignments.keyed_values_mut().for_each(|(_, a)| {
+            *a = match &*a {
+                TypedAssigns::Declarative(a) => {
+                    TypedAssigns::Declarative(replace_seals(a, &mut next_seal))
+                }
+                TypedAssigns::Fungible(a) => {
+                    TypedAssigns::Fungible(replace_seals(a, &mut next_seal))
+                }
+                TypedAssigns::Structured(a) => {
+                    TypedAssigns::Structured(replace_seals(a, &mut next_seal))
+                }
+                TypedAssigns::Attachment(a) => {
+                    TypedAssigns::Attachment(replace_seals(a, &mut next_seal))
+                }
+            }
+        });
+        genesis
+    }
+
+    /// Returns the genesis template: the genesis with all seals replaced by
+    /// placeholders and without the template id metadata.
+    pub fn to_template(&self) -> Genesis {
+        let mut template = self.with_seals_replaced(XChain::<GenesisSeal>::strict_dumb);
+        template
+            .metadata
+            .remove(&schema::MetaType::GENESIS_TEMPLATE)
+            .ok();
+        template
+    }
+
+    /// Computes id of the genesis template, which doesn't depend on the seals
+    /// and on the template id metadata.
+    pub fn template_id(&self) -> GenesisTemplateId {
+        let mut hasher = Sha256::default();
+        hasher.input_raw(GENESIS_TEMPLATE_TAG);
+        hasher.input_raw(&self.to_template().commit_id().to_byte_array());
+        GenesisTemplateId::from(hasher.finish())
+    }
+
+    /// Returns template id declared by the
+    /// [`schema::MetaType::GENESIS_TEMPLATE`] metadata, or `Some(None)` if
+    /// the metadata is absent.
+    ///
+    /// Returns `None` if the metadata value is malformed.
+    pub fn declared_template_id(&self) -> Option<Option<GenesisTemplateId>> {
+        let Some(meta) = self.metadata.get(&schema::MetaType::GENESIS_TEMPLATE) else {
+            return Some(None);
+        };
+        let id: [u8; 32] = meta.as_slice().try_into().ok()?;
+        Some(Some(GenesisTemplateId::from(id)))
+    }
+
+    /// Binds the template to the seals, replacing the placeholders in the
+    /// order of the assignment types and assignment indexes, and records the
+    /// template id in the genesis metadata.
+    pub fn bind_template(
+        &self,
+        seals: impl IntoIterator<Item = XChain<GenesisSeal>>,
+    ) -> Result<Genesis, TemplateError> {
+        let template_id = self.template_id();
+        let placeholders = self
+            .assignments
+            .values()
+            .map(|a| a.len_u16() as usize)
+            .sum::<usize>();
+        let seals = seals.into_iter().collect::<Vec<_>>();
+        if seals.len() != placeholders {
+            return Err(TemplateError::SealCountMismatch(placeholders, seals.len()));
+        }
+        let mut seals = seals.into_iter();
+        let mut genesis =
+            self.with_seals_replaced(|| seals.next().expect("number of seals is checked"));
+        genesis
+            .metadata
+            .remove(&schema::MetaType::GENESIS_TEMPLATE)
+            .ok();
+        genesis
+            .metadata
+            .add_value(schema::MetaType::GENESIS_TEMPLATE, template_id_meta(template_id))?;
+        Ok(genesis)
+    }
+}
+
+/// Constructs value for the [`schema::MetaType::GENESIS_TEMPLATE`] genesis
+/// metadata.
+pub fn template_id_meta(id: GenesisTemplateId) -> MetaValue {
+    MetaValue::from(Confined::try_from(id.to_byte_array().to_vec()).expect("32 bytes"))
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::Wrapper;
+    use bp::seals::txout::CloseMethod;
+    use bp::{Outpoint, Txid, Vout};
+
+    use super::*;
+    use crate::{
+        AssetTag, AssignmentType, Assignments, BlindingSeed, Layer1, Operation, RevealedValue,
+    };
+
+    #[test]
+    fn bind_template() {
+        let ty = AssignmentType::with(4000);
+        let tag = AssetTag::strict_dumb();
+        let mut template = Genesis::strict_dumb();
+        let placeholder = XChain::<GenesisSeal>::strict_dumb();
+        let allocations = [100u64, 250].map(|amount| {
+            Assign::revealed(placeholder, RevealedValue::new_random_blinding(amount, tag))
+        });
+        template.assignments = Assignments::from_inner(
+            Confined::try_from(bmap! {
+                ty => TypedAssigns::Fungible(Confined::try_from(allocations.to_vec()).unwrap())
+            })
+            .unwrap(),
+        );
+        let template_id = template.template_id();
+
+        let seed = BlindingSeed::from([0x24; 32]);
+        let seals = (0u32..2)
+            .map(|no| {
+                let outpoint = Outpoint::new(Txid::from([7u8; 32]), Vout::from(no));
+                seed.genesis_seal(Layer1::Bitcoin, CloseMethod::OpretFirst, outpoint, no)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            template.bind_template(seals[..1].to_vec()),
+            Err(TemplateError::SealCountMismatch(2, 1))
+        );
+        let genesis = template.bind_template(seals).unwrap();
+        assert_ne!(genesis.contract_id(), template.contract_id());
+        assert_eq!(genesis.template_id(), template_id);
+        assert_eq!(genesis.declared_template_id(), Some(Some(template_id)));
+
+        let mut tampered = genesis.clone();
+        tampered.testnet = !tampered.testnet;
+        assert_ne!(tampered.template_id(), template_id);
+    }
+}
@@ -0,0 +1,201 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contract terms committed by the genesis.
+//!
+//! Legal or usage terms of a contract are committed into the genesis under
+//! the reserved [`GlobalStateType::TERMS`] global state type, if the schema
+//! opts into the reserved types (see [`crate::SchemaFlags`]). The state
+//! contains the [`DocumentId`] of the terms document, optionally followed by
+//! the terms text itself, if it is compact enough to fit the global state.
+//! Since the genesis commits to its global state, the terms become a part of
+//! the contract id; the validator checks that the terms state is well-formed
+//! and is defined only by the genesis.
+
+use amplify::confinement::SmallBlob;
+use amplify::ByteArray;
+
+use crate::schema::GlobalStateType;
+use crate::{ContractHistory, DataState, DocumentId, Genesis, GlobalState};
+
+/// Errors parsing and verifying contract terms.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(doc_comments)]
+pub enum TermsError {
+    /// contract terms state must contain a 32-byte document id.
+    NoDocumentId,
+
+    /// contract terms text is not a valid UTF-8 string.
+    InvalidText,
+
+    /// contract terms text is too large to fit the global state.
+    TooLarge,
+
+    /// contract terms document doesn't match the committed document id
+    /// {expected}; the document id is {actual}.
+    DocumentMismatch {
+        expected: DocumentId,
+        actual: DocumentId,
+    },
+
+    /// contract terms are defined more than once.
+    Duplicated,
+}
+
+/// Contract terms committed by the genesis.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct ContractTerms {
+    pub document_id: DocumentId,
+    pub text: Option<String>,
+}
+
+impl ContractTerms {
+    /// Constructs terms embedding the text into the contract.
+    pub fn with_text(text: impl Into<String>) -> Self {
+        let text = text.into();
+        ContractTerms {
+            document_id: DocumentId::with_content(&text),
+            text: Some(text),
+        }
+    }
+
+    /// Constructs terms committing only to the id of an external document.
+    pub fn with_document(document_id: DocumentId) -> Self {
+        ContractTerms {
+            document_id,
+            text: None,
+        }
+    }
+
+    /// Parses terms from the global state data, checking that the embedded
+    /// text, if any, matches the document id.
+    pub fn from_data(data: &DataState) -> Result<Self, TermsError> {
+        let data = data.as_slice();
+        if data.len() < 32 {
+            return Err(TermsError::NoDocumentId);
+        }
+        let (id, text) = data.split_at(32);
+        let document_id = DocumentId::from(<[u8; 32]>::try_from(id).expect("32 bytes"));
+        let terms = if text.is_empty() {
+            ContractTerms::with_document(document_id)
+        } else {
+            let text = String::from_utf8(text.to_vec()).map_err(|_| TermsError::InvalidText)?;
+            let terms = ContractTerms {
+                document_id,
+                text: Some(text),
+            };
+            terms.verify_text()?;
+            terms
+        };
+        Ok(terms)
+    }
+
+    /// Serializes terms into the global state data.
+    pub fn to_data(&self) -> Result<DataState, TermsError> {
+        let mut data = self.document_id.to_byte_array().to_vec();
+        if let Some(text) = &self.text {
+            data.extend(text.as_bytes());
+        }
+        SmallBlob::try_from(data)
+            .map(DataState::from)
+            .map_err(|_| TermsError::TooLarge)
+    }
+
+    /// Verifies that the terms document matches the committed document id.
+    pub fn verify(&self, document: impl AsRef<[u8]>) -> Result<(), TermsError> {
+        let actual = DocumentId::with_content(document);
+        if actual != self.document_id {
+            return Err(TermsError::DocumentMismatch {
+                expected: self.document_id,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    fn verify_text(&self) -> Result<(), TermsError> {
+        match &self.text {
+            Some(text) => self.verify(text),
+            None => Ok(()),
+        }
+    }
+
+    /// Extracts terms from the global state of an operation, if present.
+    pub fn from_globals(globals: &GlobalState) -> Option<Result<Self, TermsError>> {
+        let values = globals.get(&GlobalStateType::TERMS)?;
+        if values.len() > 1 {
+            return Some(Err(TermsError::Duplicated));
+        }
+        values.first().map(ContractTerms::from_data)
+    }
+}
+
+impl Genesis {
+    /// Returns contract terms committed by the genesis, if any.
+    pub fn terms(&self) -> Option<Result<ContractTerms, TermsError>> {
+        ContractTerms::from_globals(&self.globals)
+    }
+}
+
+impl ContractHistory {
+    /// Returns contract terms committed by the genesis, if any.
+    ///
+    /// Terms defined by other operations are rejected by the validator and
+    /// ignored here.
+    pub fn terms(&self) -> Option<Result<ContractTerms, TermsError>> {
+        self.global_state(GlobalStateType::TERMS)?
+            .iter()
+            .find(|(ord, _)| ord.witness_anchor.is_none())
+            .map(|(_, data)| ContractTerms::from_data(data))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn terms_roundtrip() {
+        let terms = ContractTerms::with_text("Redeemable at par by the issuer.");
+        let data = terms.to_data().unwrap();
+        assert_eq!(ContractTerms::from_data(&data), Ok(terms.clone()));
+        assert_eq!(terms.verify("Redeemable at par by the issuer."), Ok(()));
+        assert!(terms.verify("Not redeemable.").is_err());
+
+        let external = ContractTerms::with_document(terms.document_id);
+        let data = external.to_data().unwrap();
+        assert_eq!(ContractTerms::from_data(&data), Ok(external));
+
+        let mut forged = terms.document_id.to_byte_array().to_vec();
+        forged.extend(b"Not redeemable.");
+        let forged = DataState::from(SmallBlob::try_from(forged).unwrap());
+        assert!(matches!(
+            ContractTerms::from_data(&forged),
+            Err(TermsError::DocumentMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,229 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Timestamping of auxiliary contract documents.
+//!
+//! Issuers may commit documents related to a contract (audit reports, legal
+//! prospectus etc.) into the multi-protocol commitment of any witness
+//! transaction. Documents are committed under a protocol id derived from the
+//! contract id, which is different from the contract id itself, such that
+//! document commitments never interfere with the commitments to the contract
+//! transition bundles.
+
+use amplify::{ByteArray, Bytes32, Wrapper};
+use bp::dbc::Proof;
+use commit_verify::mpc::{self, InvalidProof};
+use commit_verify::{DigestExt, Sha256};
+
+use crate::{ContractId, DbcError, EAnchor, XWitnessId, XWitnessTx, LIB_NAME_RGB};
+
+pub const DOCUMENT_ID_TAG: &[u8] = b"urn:lnp-bp:rgb:document#2024-05-20";
+pub const DOCUMENT_PROTOCOL_TAG: &[u8] = b"urn:lnp-bp:rgb:document-protocol#2024-05-20";
+
+/// Identifier of a document committed to a contract, which is a tagged hash
+/// of the document content.
+#[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From)]
+#[wrapper(Deref, BorrowSlice, Display, Hex, Index, RangeOps)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", transparent)
+)]
+pub struct DocumentId(
+    #[from]
+    #[from([u8; 32])]
+    Bytes32,
+);
+
+impl DocumentId {
+    pub fn with_content(data: impl AsRef<[u8]>) -> Self {
+        let mut hasher = Sha256::default();
+        hasher.input_raw(DOCUMENT_ID_TAG);
+        hasher.input_raw(data.as_ref());
+        DocumentId::from(hasher.finish())
+    }
+}
+
+impl From<DocumentId> for mpc::Message {
+    fn from(id: DocumentId) -> Self { mpc::Message::from_inner(id.into_inner()) }
+}
+
+impl ContractId {
+    /// Protocol id under which documents of the contract are committed into
+    /// the multi-protocol commitment tree.
+    pub fn document_protocol(&self) -> mpc::ProtocolId {
+        let mut hasher = Sha256::default();
+        hasher.input_raw(DOCUMENT_PROTOCOL_TAG);
+        hasher.input_raw(&self.to_byte_array());
+        mpc::ProtocolId::from_byte_array(hasher.finish())
+    }
+
+    /// Returns protocol id and message which has to be put into the
+    /// multi-protocol commitment tree of a witness transaction in order to
+    /// timestamp the document.
+    pub fn document_commitment(&self, document_id: DocumentId) -> (mpc::ProtocolId, mpc::Message) {
+        (self.document_protocol(), mpc::Message::from(document_id))
+    }
+}
+
+/// Errors verifying document timestamp.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum TimestampError {
+    /// timestamp is made for document {0}, while document {1} was provided.
+    DocumentMismatch(DocumentId, DocumentId),
+
+    /// transaction {0} is provided instead of the timestamp witness {1}.
+    WitnessMismatch(XWitnessId, XWitnessId),
+
+    /// document is not committed into the multi-protocol commitment. Details:
+    /// {0}
+    #[from]
+    Mpc(InvalidProof),
+
+    /// multi-protocol commitment is not committed into the witness
+    /// transaction. Details: {0}
+    #[from]
+    Dbc(DbcError),
+}
+
+/// Proof that a document was committed by a witness transaction under a
+/// contract.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+pub struct DocumentTimestamp {
+    pub contract_id: ContractId,
+    pub document_id: DocumentId,
+    pub witness_id: XWitnessId,
+    pub anchor: EAnchor,
+}
+
+impl DocumentTimestamp {
+    pub fn new(
+        contract_id: ContractId,
+        document_id: DocumentId,
+        witness_id: XWitnessId,
+        anchor: EAnchor,
+    ) -> Self {
+        DocumentTimestamp {
+            contract_id,
+            document_id,
+            witness_id,
+            anchor,
+        }
+    }
+
+    /// Verifies that the document was committed by the witness transaction.
+    ///
+    /// The verification doesn't check whether the witness transaction is
+    /// mined; the time of the commitment is defined by the witness mining
+    /// height, which should be resolved by the caller.
+    pub fn verify(
+        &self,
+        document: impl AsRef<[u8]>,
+        witness_tx: &XWitnessTx,
+    ) -> Result<(), TimestampError> {
+        let document_id = DocumentId::with_content(document);
+        if document_id != self.document_id {
+            return Err(TimestampError::DocumentMismatch(self.document_id, document_id));
+        }
+        let witness_id = witness_tx.witness_id();
+        if witness_id != self.witness_id {
+            return Err(TimestampError::WitnessMismatch(witness_id, self.witness_id));
+        }
+        let (protocol_id, message) = self.contract_id.document_commitment(self.document_id);
+        let commitment = self.anchor.convolve(protocol_id, message)?;
+        self.anchor
+            .dbc_proof
+            .verify(&commitment, witness_tx.as_reduced_unsafe())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::validation::fixtures::TestChain;
+    use crate::validation::ResolveWitness;
+
+    const DOCUMENT: &[u8] = b"prospectus";
+
+    fn timestamped(
+        chain: &mut TestChain,
+        contract_id: ContractId,
+        document: &[u8],
+    ) -> DocumentTimestamp {
+        let document_id = DocumentId::with_content(document);
+        let (protocol_id, message) = contract_id.document_commitment(document_id);
+        let spent = chain.outpoint();
+        let (witness_id, mut anchors) = chain.commit_messages([(protocol_id, message)], [spent], 1);
+        let anchor = anchors.remove(&protocol_id).unwrap();
+        DocumentTimestamp::new(contract_id, document_id, witness_id, anchor)
+    }
+
+    #[test]
+    fn document_protocol() {
+        let contract_id = ContractId::from_byte_array([0x6c; 32]);
+        let other_id = ContractId::from_byte_array([0x6d; 32]);
+        assert_eq!(contract_id.document_protocol(), contract_id.document_protocol());
+        assert_ne!(contract_id.document_protocol(), mpc::ProtocolId::from(contract_id));
+        assert_ne!(contract_id.document_protocol(), other_id.document_protocol());
+
+        assert_eq!(DocumentId::with_content(DOCUMENT), DocumentId::with_content(DOCUMENT));
+        assert_ne!(DocumentId::with_content(DOCUMENT), DocumentId::with_content(b"audit"));
+    }
+
+    #[test]
+    fn verify() {
+        let mut chain = TestChain::regtest();
+        let contract_id = ContractId::from_byte_array([0x6c; 32]);
+        let timestamp = timestamped(&mut chain, contract_id, DOCUMENT);
+        let witness_tx = chain.resolve_pub_witness(timestamp.witness_id).unwrap();
+        assert_eq!(timestamp.verify(DOCUMENT, &witness_tx), Ok(()));
+
+        assert_eq!(
+            timestamp.verify(b"audit", &witness_tx),
+            Err(TimestampError::DocumentMismatch(
+                timestamp.document_id,
+                DocumentId::with_content(b"audit")
+            ))
+        );
+
+        let other = timestamped(&mut chain, contract_id, DOCUMENT);
+        let other_tx = chain.resolve_pub_witness(other.witness_id).unwrap();
+        assert_eq!(
+            timestamp.verify(DOCUMENT, &other_tx),
+            Err(TimestampError::WitnessMismatch(other.witness_id, timestamp.witness_id))
+        );
+
+        // Documents of one contract can't be presented as documents of another
+        let mut foreign = timestamp.clone();
+        foreign.contract_id = ContractId::from_byte_array([0x6d; 32]);
+        assert!(matches!(
+            foreign.verify(DOCUMENT, &witness_tx),
+            Err(TimestampError::Mpc(_) | TimestampError::Dbc(_))
+        ));
+    }
+}
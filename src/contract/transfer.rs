@@ -0,0 +1,701 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Assembling of state ownership transfers.
+//!
+//! [`TransferAssembler`] constructs a state transition spending allocations of
+//! the sender and assigning state to the seals provided by the receiver (and
+//! to the change seals of the sender). Together with the transition it
+//! produces the delta of the sender state and the minimal consignment for the
+//! receiver, which contains only the operations the transition depends on.
+//!
+//! The assembling is deterministic: inputs are ordered by their outpoints and
+//! assignments of each type are ordered by their concealed seals, following
+//! the consensus ordering, such that different implementations produce the
+//! same transition from the same data.
+//!
+//! The receiver consignment implements [`ConsignmentApi`] and can be validated
+//! once the transfer transition is anchored with
+//! [`TransferConsignment::anchor_transfer`].
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use amplify::confinement::{Confined, SmallVec};
+use strict_types::TypeSystem;
+
+use crate::schema::{GlobalStateType, MetaType, TransitionType};
+use crate::validation::{ConsignmentApi, Scripts, WitnessHint};
+use crate::{
+    Assign, AssignmentType, Assignments, BundleId, ContractId, ContractState, DataState, EAnchor,
+    ExposedState, Extension, Genesis, GlobalState, GraphSeal, Input, Inputs, MetaValue, Metadata,
+    MetadataError, NonEmptyInputs, OpId, OpOutputs, OpRef, Operation, Opout, RefinementError,
+    RevealedAttach, RevealedData, RevealedValue, Schema, SecretSeal, Transition, TransitionBundle,
+    TypedAssigns, Valencies, VoidState, XChain, XGraphSeal, XWitnessId,
+};
+
+/// Errors assembling state transfer.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum TransferError {
+    /// allocation {0} is not a part of the sender state.
+    UnknownAllocation(Opout),
+
+    /// assignment type {0} is not defined by the contract schema or has a
+    /// different state type.
+    StateTypeMismatch(AssignmentType),
+
+    /// transfer has too many inputs or assignments.
+    TooManyItems,
+
+    /// operation {0} from the transfer history is absent in the sender
+    /// consignment.
+    OperationAbsent(OpId),
+
+    /// state transition {0} from the transfer history is not a part of any
+    /// bundle of the sender consignment.
+    BundleAbsent(OpId),
+
+    /// anchor for the bundle {0} is absent in the sender consignment.
+    AnchorAbsent(BundleId),
+
+    /// metadata type {0} is not allowed in the transfer transition.
+    MetaNotAllowed(MetaType),
+
+    /// global state type {0} is not allowed in the transfer transition.
+    GlobalNotAllowed(GlobalStateType),
+
+    /// bundle {0} doesn't contain the transfer transition.
+    TransferUnbundled(BundleId),
+
+    #[from]
+    #[display(inner)]
+    Metadata(MetadataError),
+
+    #[from]
+    #[display(inner)]
+    Refinement(RefinementError),
+}
+
+/// State types which can be assigned by the [`TransferAssembler`].
+pub trait TransferState: ExposedState {
+    /// Wraps list of assignments into the typed assignments.
+    fn into_typed(list: SmallVec<Assign<Self, GraphSeal>>) -> TypedAssigns<GraphSeal>;
+
+    /// Returns list of assignments of the typed assignments, if they have the
+    /// same state type.
+    fn list_mut(
+        typed: &mut TypedAssigns<GraphSeal>,
+    ) -> Option<&mut SmallVec<Assign<Self, GraphSeal>>>;
+}
+
+impl TransferState for VoidState {
+    fn into_typed(list: SmallVec<Assign<Self, GraphSeal>>) -> TypedAssigns<GraphSeal> {
+        TypedAssigns::Declarative(list)
+    }
+    fn list_mut(
+        typed: &mut TypedAssigns<GraphSeal>,
+    ) -> Option<&mut SmallVec<Assign<Self, GraphSeal>>> {
+        typed.as_declarative_mut()
+    }
+}
+
+impl TransferState for RevealedValue {
+    fn into_typed(list: SmallVec<Assign<Self, GraphSeal>>) -> TypedAssigns<GraphSeal> {
+        TypedAssigns::Fungible(list)
+    }
+    fn list_mut(
+        typed: &mut TypedAssigns<GraphSeal>,
+    ) -> Option<&mut SmallVec<Assign<Self, GraphSeal>>> {
+        typed.as_fungible_mut()
+    }
+}
+
+impl TransferState for RevealedData {
+    fn into_typed(list: SmallVec<Assign<Self, GraphSeal>>) -> TypedAssigns<GraphSeal> {
+        TypedAssigns::Structured(list)
+    }
+    fn list_mut(
+        typed: &mut TypedAssigns<GraphSeal>,
+    ) -> Option<&mut SmallVec<Assign<Self, GraphSeal>>> {
+        typed.as_structured_mut()
+    }
+}
+
+impl TransferState for RevealedAttach {
+    fn into_typed(list: SmallVec<Assign<Self, GraphSeal>>) -> TypedAssigns<GraphSeal> {
+        TypedAssigns::Attachment(list)
+    }
+    fn list_mut(
+        typed: &mut TypedAssigns<GraphSeal>,
+    ) -> Option<&mut SmallVec<Assign<Self, GraphSeal>>> {
+        typed.as_attachment_mut()
+    }
+}
+
+/// Changes to the sender state made by the transfer, which are applied once
+/// the transfer witness is mined.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct StateDelta {
+    /// Allocations spent by the transfer.
+    pub spent: BTreeSet<Opout>,
+    /// Allocations assigned by the transfer to the sender change seals.
+    pub change: BTreeMap<Opout, XGraphSeal>,
+}
+
+/// Minimal consignment for the receiver of the transfer, containing the
+/// transfer transition and all the operations it depends on.
+///
+/// The transfer transition is not anchored yet; the receiver gets its anchor
+/// once the sender constructs the witness transaction, and it is added with
+/// [`TransferConsignment::anchor_transfer`]. Until then the consignment has no
+/// terminals.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TransferConsignment {
+    pub schema: Schema,
+    pub types: TypeSystem,
+    pub scripts: Scripts,
+    pub genesis: Genesis,
+    pub extensions: BTreeMap<OpId, Extension>,
+    pub bundles: BTreeMap<BundleId, TransitionBundle>,
+    pub anchors: BTreeMap<BundleId, (XWitnessId, EAnchor)>,
+    /// Position hints for the witnesses of the anchored bundles, as provided
+    /// by the source consignment.
+    pub hints: BTreeMap<XWitnessId, WitnessHint>,
+    pub transition: Transition,
+    /// Seals of the receiver to which the transition assigns state.
+    pub terminals: BTreeSet<XChain<SecretSeal>>,
+}
+
+impl TransferConsignment {
+    /// Adds the bundle containing the transfer transition together with its
+    /// anchor, returning the bundle id.
+    pub fn anchor_transfer(
+        &mut self,
+        bundle: TransitionBundle,
+        witness_id: XWitnessId,
+        anchor: EAnchor,
+    ) -> Result<BundleId, TransferError> {
+        let bundle_id = bundle.bundle_id();
+        if bundle.known_transitions.get(&self.transition.id()) != Some(&self.transition) {
+            return Err(TransferError::TransferUnbundled(bundle_id));
+        }
+        self.bundles.insert(bundle_id, bundle);
+        self.anchors.insert(bundle_id, (witness_id, anchor));
+        Ok(bundle_id)
+    }
+
+    /// Returns id of the bundle containing the transfer transition, if the
+    /// transition is anchored.
+    pub fn transfer_bundle_id(&self) -> Option<BundleId> {
+        let opid = self.transition.id();
+        self.bundles
+            .iter()
+            .find(|(_, bundle)| bundle.known_transitions.contains_key(&opid))
+            .map(|(bundle_id, _)| *bundle_id)
+    }
+}
+
+impl ConsignmentApi for TransferConsignment {
+    fn schema(&self) -> &Schema { &self.schema }
+
+    fn types(&self) -> &TypeSystem { &self.types }
+
+    fn scripts(&self) -> &Scripts { &self.scripts }
+
+    fn operation(&self, opid: OpId) -> Option<OpRef<'_>> {
+        if opid == self.genesis.id() {
+            return Some(OpRef::Genesis(&self.genesis));
+        }
+        if opid == self.transition.id() {
+            return Some(OpRef::Transition(&self.transition));
+        }
+        if let Some(extension) = self.extensions.get(&opid) {
+            return Some(OpRef::Extension(extension));
+        }
+        self.bundles
+            .values()
+            .find_map(|bundle| bundle.known_transitions.get(&opid))
+            .map(OpRef::Transition)
+    }
+
+    fn genesis(&self) -> &Genesis { &self.genesis }
+
+    fn terminals<'iter>(&self) -> impl Iterator<Item = (BundleId, XChain<SecretSeal>)> + 'iter {
+        let terminals = match self.transfer_bundle_id() {
+            Some(bundle_id) => self
+                .terminals
+                .iter()
+                .map(|seal| (bundle_id, *seal))
+                .collect(),
+            None => vec![],
+        };
+        terminals.into_iter()
+    }
+
+    fn bundle_ids<'iter>(&self) -> impl Iterator<Item = BundleId> + 'iter {
+        self.bundles.keys().copied().collect::<Vec<_>>().into_iter()
+    }
+
+    fn extension_ids<'iter>(&self) -> impl Iterator<Item = OpId> + 'iter {
+        self.extensions
+            .keys()
+            .copied()
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn bundle(&self, bundle_id: BundleId) -> Option<&TransitionBundle> {
+        self.bundles.get(&bundle_id)
+    }
+
+    fn anchor(&self, bundle_id: BundleId) -> Option<(XWitnessId, &EAnchor)> {
+        self.anchors
+            .get(&bundle_id)
+            .map(|(witness_id, anchor)| (*witness_id, anchor))
+    }
+
+    fn op_witness_id(&self, opid: OpId) -> Option<XWitnessId> {
+        let (bundle_id, _) = self
+            .bundles
+            .iter()
+            .find(|(_, bundle)| bundle.known_transitions.contains_key(&opid))?;
+        self.anchors
+            .get(bundle_id)
+            .map(|(witness_id, _)| *witness_id)
+    }
+
+    fn witness_hint(&self, witness_id: XWitnessId) -> Option<WitnessHint> {
+        self.hints.get(&witness_id).copied()
+    }
+}
+
+/// Result of the transfer assembling.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Transfer {
+    pub transition: Transition,
+    pub delta: StateDelta,
+    pub consignment: TransferConsignment,
+}
+
+/// Assembler of a state transition transferring state from the sender to the
+/// receiver.
+#[derive(Clone, Debug)]
+pub struct TransferAssembler<'state> {
+    state: &'state ContractState,
+    transition_type: TransitionType,
+    metadata: Metadata,
+    globals: GlobalState,
+    inputs: BTreeSet<Opout>,
+    assignments: BTreeMap<AssignmentType, TypedAssigns<GraphSeal>>,
+}
+
+impl<'state> TransferAssembler<'state> {
+    /// Starts assembling transfer from the sender state.
+    pub fn new(state: &'state ContractState, transition_type: TransitionType) -> Self {
+        TransferAssembler {
+            state,
+            transition_type,
+            metadata: empty!(),
+            globals: empty!(),
+            inputs: empty!(),
+            assignments: empty!(),
+        }
+    }
+
+    pub fn contract_id(&self) -> ContractId { self.state.contract_id() }
+
+    /// Adds metadata to the transfer transition, if the metadata type is
+    /// allowed by the schema for the transition type.
+    pub fn add_metadata(
+        &mut self,
+        ty: MetaType,
+        value: MetaValue,
+    ) -> Result<&mut Self, TransferError> {
+        let allowed = self
+            .state
+            .schema
+            .transitions
+            .get(&self.transition_type)
+            .is_some_and(|schema| schema.metadata.contains(&ty));
+        if !allowed {
+            return Err(TransferError::MetaNotAllowed(ty));
+        }
+        self.metadata.add_value(ty, value)?;
+        Ok(self)
+    }
+
+    /// Adds global state to the transfer transition, if the global state type
+    /// is allowed by the schema for the transition type.
+    pub fn add_global_state(
+        &mut self,
+        ty: GlobalStateType,
+        state: DataState,
+    ) -> Result<&mut Self, TransferError> {
+        let allowed = self
+            .state
+            .schema
+            .transitions
+            .get(&self.transition_type)
+            .is_some_and(|schema| schema.globals.contains_key(&ty));
+        if !allowed {
+            return Err(TransferError::GlobalNotAllowed(ty));
+        }
+        self.globals
+            .add_state(ty, state)
+            .map_err(|_| TransferError::TooManyItems)?;
+        Ok(self)
+    }
+
+    /// Spends allocation of the sender.
+    pub fn spend(&mut self, opout: Opout) -> Result<&mut Self, TransferError> {
+        let known = self.state.rights().iter().any(|a| a.opout == opout) ||
+            self.state.fungibles().iter().any(|a| a.opout == opout) ||
+            self.state.data().iter().any(|a| a.opout == opout) ||
+            self.state.attach().iter().any(|a| a.opout == opout);
+        if !known {
+            return Err(TransferError::UnknownAllocation(opout));
+        }
+        self.inputs.insert(opout);
+        Ok(self)
+    }
+
+    /// Assigns state to the concealed seal of the receiver.
+    pub fn assign_receiver<State: TransferState>(
+        &mut self,
+        ty: AssignmentType,
+        seal: XChain<SecretSeal>,
+        state: State,
+    ) -> Result<&mut Self, TransferError> {
+        let assign = Assign::ConfidentialSeal {
+            seal,
+            state,
+            lock: default!(),
+        };
+        self.assign(ty, assign)
+    }
+
+    /// Assigns state to the change seal of the sender.
+    pub fn assign_change<State: TransferState>(
+        &mut self,
+        ty: AssignmentType,
+        seal: XGraphSeal,
+        state: State,
+    ) -> Result<&mut Self, TransferError> {
+        self.assign(ty, Assign::revealed(seal, state))
+    }
+
+    fn assign<State: TransferState>(
+        &mut self,
+        ty: AssignmentType,
+        assign: Assign<State, GraphSeal>,
+    ) -> Result<&mut Self, TransferError> {
+        let state_type = assign
+            .as_revealed_state()
+            .map(ExposedState::state_type)
+            .expect("assigned state is always revealed");
+        match self.state.schema.owned_types.get(&ty) {
+            Some(schema) if schema.state_type() == state_type => {}
+            _ => return Err(TransferError::StateTypeMismatch(ty)),
+        }
+        match self.assignments.get_mut(&ty) {
+            Some(typed) => State::list_mut(typed)
+                .ok_or(TransferError::StateTypeMismatch(ty))?
+                .push(assign)
+                .map_err(|_| TransferError::TooManyItems)?,
+            None => {
+                self.assignments
+                    .insert(ty, State::into_typed(small_vec![assign]));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Constructs the transfer transition, the sender state delta and the
+    /// receiver consignment, taking the history of the spent allocations from
+    /// the sender consignment.
+    pub fn assemble(self, source: &impl ConsignmentApi) -> Result<Transfer, TransferError> {
+        let inputs = Inputs::try_from_inputs(self.inputs.iter().copied().map(Input::with))
+            .map_err(|_| TransferError::TooManyItems)?;
+        let assignments = self
+            .assignments
+            .iter()
+            .map(|(ty, typed)| (*ty, sorted(typed)))
+            .collect::<BTreeMap<_, _>>();
+        let assignments = Assignments::from(
+            Confined::try_from(assignments).map_err(|_| TransferError::TooManyItems)?,
+        );
+        let outputs = OpOutputs::new(assignments, Valencies::default())?;
+        let mut transition = Transition::with(
+            self.contract_id(),
+            self.transition_type,
+            NonEmptyInputs::try_from(inputs)?,
+            outputs,
+        );
+        transition.metadata = self.metadata;
+        transition.globals = self.globals;
+
+        let opid = transition.id();
+        let mut delta = StateDelta {
+            spent: self.inputs.clone(),
+            change: empty!(),
+        };
+        let mut terminals = BTreeSet::new();
+        for (ty, typed) in transition.assignments.iter() {
+            for no in 0..typed.len_u16() {
+                match typed.revealed_seal_at(no).expect("index within the range") {
+                    Some(seal) => {
+                        delta.change.insert(Opout::new(opid, *ty, no), seal);
+                    }
+                    None => {
+                        terminals.insert(typed.to_confidential_seals()[no as usize]);
+                    }
+                }
+            }
+        }
+
+        let mut consignment = TransferConsignment {
+            schema: source.schema().clone(),
+            types: source.types().clone(),
+            scripts: source.scripts().clone(),
+            genesis: source.genesis().clone(),
+            extensions: empty!(),
+            bundles: empty!(),
+            anchors: empty!(),
+            hints: empty!(),
+            transition: transition.clone(),
+            terminals,
+        };
+        collect_history(source, &transition, &mut consignment)?;
+
+        Ok(Transfer {
+            transition,
+            delta,
+            consignment,
+        })
+    }
+}
+
+/// Orders assignments by their concealed seals.
+fn sorted(typed: &TypedAssigns<GraphSeal>) -> TypedAssigns<GraphSeal> {
+    fn sort<State: ExposedState>(
+        list: &SmallVec<Assign<State, GraphSeal>>,
+    ) -> SmallVec<Assign<State, GraphSeal>> {
+        let mut list = list.iter().cloned().collect::<Vec<_>>();
+        list.sort();
+        Confined::try_from(list).expect("same size")
+    }
+    match typed {
+        TypedAssigns::Declarative(list) => TypedAssigns::Declarative(sort(list)),
+        TypedAssigns::Fungible(list) => TypedAssigns::Fungible(sort(list)),
+        TypedAssigns::Structured(list) => TypedAssigns::Structured(sort(list)),
+        TypedAssigns::Attachment(list) => TypedAssigns::Attachment(sort(list)),
+    }
+}
+
+/// Collects bundles, anchors and extensions the transition depends on.
+fn collect_history(
+    source: &impl ConsignmentApi,
+    transition: &Transition,
+    consignment: &mut TransferConsignment,
+) -> Result<(), TransferError> {
+    let mut op_bundles = BTreeMap::new();
+    for bundle_id in source.bundle_ids() {
+        if let Some(bundle) = source.bundle(bundle_id) {
+            for opid in bundle.known_transitions.keys() {
+                op_bundles.insert(*opid, bundle_id);
+            }
+        }
+    }
+
+    let genesis_id = source.genesis().id();
+    let mut queue = transition
+        .inputs()
+        .iter()
+        .map(|input| input.prev_out.op)
+        .collect::<Vec<_>>();
+    let mut visited = BTreeSet::new();
+    while let Some(opid) = queue.pop() {
+        if opid == genesis_id || !visited.insert(opid) {
+            continue;
+        }
+        match source
+            .operation(opid)
+            .ok_or(TransferError::OperationAbsent(opid))?
+        {
+            OpRef::Genesis(_) => {}
+            OpRef::Transition(prev) => {
+                let bundle_id = *op_bundles
+                    .get(&opid)
+                    .ok_or(TransferError::BundleAbsent(opid))?;
+                if let std::collections::btree_map::Entry::Vacant(e) =
+                    consignment.bundles.entry(bundle_id)
+                {
+                    let bundle = source
+                        .bundle(bundle_id)
+                        .ok_or(TransferError::BundleAbsent(opid))?;
+                    let (witness_id, anchor) = source
+                        .anchor(bundle_id)
+                        .ok_or(TransferError::AnchorAbsent(bundle_id))?;
+                    e.insert(bundle.clone());
+                    consignment
+                        .anchors
+                        .insert(bundle_id, (witness_id, anchor.clone()));
+                    if let Some(hint) = source.witness_hint(witness_id) {
+                        consignment.hints.insert(witness_id, hint);
+                    }
+                }
+                queue.extend(prev.inputs().iter().map(|input| input.prev_out.op));
+            }
+            OpRef::Extension(prev) => {
+                consignment.extensions.insert(opid, prev.clone());
+                queue.extend(prev.redeemed.values().copied());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::Wrapper;
+    use bp::seals::txout::CloseMethod;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::schema::{Occurrences, OwnedStateSchema, TransitionSchema};
+    use crate::validation::fixtures::{TestChain, TestConsignment};
+    use crate::validation::{Failure, Validator};
+    use crate::{ContractHistory, InputMap, Vin};
+
+    const TY: AssignmentType = AssignmentType::with(1);
+    const TRANSITION_TY: TransitionType = TransitionType::with(1);
+
+    #[test]
+    fn transfer() {
+        let mut chain = TestChain::regtest();
+        let mut schema = Schema::strict_dumb();
+        schema.owned_types = Confined::from_iter_unsafe([(TY, OwnedStateSchema::Declarative)]);
+        schema.genesis.assignments = Confined::from_iter_unsafe([(TY, Occurrences::Once)]);
+        schema.transitions = Confined::from_iter_unsafe([(TRANSITION_TY, TransitionSchema {
+            inputs: Confined::from_iter_unsafe([(TY, Occurrences::Once)]),
+            assignments: Confined::from_iter_unsafe([(TY, Occurrences::OnceOrMore)]),
+            ..default!()
+        })]);
+
+        let seal = chain.genesis_seal();
+        let mut genesis = Genesis::strict_dumb();
+        genesis.schema_id = schema.schema_id();
+        genesis.testnet = chain.testnet();
+        genesis.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            TY,
+            TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                seal,
+                VoidState::default(),
+            )])),
+        )]));
+        let contract_id = genesis.contract_id();
+        let opout = Opout::new(genesis.id(), TY, 0);
+        let source = TestConsignment::new(schema.clone(), TypeSystem::default(), genesis.clone());
+        let state = ContractState {
+            history: ContractHistory::with(schema.schema_id(), contract_id, &genesis),
+            schema,
+        };
+
+        let receiver = chain.graph_seal(1).to_secret_seal();
+        let change = chain.graph_seal(2);
+        let mut assembler = TransferAssembler::new(&state, TRANSITION_TY);
+        assert_eq!(
+            assembler
+                .add_metadata(MetaType::with(1), default!())
+                .unwrap_err(),
+            TransferError::MetaNotAllowed(MetaType::with(1))
+        );
+        assert_eq!(
+            assembler
+                .add_global_state(GlobalStateType::with(1), default!())
+                .unwrap_err(),
+            TransferError::GlobalNotAllowed(GlobalStateType::with(1))
+        );
+        assert_eq!(
+            assembler
+                .spend(Opout::new(genesis.id(), TY, 1))
+                .unwrap_err(),
+            TransferError::UnknownAllocation(Opout::new(genesis.id(), TY, 1))
+        );
+        assembler
+            .spend(opout)
+            .unwrap()
+            .assign_receiver(TY, receiver, VoidState::default())
+            .unwrap()
+            .assign_change(TY, change, VoidState::default())
+            .unwrap();
+        let Transfer {
+            transition,
+            delta,
+            mut consignment,
+        } = assembler.assemble(&source).unwrap();
+        let opid = transition.id();
+
+        assert_eq!(delta.spent, bset! { opout });
+        assert_eq!(delta.change.values().copied().collect::<Vec<_>>(), vec![change]);
+        assert_eq!(consignment.terminals, bset! { receiver });
+        assert_eq!(consignment.terminals().count(), 0);
+        assert_eq!(consignment.operation(opid), Some(OpRef::Transition(&transition)));
+
+        let bundle = TransitionBundle {
+            close_method: CloseMethod::OpretFirst,
+            input_map: InputMap::with(Vin::from(0u32), opid),
+            known_transitions: Confined::with((opid, transition)),
+        };
+        let spent = *seal.to_outpoint().as_reduced_unsafe();
+        let (witness_id, anchor) = chain.commit(contract_id, bundle.bundle_id(), [spent], 2);
+        chain.mine(witness_id);
+        let mut foreign = bundle.clone();
+        let other = Transition::strict_dumb();
+        foreign.known_transitions = Confined::with((other.id(), other));
+        assert_eq!(
+            consignment
+                .anchor_transfer(foreign.clone(), witness_id, anchor.clone())
+                .unwrap_err(),
+            TransferError::TransferUnbundled(foreign.bundle_id())
+        );
+        let bundle_id = consignment
+            .anchor_transfer(bundle, witness_id, anchor)
+            .unwrap();
+        assert_eq!(consignment.terminals().collect::<Vec<_>>(), vec![(bundle_id, receiver)]);
+        assert_eq!(consignment.op_witness_id(opid), Some(witness_id));
+
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(
+            !status.failures.iter().any(|failure| matches!(
+                failure,
+                Failure::OperationAbsent(_) |
+                    Failure::TerminalBundleAbsent(_) |
+                    Failure::SealsUnvalidated(_) |
+                    Failure::SealNoWitnessTx(..) |
+                    Failure::MpcInvalid(..) |
+                    Failure::SealsInvalid(..)
+            )),
+            "{status}"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Planned schema upgrades of live contracts.
+//!
+//! Contract is upgraded to a successor schema with a state extension carrying
+//! [`schema::MetaType::SCHEMA_UPGRADE`] metadata, which commits to the
+//! successor schema id and the height at which the upgrade activates. The
+//! contract schema opts into the upgrades by setting
+//! [`schema::SchemaFlags::RESERVED_TYPES`] and declaring the metadata type for
+//! the extension types permitted to upgrade the contract; the issuer controls
+//! who may perform the upgrade with the valencies redeemed by these extensions
+//! and their validation scripts. Other operations can't carry the metadata.
+//!
+//! An upgrade applies to the state transitions descending from the upgrade
+//! extension, i.e. spending the state it assigns directly or through other
+//! operations. Since a consignment contains the whole history of its
+//! transitions, every consignment of such a transition contains the upgrade,
+//! and transitions which don't descend from it are validated against the same
+//! schema by all consignments.
+//!
+//! Descending transitions mined at or after the activation height are
+//! validated against the successor schema, which must be provided by the
+//! consignment, and the ones mined before it against the previous schema.
+//! Transitions which are not mined yet may be mined on either side of the
+//! activation height, so they must be valid both under the contract schema
+//! and under the successor schemas of all upgrades in their history. This
+//! keeps the validity of unmined transitions independent of the chain tip.
+
+use amplify::confinement::Confined;
+use amplify::ByteArray;
+
+use crate::schema::{self, SchemaId};
+use crate::{MetaValue, Metadata};
+
+/// Upgrade of the contract to a successor schema.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct SchemaUpgrade {
+    pub successor: SchemaId,
+    pub activation_height: u32,
+}
+
+impl SchemaUpgrade {
+    pub fn new(successor: SchemaId, activation_height: u32) -> Self {
+        SchemaUpgrade {
+            successor,
+            activation_height,
+        }
+    }
+
+    /// Checks whether the upgrade is active for an operation mined at the
+    /// given height.
+    pub fn is_active_at(&self, height: u32) -> bool { height >= self.activation_height }
+
+    /// Converts upgrade into a value for the
+    /// [`schema::MetaType::SCHEMA_UPGRADE`] metadata.
+    pub fn to_meta(&self) -> MetaValue {
+        let mut data = Vec::with_capacity(36);
+        data.extend(self.successor.to_byte_array());
+        data.extend(self.activation_height.to_le_bytes());
+        MetaValue::from(Confined::try_from(data).expect("fixed size"))
+    }
+
+    /// Parses upgrade from the [`schema::MetaType::SCHEMA_UPGRADE`] metadata
+    /// value. Returns `None` if the value is malformed.
+    pub fn from_meta(meta: &MetaValue) -> Option<Self> {
+        let data = meta.as_slice();
+        if data.len() != 36 {
+            return None;
+        }
+        let successor = SchemaId::from(<[u8; 32]>::try_from(&data[..32]).expect("fixed size"));
+        let activation_height = u32::from_le_bytes(data[32..].try_into().expect("fixed size"));
+        Some(SchemaUpgrade::new(successor, activation_height))
+    }
+}
+
+impl Metadata {
+    /// Returns schema upgrade performed by the operation, as declared by the
+    /// operation metadata, or `Some(None)` if the metadata are absent.
+    ///
+    /// Returns `None` if the metadata value is malformed.
+    pub fn schema_upgrade(&self) -> Option<Option<SchemaUpgrade>> {
+        match self.get(&schema::MetaType::SCHEMA_UPGRADE) {
+            None => Some(None),
+            Some(meta) => SchemaUpgrade::from_meta(meta).map(Some),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn meta_roundtrip() {
+        let upgrade = SchemaUpgrade::new(SchemaId::from([0xAB; 32]), 840_000);
+        assert!(!upgrade.is_active_at(839_999));
+        assert!(upgrade.is_active_at(840_000));
+        assert_eq!(SchemaUpgrade::from_meta(&upgrade.to_meta()), Some(upgrade));
+
+        let mut metadata = Metadata::default();
+        assert_eq!(metadata.schema_upgrade(), Some(None));
+        metadata
+            .add_value(schema::MetaType::SCHEMA_UPGRADE, upgrade.to_meta())
+            .unwrap();
+        assert_eq!(metadata.schema_upgrade(), Some(Some(upgrade)));
+
+        let mut metadata = Metadata::default();
+        let short = MetaValue::from(Confined::try_from(vec![0u8; 35]).unwrap());
+        metadata
+            .add_value(schema::MetaType::SCHEMA_UPGRADE, short)
+            .unwrap();
+        assert_eq!(metadata.schema_upgrade(), None);
+    }
+}
@@ -0,0 +1,320 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Redemption limits of valencies.
+//!
+//! Valencies allow anyone to create state extensions redeeming them, and
+//! without further restrictions each valency can be redeemed by an unbounded
+//! number of extensions at any time. Contract genesis may restrict this in
+//! its [`schema::MetaType::VALENCY_LIMITS`] metadata, declaring for each
+//! valency type how many times a valency may be redeemed and the height after
+//! which it may not be used. The limits are enforced by the validator:
+//! [`ValencyTracker`] counts redemptions of each valency defined by contract
+//! operations.
+//!
+//! The limits are not a part of the schema, since the schema encoding has no
+//! place for them. They are committed by the contract id as a part of the
+//! genesis, and the schema controls them by opting into the consensus-reserved
+//! types (see [`schema::SchemaFlags::RESERVED_TYPES`]) and declaring the
+//! genesis metadata type.
+//!
+//! # Limitations
+//!
+//! Extensions are not committed into the bitcoin transaction graph, and the
+//! validator counts only the redemptions by the extensions included in the
+//! validated consignment. The sender of a consignment may omit extensions
+//! redeeming the same valency which are not ancestors of the transferred
+//! state, thus the redemption limits, including [`Redemptions::ONCE`], are
+//! not enforceable by the validation of a single consignment. Software
+//! keeping the contract state across consignments must register all the
+//! extensions it has accepted in a single [`ValencyTracker`] to detect
+//! redemptions over the limit.
+//!
+//! State extensions have no witnesses, thus the expiry is enforced against
+//! the witnesses of the state transitions spending the extension state. The
+//! expiry is enforceable, since these witnesses are a part of the validated
+//! history of the transferred state.
+
+use std::collections::BTreeMap;
+use std::num::{NonZeroU16, NonZeroU32};
+
+use amplify::confinement::Confined;
+
+use crate::schema::{self, ValencyType};
+use crate::{Extension, Genesis, MetaValue, OpId, Operation};
+
+/// Number of times a valency can be redeemed.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum Redemptions {
+    #[display("at most {0} times")]
+    Limited(NonZeroU16),
+    #[display("unlimited")]
+    Unlimited,
+}
+
+impl Redemptions {
+    /// Valency may be redeemed once. The limit is enforced only for the
+    /// extensions known to the validator (see the module documentation).
+    pub const ONCE: Self = Redemptions::Limited(NonZeroU16::MIN);
+
+    /// Returns maximum number of redemptions, or `None` if unlimited.
+    pub fn max(self) -> Option<u16> {
+        match self {
+            Redemptions::Limited(max) => Some(max.get()),
+            Redemptions::Unlimited => None,
+        }
+    }
+}
+
+/// Restrictions on the redemption of a valency type.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ValencyLimit {
+    /// Number of extensions which may redeem each valency of the type.
+    pub redemptions: Redemptions,
+    /// Height of the last block at which the state of extensions redeeming
+    /// the valency may be spent.
+    pub expiry: Option<NonZeroU32>,
+}
+
+impl ValencyLimit {
+    fn from_le_bytes(bytes: [u8; 6]) -> Self {
+        let redemptions = match NonZeroU16::new(u16::from_le_bytes([bytes[0], bytes[1]])) {
+            Some(max) => Redemptions::Limited(max),
+            None => Redemptions::Unlimited,
+        };
+        let expiry = NonZeroU32::new(u32::from_le_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]));
+        ValencyLimit {
+            redemptions,
+            expiry,
+        }
+    }
+
+    fn to_le_bytes(self) -> [u8; 6] {
+        let redemptions = self.redemptions.max().unwrap_or_default();
+        let expiry = self.expiry.map(NonZeroU32::get).unwrap_or_default();
+        let mut bytes = [0u8; 6];
+        bytes[..2].copy_from_slice(&redemptions.to_le_bytes());
+        bytes[2..].copy_from_slice(&expiry.to_le_bytes());
+        bytes
+    }
+}
+
+impl Genesis {
+    /// Returns valency limits declared by the
+    /// [`schema::MetaType::VALENCY_LIMITS`] metadata. If the metadata is
+    /// absent, valencies are not limited.
+    ///
+    /// Returns `None` if the metadata value is malformed.
+    pub fn valency_limits(&self) -> Option<BTreeMap<ValencyType, ValencyLimit>> {
+        let Some(meta) = self.metadata.get(&schema::MetaType::VALENCY_LIMITS) else {
+            return Some(empty!());
+        };
+        let data = meta.as_slice();
+        if data.is_empty() || data.len() % 8 != 0 {
+            return None;
+        }
+        let limits = data
+            .chunks(8)
+            .map(|chunk| {
+                let ty = ValencyType::with(u16::from_le_bytes([chunk[0], chunk[1]]));
+                let limit = ValencyLimit::from_le_bytes(chunk[2..].try_into().expect("fixed size"));
+                (ty, limit)
+            })
+            .collect::<BTreeMap<_, _>>();
+        if limits.len() * 8 != data.len() {
+            return None;
+        }
+        Some(limits)
+    }
+}
+
+/// Constructs value for the [`schema::MetaType::VALENCY_LIMITS`] genesis
+/// metadata.
+///
+/// Returns `None` if there are no limits or too many of them to fit the
+/// metadata.
+pub fn valency_limits_meta(limits: &BTreeMap<ValencyType, ValencyLimit>) -> Option<MetaValue> {
+    if limits.is_empty() {
+        return None;
+    }
+    let data = limits
+        .iter()
+        .flat_map(|(ty, limit)| {
+            let mut entry = ty.to_le_bytes().to_vec();
+            entry.extend(limit.to_le_bytes());
+            entry
+        })
+        .collect::<Vec<_>>();
+    Confined::try_from(data).ok().map(MetaValue::from)
+}
+
+/// Error redeeming a valency.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(doc_comments)]
+pub enum ValencyError {
+    /// extension {opid} redeems valency {valency} of operation {prev_id} over
+    /// the limit of {max} redemptions.
+    RedemptionsExceeded {
+        opid: OpId,
+        prev_id: OpId,
+        valency: ValencyType,
+        max: u16,
+    },
+
+    /// state of extension {opid} redeeming valency {valency} is spent at
+    /// height {height}, after the valency expiry at height {expiry}.
+    Expired {
+        opid: OpId,
+        valency: ValencyType,
+        expiry: u32,
+        height: u32,
+    },
+}
+
+/// Tracker of the valencies redeemed by contract state extensions.
+///
+/// The tracker detects redemptions over the limit only among the registered
+/// extensions; to enforce the limits across consignments, all extensions of
+/// the contract known to the software must be registered.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ValencyTracker {
+    limits: BTreeMap<ValencyType, ValencyLimit>,
+    redeemed: BTreeMap<(OpId, ValencyType), u16>,
+}
+
+impl ValencyTracker {
+    pub fn with(limits: BTreeMap<ValencyType, ValencyLimit>) -> Self {
+        ValencyTracker {
+            limits,
+            redeemed: empty!(),
+        }
+    }
+
+    /// Constructs tracker with the limits declared by the contract genesis.
+    ///
+    /// Returns `None` if the declaration is malformed.
+    pub fn from_genesis(genesis: &Genesis) -> Option<Self> {
+        genesis.valency_limits().map(Self::with)
+    }
+
+    pub fn limit(&self, valency: ValencyType) -> Option<ValencyLimit> {
+        self.limits.get(&valency).copied()
+    }
+
+    /// Returns how many registered extensions have redeemed the valency
+    /// defined by the operation.
+    pub fn redemptions(&self, prev_id: OpId, valency: ValencyType) -> u16 {
+        self.redeemed
+            .get(&(prev_id, valency))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Registers valencies redeemed by the state extension. Extensions must
+    /// be registered only once.
+    ///
+    /// # Errors
+    ///
+    /// If some valency is redeemed more times than permitted by its limit.
+    /// The redemptions are registered nevertheless.
+    pub fn register(&mut self, extension: &Extension) -> Result<(), ValencyError> {
+        let opid = extension.id();
+        let mut result = Ok(());
+        for (valency, prev_id) in &extension.redeemed {
+            let count = self.redeemed.entry((*prev_id, *valency)).or_default();
+            *count = count.saturating_add(1);
+            let count = *count;
+            let Some(max) = self
+                .limit(*valency)
+                .and_then(|limit| limit.redemptions.max())
+            else {
+                continue;
+            };
+            if count > max && result.is_ok() {
+                result = Err(ValencyError::RedemptionsExceeded {
+                    opid,
+                    prev_id: *prev_id,
+                    valency: *valency,
+                    max,
+                });
+            }
+        }
+        result
+    }
+
+    /// Checks that the state of the extension may be spent at the given
+    /// height, i.e. none of the valencies it redeems has expired.
+    pub fn check_expiry(&self, extension: &Extension, height: u32) -> Result<(), ValencyError> {
+        for valency in extension.redeemed.keys() {
+            let Some(expiry) = self.limit(*valency).and_then(|limit| limit.expiry) else {
+                continue;
+            };
+            if height > expiry.get() {
+                return Err(ValencyError::Expired {
+                    opid: extension.id(),
+                    valency: *valency,
+                    expiry: expiry.get(),
+                    height,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn limits_meta_roundtrip() {
+        let limits = bmap! {
+            ValencyType::with(1) => ValencyLimit {
+                redemptions: Redemptions::ONCE,
+                expiry: NonZeroU32::new(840_000),
+            },
+            ValencyType::with(2) => ValencyLimit {
+                redemptions: Redemptions::Unlimited,
+                expiry: None,
+            }
+        };
+        let meta = valency_limits_meta(&limits).unwrap();
+        assert_eq!(meta.len(), 16);
+        let limit = ValencyLimit::from_le_bytes(meta.as_slice()[2..8].try_into().unwrap());
+        assert_eq!(limit, limits[&ValencyType::with(1)]);
+    }
+}
@@ -0,0 +1,324 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Watch-lists for the seal-spend watchtowers.
+//!
+//! A watch-list is a compact export of the data a watchtower service needs in
+//! order to notify the contract participants when a seal they care about gets
+//! closed by someone else: outpoints of the contract seals together with the
+//! close method of each seal, the script patterns of the outputs which may
+//! carry a commitment, and the witness transactions which are not yet known to
+//! be mined. Alerts sent by the watchtower are checked with
+//! [`WatchList::verify_alert`] against the spending transaction, without
+//! trusting the watchtower.
+
+use amplify::confinement::{Confined, LargeOrdMap, LargeOrdSet, TinyOrdSet};
+use bp::dbc::Method;
+use strict_encoding::{StrictDeserialize, StrictSerialize};
+
+use crate::{
+    ContractHistory, ContractId, KnownState, OutputAssignment, TxoSeal, XOutpoint, XWitnessId,
+    XWitnessTx, LIB_NAME_RGB, OPRET_FILTER_ITEM, TAPRET_FILTER_ITEM,
+};
+
+/// Script pattern of an output carrying a seal-closing commitment.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB, tags = repr, into_u8, try_from_u8)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(lowercase)]
+#[repr(u8)]
+pub enum ScriptPattern {
+    /// `OP_RETURN OP_PUSHBYTES_32 <commitment>` output.
+    #[strict_type(dumb)]
+    Opret = 0,
+    /// `OP_1 OP_PUSHBYTES_32 <output key>` (P2TR) output.
+    Tapret = 1,
+}
+
+impl From<Method> for ScriptPattern {
+    fn from(method: Method) -> Self {
+        match method {
+            Method::OpretFirst => ScriptPattern::Opret,
+            Method::TapretFirst => ScriptPattern::Tapret,
+        }
+    }
+}
+
+impl ScriptPattern {
+    /// Checks whether the output script matches the pattern.
+    pub fn matches(self, script: &[u8]) -> bool {
+        match self {
+            ScriptPattern::Opret => script.len() == 34 && script.starts_with(&OPRET_FILTER_ITEM),
+            ScriptPattern::Tapret => script.len() == 34 && script.starts_with(&TAPRET_FILTER_ITEM),
+        }
+    }
+}
+
+/// Alert sent by a watchtower, reporting that a watched seal outpoint was
+/// spent by a transaction.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct SpendAlert {
+    pub outpoint: XOutpoint,
+    pub witness_id: XWitnessId,
+}
+
+/// Seal closing confirmed by an alert verification.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[display(doc_comments)]
+pub enum SealClosing {
+    /// seal is closed by a transaction having an output which may carry a
+    /// commitment with the seal close method.
+    Committed,
+
+    /// seal is closed by a transaction without outputs able to carry a
+    /// commitment with the seal close method, destroying the state assigned
+    /// to it.
+    Uncommitted,
+}
+
+/// Errors verifying watchtower alerts.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum AlertError {
+    /// outpoint {0} is not a part of the watch-list.
+    NotWatched(XOutpoint),
+
+    /// alert references witness {expected}, while the provided transaction is
+    /// {actual}.
+    WitnessMismatch {
+        expected: XWitnessId,
+        actual: XWitnessId,
+    },
+
+    /// witness {1} does not spend outpoint {0}.
+    NotSpent(XOutpoint, XWitnessId),
+}
+
+/// Watch-list for a seal-spend watchtower.
+#[derive(Getters, Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct WatchList {
+    #[getter(as_copy)]
+    contract_id: ContractId,
+    /// Witness transactions which are not known to be mined.
+    witnesses: LargeOrdSet<XWitnessId>,
+    /// Outpoints of the seals with their close methods.
+    seals: LargeOrdMap<XOutpoint, Method>,
+    /// Script patterns of the outputs able to carry commitments for the
+    /// watched seals.
+    patterns: TinyOrdSet<ScriptPattern>,
+}
+
+impl StrictSerialize for WatchList {}
+impl StrictDeserialize for WatchList {}
+
+impl WatchList {
+    /// Constructs an empty watch-list for the contract.
+    pub fn new(contract_id: ContractId) -> Self {
+        WatchList {
+            contract_id,
+            witnesses: empty!(),
+            seals: empty!(),
+            patterns: empty!(),
+        }
+    }
+
+    /// Adds seal outpoint to the watch-list.
+    pub fn watch_seal(&mut self, outpoint: XOutpoint, method: Method) {
+        self.seals
+            .insert(outpoint, method)
+            .expect("watch-list seals are limited by the contract history size");
+        self.patterns
+            .push(method.into())
+            .expect("number of script patterns is less than 256");
+    }
+
+    /// Adds witness transaction to the watch-list.
+    pub fn watch_witness(&mut self, witness_id: XWitnessId) {
+        self.witnesses
+            .push(witness_id)
+            .expect("watch-list witnesses are limited by the contract history size");
+    }
+
+    /// Removes seals for which the filter returns `false`, updating the
+    /// script patterns accordingly.
+    pub fn retain_seals(&mut self, mut f: impl FnMut(XOutpoint) -> bool) {
+        let seals = self.seals.iter().filter(|(outpoint, _)| f(**outpoint));
+        let seals = seals.map(|(outpoint, method)| (*outpoint, *method));
+        self.seals = Confined::from_iter_unsafe(seals);
+        let patterns = self.seals.values().copied().map(ScriptPattern::from);
+        self.patterns = Confined::from_iter_unsafe(patterns);
+    }
+
+    /// Checks whether the outpoint is watched.
+    pub fn is_watched(&self, outpoint: XOutpoint) -> bool { self.seals.contains_key(&outpoint) }
+
+    /// Verifies watchtower alert against the spending transaction.
+    ///
+    /// Returns whether the seal was closed by a transaction able to carry a
+    /// commitment with the seal close method; the commitment itself can be
+    /// checked only once the consignment with the closing operation is
+    /// received.
+    pub fn verify_alert(
+        &self,
+        alert: SpendAlert,
+        tx: &XWitnessTx,
+    ) -> Result<SealClosing, AlertError> {
+        let method = *self
+            .seals
+            .get(&alert.outpoint)
+            .ok_or(AlertError::NotWatched(alert.outpoint))?;
+        let actual = tx.witness_id();
+        if actual != alert.witness_id {
+            return Err(AlertError::WitnessMismatch {
+                expected: alert.witness_id,
+                actual,
+            });
+        }
+        if tx.layer1() != alert.outpoint.layer1() {
+            return Err(AlertError::NotSpent(alert.outpoint, actual));
+        }
+
+        let tx = tx.as_reduced_unsafe();
+        let outpoint = *alert.outpoint.as_reduced_unsafe();
+        if !tx.inputs.iter().any(|input| input.prev_output == outpoint) {
+            return Err(AlertError::NotSpent(alert.outpoint, actual));
+        }
+        let pattern = ScriptPattern::from(method);
+        if tx
+            .outputs
+            .iter()
+            .any(|output| pattern.matches(&output.script_pubkey[..]))
+        {
+            Ok(SealClosing::Committed)
+        } else {
+            Ok(SealClosing::Uncommitted)
+        }
+    }
+}
+
+impl ContractHistory {
+    /// Constructs watch-list with the seals of all known contract allocations
+    /// and the witnesses which are not known to be mined.
+    pub fn watch_list(&self) -> WatchList {
+        fn watch<State: KnownState>(list: &mut WatchList, a: &OutputAssignment<State>) {
+            list.watch_seal(a.seal.to_outpoint(), a.seal.method());
+        }
+        let mut list = WatchList::new(self.contract_id());
+        self.rights().iter().for_each(|a| watch(&mut list, a));
+        self.fungibles().iter().for_each(|a| watch(&mut list, a));
+        self.data().iter().for_each(|a| watch(&mut list, a));
+        self.attach().iter().for_each(|a| watch(&mut list, a));
+        for witness_id in self.pending_witnesses() {
+            list.watch_witness(witness_id);
+        }
+        list
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bp::{LockTime, Outpoint, Sats, ScriptPubkey, SeqNo, Tx, TxIn, TxOut, TxVer, Txid, Vout};
+
+    use super::*;
+    use crate::XChain;
+
+    fn tx(spent: Outpoint, script: ScriptPubkey) -> XWitnessTx {
+        let input = TxIn {
+            prev_output: spent,
+            sig_script: default!(),
+            sequence: SeqNo::from_consensus_u32(0xFFFF_FFFF),
+            witness: default!(),
+        };
+        let output = TxOut {
+            value: Sats::ZERO,
+            script_pubkey: script,
+        };
+        XChain::Bitcoin(Tx {
+            version: TxVer::V2,
+            inputs: Confined::try_from_iter([input]).unwrap(),
+            outputs: Confined::try_from_iter([output]).unwrap(),
+            lock_time: LockTime::ZERO,
+        })
+    }
+
+    #[test]
+    fn alerts() {
+        let watched = Outpoint::new(Txid::from([1; 32]), Vout::from(0u32));
+        let other = Outpoint::new(Txid::from([2; 32]), Vout::from(1u32));
+        let mut list = WatchList::new(ContractId::from([0xA1; 32]));
+        list.watch_seal(XChain::Bitcoin(watched).into(), Method::OpretFirst);
+        assert_eq!(list.patterns().iter().collect::<Vec<_>>(), vec![&ScriptPattern::Opret]);
+
+        let committed = tx(watched, ScriptPubkey::op_return(&[0u8; 32]));
+        let alert = SpendAlert {
+            outpoint: XChain::Bitcoin(watched).into(),
+            witness_id: committed.witness_id(),
+        };
+        assert_eq!(list.verify_alert(alert, &committed), Ok(SealClosing::Committed));
+
+        let uncommitted = tx(watched, ScriptPubkey::from_unsafe(vec![0x51]));
+        let alert = SpendAlert {
+            outpoint: XChain::Bitcoin(watched).into(),
+            witness_id: uncommitted.witness_id(),
+        };
+        assert_eq!(list.verify_alert(alert, &uncommitted), Ok(SealClosing::Uncommitted));
+        assert!(matches!(
+            list.verify_alert(alert, &committed),
+            Err(AlertError::WitnessMismatch { .. })
+        ));
+
+        let unrelated = tx(other, ScriptPubkey::op_return(&[0u8; 32]));
+        let alert = SpendAlert {
+            outpoint: XChain::Bitcoin(watched).into(),
+            witness_id: unrelated.witness_id(),
+        };
+        assert!(matches!(list.verify_alert(alert, &unrelated), Err(AlertError::NotSpent(..))));
+        let alert = SpendAlert {
+            outpoint: XChain::Bitcoin(other).into(),
+            witness_id: unrelated.witness_id(),
+        };
+        assert!(matches!(list.verify_alert(alert, &unrelated), Err(AlertError::NotWatched(_))));
+
+        list.retain_seals(|_| false);
+        assert!(!list.is_watched(XChain::Bitcoin(watched).into()));
+        assert!(list.patterns().is_empty());
+    }
+}
@@ -47,6 +47,8 @@ extern crate core;
 mod contract;
 pub mod schema;
 pub mod validation;
+pub mod consensus_rules;
+pub mod migrate;
 #[macro_use]
 pub mod vm;
 #[cfg(feature = "stl")]
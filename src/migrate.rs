@@ -0,0 +1,427 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Migration of data encoded by previous versions of RGB Core.
+//!
+//! Stashes keep contract operations, anchors and schemata in their strict
+//! encoding, which may change between the major versions of the consensus
+//! library. [`Migrator`] detects whether encoded data match the current
+//! structures and, if not, converts them with one of the registered
+//! [`LegacyFormat`]s. Definitions of the legacy structures belong to the
+//! previous versions of the library, thus each legacy format is provided by
+//! the software which still depends on it; this module defines the procedure
+//! and reports the outcome for each migrated item.
+//!
+//! Migration never changes the consensus data: a legacy item can be migrated
+//! only if its semantics are fully representable by the current structures.
+//! Since operation ids commit to the encoding of the operations, formats
+//! report the id each item had under the legacy format, and [`Migrator`]
+//! rejects items which would change their ids.
+
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+use amplify::confinement::U32;
+use strict_encoding::{DeserializeError, StreamReader, StrictDecode, StrictReader};
+
+use crate::{
+    deserialize_operation, BundleId, EAnchor, Extension, Genesis, OpId, Operation, Schema,
+    SchemaId, Transition, TransitionBundle,
+};
+
+/// Kind of the encoded item.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display(lowercase)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum ItemKind {
+    Schema,
+    Genesis,
+    Extension,
+    Transition,
+    Bundle,
+    Anchor,
+}
+
+/// Id of the encoded item.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display, From)]
+#[display(inner)]
+pub enum ItemId {
+    #[from]
+    Schema(SchemaId),
+    #[from]
+    Operation(OpId),
+    #[from]
+    Bundle(BundleId),
+}
+
+/// Item decoded into the current structures.
+#[derive(Clone, Eq, PartialEq, Debug, From)]
+#[allow(clippy::large_enum_variant)]
+pub enum MigratedItem {
+    #[from]
+    Schema(Schema),
+    #[from]
+    Genesis(Genesis),
+    #[from]
+    Extension(Extension),
+    #[from]
+    Transition(Transition),
+    #[from]
+    Bundle(TransitionBundle),
+    #[from]
+    Anchor(EAnchor),
+}
+
+impl MigratedItem {
+    pub fn kind(&self) -> ItemKind {
+        match self {
+            MigratedItem::Schema(_) => ItemKind::Schema,
+            MigratedItem::Genesis(_) => ItemKind::Genesis,
+            MigratedItem::Extension(_) => ItemKind::Extension,
+            MigratedItem::Transition(_) => ItemKind::Transition,
+            MigratedItem::Bundle(_) => ItemKind::Bundle,
+            MigratedItem::Anchor(_) => ItemKind::Anchor,
+        }
+    }
+
+    /// Returns id of the item, or `None` for anchors, which are not
+    /// identified by an id.
+    pub fn id(&self) -> Option<ItemId> {
+        Some(match self {
+            MigratedItem::Schema(schema) => schema.schema_id().into(),
+            MigratedItem::Genesis(genesis) => genesis.id().into(),
+            MigratedItem::Extension(extension) => extension.id().into(),
+            MigratedItem::Transition(transition) => transition.id().into(),
+            MigratedItem::Bundle(bundle) => bundle.bundle_id().into(),
+            MigratedItem::Anchor(_) => return None,
+        })
+    }
+}
+
+/// Item decoded by a legacy format.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct LegacyItem {
+    /// Item converted into the current structures.
+    pub item: MigratedItem,
+    /// Id of the item computed according to the legacy format, or `None` if
+    /// the item is not identified by an id.
+    pub legacy_id: Option<ItemId>,
+}
+
+impl LegacyItem {
+    /// Checks that the item is of the expected kind and that the migration
+    /// preserves its id.
+    pub fn check(self, kind: ItemKind) -> Result<MigratedItem, MigrationError> {
+        if self.item.kind() != kind {
+            return Err(MigrationError::KindMismatch(kind, self.item.kind()));
+        }
+        match (self.legacy_id, self.item.id()) {
+            (legacy, current) if legacy == current => Ok(self.item),
+            (Some(legacy), Some(current)) => Err(MigrationError::IdChanged {
+                kind,
+                legacy,
+                current,
+            }),
+            _ => Err(MigrationError::IdUnknown(kind)),
+        }
+    }
+}
+
+/// Errors migrating legacy data.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum MigrationError {
+    /// data are not encoded with the legacy format.
+    NotRecognized,
+
+    /// {0} encoded with the legacy format can't be represented with the
+    /// current structures: {1}
+    Unrepresentable(ItemKind, String),
+
+    /// legacy {0} returned {1} instead.
+    KindMismatch(ItemKind, ItemKind),
+
+    /// legacy {kind} {legacy} is migrated into {current}, while migration must
+    /// preserve ids.
+    IdChanged {
+        kind: ItemKind,
+        legacy: ItemId,
+        current: ItemId,
+    },
+
+    /// legacy format doesn't report id of the {0}, which is required to check
+    /// that the migration preserves it.
+    IdUnknown(ItemKind),
+}
+
+/// Decoder of the data encoded with a format of a previous version of RGB
+/// Core.
+pub trait LegacyFormat {
+    /// Human-readable name of the format, like the version of RGB Core which
+    /// used it.
+    fn name(&self) -> &str;
+
+    /// Decodes the item and converts it into the current structures,
+    /// reporting the id the item has according to the legacy format.
+    ///
+    /// Returns [`MigrationError::NotRecognized`] if the data are not encoded
+    /// with this format.
+    fn migrate(&self, kind: ItemKind, data: &[u8]) -> Result<LegacyItem, MigrationError>;
+}
+
+/// Outcome of the migration of a single item.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase", tag = "outcome")
+)]
+pub enum MigrationOutcome {
+    /// Item is already encoded with the current format.
+    Current,
+    /// Item was migrated from the legacy format.
+    Migrated { format: String },
+    /// Item can't be migrated; contains the errors reported by the current
+    /// and legacy formats.
+    Failed { errors: Vec<String> },
+}
+
+/// Report on the migration of a single item.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct MigrationEntry {
+    /// Position of the item in the migrated sequence.
+    pub no: usize,
+    pub kind: ItemKind,
+    pub outcome: MigrationOutcome,
+}
+
+impl Display for MigrationEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} {}: ", self.no, self.kind)?;
+        match &self.outcome {
+            MigrationOutcome::Current => f.write_str("current"),
+            MigrationOutcome::Migrated { format } => write!(f, "migrated from {format}"),
+            MigrationOutcome::Failed { errors } => write!(f, "failed ({})", errors.join("; ")),
+        }
+    }
+}
+
+/// Report on the migration of a sequence of items.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct MigrationReport {
+    pub entries: Vec<MigrationEntry>,
+}
+
+impl MigrationReport {
+    /// Detects whether all items were decoded, either as current or migrated
+    /// ones.
+    pub fn is_complete(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|entry| !matches!(entry.outcome, MigrationOutcome::Failed { .. }))
+    }
+
+    pub fn migrated(&self) -> impl Iterator<Item = &MigrationEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| matches!(entry.outcome, MigrationOutcome::Migrated { .. }))
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &MigrationEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| matches!(entry.outcome, MigrationOutcome::Failed { .. }))
+    }
+}
+
+impl Display for MigrationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{entry}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Migrates encoded items into the current structures using registered
+/// legacy formats.
+#[derive(Default)]
+pub struct Migrator<'formats> {
+    formats: Vec<&'formats dyn LegacyFormat>,
+}
+
+impl<'formats> Migrator<'formats> {
+    pub fn new() -> Self { Self::default() }
+
+    /// Registers legacy format. Formats are tried in the order of their
+    /// registration.
+    pub fn with_format(mut self, format: &'formats dyn LegacyFormat) -> Self {
+        self.formats.push(format);
+        self
+    }
+
+    /// Decodes data with the current format, without trying legacy ones.
+    pub fn decode_current(kind: ItemKind, data: &[u8]) -> Result<MigratedItem, DeserializeError> {
+        Ok(match kind {
+            ItemKind::Schema => decode::<Schema>(data)?.into(),
+            ItemKind::Genesis => deserialize_operation::<Genesis>(data)?.into(),
+            ItemKind::Extension => deserialize_operation::<Extension>(data)?.into(),
+            ItemKind::Transition => deserialize_operation::<Transition>(data)?.into(),
+            ItemKind::Bundle => decode::<TransitionBundle>(data)?.into(),
+            ItemKind::Anchor => decode::<EAnchor>(data)?.into(),
+        })
+    }
+
+    /// Decodes a single item, migrating it from a legacy format if it is not
+    /// encoded with the current one.
+    pub fn migrate(&self, kind: ItemKind, data: &[u8]) -> (Option<MigratedItem>, MigrationOutcome) {
+        let err = match Self::decode_current(kind, data) {
+            Ok(item) => return (Some(item), MigrationOutcome::Current),
+            Err(err) => err,
+        };
+        let mut errors = vec![format!("current: {err}")];
+        for format in &self.formats {
+            match format
+                .migrate(kind, data)
+                .and_then(|legacy| legacy.check(kind))
+            {
+                Ok(item) => {
+                    let outcome = MigrationOutcome::Migrated {
+                        format: format.name().to_owned(),
+                    };
+                    return (Some(item), outcome);
+                }
+                Err(MigrationError::NotRecognized) => {}
+                Err(err) => errors.push(format!("{}: {err}", format.name())),
+            }
+        }
+        (None, MigrationOutcome::Failed { errors })
+    }
+
+    /// Decodes a sequence of items, migrating legacy ones. Items which can't
+    /// be decoded are skipped and reported as failed.
+    pub fn migrate_all<'data>(
+        &self,
+        items: impl IntoIterator<Item = (ItemKind, &'data [u8])>,
+    ) -> (Vec<MigratedItem>, MigrationReport) {
+        let mut migrated = vec![];
+        let mut report = MigrationReport::default();
+        for (no, (kind, data)) in items.into_iter().enumerate() {
+            let (item, outcome) = self.migrate(kind, data);
+            migrated.extend(item);
+            report.entries.push(MigrationEntry { no, kind, outcome });
+        }
+        (migrated, report)
+    }
+}
+
+fn decode<T: StrictDecode>(data: &[u8]) -> Result<T, DeserializeError> {
+    let cursor = io::Cursor::new(data);
+    let mut reader = StrictReader::with(StreamReader::new::<U32>(cursor));
+    let item = T::strict_decode(&mut reader)?;
+    let cursor = reader.unbox().unconfine();
+    if cursor.position() as usize != data.len() {
+        return Err(DeserializeError::DataNotEntirelyConsumed);
+    }
+    Ok(item)
+}
+
+#[cfg(test)]
+mod test {
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    struct Legacy;
+
+    impl LegacyFormat for Legacy {
+        fn name(&self) -> &str { "legacy" }
+
+        fn migrate(&self, kind: ItemKind, data: &[u8]) -> Result<LegacyItem, MigrationError> {
+            let genesis = Genesis::strict_dumb();
+            match (kind, data) {
+                (ItemKind::Genesis, b"legacy") => Ok(LegacyItem {
+                    legacy_id: Some(genesis.id().into()),
+                    item: genesis.into(),
+                }),
+                (ItemKind::Genesis, b"reissued") => Ok(LegacyItem {
+                    legacy_id: Some(OpId::from([0xA5; 32]).into()),
+                    item: genesis.into(),
+                }),
+                (_, b"legacy") => {
+                    Err(MigrationError::Unrepresentable(kind, "unsupported".to_owned()))
+                }
+                _ => Err(MigrationError::NotRecognized),
+            }
+        }
+    }
+
+    #[test]
+    fn ids_preserved() {
+        let migrator = Migrator::new().with_format(&Legacy);
+        let (item, outcome) = migrator.migrate(ItemKind::Genesis, b"reissued");
+        assert_eq!(item, None);
+        let MigrationOutcome::Failed { errors } = outcome else {
+            panic!("genesis id must not change");
+        };
+        let legacy = ItemId::from(OpId::from([0xA5; 32]));
+        let current = ItemId::from(Genesis::strict_dumb().id());
+        let err = MigrationError::IdChanged {
+            kind: ItemKind::Genesis,
+            legacy,
+            current,
+        };
+        assert_eq!(errors[1], format!("legacy: {err}"));
+    }
+
+    #[test]
+    fn report() {
+        let migrator = Migrator::new().with_format(&Legacy);
+        let (items, report) = migrator.migrate_all([
+            (ItemKind::Genesis, &b"legacy"[..]),
+            (ItemKind::Transition, &b"legacy"[..]),
+            (ItemKind::Schema, &b"garbage"[..]),
+        ]);
+        assert_eq!(items, vec![MigratedItem::Genesis(Genesis::strict_dumb())]);
+        assert!(!report.is_complete());
+        assert_eq!(report.migrated().count(), 1);
+        assert_eq!(report.failed().map(|entry| entry.no).collect::<Vec<_>>(), vec![1, 2]);
+        let MigrationOutcome::Failed { errors } = &report.entries[1].outcome else {
+            panic!("transition must fail");
+        };
+        assert_eq!(errors.len(), 2);
+    }
+}
@@ -0,0 +1,248 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Static analysis of schemata detecting components which can never be used
+//! by a contract.
+
+use std::collections::BTreeSet;
+
+use aluvm::library::LibSite;
+
+use super::{
+    AssignmentType, AssignmentsSchema, ExtensionType, Schema, TransitionType, ValencyType,
+};
+
+/// Results of the static schema analysis, listing schema components which
+/// can't be used by any contract under the schema.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct SchemaAnalysis {
+    /// State transitions which can't be created, since they have no inputs
+    /// or require inputs of types which can't be assigned by any operation.
+    pub unreachable_transitions: BTreeSet<TransitionType>,
+    /// State extensions which can't be created, since they redeem valencies
+    /// not declared by any reachable operation.
+    pub unreachable_extensions: BTreeSet<ExtensionType>,
+    /// Owned state types which are never assigned by a reachable operation.
+    pub unassignable_types: BTreeSet<AssignmentType>,
+    /// Valencies which are not redeemed by any state extension.
+    pub unredeemable_valencies: BTreeSet<ValencyType>,
+    /// Validation scripts which are referenced only by unreachable operations.
+    pub unused_scripts: BTreeSet<LibSite>,
+}
+
+impl SchemaAnalysis {
+    /// Detects whether the analysis has found no issues with the schema.
+    pub fn is_clean(&self) -> bool {
+        self.unreachable_transitions.is_empty() &&
+            self.unreachable_extensions.is_empty() &&
+            self.unassignable_types.is_empty() &&
+            self.unredeemable_valencies.is_empty() &&
+            self.unused_scripts.is_empty()
+    }
+}
+
+fn assignable(schema: &AssignmentsSchema) -> impl Iterator<Item = AssignmentType> + '_ {
+    schema
+        .iter()
+        .filter(|(_, occ)| occ.max_value() > 0)
+        .map(|(ty, _)| *ty)
+}
+
+impl Schema {
+    /// Analyzes schema for the components which are unreachable or can never
+    /// be used by contracts.
+    ///
+    /// Reachability is computed starting from genesis: an extension is
+    /// reachable if all valencies it redeems are declared by reachable
+    /// operations, and a transition is reachable if it has inputs and all its
+    /// mandatory inputs are assignable by reachable operations.
+    pub fn analyze(&self) -> SchemaAnalysis {
+        let mut assigned = assignable(&self.genesis.assignments).collect::<BTreeSet<_>>();
+        let mut declared = self
+            .genesis
+            .valencies
+            .iter()
+            .copied()
+            .collect::<BTreeSet<_>>();
+        let mut transitions = BTreeSet::<TransitionType>::new();
+        let mut extensions = BTreeSet::<ExtensionType>::new();
+
+        loop {
+            let mut changed = false;
+            for (ty, schema) in &self.extensions {
+                if extensions.contains(ty) ||
+                    !schema
+                        .redeems
+                        .iter()
+                        .all(|valency| declared.contains(valency))
+                {
+                    continue;
+                }
+                extensions.insert(*ty);
+                assigned.extend(assignable(&schema.assignments));
+                declared.extend(schema.valencies.iter().copied());
+                changed = true;
+            }
+            for (ty, schema) in &self.transitions {
+                if transitions.contains(ty) || schema.inputs.is_empty() {
+                    continue;
+                }
+                let mandatory = schema
+                    .inputs
+                    .iter()
+                    .filter(|(_, occ)| occ.min_value() > 0)
+                    .all(|(input, _)| assigned.contains(input));
+                let any = schema.inputs.keys().any(|input| assigned.contains(input));
+                if !mandatory || !any {
+                    continue;
+                }
+                transitions.insert(*ty);
+                assigned.extend(assignable(&schema.assignments));
+                declared.extend(schema.valencies.iter().copied());
+                changed = true;
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let redeemed = self
+            .extensions
+            .values()
+            .flat_map(|schema| schema.redeems.iter().copied())
+            .collect::<BTreeSet<_>>();
+
+        let used_scripts = self
+            .genesis
+            .validator
+            .iter()
+            .copied()
+            .chain(
+                self.transitions
+                    .iter()
+                    .filter(|(ty, _)| transitions.contains(*ty))
+                    .filter_map(|(_, schema)| schema.validator),
+            )
+            .chain(
+                self.extensions
+                    .iter()
+                    .filter(|(ty, _)| extensions.contains(*ty))
+                    .filter_map(|(_, schema)| schema.validator),
+            )
+            .collect::<BTreeSet<_>>();
+        let unused_scripts = self
+            .transitions
+            .values()
+            .filter_map(|schema| schema.validator)
+            .chain(
+                self.extensions
+                    .values()
+                    .filter_map(|schema| schema.validator),
+            )
+            .filter(|site| !used_scripts.contains(site))
+            .collect();
+
+        SchemaAnalysis {
+            unreachable_transitions: self
+                .transitions
+                .keys()
+                .filter(|ty| !transitions.contains(*ty))
+                .copied()
+                .collect(),
+            unreachable_extensions: self
+                .extensions
+                .keys()
+                .filter(|ty| !extensions.contains(*ty))
+                .copied()
+                .collect(),
+            unassignable_types: self
+                .owned_types
+                .keys()
+                .filter(|ty| !assigned.contains(*ty))
+                .copied()
+                .collect(),
+            unredeemable_valencies: self
+                .valency_types
+                .iter()
+                .filter(|ty| !redeemed.contains(*ty))
+                .copied()
+                .collect(),
+            unused_scripts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::schema::{Occurrences, OwnedStateSchema, TransitionSchema};
+
+    #[test]
+    fn unreachable_components() {
+        let owned = AssignmentType::with(1);
+        let orphan = AssignmentType::with(2);
+        let valency = ValencyType::with(1);
+
+        let mut schema = Schema::strict_dumb();
+        schema
+            .owned_types
+            .insert(owned, OwnedStateSchema::Declarative)
+            .unwrap();
+        schema
+            .owned_types
+            .insert(orphan, OwnedStateSchema::Declarative)
+            .unwrap();
+        schema.valency_types.push(valency).unwrap();
+        schema
+            .genesis
+            .assignments
+            .insert(owned, Occurrences::Once)
+            .unwrap();
+
+        let mut transfer = TransitionSchema::default();
+        transfer.inputs.insert(owned, Occurrences::Once).unwrap();
+        transfer
+            .assignments
+            .insert(owned, Occurrences::OnceOrMore)
+            .unwrap();
+        let mut burn = TransitionSchema::default();
+        burn.inputs.insert(orphan, Occurrences::Once).unwrap();
+        schema
+            .transitions
+            .insert(TransitionType::with(1), transfer)
+            .unwrap();
+        schema
+            .transitions
+            .insert(TransitionType::with(2), burn)
+            .unwrap();
+
+        let analysis = schema.analyze();
+        assert!(!analysis.is_clean());
+        assert_eq!(analysis.unreachable_transitions, bset! { TransitionType::with(2) });
+        assert_eq!(analysis.unassignable_types, bset! { orphan });
+        assert_eq!(analysis.unredeemable_valencies, bset! { valency });
+        assert!(analysis.unreachable_extensions.is_empty());
+        assert!(analysis.unused_scripts.is_empty());
+    }
+}
@@ -25,11 +25,23 @@ mod operations;
 mod schema;
 mod state;
 mod occurrences;
+mod presentation;
+mod analysis;
+mod registry;
+mod names;
 
+pub use analysis::SchemaAnalysis;
+pub use names::{NamesError, SchemaNames, TYPE_NAME_MAX_LEN};
 pub use occurrences::{Occurrences, OccurrencesMismatch};
 pub use operations::{
     AssignmentType, AssignmentsSchema, ExtensionSchema, GenesisSchema, GlobalSchema, MetaSchema,
     OpFullType, OpSchema, OpType, TransitionSchema, ValencySchema, ValencyType,
 };
-pub use schema::{ExtensionType, GlobalStateType, MetaType, Schema, SchemaId, TransitionType};
+pub use presentation::PresentationRules;
+pub use registry::{
+    state_formats_meta, RegistryError, StateFormat, StateKey, StateRegistry, StateVersion,
+};
+pub use schema::{
+    ExtensionType, GlobalStateType, MetaType, Schema, SchemaFlags, SchemaId, TransitionType,
+};
 pub use state::{FungibleType, GlobalStateSchema, MediaType, OwnedStateSchema};
@@ -0,0 +1,303 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stable names for the types defined by a schema.
+//!
+//! Numeric ids of global state, assignment, transition and extension types
+//! differ between schemas. Schema developers may assign fixed names to these
+//! types with [`SchemaNames`], such that applications and operation builders
+//! can look up types by their names. Names are a part of the [`Schema`] and
+//! are committed into its [`crate::SchemaId`], thus they can't be changed
+//! without changing the schema.
+
+use std::str::FromStr;
+
+use amplify::confinement::TinyOrdMap;
+use strict_encoding::FieldName;
+
+use crate::schema::{AssignmentType, ExtensionType, GlobalStateType, Schema, TransitionType};
+use crate::LIB_NAME_RGB;
+
+/// Maximal length of a type name.
+pub const TYPE_NAME_MAX_LEN: usize = 64;
+
+/// Errors assigning names to the schema types.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum NamesError {
+    /// invalid type name '{0}': names must start with a lowercase letter and
+    /// contain only lowercase letters, digits and underscores.
+    InvalidName(String),
+
+    /// name '{0}' is already used for another type.
+    DuplicateName(String),
+
+    /// type named '{0}' already has another name.
+    DuplicateType(String),
+
+    /// too many type names; at most 255 names per each kind of types are
+    /// allowed.
+    TooManyNames,
+
+    /// type named '{0}' is not defined by the schema.
+    UnknownType(String),
+}
+
+/// Names of the types defined by a schema.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct SchemaNames {
+    global: TinyOrdMap<FieldName, GlobalStateType>,
+    owned: TinyOrdMap<FieldName, AssignmentType>,
+    transitions: TinyOrdMap<FieldName, TransitionType>,
+    extensions: TinyOrdMap<FieldName, ExtensionType>,
+}
+
+impl SchemaNames {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn is_empty(&self) -> bool {
+        self.global.is_empty() &&
+            self.owned.is_empty() &&
+            self.transitions.is_empty() &&
+            self.extensions.is_empty()
+    }
+
+    pub fn name_global(&mut self, name: &str, ty: GlobalStateType) -> Result<(), NamesError> {
+        insert(&mut self.global, name, ty)
+    }
+
+    pub fn name_owned(&mut self, name: &str, ty: AssignmentType) -> Result<(), NamesError> {
+        insert(&mut self.owned, name, ty)
+    }
+
+    pub fn name_transition(&mut self, name: &str, ty: TransitionType) -> Result<(), NamesError> {
+        insert(&mut self.transitions, name, ty)
+    }
+
+    pub fn name_extension(&mut self, name: &str, ty: ExtensionType) -> Result<(), NamesError> {
+        insert(&mut self.extensions, name, ty)
+    }
+
+    pub fn global_type(&self, name: &str) -> Option<GlobalStateType> { type_of(&self.global, name) }
+
+    pub fn assignment_type(&self, name: &str) -> Option<AssignmentType> {
+        type_of(&self.owned, name)
+    }
+
+    pub fn transition_type(&self, name: &str) -> Option<TransitionType> {
+        type_of(&self.transitions, name)
+    }
+
+    pub fn extension_type(&self, name: &str) -> Option<ExtensionType> {
+        type_of(&self.extensions, name)
+    }
+
+    pub fn global_name(&self, ty: GlobalStateType) -> Option<&FieldName> {
+        name_of(&self.global, ty)
+    }
+
+    pub fn assignment_name(&self, ty: AssignmentType) -> Option<&FieldName> {
+        name_of(&self.owned, ty)
+    }
+
+    pub fn transition_name(&self, ty: TransitionType) -> Option<&FieldName> {
+        name_of(&self.transitions, ty)
+    }
+
+    pub fn extension_name(&self, ty: ExtensionType) -> Option<&FieldName> {
+        name_of(&self.extensions, ty)
+    }
+
+    /// Checks that all the named types are defined by the schema.
+    pub fn verify(&self, schema: &Schema) -> Result<(), NamesError> {
+        let unknown = self
+            .global
+            .iter()
+            .find(|(_, ty)| !schema.global_types.contains_key(*ty))
+            .map(|(name, _)| name)
+            .or_else(|| {
+                self.owned
+                    .iter()
+                    .find(|(_, ty)| !schema.owned_types.contains_key(*ty))
+                    .map(|(name, _)| name)
+            })
+            .or_else(|| {
+                self.transitions
+                    .iter()
+                    .find(|(_, ty)| !ty.is_blank() && !schema.transitions.contains_key(*ty))
+                    .map(|(name, _)| name)
+            })
+            .or_else(|| {
+                self.extensions
+                    .iter()
+                    .find(|(_, ty)| !schema.extensions.contains_key(*ty))
+                    .map(|(name, _)| name)
+            });
+        match unknown {
+            Some(name) => Err(NamesError::UnknownType(name.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Schema {
+    /// Returns id of the global state type with the given name.
+    pub fn global_type_by_name(&self, name: &str) -> Option<GlobalStateType> {
+        self.names
+            .global_type(name)
+            .filter(|ty| self.global_types.contains_key(ty))
+    }
+
+    /// Returns id of the assignment type with the given name.
+    pub fn assignment_type_by_name(&self, name: &str) -> Option<AssignmentType> {
+        self.names
+            .assignment_type(name)
+            .filter(|ty| self.owned_types.contains_key(ty))
+    }
+
+    /// Returns id of the state transition type with the given name.
+    pub fn transition_type_by_name(&self, name: &str) -> Option<TransitionType> {
+        self.names
+            .transition_type(name)
+            .filter(|ty| ty.is_blank() || self.transitions.contains_key(ty))
+    }
+
+    /// Returns id of the state extension type with the given name.
+    pub fn extension_type_by_name(&self, name: &str) -> Option<ExtensionType> {
+        self.names
+            .extension_type(name)
+            .filter(|ty| self.extensions.contains_key(ty))
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    name.len() <= TYPE_NAME_MAX_LEN &&
+        name.starts_with(|c: char| c.is_ascii_lowercase()) &&
+        name.chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+fn insert<T: Copy + Eq>(
+    names: &mut TinyOrdMap<FieldName, T>,
+    name: &str,
+    ty: T,
+) -> Result<(), NamesError> {
+    let field_name = FieldName::from_str(name)
+        .ok()
+        .filter(|_| is_valid_name(name))
+        .ok_or_else(|| NamesError::InvalidName(name.to_owned()))?;
+    if names.contains_key(&field_name) {
+        return Err(NamesError::DuplicateName(name.to_owned()));
+    }
+    if let Some((other, _)) = names.iter().find(|(_, t)| **t == ty) {
+        return Err(NamesError::DuplicateType(other.to_string()));
+    }
+    names
+        .insert(field_name, ty)
+        .map_err(|_| NamesError::TooManyNames)?;
+    Ok(())
+}
+
+fn type_of<T: Copy>(names: &TinyOrdMap<FieldName, T>, name: &str) -> Option<T> {
+    let name = FieldName::from_str(name).ok()?;
+    names.get(&name).copied()
+}
+
+fn name_of<T: Copy + Eq>(names: &TinyOrdMap<FieldName, T>, ty: T) -> Option<&FieldName> {
+    names.iter().find(|(_, t)| **t == ty).map(|(name, _)| name)
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{GlobalStateSchema, OwnedStateSchema};
+
+    #[test]
+    fn naming() {
+        let mut names = SchemaNames::new();
+        names
+            .name_global("ticker", GlobalStateType::with(2000))
+            .unwrap();
+        names
+            .name_owned("assets", AssignmentType::with(4000))
+            .unwrap();
+        names
+            .name_transition("transfer", TransitionType::with(10000))
+            .unwrap();
+
+        assert_eq!(names.global_type("ticker"), Some(GlobalStateType::with(2000)));
+        assert_eq!(names.assignment_name(AssignmentType::with(4000)), Some(&fname!("assets")));
+        assert_eq!(
+            names.name_global("Ticker", GlobalStateType::with(2001)),
+            Err(NamesError::InvalidName(s!("Ticker")))
+        );
+        assert_eq!(
+            names.name_global("name", GlobalStateType::with(2000)),
+            Err(NamesError::DuplicateType(s!("ticker")))
+        );
+    }
+
+    #[test]
+    fn committed_to_schema() {
+        let mut schema = Schema::strict_dumb();
+        schema.global_types = Confined::from_iter_unsafe([(
+            GlobalStateType::with(2000),
+            GlobalStateSchema::once(strict_types::SemId::strict_dumb()),
+        )]);
+        schema.owned_types = Confined::from_iter_unsafe([(
+            AssignmentType::with(4000),
+            OwnedStateSchema::Fungible(default!()),
+        )]);
+        let schema_id = schema.schema_id();
+
+        schema
+            .names
+            .name_global("ticker", GlobalStateType::with(2000))
+            .unwrap();
+        schema
+            .names
+            .name_owned("assets", AssignmentType::with(4000))
+            .unwrap();
+        assert_eq!(schema.names.verify(&schema), Ok(()));
+        assert_ne!(schema.schema_id(), schema_id);
+        assert_eq!(schema.global_type_by_name("ticker"), Some(GlobalStateType::with(2000)));
+        assert_eq!(schema.assignment_type_by_name("assets"), Some(AssignmentType::with(4000)));
+        assert_eq!(schema.assignment_type_by_name("ticker"), None);
+
+        schema
+            .names
+            .name_transition("transfer", TransitionType::with(1))
+            .unwrap();
+        assert_eq!(schema.names.verify(&schema), Err(NamesError::UnknownType(s!("transfer"))));
+        assert_eq!(schema.transition_type_by_name("transfer"), None);
+    }
+}
@@ -58,6 +58,8 @@ impl AssignmentType {
 pub struct ValencyType(u16);
 impl ValencyType {
     pub const fn with(ty: u16) -> Self { Self(ty) }
+    #[inline]
+    pub fn to_le_bytes(&self) -> [u8; 2] { self.0.to_le_bytes() }
 }
 
 pub type MetaSchema = TinyOrdSet<MetaType>;
@@ -155,6 +157,11 @@ pub struct GenesisSchema {
     pub assignments: AssignmentsSchema,
     pub valencies: ValencySchema,
     // NB: it is possible to transform option into enum covering other virtual machines
+    /// Entry point of the genesis validation script checking issuance-time
+    /// invariants, like the issued amounts matching the declared supply. The
+    /// script is run only for the contract genesis, with access to its global
+    /// state and metadata; unlike transition and extension scripts it runs
+    /// with no operation type in the `a16[0]` register.
     pub validator: Option<LibSite>,
 }
 
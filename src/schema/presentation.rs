@@ -0,0 +1,140 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Asset presentation constraints declared for a schema.
+//!
+//! Ticker, asset name and decimal precision are not interpreted by the
+//! consensus, however malformed values make assets unusable in wallets. The
+//! constraints defined here allow to reject such values at issuance, as a
+//! part of genesis validation.
+
+use std::ops::RangeInclusive;
+
+use crate::schema::{GlobalStateType, SchemaId};
+use crate::validation::{Failure, Status};
+use crate::{DataState, Genesis, Operation};
+
+/// Presentation constraints for the assets issued under a specific schema.
+///
+/// Ticker and name global state values are expected to be strict-encoded
+/// ASCII strings with a single-byte length prefix; precision value is a
+/// single byte.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct PresentationRules {
+    pub schema_id: SchemaId,
+    pub ticker: Option<GlobalStateType>,
+    pub ticker_len: RangeInclusive<u8>,
+    pub name: Option<GlobalStateType>,
+    pub name_len: RangeInclusive<u8>,
+    pub precision: Option<GlobalStateType>,
+    pub max_precision: u8,
+}
+
+impl PresentationRules {
+    /// Constructs rules with the commonly used bounds: tickers of 1 to 8
+    /// characters, names of 1 to 40 characters and up to 18 decimal digits.
+    pub fn with(schema_id: SchemaId) -> Self {
+        PresentationRules {
+            schema_id,
+            ticker: None,
+            ticker_len: 1..=8,
+            name: None,
+            name_len: 1..=40,
+            precision: None,
+            max_precision: 18,
+        }
+    }
+
+    /// Checks genesis global state against the presentation rules.
+    pub fn validate(&self, genesis: &Genesis) -> Status {
+        let mut status = Status::new();
+        if genesis.schema_id != self.schema_id {
+            return status;
+        }
+        let opid = genesis.id();
+        let values = |ty: Option<GlobalStateType>| {
+            ty.and_then(|ty| genesis.globals.get(&ty).map(|values| (ty, values.to_vec())))
+        };
+
+        if let Some((ty, values)) = values(self.ticker) {
+            for value in values {
+                let valid = parse_string(&value).is_some_and(|ticker| {
+                    self.ticker_len.contains(&(ticker.len() as u8)) &&
+                        ticker
+                            .bytes()
+                            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+                });
+                if !valid {
+                    status.add_failure(Failure::PresentationInvalidTicker(opid, ty));
+                }
+            }
+        }
+        if let Some((ty, values)) = values(self.name) {
+            for value in values {
+                let valid = parse_string(&value).is_some_and(|name| {
+                    self.name_len.contains(&(name.len() as u8)) &&
+                        name.bytes().all(|c| c.is_ascii_graphic() || c == b' ')
+                });
+                if !valid {
+                    status.add_failure(Failure::PresentationInvalidName(opid, ty));
+                }
+            }
+        }
+        if let Some((ty, values)) = values(self.precision) {
+            for value in values {
+                match value.as_slice() {
+                    [precision] if *precision <= self.max_precision => {}
+                    _ => {
+                        status.add_failure(Failure::PresentationInvalidPrecision(opid, ty));
+                    }
+                }
+            }
+        }
+        status
+    }
+}
+
+fn parse_string(value: &DataState) -> Option<&str> {
+    let (len, data) = value.as_slice().split_first()?;
+    if *len as usize != data.len() || !data.is_ascii() {
+        return None;
+    }
+    std::str::from_utf8(data).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::SmallBlob;
+
+    use super::*;
+
+    fn data(bytes: &[u8]) -> DataState {
+        DataState::from(SmallBlob::try_from(bytes.to_vec()).unwrap())
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(parse_string(&data(b"\x03BTC")), Some("BTC"));
+        assert_eq!(parse_string(&data(b"\x04BTC")), None);
+        assert_eq!(parse_string(&data(b"")), None);
+    }
+}
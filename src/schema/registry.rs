@@ -0,0 +1,315 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Registry of versioned formats of the contract state.
+//!
+//! State formats are versioned with a major and minor version. A new minor
+//! version may only extend the format of the previous version by appending
+//! new fields to its structure, such that the new version keeps the meaning
+//! of all the existing fields. Changes not following this rule require a new
+//! major version. Values encoded with older minor versions of the same major
+//! version remain valid and are accepted by the validator.
+//!
+//! Since the acceptance of the older versions affects the contract validity,
+//! the versions are committed by the genesis in its
+//! [`MetaType::STATE_FORMATS`] metadata, from which the validator constructs
+//! the registry.
+
+use std::collections::BTreeMap;
+
+use amplify::confinement::Confined;
+use amplify::{Bytes32, Wrapper};
+use strict_types::{SemId, Ty, TypeSystem};
+
+use super::{AssignmentType, GlobalStateType, MetaType};
+use crate::{Genesis, MetaValue};
+
+/// State type which format is versioned.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+pub enum StateKey {
+    #[display("global {0}")]
+    Global(GlobalStateType),
+    #[display("owned {0}")]
+    Owned(AssignmentType),
+}
+
+/// Version of a state format.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display("{major}.{minor}")]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct StateVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl StateVersion {
+    pub fn new(major: u16, minor: u16) -> Self { StateVersion { major, minor } }
+}
+
+/// Format of a state type under a specific version.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+pub struct StateFormat {
+    pub key: StateKey,
+    pub version: StateVersion,
+    pub sem_id: SemId,
+}
+
+impl StateFormat {
+    const LEN: usize = 39;
+
+    fn to_bytes(self) -> [u8; Self::LEN] {
+        let (kind, ty) = match self.key {
+            StateKey::Global(ty) => (0u8, ty.to_inner()),
+            StateKey::Owned(ty) => (1u8, ty.to_inner()),
+        };
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0] = kind;
+        bytes[1..3].copy_from_slice(&ty.to_le_bytes());
+        bytes[3..5].copy_from_slice(&self.version.major.to_le_bytes());
+        bytes[5..7].copy_from_slice(&self.version.minor.to_le_bytes());
+        bytes[7..].copy_from_slice(&self.sem_id.as_inner().to_byte_array());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let u16_at = |pos: usize| u16::from_le_bytes([bytes[pos], bytes[pos + 1]]);
+        let key = match bytes[0] {
+            0 => StateKey::Global(GlobalStateType::with(u16_at(1))),
+            1 => StateKey::Owned(AssignmentType::with(u16_at(1))),
+            _ => return None,
+        };
+        let version = StateVersion::new(u16_at(3), u16_at(5));
+        let sem_id = <[u8; 32]>::try_from(&bytes[7..]).expect("fixed size");
+        Some(StateFormat {
+            key,
+            version,
+            sem_id: SemId::from_inner(Bytes32::from_byte_array(sem_id)),
+        })
+    }
+}
+
+impl Genesis {
+    /// Returns versions of the state formats declared by the
+    /// [`MetaType::STATE_FORMATS`] metadata. If the metadata is absent, only
+    /// the formats used by the schema are accepted.
+    ///
+    /// Returns `None` if the metadata value is malformed.
+    pub fn state_formats(&self) -> Option<Vec<StateFormat>> {
+        let Some(meta) = self.metadata.get(&MetaType::STATE_FORMATS) else {
+            return Some(vec![]);
+        };
+        let data = meta.as_slice();
+        if data.is_empty() || data.len() % StateFormat::LEN != 0 {
+            return None;
+        }
+        data.chunks(StateFormat::LEN)
+            .map(StateFormat::from_bytes)
+            .collect()
+    }
+}
+
+/// Constructs value for the [`MetaType::STATE_FORMATS`] genesis metadata.
+///
+/// Returns `None` if there are no formats or too many of them to fit the
+/// metadata.
+pub fn state_formats_meta(formats: &[StateFormat]) -> Option<MetaValue> {
+    if formats.is_empty() {
+        return None;
+    }
+    let data = formats
+        .iter()
+        .flat_map(|format| format.to_bytes())
+        .collect::<Vec<_>>();
+    Confined::try_from(data).ok().map(MetaValue::from)
+}
+
+/// Errors registering state formats.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum RegistryError {
+    /// version {1} of the {0} state format is not newer than the already
+    /// registered version {2}.
+    NotNewer(StateKey, StateVersion, StateVersion),
+
+    /// semantic type {0} is absent from the type system.
+    UnknownType(SemId),
+
+    /// version {1} of the {0} state format doesn't extend the format of the
+    /// previous minor version {2} and requires a new major version.
+    NotExtension(StateKey, StateVersion, StateVersion),
+}
+
+/// Registry of the formats of state types known under different versions.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct StateRegistry(BTreeMap<StateKey, BTreeMap<StateVersion, SemId>>);
+
+impl StateRegistry {
+    pub fn new() -> Self { Self::default() }
+
+    /// Constructs registry from the formats committed by the genesis (see
+    /// [`Genesis::state_formats`]), registering them in ascending order of
+    /// their versions.
+    pub fn from_formats(
+        formats: impl IntoIterator<Item = StateFormat>,
+        types: &TypeSystem,
+    ) -> Result<Self, RegistryError> {
+        let mut formats = formats.into_iter().collect::<Vec<_>>();
+        formats.sort();
+        let mut registry = StateRegistry::new();
+        for format in formats {
+            registry.register(format.key, format.version, format.sem_id, types)?;
+        }
+        Ok(registry)
+    }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Registers new version of the state format.
+    ///
+    /// Versions must be registered in ascending order. A new minor version
+    /// must extend the format of the latest registered version under the same
+    /// major version, which is checked against the provided type system.
+    pub fn register(
+        &mut self,
+        key: StateKey,
+        version: StateVersion,
+        sem_id: SemId,
+        types: &TypeSystem,
+    ) -> Result<(), RegistryError> {
+        let Some(ty) = types.as_inner().get(&sem_id) else {
+            return Err(RegistryError::UnknownType(sem_id));
+        };
+        let versions = self.0.entry(key).or_default();
+        if let Some((last, last_id)) = versions.last_key_value() {
+            if *last >= version {
+                return Err(RegistryError::NotNewer(key, version, *last));
+            }
+            if last.major == version.major {
+                let last_ty = types
+                    .as_inner()
+                    .get(last_id)
+                    .ok_or(RegistryError::UnknownType(*last_id))?;
+                if !extends(last_ty, ty) {
+                    return Err(RegistryError::NotExtension(key, version, *last));
+                }
+            }
+        }
+        versions.insert(version, sem_id);
+        Ok(())
+    }
+
+    /// Returns all registered versions of the state format.
+    pub fn versions(&self, key: StateKey) -> impl Iterator<Item = (StateVersion, SemId)> + '_ {
+        self.0
+            .get(&key)
+            .into_iter()
+            .flat_map(|versions| versions.iter().map(|(v, id)| (*v, *id)))
+    }
+
+    /// Returns version of the state format with the given semantic type id.
+    pub fn version(&self, key: StateKey, sem_id: SemId) -> Option<StateVersion> {
+        self.versions(key)
+            .find(|(_, id)| *id == sem_id)
+            .map(|(version, _)| version)
+    }
+
+    /// Detects older version of the state format compatible with the format
+    /// `sem_id` under which the value can be read.
+    ///
+    /// Returns `None` if `sem_id` is not registered, or if the value can't be
+    /// read by any older minor version under the same major version.
+    pub fn read_older(
+        &self,
+        key: StateKey,
+        sem_id: SemId,
+        types: &TypeSystem,
+        data: impl AsRef<[u8]>,
+    ) -> Option<StateVersion> {
+        let current = self.version(key, sem_id)?;
+        self.versions(key)
+            .filter(|(version, _)| version.major == current.major && *version < current)
+            .find(|(_, id)| types.strict_deserialize_type(*id, data.as_ref()).is_ok())
+            .map(|(version, _)| version)
+    }
+}
+
+/// Checks whether type `new` extends type `old` by appending fields to it.
+fn extends(old: &Ty<SemId>, new: &Ty<SemId>) -> bool {
+    match (old, new) {
+        (Ty::Struct(old), Ty::Struct(new)) => {
+            old.len() <= new.len() && old.iter().zip(new.iter()).all(|(a, b)| a == b)
+        }
+        (Ty::Tuple(old), Ty::Tuple(new)) => {
+            old.len() <= new.len() && old.iter().zip(new.iter()).all(|(a, b)| a == b)
+        }
+        (old, new) => old == new,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn genesis_formats() {
+        let global = StateFormat {
+            key: StateKey::Global(GlobalStateType::with(2000)),
+            version: StateVersion::new(1, 0),
+            sem_id: SemId::from_inner(Bytes32::from_byte_array([0xAB; 32])),
+        };
+        let owned = StateFormat {
+            key: StateKey::Owned(AssignmentType::with(4000)),
+            version: StateVersion::new(2, 1),
+            sem_id: SemId::from_inner(Bytes32::from_byte_array([0xCD; 32])),
+        };
+        assert_eq!(state_formats_meta(&[]), None);
+
+        let mut genesis = Genesis::strict_dumb();
+        assert_eq!(genesis.state_formats(), Some(vec![]));
+        let meta = state_formats_meta(&[global, owned]).unwrap();
+        genesis
+            .metadata
+            .add_value(MetaType::STATE_FORMATS, meta)
+            .unwrap();
+        assert_eq!(genesis.state_formats(), Some(vec![global, owned]));
+        assert_eq!(
+            StateRegistry::from_formats([global], &TypeSystem::default()),
+            Err(RegistryError::UnknownType(global.sem_id))
+        );
+
+        let mut bytes = global.to_bytes();
+        bytes[0] = 2;
+        let mut genesis = Genesis::strict_dumb();
+        let meta = MetaValue::from(Confined::try_from(bytes.to_vec()).unwrap());
+        genesis
+            .metadata
+            .add_value(MetaType::STATE_FORMATS, meta)
+            .unwrap();
+        assert_eq!(genesis.state_formats(), None);
+    }
+}
@@ -21,6 +21,7 @@
 // limitations under the License.
 
 use std::cmp::Ordering;
+use std::collections::BTreeSet;
 use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 
@@ -37,9 +38,13 @@ use strict_encoding::{
 use strict_types::SemId;
 
 use super::{
-    AssignmentType, ExtensionSchema, GenesisSchema, OwnedStateSchema, TransitionSchema, ValencyType,
+    AssignmentType, ExtensionSchema, GenesisSchema, OwnedStateSchema, SchemaNames,
+    TransitionSchema, ValencyType,
+};
+use crate::{
+    impl_serde_baid64, Ffv, GlobalStateSchema, Identity, Occurrences, WitnessConstraints,
+    LIB_NAME_RGB,
 };
-use crate::{impl_serde_baid64, Ffv, GlobalStateSchema, Identity, Occurrences, LIB_NAME_RGB};
 
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From, Display)]
 #[wrapper(FromStr, LowerHex, UpperHex)]
@@ -54,6 +59,90 @@ use crate::{impl_serde_baid64, Ffv, GlobalStateSchema, Identity, Occurrences, LI
 pub struct MetaType(u16);
 impl MetaType {
     pub const fn with(ty: u16) -> Self { Self(ty) }
+
+    /// Detects whether the type belongs to the upper 256 metadata types,
+    /// which are reserved by the consensus for the schemata opting into
+    /// [`SchemaFlags::RESERVED_TYPES`].
+    pub const fn is_reserved(self) -> bool { self.0 > u16::MAX - 0x100 }
+}
+
+impl MetaType {
+    /// Genesis metadata type declaring the set of seal close methods
+    /// acceptable for the contract (see [`crate::CloseMethodSet`]). Schemas
+    /// supporting the restriction must declare this metadata type with a
+    /// single-byte semantic type.
+    pub const CLOSE_METHODS: Self = MetaType(u16::MAX);
+
+    /// Genesis metadata type declaring ids of the contracts the contract
+    /// depends on, as a concatenation of 32-byte contract ids (see
+    /// [`crate::DependencyGraph`]).
+    pub const DEPENDENCIES: Self = MetaType(u16::MAX - 1);
+
+    /// Operation metadata type locking assignments of the operation with
+    /// scripts evaluated when the assignments are spent (see
+    /// [`crate::AssignmentLocks`]).
+    pub const ASSIGNMENT_LOCKS: Self = MetaType(u16::MAX - 2);
+
+    /// Operation metadata type providing data to the lock scripts of the
+    /// assignments spent by the operation.
+    pub const LOCK_WITNESS: Self = MetaType(u16::MAX - 3);
+
+    /// Metadata type of the state extensions upgrading the contract to a
+    /// successor schema, as a 32-byte successor schema id followed by a
+    /// little-endian 32-bit activation height (see [`crate::SchemaUpgrade`]).
+    /// Extension types permitted to upgrade the contract must declare this
+    /// metadata type in their schema.
+    pub const SCHEMA_UPGRADE: Self = MetaType(u16::MAX - 5);
+
+    /// Genesis metadata type declaring global state types which values are
+    /// replaced by each new value, as a concatenation of little-endian
+    /// 16-bit type ids (see [`crate::GlobalSupersession`]).
+    pub const REPLACEABLE_GLOBALS: Self = MetaType(u16::MAX - 6);
+
+    /// Genesis metadata type salting the contract id with a single-byte code
+    /// of the network the contract is issued for (see [`crate::ChainNet`]).
+    pub const CHAIN_SALT: Self = MetaType(u16::MAX - 7);
+
+    /// Operation metadata type declaring features used by the operation, as
+    /// a little-endian 16-bit mask (see [`crate::OpFeatures`]). Operation
+    /// types permitted to use features must declare this metadata type in
+    /// their schema.
+    pub const OP_FEATURES: Self = MetaType(u16::MAX - 8);
+
+    /// Genesis metadata type declaring maximum total supply of fungible
+    /// assignment types, as a concatenation of little-endian 16-bit type ids,
+    /// each followed by a little-endian 64-bit cap (see
+    /// [`crate::SupplyTracker`]).
+    pub const SUPPLY_CAPS: Self = MetaType(u16::MAX - 9);
+
+    /// Genesis metadata type declaring oracle feeds, as a concatenation of
+    /// little-endian 16-bit global state types, where each attestation type
+    /// is followed by the type of its oracle set (see [`crate::OracleFeed`]).
+    pub const ORACLE_FEEDS: Self = MetaType(u16::MAX - 10);
+
+    /// Genesis metadata type declaring the maximum reorg depth assumed by the
+    /// contract, as a little-endian 32-bit number of blocks (see
+    /// [`crate::FinalityDepth`]).
+    pub const FINALITY_DEPTH: Self = MetaType(u16::MAX - 11);
+
+    /// Genesis metadata type declaring limits on the redemption of valencies,
+    /// as a concatenation of little-endian 16-bit valency types, each followed
+    /// by a little-endian 16-bit maximum number of redemptions (zero for
+    /// unlimited) and a little-endian 32-bit expiry height (zero for no
+    /// expiry) (see [`crate::ValencyTracker`]).
+    pub const VALENCY_LIMITS: Self = MetaType(u16::MAX - 12);
+
+    /// Genesis metadata type holding the 32-byte id of the genesis template
+    /// the genesis was bound from (see [`crate::GenesisTemplateId`]).
+    pub const GENESIS_TEMPLATE: Self = MetaType(u16::MAX - 13);
+
+    /// Genesis metadata type declaring formats of the state types under their
+    /// versions, accepted by the validator (see [`crate::StateFormat`]). Each
+    /// format is encoded as a byte of the state kind (zero for global and one
+    /// for owned state), followed by the little-endian 16-bit state type, the
+    /// little-endian 16-bit major and minor versions and the 32-byte semantic
+    /// type id.
+    pub const STATE_FORMATS: Self = MetaType(u16::MAX - 14);
 }
 
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From, Display)]
@@ -69,6 +158,17 @@ impl MetaType {
 pub struct GlobalStateType(u16);
 impl GlobalStateType {
     pub const fn with(ty: u16) -> Self { Self(ty) }
+
+    /// Detects whether the type belongs to the upper 256 global state types,
+    /// which are reserved by the consensus for the schemata opting into
+    /// [`SchemaFlags::RESERVED_TYPES`].
+    pub const fn is_reserved(self) -> bool { self.0 > u16::MAX - 0x100 }
+}
+
+impl GlobalStateType {
+    /// Global state type holding contract terms committed by the genesis (see
+    /// [`crate::ContractTerms`]).
+    pub const TERMS: Self = GlobalStateType(u16::MAX);
 }
 
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From, Display)]
@@ -147,6 +247,43 @@ impl Display for SchemaId {
 
 impl_serde_baid64!(SchemaId);
 
+/// Flags opting the schema into consensus features.
+///
+/// Flags are encoded as a single byte, which was reserved (and thus zero) in
+/// schemata created before the flags were introduced, so these schemata use
+/// none of the features.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default, Display)]
+#[display("0x{0:02X}")]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", transparent)
+)]
+pub struct SchemaFlags(u8);
+
+impl SchemaFlags {
+    /// Schema uses metadata and global state types reserved by the consensus
+    /// (see [`MetaType::is_reserved`] and [`GlobalStateType::is_reserved`])
+    /// with their consensus meaning, like [`GlobalStateType::TERMS`] or
+    /// [`MetaType::SCHEMA_UPGRADE`]. For other schemata these types are
+    /// ordinary types defined by the schema.
+    pub const RESERVED_TYPES: Self = SchemaFlags(0x01);
+
+    /// Flags known to this version of the library.
+    pub const KNOWN: Self = Self::RESERVED_TYPES;
+
+    pub const fn with(bits: u8) -> Self { Self(bits) }
+
+    pub const fn bits(self) -> u8 { self.0 }
+
+    pub const fn contains(self, flags: Self) -> bool { self.0 & flags.0 == flags.0 }
+
+    /// Returns flags unknown to this version of the library.
+    pub const fn unknown(self) -> Self { Self(self.0 & !Self::KNOWN.0) }
+}
+
 #[derive(Clone, Eq, Debug)]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
 #[strict_type(lib = LIB_NAME_RGB)]
@@ -157,7 +294,7 @@ impl_serde_baid64!(SchemaId);
 )]
 pub struct Schema {
     pub ffv: Ffv,
-    pub flags: ReservedBytes<1, 0>,
+    pub flags: SchemaFlags,
 
     pub name: TypeName,
     pub timestamp: i64,
@@ -170,6 +307,12 @@ pub struct Schema {
     pub genesis: GenesisSchema,
     pub extensions: TinyOrdMap<ExtensionType, ExtensionSchema>,
     pub transitions: TinyOrdMap<TransitionType, TransitionSchema>,
+    /// Names of the types defined by the schema, committed into the schema
+    /// id.
+    pub names: SchemaNames,
+    /// Constraints on `nSequence` and locktime of the witness transactions
+    /// of the schema contracts.
+    pub witness_constraints: WitnessConstraints,
 
     pub reserved: ReservedBytes<8, 0>,
 }
@@ -192,6 +335,8 @@ impl CommitEncode for Schema {
         e.commit_to_serialized(&self.genesis);
         e.commit_to_map(&self.extensions);
         e.commit_to_map(&self.transitions);
+        e.commit_to_serialized(&self.names);
+        e.commit_to_serialized(&self.witness_constraints);
 
         e.commit_to_serialized(&self.reserved);
     }
@@ -216,6 +361,10 @@ impl Schema {
     #[inline]
     pub fn schema_id(&self) -> SchemaId { self.commit_id() }
 
+    /// Detects whether the schema uses types reserved by the consensus (see
+    /// [`SchemaFlags::RESERVED_TYPES`]).
+    pub fn reserves_types(&self) -> bool { self.flags.contains(SchemaFlags::RESERVED_TYPES) }
+
     pub fn blank_transition(&self) -> TransitionSchema {
         let mut schema = TransitionSchema::default();
         for id in self.owned_types.keys() {
@@ -246,13 +395,40 @@ impl Schema {
             .chain(self.extensions.values().filter_map(|i| i.validator))
             .map(|site| site.lib)
     }
+
+    /// Returns ids of all libraries referenced by the schema scripts, which
+    /// must be provided to validate contracts under the schema.
+    pub fn required_libs(&self) -> BTreeSet<LibId> { self.libs().collect() }
 }
 
 #[cfg(test)]
 mod test {
     use strict_encoding::StrictDumb;
+    use strict_types::TypeSystem;
 
     use super::*;
+    use crate::validation::Failure;
+
+    #[test]
+    fn flags() {
+        let mut schema = Schema::strict_dumb();
+        assert!(!schema.reserves_types());
+        assert!(MetaType::SCHEMA_UPGRADE.is_reserved());
+        assert!(GlobalStateType::TERMS.is_reserved());
+        assert!(!MetaType::with(0xFEFF).is_reserved());
+
+        let id = schema.schema_id();
+        schema.flags = SchemaFlags::RESERVED_TYPES;
+        assert!(schema.reserves_types());
+        assert_ne!(schema.schema_id(), id);
+        assert!(schema.verify(&TypeSystem::default()).failures.is_empty());
+
+        schema.flags = SchemaFlags::with(0x03);
+        assert!(schema.reserves_types());
+        assert_eq!(schema.verify(&TypeSystem::default()).failures, vec![
+            Failure::SchemaFlagsUnknown(SchemaFlags::with(0x02))
+        ]);
+    }
 
     #[test]
     fn display() {
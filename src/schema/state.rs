@@ -37,6 +37,22 @@ use crate::{StateType, LIB_NAME_RGB};
 #[non_exhaustive]
 #[repr(u8)]
 pub enum MediaType {
+    #[display("image/png")]
+    ImagePng = 0x01,
+    #[display("image/jpeg")]
+    ImageJpeg = 0x02,
+    #[display("image/gif")]
+    ImageGif = 0x03,
+    #[display("image/webp")]
+    ImageWebp = 0x04,
+    #[display("application/pdf")]
+    ApplicationPdf = 0x10,
+    #[display("application/zip")]
+    ApplicationZip = 0x11,
+    #[display("application/gzip")]
+    ApplicationGzip = 0x12,
+    #[display("video/mp4")]
+    VideoMp4 = 0x20,
     #[display("*/*")]
     #[strict_type(dumb)]
     Any = 0xFF,
@@ -44,10 +60,34 @@ pub enum MediaType {
 }
 
 impl MediaType {
-    pub fn conforms(&self, other: &MediaType) -> bool {
-        match (self, other) {
-            (MediaType::Any, MediaType::Any) => true,
+    /// Checks whether the media type matches the `other` media type, which
+    /// may be a wildcard.
+    pub fn conforms(&self, other: &MediaType) -> bool { *other == MediaType::Any || self == other }
+
+    /// Detects media type of the data from their magic bytes.
+    ///
+    /// Returns `None` if the data doesn't start with the magic bytes of any
+    /// of the known media types.
+    pub fn detect(data: &[u8]) -> Option<MediaType> {
+        const MAGIC: [(&[u8], MediaType); 7] = [
+            (b"\x89PNG\r\n\x1a\n", MediaType::ImagePng),
+            (b"\xFF\xD8\xFF", MediaType::ImageJpeg),
+            (b"GIF87a", MediaType::ImageGif),
+            (b"GIF89a", MediaType::ImageGif),
+            (b"%PDF-", MediaType::ApplicationPdf),
+            (b"PK\x03\x04", MediaType::ApplicationZip),
+            (b"\x1F\x8B", MediaType::ApplicationGzip),
+        ];
+        if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+            return Some(MediaType::ImageWebp);
+        }
+        if data.len() >= 8 && &data[4..8] == b"ftyp" {
+            return Some(MediaType::VideoMp4);
         }
+        MAGIC
+            .iter()
+            .find(|(magic, _)| data.starts_with(magic))
+            .map(|(_, media_type)| *media_type)
     }
 }
 
@@ -28,6 +28,7 @@ use strict_types::stl::{std_stl, strict_types_stl};
 use strict_types::typelib::LibBuilder;
 use strict_types::{CompileError, TypeLib};
 
+use crate::validation::SpentState;
 use crate::{
     ContractState, DbcProof, Extension, Genesis, OpCommitment, Schema, TransitionBundle,
     XWitnessId, LIB_NAME_RGB,
@@ -35,8 +36,9 @@ use crate::{
 
 /// Strict types id for the library providing data types for RGB consensus.
 pub const LIB_ID_RGB: &str =
-    "stl:sqbS4Bea-l!IK7Dt-86Fkfgg-NOhi22w-S!kz5bC-l$99W!Y#bless-donald-poker";
+    "stl:oaRkSBGb-GlaJE9$-AlooQVW-t5cbPQ5-bdgpUjg-7abIDwQ#beauty-vienna-capitan";
 
+#[allow(clippy::result_large_err)]
 fn _rgb_core_stl() -> Result<TypeLib, CompileError> {
     LibBuilder::new(libname!(LIB_NAME_RGB), tiny_bset! {
         std_stl().to_dependency(),
@@ -54,6 +56,7 @@ fn _rgb_core_stl() -> Result<TypeLib, CompileError> {
     .transpile::<Extension>()
     .transpile::<ContractState>()
     .transpile::<OpCommitment>()
+    .transpile::<SpentState>()
     .compile()
 }
 
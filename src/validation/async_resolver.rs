@@ -0,0 +1,236 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Asynchronous witness resolution.
+//!
+//! Wallets resolving witnesses with asynchronous clients (Electrum, Esplora
+//! etc. running on an async runtime) implement [`AsyncResolveWitness`] and
+//! validate consignments with [`validate_async`]. The validation resolves all
+//! the witnesses of the consignment asynchronously first, and then runs the
+//! synchronous validation procedure against the resolved data, such that no
+//! thread gets blocked waiting for the resolver.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::{ready, Future};
+
+use super::prefetch::{consignment_witnesses, NoResolver, PrefetchedResolver};
+use super::{ConsignmentApi, Status, Validator, WitnessHint, WitnessResolverError};
+use crate::{Layer1, WitnessOrd, XWitnessId, XWitnessTx};
+
+/// Asynchronous counterpart of [`super::ResolveWitness`].
+pub trait AsyncResolveWitness {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> impl Future<Output = Result<XWitnessTx, WitnessResolverError>>;
+
+    /// Resolves the witness transaction using the hint provided by the
+    /// consignment (see
+    /// [`super::ResolveWitness::resolve_pub_witness_hinted`]).
+    fn resolve_pub_witness_hinted(
+        &self,
+        witness_id: XWitnessId,
+        _hint: WitnessHint,
+    ) -> impl Future<Output = Result<XWitnessTx, WitnessResolverError>> {
+        self.resolve_pub_witness(witness_id)
+    }
+
+    /// Resolves position of the witness transaction in the blockchain (see
+    /// [`super::ResolveWitness::resolve_witness_ord`]).
+    fn resolve_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> impl Future<Output = Result<WitnessOrd, WitnessResolverError>> {
+        ready(Err(WitnessResolverError::Unknown(witness_id)))
+    }
+
+    /// Resolves position of the witness transaction using the hint provided
+    /// by the consignment (see
+    /// [`super::ResolveWitness::resolve_witness_ord_hinted`]).
+    fn resolve_witness_ord_hinted(
+        &self,
+        witness_id: XWitnessId,
+        _hint: WitnessHint,
+    ) -> impl Future<Output = Result<WitnessOrd, WitnessResolverError>> {
+        self.resolve_witness_ord(witness_id)
+    }
+
+    /// Resolves height of the chain tip for the layer 1 (see
+    /// [`super::ResolveWitness::resolve_tip_height`]).
+    fn resolve_tip_height(&self, _layer1: Layer1) -> impl Future<Output = Option<u32>> {
+        ready(None)
+    }
+}
+
+/// Validation procedure which works like [`Validator::validate`], but
+/// resolves witnesses with an asynchronous resolver.
+///
+/// Witnesses are resolved one by one; resolvers able to serve concurrent
+/// requests may batch them internally.
+pub async fn validate_async<C: ConsignmentApi, R: AsyncResolveWitness>(
+    consignment: &C,
+    resolver: &R,
+    testnet: bool,
+) -> Status {
+    let mut resolved = BTreeMap::new();
+    let mut layers1 = BTreeSet::new();
+    for (witness_id, hint) in consignment_witnesses(consignment) {
+        let (tx, ord) = match hint {
+            Some(hint) => (
+                resolver.resolve_pub_witness_hinted(witness_id, hint).await,
+                resolver.resolve_witness_ord_hinted(witness_id, hint).await,
+            ),
+            None => (
+                resolver.resolve_pub_witness(witness_id).await,
+                resolver.resolve_witness_ord(witness_id).await,
+            ),
+        };
+        resolved.insert(witness_id, (tx, ord));
+        layers1.insert(witness_id.layer1());
+    }
+    let mut tips = BTreeMap::new();
+    for layer1 in layers1 {
+        if let Some(height) = resolver.resolve_tip_height(layer1).await {
+            tips.insert(layer1, height);
+        }
+    }
+
+    let prefetched = PrefetchedResolver {
+        inner: &NoResolver,
+        resolved,
+        tips,
+    };
+    Validator::validate(consignment, &prefetched, testnet)
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use amplify::confinement::Confined;
+    use amplify::Wrapper;
+    use bp::seals::txout::CloseMethod;
+    use strict_encoding::StrictDumb;
+    use strict_types::TypeSystem;
+
+    use super::*;
+    use crate::validation::fixtures::{TestChain, TestConsignment};
+    use crate::validation::ResolveWitness;
+    use crate::{
+        block_on, Assign, AssignmentType, Assignments, Genesis, InputMap, Operation, Schema,
+        Transition, TransitionBundle, TypedAssigns, Vin, VoidState,
+    };
+
+    /// Asynchronous resolver over the test chain, recording the requested
+    /// witnesses.
+    struct AsyncChain<'chain> {
+        chain: &'chain TestChain,
+        requests: RefCell<Vec<XWitnessId>>,
+    }
+
+    impl<'chain> AsyncChain<'chain> {
+        fn new(chain: &'chain TestChain) -> Self {
+            AsyncChain {
+                chain,
+                requests: default!(),
+            }
+        }
+    }
+
+    impl AsyncResolveWitness for AsyncChain<'_> {
+        fn resolve_pub_witness(
+            &self,
+            witness_id: XWitnessId,
+        ) -> impl Future<Output = Result<XWitnessTx, WitnessResolverError>> {
+            self.requests.borrow_mut().push(witness_id);
+            ready(self.chain.resolve_pub_witness(witness_id))
+        }
+
+        fn resolve_witness_ord(
+            &self,
+            witness_id: XWitnessId,
+        ) -> impl Future<Output = Result<WitnessOrd, WitnessResolverError>> {
+            ready(self.chain.resolve_witness_ord(witness_id))
+        }
+
+        fn resolve_tip_height(&self, layer1: Layer1) -> impl Future<Output = Option<u32>> {
+            ready(self.chain.resolve_tip_height(layer1))
+        }
+    }
+
+    fn consignment(chain: &mut TestChain) -> (TestConsignment, XWitnessId) {
+        let schema = Schema::strict_dumb();
+        let mut genesis = Genesis::strict_dumb();
+        genesis.schema_id = schema.schema_id();
+        genesis.testnet = chain.testnet();
+
+        let output = Assign::revealed(chain.graph_seal(1), VoidState::default());
+        let mut transition = Transition::strict_dumb();
+        transition.contract_id = genesis.contract_id();
+        transition.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            AssignmentType::with(1),
+            TypedAssigns::Declarative(Confined::from_iter_unsafe([output.clone()])),
+        )]));
+        let opid = transition.id();
+        let bundle = TransitionBundle {
+            close_method: CloseMethod::OpretFirst,
+            input_map: InputMap::with(Vin::from(0u32), opid),
+            known_transitions: Confined::with((opid, transition)),
+        };
+        let spent = chain.outpoint();
+        let (witness_id, anchor) =
+            chain.commit(genesis.contract_id(), bundle.bundle_id(), [spent], 2);
+
+        let mut consignment = TestConsignment::new(schema, TypeSystem::default(), genesis);
+        let bundle_id = consignment.add_bundle(bundle, witness_id, anchor);
+        consignment
+            .terminals
+            .push((bundle_id, output.to_confidential_seal()));
+        (consignment, witness_id)
+    }
+
+    #[test]
+    fn matches_sync_validation() {
+        let mut chain = TestChain::regtest();
+        let (consignment, witness_id) = consignment(&mut chain);
+        chain.mine(witness_id);
+
+        let resolver = AsyncChain::new(&chain);
+        let status = block_on(validate_async(&consignment, &resolver, chain.testnet()));
+        assert_eq!(status, Validator::validate(&consignment, &chain, chain.testnet()));
+        assert_eq!(resolver.requests.into_inner(), vec![witness_id]);
+        assert!(status.absent_pub_witnesses.is_empty(), "{status}");
+    }
+
+    #[test]
+    fn unknown_witness() {
+        let mut chain = TestChain::regtest();
+        let (consignment, witness_id) = consignment(&mut chain);
+        chain.drop_witness(witness_id);
+
+        let resolver = AsyncChain::new(&chain);
+        let status = block_on(validate_async(&consignment, &resolver, chain.testnet()));
+        assert_eq!(status, Validator::validate(&consignment, &chain, chain.testnet()));
+        assert_eq!(resolver.requests.into_inner(), vec![witness_id]);
+        assert!(status.absent_pub_witnesses.contains(&witness_id), "{status}");
+    }
+}
@@ -0,0 +1,172 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of attachment content against the attachment state.
+
+use super::{Failure, Info, Status};
+use crate::{AttachId, MediaType, OpId, Operation, RevealedAttach, TypedAssigns};
+
+/// Source of attachment data used during the validation.
+///
+/// Attachments are not a part of consignments, thus their content is never
+/// checked by the consensus validation. If the actual data are known to the
+/// software running validation, this hook allows to check them against the
+/// attachment digest, declared media type and size, recording results into the
+/// validation status.
+pub trait AttachmentVerifier {
+    /// Returns attachment content, if it is available.
+    fn attachment_data(&self, id: AttachId) -> Option<Vec<u8>>;
+
+    /// Detects media type of the attachment content from its magic bytes.
+    /// Returns `None` if the media type can't be detected.
+    ///
+    /// Defaults to [`MediaType::detect`].
+    fn detect_media_type(&self, data: &[u8]) -> Option<MediaType> { MediaType::detect(data) }
+
+    /// Maximum size of attachment content accepted by the verifier.
+    fn max_size(&self) -> Option<usize> { None }
+}
+
+/// Checks content of a single attachment state against its declaration.
+pub fn verify_attachment(
+    verifier: &(impl AttachmentVerifier + ?Sized),
+    opid: OpId,
+    attach: &RevealedAttach,
+) -> Status {
+    let mut status = Status::new();
+    let Some(data) = verifier.attachment_data(attach.id) else {
+        status.add_info(Info::AttachmentUnavailable(opid, attach.id));
+        return status;
+    };
+    if AttachId::with_content(&data) != attach.id {
+        status.add_failure(Failure::AttachmentDigestMismatch(opid, attach.id));
+    }
+    if let Some(max_size) = verifier.max_size() {
+        if data.len() > max_size {
+            status.add_failure(Failure::AttachmentSizeExceeded(opid, attach.id, data.len()));
+        }
+    }
+    if let Some(found) = verifier.detect_media_type(&data) {
+        if !found.conforms(&attach.media_type) {
+            status.add_failure(Failure::AttachmentMediaTypeMismatch {
+                opid,
+                id: attach.id,
+                expected: attach.media_type,
+                found,
+            });
+        }
+    }
+    status
+}
+
+/// Checks content of all revealed attachments assigned by the operation.
+pub fn verify_op_attachments(
+    verifier: &(impl AttachmentVerifier + ?Sized),
+    operation: &impl Operation,
+) -> Status {
+    let opid = operation.id();
+    let mut status = Status::new();
+    for (_, assignments) in operation.assignments().flat() {
+        let TypedAssigns::Attachment(list) = assignments else {
+            continue;
+        };
+        for attach in list.iter().filter_map(|a| a.as_revealed_state()) {
+            status += verify_attachment(verifier, opid, attach);
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    struct Attachments(BTreeMap<AttachId, Vec<u8>>);
+
+    impl AttachmentVerifier for Attachments {
+        fn attachment_data(&self, id: AttachId) -> Option<Vec<u8>> { self.0.get(&id).cloned() }
+
+        fn max_size(&self) -> Option<usize> { Some(16) }
+    }
+
+    #[test]
+    fn media_type_detection() {
+        assert_eq!(MediaType::detect(b"\x89PNG\r\n\x1a\n\0\0"), Some(MediaType::ImagePng));
+        assert_eq!(MediaType::detect(b"GIF89a"), Some(MediaType::ImageGif));
+        assert_eq!(MediaType::detect(b"RIFF\0\0\0\0WEBPVP8 "), Some(MediaType::ImageWebp));
+        assert_eq!(MediaType::detect(b"\0\0\0\x20ftypisom"), Some(MediaType::VideoMp4));
+        assert_eq!(MediaType::detect(b"%PDF-1.7"), Some(MediaType::ApplicationPdf));
+        assert_eq!(MediaType::detect(b"plain text"), None);
+        assert_eq!(MediaType::detect(b""), None);
+
+        assert!(MediaType::ImagePng.conforms(&MediaType::Any));
+        assert!(MediaType::ImagePng.conforms(&MediaType::ImagePng));
+        assert!(!MediaType::ImagePng.conforms(&MediaType::ImageJpeg));
+        assert!(!MediaType::Any.conforms(&MediaType::ImagePng));
+    }
+
+    #[test]
+    fn verification() {
+        let opid = OpId::from([1; 32]);
+        let png = b"\x89PNG\r\n\x1a\n\0\0".to_vec();
+        let large = b"%PDF-1.7 and a long body".to_vec();
+        let id = AttachId::with_content(&png);
+        let large_id = AttachId::with_content(&large);
+        let forged = AttachId::from([3; 32]);
+        let verifier = Attachments(bmap! { id => png.clone(), large_id => large, forged => png });
+        let attach = |id, media_type| RevealedAttach::with_salt(id, media_type, 0);
+
+        let status = verify_attachment(&verifier, opid, &attach(id, MediaType::ImagePng));
+        assert!(status.failures.is_empty());
+        let status = verify_attachment(&verifier, opid, &attach(id, MediaType::Any));
+        assert!(status.failures.is_empty());
+
+        let status = verify_attachment(&verifier, opid, &attach(id, MediaType::ImageJpeg));
+        assert_eq!(status.failures, vec![Failure::AttachmentMediaTypeMismatch {
+            opid,
+            id,
+            expected: MediaType::ImageJpeg,
+            found: MediaType::ImagePng,
+        }]);
+
+        let status = verify_attachment(&verifier, opid, &attach(large_id, MediaType::ImagePng));
+        assert_eq!(status.failures, vec![
+            Failure::AttachmentSizeExceeded(opid, large_id, 24),
+            Failure::AttachmentMediaTypeMismatch {
+                opid,
+                id: large_id,
+                expected: MediaType::ImagePng,
+                found: MediaType::ApplicationPdf,
+            }
+        ]);
+
+        let status = verify_attachment(&verifier, opid, &attach(forged, MediaType::ImagePng));
+        assert_eq!(status.failures, vec![Failure::AttachmentDigestMismatch(opid, forged)]);
+
+        let unknown = AttachId::from([2; 32]);
+        let status = verify_attachment(&verifier, opid, &attach(unknown, MediaType::Any));
+        assert!(status.failures.is_empty());
+        assert_eq!(status.info, vec![Info::AttachmentUnavailable(opid, unknown)]);
+    }
+}
@@ -0,0 +1,123 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cancellation of long-running validation.
+//!
+//! Validation of large consignments may take minutes. Software with a user
+//! interface passes a [`CancelToken`] or a deadline with the
+//! [`super::ValidationHooks`]; the validator checks them between operations
+//! and bundles and, once triggered, stops and returns the status collected so
+//! far with a [`super::Failure::ValidationInterrupted`] describing how far the
+//! validation went.
+//!
+//! The deadline is measured with a [`CacheClock`], which defaults to the
+//! system time. WebAssembly targets have no system time, so there the
+//! deadline is not checked unless a clock is provided with the hooks.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::resolver_cache::CacheClock;
+
+/// Token allowing to cancel validation from another thread.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self { Self::default() }
+
+    /// Requests validations using this token to stop.
+    pub fn cancel(&self) { self.0.store(true, Ordering::Relaxed) }
+
+    pub fn is_cancelled(&self) -> bool { self.0.load(Ordering::Relaxed) }
+}
+
+/// Reason validation was stopped before completion.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum Interruption {
+    #[display("cancelled")]
+    Cancelled,
+
+    #[display("deadline exceeded")]
+    DeadlineExceeded,
+}
+
+/// Conditions checked by the validator to stop the validation early.
+#[derive(Clone, Debug, Default)]
+pub(super) struct StopConditions {
+    pub cancel: Option<CancelToken>,
+    pub deadline: Option<Duration>,
+    pub clock: Option<CacheClock>,
+}
+
+impl StopConditions {
+    pub fn check(&self) -> Option<Interruption> {
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Some(Interruption::Cancelled);
+        }
+        if let (Some(deadline), Some(clock)) = (self.deadline, self.clock) {
+            if clock() >= deadline {
+                return Some(Interruption::DeadlineExceeded);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn clock() -> Duration { Duration::from_secs(1000) }
+
+    #[test]
+    fn stop_conditions() {
+        let token = CancelToken::new();
+        let conditions = StopConditions {
+            cancel: Some(token.clone()),
+            deadline: Some(Duration::from_secs(1001)),
+            clock: Some(clock),
+        };
+        assert_eq!(conditions.check(), None);
+        token.cancel();
+        assert_eq!(conditions.check(), Some(Interruption::Cancelled));
+
+        let expired = StopConditions {
+            cancel: None,
+            deadline: Some(Duration::from_secs(1000)),
+            clock: Some(clock),
+        };
+        assert_eq!(expired.check(), Some(Interruption::DeadlineExceeded));
+
+        let no_clock = StopConditions {
+            clock: None,
+            ..expired
+        };
+        assert_eq!(no_clock.check(), None);
+    }
+}
@@ -0,0 +1,332 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Resumable validation.
+//!
+//! Validation of consignments with tens of thousands of operations may take
+//! longer than the software running it is allowed to stay active (for
+//! instance, mobile wallets get suspended by the operating system).
+//! [`Validator::validate_resumable`] records validation progress into a
+//! [`ValidationCheckpoint`]: statuses of validated operations and bundles,
+//! and witnesses resolved so far. The checkpoint is passed to the caller after
+//! each step, such that it can be persisted; validation started again with
+//! the persisted checkpoint skips the recorded steps and produces the same
+//! status as an uninterrupted validation.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use super::{
+    ConsignmentApi, ResolveWitness, Status, ValidationContext, ValidationStore, Validator,
+    WitnessHint, WitnessResolverError,
+};
+use crate::{BundleId, Layer1, OpId, WitnessOrd, XWitnessId, XWitnessTx};
+
+/// Progress of a validation, which can be persisted and used to resume the
+/// validation later.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ValidationCheckpoint {
+    context: Option<ValidationContext>,
+    ops: BTreeMap<OpId, Status>,
+    bundles: BTreeMap<BundleId, (XWitnessId, Status)>,
+    witness_txes: BTreeMap<XWitnessId, XWitnessTx>,
+    witness_ords: BTreeMap<XWitnessId, WitnessOrd>,
+}
+
+impl ValidationCheckpoint {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty() && self.bundles.is_empty() && self.witness_txes.is_empty()
+    }
+
+    /// Returns ids of operations which validation was completed.
+    pub fn validated_ops(&self) -> impl Iterator<Item = OpId> + '_ { self.ops.keys().copied() }
+
+    /// Returns ids of the witnesses resolved so far.
+    pub fn resolved_witnesses(&self) -> impl Iterator<Item = XWitnessId> + '_ {
+        self.witness_txes.keys().copied()
+    }
+
+    /// Combines statuses of the validated operations and bundles.
+    ///
+    /// The partial status lacks checks performed over the whole consignment,
+    /// which are done only once the validation completes.
+    pub fn partial_status(&self) -> Status {
+        let mut status = Status::new();
+        for op_status in self.ops.values() {
+            status += op_status.clone();
+        }
+        for (_, bundle_status) in self.bundles.values() {
+            status += bundle_status.clone();
+        }
+        status
+    }
+
+    /// Resets progress recorded under another validation context.
+    fn switch_context(&mut self, context: ValidationContext) {
+        if self.context != Some(context) {
+            *self = ValidationCheckpoint {
+                context: Some(context),
+                ..default!()
+            };
+        }
+    }
+}
+
+impl ValidationStore for ValidationCheckpoint {
+    fn op_status(&self, context: ValidationContext, opid: OpId) -> Option<Status> {
+        if self.context != Some(context) {
+            return None;
+        }
+        self.ops.get(&opid).cloned()
+    }
+
+    fn store_op_status(&mut self, context: ValidationContext, opid: OpId, status: &Status) {
+        self.switch_context(context);
+        self.ops.insert(opid, status.clone());
+    }
+
+    fn witness_status(
+        &self,
+        context: ValidationContext,
+        witness_id: XWitnessId,
+        bundle_id: BundleId,
+    ) -> Option<Status> {
+        if self.context != Some(context) {
+            return None;
+        }
+        self.bundles
+            .get(&bundle_id)
+            .filter(|(id, _)| *id == witness_id)
+            .map(|(_, status)| status.clone())
+    }
+
+    fn store_witness_status(
+        &mut self,
+        context: ValidationContext,
+        witness_id: XWitnessId,
+        bundle_id: BundleId,
+        status: &Status,
+    ) {
+        self.switch_context(context);
+        self.bundles.insert(bundle_id, (witness_id, status.clone()));
+    }
+}
+
+/// Checkpoint shared by the store and the resolver used during resumable
+/// validation, reporting each recorded step to the caller.
+struct Recorder<'checkpoint> {
+    checkpoint: RefCell<&'checkpoint mut ValidationCheckpoint>,
+    on_progress: RefCell<&'checkpoint mut dyn FnMut(&ValidationCheckpoint)>,
+}
+
+impl<'checkpoint> Recorder<'checkpoint> {
+    fn record(&self, f: impl FnOnce(&mut ValidationCheckpoint)) {
+        f(&mut self.checkpoint.borrow_mut());
+        let mut on_progress = self.on_progress.borrow_mut();
+        (*on_progress)(&self.checkpoint.borrow());
+    }
+}
+
+struct RecordingStore<'recorder, 'checkpoint>(&'recorder Recorder<'checkpoint>);
+
+impl<'recorder, 'checkpoint> ValidationStore for RecordingStore<'recorder, 'checkpoint> {
+    fn op_status(&self, context: ValidationContext, opid: OpId) -> Option<Status> {
+        self.0.checkpoint.borrow().op_status(context, opid)
+    }
+
+    fn store_op_status(&mut self, context: ValidationContext, opid: OpId, status: &Status) {
+        self.0
+            .record(|checkpoint| checkpoint.store_op_status(context, opid, status));
+    }
+
+    fn witness_status(
+        &self,
+        context: ValidationContext,
+        witness_id: XWitnessId,
+        bundle_id: BundleId,
+    ) -> Option<Status> {
+        self.0
+            .checkpoint
+            .borrow()
+            .witness_status(context, witness_id, bundle_id)
+    }
+
+    fn store_witness_status(
+        &mut self,
+        context: ValidationContext,
+        witness_id: XWitnessId,
+        bundle_id: BundleId,
+        status: &Status,
+    ) {
+        self.0.record(|checkpoint| {
+            checkpoint.store_witness_status(context, witness_id, bundle_id, status)
+        });
+    }
+}
+
+struct RecordingResolver<'recorder, 'checkpoint, 'resolver, R: ResolveWitness> {
+    recorder: &'recorder Recorder<'checkpoint>,
+    inner: &'resolver R,
+}
+
+impl<'recorder, 'checkpoint, 'resolver, R: ResolveWitness>
+    RecordingResolver<'recorder, 'checkpoint, 'resolver, R>
+{
+    fn record_tx(
+        &self,
+        witness_id: XWitnessId,
+        resolve: impl FnOnce() -> Result<XWitnessTx, WitnessResolverError>,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        if let Some(tx) = self
+            .recorder
+            .checkpoint
+            .borrow()
+            .witness_txes
+            .get(&witness_id)
+        {
+            return Ok(tx.clone());
+        }
+        let tx = resolve()?;
+        self.recorder.record(|checkpoint| {
+            checkpoint.witness_txes.insert(witness_id, tx.clone());
+        });
+        Ok(tx)
+    }
+
+    fn record_ord(
+        &self,
+        witness_id: XWitnessId,
+        resolve: impl FnOnce() -> Result<WitnessOrd, WitnessResolverError>,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        if let Some(ord) = self
+            .recorder
+            .checkpoint
+            .borrow()
+            .witness_ords
+            .get(&witness_id)
+        {
+            return Ok(*ord);
+        }
+        let ord = resolve()?;
+        self.recorder.record(|checkpoint| {
+            checkpoint.witness_ords.insert(witness_id, ord);
+        });
+        Ok(ord)
+    }
+}
+
+impl<'recorder, 'checkpoint, 'resolver, R: ResolveWitness> ResolveWitness
+    for RecordingResolver<'recorder, 'checkpoint, 'resolver, R>
+{
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        self.record_tx(witness_id, || self.inner.resolve_pub_witness(witness_id))
+    }
+
+    fn resolve_pub_witness_hinted(
+        &self,
+        witness_id: XWitnessId,
+        hint: WitnessHint,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        self.record_tx(witness_id, || self.inner.resolve_pub_witness_hinted(witness_id, hint))
+    }
+
+    fn resolve_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        self.record_ord(witness_id, || self.inner.resolve_witness_ord(witness_id))
+    }
+
+    fn resolve_witness_ord_hinted(
+        &self,
+        witness_id: XWitnessId,
+        hint: WitnessHint,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        self.record_ord(witness_id, || self.inner.resolve_witness_ord_hinted(witness_id, hint))
+    }
+
+    fn resolve_tip_height(&self, layer1: Layer1) -> Option<u32> {
+        self.inner.resolve_tip_height(layer1)
+    }
+}
+
+impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
+    Validator<'consignment, 'resolver, C, R>
+{
+    /// Validation procedure which works like [`Validator::validate`], but
+    /// records its progress into the checkpoint, calling `on_progress` after
+    /// each recorded step.
+    ///
+    /// If the checkpoint was produced by an interrupted validation of the
+    /// same contract, the steps recorded in it are not repeated.
+    pub fn validate_resumable(
+        consignment: &'consignment C,
+        resolver: &'resolver R,
+        checkpoint: &mut ValidationCheckpoint,
+        on_progress: &mut dyn FnMut(&ValidationCheckpoint),
+        testnet: bool,
+    ) -> Status {
+        let recorder = Recorder {
+            checkpoint: RefCell::new(checkpoint),
+            on_progress: RefCell::new(on_progress),
+        };
+        let resolver = RecordingResolver {
+            recorder: &recorder,
+            inner: resolver,
+        };
+        let mut store = RecordingStore(&recorder);
+        Validator::validate_with_store(consignment, &resolver, &mut store, testnet)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ContractId, SchemaId};
+
+    #[test]
+    fn checkpoint_context() {
+        let opid = OpId::from([0xA1; 32]);
+        let schema_id = SchemaId::from([0xA0; 32]);
+        let context = ValidationContext::with(ContractId::from([0xA2; 32]), schema_id, false);
+        let other = ValidationContext::with(ContractId::from([0xA3; 32]), schema_id, false);
+
+        let mut checkpoint = ValidationCheckpoint::new();
+        checkpoint.store_op_status(context, opid, &Status::new());
+        assert_eq!(checkpoint.op_status(context, opid), Some(Status::new()));
+        assert_eq!(checkpoint.op_status(other, opid), None);
+        assert_eq!(checkpoint.validated_ops().collect::<Vec<_>>(), vec![opid]);
+
+        checkpoint.store_op_status(other, OpId::from([0xA4; 32]), &Status::new());
+        assert_eq!(checkpoint.op_status(context, opid), None);
+    }
+}
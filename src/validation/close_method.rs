@@ -0,0 +1,217 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Plugins implementing seal close methods.
+//!
+//! Validation of the witness commitments for the standard close methods is
+//! built into the validator. Deterministic bitcoin commitment schemes without
+//! a built-in verifier can be prototyped by implementing [`CloseMethodPlugin`]
+//! and registering it in a [`CloseMethodRegistry`] passed to the validator.
+//!
+//! Plugins can't replace built-in verifiers: the registry refuses plugins for
+//! the close methods the validator already implements, so the semantics of
+//! the consensus close methods can't be changed by the software running the
+//! validation.
+
+use std::collections::BTreeMap;
+
+use bp::dbc::Anchor;
+use bp::seals::txout::CloseMethod;
+use bp::{Outpoint, Tx};
+use commit_verify::mpc;
+
+use crate::{DbcProof, OutputSeal};
+
+/// Implementation of a seal close method.
+pub trait CloseMethodPlugin {
+    /// Close method implemented by the plugin.
+    fn close_method(&self) -> CloseMethod;
+
+    /// Verifies that the witness transaction commits to the multi-protocol
+    /// commitment using the proof provided by the anchor.
+    fn verify_commitment(
+        &self,
+        witness: &Tx,
+        commitment: &mpc::Commitment,
+        proof: &DbcProof,
+    ) -> Result<(), String>;
+
+    /// Checks whether the witness transaction closes the seal, which by
+    /// default means spending the seal outpoint.
+    fn closes_seal(&self, witness: &Tx, seal: &OutputSeal) -> bool {
+        let outpoint = Outpoint::new(seal.txid, seal.vout);
+        witness
+            .inputs
+            .iter()
+            .any(|input| input.prev_output == outpoint)
+    }
+}
+
+/// Errors verifying witness with a close method plugin.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum PluginError {
+    /// anchor is not committed to the bundle. Details: {0}
+    Mpc(mpc::InvalidProof),
+
+    /// witness transaction doesn't commit to the anchor. Details: {0}
+    Commitment(String),
+
+    /// witness transaction doesn't close seal {0}.
+    SealNotClosed(OutputSeal),
+
+    /// close method {0} has a built-in verifier which can't be replaced by a
+    /// plugin.
+    BuiltIn(CloseMethod),
+}
+
+/// Registry of the close method plugins consulted by the validator.
+#[derive(Default)]
+pub struct CloseMethodRegistry<'plugin>(BTreeMap<CloseMethod, &'plugin dyn CloseMethodPlugin>);
+
+impl<'plugin> CloseMethodRegistry<'plugin> {
+    pub fn new() -> Self { Self::default() }
+
+    /// Detects whether the validator has a built-in verifier for the close
+    /// method.
+    pub fn has_builtin(method: CloseMethod) -> bool {
+        matches!(method, CloseMethod::TapretFirst | CloseMethod::OpretFirst)
+    }
+
+    /// Registers plugin for its close method, returning the plugin previously
+    /// registered for the same method.
+    ///
+    /// # Errors
+    ///
+    /// If the close method has a built-in verifier.
+    pub fn register(
+        &mut self,
+        plugin: &'plugin dyn CloseMethodPlugin,
+    ) -> Result<Option<&'plugin dyn CloseMethodPlugin>, PluginError> {
+        let method = plugin.close_method();
+        if Self::has_builtin(method) {
+            return Err(PluginError::BuiltIn(method));
+        }
+        Ok(self.0.insert(method, plugin))
+    }
+
+    /// Returns plugin registered for the close method, if any.
+    pub fn plugin(&self, method: CloseMethod) -> Option<&'plugin dyn CloseMethodPlugin> {
+        self.0.get(&method).copied()
+    }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+}
+
+/// Verifies that the anchor commits to the message under the protocol and
+/// that the witness closes all the seals, using the plugin.
+pub fn verify_with_plugin<'seal>(
+    plugin: &dyn CloseMethodPlugin,
+    anchor: &Anchor<mpc::MerkleProof, DbcProof>,
+    protocol_id: mpc::ProtocolId,
+    message: mpc::Message,
+    witness: &Tx,
+    seals: impl IntoIterator<Item = &'seal OutputSeal>,
+) -> Result<(), PluginError> {
+    let commitment = anchor
+        .convolve(protocol_id, message)
+        .map_err(PluginError::Mpc)?;
+    plugin
+        .verify_commitment(witness, &commitment, &anchor.dbc_proof)
+        .map_err(PluginError::Commitment)?;
+    if let Some(seal) = seals
+        .into_iter()
+        .find(|seal| !plugin.closes_seal(witness, seal))
+    {
+        return Err(PluginError::SealNotClosed(*seal));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use bp::dbc::Proof;
+
+    use super::*;
+    use crate::validation::fixtures::TestChain;
+    use crate::validation::ResolveWitness;
+    use crate::{BundleId, ContractId};
+
+    struct OpretPlugin;
+
+    impl CloseMethodPlugin for OpretPlugin {
+        fn close_method(&self) -> CloseMethod { CloseMethod::OpretFirst }
+
+        fn verify_commitment(
+            &self,
+            witness: &Tx,
+            commitment: &mpc::Commitment,
+            proof: &DbcProof,
+        ) -> Result<(), String> {
+            proof
+                .verify(commitment, witness)
+                .map_err(|err| err.to_string())
+        }
+    }
+
+    #[test]
+    fn builtin_not_replaced() {
+        let mut registry = CloseMethodRegistry::new();
+        assert!(CloseMethodRegistry::has_builtin(CloseMethod::TapretFirst));
+        assert_eq!(
+            registry.register(&OpretPlugin).err(),
+            Some(PluginError::BuiltIn(CloseMethod::OpretFirst))
+        );
+        assert!(registry.is_empty());
+        assert!(registry.plugin(CloseMethod::OpretFirst).is_none());
+    }
+
+    #[test]
+    fn plugin_verification() {
+        let mut chain = TestChain::regtest();
+        let contract_id = ContractId::from([0x11; 32]);
+        let bundle_id = BundleId::from([0x22; 32]);
+        let funding = chain.outpoint();
+        let (witness_id, anchor) = chain.commit(contract_id, bundle_id, [funding], 1);
+        let tx = chain.resolve_pub_witness(witness_id).unwrap();
+        let tx = tx.as_reduced_unsafe();
+        let message = mpc::Message::from(bundle_id);
+
+        let spent = OutputSeal::new(CloseMethod::OpretFirst, funding);
+        assert_eq!(
+            verify_with_plugin(&OpretPlugin, &anchor, contract_id.into(), message, tx, [&spent]),
+            Ok(())
+        );
+
+        let unspent = OutputSeal::new(CloseMethod::OpretFirst, chain.outpoint());
+        assert_eq!(
+            verify_with_plugin(&OpretPlugin, &anchor, contract_id.into(), message, tx, [&unspent]),
+            Err(PluginError::SealNotClosed(unspent))
+        );
+
+        let other = mpc::Message::from(BundleId::from([0x33; 32]));
+        assert!(matches!(
+            verify_with_plugin(&OpretPlugin, &anchor, contract_id.into(), other, tx, [&spent]),
+            Err(PluginError::Mpc(_) | PluginError::Commitment(_))
+        ));
+    }
+}
@@ -0,0 +1,157 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Strictness of the validation configured by the caller.
+//!
+//! Validator reports issues which do not make a consignment invalid under
+//! the consensus rules as warnings, leaving it to the software to decide
+//! whether such consignments are acceptable. Different software has
+//! different requirements: an exchange may refuse consignments which a wallet
+//! would accept. [`ValidationConfig`] selects the [`ValidationMode`] and
+//! adjusts the severity of the warnings and information messages, identified
+//! by their stable codes (see [`super::Warning::code`] and [`Info::code`]),
+//! after the validation is complete.
+//!
+//! The configuration never affects the consensus validity: warnings rejected
+//! by the configuration make the consignment locally rejected (see
+//! [`Validity::LocallyRejected`](super::Validity::LocallyRejected)), like the
+//! local policies do.
+
+use std::collections::BTreeSet;
+use std::mem;
+
+use super::{Info, Status, ValidationMode, WARNING_CODES};
+
+/// Configuration of the validation strictness.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ValidationConfig {
+    /// Strictness of the validation: whether violations of the standardness
+    /// rules are reported as warnings or as local policy violations.
+    pub mode: ValidationMode,
+    /// Codes of the warnings which make the consignment locally rejected.
+    pub rejected_warnings: BTreeSet<u16>,
+    /// Codes of the warnings reported as information messages.
+    pub demoted_warnings: BTreeSet<u16>,
+    /// Codes of the information messages which are not reported.
+    pub suppressed_info: BTreeSet<u16>,
+}
+
+impl ValidationConfig {
+    /// Configuration enforcing the standardness rules and locally rejecting
+    /// consignments with any warnings.
+    pub fn strict() -> Self {
+        ValidationConfig {
+            mode: ValidationMode::Standard,
+            rejected_warnings: WARNING_CODES.collect(),
+            ..default!()
+        }
+    }
+
+    /// Locally rejects consignments with the warning of the given code.
+    pub fn reject(mut self, code: u16) -> Self {
+        self.demoted_warnings.remove(&code);
+        self.rejected_warnings.insert(code);
+        self
+    }
+
+    /// Reports the warning with the given code as an information message.
+    pub fn demote(mut self, code: u16) -> Self {
+        self.rejected_warnings.remove(&code);
+        self.demoted_warnings.insert(code);
+        self
+    }
+
+    /// Doesn't report information messages with the given code.
+    pub fn suppress(mut self, code: u16) -> Self {
+        self.suppressed_info.insert(code);
+        self
+    }
+
+    /// Adjusts the severity of the status entries according to the
+    /// configuration.
+    pub fn apply(&self, status: &mut Status) {
+        let mut info = Vec::with_capacity(status.info.len());
+        for warning in mem::take(&mut status.warnings) {
+            if self.rejected_warnings.contains(&warning.code()) {
+                status.rejected_warnings.push(warning);
+            } else if self.demoted_warnings.contains(&warning.code()) {
+                info.push(Info::WarningDemoted(warning));
+            } else {
+                status.warnings.push(warning);
+            }
+        }
+        status
+            .info
+            .retain(|item| !self.suppressed_info.contains(&item.code()));
+        status.info.extend(info);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::validation::{Validity, Warning, INFO_CODES};
+    use crate::ContractId;
+
+    #[test]
+    fn reject_demote() {
+        let unsalted = Warning::ContractIdUnsalted(ContractId::from([0xA5; 32]));
+        let mut status = Status::new();
+        status.add_warning(unsalted.clone());
+        status.add_warning(Warning::Custom(s!("custom")));
+        status.add_info(Info::Custom(s!("custom")));
+
+        let mut strict = status.clone();
+        ValidationConfig::default()
+            .reject(unsalted.code())
+            .suppress(INFO_CODES.start)
+            .apply(&mut strict);
+        assert!(strict.failures.is_empty());
+        assert_eq!(strict.rejected_warnings, vec![unsalted.clone()]);
+        assert_eq!(strict.warnings, vec![Warning::Custom(s!("custom"))]);
+        assert!(strict.info.is_empty());
+        assert_eq!(strict.consensus_validity(), Validity::Valid);
+        assert_eq!(strict.validity(), Validity::LocallyRejected);
+
+        let mut permissive = status;
+        ValidationConfig::default()
+            .demote(unsalted.code())
+            .apply(&mut permissive);
+        assert!(permissive.failures.is_empty());
+        assert_eq!(permissive.info.last(), Some(&Info::WarningDemoted(unsalted)));
+        assert_eq!(permissive.validity(), Validity::Valid);
+    }
+
+    #[test]
+    fn strict() {
+        let config = ValidationConfig::strict();
+        assert_eq!(config.mode, ValidationMode::Standard);
+        assert!(config.rejected_warnings.contains(&WARNING_CODES.start));
+        assert!(config.rejected_warnings.contains(&(WARNING_CODES.end - 1)));
+        assert!(!config.rejected_warnings.contains(&INFO_CODES.start));
+    }
+}
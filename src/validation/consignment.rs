@@ -25,20 +25,94 @@
 //! single-use-seal data.
 
 use std::collections::BTreeMap;
+use std::iter;
 
 use aluvm::library::{Lib, LibId};
 use amplify::confinement::Confined;
+use bp::BlockHash;
 use strict_types::TypeSystem;
 
+use super::PrivacyReport;
 use crate::{
-    BundleId, EAnchor, Genesis, OpId, OpRef, Operation, Schema, SecretSeal, TransitionBundle,
-    XChain, XWitnessId,
+    AssignAttach, AssignData, AssignFungible, AssignRights, BundleId, EAnchor, Genesis, GraphSeal,
+    IssuerSignature, OpId, OpRef, Operation, Opout, Schema, SchemaId, SecretSeal, TransitionBundle,
+    TypedAssigns, WitnessSubstitution, XChain, XWitnessId, XWitnessTx, LIB_NAME_RGB,
 };
 
 pub const CONSIGNMENT_MAX_LIBS: usize = 1024;
 
 pub type Scripts = Confined<BTreeMap<LibId, Lib>, 0, CONSIGNMENT_MAX_LIBS>;
 
+/// Untrusted hint on the position of a witness transaction in the blockchain,
+/// which can be provided by a consignment to reduce the number of resolver
+/// queries.
+///
+/// Consignments carry hints as a map from the witness id to the hint.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct WitnessHint {
+    pub height: u32,
+    pub block_hash: Option<BlockHash>,
+}
+
+/// Assignment spent by a transition input, which a consignment may provide
+/// together with the transition (see [`ConsignmentApi::spent_state`]).
+///
+/// The assignment is expected to be revealed, such that validators are able
+/// to check conservation rules without looking up the ancestor operation.
+/// Validators verify that it matches the ancestor assignment commitment when
+/// walking the operation graph. Consignments carry spent states as a map
+/// from the spent [`Opout`] to the assignment.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB, tags = custom, dumb = Self::Declarative(strict_dumb!()))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[allow(clippy::large_enum_variant)]
+pub enum SpentState {
+    #[strict_type(tag = 0x00)]
+    Declarative(AssignRights<GraphSeal>),
+    #[strict_type(tag = 0x01)]
+    Fungible(AssignFungible<GraphSeal>),
+    #[strict_type(tag = 0x02)]
+    Structured(AssignData<GraphSeal>),
+    #[strict_type(tag = 0xFF)]
+    Attachment(AssignAttach<GraphSeal>),
+}
+
+impl SpentState {
+    /// Takes the assignment with the given number from the ancestor
+    /// assignments, if present.
+    pub fn from_assigns(assigns: &TypedAssigns<GraphSeal>, no: u16) -> Option<Self> {
+        let no = no as usize;
+        Some(match assigns {
+            TypedAssigns::Declarative(assigns) => Self::Declarative(assigns.get(no)?.clone()),
+            TypedAssigns::Fungible(assigns) => Self::Fungible(assigns.get(no)?.clone()),
+            TypedAssigns::Structured(assigns) => Self::Structured(assigns.get(no)?.clone()),
+            TypedAssigns::Attachment(assigns) => Self::Attachment(assigns.get(no)?.clone()),
+        })
+    }
+
+    /// Detects whether both seal and state of the assignment are revealed.
+    pub fn is_revealed(&self) -> bool {
+        match self {
+            SpentState::Declarative(assign) => assign.to_revealed().is_some(),
+            SpentState::Fungible(assign) => assign.to_revealed().is_some(),
+            SpentState::Structured(assign) => assign.to_revealed().is_some(),
+            SpentState::Attachment(assign) => assign.to_revealed().is_some(),
+        }
+    }
+}
+
 pub struct CheckedConsignment<'consignment, C: ConsignmentApi>(&'consignment C);
 
 impl<'consignment, C: ConsignmentApi> CheckedConsignment<'consignment, C> {
@@ -52,8 +126,10 @@ impl<'consignment, C: ConsignmentApi> ConsignmentApi for CheckedConsignment<'con
 
     fn scripts(&self) -> &Scripts { self.0.scripts() }
 
-    fn operation(&self, opid: OpId) -> Option<OpRef> {
-        self.0.operation(opid).filter(|op| op.id() == opid)
+    fn operation(&self, opid: OpId) -> Option<OpRef<'_>> {
+        self.0
+            .operation(opid)
+            .filter(|op| op.verify_id(opid).is_some())
     }
 
     fn genesis(&self) -> &Genesis { self.0.genesis() }
@@ -64,6 +140,14 @@ impl<'consignment, C: ConsignmentApi> ConsignmentApi for CheckedConsignment<'con
 
     fn bundle_ids<'iter>(&self) -> impl Iterator<Item = BundleId> + 'iter { self.0.bundle_ids() }
 
+    fn extension_ids<'iter>(&self) -> impl Iterator<Item = OpId> + 'iter {
+        self.0
+            .extension_ids()
+            .filter(|opid| matches!(self.operation(*opid), Some(OpRef::Extension(_))))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
     fn bundle(&self, bundle_id: BundleId) -> Option<&TransitionBundle> {
         self.0
             .bundle(bundle_id)
@@ -75,6 +159,36 @@ impl<'consignment, C: ConsignmentApi> ConsignmentApi for CheckedConsignment<'con
     }
 
     fn op_witness_id(&self, opid: OpId) -> Option<XWitnessId> { self.0.op_witness_id(opid) }
+
+    fn witness_substitution(&self, bundle_id: BundleId) -> Option<WitnessSubstitution> {
+        self.0
+            .witness_substitution(bundle_id)
+            .filter(|substitution| substitution.bundle_id == bundle_id)
+    }
+
+    fn replaced_witness(&self, witness_id: XWitnessId) -> Option<XWitnessTx> {
+        self.0
+            .replaced_witness(witness_id)
+            .filter(|tx| tx.witness_id() == witness_id)
+    }
+
+    fn alt_anchors(&self, bundle_id: BundleId) -> Vec<(XWitnessId, &EAnchor)> {
+        self.0.alt_anchors(bundle_id)
+    }
+
+    fn witness_hint(&self, witness_id: XWitnessId) -> Option<WitnessHint> {
+        self.0.witness_hint(witness_id)
+    }
+
+    fn issuer_signature(&self) -> Option<IssuerSignature> { self.0.issuer_signature() }
+
+    fn schema_successor(&self, schema_id: SchemaId) -> Option<&Schema> {
+        self.0
+            .schema_successor(schema_id)
+            .filter(|schema| schema.schema_id() == schema_id)
+    }
+
+    fn spent_state(&self, opout: Opout) -> Option<SpentState> { self.0.spent_state(opout) }
 }
 
 /// Trait defining common data access API for all storage-related RGB structures
@@ -97,7 +211,7 @@ pub trait ConsignmentApi {
 
     /// Retrieves reference to an operation (genesis, state transition or state
     /// extension) matching the provided id, or `None` otherwise
-    fn operation(&self, opid: OpId) -> Option<OpRef>;
+    fn operation(&self, opid: OpId) -> Option<OpRef<'_>>;
 
     /// Contract genesis.
     fn genesis(&self) -> &Genesis;
@@ -116,6 +230,14 @@ pub trait ConsignmentApi {
     /// Returns iterator over all bundle ids present in the consignment.
     fn bundle_ids<'iter>(&self) -> impl Iterator<Item = BundleId> + 'iter;
 
+    /// Returns iterator over ids of all state extensions present in the
+    /// consignment.
+    ///
+    /// Extensions are validated only as a part of the history of the
+    /// consignment terminals; the iterator allows tools to discover
+    /// extensions which are not (yet) spent or redeemed by other operations.
+    fn extension_ids<'iter>(&self) -> impl Iterator<Item = OpId> + 'iter { iter::empty() }
+
     /// Returns reference to a bundle given a bundle id.
     fn bundle(&self, bundle_id: BundleId) -> Option<&TransitionBundle>;
 
@@ -124,4 +246,60 @@ pub trait ConsignmentApi {
 
     /// Returns witness id for a given operation.
     fn op_witness_id(&self, opid: OpId) -> Option<XWitnessId>;
+
+    /// Returns proof that the bundle was re-anchored from a replaced witness
+    /// transaction to the one returned by [`ConsignmentApi::anchor`], if the
+    /// bundle was re-anchored.
+    fn witness_substitution(&self, _bundle_id: BundleId) -> Option<WitnessSubstitution> { None }
+
+    /// Returns the witness transaction replaced by a [`WitnessSubstitution`]
+    /// or by one of the alternative witnesses (see
+    /// [`ConsignmentApi::alt_anchors`]), if the consignment provides it.
+    ///
+    /// A replaced transaction usually can't be resolved once its replacement
+    /// is broadcast, so the consignment should carry it as a part of the
+    /// substitution proof. The validator falls back to the resolver only if
+    /// the transaction is not provided.
+    fn replaced_witness(&self, _witness_id: XWitnessId) -> Option<XWitnessTx> { None }
+
+    /// Returns alternative anchors for the bundle, which differ from the one
+    /// returned by [`ConsignmentApi::anchor`] only in the witness transaction.
+    ///
+    /// Alternative witnesses must replace each other (like RBF transactions on
+    /// bitcoin), such that at most one of them can be mined. The validator
+    /// uses the mined witness, if any, marking the rest as void.
+    fn alt_anchors(&self, _bundle_id: BundleId) -> Vec<(XWitnessId, &EAnchor)> { vec![] }
+
+    /// Returns hint on the position of the witness transaction in the
+    /// blockchain, if the consignment provides one.
+    ///
+    /// Hints are never trusted: the validator passes them to the resolver,
+    /// which must verify the witness position independently.
+    fn witness_hint(&self, _witness_id: XWitnessId) -> Option<WitnessHint> { None }
+
+    /// Returns signature of the contract issuer over the genesis, if the
+    /// consignment provides one.
+    fn issuer_signature(&self) -> Option<IssuerSignature> { None }
+
+    /// Returns successor schema the contract is upgraded to by one of the
+    /// schema upgrades declared by the genesis (see [`crate::SchemaUpgrade`]),
+    /// if the consignment provides it.
+    fn schema_successor(&self, _schema_id: SchemaId) -> Option<&Schema> { None }
+
+    /// Returns assignment spent by the transition input, if the consignment
+    /// provides it together with the transition.
+    ///
+    /// Revealed spent states are used to validate the transition state without
+    /// looking up the ancestor operation. They are never trusted: the
+    /// validator checks that they match the ancestor assignments when walking
+    /// the operation graph, reporting [`super::Failure::SpentStateMismatch`]
+    /// otherwise.
+    fn spent_state(&self, _opout: Opout) -> Option<SpentState> { None }
+
+    /// Reports which data are revealed and concealed by the consignment, and
+    /// to whom they are visible (see [`PrivacyReport`]).
+    fn privacy_report(&self) -> PrivacyReport
+    where Self: Sized {
+        PrivacyReport::with(self)
+    }
 }
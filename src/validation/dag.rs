@@ -0,0 +1,366 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Graph of contract operations contained in a consignment.
+//!
+//! Operations form a directed acyclic graph, where edges go from the
+//! operations defining state to the operations spending that state or
+//! redeeming their valencies. [`DagWalker`] walks the graph from the provided
+//! operations up to genesis; the validator uses it to walk the history of the
+//! consignment terminals. [`ConsignmentDag`] is constructed with the same
+//! walker, starting from all the consignment bundles and state extensions,
+//! and provides queries for tools which need to inspect contract history
+//! (explorers, visualizers and wallets).
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use super::ConsignmentApi;
+use crate::{OpId, OpRef, Operation, Opout, ValencyType};
+
+/// Reference from an operation to its parent operation.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ParentRef {
+    /// Transition input spending state assigned by the parent.
+    Input(Opout),
+    /// Valency of the parent redeemed by a state extension.
+    Valency(ValencyType, OpId),
+}
+
+impl ParentRef {
+    /// Returns id of the parent operation.
+    pub fn opid(&self) -> OpId {
+        match self {
+            ParentRef::Input(opout) => opout.op,
+            ParentRef::Valency(_, opid) => *opid,
+        }
+    }
+
+    /// Returns references from the operation to its parents.
+    pub fn all(op: &OpRef) -> Vec<ParentRef> {
+        match op {
+            OpRef::Genesis(_) => vec![],
+            OpRef::Transition(transition) => transition
+                .inputs
+                .iter()
+                .map(|input| ParentRef::Input(input.prev_out))
+                .collect(),
+            OpRef::Extension(extension) => extension
+                .redeemed
+                .iter()
+                .map(|(valency, opid)| ParentRef::Valency(*valency, *opid))
+                .collect(),
+        }
+    }
+}
+
+/// Breadth-first walker over the operations of a consignment, going from the
+/// provided operations up to genesis.
+///
+/// The walker visits each operation once. Parents of a visited operation are
+/// walked only if [`DagWalker::descend`] is called for it, allowing callers to
+/// stop at operations which histories are not of interest (or are already
+/// known).
+pub struct DagWalker<'consignment, C: ConsignmentApi> {
+    consignment: &'consignment C,
+    queue: VecDeque<OpId>,
+    visited: BTreeSet<OpId>,
+}
+
+impl<'consignment, C: ConsignmentApi> DagWalker<'consignment, C> {
+    /// Constructs walker starting from the provided operations.
+    pub fn new(consignment: &'consignment C, from: impl IntoIterator<Item = OpId>) -> Self {
+        DagWalker {
+            consignment,
+            queue: from.into_iter().collect(),
+            visited: empty!(),
+        }
+    }
+
+    /// Returns the next operation which was not visited yet, together with
+    /// the operation data, if the consignment contains it.
+    pub fn next_op(&mut self) -> Option<(OpId, Option<OpRef<'consignment>>)> {
+        while let Some(opid) = self.queue.pop_front() {
+            if self.visited.insert(opid) {
+                return Some((opid, self.consignment.operation(opid)));
+            }
+        }
+        None
+    }
+
+    /// Queues parents of the operation for walking, returning references to
+    /// them together with the parent operations, if the consignment contains
+    /// them. Parents absent from the consignment are not walked.
+    pub fn descend(&mut self, op: &OpRef) -> Vec<(ParentRef, Option<OpRef<'consignment>>)> {
+        ParentRef::all(op)
+            .into_iter()
+            .map(|parent| {
+                let prev_op = self.consignment.operation(parent.opid());
+                if prev_op.is_some() {
+                    self.queue.push_back(parent.opid());
+                }
+                (parent, prev_op)
+            })
+            .collect()
+    }
+}
+
+/// Directed acyclic graph of the operations contained in a consignment.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ConsignmentDag {
+    genesis_id: OpId,
+    parents: BTreeMap<OpId, BTreeSet<OpId>>,
+    children: BTreeMap<OpId, BTreeSet<OpId>>,
+    missing: BTreeSet<OpId>,
+}
+
+impl ConsignmentDag {
+    fn new(genesis_id: OpId) -> Self {
+        ConsignmentDag {
+            genesis_id,
+            parents: empty!(),
+            children: empty!(),
+            missing: empty!(),
+        }
+    }
+
+    fn insert(&mut self, opid: OpId) {
+        self.parents.entry(opid).or_default();
+        self.children.entry(opid).or_default();
+    }
+
+    fn connect(&mut self, parent: OpId, child: OpId) {
+        self.insert(parent);
+        self.insert(child);
+        self.parents.entry(child).or_default().insert(parent);
+        self.children.entry(parent).or_default().insert(child);
+    }
+
+    /// Constructs graph of the contract genesis, the consignment state
+    /// extensions and bundle transitions, and all operations they depend on.
+    ///
+    /// Operations referenced by other operations but absent from the
+    /// consignment are not added to the graph and are reported by
+    /// [`ConsignmentDag::missing`].
+    pub fn with(consignment: &impl ConsignmentApi) -> Self {
+        let genesis_id = consignment.genesis().id();
+        let mut dag = ConsignmentDag::new(genesis_id);
+
+        let mut from = vec![genesis_id];
+        from.extend(consignment.extension_ids());
+        for bundle_id in consignment.bundle_ids() {
+            let Some(bundle) = consignment.bundle(bundle_id) else {
+                continue;
+            };
+            from.extend(bundle.known_transitions.keys());
+        }
+
+        let mut walker = DagWalker::new(consignment, from);
+        while let Some((opid, op)) = walker.next_op() {
+            let Some(op) = op else {
+                dag.missing.insert(opid);
+                continue;
+            };
+            dag.insert(opid);
+            for (parent, prev_op) in walker.descend(&op) {
+                match prev_op {
+                    Some(_) => dag.connect(parent.opid(), opid),
+                    None => {
+                        dag.missing.insert(parent.opid());
+                    }
+                }
+            }
+        }
+        dag
+    }
+
+    /// Returns id of the contract genesis.
+    pub fn genesis_id(&self) -> OpId { self.genesis_id }
+
+    /// Returns number of operations in the graph.
+    pub fn len(&self) -> usize { self.parents.len() }
+
+    /// Detects whether the graph has no operations.
+    pub fn is_empty(&self) -> bool { self.parents.is_empty() }
+
+    /// Checks whether the operation is a part of the graph.
+    pub fn contains(&self, opid: OpId) -> bool { self.parents.contains_key(&opid) }
+
+    /// Returns ids of all operations in the graph.
+    pub fn operations(&self) -> impl Iterator<Item = OpId> + '_ { self.parents.keys().copied() }
+
+    /// Returns ids of the operations referenced by the consignment operations,
+    /// but absent from the consignment.
+    pub fn missing(&self) -> &BTreeSet<OpId> { &self.missing }
+
+    /// Returns operations whose state or valencies are used by the operation.
+    pub fn parents(&self, opid: OpId) -> impl Iterator<Item = OpId> + '_ {
+        self.parents.get(&opid).into_iter().flatten().copied()
+    }
+
+    /// Returns operations using state or valencies of the operation.
+    pub fn children(&self, opid: OpId) -> impl Iterator<Item = OpId> + '_ {
+        self.children.get(&opid).into_iter().flatten().copied()
+    }
+
+    /// Returns all operations the operation depends on, up to genesis.
+    pub fn ancestors(&self, opid: OpId) -> BTreeSet<OpId> { self.walk(opid, &self.parents) }
+
+    /// Returns all operations depending on the operation.
+    pub fn descendants(&self, opid: OpId) -> BTreeSet<OpId> { self.walk(opid, &self.children) }
+
+    fn walk(&self, opid: OpId, edges: &BTreeMap<OpId, BTreeSet<OpId>>) -> BTreeSet<OpId> {
+        let mut found = BTreeSet::new();
+        let mut queue = VecDeque::from([opid]);
+        while let Some(opid) = queue.pop_front() {
+            for next in edges.get(&opid).into_iter().flatten() {
+                if found.insert(*next) {
+                    queue.push_back(*next);
+                }
+            }
+        }
+        found
+    }
+
+    /// Returns operations in topological order, such that each operation
+    /// follows all of its ancestors. Operations which do not depend on each
+    /// other are ordered by their ids, making the order deterministic.
+    pub fn topological(&self) -> Vec<OpId> {
+        let mut pending = self
+            .parents
+            .iter()
+            .map(|(opid, parents)| (*opid, parents.len()))
+            .collect::<BTreeMap<_, _>>();
+        let mut ready = pending
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(opid, _)| *opid)
+            .collect::<BTreeSet<_>>();
+        let mut order = Vec::with_capacity(pending.len());
+        while let Some(opid) = ready.pop_first() {
+            order.push(opid);
+            for child in self.children(opid) {
+                let count = pending.get_mut(&child).expect("graph is consistent");
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(child);
+                }
+            }
+        }
+        order
+    }
+
+    /// Extracts subgraph consisting of the provided operations.
+    pub fn subgraph(&self, opids: &BTreeSet<OpId>) -> Self {
+        let mut dag = ConsignmentDag::new(self.genesis_id);
+        for opid in opids.iter().filter(|opid| self.contains(**opid)) {
+            dag.insert(*opid);
+            for parent in self.parents(*opid).filter(|parent| opids.contains(parent)) {
+                dag.connect(parent, *opid);
+            }
+        }
+        dag
+    }
+
+    /// Extracts history of an allocation: subgraph of the operation defining
+    /// the allocation and all of its ancestors.
+    pub fn allocation_history(&self, opout: Opout) -> Self {
+        let mut opids = self.ancestors(opout.op);
+        opids.insert(opout.op);
+        self.subgraph(&opids)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use strict_encoding::StrictDumb;
+    use strict_types::TypeSystem;
+
+    use super::*;
+    use crate::validation::fixtures::TestConsignment;
+    use crate::{AssignmentType, Extension, Genesis, Schema};
+
+    #[test]
+    fn traversal() {
+        let id = |no: u8| OpId::from([no; 32]);
+        let mut dag = ConsignmentDag::new(id(0));
+        dag.connect(id(0), id(1));
+        dag.connect(id(0), id(2));
+        dag.connect(id(1), id(3));
+        dag.connect(id(2), id(3));
+        dag.connect(id(2), id(4));
+
+        assert_eq!(dag.topological(), vec![id(0), id(1), id(2), id(3), id(4)]);
+        assert_eq!(dag.ancestors(id(3)), bset![id(0), id(1), id(2)]);
+        assert_eq!(dag.descendants(id(2)), bset![id(3), id(4)]);
+
+        let history = dag.allocation_history(Opout::new(id(4), AssignmentType::with(1), 0));
+        assert_eq!(history.topological(), vec![id(0), id(2), id(4)]);
+        assert_eq!(history.children(id(2)).collect::<Vec<_>>(), vec![id(4)]);
+
+        assert!(dag.subgraph(&bset![id(5)]).is_empty());
+        assert!(!dag.subgraph(&bset![id(0)]).is_empty());
+    }
+
+    #[test]
+    fn extensions() {
+        let genesis = Genesis::strict_dumb();
+        let genesis_id = genesis.id();
+        let mut consignment =
+            TestConsignment::new(Schema::strict_dumb(), TypeSystem::default(), genesis);
+        let dag = ConsignmentDag::with(&consignment);
+        assert_eq!(dag.operations().collect::<Vec<_>>(), vec![genesis_id]);
+        assert!(!dag.is_empty());
+
+        // Extensions which are not spent or redeemed by other operations are a part of
+        // the graph as well
+        let valency = ValencyType::with(1);
+        let mut extension = Extension::strict_dumb();
+        extension.redeemed.insert(valency, genesis_id).unwrap();
+        let opid = extension.id();
+        let mut orphan = Extension::strict_dumb();
+        orphan
+            .redeemed
+            .insert(valency, OpId::from([1; 32]))
+            .unwrap();
+        let orphan_id = orphan.id();
+        consignment.extensions.insert(opid, extension);
+        consignment.extensions.insert(orphan_id, orphan);
+
+        let dag = ConsignmentDag::with(&consignment);
+        assert_eq!(dag.len(), 3);
+        assert_eq!(dag.parents(opid).collect::<Vec<_>>(), vec![genesis_id]);
+        assert_eq!(dag.descendants(genesis_id), bset![opid]);
+        assert!(dag.contains(orphan_id));
+        assert_eq!(dag.missing(), &bset![OpId::from([1; 32])]);
+
+        let mut walker = DagWalker::new(&consignment, [orphan_id]);
+        let (walked, Some(op)) = walker.next_op().unwrap() else {
+            panic!("extension is present");
+        };
+        assert_eq!(walked, orphan_id);
+        assert_eq!(walker.descend(&op), vec![(
+            ParentRef::Valency(valency, OpId::from([1; 32])),
+            None
+        )]);
+        assert!(walker.next_op().is_none());
+    }
+}
@@ -0,0 +1,228 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dry-run validation of a single operation against the contract state.
+//!
+//! Wallets construct state transitions and extensions against the contract
+//! state they keep, and need to know whether the operation is valid before
+//! anchoring it and broadcasting the witness transaction. [`dry_run`]
+//! validates the operation against the schema of the contract state, taking
+//! the spent state from the allocations of the contract state, and returns
+//! the same failures the validator would report for the operation.
+//!
+//! The operation is validated as if its witness were not mined yet, thus
+//! scripts depending on the witness position fail. Lock scripts of the spent
+//! assignments are not evaluated, since the contract state doesn't keep the
+//! metadata of the operations which have defined the assignments; for the
+//! schemata using assignment locks this is reported with
+//! [`Info::LockScriptsUnchecked`].
+
+use std::collections::BTreeMap;
+
+use amplify::confinement::Confined;
+use bp::seals::txout::TxPtr;
+use strict_types::TypeSystem;
+
+use super::logic::{push_spent_state, OpContext};
+use super::{Failure, Info, Scripts, SpentState, Status};
+use crate::{
+    Assign, ContractState, ExposedState, Genesis, GraphSeal, KnownState, OpRef, Operation, Opout,
+    OutputAssignment, WitnessOrd, XGraphSeal, XOutputSeal,
+};
+
+pub(super) fn graph_seal(seal: XOutputSeal) -> XGraphSeal {
+    seal.map(|seal| GraphSeal {
+        method: seal.method,
+        txid: TxPtr::Txid(seal.txid),
+        vout: seal.vout,
+        blinding: 0,
+    })
+}
+
+fn find_revealed<'state, State: KnownState + ExposedState + 'state>(
+    mut allocations: impl Iterator<Item = &'state OutputAssignment<State>>,
+    opout: Opout,
+) -> Option<Assign<State, GraphSeal>> {
+    allocations
+        .find(|a| a.opout == opout)
+        .map(|a| Assign::revealed(graph_seal(a.seal), a.state.clone()))
+}
+
+/// Takes allocation of the contract state as the assignment spent by a
+/// transition input. The contract state keeps spent allocations, thus the
+/// caller must check that the allocation is not spent yet.
+fn spent_state(state: &ContractState, opout: Opout) -> Option<SpentState> {
+    find_revealed(state.rights().iter(), opout)
+        .map(SpentState::Declarative)
+        .or_else(|| find_revealed(state.fungibles().iter(), opout).map(SpentState::Fungible))
+        .or_else(|| find_revealed(state.data().iter(), opout).map(SpentState::Structured))
+        .or_else(|| find_revealed(state.attach().iter(), opout).map(SpentState::Attachment))
+}
+
+/// Validates a state transition or extension against the contract state and
+/// its schema, without a consignment.
+///
+/// The `genesis`, type system and scripts must be the ones used to validate
+/// the contract. Returns validation status with the failures the validator
+/// would report for the operation; seals and anchoring of the operation are
+/// not validated.
+pub fn dry_run(
+    state: &ContractState,
+    genesis: &Genesis,
+    types: &TypeSystem,
+    scripts: &Scripts,
+    op: OpRef,
+) -> Status {
+    let opid = op.id();
+    let mut status = Status::new();
+    if genesis.contract_id() != state.contract_id() {
+        status.add_failure(Failure::ContractMismatch(genesis.id(), genesis.contract_id()));
+        return status;
+    }
+    if op.contract_id() != state.contract_id() {
+        status.add_failure(Failure::ContractMismatch(opid, op.contract_id()));
+        return status;
+    }
+
+    let mut prev_status = Status::new();
+    let mut assignments = BTreeMap::new();
+    for input in &op.inputs() {
+        let opout = input.prev_out;
+        match (state.spent_by(opout), spent_state(state, opout)) {
+            (Some(spender), _) => {
+                prev_status.add_failure(Failure::InputSpent(opid, opout, spender.opid));
+            }
+            (None, Some(spent)) => push_spent_state(&mut assignments, opout.ty, spent),
+            (None, None) => {
+                prev_status.add_failure(Failure::NoPrevOut(opid, opout));
+            }
+        }
+    }
+    let prev_state = Confined::try_from(assignments)
+        .expect("collections is assembled from another collection with the same size requirements")
+        .into();
+
+    let context = OpContext {
+        contract_id: state.contract_id(),
+        asset_tags: &genesis.asset_tags,
+        types,
+        scripts,
+    };
+    let witness = match op {
+        OpRef::Transition(_) => Some(WitnessOrd::OffChain),
+        OpRef::Genesis(_) | OpRef::Extension(_) => None,
+    };
+    status +=
+        state
+            .schema
+            .validate_state_with(context, op, (prev_state, prev_status), None, witness);
+    if state.schema.reserves_types() && !op.inputs().is_empty() {
+        status.add_info(Info::LockScriptsUnchecked(opid));
+    }
+    status
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::Wrapper;
+    use bp::Txid;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::validation::fixtures::TestChain;
+    use crate::{
+        AssignmentType, Assignments, ContractHistory, Input, Inputs, Occurrences, OwnedStateSchema,
+        Schema, SchemaFlags, Transition, TransitionSchema, TransitionType, TypedAssigns, VoidState,
+        WitnessAnchor, WitnessPos, XChain,
+    };
+
+    const TY: AssignmentType = AssignmentType::with(1);
+
+    fn transfer(chain: &mut TestChain, state: &ContractState, prev_out: Opout) -> Transition {
+        let mut transition = Transition::strict_dumb();
+        transition.contract_id = state.contract_id();
+        transition.transition_type = TransitionType::with(1);
+        transition.inputs = Inputs::try_from_inputs([Input::with(prev_out)]).unwrap();
+        transition.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            TY,
+            TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                chain.graph_seal(1),
+                VoidState::default(),
+            )])),
+        )]));
+        transition
+    }
+
+    #[test]
+    fn double_spend() {
+        let mut schema = Schema::strict_dumb();
+        schema.flags = SchemaFlags::RESERVED_TYPES;
+        schema.owned_types = Confined::from_iter_unsafe([(TY, OwnedStateSchema::Declarative)]);
+        schema.transitions =
+            Confined::from_iter_unsafe([(TransitionType::with(1), TransitionSchema {
+                inputs: Confined::from_iter_unsafe([(TY, Occurrences::Once)]),
+                assignments: Confined::from_iter_unsafe([(TY, Occurrences::Once)]),
+                ..default!()
+            })]);
+
+        let mut chain = TestChain::regtest();
+        let mut genesis = Genesis::strict_dumb();
+        genesis.schema_id = schema.schema_id();
+        genesis.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            TY,
+            TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                chain.genesis_seal(),
+                VoidState::default(),
+            )])),
+        )]));
+        let history = ContractHistory::with(genesis.schema_id, genesis.contract_id(), &genesis);
+        let mut state = ContractState { schema, history };
+        let types = TypeSystem::default();
+        let scripts = Scripts::default();
+        let allocation = Opout::new(genesis.id(), TY, 0);
+
+        let first = transfer(&mut chain, &state, allocation);
+        let status = dry_run(&state, &genesis, &types, &scripts, OpRef::Transition(&first));
+        assert!(status.failures.is_empty(), "{status}");
+        assert_eq!(status.info, vec![Info::LockScriptsUnchecked(first.id())]);
+
+        let unknown = Opout::new(first.id(), TY, 0);
+        let orphan = transfer(&mut chain, &state, unknown);
+        let status = dry_run(&state, &genesis, &types, &scripts, OpRef::Transition(&orphan));
+        assert!(status
+            .failures
+            .contains(&Failure::NoPrevOut(orphan.id(), unknown)));
+
+        let anchor = WitnessAnchor {
+            witness_ord: WitnessOrd::OnChain(WitnessPos::new(100, 1_700_000_000).unwrap()),
+            witness_id: XChain::Bitcoin(Txid::from([0x11; 32])),
+        };
+        state.add_transition(&first, anchor);
+        let second = transfer(&mut chain, &state, allocation);
+        let status = dry_run(&state, &genesis, &types, &scripts, OpRef::Transition(&second));
+        assert!(status.failures.contains(&Failure::InputSpent(
+            second.id(),
+            allocation,
+            first.id()
+        )));
+    }
+}
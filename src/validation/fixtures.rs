@@ -0,0 +1,492 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deterministic test-chain fixtures.
+//!
+//! Fixtures produce witness transactions, anchors and seals for the regtest
+//! and Liquid test networks, and resolve them in the validator, allowing
+//! integration tests (including downstream ones) to exercise cross-chain
+//! code paths without access to a blockchain backend. [`TestConsignment`]
+//! provides the validator with the contract operations anchored to such a
+//! chain. All the produced data
+//! are deterministic: the same sequence of calls always results in the same
+//! transactions, seals and anchors.
+//!
+//! Fixtures are available in tests and with the `fixtures` feature.
+
+use std::collections::BTreeMap;
+
+use amplify::confinement::Confined;
+use amplify::num::u5;
+use bp::dbc::opret::OpretFirst;
+use bp::seals::txout::CloseMethod;
+use bp::{
+    LockTime, OpCode, Outpoint, Sats, ScriptPubkey, SeqNo, Tx, TxIn, TxOut, TxVer, Txid, Vout,
+};
+use commit_verify::mpc::{self, MerkleBlock, MerkleTree, MultiSource};
+use commit_verify::{CommitId, EmbedCommitVerify, TryCommitVerify};
+use strict_types::TypeSystem;
+
+use super::{
+    ConsignmentApi, ResolveWitness, Scripts, SpentState, WitnessHint, WitnessResolverError,
+};
+use crate::{
+    BlindingSeed, BundleId, ContractId, DbcProof, EAnchor, Extension, Genesis, Layer1, OpId, OpRef,
+    Operation, Opout, Schema, SchemaId, SecretSeal, TransitionBundle, WitnessOrd, WitnessPos,
+    WitnessSubstitution, XChain, XGenesisSeal, XGraphSeal, XWitnessId, XWitnessTx,
+};
+
+/// Timestamp of the first block of the test chain.
+pub const TEST_CHAIN_GENESIS_TIME: i64 = 1_700_000_000;
+
+/// Interval between blocks of the test chain, in seconds.
+pub const TEST_CHAIN_BLOCK_INTERVAL: i64 = 600;
+
+/// Deterministic test chain, producing witness transactions with opret
+/// commitments and resolving them during validation.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TestChain {
+    layer1: Layer1,
+    seed: BlindingSeed,
+    height: u32,
+    outpoints: u32,
+    seals: u32,
+    txes: BTreeMap<XWitnessId, XWitnessTx>,
+    mined: BTreeMap<XWitnessId, WitnessOrd>,
+}
+
+impl TestChain {
+    fn with(layer1: Layer1, seed: u8) -> Self {
+        TestChain {
+            layer1,
+            seed: BlindingSeed::from([seed; 32]),
+            height: 0,
+            outpoints: 0,
+            seals: 0,
+            txes: empty!(),
+            mined: empty!(),
+        }
+    }
+
+    /// Creates bitcoin regtest chain.
+    pub fn regtest() -> Self { Self::with(Layer1::Bitcoin, 0x01) }
+
+    /// Creates Liquid testnet chain.
+    pub fn liquid_testnet() -> Self { Self::with(Layer1::Liquid, 0x02) }
+
+    pub fn layer1(&self) -> Layer1 { self.layer1 }
+
+    /// Value for the [`crate::Genesis::testnet`] flag of contracts issued on
+    /// the chain.
+    pub fn testnet(&self) -> bool { true }
+
+    /// Current height of the chain.
+    pub fn height(&self) -> u32 { self.height }
+
+    /// Returns a new outpoint not known to the chain, which can be used as a
+    /// genesis seal or funding of a witness transaction.
+    pub fn outpoint(&mut self) -> Outpoint {
+        self.outpoints += 1;
+        let mut txid = [0xF0u8; 32];
+        txid[..4].copy_from_slice(&self.outpoints.to_le_bytes());
+        txid[4] = self.layer1 as u8;
+        Outpoint::new(Txid::from(txid), Vout::from(0))
+    }
+
+    /// Creates genesis seal defined over a new outpoint.
+    pub fn genesis_seal(&mut self) -> XGenesisSeal {
+        let outpoint = self.outpoint();
+        self.seals += 1;
+        self.seed
+            .genesis_seal(self.layer1, CloseMethod::OpretFirst, outpoint, self.seals)
+    }
+
+    /// Creates seal defined over the output of the witness transaction.
+    pub fn graph_seal(&mut self, vout: u32) -> XGraphSeal {
+        self.seals += 1;
+        self.seed.graph_seal(
+            self.layer1,
+            CloseMethod::OpretFirst,
+            None,
+            Vout::from(vout),
+            self.seals,
+        )
+    }
+
+    /// Creates witness transaction spending the provided outpoints and
+    /// committing to the bundle of the contract, returning the witness id and
+    /// the anchor.
+    ///
+    /// The transaction has an opret output followed by `outputs` outputs,
+    /// which can be used by [`TestChain::graph_seal`]s. The transaction is not
+    /// mined until [`TestChain::mine`] is called.
+    pub fn commit(
+        &mut self,
+        contract_id: ContractId,
+        bundle_id: BundleId,
+        spent: impl IntoIterator<Item = Outpoint>,
+        outputs: u32,
+    ) -> (XWitnessId, EAnchor) {
+        let (witness_id, mut anchors) =
+            self.commit_many([(contract_id, bundle_id)], spent, outputs);
+        let anchor = anchors.remove(&contract_id).expect("contract is committed");
+        (witness_id, anchor)
+    }
+
+    /// Creates witness transaction spending the provided outpoints and
+    /// committing to the bundles of multiple contracts, as it happens in
+    /// coinjoin or payjoin transfers. Returns the witness id and the anchors
+    /// for each of the contracts.
+    ///
+    /// See [`TestChain::commit`] for the transaction structure.
+    pub fn commit_many(
+        &mut self,
+        bundles: impl IntoIterator<Item = (ContractId, BundleId)>,
+        spent: impl IntoIterator<Item = Outpoint>,
+        outputs: u32,
+    ) -> (XWitnessId, BTreeMap<ContractId, EAnchor>) {
+        let bundles = bundles.into_iter().collect::<Vec<_>>();
+        let messages = bundles.iter().map(|(contract_id, bundle_id)| {
+            (mpc::ProtocolId::from(*contract_id), mpc::Message::from(*bundle_id))
+        });
+        let (witness_id, mut anchors) = self.commit_messages(messages, spent, outputs);
+        let anchors = bundles
+            .into_iter()
+            .map(|(contract_id, _)| {
+                let anchor = anchors
+                    .remove(&mpc::ProtocolId::from(contract_id))
+                    .expect("contract is committed");
+                (contract_id, anchor)
+            })
+            .collect();
+        (witness_id, anchors)
+    }
+
+    /// Creates witness transaction spending the provided outpoints and
+    /// committing to arbitrary messages under the given protocols, like
+    /// document timestamps (see [`crate::DocumentTimestamp`]). Returns the
+    /// witness id and the anchors for each of the protocols.
+    ///
+    /// See [`TestChain::commit`] for the transaction structure.
+    pub fn commit_messages(
+        &mut self,
+        messages: impl IntoIterator<Item = (mpc::ProtocolId, mpc::Message)>,
+        spent: impl IntoIterator<Item = Outpoint>,
+        outputs: u32,
+    ) -> (XWitnessId, BTreeMap<mpc::ProtocolId, EAnchor>) {
+        let mut source = MultiSource {
+            static_entropy: Some(self.height as u64),
+            min_depth: u5::with(3),
+            ..default!()
+        };
+        let mut protocols = vec![];
+        for (protocol_id, message) in messages {
+            source
+                .messages
+                .insert(protocol_id, message)
+                .expect("too many protocols");
+            protocols.push(protocol_id);
+        }
+        let tree = MerkleTree::try_commit(&source).expect("too many protocols");
+        let commitment = tree.commit_id();
+        let block = MerkleBlock::from(tree);
+        let mpc_proofs = protocols
+            .into_iter()
+            .map(|protocol_id| {
+                let mpc_proof = block
+                    .to_merkle_proof(protocol_id)
+                    .expect("protocol is committed");
+                (protocol_id, mpc_proof)
+            })
+            .collect::<Vec<_>>();
+
+        let inputs = spent.into_iter().map(|prev_output| TxIn {
+            prev_output,
+            sig_script: default!(),
+            sequence: SeqNo::from_consensus_u32(0xFFFF_FFFD),
+            witness: default!(),
+        });
+        let opret = TxOut {
+            value: Sats::ZERO,
+            script_pubkey: ScriptPubkey::from_unsafe(vec![OpCode::Return as u8]),
+        };
+        let outputs = (0..outputs).map(|no| TxOut {
+            value: Sats::from(1000u64 + no as u64),
+            script_pubkey: ScriptPubkey::from_unsafe(vec![0x51]),
+        });
+        let mut tx = Tx {
+            version: TxVer::V2,
+            inputs: Confined::try_from_iter(inputs).expect("too many inputs"),
+            outputs: Confined::try_from_iter([opret].into_iter().chain(outputs))
+                .expect("too many outputs"),
+            lock_time: LockTime::ZERO,
+        };
+        let opret = <Tx as EmbedCommitVerify<mpc::Commitment, OpretFirst>>::embed_commit(
+            &mut tx,
+            &commitment,
+        )
+        .expect("transaction has opret output");
+
+        let tx = XWitnessTx::with(self.layer1, tx);
+        let witness_id = tx.witness_id();
+        self.txes.insert(witness_id, tx);
+        let anchors = mpc_proofs
+            .into_iter()
+            .map(|(protocol_id, mpc_proof)| {
+                (protocol_id, EAnchor::new(mpc_proof, DbcProof::Opret(opret)))
+            })
+            .collect();
+        (witness_id, anchors)
+    }
+
+    /// Mines the witness transaction in a new block, returning its position.
+    ///
+    /// # Panics
+    ///
+    /// If the witness is not known to the chain.
+    pub fn mine(&mut self, witness_id: XWitnessId) -> WitnessOrd {
+        assert!(self.txes.contains_key(&witness_id), "unknown witness {witness_id}");
+        self.height += 1;
+        let timestamp = TEST_CHAIN_GENESIS_TIME + self.height as i64 * TEST_CHAIN_BLOCK_INTERVAL;
+        let pos = WitnessPos::new(self.height, timestamp).expect("valid test chain position");
+        let ord = WitnessOrd::OnChain(pos);
+        self.mined.insert(witness_id, ord);
+        ord
+    }
+
+    /// Removes the witness from the chain, simulating transaction which was
+    /// never broadcast or was replaced.
+    pub fn drop_witness(&mut self, witness_id: XWitnessId) {
+        self.txes.remove(&witness_id);
+        self.mined.remove(&witness_id);
+    }
+}
+
+impl ResolveWitness for TestChain {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        self.txes
+            .get(&witness_id)
+            .cloned()
+            .ok_or(WitnessResolverError::Unknown(witness_id))
+    }
+
+    fn resolve_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        if !self.txes.contains_key(&witness_id) {
+            return Err(WitnessResolverError::Unknown(witness_id));
+        }
+        Ok(self
+            .mined
+            .get(&witness_id)
+            .copied()
+            .unwrap_or(WitnessOrd::OffChain))
+    }
+
+    fn resolve_tip_height(&self, layer1: Layer1) -> Option<u32> {
+        (layer1 == self.layer1).then_some(self.height)
+    }
+}
+
+/// Consignment of the contract operations, which can be validated against
+/// the [`TestChain`] the bundles are anchored to.
+///
+/// Only the bundles reachable from the `terminals` are validated.
+#[derive(Clone, Debug)]
+pub struct TestConsignment {
+    pub schema: Schema,
+    pub types: TypeSystem,
+    pub scripts: Scripts,
+    pub genesis: Genesis,
+    pub extensions: BTreeMap<OpId, Extension>,
+    pub bundles: BTreeMap<BundleId, TransitionBundle>,
+    pub anchors: BTreeMap<BundleId, (XWitnessId, EAnchor)>,
+    pub terminals: Vec<(BundleId, XChain<SecretSeal>)>,
+    pub spent_states: BTreeMap<Opout, SpentState>,
+    pub substitutions: BTreeMap<BundleId, WitnessSubstitution>,
+    pub alt_anchors: BTreeMap<BundleId, Vec<(XWitnessId, EAnchor)>>,
+    pub replaced_witnesses: BTreeMap<XWitnessId, XWitnessTx>,
+    pub hints: BTreeMap<XWitnessId, WitnessHint>,
+    pub successors: BTreeMap<SchemaId, Schema>,
+}
+
+impl TestConsignment {
+    /// Constructs consignment of the contract genesis, without operations
+    /// and scripts.
+    pub fn new(schema: Schema, types: TypeSystem, genesis: Genesis) -> Self {
+        TestConsignment {
+            schema,
+            types,
+            scripts: empty!(),
+            genesis,
+            extensions: empty!(),
+            bundles: empty!(),
+            anchors: empty!(),
+            terminals: empty!(),
+            spent_states: empty!(),
+            substitutions: empty!(),
+            alt_anchors: empty!(),
+            replaced_witnesses: empty!(),
+            hints: empty!(),
+            successors: empty!(),
+        }
+    }
+
+    /// Adds the bundle together with its anchor (see [`TestChain::commit`]),
+    /// returning the bundle id.
+    pub fn add_bundle(
+        &mut self,
+        bundle: TransitionBundle,
+        witness_id: XWitnessId,
+        anchor: EAnchor,
+    ) -> BundleId {
+        let bundle_id = bundle.bundle_id();
+        self.bundles.insert(bundle_id, bundle);
+        self.anchors.insert(bundle_id, (witness_id, anchor));
+        bundle_id
+    }
+}
+
+impl ConsignmentApi for TestConsignment {
+    fn schema(&self) -> &Schema { &self.schema }
+
+    fn types(&self) -> &TypeSystem { &self.types }
+
+    fn scripts(&self) -> &Scripts { &self.scripts }
+
+    fn operation(&self, opid: OpId) -> Option<OpRef<'_>> {
+        if opid == self.genesis.id() {
+            return Some(OpRef::Genesis(&self.genesis));
+        }
+        if let Some(extension) = self.extensions.get(&opid) {
+            return Some(OpRef::Extension(extension));
+        }
+        self.bundles
+            .values()
+            .find_map(|bundle| bundle.known_transitions.get(&opid))
+            .map(OpRef::Transition)
+    }
+
+    fn genesis(&self) -> &Genesis { &self.genesis }
+
+    fn terminals<'iter>(&self) -> impl Iterator<Item = (BundleId, XChain<SecretSeal>)> + 'iter {
+        self.terminals.clone().into_iter()
+    }
+
+    fn bundle_ids<'iter>(&self) -> impl Iterator<Item = BundleId> + 'iter {
+        self.bundles.keys().copied().collect::<Vec<_>>().into_iter()
+    }
+
+    fn extension_ids<'iter>(&self) -> impl Iterator<Item = OpId> + 'iter {
+        self.extensions
+            .keys()
+            .copied()
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn bundle(&self, bundle_id: BundleId) -> Option<&TransitionBundle> {
+        self.bundles.get(&bundle_id)
+    }
+
+    fn anchor(&self, bundle_id: BundleId) -> Option<(XWitnessId, &EAnchor)> {
+        self.anchors
+            .get(&bundle_id)
+            .map(|(witness_id, anchor)| (*witness_id, anchor))
+    }
+
+    fn op_witness_id(&self, opid: OpId) -> Option<XWitnessId> {
+        let (bundle_id, _) = self
+            .bundles
+            .iter()
+            .find(|(_, bundle)| bundle.known_transitions.contains_key(&opid))?;
+        self.anchors
+            .get(bundle_id)
+            .map(|(witness_id, _)| *witness_id)
+    }
+
+    fn witness_substitution(&self, bundle_id: BundleId) -> Option<WitnessSubstitution> {
+        self.substitutions.get(&bundle_id).copied()
+    }
+
+    fn alt_anchors(&self, bundle_id: BundleId) -> Vec<(XWitnessId, &EAnchor)> {
+        self.alt_anchors
+            .get(&bundle_id)
+            .into_iter()
+            .flatten()
+            .map(|(witness_id, anchor)| (*witness_id, anchor))
+            .collect()
+    }
+
+    fn replaced_witness(&self, witness_id: XWitnessId) -> Option<XWitnessTx> {
+        self.replaced_witnesses.get(&witness_id).cloned()
+    }
+
+    fn witness_hint(&self, witness_id: XWitnessId) -> Option<WitnessHint> {
+        self.hints.get(&witness_id).copied()
+    }
+
+    fn schema_successor(&self, schema_id: SchemaId) -> Option<&Schema> {
+        self.successors.get(&schema_id)
+    }
+
+    fn spent_state(&self, opout: Opout) -> Option<SpentState> {
+        self.spent_states.get(&opout).cloned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bp::dbc::Proof;
+
+    use super::*;
+
+    #[test]
+    fn anchors_verify() {
+        for mut chain in [TestChain::regtest(), TestChain::liquid_testnet()] {
+            let contract_id = ContractId::from([0x11; 32]);
+            let bundle_id = BundleId::from([0x22; 32]);
+            let funding = chain.outpoint();
+            let (witness_id, anchor) = chain.commit(contract_id, bundle_id, [funding], 2);
+            assert_eq!(witness_id.layer1(), chain.layer1());
+            assert_eq!(chain.resolve_witness_ord(witness_id).ok(), Some(WitnessOrd::OffChain));
+
+            let tx = chain.resolve_pub_witness(witness_id).unwrap();
+            assert_eq!(tx.as_reduced_unsafe().inputs[0].prev_output, funding);
+            let commitment = anchor
+                .convolve(mpc::ProtocolId::from(contract_id), mpc::Message::from(bundle_id))
+                .unwrap();
+            assert!(anchor
+                .dbc_proof
+                .verify(&commitment, tx.as_reduced_unsafe())
+                .is_ok());
+
+            let ord = chain.mine(witness_id);
+            assert_eq!(chain.resolve_witness_ord(witness_id).ok(), Some(ord));
+        }
+        assert_eq!(TestChain::regtest().outpoint(), TestChain::regtest().outpoint());
+    }
+}
@@ -0,0 +1,363 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Index of contract operations within an encoded consignment.
+//!
+//! Large consignments may carry an optional index section, listing offsets of
+//! the genesis, each of the transition bundles and state extensions in the
+//! consignment data section. The index allows decoding a single operation
+//! without parsing everything which precedes it. Since the index is not
+//! committed to, it must be verified against the data it refers to with
+//! [`ConsignmentIndex::verify`] before being trusted.
+
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use amplify::confinement::{Confined, MediumOrdMap, U32};
+use strict_encoding::{StrictDeserialize, StrictSerialize};
+
+use crate::{
+    BundleId, Extension, Genesis, OpId, Operation, Transition, TransitionBundle, LIB_NAME_RGB,
+};
+
+/// Position of an encoded item within the consignment data section.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default, Display)]
+#[display("{offset}+{len}")]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct IndexSpan {
+    pub offset: u64,
+    pub len: u32,
+}
+
+impl IndexSpan {
+    /// Returns range of the data section covered by the span.
+    ///
+    /// Since the index is not trusted, the span may point beyond the maximal
+    /// offset, in which case [`IndexError::OutOfBounds`] is returned.
+    pub fn range(&self) -> Result<Range<u64>, IndexError> {
+        let end = self
+            .offset
+            .checked_add(self.len as u64)
+            .ok_or(IndexError::OutOfBounds(*self))?;
+        Ok(self.offset..end)
+    }
+}
+
+/// Errors building, verifying or using consignment index.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum IndexError {
+    /// consignment index has too many entries.
+    TooManyEntries,
+
+    /// item of the consignment is too large to be indexed.
+    ItemTooLarge,
+
+    /// {0} is not present in the consignment index.
+    NotIndexed(String),
+
+    /// span {0} is outside of the consignment data section.
+    OutOfBounds(IndexSpan),
+
+    /// span {0} overlaps with other items or leaves data not covered by the
+    /// index.
+    Gap(IndexSpan),
+
+    /// data at span {0} can't be decoded: {1}
+    Decode(IndexSpan, String),
+
+    /// data at span {span} contains {found} instead of {expected}.
+    Mismatch {
+        span: IndexSpan,
+        expected: String,
+        found: String,
+    },
+
+    /// transition {0} from bundle {1} is not indexed.
+    TransitionNotIndexed(OpId, BundleId),
+}
+
+/// Index of the consignment data section.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ConsignmentIndex {
+    pub genesis: IndexSpan,
+    pub bundles: MediumOrdMap<BundleId, IndexSpan>,
+    pub extensions: MediumOrdMap<OpId, IndexSpan>,
+    /// Bundles containing each of the state transitions.
+    pub transitions: MediumOrdMap<OpId, BundleId>,
+}
+
+impl StrictSerialize for ConsignmentIndex {}
+impl StrictDeserialize for ConsignmentIndex {}
+
+impl ConsignmentIndex {
+    /// Encodes the genesis, bundles and extensions one after another into a
+    /// data section, returning it together with its index.
+    pub fn write<'op>(
+        genesis: &Genesis,
+        bundles: impl IntoIterator<Item = &'op TransitionBundle>,
+        extensions: impl IntoIterator<Item = &'op Extension>,
+    ) -> Result<(Vec<u8>, Self), IndexError> {
+        let mut data = vec![];
+        let genesis = append(&mut data, genesis)?;
+        let mut index = ConsignmentIndex {
+            genesis,
+            bundles: empty!(),
+            extensions: empty!(),
+            transitions: empty!(),
+        };
+        for bundle in bundles {
+            let bundle_id = bundle.bundle_id();
+            let span = append(&mut data, bundle)?;
+            index
+                .bundles
+                .insert(bundle_id, span)
+                .map_err(|_| IndexError::TooManyEntries)?;
+            for opid in bundle.known_transitions.keys() {
+                index
+                    .transitions
+                    .insert(*opid, bundle_id)
+                    .map_err(|_| IndexError::TooManyEntries)?;
+            }
+        }
+        for extension in extensions {
+            let span = append(&mut data, extension)?;
+            index
+                .extensions
+                .insert(extension.id(), span)
+                .map_err(|_| IndexError::TooManyEntries)?;
+        }
+        Ok((data, index))
+    }
+
+    /// Decodes genesis from the data section.
+    pub fn decode_genesis(&self, data: &[u8]) -> Result<Genesis, IndexError> {
+        decode(data, self.genesis)
+    }
+
+    /// Decodes a single bundle from the data section.
+    pub fn decode_bundle(
+        &self,
+        data: &[u8],
+        bundle_id: BundleId,
+    ) -> Result<TransitionBundle, IndexError> {
+        let span = self
+            .bundles
+            .get(&bundle_id)
+            .ok_or_else(|| IndexError::NotIndexed(format!("bundle {bundle_id}")))?;
+        decode(data, *span)
+    }
+
+    /// Decodes a single state transition from the data section, parsing only
+    /// the bundle which contains it.
+    pub fn decode_transition(&self, data: &[u8], opid: OpId) -> Result<Transition, IndexError> {
+        let not_indexed = || IndexError::NotIndexed(format!("transition {opid}"));
+        let bundle_id = self.transitions.get(&opid).ok_or_else(not_indexed)?;
+        let bundle = self.decode_bundle(data, *bundle_id)?;
+        bundle
+            .known_transitions
+            .into_inner()
+            .remove(&opid)
+            .ok_or_else(not_indexed)
+    }
+
+    /// Decodes a single state extension from the data section.
+    pub fn decode_extension(&self, data: &[u8], opid: OpId) -> Result<Extension, IndexError> {
+        let span = self
+            .extensions
+            .get(&opid)
+            .ok_or_else(|| IndexError::NotIndexed(format!("extension {opid}")))?;
+        decode(data, *span)
+    }
+
+    /// Verifies the index against the data section.
+    ///
+    /// Checks that the indexed spans cover the whole data section without
+    /// overlaps, that each of them decodes into the item with the indexed id,
+    /// and that each state transition is indexed under the bundle containing
+    /// it.
+    pub fn verify(&self, data: &[u8]) -> Result<(), IndexError> {
+        let spans = self
+            .bundles
+            .values()
+            .chain(self.extensions.values())
+            .chain([&self.genesis])
+            .copied()
+            .collect::<BTreeSet<_>>();
+        let mut pos = 0u64;
+        for span in spans {
+            let range = span.range()?;
+            if range.end > data.len() as u64 {
+                return Err(IndexError::OutOfBounds(span));
+            }
+            if span.offset != pos {
+                return Err(IndexError::Gap(span));
+            }
+            pos = range.end;
+        }
+        if pos != data.len() as u64 {
+            return Err(IndexError::Gap(IndexSpan {
+                offset: pos,
+                len: u32::try_from(data.len() as u64 - pos).unwrap_or(u32::MAX),
+            }));
+        }
+
+        self.decode_genesis(data)?;
+        let mut transitions = BTreeSet::new();
+        for (bundle_id, span) in &self.bundles {
+            let bundle: TransitionBundle = decode(data, *span)?;
+            if bundle.bundle_id() != *bundle_id {
+                return Err(IndexError::Mismatch {
+                    span: *span,
+                    expected: format!("bundle {bundle_id}"),
+                    found: format!("bundle {}", bundle.bundle_id()),
+                });
+            }
+            for opid in bundle.known_transitions.keys() {
+                if self.transitions.get(opid) != Some(bundle_id) {
+                    return Err(IndexError::TransitionNotIndexed(*opid, *bundle_id));
+                }
+                transitions.insert(*opid);
+            }
+        }
+        if let Some(opid) = self
+            .transitions
+            .keys()
+            .find(|id| !transitions.contains(*id))
+        {
+            return Err(IndexError::NotIndexed(format!("transition {opid}")));
+        }
+        for (opid, span) in &self.extensions {
+            let extension: Extension = decode(data, *span)?;
+            if extension.id() != *opid {
+                return Err(IndexError::Mismatch {
+                    span: *span,
+                    expected: format!("extension {opid}"),
+                    found: format!("extension {}", extension.id()),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn append(data: &mut Vec<u8>, item: &impl StrictSerialize) -> Result<IndexSpan, IndexError> {
+    let bytes = item
+        .to_strict_serialized::<U32>()
+        .map_err(|_| IndexError::ItemTooLarge)?;
+    let span = IndexSpan {
+        offset: data.len() as u64,
+        len: bytes.len() as u32,
+    };
+    data.extend(bytes.into_inner());
+    Ok(span)
+}
+
+fn decode<T: StrictDeserialize>(data: &[u8], span: IndexSpan) -> Result<T, IndexError> {
+    let range = span.range()?;
+    if range.end > data.len() as u64 {
+        return Err(IndexError::OutOfBounds(span));
+    }
+    let bytes = data[range.start as usize..range.end as usize].to_vec();
+    let bytes = Confined::<Vec<u8>, 0, U32>::try_from(bytes)
+        .map_err(|err| IndexError::Decode(span, err.to_string()))?;
+    T::from_strict_serialized(bytes).map_err(|err| IndexError::Decode(span, err.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn random_access() {
+        let genesis = Genesis::strict_dumb();
+        let bundle = TransitionBundle::strict_dumb();
+        let extension = Extension::strict_dumb();
+        let (mut data, index) = ConsignmentIndex::write(&genesis, [&bundle], [&extension]).unwrap();
+        assert_eq!(index.verify(&data), Ok(()));
+
+        let (opid, transition) = bundle.known_transitions.iter().next().unwrap();
+        assert_eq!(index.decode_transition(&data, *opid).as_ref(), Ok(transition));
+        assert_eq!(index.decode_extension(&data, extension.id()), Ok(extension));
+        assert_eq!(index.decode_genesis(&data), Ok(genesis));
+
+        data.push(0);
+        assert!(matches!(index.verify(&data), Err(IndexError::Gap(_))));
+        data.pop();
+        let span = index.bundles.values().next().copied().unwrap();
+        data[span.offset as usize] ^= 0xFF;
+        assert!(index.verify(&data).is_err());
+    }
+
+    #[test]
+    fn untrusted_spans() {
+        let genesis = Genesis::strict_dumb();
+        let (data, mut index) = ConsignmentIndex::write(
+            &genesis,
+            Vec::<&TransitionBundle>::new(),
+            Vec::<&Extension>::new(),
+        )
+        .unwrap();
+
+        let overflow = IndexSpan {
+            offset: u64::MAX - 1,
+            len: 2,
+        };
+        assert_eq!(overflow.range(), Err(IndexError::OutOfBounds(overflow)));
+        index.genesis = overflow;
+        assert_eq!(index.verify(&data), Err(IndexError::OutOfBounds(overflow)));
+        assert_eq!(index.decode_genesis(&data), Err(IndexError::OutOfBounds(overflow)));
+
+        let beyond = IndexSpan {
+            offset: 0,
+            len: data.len() as u32 + 1,
+        };
+        index.genesis = beyond;
+        assert_eq!(index.verify(&data), Err(IndexError::OutOfBounds(beyond)));
+
+        let short = IndexSpan {
+            offset: 0,
+            len: data.len() as u32 - 1,
+        };
+        index.genesis = short;
+        let gap = IndexSpan {
+            offset: short.len as u64,
+            len: 1,
+        };
+        assert_eq!(index.verify(&data), Err(IndexError::Gap(gap)));
+    }
+}
@@ -20,7 +20,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use aluvm::data::Number;
 use aluvm::isa::Instr;
@@ -31,20 +31,68 @@ use amplify::Wrapper;
 use strict_types::TypeSystem;
 
 use crate::schema::{AssignmentsSchema, GlobalSchema, ValencySchema};
-use crate::validation::{CheckedConsignment, ConsignmentApi, Failure};
+use crate::validation::{CheckedConsignment, ConsignmentApi, Failure, Scripts, SpentState};
 use crate::vm::RgbIsa;
 use crate::{
-    validation, AssetTags, Assignments, AssignmentsRef, ContractId, ExposedSeal, Extension,
-    GlobalState, GlobalStateSchema, GlobalValues, GraphSeal, Inputs, MetaSchema, Metadata,
-    OpFullType, OpId, OpRef, Operation, Opout, OwnedStateSchema, Schema, StateType, Transition,
-    TypedAssigns, Valencies,
+    validation, AssetTags, AssignmentLocks, AssignmentType, Assignments, AssignmentsRef,
+    ContractId, ExposedSeal, Extension, GlobalState, GlobalStateSchema, GlobalValues, GraphSeal,
+    Inputs, MetaSchema, Metadata, OpFullType, OpId, OpRef, Operation, Opout, OwnedStateSchema,
+    Schema, StateType, Transition, TypedAssigns, Valencies, WitnessOrd,
 };
 
+/// Contract data, which is not a part of the validated operation, but is
+/// required to validate its state.
+pub(super) struct OpContext<'ctx> {
+    pub contract_id: ContractId,
+    pub asset_tags: &'ctx AssetTags,
+    pub types: &'ctx TypeSystem,
+    pub scripts: &'ctx Scripts,
+}
+
 impl Schema {
     pub fn validate_state<'validator, C: ConsignmentApi>(
         &'validator self,
         consignment: &'validator CheckedConsignment<'_, C>,
         op: OpRef,
+        witness: Option<WitnessOrd>,
+    ) -> validation::Status {
+        let mut prev_status = validation::Status::new();
+        let prev_state = match op {
+            OpRef::Transition(transition) => {
+                extract_prev_state(consignment, op.id(), &transition.inputs, &mut prev_status)
+            }
+            OpRef::Genesis(_) | OpRef::Extension(_) => Assignments::default(),
+        };
+        let genesis = consignment.genesis();
+        let context = OpContext {
+            contract_id: genesis.contract_id(),
+            asset_tags: &genesis.asset_tags,
+            types: consignment.types(),
+            scripts: consignment.scripts(),
+        };
+        let locks = |prev_id: OpId| {
+            consignment
+                .operation(prev_id)
+                .and_then(|prev_op| prev_op.metadata().assignment_locks())
+        };
+        self.validate_state_with(context, op, (prev_state, prev_status), Some(&locks), witness)
+    }
+
+    /// Validates operation state against the schema given the state spent by
+    /// the operation, together with the status of the spent state extraction,
+    /// and assignment locks of the ancestor operations.
+    ///
+    /// Locks are looked up by the ancestor operation id, returning `None` if
+    /// the ancestor is absent or its locks are invalid, which fails the
+    /// validation. Callers not having access to the ancestor operations
+    /// provide no lookup, in which case lock scripts are not run.
+    pub(super) fn validate_state_with(
+        &self,
+        context: OpContext,
+        op: OpRef,
+        (prev_state, prev_status): (Assignments<GraphSeal>, validation::Status),
+        locks: Option<&dyn Fn(OpId) -> Option<AssignmentLocks>>,
+        witness: Option<WitnessOrd>,
     ) -> validation::Status {
         let opid = op.id();
         let mut status = validation::Status::new();
@@ -157,16 +205,12 @@ impl Schema {
 
         // Validate type system
         status += self.validate_type_system();
-        status += self.validate_metadata(opid, op.metadata(), metadata_schema, consignment.types());
-        status +=
-            self.validate_global_state(opid, op.globals(), global_schema, consignment.types());
-        let prev_state = if let OpRef::Transition(transition) = op {
-            let prev_state = extract_prev_state(consignment, opid, &transition.inputs, &mut status);
+        status += self.validate_metadata(opid, op.metadata(), metadata_schema, context.types);
+        status += self.validate_global_state(opid, op.globals(), global_schema, context.types);
+        if let OpRef::Transition(_) = op {
+            status += prev_status;
             status += self.validate_prev_state(opid, &prev_state, owned_schema);
-            prev_state
-        } else {
-            Assignments::default()
-        };
+        }
         let mut redeemed = Valencies::default();
         if let OpRef::Extension(extension) = op {
             for valency in extension.redeemed.keys() {
@@ -176,31 +220,34 @@ impl Schema {
         }
         status += match op.assignments() {
             AssignmentsRef::Genesis(assignments) => {
-                self.validate_owned_state(opid, assignments, assign_schema, consignment.types())
+                self.validate_owned_state(opid, assignments, assign_schema, context.types)
             }
             AssignmentsRef::Graph(assignments) => {
-                self.validate_owned_state(opid, assignments, assign_schema, consignment.types())
+                self.validate_owned_state(opid, assignments, assign_schema, context.types)
             }
         };
 
         status += self.validate_valencies(opid, op.valencies(), valency_schema);
 
-        let genesis = consignment.genesis();
         let op_info = OpInfo::with(
-            genesis.contract_id(),
+            context.contract_id,
             opid,
             &op,
             &prev_state,
             &redeemed,
-            &genesis.asset_tags,
+            context.asset_tags,
+            witness,
         );
 
         // We need to run scripts as the very last step, since before that
         // we need to make sure that the operation data match the schema, so
         // scripts are not required to validate the structure of the state
         if let Some(validator) = validator {
-            let scripts = consignment.scripts();
+            trace_span!("rgb::vm_exec", opid = opid, lib = validator.lib);
+            let scripts = context.scripts;
             let mut vm = Vm::<Instr<RgbIsa>>::new();
+            // Genesis script is told apart from the other scripts sharing the same library
+            // by the absence of the operation type
             if let Some(ty) = ty {
                 vm.registers.set_n(RegA::A16, Reg32::Reg0, ty);
             }
@@ -213,6 +260,52 @@ impl Schema {
                 ));
             }
         }
+
+        // Assignment locks and features are declared with the reserved metadata types,
+        // which are ordinary types unless the schema opts into them
+        if !self.reserves_types() {
+            return status;
+        }
+        if op.metadata().assignment_locks().is_none() {
+            status.add_failure(validation::Failure::AssignmentLocksInvalid(opid));
+        }
+        if op.metadata().op_features().is_none() {
+            status.add_failure(validation::Failure::OpFeaturesInvalid(opid));
+        }
+        // Lock scripts of the spent assignments are evaluated in the context of the
+        // spending operation
+        if let (OpRef::Transition(transition), Some(locks)) = (op, locks) {
+            let scripts = context.scripts;
+            for input in &transition.inputs {
+                let Opout {
+                    op: prev_id,
+                    ty,
+                    no,
+                } = input.prev_out;
+                // Absent ancestor may have locked the assignment, thus skipping it would allow
+                // to bypass the lock
+                let Some(prev_locks) = locks(prev_id) else {
+                    status.add_failure(validation::Failure::LockUnverifiable(opid, input.prev_out));
+                    continue;
+                };
+                let Some(lock) = prev_locks.lock(ty, no) else {
+                    continue;
+                };
+                trace_event!("running lock script", opid = opid, opout = input.prev_out);
+                let mut vm = Vm::<Instr<RgbIsa>>::new();
+                vm.registers.set_n(RegA::A16, Reg32::Reg0, ty.into_inner());
+                vm.registers.set_n(RegA::A16, Reg32::Reg1, no);
+                if !vm.exec(lock, |id| scripts.get(&id), &op_info) {
+                    let error_code: Option<Number> =
+                        vm.registers.get_n(RegA::A8, Reg32::Reg0).into();
+                    status.add_failure(validation::Failure::LockScriptFailure(
+                        opid,
+                        input.prev_out,
+                        error_code.map(u8::from),
+                    ));
+                }
+            }
+        }
         status
     }
 
@@ -481,6 +574,9 @@ pub struct OpInfo<'op> {
     pub redeemed: &'op Valencies,
     pub valencies: &'op Valencies,
     pub global: &'op GlobalState,
+    /// Position of the operation witness, if the operation has a witness and
+    /// the position is known.
+    pub witness: Option<WitnessOrd>,
 }
 
 impl<'op> OpInfo<'op> {
@@ -491,6 +587,7 @@ impl<'op> OpInfo<'op> {
         prev_state: &'op Assignments<GraphSeal>,
         redeemed: &'op Valencies,
         asset_tags: &'op AssetTags,
+        witness: Option<WitnessOrd>,
     ) -> Self {
         OpInfo {
             id,
@@ -503,6 +600,7 @@ impl<'op> OpInfo<'op> {
             redeemed,
             valencies: op.valencies(),
             global: op.globals(),
+            witness,
         }
     }
 }
@@ -517,6 +615,17 @@ fn extract_prev_state<C: ConsignmentApi>(
     for input in inputs {
         let Opout { op, ty, no } = input.prev_out;
 
+        // Revealed spent state provided by the consignment is used without looking up
+        // the ancestor operation; the validator checks that it matches the
+        // ancestor when walking the operation graph
+        if let Some(spent) = consignment
+            .spent_state(input.prev_out)
+            .filter(SpentState::is_revealed)
+        {
+            push_spent_state(&mut assignments, ty, spent);
+            continue;
+        }
+
         let prev_op = match consignment.operation(op) {
             None => {
                 status.add_failure(validation::Failure::OperationAbsent(op));
@@ -525,68 +634,182 @@ fn extract_prev_state<C: ConsignmentApi>(
             Some(op) => op,
         };
 
-        let no = no as usize;
-        match prev_op.assignments_by_type(ty) {
-            Some(TypedAssigns::Declarative(prev_assignments)) => {
-                if let Some(prev_assign) = prev_assignments.get(no) {
-                    if let Some(typed_assigns) = assignments
-                        .entry(ty)
-                        .or_insert_with(|| TypedAssigns::Declarative(Default::default()))
-                        .as_declarative_mut()
-                    {
-                        typed_assigns.push(prev_assign.clone()).expect("same size");
-                    }
-                } else {
-                    status.add_failure(validation::Failure::NoPrevOut(opid, input.prev_out));
-                }
-            }
-            Some(TypedAssigns::Fungible(prev_assignments)) => {
-                if let Some(prev_assign) = prev_assignments.get(no) {
-                    if let Some(typed_assigns) = assignments
-                        .entry(ty)
-                        .or_insert_with(|| TypedAssigns::Fungible(Default::default()))
-                        .as_fungible_mut()
-                    {
-                        typed_assigns.push(prev_assign.clone()).expect("same size");
-                    }
-                } else {
-                    status.add_failure(validation::Failure::NoPrevOut(opid, input.prev_out));
-                }
+        let Some(prev_assignments) = prev_op.assignments_by_type(ty) else {
+            // Presence of the required owned rights type in the
+            // parent operation was already validated; we have nothing
+            // to report here
+            continue;
+        };
+        let Some(spent) = SpentState::from_assigns(&prev_assignments, no) else {
+            status.add_failure(validation::Failure::NoPrevOut(opid, input.prev_out));
+            continue;
+        };
+
+        push_spent_state(&mut assignments, ty, spent);
+    }
+    Confined::try_from(assignments)
+        .expect("collections is assembled from another collection with the same size requirements")
+        .into()
+}
+
+/// Adds assignment spent by a transition input to the previous state of the
+/// transition.
+pub(super) fn push_spent_state(
+    assignments: &mut BTreeMap<AssignmentType, TypedAssigns<GraphSeal>>,
+    ty: AssignmentType,
+    spent: SpentState,
+) {
+    match spent {
+        SpentState::Declarative(prev_assign) => {
+            if let Some(typed_assigns) = assignments
+                .entry(ty)
+                .or_insert_with(|| TypedAssigns::Declarative(Default::default()))
+                .as_declarative_mut()
+            {
+                typed_assigns.push(prev_assign).expect("same size");
             }
-            Some(TypedAssigns::Structured(prev_assignments)) => {
-                if let Some(prev_assign) = prev_assignments.get(no) {
-                    if let Some(typed_assigns) = assignments
-                        .entry(ty)
-                        .or_insert_with(|| TypedAssigns::Structured(Default::default()))
-                        .as_structured_mut()
-                    {
-                        typed_assigns.push(prev_assign.clone()).expect("same size");
-                    }
-                } else {
-                    status.add_failure(validation::Failure::NoPrevOut(opid, input.prev_out));
-                }
+        }
+        SpentState::Fungible(prev_assign) => {
+            if let Some(typed_assigns) = assignments
+                .entry(ty)
+                .or_insert_with(|| TypedAssigns::Fungible(Default::default()))
+                .as_fungible_mut()
+            {
+                typed_assigns.push(prev_assign).expect("same size");
             }
-            Some(TypedAssigns::Attachment(prev_assignments)) => {
-                if let Some(prev_assign) = prev_assignments.get(no) {
-                    if let Some(typed_assigns) = assignments
-                        .entry(ty)
-                        .or_insert_with(|| TypedAssigns::Attachment(Default::default()))
-                        .as_attachment_mut()
-                    {
-                        typed_assigns.push(prev_assign.clone()).expect("same size");
-                    }
-                } else {
-                    status.add_failure(validation::Failure::NoPrevOut(opid, input.prev_out));
-                }
+        }
+        SpentState::Structured(prev_assign) => {
+            if let Some(typed_assigns) = assignments
+                .entry(ty)
+                .or_insert_with(|| TypedAssigns::Structured(Default::default()))
+                .as_structured_mut()
+            {
+                typed_assigns.push(prev_assign).expect("same size");
             }
-            None => {
-                // Presence of the required owned rights type in the
-                // parent operation was already validated; we have nothing
-                // to report here
+        }
+        SpentState::Attachment(prev_assign) => {
+            if let Some(typed_assigns) = assignments
+                .entry(ty)
+                .or_insert_with(|| TypedAssigns::Attachment(Default::default()))
+                .as_attachment_mut()
+            {
+                typed_assigns.push(prev_assign).expect("same size");
             }
         }
     }
-    Confined::try_from(assignments)
-        .expect("collections is assembled from another collection with the same size requirements")
-        .into()
+}
+
+#[cfg(test)]
+mod test {
+    use std::slice;
+
+    use aluvm::isa::ControlFlowOp;
+    use aluvm::library::{Lib, LibSite};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::validation::fixtures::TestConsignment;
+    use crate::{
+        AssetTag, Assign, Genesis, Input, LockedAssignment, RevealedValue, SchemaFlags,
+        TransitionType, XGraphSeal,
+    };
+
+    #[test]
+    fn spent_state_without_ancestor() {
+        let ty = AssignmentType::with(1);
+        let opid = OpId::from([0x01; 32]);
+        let opout = Opout::new(OpId::from([0x02; 32]), ty, 0);
+        let inputs = Inputs::try_from_inputs([Input::with(opout)]).unwrap();
+        let spent = Assign::revealed(
+            XGraphSeal::strict_dumb(),
+            RevealedValue::new_random_blinding(100, AssetTag::strict_dumb()),
+        );
+        let mut consignment = TestConsignment::new(
+            Schema::strict_dumb(),
+            TypeSystem::default(),
+            Genesis::strict_dumb(),
+        );
+        consignment
+            .spent_states
+            .insert(opout, SpentState::Fungible(spent.clone()));
+
+        let mut status = validation::Status::new();
+        let prev_state = extract_prev_state(&consignment, opid, &inputs, &mut status);
+        assert!(status.failures.is_empty(), "{status}");
+        assert_eq!(prev_state.get(&ty).unwrap().as_fungible(), slice::from_ref(&spent));
+
+        // Concealed spent state doesn't allow to validate the transition state, so the
+        // ancestor is looked up
+        consignment
+            .spent_states
+            .insert(opout, SpentState::Fungible(spent.conceal_state()));
+        let mut status = validation::Status::new();
+        let prev_state = extract_prev_state(&consignment, opid, &inputs, &mut status);
+        assert_eq!(status.failures, vec![Failure::OperationAbsent(opout.op)]);
+        assert!(prev_state.is_empty());
+    }
+
+    #[test]
+    fn lock_scripts() {
+        let fail =
+            Lib::assemble::<Instr<RgbIsa>>(&[Instr::ControlFlow(ControlFlowOp::Fail)]).unwrap();
+        let scripts = Confined::from_iter_unsafe([(fail.id(), fail.clone())]);
+        let types = TypeSystem::default();
+        let mut schema = Schema::strict_dumb();
+        schema.flags = SchemaFlags::RESERVED_TYPES;
+        let genesis = Genesis::strict_dumb();
+
+        let ty = AssignmentType::with(1);
+        let opout = Opout::new(OpId::from([0x02; 32]), ty, 0);
+        let mut transition = Transition::strict_dumb();
+        transition.transition_type = TransitionType::BLANK;
+        transition.inputs = Inputs::try_from_inputs([Input::with(opout)]).unwrap();
+        let opid = transition.id();
+        let validate = |locks: Option<&dyn Fn(OpId) -> Option<AssignmentLocks>>| {
+            let context = OpContext {
+                contract_id: genesis.contract_id(),
+                asset_tags: &genesis.asset_tags,
+                types: &types,
+                scripts: &scripts,
+            };
+            schema.validate_state_with(
+                context,
+                OpRef::Transition(&transition),
+                (Assignments::default(), validation::Status::new()),
+                locks,
+                None,
+            )
+        };
+        let is_lock_failure = |failure: &Failure| {
+            matches!(failure, Failure::LockScriptFailure(..) | Failure::LockUnverifiable(..))
+        };
+
+        // Lock of an absent ancestor can't be bypassed
+        let status = validate(Some(&|_| None));
+        assert!(
+            status
+                .failures
+                .contains(&Failure::LockUnverifiable(opid, opout)),
+            "{status}"
+        );
+
+        let status = validate(Some(&|_| Some(AssignmentLocks::default())));
+        assert!(!status.failures.iter().any(is_lock_failure), "{status}");
+
+        let mut locks = AssignmentLocks::default();
+        locks
+            .insert(LockedAssignment::new(ty, 0), LibSite::with(0, fail.id()))
+            .unwrap();
+        let status = validate(Some(&|_| Some(locks.clone())));
+        assert!(
+            status
+                .failures
+                .contains(&Failure::LockScriptFailure(opid, opout, None)),
+            "{status}"
+        );
+
+        // Locks are not checked without access to the ancestors
+        let status = validate(None);
+        assert!(!status.failures.iter().any(is_lock_failure), "{status}");
+    }
 }
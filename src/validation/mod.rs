@@ -20,14 +20,83 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+/// Enters a tracing span lasting till the end of the current block, with the
+/// provided values as the span fields. Does nothing unless the `telemetry`
+/// feature is enabled.
+macro_rules! trace_span {
+    ($name:literal $(, $field:ident = $value:expr)* $(,)?) => {
+        #[cfg(feature = "telemetry")]
+        let _span = tracing::debug_span!($name $(, $field = %$value)*).entered();
+    };
+}
+
+/// Emits a tracing event with the provided values as the event fields. Does
+/// nothing unless the `telemetry` feature is enabled.
+macro_rules! trace_event {
+    ($msg:literal $(, $field:ident = $value:expr)* $(,)?) => {
+        #[cfg(feature = "telemetry")]
+        tracing::debug!($($field = %$value,)* $msg);
+    };
+}
+
 mod schema;
 mod logic;
 mod state;
 mod validator;
 mod consignment;
 mod status;
+mod attach;
+mod store;
+mod policy;
+mod dag;
+mod close_method;
+mod index;
+mod standard;
+mod privacy;
+mod resolver_cache;
+mod stateless;
+mod checkpoint;
+mod prefetch;
+mod report;
+mod config;
+mod cancel;
+mod dry_run;
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(feature = "async")]
+mod async_resolver;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
 
-pub use consignment::{CheckedConsignment, ConsignmentApi, Scripts, CONSIGNMENT_MAX_LIBS};
+#[cfg(feature = "async")]
+pub use async_resolver::{validate_async, AsyncResolveWitness};
+pub use attach::{verify_attachment, verify_op_attachments, AttachmentVerifier};
+pub use cancel::{CancelToken, Interruption};
+pub use checkpoint::ValidationCheckpoint;
+pub use close_method::{verify_with_plugin, CloseMethodPlugin, CloseMethodRegistry, PluginError};
+pub use config::ValidationConfig;
+pub use consignment::{
+    CheckedConsignment, ConsignmentApi, Scripts, SpentState, WitnessHint, CONSIGNMENT_MAX_LIBS,
+};
+pub use dag::{ConsignmentDag, DagWalker, ParentRef};
+pub use dry_run::dry_run;
+pub use index::{ConsignmentIndex, IndexError, IndexSpan};
 pub(crate) use logic::OpInfo;
-pub use status::{Failure, Info, Status, Validity, Warning};
-pub use validator::{ResolveWitness, Validator, WitnessResolverError};
+pub use policy::{LocalPolicy, PolicyViolation, WitnessMetrics};
+pub use privacy::{Audience, PrivacyEntry, PrivacyItem, PrivacyReport};
+pub use report::{ReportEntry, Severity, ValidationReport};
+#[cfg(not(target_arch = "wasm32"))]
+pub use resolver_cache::system_clock;
+pub use resolver_cache::{CacheClock, CachingResolver, CACHE_DEFAULT_TTL, CACHE_FINAL_DEPTH};
+pub use schema::missing_libs;
+pub use standard::{
+    check_standardness, NonStandard, ValidationMode, GLOBAL_STANDARD_MAX_LEN,
+    OP_STANDARD_MAX_ASSIGNMENTS, OP_STANDARD_MAX_LEN,
+};
+pub use stateless::{verify_against_root, StatelessError, TransitionProof};
+pub use status::{
+    Failure, Info, ReasonParams, Status, Validity, Warning, FAILURE_CODES, INFO_CODES,
+    WARNING_CODES,
+};
+pub use store::{MemValidationStore, ValidationContext, ValidationStore, VALIDATION_CONTEXT_TAG};
+pub use validator::{ResolveWitness, ValidationHooks, Validator, WitnessResolverError};
@@ -0,0 +1,232 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parallel validation of transition bundles.
+//!
+//! Commitments of each transition bundle to its witness transaction (the MPC
+//! commitment, the deterministic bitcoin commitment and the closing of the
+//! bundle seals) do not depend on other bundles, so
+//! [`Validator::validate_parallel`] validates them concurrently on the rayon
+//! thread pool. Witnesses of all the bundles (including alternative
+//! witnesses) are resolved concurrently before the validation as well.
+//!
+//! Bundles closed with close method plugins, which are not required to be
+//! thread-safe, and the validation of the contract state, which follows the
+//! order of the operations in the contract history, run in a single thread.
+//! Statuses of the bundles are merged in the same order as by
+//! [`Validator::validate`], thus the final status is the same as the one
+//! returned by [`Validator::validate`] with the same resolver.
+
+use rayon::prelude::*;
+
+use super::prefetch::{consignment_witnesses, PrefetchedResolver};
+use super::validator::{BundleCheck, CommitmentChecker};
+use super::{CloseMethodRegistry, ConsignmentApi, ResolveWitness, Status, Validator};
+
+impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness + Sync>
+    Validator<'consignment, 'resolver, C, R>
+{
+    /// Validation procedure which works like [`Validator::validate`], but
+    /// resolves witnesses and validates commitments of the consignment
+    /// bundles concurrently.
+    ///
+    /// The returned status is deterministic and doesn't depend on the order
+    /// in which the witnesses were resolved and the bundles were validated.
+    pub fn validate_parallel(
+        consignment: &'consignment C,
+        resolver: &'resolver R,
+        testnet: bool,
+    ) -> Status {
+        let resolved = consignment_witnesses(consignment)
+            .into_par_iter()
+            .map(|(witness_id, hint)| {
+                let (tx, ord) = match hint {
+                    Some(hint) => (
+                        resolver.resolve_pub_witness_hinted(witness_id, hint),
+                        resolver.resolve_witness_ord_hinted(witness_id, hint),
+                    ),
+                    None => (
+                        resolver.resolve_pub_witness(witness_id),
+                        resolver.resolve_witness_ord(witness_id),
+                    ),
+                };
+                (witness_id, (tx, ord))
+            })
+            .collect();
+        let prefetched = PrefetchedResolver {
+            inner: resolver,
+            resolved,
+            tips: empty!(),
+        };
+        Validator::validate_concurrently(consignment, &prefetched, testnet)
+    }
+}
+
+impl CommitmentChecker {
+    /// Validates commitments of the bundles concurrently, returning their
+    /// statuses in the order of the bundles.
+    pub(super) fn check_parallel(
+        &self,
+        checks: &[BundleCheck],
+        plugins: Option<&CloseMethodRegistry>,
+    ) -> Vec<Status> {
+        let statuses = checks
+            .par_iter()
+            .map(|check| check.is_builtin_method().then(|| self.check(check, None)))
+            .collect::<Vec<_>>();
+        // Plugins are not required to be thread-safe
+        statuses
+            .into_iter()
+            .zip(checks)
+            .map(|(status, check)| status.unwrap_or_else(|| self.check(check, plugins)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+    use amplify::Wrapper;
+    use bp::seals::txout::CloseMethod;
+    use strict_encoding::StrictDumb;
+    use strict_types::TypeSystem;
+
+    use super::*;
+    use crate::validation::fixtures::{TestChain, TestConsignment};
+    use crate::validation::Failure;
+    use crate::{
+        Assign, AssignmentType, Assignments, Genesis, Input, InputMap, Inputs, Occurrences,
+        Operation, Opout, OwnedStateSchema, Schema, Transition, TransitionBundle, TransitionSchema,
+        TransitionType, TypedAssigns, Vin, VoidState,
+    };
+
+    const BUNDLES: u16 = 8;
+
+    fn contract(chain: &mut TestChain) -> TestConsignment {
+        let ty = AssignmentType::with(1);
+        let transition_ty = TransitionType::with(1);
+        let mut schema = Schema::strict_dumb();
+        schema.owned_types = Confined::from_iter_unsafe([(ty, OwnedStateSchema::Declarative)]);
+        schema.genesis.assignments = Confined::from_iter_unsafe([(ty, Occurrences::OnceOrMore)]);
+        schema.transitions = Confined::from_iter_unsafe([(transition_ty, TransitionSchema {
+            inputs: Confined::from_iter_unsafe([(ty, Occurrences::Once)]),
+            assignments: Confined::from_iter_unsafe([(ty, Occurrences::Once)]),
+            ..default!()
+        })]);
+
+        let seals = (0..BUNDLES)
+            .map(|_| chain.genesis_seal())
+            .collect::<Vec<_>>();
+        let mut genesis = Genesis::strict_dumb();
+        genesis.schema_id = schema.schema_id();
+        genesis.testnet = chain.testnet();
+        let allocations = seals
+            .iter()
+            .map(|seal| Assign::revealed(*seal, VoidState::default()))
+            .collect::<Vec<_>>();
+        genesis.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            ty,
+            TypedAssigns::Declarative(Confined::try_from(allocations).unwrap()),
+        )]));
+        let contract_id = genesis.contract_id();
+        let genesis_id = genesis.id();
+
+        let mut consignment = TestConsignment::new(schema, TypeSystem::default(), genesis);
+        for (no, seal) in seals.into_iter().enumerate() {
+            let mut transition = Transition::strict_dumb();
+            transition.contract_id = contract_id;
+            transition.transition_type = transition_ty;
+            transition.inputs =
+                Inputs::try_from_inputs([Input::with(Opout::new(genesis_id, ty, no as u16))])
+                    .unwrap();
+            transition.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+                ty,
+                TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                    chain.graph_seal(1),
+                    VoidState::default(),
+                )])),
+            )]));
+            let opid = transition.id();
+            let bundle = TransitionBundle {
+                close_method: CloseMethod::OpretFirst,
+                input_map: InputMap::with(Vin::from(0u32), opid),
+                known_transitions: Confined::with((opid, transition)),
+            };
+
+            let spent = *seal.to_outpoint().as_reduced_unsafe();
+            let (witness_id, anchor) = chain.commit(contract_id, bundle.bundle_id(), [spent], 1);
+            chain.mine(witness_id);
+            consignment.add_bundle(bundle, witness_id, anchor);
+        }
+        consignment
+    }
+
+    fn is_commitment_failure(failure: &Failure) -> bool {
+        matches!(
+            failure,
+            Failure::SealNoWitnessTx(..) | Failure::MpcInvalid(..) | Failure::SealsInvalid(..)
+        )
+    }
+
+    #[test]
+    fn valid_bundles() {
+        let mut chain = TestChain::regtest();
+        let consignment = contract(&mut chain);
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(!status.failures.iter().any(is_commitment_failure), "{status}");
+        assert_eq!(status.witness_metrics.len(), BUNDLES as usize);
+        assert_eq!(Validator::validate_parallel(&consignment, &chain, chain.testnet()), status);
+    }
+
+    #[test]
+    fn deterministic_failures() {
+        let mut chain = TestChain::regtest();
+        let mut consignment = contract(&mut chain);
+        let bundle_ids = consignment.bundles.keys().copied().collect::<Vec<_>>();
+
+        let (absent_id, _) = consignment.anchors[&bundle_ids[1]];
+        chain.drop_witness(absent_id);
+        let foreign_anchor = consignment.anchors[&bundle_ids[2]].1.clone();
+        consignment.anchors.get_mut(&bundle_ids[5]).unwrap().1 = foreign_anchor;
+
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert_eq!(status.absent_pub_witnesses, vec![absent_id]);
+        assert!(status
+            .failures
+            .contains(&Failure::SealNoWitnessTx(absent_id)));
+        assert!(status.failures.iter().any(|failure| matches!(
+            failure,
+            Failure::MpcInvalid(id, ..) | Failure::SealsInvalid(id, ..) if *id == bundle_ids[5]
+        )));
+        assert_eq!(
+            status
+                .failures
+                .iter()
+                .filter(|f| is_commitment_failure(f))
+                .count(),
+            2
+        );
+        for _ in 0..4 {
+            assert_eq!(Validator::validate_parallel(&consignment, &chain, chain.testnet()), status);
+        }
+    }
+}
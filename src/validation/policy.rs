@@ -0,0 +1,108 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Local validation policies.
+//!
+//! Policies are checks defined by the software running the validation (for
+//! instance, blacklists or compliance rules) on top of the consensus rules.
+//! They can only reject operations which are valid under the consensus, and
+//! their results are reported separately from the consensus failures.
+//!
+//! Policies may also check witness transactions, using their size and weight
+//! reported in [`WitnessMetrics`] (for instance, to reject huge batched
+//! witnesses making future spends of the allocations costly).
+
+use bp::{ConsensusEncode, Tx, Weight};
+
+use crate::{ContractId, OpId, OpRef, XWitnessId};
+
+/// Local policy check applied to each validated contract operation.
+///
+/// Any closure with a matching signature is a policy.
+pub trait LocalPolicy {
+    /// Checks operation against the policy, returning the reason of the
+    /// rejection if the operation violates the policy.
+    fn check(&self, contract_id: ContractId, op: OpRef) -> Result<(), String>;
+
+    /// Checks witness transaction against the policy, returning the reason of
+    /// the rejection if the witness violates the policy. Rejection of a
+    /// witness rejects all operations anchored to it.
+    ///
+    /// By default, all witnesses are accepted.
+    fn check_witness(
+        &self,
+        _contract_id: ContractId,
+        _metrics: WitnessMetrics,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl<F> LocalPolicy for F
+where F: Fn(ContractId, OpRef) -> Result<(), String>
+{
+    fn check(&self, contract_id: ContractId, op: OpRef) -> Result<(), String> {
+        self(contract_id, op)
+    }
+}
+
+/// Violation of a local policy by a contract operation.
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display("operation {opid} is rejected by the local policy: {reason}")]
+pub struct PolicyViolation {
+    pub opid: OpId,
+    pub reason: String,
+}
+
+/// Size and weight of a witness transaction resolved during the validation.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display("witness {witness_id} of {size} bytes and {weight} weight units")]
+pub struct WitnessMetrics {
+    pub witness_id: XWitnessId,
+    /// Size of the consensus-serialized transaction, in bytes.
+    pub size: u32,
+    /// Transaction weight, in weight units.
+    pub weight: u32,
+}
+
+impl WitnessMetrics {
+    pub fn with(witness_id: XWitnessId, tx: &Tx) -> Self {
+        WitnessMetrics {
+            witness_id,
+            size: tx.consensus_serialize().len() as u32,
+            weight: tx.weight_units().into_u32(),
+        }
+    }
+
+    /// Virtual size of the transaction, in virtual bytes.
+    pub fn vsize(&self) -> u32 { self.weight.div_ceil(4) }
+}
@@ -0,0 +1,142 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Witnesses resolved before the validation.
+//!
+//! The validator passes all witnesses of the consignment to the resolver at
+//! once, allowing it to use batch requests. Validation entry points which
+//! resolve witnesses concurrently (with rayon or with an asynchronous
+//! resolver) collect the witnesses of the consignment first, resolve them all
+//! at once, and then run the validator against a `PrefetchedResolver`
+//! answering from the resolved data.
+
+use std::collections::BTreeMap;
+
+use super::{CheckedConsignment, ConsignmentApi, WitnessHint};
+#[cfg(any(feature = "parallel", feature = "async"))]
+use super::{ResolveWitness, WitnessResolverError};
+use crate::XWitnessId;
+#[cfg(any(feature = "parallel", feature = "async"))]
+use crate::{Layer1, WitnessOrd, XWitnessTx};
+
+#[cfg(any(feature = "parallel", feature = "async"))]
+pub(super) type Resolved =
+    (Result<XWitnessTx, WitnessResolverError>, Result<WitnessOrd, WitnessResolverError>);
+
+/// Collects witnesses the validator will resolve for the consignment, with
+/// the position hints provided by the consignment.
+pub(super) fn consignment_witnesses<C: ConsignmentApi>(
+    consignment: &C,
+) -> BTreeMap<XWitnessId, Option<WitnessHint>> {
+    let checked = CheckedConsignment::new(consignment);
+    let mut witness_ids = vec![];
+    for bundle_id in checked.bundle_ids() {
+        if let Some((witness_id, _)) = checked.anchor(bundle_id) {
+            witness_ids.push(witness_id);
+        }
+        witness_ids.extend(checked.alt_anchors(bundle_id).into_iter().map(|(id, _)| id));
+        if let Some(substitution) = checked.witness_substitution(bundle_id) {
+            if checked.replaced_witness(substitution.replaced).is_none() {
+                witness_ids.push(substitution.replaced);
+            }
+        }
+    }
+    witness_ids
+        .into_iter()
+        .map(|witness_id| (witness_id, checked.witness_hint(witness_id)))
+        .collect()
+}
+
+/// Resolver which doesn't know any witness.
+#[cfg(feature = "async")]
+pub(super) struct NoResolver;
+
+#[cfg(feature = "async")]
+impl ResolveWitness for NoResolver {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        Err(WitnessResolverError::Unknown(witness_id))
+    }
+}
+
+/// Resolver answering from the witnesses resolved before the validation,
+/// falling back to the inner resolver for other witnesses.
+#[cfg(any(feature = "parallel", feature = "async"))]
+pub(super) struct PrefetchedResolver<'resolver, R: ResolveWitness> {
+    pub(super) inner: &'resolver R,
+    pub(super) resolved: BTreeMap<XWitnessId, Resolved>,
+    pub(super) tips: BTreeMap<Layer1, u32>,
+}
+
+#[cfg(any(feature = "parallel", feature = "async"))]
+impl<'resolver, R: ResolveWitness> ResolveWitness for PrefetchedResolver<'resolver, R> {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        match self.resolved.get(&witness_id) {
+            Some((tx, _)) => tx.clone(),
+            None => self.inner.resolve_pub_witness(witness_id),
+        }
+    }
+
+    fn resolve_pub_witness_hinted(
+        &self,
+        witness_id: XWitnessId,
+        hint: WitnessHint,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        match self.resolved.get(&witness_id) {
+            Some((tx, _)) => tx.clone(),
+            None => self.inner.resolve_pub_witness_hinted(witness_id, hint),
+        }
+    }
+
+    fn resolve_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        match self.resolved.get(&witness_id) {
+            Some((_, ord)) => ord.clone(),
+            None => self.inner.resolve_witness_ord(witness_id),
+        }
+    }
+
+    fn resolve_witness_ord_hinted(
+        &self,
+        witness_id: XWitnessId,
+        hint: WitnessHint,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        match self.resolved.get(&witness_id) {
+            Some((_, ord)) => ord.clone(),
+            None => self.inner.resolve_witness_ord_hinted(witness_id, hint),
+        }
+    }
+
+    fn resolve_tip_height(&self, layer1: Layer1) -> Option<u32> {
+        match self.tips.get(&layer1) {
+            Some(height) => Some(*height),
+            None => self.inner.resolve_tip_height(layer1),
+        }
+    }
+}
@@ -0,0 +1,329 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Privacy summary of a consignment.
+//!
+//! Before sharing a consignment (for instance, as a proof of ownership for a
+//! third party), a wallet may need to show which data the consignment leaks.
+//! [`PrivacyReport`] lists seals and owned state of all operations in the
+//! consignment as revealed or concealed, together with the data which can't
+//! be concealed at all: operation metadata and global state, which are seen
+//! by the consignment recipient, and witness transactions, which are seen by
+//! anyone observing the blockchain.
+
+use super::{ConsignmentApi, ConsignmentDag};
+use crate::schema::{GlobalStateType, MetaType};
+use crate::{
+    Assign, AssignmentType, ExposedState, GraphSeal, OpId, Operation, Opout, StateType,
+    TypedAssigns, XWitnessId,
+};
+
+/// Part of the consignment data subject to the privacy analysis.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum PrivacyItem {
+    /// seal of the assignment {0}.
+    #[display("seal of {0}")]
+    Seal(Opout),
+
+    /// state of the assignment {0}.
+    #[display("{1} state of {0}")]
+    State(Opout, StateType),
+
+    /// metadata of the operation.
+    #[display("metadata {1} of {0}")]
+    Metadata(OpId, MetaType),
+
+    /// global state of the operation.
+    #[display("global state {1} of {0}")]
+    Global(OpId, GlobalStateType),
+
+    /// witness transaction of a transition bundle.
+    #[display("witness {0}")]
+    Witness(XWitnessId),
+}
+
+/// Party which is able to see a piece of the consignment data.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display(lowercase)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum Audience {
+    /// Anyone the consignment is shared with.
+    Recipient,
+    /// Anyone observing the blockchain.
+    Public,
+}
+
+/// Privacy status of a piece of the consignment data.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct PrivacyEntry {
+    pub item: PrivacyItem,
+    pub revealed: bool,
+    pub audience: Audience,
+}
+
+/// Report on the data revealed and concealed by a consignment.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct PrivacyReport {
+    pub entries: Vec<PrivacyEntry>,
+}
+
+impl PrivacyReport {
+    /// Analyzes all operations reachable from the consignment bundles and
+    /// witnesses of the bundles.
+    pub fn with(consignment: &impl ConsignmentApi) -> Self {
+        let mut report = PrivacyReport::default();
+        for opid in ConsignmentDag::with(consignment).operations() {
+            let Some(op) = consignment.operation(opid) else {
+                continue;
+            };
+            for ty in op.metadata().keys() {
+                report.push(PrivacyItem::Metadata(opid, *ty), true, Audience::Recipient);
+            }
+            for ty in op.globals().keys() {
+                report.push(PrivacyItem::Global(opid, *ty), true, Audience::Recipient);
+            }
+            for (ty, assigns) in op.assignments().flat() {
+                match assigns {
+                    TypedAssigns::Declarative(a) => report.push_assigns(opid, ty, &a, None),
+                    TypedAssigns::Fungible(a) => {
+                        report.push_assigns(opid, ty, &a, Some(StateType::Fungible))
+                    }
+                    TypedAssigns::Structured(a) => {
+                        report.push_assigns(opid, ty, &a, Some(StateType::Structured))
+                    }
+                    TypedAssigns::Attachment(a) => {
+                        report.push_assigns(opid, ty, &a, Some(StateType::Attachment))
+                    }
+                }
+            }
+        }
+        for bundle_id in consignment.bundle_ids() {
+            if let Some((witness_id, _)) = consignment.anchor(bundle_id) {
+                report.push(PrivacyItem::Witness(witness_id), true, Audience::Public);
+            }
+        }
+        report
+    }
+
+    fn push(&mut self, item: PrivacyItem, revealed: bool, audience: Audience) {
+        let entry = PrivacyEntry {
+            item,
+            revealed,
+            audience,
+        };
+        if !self.entries.contains(&entry) {
+            self.entries.push(entry);
+        }
+    }
+
+    fn push_assigns<State: ExposedState>(
+        &mut self,
+        opid: OpId,
+        ty: AssignmentType,
+        assigns: &[Assign<State, GraphSeal>],
+        state_type: Option<StateType>,
+    ) {
+        for (no, assign) in assigns.iter().enumerate() {
+            let opout = Opout::new(opid, ty, no as u16);
+            let seal_revealed = assign.revealed_seal().is_some();
+            self.push(PrivacyItem::Seal(opout), seal_revealed, Audience::Recipient);
+            // Declarative state carries no data which can be concealed
+            if let Some(state_type) = state_type {
+                let state_revealed = assign.as_revealed_state().is_some();
+                let item = PrivacyItem::State(opout, state_type);
+                self.push(item, state_revealed, Audience::Recipient);
+            }
+        }
+    }
+
+    /// Returns entries for the data revealed by the consignment.
+    pub fn revealed(&self) -> impl Iterator<Item = &PrivacyEntry> {
+        self.entries.iter().filter(|entry| entry.revealed)
+    }
+
+    /// Returns entries for the data concealed by the consignment.
+    pub fn concealed(&self) -> impl Iterator<Item = &PrivacyEntry> {
+        self.entries.iter().filter(|entry| !entry.revealed)
+    }
+
+    /// Returns entries for the data visible to the given audience.
+    pub fn visible_to(&self, audience: Audience) -> impl Iterator<Item = &PrivacyEntry> {
+        self.revealed()
+            .filter(move |entry| entry.audience == audience)
+    }
+
+    /// Counts revealed and concealed state of the given type, returning a
+    /// tuple of the counts.
+    pub fn state_counts(&self, state_type: StateType) -> (usize, usize) {
+        self.count(|item| matches!(item, PrivacyItem::State(_, ty) if *ty == state_type))
+    }
+
+    /// Counts revealed and concealed seals, returning a tuple of the counts.
+    pub fn seal_counts(&self) -> (usize, usize) {
+        self.count(|item| matches!(item, PrivacyItem::Seal(_)))
+    }
+
+    fn count(&self, filter: impl Fn(&PrivacyItem) -> bool) -> (usize, usize) {
+        self.entries
+            .iter()
+            .filter(|entry| filter(&entry.item))
+            .fold((0, 0), |(revealed, concealed), entry| {
+                if entry.revealed {
+                    (revealed + 1, concealed)
+                } else {
+                    (revealed, concealed + 1)
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::{Confined, SmallBlob};
+    use amplify::Wrapper;
+    use bp::seals::txout::CloseMethod;
+    use strict_encoding::StrictDumb;
+    use strict_types::TypeSystem;
+
+    use super::*;
+    use crate::validation::fixtures::{TestChain, TestConsignment};
+    use crate::{
+        AssetTag, Assignments, DataState, Genesis, Input, InputMap, Inputs, MetaValue,
+        RevealedValue, Schema, Transition, TransitionBundle, Vin, VoidState,
+    };
+
+    const RIGHT: AssignmentType = AssignmentType::with(1);
+    const ASSET: AssignmentType = AssignmentType::with(2);
+
+    #[test]
+    fn report() {
+        let mut chain = TestChain::regtest();
+        let tag = AssetTag::strict_dumb();
+        let mut genesis = Genesis::strict_dumb();
+        genesis
+            .metadata
+            .add_value(MetaType::with(1), MetaValue::from(SmallBlob::from_iter_unsafe([1])))
+            .unwrap();
+        genesis
+            .globals
+            .add_state(GlobalStateType::with(1), DataState::from(SmallBlob::from_iter_unsafe([1])))
+            .unwrap();
+        let right = Assign::revealed(chain.genesis_seal(), VoidState::default());
+        let allocations = vec![
+            Assign::revealed(chain.genesis_seal(), RevealedValue::new_random_blinding(100, tag)),
+            Assign::revealed(chain.genesis_seal(), RevealedValue::new_random_blinding(200, tag))
+                .conceal_seal(),
+            Assign::revealed(chain.genesis_seal(), RevealedValue::new_random_blinding(300, tag))
+                .conceal_state(),
+        ];
+        genesis.assignments = Assignments::from_inner(
+            Confined::try_from(bmap! {
+                RIGHT => TypedAssigns::Declarative(Confined::from_iter_unsafe([right])),
+                ASSET => TypedAssigns::Fungible(Confined::try_from(allocations).unwrap()),
+            })
+            .unwrap(),
+        );
+        let genesis_id = genesis.id();
+
+        // Transfer to a recipient who provided a concealed seal
+        let output =
+            Assign::revealed(chain.graph_seal(1), RevealedValue::new_random_blinding(100, tag))
+                .conceal_seal();
+        let mut transition = Transition::strict_dumb();
+        transition.inputs =
+            Inputs::try_from_inputs([Input::with(Opout::new(genesis_id, ASSET, 0))]).unwrap();
+        transition.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            ASSET,
+            TypedAssigns::Fungible(Confined::from_iter_unsafe([output])),
+        )]));
+        let opid = transition.id();
+        let bundle = TransitionBundle {
+            close_method: CloseMethod::OpretFirst,
+            input_map: InputMap::with(Vin::from(0u32), opid),
+            known_transitions: Confined::with((opid, transition)),
+        };
+        let spent = chain.outpoint();
+        let (witness_id, anchor) =
+            chain.commit(genesis.contract_id(), bundle.bundle_id(), [spent], 2);
+        let mut consignment =
+            TestConsignment::new(Schema::strict_dumb(), TypeSystem::default(), genesis);
+        consignment.add_bundle(bundle, witness_id, anchor);
+
+        let report = consignment.privacy_report();
+        let entry = |item, revealed, audience| PrivacyEntry {
+            item,
+            revealed,
+            audience,
+        };
+        for expected in [
+            entry(PrivacyItem::Metadata(genesis_id, MetaType::with(1)), true, Audience::Recipient),
+            entry(
+                PrivacyItem::Global(genesis_id, GlobalStateType::with(1)),
+                true,
+                Audience::Recipient,
+            ),
+            entry(PrivacyItem::Seal(Opout::new(genesis_id, RIGHT, 0)), true, Audience::Recipient),
+            entry(PrivacyItem::Seal(Opout::new(genesis_id, ASSET, 1)), false, Audience::Recipient),
+            entry(
+                PrivacyItem::State(Opout::new(genesis_id, ASSET, 2), StateType::Fungible),
+                false,
+                Audience::Recipient,
+            ),
+            entry(PrivacyItem::Seal(Opout::new(opid, ASSET, 0)), false, Audience::Recipient),
+            entry(PrivacyItem::Witness(witness_id), true, Audience::Public),
+        ] {
+            assert!(report.entries.contains(&expected), "{expected:?} is absent");
+        }
+        // Declarative state carries no data
+        assert!(!report
+            .entries
+            .iter()
+            .any(|entry| matches!(entry.item, PrivacyItem::State(opout, _) if opout.ty == RIGHT)));
+
+        assert_eq!(report.seal_counts(), (3, 2));
+        assert_eq!(report.state_counts(StateType::Fungible), (3, 1));
+        assert_eq!(report.state_counts(StateType::Structured), (0, 0));
+        assert_eq!(report.concealed().count(), 3);
+        assert_eq!(report.revealed().count(), report.entries.len() - 3);
+        let public = report.visible_to(Audience::Public).collect::<Vec<_>>();
+        assert_eq!(public, vec![&entry(PrivacyItem::Witness(witness_id), true, Audience::Public)]);
+    }
+}
@@ -0,0 +1,190 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Machine-readable validation report.
+//!
+//! Validation [`Status`] lists failures, warnings and information messages
+//! as Rust enums with human-oriented descriptions. Integrators working with
+//! other languages or providing localized user interfaces use the
+//! [`ValidationReport`] instead, which is produced by [`Status::to_report`]
+//! and can be serialized into JSON or other formats with serde. Each report
+//! entry has a stable numeric code and reason key, which can be used for
+//! branching on the reason and for localizing messages with the entry
+//! parameters.
+
+use std::collections::BTreeMap;
+
+use super::{Failure, Info, ReasonParams, Status, Validity, Warning};
+
+/// Severity of a validation report entry.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display(lowercase)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum Severity {
+    Failure,
+    Warning,
+    Info,
+}
+
+/// Entry of the validation report.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ReportEntry {
+    pub severity: Severity,
+    /// Stable numeric code of the entry (see [`Failure::code`]).
+    pub code: u16,
+    /// Stable reason key of the entry (see [`Failure::reason_key`]).
+    pub key: String,
+    /// Message describing the entry in English.
+    pub message: String,
+    /// Parameters for formatting a localized message.
+    pub params: BTreeMap<String, String>,
+}
+
+impl ReportEntry {
+    fn with(
+        severity: Severity,
+        code: u16,
+        key: &str,
+        message: String,
+        params: ReasonParams,
+    ) -> Self {
+        ReportEntry {
+            severity,
+            code,
+            key: key.to_owned(),
+            message,
+            params: params
+                .into_iter()
+                .map(|(name, value)| (name.to_owned(), value))
+                .collect(),
+        }
+    }
+}
+
+impl From<&Failure> for ReportEntry {
+    fn from(failure: &Failure) -> Self {
+        let key = failure.reason_key();
+        Self::with(Severity::Failure, failure.code(), key, failure.to_string(), failure.params())
+    }
+}
+
+impl From<&Warning> for ReportEntry {
+    fn from(warning: &Warning) -> Self {
+        let key = warning.reason_key();
+        Self::with(Severity::Warning, warning.code(), key, warning.to_string(), warning.params())
+    }
+}
+
+impl From<&Info> for ReportEntry {
+    fn from(info: &Info) -> Self {
+        Self::with(Severity::Info, info.code(), info.reason_key(), info.to_string(), info.params())
+    }
+}
+
+/// Machine-readable report of the validation status.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ValidationReport {
+    pub validity: Validity,
+    /// Failures, warnings (including the ones rejected by the validation
+    /// config) and information messages, in this order.
+    pub entries: Vec<ReportEntry>,
+}
+
+impl ValidationReport {
+    /// Iterates over the entries of the given severity.
+    pub fn by_severity(&self, severity: Severity) -> impl Iterator<Item = &ReportEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.severity == severity)
+    }
+
+    /// Detects whether the report contains an entry with the given code.
+    pub fn has_code(&self, code: u16) -> bool {
+        self.entries.iter().any(|entry| entry.code == code)
+    }
+}
+
+impl Status {
+    /// Converts the status into a machine-readable report, covering
+    /// failures, warnings and information messages.
+    pub fn to_report(&self) -> ValidationReport {
+        let entries = self
+            .failures
+            .iter()
+            .map(ReportEntry::from)
+            .chain(self.warnings.iter().map(ReportEntry::from))
+            .chain(self.rejected_warnings.iter().map(ReportEntry::from))
+            .chain(self.info.iter().map(ReportEntry::from))
+            .collect();
+        ValidationReport {
+            validity: self.validity(),
+            entries,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::validation::FAILURE_CODES;
+    use crate::{schema, OpId};
+
+    #[test]
+    fn report_entries() {
+        let opid = OpId::from([0xA5; 32]);
+        let mut status = Status::new();
+        status.add_failure(Failure::SchemaUnknownTransitionType(
+            opid,
+            schema::TransitionType::with(7),
+        ));
+        status.add_warning(Warning::Custom(s!("custom warning")));
+
+        let report = status.to_report();
+        assert_eq!(report.validity, Validity::Invalid);
+        assert_eq!(report.entries.len(), 2);
+
+        let failure = &report.entries[0];
+        assert_eq!(failure.severity, Severity::Failure);
+        assert!(FAILURE_CODES.contains(&failure.code));
+        assert_eq!(failure.key, "schema.unknown_transition_type");
+        assert_eq!(failure.params.get("op_type"), Some(&s!("0x0007")));
+
+        let warning = report.by_severity(Severity::Warning).next().unwrap();
+        assert_eq!(warning.code, 2000);
+        assert_eq!(warning.message, "custom warning");
+        assert!(report.has_code(2000));
+    }
+}
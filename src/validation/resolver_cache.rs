@@ -0,0 +1,374 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Caching of the witness resolver results.
+//!
+//! Long-running nodes validate many consignments referencing the same
+//! witnesses. [`CachingResolver`] wraps a [`ResolveWitness`] implementation
+//! (like an Electrum or Esplora client) and keeps its results: witness
+//! transactions and positions of the witnesses which are deep enough in the
+//! blockchain are cached permanently, while positions of the witnesses in the
+//! mempool or in the recent blocks expire after a configurable time. Since
+//! the positions may change with chain reorganizations, software receiving
+//! reorg notifications must report them with [`CachingResolver::reorg`].
+//!
+//! The expiration time is measured with a [`CacheClock`], which defaults to
+//! the system time. WebAssembly targets have no system time, so unless a
+//! clock is provided with [`CachingResolver::with_clock`], positions which
+//! are not final are not cached there.
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{ResolveWitness, WitnessHint, WitnessResolverError};
+use crate::{Layer1, WitnessOrd, XWitnessId, XWitnessTx};
+
+/// Default number of confirmations after which a witness position is cached
+/// permanently.
+pub const CACHE_FINAL_DEPTH: u32 = 6;
+
+/// Default time after which non-final witness positions expire.
+pub const CACHE_DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+/// Source of the current time for the expiration of the cached witness
+/// positions, returning the time elapsed since an arbitrary fixed moment.
+pub type CacheClock = fn() -> Duration;
+
+/// Clock measuring the system time since the Unix epoch.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn system_clock() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) const DEFAULT_CLOCK: Option<CacheClock> = Some(system_clock);
+#[cfg(target_arch = "wasm32")]
+pub(super) const DEFAULT_CLOCK: Option<CacheClock> = None;
+
+/// Resolver decorator caching results of the inner resolver.
+#[derive(Debug)]
+pub struct CachingResolver<R: ResolveWitness> {
+    inner: R,
+    ttl: Duration,
+    final_depth: u32,
+    clock: Option<CacheClock>,
+    tip_height: Cell<u32>,
+    txes: RefCell<BTreeMap<XWitnessId, XWitnessTx>>,
+    ords: RefCell<BTreeMap<XWitnessId, (WitnessOrd, Option<Duration>)>>,
+}
+
+impl<R: ResolveWitness> CachingResolver<R> {
+    pub fn new(inner: R) -> Self {
+        CachingResolver {
+            inner,
+            ttl: CACHE_DEFAULT_TTL,
+            final_depth: CACHE_FINAL_DEPTH,
+            clock: DEFAULT_CLOCK,
+            tip_height: Cell::new(0),
+            txes: empty!(),
+            ords: empty!(),
+        }
+    }
+
+    /// Sets time after which positions of the witnesses in the mempool and
+    /// of the witnesses with less than the final number of confirmations
+    /// expire.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets number of confirmations after which a witness position is cached
+    /// permanently.
+    pub fn with_final_depth(mut self, depth: u32) -> Self {
+        self.final_depth = depth;
+        self
+    }
+
+    /// Sets clock measuring the expiration time of the cached positions,
+    /// which is required on WebAssembly targets.
+    pub fn with_clock(mut self, clock: CacheClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn inner(&self) -> &R { &self.inner }
+
+    /// Provides mutable access to the inner resolver. Positions cached before
+    /// remain in the cache, unless invalidated.
+    pub fn inner_mut(&mut self) -> &mut R { &mut self.inner }
+
+    pub fn into_inner(self) -> R { self.inner }
+
+    /// Updates height of the blockchain tip, which determines whether cached
+    /// witness positions are final.
+    ///
+    /// Until the tip is reported, all positions are treated as non-final.
+    pub fn set_tip_height(&self, height: u32) { self.tip_height.set(height); }
+
+    /// Invalidates cached positions of the witnesses mined at the given
+    /// height or above, and positions of the witnesses in the mempool,
+    /// following a chain reorganization at the height.
+    pub fn reorg(&self, height: u32) {
+        self.ords.borrow_mut().retain(|_, (ord, _)| match ord {
+            WitnessOrd::OnChain(pos) => pos.height().get() < height,
+            WitnessOrd::OffChain => false,
+        });
+        self.tip_height
+            .set(self.tip_height.get().min(height.saturating_sub(1)));
+    }
+
+    /// Removes the cached data of a single witness.
+    pub fn invalidate(&self, witness_id: XWitnessId) {
+        self.txes.borrow_mut().remove(&witness_id);
+        self.ords.borrow_mut().remove(&witness_id);
+    }
+
+    /// Removes all cached data.
+    pub fn clear(&self) {
+        self.txes.borrow_mut().clear();
+        self.ords.borrow_mut().clear();
+    }
+
+    fn is_final(&self, ord: WitnessOrd) -> bool {
+        match ord {
+            WitnessOrd::OnChain(pos) => {
+                let (tip, height) = (self.tip_height.get(), pos.height().get());
+                tip >= height && tip - height + 1 >= self.final_depth
+            }
+            WitnessOrd::OffChain => false,
+        }
+    }
+
+    fn cache_tx(
+        &self,
+        witness_id: XWitnessId,
+        res: Result<XWitnessTx, WitnessResolverError>,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        // Transaction data never change, thus they are cached permanently
+        if let Ok(tx) = &res {
+            self.txes.borrow_mut().insert(witness_id, tx.clone());
+        }
+        res
+    }
+
+    fn cached_ord(&self, witness_id: XWitnessId) -> Option<WitnessOrd> {
+        let mut ords = self.ords.borrow_mut();
+        let (ord, expiry) = *ords.get(&witness_id)?;
+        let expired = match (expiry, self.clock) {
+            (None, _) => false,
+            (Some(expiry), Some(clock)) => expiry <= clock(),
+            (Some(_), None) => true,
+        };
+        if expired {
+            ords.remove(&witness_id);
+            return None;
+        }
+        Some(ord)
+    }
+
+    fn cache_ord(
+        &self,
+        witness_id: XWitnessId,
+        res: Result<WitnessOrd, WitnessResolverError>,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        // Errors are not cached since the witness may become known later
+        let Ok(ord) = res else {
+            return res;
+        };
+        let expiry = match (self.is_final(ord), self.clock) {
+            (true, _) => None,
+            (false, Some(clock)) => Some(clock() + self.ttl),
+            // Without a clock the position can't expire, thus it is not cached
+            (false, None) => return res,
+        };
+        self.ords.borrow_mut().insert(witness_id, (ord, expiry));
+        res
+    }
+}
+
+impl<R: ResolveWitness> ResolveWitness for CachingResolver<R> {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        if let Some(tx) = self.txes.borrow().get(&witness_id) {
+            return Ok(tx.clone());
+        }
+        self.cache_tx(witness_id, self.inner.resolve_pub_witness(witness_id))
+    }
+
+    fn resolve_pub_witness_hinted(
+        &self,
+        witness_id: XWitnessId,
+        hint: WitnessHint,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        if let Some(tx) = self.txes.borrow().get(&witness_id) {
+            return Ok(tx.clone());
+        }
+        self.cache_tx(witness_id, self.inner.resolve_pub_witness_hinted(witness_id, hint))
+    }
+
+    fn resolve_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        if let Some(ord) = self.cached_ord(witness_id) {
+            return Ok(ord);
+        }
+        self.cache_ord(witness_id, self.inner.resolve_witness_ord(witness_id))
+    }
+
+    fn resolve_witness_ord_hinted(
+        &self,
+        witness_id: XWitnessId,
+        hint: WitnessHint,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        if let Some(ord) = self.cached_ord(witness_id) {
+            return Ok(ord);
+        }
+        self.cache_ord(witness_id, self.inner.resolve_witness_ord_hinted(witness_id, hint))
+    }
+
+    fn resolve_tip_height(&self, layer1: Layer1) -> Option<u32> {
+        // Chain tip changes with every block, thus it is never cached
+        self.inner.resolve_tip_height(layer1)
+    }
+
+    fn resolve_pub_witnesses(
+        &self,
+        witnesses: &BTreeMap<XWitnessId, Option<WitnessHint>>,
+    ) -> BTreeMap<XWitnessId, Result<XWitnessTx, WitnessResolverError>> {
+        let mut resolved = BTreeMap::new();
+        let mut missing = BTreeMap::new();
+        for (witness_id, hint) in witnesses {
+            match self.txes.borrow().get(witness_id) {
+                Some(tx) => {
+                    resolved.insert(*witness_id, Ok(tx.clone()));
+                }
+                None => {
+                    missing.insert(*witness_id, *hint);
+                }
+            }
+        }
+        if missing.is_empty() {
+            return resolved;
+        }
+        for (witness_id, res) in self.inner.resolve_pub_witnesses(&missing) {
+            resolved.insert(witness_id, self.cache_tx(witness_id, res));
+        }
+        resolved
+    }
+
+    fn resolve_witness_ords(
+        &self,
+        witnesses: &BTreeMap<XWitnessId, Option<WitnessHint>>,
+    ) -> BTreeMap<XWitnessId, Result<WitnessOrd, WitnessResolverError>> {
+        let mut resolved = BTreeMap::new();
+        let mut missing = BTreeMap::new();
+        for (witness_id, hint) in witnesses {
+            match self.cached_ord(*witness_id) {
+                Some(ord) => {
+                    resolved.insert(*witness_id, Ok(ord));
+                }
+                None => {
+                    missing.insert(*witness_id, *hint);
+                }
+            }
+        }
+        if missing.is_empty() {
+            return resolved;
+        }
+        for (witness_id, res) in self.inner.resolve_witness_ords(&missing) {
+            resolved.insert(witness_id, self.cache_ord(witness_id, res));
+        }
+        resolved
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+    use crate::validation::fixtures::TestChain;
+    use crate::{BundleId, ContractId};
+
+    #[test]
+    fn positions_expire_and_finalize() {
+        let mut chain = TestChain::regtest();
+        let funding = chain.outpoint();
+        let (witness_id, _) =
+            chain.commit(ContractId::from([0x11; 32]), BundleId::from([0x22; 32]), [funding], 1);
+        let mut cache = CachingResolver::new(chain).with_final_depth(1);
+        assert_eq!(cache.resolve_witness_ord(witness_id).ok(), Some(WitnessOrd::OffChain));
+
+        let ord = cache.inner_mut().mine(witness_id);
+        // The mempool position is still cached
+        assert_eq!(cache.resolve_witness_ord(witness_id).ok(), Some(WitnessOrd::OffChain));
+        cache.invalidate(witness_id);
+
+        let WitnessOrd::OnChain(pos) = ord else {
+            unreachable!()
+        };
+        cache.set_tip_height(pos.height().get());
+        assert_eq!(cache.resolve_witness_ord(witness_id).ok(), Some(ord));
+        assert_eq!(cache.ords.borrow()[&witness_id].1, None);
+
+        cache.reorg(pos.height().get());
+        assert!(cache.ords.borrow().is_empty());
+    }
+
+    static NOW: AtomicU64 = AtomicU64::new(0);
+
+    fn test_clock() -> Duration { Duration::from_secs(NOW.load(Ordering::Relaxed)) }
+
+    #[test]
+    fn injected_clock() {
+        let mut chain = TestChain::regtest();
+        let funding = chain.outpoint();
+        let (witness_id, _) =
+            chain.commit(ContractId::from([0x11; 32]), BundleId::from([0x22; 32]), [funding], 1);
+        let mut cache = CachingResolver::new(chain)
+            .with_ttl(Duration::from_secs(10))
+            .with_clock(test_clock);
+        assert_eq!(cache.resolve_witness_ord(witness_id).ok(), Some(WitnessOrd::OffChain));
+
+        let ord = cache.inner_mut().mine(witness_id);
+        NOW.store(9, Ordering::Relaxed);
+        assert_eq!(cache.resolve_witness_ord(witness_id).ok(), Some(WitnessOrd::OffChain));
+        NOW.store(10, Ordering::Relaxed);
+        assert_eq!(cache.resolve_witness_ord(witness_id).ok(), Some(ord));
+        assert_eq!(cache.ords.borrow()[&witness_id].1, Some(Duration::from_secs(20)));
+
+        // Without a clock non-final positions are not cached
+        cache.clock = None;
+        cache.invalidate(witness_id);
+        assert_eq!(cache.resolve_witness_ord(witness_id).ok(), Some(ord));
+        assert!(cache.ords.borrow().is_empty());
+    }
+}
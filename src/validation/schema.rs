@@ -20,9 +20,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeSet;
+
+use aluvm::library::LibId;
 use strict_types::TypeSystem;
 
-use crate::{validation, OpFullType, OpSchema, OwnedStateSchema, Schema, TransitionType};
+use crate::schema::NamesError;
+use crate::validation::Scripts;
+use crate::{
+    validation, OpFullType, OpSchema, OwnedStateSchema, Schema, SchemaFlags, TransitionType,
+};
 
 impl Schema {
     pub fn verify(&self, types: &TypeSystem) -> validation::Status {
@@ -39,6 +46,16 @@ impl Schema {
         if self.transitions.contains_key(&TransitionType::BLANK) {
             status.add_failure(validation::Failure::SchemaBlankTransitionRedefined);
         }
+        let unknown = self.flags.unknown();
+        if unknown != SchemaFlags::default() {
+            status.add_failure(validation::Failure::SchemaFlagsUnknown(unknown));
+        }
+        if !self.witness_constraints.is_valid() {
+            status.add_failure(validation::Failure::SchemaWitnessConstraintsInvalid);
+        }
+        if let Err(NamesError::UnknownType(name)) = self.names.verify(self) {
+            status.add_failure(validation::Failure::SchemaNameUnknownType(name));
+        }
 
         for (type_id, sem_id) in &self.meta_types {
             if !types.contains_key(sem_id) {
@@ -68,6 +85,21 @@ impl Schema {
         status
     }
 
+    /// Checks that all libraries referenced by the schema scripts, together
+    /// with the libraries called by them, are present in the provided library
+    /// set, reporting each of the missing libraries.
+    ///
+    /// The check covers scripts of all operation types defined by the schema.
+    /// The validator requires only the libraries of the scripts which are run
+    /// during the validation of a specific consignment.
+    pub fn verify_libs(&self, scripts: &Scripts) -> validation::Status {
+        let mut status = validation::Status::new();
+        for lib_id in missing_libs(self.required_libs(), scripts) {
+            status.add_failure(validation::Failure::SchemaLibUnknown(lib_id));
+        }
+        status
+    }
+
     fn verify_operation(&self, op_type: OpFullType, schema: &impl OpSchema) -> validation::Status {
         let mut status = validation::Status::new();
 
@@ -106,3 +138,24 @@ impl Schema {
         status
     }
 }
+
+/// Returns libraries which are absent in the library set, while being
+/// required to run scripts from the `libs`, either directly or by being called
+/// from other required libraries.
+pub fn missing_libs(libs: impl IntoIterator<Item = LibId>, scripts: &Scripts) -> BTreeSet<LibId> {
+    let mut missing = BTreeSet::new();
+    let mut visited = BTreeSet::new();
+    let mut queue = libs.into_iter().collect::<Vec<_>>();
+    while let Some(lib_id) = queue.pop() {
+        if !visited.insert(lib_id) {
+            continue;
+        }
+        match scripts.get(&lib_id) {
+            Some(lib) => queue.extend(lib.libs.iter().copied()),
+            None => {
+                missing.insert(lib_id);
+            }
+        }
+    }
+    missing
+}
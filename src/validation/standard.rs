@@ -0,0 +1,200 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Standardness rules.
+//!
+//! Similarly to bitcoin, RGB distinguishes consensus rules, violation of
+//! which makes a contract invalid, from standardness rules, which are stricter
+//! than the consensus and are intended for relaying nodes and services which
+//! don't want to process operations close to the consensus limits or using
+//! deprecated features. Violations of the standardness rules are reported as
+//! warnings in [`ValidationMode::Consensus`], and as local policy violations
+//! in [`ValidationMode::Standard`]: they never make a contract invalid, but
+//! may make it [`super::Validity::LocallyRejected`].
+
+use amplify::confinement::U32;
+use strict_encoding::StrictSerialize;
+
+use super::{PolicyViolation, Status, Warning};
+use crate::schema::{AssignmentType, GlobalStateType};
+use crate::{OpRef, Operation, TypedAssigns};
+
+/// Maximum size of a standard strict-serialized contract operation, in bytes.
+pub const OP_STANDARD_MAX_LEN: usize = 0x4_0000;
+
+/// Maximum number of assignments of all types in a standard operation.
+pub const OP_STANDARD_MAX_ASSIGNMENTS: usize = 0x400;
+
+/// Maximum size of a single global state entry of a standard operation, in
+/// bytes.
+pub const GLOBAL_STANDARD_MAX_LEN: usize = 0x1000;
+
+/// Strictness of the validation.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum ValidationMode {
+    /// Only consensus rules are enforced; violations of the standardness
+    /// rules are reported as warnings.
+    #[default]
+    #[display("consensus")]
+    Consensus,
+
+    /// Both consensus and standardness rules are enforced; violations of the
+    /// standardness rules are reported as local policy violations.
+    #[display("standard")]
+    Standard,
+}
+
+/// Violation of a standardness rule by a contract operation.
+#[derive(Clone, Eq, PartialEq, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(doc_comments)]
+pub enum NonStandard {
+    /// operation size of {0} bytes exceeds the standard limit.
+    OversizedOperation(usize),
+
+    /// operation has {0} assignments, exceeding the standard limit.
+    TooManyAssignments(usize),
+
+    /// global state of type {0} has entry of {1} bytes, exceeding the standard
+    /// limit.
+    OversizedGlobal(GlobalStateType, usize),
+
+    /// fungible assignment {0}/{1} has zero amount.
+    ZeroAmount(AssignmentType, u16),
+}
+
+/// Checks operation against the standardness rules.
+pub fn check_standardness(op: OpRef) -> Vec<NonStandard> {
+    let mut issues = vec![];
+
+    let len = match op {
+        OpRef::Genesis(genesis) => genesis.to_strict_serialized::<U32>().map(|data| data.len()),
+        OpRef::Transition(transition) => transition
+            .to_strict_serialized::<U32>()
+            .map(|data| data.len()),
+        OpRef::Extension(extension) => extension
+            .to_strict_serialized::<U32>()
+            .map(|data| data.len()),
+    }
+    .unwrap_or(usize::MAX);
+    if len > OP_STANDARD_MAX_LEN {
+        issues.push(NonStandard::OversizedOperation(len));
+    }
+
+    let assignments = op.assignments().flat();
+    let count: usize = assignments
+        .values()
+        .map(|typed| typed.len_u16() as usize)
+        .sum();
+    if count > OP_STANDARD_MAX_ASSIGNMENTS {
+        issues.push(NonStandard::TooManyAssignments(count));
+    }
+    for (ty, typed) in assignments.iter() {
+        let TypedAssigns::Fungible(list) = typed else {
+            continue;
+        };
+        for (no, assign) in list.iter().enumerate() {
+            if matches!(assign.as_revealed_state(), Some(state) if state.value.as_u64() == 0) {
+                issues.push(NonStandard::ZeroAmount(*ty, no as u16));
+            }
+        }
+    }
+
+    for (ty, values) in op.globals().iter() {
+        for value in values.iter() {
+            if value.len() > GLOBAL_STANDARD_MAX_LEN {
+                issues.push(NonStandard::OversizedGlobal(*ty, value.len()));
+            }
+        }
+    }
+
+    issues
+}
+
+/// Reports warnings about violations of the standardness rules as local
+/// policy violations.
+pub(crate) fn reject_non_standard(status: &mut Status) {
+    let warnings = status.warnings.drain(..).collect::<Vec<_>>();
+    for warning in warnings {
+        match warning {
+            Warning::NonStandard(opid, issue) => {
+                status.policy_violations.push(PolicyViolation {
+                    opid,
+                    reason: format!("standardness rule is violated: {issue}"),
+                });
+            }
+            warning => status.warnings.push(warning),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::SmallBlob;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::validation::Validity;
+    use crate::{DataState, Genesis, OpId};
+
+    #[test]
+    fn oversized_global() {
+        let mut genesis = Genesis::strict_dumb();
+        assert!(check_standardness(OpRef::Genesis(&genesis)).is_empty());
+
+        let ty = GlobalStateType::with(1);
+        let value = SmallBlob::try_from(vec![0u8; GLOBAL_STANDARD_MAX_LEN + 1]).unwrap();
+        genesis
+            .globals
+            .add_state(ty, DataState::from(value))
+            .unwrap();
+        assert_eq!(check_standardness(OpRef::Genesis(&genesis)), vec![
+            NonStandard::OversizedGlobal(ty, GLOBAL_STANDARD_MAX_LEN + 1)
+        ]);
+    }
+
+    #[test]
+    fn locally_rejected() {
+        let opid = OpId::from([0xA5; 32]);
+        let issue = NonStandard::TooManyAssignments(OP_STANDARD_MAX_ASSIGNMENTS + 1);
+        let mut status = Status::new();
+        status.add_warning(Warning::NonStandard(opid, issue));
+        status.add_warning(Warning::Custom("custom".to_owned()));
+        assert_eq!(status.validity(), Validity::Valid);
+
+        reject_non_standard(&mut status);
+        assert_eq!(status.warnings, vec![Warning::Custom("custom".to_owned())]);
+        assert_eq!(status.policy_violations.len(), 1);
+        assert_eq!(status.policy_violations[0].opid, opid);
+        assert_eq!(status.consensus_validity(), Validity::Valid);
+        assert_eq!(status.validity(), Validity::LocallyRejected);
+    }
+}
@@ -0,0 +1,445 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validation of a single operation against a trusted state root.
+//!
+//! Stateless validators and sharded validation services do not keep the
+//! contract history. Instead they are given a trusted [`StateRoot`] of the
+//! contract state preceding the operation (for instance, published by an
+//! indexer or computed by another shard) and merkle inclusion proofs only for
+//! the allocations the operation consumes. [`verify_against_root`] checks
+//! that each consumed allocation is a part of the state under the root, that
+//! its seal is closed by the witness the anchor commits the transition bundle
+//! into, and validates the transition against the schema, taking the proven
+//! allocations as the spent state.
+//!
+//! Since the state root commits to all allocations known to the contract
+//! history, the proofs do not demonstrate that the allocations are still
+//! unspent; double spends are prevented by the single-use seals, which the
+//! witness closes. Lock scripts of the consumed allocations are not evaluated,
+//! since the proofs do not carry the metadata of the operations which have
+//! defined them.
+
+use std::collections::BTreeMap;
+
+use amplify::confinement::Confined;
+use bp::dbc::Proof;
+use bp::seals::txout::CloseMethod;
+use bp::Outpoint;
+use commit_verify::mpc;
+use strict_types::TypeSystem;
+
+use super::dry_run::graph_seal;
+use super::logic::{push_spent_state, OpContext};
+use super::{Info, Scripts, SpentState, Status};
+use crate::{
+    Assign, DbcProof, EAnchor, Genesis, OpId, OpRef, Operation, Opout, Schema, StateLeaf,
+    StateProof, StateProofError, StateRoot, TransitionBundle, WitnessOrd, XChain, XOutpoint,
+    XOutputSeal, XWitnessId, XWitnessTx,
+};
+
+/// Errors validating an operation against a trusted state root.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum StatelessError {
+    /// input {0} consumed by the operation has no inclusion proof.
+    InputUnproven(Opout),
+
+    /// input {0} has more than one inclusion proof.
+    ProofDuplicate(Opout),
+
+    /// inclusion proof #{0} does not prove an allocation consumed by the
+    /// operation.
+    ProofUnused(usize),
+
+    /// inclusion proof for input {0} is invalid: {1}
+    ProofInvalid(Opout, StateProofError),
+
+    /// seal {1} of input {0} is not closed by the witness {2}.
+    SealNotClosed(Opout, XOutpoint, XWitnessId),
+
+    /// transition {0} is not a part of the bundle.
+    TransitionAbsent(OpId),
+
+    /// transition {0} belongs to a different contract or the genesis doesn't
+    /// match the schema.
+    ContractMismatch(OpId),
+
+    /// witness input spending seal {1} of input {0} is not mapped to the
+    /// transition by the bundle.
+    InputUnmapped(Opout, XOutpoint),
+
+    /// anchor or a consumed seal uses a close method other than the bundle
+    /// close method.
+    CloseMethodMismatch,
+
+    /// bundle is not committed into the witness {0}: {1}
+    CommitmentInvalid(XWitnessId, String),
+}
+
+/// Data proving a state transition to a stateless validator.
+#[derive(Clone, Copy, Debug)]
+pub struct TransitionProof<'proof> {
+    /// Bundle containing the transition.
+    pub bundle: &'proof TransitionBundle,
+    /// Anchor committing the bundle into the witness transaction.
+    pub anchor: &'proof EAnchor,
+    /// Witness transaction closing the seals of the consumed allocations.
+    pub witness: &'proof XWitnessTx,
+    /// Trusted position of the witness transaction.
+    pub witness_ord: WitnessOrd,
+    /// Trusted root of the contract state preceding the transition.
+    pub state_root: StateRoot,
+    /// Inclusion proofs of the allocations consumed by the transition.
+    pub inclusion_proofs: &'proof [(StateLeaf, StateProof)],
+}
+
+fn allocation(leaf: &StateLeaf) -> Option<(Opout, XOutputSeal)> {
+    match leaf {
+        StateLeaf::Global { .. } => None,
+        StateLeaf::Rights(a) => Some((a.opout, a.seal)),
+        StateLeaf::Fungible(a) => Some((a.opout, a.seal)),
+        StateLeaf::Data(a) => Some((a.opout, a.seal)),
+        StateLeaf::Attach(a) => Some((a.opout, a.seal)),
+    }
+}
+
+fn spent_state(leaf: &StateLeaf) -> Option<SpentState> {
+    Some(match leaf {
+        StateLeaf::Global { .. } => return None,
+        StateLeaf::Rights(a) => {
+            SpentState::Declarative(Assign::revealed(graph_seal(a.seal), a.state))
+        }
+        StateLeaf::Fungible(a) => {
+            SpentState::Fungible(Assign::revealed(graph_seal(a.seal), a.state))
+        }
+        StateLeaf::Data(a) => {
+            SpentState::Structured(Assign::revealed(graph_seal(a.seal), a.state.clone()))
+        }
+        StateLeaf::Attach(a) => {
+            SpentState::Attachment(Assign::revealed(graph_seal(a.seal), a.state.clone()))
+        }
+    })
+}
+
+/// Checks that the anchor commits the bundle into the witness transaction
+/// with a DBC proof of the bundle close method.
+fn verify_commitment(genesis: &Genesis, proof: &TransitionProof) -> Result<(), StatelessError> {
+    let witness_id = proof.witness.witness_id();
+    let anchor = proof.anchor;
+    let method = match anchor.dbc_proof {
+        DbcProof::Tapret(_) => CloseMethod::TapretFirst,
+        DbcProof::Opret(_) => CloseMethod::OpretFirst,
+    };
+    if method != proof.bundle.close_method {
+        return Err(StatelessError::CloseMethodMismatch);
+    }
+    let message = mpc::Message::from(proof.bundle.bundle_id());
+    let commitment = anchor
+        .convolve(mpc::ProtocolId::from(genesis.contract_id()), message)
+        .map_err(|err| StatelessError::CommitmentInvalid(witness_id, err.to_string()))?;
+    anchor
+        .dbc_proof
+        .verify(&commitment, proof.witness.as_reduced_unsafe())
+        .map_err(|err| StatelessError::CommitmentInvalid(witness_id, err.to_string()))
+}
+
+/// Validates state transition given only the trusted root of the contract
+/// state preceding it and inclusion proofs for the allocations it consumes.
+///
+/// Each input of the transition must have exactly one inclusion proof,
+/// verifying against the state root, and the seal of the proven allocation
+/// must be spent by an input of the witness transaction, which the bundle
+/// maps to the transition. The anchor must commit the bundle into the
+/// witness. Proofs which do not match any of the transition inputs are
+/// rejected.
+///
+/// The `genesis`, `schema`, type system and scripts must be the ones used to
+/// validate the contract. Returns the status of the transition validation
+/// against the schema.
+pub fn verify_against_root(
+    genesis: &Genesis,
+    schema: &Schema,
+    types: &TypeSystem,
+    scripts: &Scripts,
+    opid: OpId,
+    proof: TransitionProof,
+) -> Result<Status, StatelessError> {
+    let Some(transition) = proof.bundle.known_transitions.get(&opid) else {
+        return Err(StatelessError::TransitionAbsent(opid));
+    };
+    if transition.contract_id != genesis.contract_id() || genesis.schema_id != schema.schema_id() {
+        return Err(StatelessError::ContractMismatch(opid));
+    }
+
+    let mut proven = BTreeMap::<Opout, (XOutputSeal, &StateLeaf, &StateProof)>::new();
+    for (no, (leaf, state_proof)) in proof.inclusion_proofs.iter().enumerate() {
+        let Some((opout, seal)) = allocation(leaf) else {
+            return Err(StatelessError::ProofUnused(no));
+        };
+        if proven.insert(opout, (seal, leaf, state_proof)).is_some() {
+            return Err(StatelessError::ProofDuplicate(opout));
+        }
+    }
+
+    let witness_id = proof.witness.witness_id();
+    let layer1 = proof.witness.layer1();
+    let spent = proof
+        .witness
+        .as_reduced_unsafe()
+        .inputs
+        .iter()
+        .map(|input| XOutpoint::from(XChain::<Outpoint>::with(layer1, input.prev_output)))
+        .collect::<Vec<_>>();
+
+    let mut assignments = BTreeMap::new();
+    for input in &transition.inputs() {
+        let opout = input.prev_out;
+        let Some((seal, leaf, state_proof)) = proven.remove(&opout) else {
+            return Err(StatelessError::InputUnproven(opout));
+        };
+        state_proof
+            .verify(proof.state_root, leaf)
+            .map_err(|err| StatelessError::ProofInvalid(opout, err))?;
+        if seal.as_reduced_unsafe().method != proof.bundle.close_method {
+            return Err(StatelessError::CloseMethodMismatch);
+        }
+        let outpoint = seal.to_outpoint();
+        let Some(vin) = spent.iter().position(|spent| *spent == outpoint) else {
+            return Err(StatelessError::SealNotClosed(opout, outpoint, witness_id));
+        };
+        if !(&proof.bundle.input_map)
+            .into_iter()
+            .any(|(mapped, id)| *id == opid && mapped.to_usize() == vin)
+        {
+            return Err(StatelessError::InputUnmapped(opout, outpoint));
+        }
+        let spent = spent_state(leaf).expect("global state leaves are rejected above");
+        push_spent_state(&mut assignments, opout.ty, spent);
+    }
+
+    if let Some((opout, _)) = proven.into_iter().next() {
+        let no = proof
+            .inclusion_proofs
+            .iter()
+            .position(|(leaf, _)| allocation(leaf).map(|(o, _)| o) == Some(opout))
+            .expect("proof is present");
+        return Err(StatelessError::ProofUnused(no));
+    }
+
+    verify_commitment(genesis, &proof)?;
+
+    let prev_state = Confined::try_from(assignments)
+        .expect("collections is assembled from another collection with the same size requirements")
+        .into();
+    let context = OpContext {
+        contract_id: genesis.contract_id(),
+        asset_tags: &genesis.asset_tags,
+        types,
+        scripts,
+    };
+    let mut status = schema.validate_state_with(
+        context,
+        OpRef::Transition(transition),
+        (prev_state, Status::new()),
+        None,
+        Some(proof.witness_ord),
+    );
+    if schema.reserves_types() && !transition.inputs.is_empty() {
+        status.add_info(Info::LockScriptsUnchecked(opid));
+    }
+    Ok(status)
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::Wrapper;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::validation::fixtures::TestChain;
+    use crate::validation::ResolveWitness;
+    use crate::{
+        AssignmentType, Assignments, BundleId, ContractHistory, Input, InputMap, Inputs,
+        Occurrences, OwnedStateSchema, SchemaFlags, Transition, TransitionSchema, TransitionType,
+        TypedAssigns, Vin, VoidState,
+    };
+
+    struct Fixture {
+        genesis: Genesis,
+        schema: Schema,
+        opid: OpId,
+        bundle: TransitionBundle,
+        anchor: EAnchor,
+        witness: XWitnessTx,
+        witness_ord: WitnessOrd,
+        state_root: StateRoot,
+        proofs: Vec<(StateLeaf, StateProof)>,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let ty = AssignmentType::with(1);
+            let transition_ty = TransitionType::with(1);
+            let mut schema = Schema::strict_dumb();
+            schema.flags = SchemaFlags::RESERVED_TYPES;
+            schema.owned_types = Confined::from_iter_unsafe([(ty, OwnedStateSchema::Declarative)]);
+            schema.transitions = Confined::from_iter_unsafe([(transition_ty, TransitionSchema {
+                inputs: Confined::from_iter_unsafe([(ty, Occurrences::Once)]),
+                assignments: Confined::from_iter_unsafe([(ty, Occurrences::Once)]),
+                ..default!()
+            })]);
+
+            let mut chain = TestChain::regtest();
+            let seal = chain.genesis_seal();
+            let mut genesis = Genesis::strict_dumb();
+            genesis.schema_id = schema.schema_id();
+            genesis.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+                ty,
+                TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                    seal,
+                    VoidState::default(),
+                )])),
+            )]));
+            let contract_id = genesis.contract_id();
+
+            let history = ContractHistory::with(genesis.schema_id, contract_id, &genesis);
+            let state_root = history.state_root();
+            let proofs = history
+                .state_leaves()
+                .into_iter()
+                .filter(|leaf| matches!(leaf, StateLeaf::Rights(_)))
+                .map(|leaf| {
+                    let proof = history.prove_leaf(&leaf).unwrap();
+                    (leaf, proof)
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(proofs.len(), 1);
+
+            let mut transition = Transition::strict_dumb();
+            transition.contract_id = contract_id;
+            transition.transition_type = transition_ty;
+            transition.inputs =
+                Inputs::try_from_inputs([Input::with(Opout::new(genesis.id(), ty, 0))]).unwrap();
+            transition.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+                ty,
+                TypedAssigns::Declarative(Confined::from_iter_unsafe([Assign::revealed(
+                    chain.graph_seal(1),
+                    VoidState::default(),
+                )])),
+            )]));
+            let opid = transition.id();
+            let bundle = TransitionBundle {
+                close_method: CloseMethod::OpretFirst,
+                input_map: InputMap::with(Vin::from(0u32), opid),
+                known_transitions: Confined::with((opid, transition)),
+            };
+
+            let spent = *seal.to_outpoint().as_reduced_unsafe();
+            let (witness_id, anchor) = chain.commit(contract_id, bundle.bundle_id(), [spent], 1);
+            let witness_ord = chain.mine(witness_id);
+            let witness = chain.resolve_pub_witness(witness_id).unwrap();
+
+            Fixture {
+                genesis,
+                schema,
+                opid,
+                bundle,
+                anchor,
+                witness,
+                witness_ord,
+                state_root,
+                proofs,
+            }
+        }
+
+        fn verify(&self) -> Result<Status, StatelessError> { self.verify_with(&self.proofs) }
+
+        fn verify_with(
+            &self,
+            proofs: &[(StateLeaf, StateProof)],
+        ) -> Result<Status, StatelessError> {
+            let proof = TransitionProof {
+                bundle: &self.bundle,
+                anchor: &self.anchor,
+                witness: &self.witness,
+                witness_ord: self.witness_ord,
+                state_root: self.state_root,
+                inclusion_proofs: proofs,
+            };
+            verify_against_root(
+                &self.genesis,
+                &self.schema,
+                &TypeSystem::default(),
+                &Scripts::default(),
+                self.opid,
+                proof,
+            )
+        }
+    }
+
+    #[test]
+    fn valid_transition() {
+        let fixture = Fixture::new();
+        let status = fixture.verify().unwrap();
+        assert!(status.failures.is_empty(), "{status}");
+        assert_eq!(status.info, vec![Info::LockScriptsUnchecked(fixture.opid)]);
+    }
+
+    #[test]
+    fn missing_proof() {
+        let fixture = Fixture::new();
+        let opout = fixture.bundle.known_transitions[&fixture.opid]
+            .inputs
+            .iter()
+            .next()
+            .unwrap()
+            .prev_out;
+        assert_eq!(fixture.verify_with(&[]), Err(StatelessError::InputUnproven(opout)));
+    }
+
+    #[test]
+    fn foreign_root() {
+        let mut fixture = Fixture::new();
+        fixture.state_root = StateRoot::from([0xAB; 32]);
+        assert!(matches!(fixture.verify(), Err(StatelessError::ProofInvalid(..))));
+    }
+
+    #[test]
+    fn unmapped_input() {
+        let mut fixture = Fixture::new();
+        fixture.bundle.input_map = InputMap::with(Vin::from(1u32), fixture.opid);
+        assert!(matches!(fixture.verify(), Err(StatelessError::InputUnmapped(..))));
+    }
+
+    #[test]
+    fn uncommitted_bundle() {
+        let mut fixture = Fixture::new();
+        let mut chain = TestChain::regtest();
+        let spent = fixture.witness.as_reduced_unsafe().inputs[0].prev_output;
+        let (witness_id, anchor) =
+            chain.commit(fixture.genesis.contract_id(), BundleId::from([0x22; 32]), [spent], 1);
+        fixture.anchor = anchor;
+        fixture.witness = chain.resolve_pub_witness(witness_id).unwrap();
+        assert!(matches!(fixture.verify(), Err(StatelessError::CommitmentInvalid(..))));
+    }
+}
@@ -20,21 +20,41 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::ops::AddAssign;
+use core::ops::{AddAssign, Range};
+use std::collections::BTreeMap;
 use std::fmt::{self, Display, Formatter};
 
+use aluvm::library::LibId;
+use bp::seals::txout::CloseMethod;
+use bp::secp256k1::XOnlyPublicKey;
 use bp::Txid;
 use commit_verify::mpc::InvalidProof;
 use strict_types::SemId;
 
+use super::{Interruption, NonStandard, PolicyViolation, WitnessMetrics};
+use crate::consensus_rules::{self as rules, ConsensusRule};
 use crate::contract::Opout;
-use crate::schema::{self, SchemaId};
+use crate::schema::{self, SchemaFlags, SchemaId, StateVersion};
 use crate::{
-    AssignmentType, BundleId, ContractId, Layer1, OccurrencesMismatch, OpFullType, OpId,
-    SecretSeal, StateType, Vin, XChain, XGraphSeal, XOutputSeal, XWitnessId,
+    AssignmentType, AttachId, BundleId, ChainNet, ContractId, GenesisTemplateId,
+    IssuerSignatureError, Layer1, OccurrencesMismatch, OpFeatures, OpFullType, OpId, OracleError,
+    SecretSeal, StateType, TermsError, ValencyError, Vin, XChain, XGraphSeal, XOutpoint,
+    XOutputSeal, XWitnessId,
 };
 
+/// Range of the [`Failure::code`] values.
+pub const FAILURE_CODES: Range<u16> = 1000..2000;
+/// Range of the [`Warning::code`] values.
+pub const WARNING_CODES: Range<u16> = 2000..3000;
+/// Range of the [`Info::code`] values.
+pub const INFO_CODES: Range<u16> = 3000..4000;
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
 #[repr(u8)]
 pub enum Validity {
     #[display("is valid")]
@@ -48,6 +68,9 @@ pub enum Validity {
 
     #[display("is NOT valid")]
     Invalid,
+
+    #[display("is rejected by the local policy")]
+    LocallyRejected,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Default)]
@@ -62,6 +85,15 @@ pub struct Status {
     pub failures: Vec<Failure>,
     pub warnings: Vec<Warning>,
     pub info: Vec<Info>,
+    /// Violations of the local validation policies, which are not a part of
+    /// the consensus validation.
+    pub policy_violations: Vec<PolicyViolation>,
+    /// Warnings which the validation config reports as local rejections (see
+    /// [`super::ValidationConfig::reject`]).
+    pub rejected_warnings: Vec<Warning>,
+    /// Size and weight of the witness transactions resolved during the
+    /// validation.
+    pub witness_metrics: Vec<WitnessMetrics>,
 }
 
 impl Display for Status {
@@ -105,6 +137,27 @@ impl Display for Status {
             }
         }
 
+        if !self.policy_violations.is_empty() {
+            f.write_str("Local policy violations:\n")?;
+            for violation in &self.policy_violations {
+                writeln!(f, "- {violation}")?;
+            }
+        }
+
+        if !self.rejected_warnings.is_empty() {
+            f.write_str("Warnings rejected by the local config:\n")?;
+            for warning in &self.rejected_warnings {
+                writeln!(f, "- {warning}")?;
+            }
+        }
+
+        if !self.witness_metrics.is_empty() {
+            f.write_str("Witness transactions:\n")?;
+            for metrics in &self.witness_metrics {
+                writeln!(f, "- {metrics}")?;
+            }
+        }
+
         Ok(())
     }
 }
@@ -116,6 +169,11 @@ impl AddAssign for Status {
         self.failures.extend(rhs.failures);
         self.warnings.extend(rhs.warnings);
         self.info.extend(rhs.info);
+        self.policy_violations.extend(rhs.policy_violations);
+        self.rejected_warnings.extend(rhs.rejected_warnings);
+        for metrics in rhs.witness_metrics {
+            self.add_witness_metrics(metrics);
+        }
     }
 }
 
@@ -127,6 +185,9 @@ impl Status {
             failures: vec![v],
             warnings: vec![],
             info: vec![],
+            policy_violations: vec![],
+            rejected_warnings: vec![],
+            witness_metrics: vec![],
         }
     }
 }
@@ -165,7 +226,54 @@ impl Status {
         self
     }
 
+    /// Records metrics of the witness transaction, unless they were already
+    /// recorded.
+    pub fn add_witness_metrics(&mut self, metrics: WitnessMetrics) -> &Self {
+        if !self
+            .witness_metrics
+            .iter()
+            .any(|known| known.witness_id == metrics.witness_id)
+        {
+            self.witness_metrics.push(metrics);
+        }
+        self
+    }
+
+    /// Key of the contract issuer, if the consignment provided a valid issuer
+    /// signature over the genesis.
+    pub fn issuer_key(&self) -> Option<XOnlyPublicKey> {
+        self.info.iter().find_map(|info| match info {
+            Info::IssuerSigned(key) => Some(*key),
+            _ => None,
+        })
+    }
+
+    /// Amount of the state of the given type which still can be issued under
+    /// the supply cap declared by the contract genesis, or `None` if the
+    /// supply is not capped.
+    pub fn remaining_supply(&self, ty: schema::AssignmentType) -> Option<u64> {
+        self.info.iter().find_map(|info| match info {
+            Info::SupplyRemaining(t, remaining) if *t == ty => Some(*remaining),
+            _ => None,
+        })
+    }
+
+    /// Validity of the consignment, taking into account local policies and
+    /// the warnings rejected by the validation config.
     pub fn validity(&self) -> Validity {
+        let validity = self.consensus_validity();
+        let consensus_valid = matches!(validity, Validity::Valid | Validity::UnminedTerminals);
+        let locally_rejected =
+            !self.policy_violations.is_empty() || !self.rejected_warnings.is_empty();
+        if consensus_valid && locally_rejected {
+            return Validity::LocallyRejected;
+        }
+        validity
+    }
+
+    /// Validity of the consignment under the consensus rules, ignoring local
+    /// policies.
+    pub fn consensus_validity(&self) -> Validity {
         if self.failures.is_empty() {
             if self.unmined_terminals.is_empty() {
                 Validity::Valid
@@ -191,6 +299,14 @@ pub enum Failure {
     /// the contract network doesn't match (validator runs in testnet={0}
     /// configuration).
     NetworkMismatch(bool),
+    /// contract id is salted for {actual} network, while the validator runs
+    /// for {expected}.
+    ChainNetMismatch {
+        /// Network the validating software operates on.
+        expected: ChainNet,
+        /// Network the contract id is salted for.
+        actual: ChainNet,
+    },
 
     /// schema {actual} provided for the consignment validation doesn't match
     /// schema {expected} used by the contract. This means that the consignment
@@ -203,6 +319,10 @@ pub enum Failure {
     },
     /// schema uses reserved type for the blank state transition.
     SchemaBlankTransitionRedefined,
+    /// schema uses flags {0} unknown to this version of the library.
+    SchemaFlagsUnknown(SchemaFlags),
+    /// schema names type '{0}' which is not defined by the schema.
+    SchemaNameUnknownType(String),
 
     /// schema global state #{0} uses semantic data type absent in type library
     /// ({1}).
@@ -213,6 +333,9 @@ pub enum Failure {
     /// schema metadata #{0} uses semantic data type absent in type library
     /// ({1}).
     SchemaMetaSemIdUnknown(schema::MetaType, SemId),
+    /// schema scripts reference library {0} which is not provided by the
+    /// consignment.
+    SchemaLibUnknown(LibId),
 
     /// schema for {0} has zero inputs.
     SchemaOpEmptyInputs(OpFullType),
@@ -259,6 +382,16 @@ pub enum Failure {
     /// invalid number of assignment entries of type {1} in operation {0} - {2}
     SchemaAssignmentOccurrences(OpId, schema::AssignmentType, OccurrencesMismatch),
 
+    /// ticker provided in global state {1} of genesis {0} doesn't match the
+    /// presentation rules of the schema.
+    PresentationInvalidTicker(OpId, schema::GlobalStateType),
+    /// asset name provided in global state {1} of genesis {0} doesn't match
+    /// the presentation rules of the schema.
+    PresentationInvalidName(OpId, schema::GlobalStateType),
+    /// decimal precision provided in global state {1} of genesis {0} is out
+    /// of the bounds defined by the presentation rules of the schema.
+    PresentationInvalidPrecision(OpId, schema::GlobalStateType),
+
     // Consignment consistency errors
     /// operation {0} is referenced within the history multiple times. RGB
     /// contracts allow only direct acyclic graphs.
@@ -276,6 +409,29 @@ pub enum Failure {
     WitnessIdAbsent(BundleId),
     /// operation {0} is under a different contract {1}.
     ContractMismatch(OpId, ContractId),
+    /// contract genesis declares malformed set of contract dependencies.
+    GenesisDependenciesInvalid,
+    /// contract genesis declares malformed chain salt or salt not matching
+    /// the contract network.
+    GenesisChainSaltInvalid,
+    /// contract genesis declares malformed supply caps.
+    GenesisSupplyCapsInvalid,
+    /// contract genesis declares malformed oracle feeds.
+    GenesisOracleFeedsInvalid,
+    /// contract genesis declares malformed finality depth.
+    GenesisFinalityDepthInvalid,
+    /// contract genesis declares malformed valency limits.
+    GenesisValencyLimitsInvalid,
+    /// contract genesis commits to invalid terms. Details: {0}
+    GenesisTermsInvalid(TermsError),
+    /// operation {0} defines contract terms, which can be defined only by the
+    /// genesis.
+    TermsRedefined(OpId),
+    /// contract depends on contract {0}, which must be validated before it.
+    DependencyUnvalidated(ContractId),
+    /// state spent by input {1} of operation {0}, which is provided by the
+    /// consignment, doesn't match the ancestor assignment.
+    SpentStateMismatch(OpId, Opout),
 
     // Errors checking bundle commitments
     /// transition bundle {0} references state transition {1} which is not
@@ -326,9 +482,31 @@ pub enum Failure {
     /// anchor provides different type of DBC proof than required by the bundle
     /// {0}.
     AnchorMethodMismatch(BundleId),
+    /// contract genesis declares malformed set of allowed seal close methods.
+    GenesisCloseMethodsInvalid,
+    /// transition bundle {0} uses close method {1} which is not allowed by the
+    /// contract genesis.
+    CloseMethodForbidden(BundleId, CloseMethod),
+    /// schema declares malformed constraints on witness transactions.
+    SchemaWitnessConstraintsInvalid,
+    /// input {2} of witness {1} closing seals of bundle {0} has nSequence
+    /// {3}, which is not allowed by the contract schema.
+    WitnessSequenceForbidden(BundleId, XWitnessId, Vin, u32),
+    /// witness {1} for bundle {0} has no locktime, which is required by the
+    /// contract schema.
+    WitnessLocktimeAbsent(BundleId, XWitnessId),
     /// transition bundle {0} is not properly anchored to the witness {1}.
     /// Details: {2}
     MpcInvalid(BundleId, XWitnessId, InvalidProof),
+    /// witness substitution for bundle {0} provides replacement witness {2},
+    /// while the bundle is anchored to the witness {1}.
+    WitnessSubstitutionMismatch(BundleId, XWitnessId, XWitnessId),
+    /// witness substitution for bundle {0} is invalid. Details: {1}
+    WitnessSubstitutionInvalid(BundleId, String),
+    /// alternative witness {1} for bundle {0} is invalid. Details: {2}
+    WitnessAlternativeInvalid(BundleId, XWitnessId, String),
+    /// multiple alternative witnesses for bundle {0} are reported as mined.
+    WitnessAlternativesMined(BundleId),
 
     // State extensions errors
     /// valency {valency} redeemed by state extension {opid} references
@@ -376,12 +554,726 @@ pub enum Failure {
     /// evaluation of AluVM script for operation {0} has failed with the code
     /// {1:?} and message {2:?}.
     ScriptFailure(OpId, Option<u8>, Option<String>),
+    /// operation {0} declares malformed assignment locks.
+    AssignmentLocksInvalid(OpId),
+    /// operation {0} declares malformed feature bits.
+    OpFeaturesInvalid(OpId),
+    /// operation {0} uses features {1} not supported by the validator.
+    OpFeaturesUnsupported(OpId, OpFeatures),
+    /// operation {0} issues state of type {1} exceeding the supply cap of {2}
+    /// units declared by the contract genesis.
+    SupplyCapExceeded(OpId, schema::AssignmentType, u64),
+    /// operation {0} conceals state of type {1} which supply is capped by the
+    /// contract genesis, or spends such state from unknown operations, so the
+    /// supply cap can't be checked.
+    SupplyUncheckable(OpId, schema::AssignmentType),
+    /// operation {0} defines invalid oracle feed state: {1}
+    OracleStateInvalid(OpId, OracleError),
+    /// operation {0} violates valency limits declared by the contract
+    /// genesis: {1}
+    ValencyLimitViolated(OpId, ValencyError),
+    /// lock script of the assignment {1} spent by operation {0} has failed
+    /// with the code {2:?}.
+    LockScriptFailure(OpId, Opout, Option<u8>),
+    /// lock of the assignment {1} spent by operation {0} can't be checked
+    /// since the ancestor operation is absent or declares invalid assignment
+    /// locks.
+    LockUnverifiable(OpId, Opout),
+
+    // Attachment content errors
+    /// content of attachment {1} from operation {0} doesn't match the
+    /// attachment digest.
+    AttachmentDigestMismatch(OpId, AttachId),
+    /// content of attachment {id} from operation {opid} is of {found} media
+    /// type, while the attachment state declares it to be {expected}.
+    AttachmentMediaTypeMismatch {
+        opid: OpId,
+        id: AttachId,
+        expected: schema::MediaType,
+        found: schema::MediaType,
+    },
+    /// content of attachment {1} from operation {0} has size of {2} bytes,
+    /// exceeding the allowed limit.
+    AttachmentSizeExceeded(OpId, AttachId, usize),
+    /// operation {0} has malformed schema upgrade metadata, or is not a state
+    /// extension and can't upgrade the contract.
+    SchemaUpgradeInvalid(OpId),
+    /// successor schema {1} of the upgrade applying to state transition {0}
+    /// is not provided by the consignment.
+    SchemaUpgradeUnavailable(OpId, SchemaId),
+    /// history of state transition {0} contains several schema upgrades
+    /// activating at the same height.
+    SchemaUpgradeConflict(OpId),
+    /// validation was interrupted ({0}) after validating seals of {1} and
+    /// state of {2} operations; the contract is not validated completely.
+    ValidationInterrupted(Interruption, usize, usize),
+    /// contract genesis declares malformed genesis template id.
+    GenesisTemplateInvalid,
+    /// contract genesis declares template id {0}, while it was bound from the
+    /// template {1}.
+    GenesisTemplateMismatch(GenesisTemplateId, GenesisTemplateId),
+    /// operation {0} spends output {1} which was already spent by operation
+    /// {2}.
+    InputSpent(OpId, Opout, OpId),
+    /// schema of state transition {0} can't be determined since the position
+    /// of its witness is unknown while its history contains schema upgrades.
+    SchemaUpgradeUndetermined(OpId),
+    /// contract genesis declares malformed state formats, or formats which
+    /// can't be registered under their versions.
+    GenesisStateFormatsInvalid,
 
     /// Custom error by external services on top of RGB Core.
     #[display(inner)]
     Custom(String),
 }
 
+/// Parameters of a validation report entry, keyed by the parameter name and
+/// rendered into strings, for the use in localized messages.
+pub type ReasonParams = BTreeMap<&'static str, String>;
+
+macro_rules! params {
+    ($($key:literal => $val:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut params = ReasonParams::new();
+        $( params.insert($key, $val.to_string()); )*
+        params
+    }};
+}
+
+impl Failure {
+    /// Stable machine-readable key identifying the reason of the failure.
+    ///
+    /// Keys do not change between library versions and can be used by the
+    /// user interfaces for localizing the failure message, which can be
+    /// parametrized with the values from [`Failure::params`].
+    pub fn reason_key(&self) -> &'static str {
+        match self {
+            Failure::NetworkMismatch(_) => "network.mismatch",
+            Failure::ChainNetMismatch { .. } => "network.chain_mismatch",
+            Failure::SchemaMismatch { .. } => "schema.mismatch",
+            Failure::SchemaBlankTransitionRedefined => "schema.blank_transition_redefined",
+            Failure::SchemaFlagsUnknown(_) => "schema.flags_unknown",
+            Failure::SchemaNameUnknownType(_) => "schema.name_unknown_type",
+            Failure::SchemaGlobalSemIdUnknown(..) => "schema.global_sem_id_unknown",
+            Failure::SchemaOwnedSemIdUnknown(..) => "schema.owned_sem_id_unknown",
+            Failure::SchemaMetaSemIdUnknown(..) => "schema.meta_sem_id_unknown",
+            Failure::SchemaLibUnknown(_) => "schema.lib_unknown",
+            Failure::SchemaOpEmptyInputs(_) => "schema.op_empty_inputs",
+            Failure::SchemaOpMetaTypeUnknown(..) => "schema.op_meta_type_unknown",
+            Failure::SchemaOpGlobalTypeUnknown(..) => "schema.op_global_type_unknown",
+            Failure::SchemaOpAssignmentTypeUnknown(..) => "schema.op_assignment_type_unknown",
+            Failure::SchemaOpValencyTypeUnknown(..) => "schema.op_valency_type_unknown",
+            Failure::SchemaUnknownExtensionType(..) => "schema.unknown_extension_type",
+            Failure::SchemaUnknownTransitionType(..) => "schema.unknown_transition_type",
+            Failure::SchemaUnknownMetaType(..) => "schema.unknown_meta_type",
+            Failure::SchemaUnknownGlobalStateType(..) => "schema.unknown_global_state_type",
+            Failure::SchemaUnknownAssignmentType(..) => "schema.unknown_assignment_type",
+            Failure::SchemaUnknownValencyType(..) => "schema.unknown_valency_type",
+            Failure::SchemaGlobalStateOccurrences(..) => "schema.global_state_occurrences",
+            Failure::SchemaGlobalStateLimit(..) => "schema.global_state_limit",
+            Failure::SchemaNoMetadata(..) => "schema.no_metadata",
+            Failure::SchemaInvalidMetadata(..) => "schema.invalid_metadata",
+            Failure::SchemaInvalidGlobalValue(..) => "schema.invalid_global_value",
+            Failure::SchemaInvalidOwnedValue(..) => "schema.invalid_owned_value",
+            Failure::SchemaInputOccurrences(..) => "schema.input_occurrences",
+            Failure::SchemaAssignmentOccurrences(..) => "schema.assignment_occurrences",
+            Failure::PresentationInvalidTicker(..) => "presentation.invalid_ticker",
+            Failure::PresentationInvalidName(..) => "presentation.invalid_name",
+            Failure::PresentationInvalidPrecision(..) => "presentation.invalid_precision",
+            Failure::CyclicGraph(_) => "consignment.cyclic_graph",
+            Failure::OperationAbsent(_) => "consignment.operation_absent",
+            Failure::TerminalBundleAbsent(_) => "consignment.terminal_bundle_absent",
+            Failure::BundleAbsent(_) => "consignment.bundle_absent",
+            Failure::AnchorAbsent(_) => "consignment.anchor_absent",
+            Failure::WitnessIdAbsent(_) => "consignment.witness_id_absent",
+            Failure::ContractMismatch(..) => "consignment.contract_mismatch",
+            Failure::GenesisDependenciesInvalid => "genesis.dependencies_invalid",
+            Failure::GenesisChainSaltInvalid => "genesis.chain_salt_invalid",
+            Failure::GenesisSupplyCapsInvalid => "genesis.supply_caps_invalid",
+            Failure::GenesisOracleFeedsInvalid => "genesis.oracle_feeds_invalid",
+            Failure::GenesisFinalityDepthInvalid => "genesis.finality_depth_invalid",
+            Failure::GenesisValencyLimitsInvalid => "genesis.valency_limits_invalid",
+            Failure::GenesisTermsInvalid(_) => "genesis.terms_invalid",
+            Failure::TermsRedefined(_) => "state.terms_redefined",
+            Failure::DependencyUnvalidated(_) => "consignment.dependency_unvalidated",
+            Failure::SpentStateMismatch(..) => "consignment.spent_state_mismatch",
+            Failure::BundleExtraTransition(..) => "bundle.extra_transition",
+            Failure::BundleInvalidInput(..) => "bundle.invalid_input",
+            Failure::BundleInvalidCommitment(..) => "bundle.invalid_commitment",
+            Failure::AssetTagNoState(_) => "asset_tag.no_state",
+            Failure::FungibleStateNoTag(_) => "asset_tag.fungible_no_tag",
+            Failure::NoPrevState { .. } => "seal.no_prev_state",
+            Failure::NoPrevOut(..) => "seal.no_prev_out",
+            Failure::ConfidentialSeal(_) => "seal.confidential",
+            Failure::SealNoWitnessTx(_) => "seal.no_witness_tx",
+            Failure::SealWitnessLayer1Mismatch { .. } => "seal.witness_layer1_mismatch",
+            Failure::SealLayerMismatch(..) => "seal.layer_mismatch",
+            Failure::SealInvalidMethod(..) => "seal.invalid_method",
+            Failure::SealsInvalid(..) => "seal.invalid",
+            Failure::SealsUnvalidated(_) => "seal.unvalidated",
+            Failure::AnchorMethodMismatch(_) => "anchor.method_mismatch",
+            Failure::GenesisCloseMethodsInvalid => "genesis.close_methods_invalid",
+            Failure::CloseMethodForbidden(..) => "anchor.close_method_forbidden",
+            Failure::SchemaWitnessConstraintsInvalid => "schema.witness_constraints_invalid",
+            Failure::WitnessSequenceForbidden(..) => "anchor.sequence_forbidden",
+            Failure::WitnessLocktimeAbsent(..) => "anchor.locktime_absent",
+            Failure::MpcInvalid(..) => "anchor.mpc_invalid",
+            Failure::WitnessSubstitutionMismatch(..) => "anchor.substitution_mismatch",
+            Failure::WitnessSubstitutionInvalid(..) => "anchor.substitution_invalid",
+            Failure::WitnessAlternativeInvalid(..) => "anchor.alternative_invalid",
+            Failure::WitnessAlternativesMined(_) => "anchor.alternatives_mined",
+            Failure::ValencyNoParent { .. } => "extension.valency_no_parent",
+            Failure::NoPrevValency { .. } => "extension.no_prev_valency",
+            Failure::StateTypeMismatch { .. } => "state.type_mismatch",
+            Failure::MediaTypeMismatch { .. } => "state.media_type_mismatch",
+            Failure::FungibleTypeMismatch { .. } => "state.fungible_type_mismatch",
+            Failure::BulletproofsInvalid(..) => "state.bulletproofs_invalid",
+            Failure::ScriptFailure(..) => "script.failure",
+            Failure::AssignmentLocksInvalid(_) => "script.assignment_locks_invalid",
+            Failure::OpFeaturesInvalid(_) => "op.features_invalid",
+            Failure::OpFeaturesUnsupported(..) => "op.features_unsupported",
+            Failure::SupplyCapExceeded(..) => "state.supply_cap_exceeded",
+            Failure::SupplyUncheckable(..) => "state.supply_uncheckable",
+            Failure::OracleStateInvalid(..) => "state.oracle_invalid",
+            Failure::ValencyLimitViolated(..) => "state.valency_limit_violated",
+            Failure::LockScriptFailure(..) => "script.lock_failure",
+            Failure::LockUnverifiable(..) => "script.lock_unverifiable",
+            Failure::AttachmentDigestMismatch(..) => "attachment.digest_mismatch",
+            Failure::AttachmentMediaTypeMismatch { .. } => "attachment.media_type_mismatch",
+            Failure::AttachmentSizeExceeded(..) => "attachment.size_exceeded",
+            Failure::SchemaUpgradeInvalid(_) => "schema.upgrade_invalid",
+            Failure::SchemaUpgradeUnavailable(..) => "schema.upgrade_unavailable",
+            Failure::SchemaUpgradeConflict(_) => "schema.upgrade_conflict",
+            Failure::ValidationInterrupted(..) => "validation.interrupted",
+            Failure::GenesisTemplateInvalid => "genesis.template_invalid",
+            Failure::GenesisTemplateMismatch(..) => "genesis.template_mismatch",
+            Failure::InputSpent(..) => "seal.input_spent",
+            Failure::SchemaUpgradeUndetermined(_) => "schema.upgrade_undetermined",
+            Failure::GenesisStateFormatsInvalid => "genesis.state_formats_invalid",
+            Failure::Custom(_) => "custom",
+        }
+    }
+
+    /// Consensus rule violated by the operation or consignment, if the
+    /// failure is caused by a consensus rule violation (see
+    /// [`crate::consensus_rules`]).
+    ///
+    /// Failures which are not a consensus matter, like the ones caused by the
+    /// local validation configuration or interruption, return `None`.
+    pub fn rule(&self) -> Option<ConsensusRule> {
+        Some(match self {
+            Failure::NetworkMismatch(_) => rules::NETWORK,
+            Failure::ChainNetMismatch { .. } | Failure::GenesisChainSaltInvalid => {
+                rules::NETWORK_SALT
+            }
+            Failure::SchemaMismatch { .. } => rules::SCHEMA_MATCH,
+            Failure::SchemaBlankTransitionRedefined |
+            Failure::SchemaFlagsUnknown(_) |
+            Failure::SchemaWitnessConstraintsInvalid |
+            Failure::SchemaNameUnknownType(_) |
+            Failure::SchemaGlobalSemIdUnknown(..) |
+            Failure::SchemaOwnedSemIdUnknown(..) |
+            Failure::SchemaMetaSemIdUnknown(..) |
+            Failure::SchemaLibUnknown(_) |
+            Failure::SchemaOpEmptyInputs(_) |
+            Failure::SchemaOpMetaTypeUnknown(..) |
+            Failure::SchemaOpGlobalTypeUnknown(..) |
+            Failure::SchemaOpAssignmentTypeUnknown(..) |
+            Failure::SchemaOpValencyTypeUnknown(..) => rules::SCHEMA_VALIDITY,
+            Failure::SchemaUnknownExtensionType(..) |
+            Failure::SchemaUnknownTransitionType(..) |
+            Failure::SchemaUnknownMetaType(..) |
+            Failure::SchemaUnknownGlobalStateType(..) |
+            Failure::SchemaUnknownAssignmentType(..) |
+            Failure::SchemaUnknownValencyType(..) |
+            Failure::SchemaGlobalStateOccurrences(..) |
+            Failure::SchemaGlobalStateLimit(..) |
+            Failure::SchemaNoMetadata(..) |
+            Failure::SchemaInvalidMetadata(..) |
+            Failure::SchemaInvalidGlobalValue(..) |
+            Failure::SchemaInvalidOwnedValue(..) |
+            Failure::SchemaInputOccurrences(..) |
+            Failure::SchemaAssignmentOccurrences(..) |
+            Failure::AssetTagNoState(_) |
+            Failure::FungibleStateNoTag(_) |
+            Failure::StateTypeMismatch { .. } |
+            Failure::MediaTypeMismatch { .. } |
+            Failure::FungibleTypeMismatch { .. } => rules::SCHEMA_CONFORMANCE,
+            Failure::SchemaUpgradeInvalid(_) |
+            Failure::SchemaUpgradeUnavailable(..) |
+            Failure::SchemaUpgradeConflict(_) |
+            Failure::SchemaUpgradeUndetermined(_) => rules::SCHEMA_UPGRADE,
+            Failure::CyclicGraph(_) |
+            Failure::OperationAbsent(_) |
+            Failure::TerminalBundleAbsent(_) |
+            Failure::BundleAbsent(_) |
+            Failure::AnchorAbsent(_) |
+            Failure::WitnessIdAbsent(_) |
+            Failure::ContractMismatch(..) |
+            Failure::SpentStateMismatch(..) |
+            Failure::NoPrevState { .. } |
+            Failure::NoPrevOut(..) |
+            Failure::ValencyNoParent { .. } |
+            Failure::NoPrevValency { .. } => rules::GRAPH_INTEGRITY,
+            Failure::GenesisDependenciesInvalid |
+            Failure::GenesisSupplyCapsInvalid |
+            Failure::GenesisOracleFeedsInvalid |
+            Failure::GenesisFinalityDepthInvalid |
+            Failure::GenesisValencyLimitsInvalid |
+            Failure::GenesisStateFormatsInvalid |
+            Failure::GenesisTermsInvalid(_) |
+            Failure::GenesisCloseMethodsInvalid => rules::GENESIS_METADATA,
+            Failure::GenesisTemplateInvalid | Failure::GenesisTemplateMismatch(..) => {
+                rules::GENESIS_TEMPLATE
+            }
+            Failure::TermsRedefined(_) => rules::TERMS_IMMUTABLE,
+            Failure::DependencyUnvalidated(_) => rules::DEPENDENCIES,
+            Failure::BundleExtraTransition(..) |
+            Failure::BundleInvalidInput(..) |
+            Failure::BundleInvalidCommitment(..) => rules::BUNDLE_COMMITMENT,
+            Failure::ConfidentialSeal(_) |
+            Failure::SealNoWitnessTx(_) |
+            Failure::SealWitnessLayer1Mismatch { .. } |
+            Failure::SealInvalidMethod(..) |
+            Failure::SealsInvalid(..) |
+            Failure::SealsUnvalidated(_) |
+            Failure::AnchorMethodMismatch(_) |
+            Failure::MpcInvalid(..) |
+            Failure::InputSpent(..) => rules::SEAL_CLOSING,
+            Failure::SealLayerMismatch(..) => rules::SEAL_LAYER1,
+            Failure::CloseMethodForbidden(..) => rules::CLOSE_METHODS,
+            Failure::WitnessSequenceForbidden(..) | Failure::WitnessLocktimeAbsent(..) => {
+                rules::WITNESS_CONSTRAINTS
+            }
+            Failure::WitnessSubstitutionMismatch(..) |
+            Failure::WitnessSubstitutionInvalid(..) |
+            Failure::WitnessAlternativeInvalid(..) |
+            Failure::WitnessAlternativesMined(_) => rules::WITNESS_SUBSTITUTION,
+            Failure::BulletproofsInvalid(..) => rules::STATE_RANGE_PROOFS,
+            Failure::ScriptFailure(..) => rules::SCRIPTS,
+            Failure::AssignmentLocksInvalid(_) |
+            Failure::LockScriptFailure(..) |
+            Failure::LockUnverifiable(..) => rules::LOCK_SCRIPTS,
+            Failure::OpFeaturesInvalid(_) | Failure::OpFeaturesUnsupported(..) => {
+                rules::OP_FEATURES
+            }
+            Failure::SupplyCapExceeded(..) | Failure::SupplyUncheckable(..) => rules::SUPPLY_CAPS,
+            Failure::OracleStateInvalid(..) => rules::ORACLE_STATE,
+            Failure::ValencyLimitViolated(..) => rules::VALENCY_LIMITS,
+            Failure::AttachmentDigestMismatch(..) |
+            Failure::AttachmentMediaTypeMismatch { .. } |
+            Failure::AttachmentSizeExceeded(..) => rules::ATTACHMENTS,
+            Failure::PresentationInvalidTicker(..) |
+            Failure::PresentationInvalidName(..) |
+            Failure::PresentationInvalidPrecision(..) |
+            Failure::ValidationInterrupted(..) |
+            Failure::Custom(_) => return None,
+        })
+    }
+
+    /// Stable numeric code of the failure.
+    ///
+    /// Codes never change between library versions and are not reused: new
+    /// failure types receive new codes. Failures use codes in the
+    /// [`FAILURE_CODES`] range, warnings and information messages use
+    /// [`WARNING_CODES`] and [`INFO_CODES`] ranges (see [`Warning::code`] and
+    /// [`Info::code`]). The first code of each range is used for the custom
+    /// entries.
+    pub fn code(&self) -> u16 {
+        match self {
+            Failure::Custom(_) => 1000,
+            Failure::NetworkMismatch(_) => 1001,
+            Failure::ChainNetMismatch { .. } => 1002,
+            Failure::SchemaMismatch { .. } => 1003,
+            Failure::SchemaBlankTransitionRedefined => 1004,
+            Failure::SchemaGlobalSemIdUnknown(..) => 1005,
+            Failure::SchemaOwnedSemIdUnknown(..) => 1006,
+            Failure::SchemaMetaSemIdUnknown(..) => 1007,
+            Failure::SchemaLibUnknown(_) => 1008,
+            Failure::SchemaOpEmptyInputs(_) => 1009,
+            Failure::SchemaOpMetaTypeUnknown(..) => 1010,
+            Failure::SchemaOpGlobalTypeUnknown(..) => 1011,
+            Failure::SchemaOpAssignmentTypeUnknown(..) => 1012,
+            Failure::SchemaOpValencyTypeUnknown(..) => 1013,
+            Failure::SchemaUnknownExtensionType(..) => 1014,
+            Failure::SchemaUnknownTransitionType(..) => 1015,
+            Failure::SchemaUnknownMetaType(..) => 1016,
+            Failure::SchemaUnknownGlobalStateType(..) => 1017,
+            Failure::SchemaUnknownAssignmentType(..) => 1018,
+            Failure::SchemaUnknownValencyType(..) => 1019,
+            Failure::SchemaGlobalStateOccurrences(..) => 1020,
+            Failure::SchemaGlobalStateLimit(..) => 1021,
+            Failure::SchemaNoMetadata(..) => 1022,
+            Failure::SchemaInvalidMetadata(..) => 1023,
+            Failure::SchemaInvalidGlobalValue(..) => 1024,
+            Failure::SchemaInvalidOwnedValue(..) => 1025,
+            Failure::SchemaInputOccurrences(..) => 1026,
+            Failure::SchemaAssignmentOccurrences(..) => 1027,
+            Failure::PresentationInvalidTicker(..) => 1028,
+            Failure::PresentationInvalidName(..) => 1029,
+            Failure::PresentationInvalidPrecision(..) => 1030,
+            Failure::CyclicGraph(_) => 1031,
+            Failure::OperationAbsent(_) => 1032,
+            Failure::TerminalBundleAbsent(_) => 1033,
+            Failure::BundleAbsent(_) => 1034,
+            Failure::AnchorAbsent(_) => 1035,
+            Failure::WitnessIdAbsent(_) => 1036,
+            Failure::ContractMismatch(..) => 1037,
+            Failure::GenesisDependenciesInvalid => 1038,
+            Failure::GenesisChainSaltInvalid => 1039,
+            Failure::GenesisSupplyCapsInvalid => 1040,
+            Failure::GenesisOracleFeedsInvalid => 1041,
+            Failure::GenesisFinalityDepthInvalid => 1042,
+            Failure::GenesisValencyLimitsInvalid => 1043,
+            Failure::GenesisTermsInvalid(_) => 1044,
+            Failure::TermsRedefined(_) => 1045,
+            Failure::DependencyUnvalidated(_) => 1046,
+            Failure::SpentStateMismatch(..) => 1047,
+            Failure::BundleExtraTransition(..) => 1048,
+            Failure::BundleInvalidInput(..) => 1049,
+            Failure::BundleInvalidCommitment(..) => 1050,
+            Failure::AssetTagNoState(_) => 1052,
+            Failure::FungibleStateNoTag(_) => 1053,
+            Failure::NoPrevState { .. } => 1054,
+            Failure::NoPrevOut(..) => 1055,
+            Failure::ConfidentialSeal(_) => 1056,
+            Failure::SealNoWitnessTx(_) => 1057,
+            Failure::SealWitnessLayer1Mismatch { .. } => 1058,
+            Failure::SealLayerMismatch(..) => 1059,
+            Failure::SealInvalidMethod(..) => 1060,
+            Failure::SealsInvalid(..) => 1061,
+            Failure::SealsUnvalidated(_) => 1062,
+            Failure::AnchorMethodMismatch(_) => 1063,
+            Failure::GenesisCloseMethodsInvalid => 1064,
+            Failure::CloseMethodForbidden(..) => 1065,
+            Failure::SchemaWitnessConstraintsInvalid => 1066,
+            Failure::WitnessSequenceForbidden(..) => 1067,
+            Failure::WitnessLocktimeAbsent(..) => 1068,
+            Failure::MpcInvalid(..) => 1069,
+            Failure::WitnessSubstitutionMismatch(..) => 1070,
+            Failure::WitnessSubstitutionInvalid(..) => 1071,
+            Failure::WitnessAlternativeInvalid(..) => 1072,
+            Failure::WitnessAlternativesMined(_) => 1073,
+            Failure::ValencyNoParent { .. } => 1074,
+            Failure::NoPrevValency { .. } => 1075,
+            Failure::StateTypeMismatch { .. } => 1076,
+            Failure::MediaTypeMismatch { .. } => 1077,
+            Failure::FungibleTypeMismatch { .. } => 1078,
+            Failure::BulletproofsInvalid(..) => 1079,
+            Failure::ScriptFailure(..) => 1080,
+            Failure::AssignmentLocksInvalid(_) => 1081,
+            Failure::OpFeaturesInvalid(_) => 1082,
+            Failure::OpFeaturesUnsupported(..) => 1083,
+            Failure::SupplyCapExceeded(..) => 1084,
+            Failure::OracleStateInvalid(..) => 1085,
+            Failure::ValencyLimitViolated(..) => 1086,
+            Failure::LockScriptFailure(..) => 1087,
+            Failure::AttachmentDigestMismatch(..) => 1088,
+            Failure::AttachmentMediaTypeMismatch { .. } => 1089,
+            Failure::AttachmentSizeExceeded(..) => 1090,
+            Failure::SchemaUpgradeInvalid(_) => 1091,
+            Failure::SchemaUpgradeUnavailable(..) => 1092,
+            Failure::SchemaUpgradeConflict(_) => 1093,
+            Failure::ValidationInterrupted(..) => 1096,
+            Failure::GenesisTemplateInvalid => 1097,
+            Failure::GenesisTemplateMismatch(..) => 1098,
+            Failure::InputSpent(..) => 1099,
+            Failure::SchemaUpgradeUndetermined(_) => 1100,
+            Failure::GenesisStateFormatsInvalid => 1101,
+            Failure::SupplyUncheckable(..) => 1102,
+            Failure::SchemaFlagsUnknown(_) => 1103,
+            Failure::SchemaNameUnknownType(_) => 1104,
+            Failure::LockUnverifiable(..) => 1105,
+        }
+    }
+
+    /// Parameters of the failure which can be used to format a localized
+    /// message for the [`Failure::reason_key`].
+    pub fn params(&self) -> ReasonParams {
+        match self {
+            Failure::NetworkMismatch(testnet) => params! { "testnet" => testnet },
+            Failure::ChainNetMismatch { expected, actual } => {
+                params! { "expected" => expected, "actual" => actual }
+            }
+            Failure::SchemaMismatch { expected, actual } => {
+                params! { "expected" => expected, "actual" => actual }
+            }
+            Failure::SchemaBlankTransitionRedefined => params! {},
+            Failure::SchemaFlagsUnknown(flags) => params! { "flags" => flags },
+            Failure::SchemaNameUnknownType(name) => params! { "name" => name },
+            Failure::SchemaGlobalSemIdUnknown(ty, sem_id) => {
+                params! { "state_type" => ty, "sem_id" => sem_id }
+            }
+            Failure::SchemaOwnedSemIdUnknown(ty, sem_id) => {
+                params! { "state_type" => ty, "sem_id" => sem_id }
+            }
+            Failure::SchemaMetaSemIdUnknown(ty, sem_id) => {
+                params! { "state_type" => ty, "sem_id" => sem_id }
+            }
+            Failure::SchemaLibUnknown(lib) => params! { "lib" => lib },
+            Failure::SchemaOpEmptyInputs(op_type) => params! { "op_type" => op_type },
+            Failure::SchemaOpMetaTypeUnknown(op_type, ty) => {
+                params! { "op_type" => op_type, "state_type" => ty }
+            }
+            Failure::SchemaOpGlobalTypeUnknown(op_type, ty) => {
+                params! { "op_type" => op_type, "state_type" => ty }
+            }
+            Failure::SchemaOpAssignmentTypeUnknown(op_type, ty) => {
+                params! { "op_type" => op_type, "state_type" => ty }
+            }
+            Failure::SchemaOpValencyTypeUnknown(op_type, ty) => {
+                params! { "op_type" => op_type, "state_type" => ty }
+            }
+            Failure::SchemaUnknownExtensionType(opid, ty) => {
+                params! { "opid" => opid, "op_type" => ty }
+            }
+            Failure::SchemaUnknownTransitionType(opid, ty) => {
+                params! { "opid" => opid, "op_type" => ty }
+            }
+            Failure::SchemaUnknownMetaType(opid, ty) => {
+                params! { "opid" => opid, "state_type" => ty }
+            }
+            Failure::SchemaUnknownGlobalStateType(opid, ty) => {
+                params! { "opid" => opid, "state_type" => ty }
+            }
+            Failure::SchemaUnknownAssignmentType(opid, ty) => {
+                params! { "opid" => opid, "state_type" => ty }
+            }
+            Failure::SchemaUnknownValencyType(opid, ty) => {
+                params! { "opid" => opid, "state_type" => ty }
+            }
+            Failure::SchemaGlobalStateOccurrences(opid, ty, mismatch) => {
+                params! { "opid" => opid, "state_type" => ty, "mismatch" => mismatch }
+            }
+            Failure::SchemaGlobalStateLimit(opid, ty, count, max) => {
+                params! { "opid" => opid, "state_type" => ty, "count" => count, "max" => max }
+            }
+            Failure::SchemaNoMetadata(opid, ty) => params! { "opid" => opid, "state_type" => ty },
+            Failure::SchemaInvalidMetadata(opid, sem_id) => {
+                params! { "opid" => opid, "sem_id" => sem_id }
+            }
+            Failure::SchemaInvalidGlobalValue(opid, ty, sem_id) => {
+                params! { "opid" => opid, "state_type" => ty, "sem_id" => sem_id }
+            }
+            Failure::SchemaInvalidOwnedValue(opid, ty, sem_id) => {
+                params! { "opid" => opid, "state_type" => ty, "sem_id" => sem_id }
+            }
+            Failure::SchemaInputOccurrences(opid, ty, mismatch) |
+            Failure::SchemaAssignmentOccurrences(opid, ty, mismatch) => {
+                params! { "opid" => opid, "state_type" => ty, "mismatch" => mismatch }
+            }
+            Failure::PresentationInvalidTicker(opid, ty) |
+            Failure::PresentationInvalidName(opid, ty) |
+            Failure::PresentationInvalidPrecision(opid, ty) => {
+                params! { "opid" => opid, "state_type" => ty }
+            }
+            Failure::CyclicGraph(opid) |
+            Failure::OperationAbsent(opid) |
+            Failure::SealsUnvalidated(opid) => params! { "opid" => opid },
+            Failure::TerminalBundleAbsent(bundle_id) |
+            Failure::BundleAbsent(bundle_id) |
+            Failure::AnchorAbsent(bundle_id) |
+            Failure::WitnessIdAbsent(bundle_id) |
+            Failure::AnchorMethodMismatch(bundle_id) => params! { "bundle_id" => bundle_id },
+            Failure::GenesisCloseMethodsInvalid => params! {},
+            Failure::CloseMethodForbidden(bundle_id, method) => {
+                params! { "bundle_id" => bundle_id, "close_method" => method }
+            }
+            Failure::SchemaWitnessConstraintsInvalid => params! {},
+            Failure::WitnessSequenceForbidden(bundle_id, witness_id, vin, sequence) => {
+                params! {
+                    "bundle_id" => bundle_id,
+                    "witness_id" => witness_id,
+                    "vin" => vin,
+                    "sequence" => sequence,
+                }
+            }
+            Failure::WitnessLocktimeAbsent(bundle_id, witness_id) => {
+                params! { "bundle_id" => bundle_id, "witness_id" => witness_id }
+            }
+            Failure::ContractMismatch(opid, contract_id) => {
+                params! { "opid" => opid, "contract_id" => contract_id }
+            }
+            Failure::GenesisDependenciesInvalid => params! {},
+            Failure::GenesisChainSaltInvalid => params! {},
+            Failure::GenesisSupplyCapsInvalid => params! {},
+            Failure::GenesisOracleFeedsInvalid => params! {},
+            Failure::GenesisFinalityDepthInvalid => params! {},
+            Failure::GenesisValencyLimitsInvalid => params! {},
+            Failure::GenesisTermsInvalid(err) => params! { "details" => err },
+            Failure::TermsRedefined(opid) => params! { "opid" => opid },
+            Failure::DependencyUnvalidated(contract_id) => params! { "contract_id" => contract_id },
+            Failure::SpentStateMismatch(opid, opout) => {
+                params! { "opid" => opid, "opout" => opout }
+            }
+            Failure::BundleExtraTransition(bundle_id, opid) => {
+                params! { "bundle_id" => bundle_id, "opid" => opid }
+            }
+            Failure::BundleInvalidInput(bundle_id, opid, witness_id) => {
+                params! { "bundle_id" => bundle_id, "opid" => opid, "witness_id" => witness_id }
+            }
+            Failure::BundleInvalidCommitment(bundle_id, vin, witness_id, opid) => params! {
+                "bundle_id" => bundle_id,
+                "vin" => vin,
+                "witness_id" => witness_id,
+                "opid" => opid,
+            },
+            Failure::AssetTagNoState(ty) | Failure::FungibleStateNoTag(ty) => {
+                params! { "state_type" => ty }
+            }
+            Failure::NoPrevState {
+                opid,
+                prev_id,
+                state_type,
+            } => params! { "opid" => opid, "prev_id" => prev_id, "state_type" => state_type },
+            Failure::NoPrevOut(opid, opout) => params! { "opid" => opid, "opout" => opout },
+            Failure::ConfidentialSeal(opout) => params! { "opout" => opout },
+            Failure::SealNoWitnessTx(witness_id) => params! { "witness_id" => witness_id },
+            Failure::SealWitnessLayer1Mismatch { seal, anchor } => {
+                params! { "seal" => seal, "anchor" => anchor }
+            }
+            Failure::SealLayerMismatch(layer1, seal) => {
+                params! { "layer1" => layer1, "seal" => seal }
+            }
+            Failure::SealInvalidMethod(bundle_id, seal) => {
+                params! { "bundle_id" => bundle_id, "seal" => seal }
+            }
+            Failure::SealsInvalid(bundle_id, witness_id, details) => params! {
+                "bundle_id" => bundle_id,
+                "witness_id" => witness_id,
+                "details" => details,
+            },
+            Failure::MpcInvalid(bundle_id, witness_id, err) => params! {
+                "bundle_id" => bundle_id,
+                "witness_id" => witness_id,
+                "details" => err,
+            },
+            Failure::WitnessSubstitutionMismatch(bundle_id, witness_id, replacement) => params! {
+                "bundle_id" => bundle_id,
+                "witness_id" => witness_id,
+                "replacement" => replacement,
+            },
+            Failure::WitnessSubstitutionInvalid(bundle_id, details) => {
+                params! { "bundle_id" => bundle_id, "details" => details }
+            }
+            Failure::WitnessAlternativeInvalid(bundle_id, witness_id, details) => params! {
+                "bundle_id" => bundle_id,
+                "witness_id" => witness_id,
+                "details" => details,
+            },
+            Failure::WitnessAlternativesMined(bundle_id) => params! { "bundle_id" => bundle_id },
+            Failure::ValencyNoParent {
+                opid,
+                prev_id,
+                valency,
+            } |
+            Failure::NoPrevValency {
+                opid,
+                prev_id,
+                valency,
+            } => params! { "opid" => opid, "prev_id" => prev_id, "valency" => valency },
+            Failure::StateTypeMismatch {
+                opid,
+                state_type,
+                expected,
+                found,
+            } => params! {
+                "opid" => opid,
+                "state_type" => state_type,
+                "expected" => expected,
+                "found" => found,
+            },
+            Failure::MediaTypeMismatch {
+                opid,
+                state_type,
+                expected,
+                found,
+            } => params! {
+                "opid" => opid,
+                "state_type" => state_type,
+                "expected" => expected,
+                "found" => found,
+            },
+            Failure::FungibleTypeMismatch {
+                opid,
+                state_type,
+                expected,
+                found,
+            } => params! {
+                "opid" => opid,
+                "state_type" => state_type,
+                "expected" => expected,
+                "found" => found,
+            },
+            Failure::BulletproofsInvalid(opid, ty, details) => {
+                params! { "opid" => opid, "state_type" => ty, "details" => details }
+            }
+            Failure::ScriptFailure(opid, code, message) => {
+                let mut params = params! { "opid" => opid };
+                if let Some(code) = code {
+                    params.insert("code", code.to_string());
+                }
+                if let Some(message) = message {
+                    params.insert("message", message.clone());
+                }
+                params
+            }
+            Failure::AssignmentLocksInvalid(opid) => params! { "opid" => opid },
+            Failure::OpFeaturesInvalid(opid) => params! { "opid" => opid },
+            Failure::OpFeaturesUnsupported(opid, features) => {
+                params! { "opid" => opid, "features" => features }
+            }
+            Failure::SupplyCapExceeded(opid, ty, cap) => {
+                params! { "opid" => opid, "state_type" => ty, "cap" => cap }
+            }
+            Failure::SupplyUncheckable(opid, ty) => params! { "opid" => opid, "state_type" => ty },
+            Failure::OracleStateInvalid(opid, err) => params! { "opid" => opid, "error" => err },
+            Failure::ValencyLimitViolated(opid, err) => params! { "opid" => opid, "error" => err },
+            Failure::LockScriptFailure(opid, opout, code) => {
+                let mut params = params! { "opid" => opid, "opout" => opout };
+                if let Some(code) = code {
+                    params.insert("code", code.to_string());
+                }
+                params
+            }
+            Failure::LockUnverifiable(opid, opout) => params! { "opid" => opid, "opout" => opout },
+            Failure::AttachmentDigestMismatch(opid, id) => params! { "opid" => opid, "id" => id },
+            Failure::AttachmentMediaTypeMismatch {
+                opid,
+                id,
+                expected,
+                found,
+            } => params! {
+                "opid" => opid,
+                "id" => id,
+                "expected" => expected,
+                "found" => found,
+            },
+            Failure::AttachmentSizeExceeded(opid, id, size) => {
+                params! { "opid" => opid, "id" => id, "size" => size }
+            }
+            Failure::SchemaUpgradeInvalid(opid) => params! { "opid" => opid },
+            Failure::SchemaUpgradeUnavailable(opid, schema_id) => {
+                params! { "opid" => opid, "schema_id" => schema_id }
+            }
+            Failure::SchemaUpgradeConflict(opid) => params! { "opid" => opid },
+            Failure::ValidationInterrupted(reason, seals, ops) => {
+                params! { "reason" => reason, "seals" => seals, "ops" => ops }
+            }
+            Failure::GenesisTemplateInvalid => params! {},
+            Failure::GenesisTemplateMismatch(declared, actual) => {
+                params! { "declared" => declared, "actual" => actual }
+            }
+            Failure::InputSpent(opid, opout, spender) => {
+                params! { "opid" => opid, "opout" => opout, "spender" => spender }
+            }
+            Failure::SchemaUpgradeUndetermined(opid) => params! { "opid" => opid },
+            Failure::GenesisStateFormatsInvalid => params! {},
+            Failure::Custom(message) => params! { "message" => message },
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Display, From)]
 #[cfg_attr(
     feature = "serde",
@@ -396,12 +1288,78 @@ pub enum Warning {
     TerminalSealAbsent(OpId, XChain<SecretSeal>),
     /// terminal witness transaction {0} is not yet mined.
     TerminalWitnessNotMined(Txid),
+    /// consignment hints that witness {0} is mined at height {1}, which
+    /// doesn't match the position reported by the resolver.
+    WitnessHintMismatch(XWitnessId, u32),
+    /// issuer signature provided with the consignment is invalid: {0}
+    IssuerSignatureInvalid(IssuerSignatureError),
+    /// outpoint {0} holding allocations of the contract also holds
+    /// allocations of contract {1}.
+    SealReused(XOutpoint, ContractId),
+    /// operation {0} violates standardness rule: {1}
+    NonStandard(OpId, NonStandard),
+    /// contract {0} uses legacy id which is not salted with the network it is
+    /// issued for.
+    ContractIdUnsalted(ContractId),
 
     /// Custom warning by external services on top of RGB Core.
     #[display(inner)]
     Custom(String),
 }
 
+impl Warning {
+    /// Stable machine-readable key identifying the reason of the warning.
+    pub fn reason_key(&self) -> &'static str {
+        match self {
+            Warning::TerminalSealAbsent(..) => "terminal.seal_absent",
+            Warning::TerminalWitnessNotMined(_) => "terminal.witness_not_mined",
+            Warning::WitnessHintMismatch(..) => "witness.hint_mismatch",
+            Warning::IssuerSignatureInvalid(_) => "genesis.issuer_signature_invalid",
+            Warning::SealReused(..) => "seal.reused",
+            Warning::NonStandard(..) => "op.non_standard",
+            Warning::ContractIdUnsalted(_) => "genesis.contract_id_unsalted",
+            Warning::Custom(_) => "custom",
+        }
+    }
+
+    /// Stable numeric code of the warning (see [`Failure::code`]).
+    pub fn code(&self) -> u16 {
+        match self {
+            Warning::Custom(_) => 2000,
+            Warning::TerminalSealAbsent(..) => 2001,
+            Warning::TerminalWitnessNotMined(_) => 2002,
+            Warning::WitnessHintMismatch(..) => 2003,
+            Warning::IssuerSignatureInvalid(_) => 2004,
+            Warning::SealReused(..) => 2005,
+            Warning::NonStandard(..) => 2006,
+            Warning::ContractIdUnsalted(_) => 2007,
+        }
+    }
+
+    /// Parameters of the warning which can be used to format a localized
+    /// message for the [`Warning::reason_key`].
+    pub fn params(&self) -> ReasonParams {
+        match self {
+            Warning::TerminalSealAbsent(opid, seal) => {
+                params! { "opid" => opid, "seal" => format!("{seal:?}") }
+            }
+            Warning::TerminalWitnessNotMined(txid) => params! { "txid" => txid },
+            Warning::WitnessHintMismatch(witness_id, height) => {
+                params! { "witness_id" => witness_id, "height" => height }
+            }
+            Warning::IssuerSignatureInvalid(IssuerSignatureError::Invalid(contract_id, key)) => {
+                params! { "contract_id" => contract_id, "key" => key }
+            }
+            Warning::SealReused(outpoint, contract_id) => {
+                params! { "outpoint" => outpoint, "contract_id" => contract_id }
+            }
+            Warning::NonStandard(opid, rule) => params! { "opid" => opid, "rule" => rule },
+            Warning::ContractIdUnsalted(contract_id) => params! { "contract_id" => contract_id },
+            Warning::Custom(message) => params! { "message" => message },
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Display, From)]
 #[cfg_attr(
     feature = "serde",
@@ -414,7 +1372,133 @@ pub enum Info {
     /// thus was not validated.
     UncheckableConfidentialState(OpId, schema::AssignmentType),
 
+    /// content of attachment {1} from operation {0} is not available and thus
+    /// was not verified.
+    AttachmentUnavailable(OpId, AttachId),
+
+    /// alternative witness {1} for bundle {0} is void, since another
+    /// alternative witness was mined.
+    WitnessVoid(BundleId, XWitnessId),
+
+    /// operation {0} contains global state of type {1} encoded with the older
+    /// version {2} of the state format.
+    GlobalStateOlderVersion(OpId, schema::GlobalStateType, StateVersion),
+
+    /// operation {0} contains owned state of type {1} encoded with the older
+    /// version {2} of the state format.
+    OwnedStateOlderVersion(OpId, schema::AssignmentType, StateVersion),
+
+    /// contract genesis is signed by the issuer key {0}.
+    IssuerSigned(XOnlyPublicKey),
+
+    /// witness {0} is not buried under the finality depth of the contract,
+    /// thus the state it defines is provisional.
+    WitnessProvisional(XWitnessId),
+
+    /// {0} (reported as information by the validation config).
+    WarningDemoted(Warning),
+
+    /// contract genesis is bound from the genesis template {0}.
+    GenesisTemplateBound(GenesisTemplateId),
+
+    /// lock scripts of the assignments spent by operation {0} were not
+    /// evaluated, since the operations defining them are not known.
+    LockScriptsUnchecked(OpId),
+
+    /// {1} units of state type {0} still can be issued under the supply cap
+    /// declared by the contract genesis, given the supply issued by the
+    /// validated operations.
+    SupplyRemaining(schema::AssignmentType, u64),
+
     /// Custom info by external services on top of RGB Core.
     #[display(inner)]
     Custom(String),
 }
+
+impl Info {
+    /// Stable machine-readable key identifying the reason of the information
+    /// message.
+    pub fn reason_key(&self) -> &'static str {
+        match self {
+            Info::UncheckableConfidentialState(..) => "state.uncheckable_confidential",
+            Info::AttachmentUnavailable(..) => "attachment.unavailable",
+            Info::WitnessVoid(..) => "anchor.witness_void",
+            Info::GlobalStateOlderVersion(..) => "state.global_older_version",
+            Info::OwnedStateOlderVersion(..) => "state.owned_older_version",
+            Info::IssuerSigned(_) => "genesis.issuer_signed",
+            Info::WitnessProvisional(_) => "witness.provisional",
+            Info::WarningDemoted(_) => "config.warning_demoted",
+            Info::GenesisTemplateBound(_) => "genesis.template_bound",
+            Info::LockScriptsUnchecked(_) => "script.lock_unchecked",
+            Info::SupplyRemaining(..) => "state.supply_remaining",
+            Info::Custom(_) => "custom",
+        }
+    }
+
+    /// Stable numeric code of the information message (see
+    /// [`Failure::code`]).
+    pub fn code(&self) -> u16 {
+        match self {
+            Info::Custom(_) => 3000,
+            Info::UncheckableConfidentialState(..) => 3001,
+            Info::AttachmentUnavailable(..) => 3002,
+            Info::WitnessVoid(..) => 3003,
+            Info::GlobalStateOlderVersion(..) => 3004,
+            Info::OwnedStateOlderVersion(..) => 3005,
+            Info::IssuerSigned(_) => 3006,
+            Info::WitnessProvisional(_) => 3007,
+            Info::WarningDemoted(_) => 3008,
+            Info::GenesisTemplateBound(_) => 3009,
+            Info::LockScriptsUnchecked(_) => 3010,
+            Info::SupplyRemaining(..) => 3011,
+        }
+    }
+
+    /// Parameters of the information message which can be used to format a
+    /// localized message for the [`Info::reason_key`].
+    pub fn params(&self) -> ReasonParams {
+        match self {
+            Info::UncheckableConfidentialState(opid, ty) => {
+                params! { "opid" => opid, "state_type" => ty }
+            }
+            Info::AttachmentUnavailable(opid, id) => params! { "opid" => opid, "id" => id },
+            Info::WitnessVoid(bundle_id, witness_id) => {
+                params! { "bundle_id" => bundle_id, "witness_id" => witness_id }
+            }
+            Info::GlobalStateOlderVersion(opid, ty, version) => {
+                params! { "opid" => opid, "state_type" => ty, "version" => version }
+            }
+            Info::OwnedStateOlderVersion(opid, ty, version) => {
+                params! { "opid" => opid, "state_type" => ty, "version" => version }
+            }
+            Info::IssuerSigned(key) => params! { "key" => key },
+            Info::WitnessProvisional(witness_id) => params! { "witness_id" => witness_id },
+            Info::WarningDemoted(warning) => {
+                let mut params = warning.params();
+                params.insert("warning", warning.reason_key().to_owned());
+                params
+            }
+            Info::GenesisTemplateBound(id) => params! { "template_id" => id },
+            Info::LockScriptsUnchecked(opid) => params! { "opid" => opid },
+            Info::SupplyRemaining(ty, remaining) => {
+                params! { "state_type" => ty, "remaining" => remaining }
+            }
+            Info::Custom(message) => params! { "message" => message },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn failure_reason_params() {
+        let opid = OpId::from([0xA5; 32]);
+        let failure = Failure::SchemaUnknownTransitionType(opid, schema::TransitionType::with(7));
+        assert_eq!(failure.reason_key(), "schema.unknown_transition_type");
+        let params = failure.params();
+        assert_eq!(params.get("opid"), Some(&opid.to_string()));
+        assert_eq!(params.get("op_type"), Some(&s!("0x0007")));
+    }
+}
@@ -0,0 +1,196 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Persistence of validation outcomes across validator runs.
+//!
+//! Validation of the operation against the schema and of the bundle seal
+//! closing against its witness are deterministic procedures for a given
+//! contract, schema and network. Their successful outcomes can be saved and
+//! reused by the node software, such that the same history is not
+//! re-validated after restart. Failures are never saved, since they may be
+//! caused by the data of a specific consignment (for instance, absent previous
+//! operations) or by an unavailable witness resolver, and must not affect
+//! validation of other consignments.
+//!
+//! The same store can be used for the validation of different consignments
+//! of a contract: since the operation id commits to the contract and all the
+//! spent state, incremental transfers of long-history assets skip
+//! re-verification of the already known history.
+
+use std::collections::BTreeMap;
+
+use amplify::{ByteArray, Bytes32};
+use commit_verify::{DigestExt, Sha256};
+
+use super::Status;
+use crate::{BundleId, ContractId, OpId, SchemaId, XWitnessId};
+
+pub const VALIDATION_CONTEXT_TAG: &[u8] = b"urn:lnp-bp:rgb:validation-context#2024-05-20";
+
+/// Hash of the context in which validation outcomes were produced.
+///
+/// Commits to the contract id, schema id and network, such that a verdict
+/// obtained under one context never gets reused under another one.
+#[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From)]
+#[wrapper(Deref, BorrowSlice, Hex, Index, RangeOps)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", transparent)
+)]
+pub struct ValidationContext(
+    #[from]
+    #[from([u8; 32])]
+    Bytes32,
+);
+
+impl ValidationContext {
+    pub fn with(contract_id: ContractId, schema_id: SchemaId, testnet: bool) -> Self {
+        let mut hasher = Sha256::default();
+        hasher.input_raw(VALIDATION_CONTEXT_TAG);
+        hasher.input_raw(&contract_id.to_byte_array());
+        hasher.input_raw(&schema_id.to_byte_array());
+        hasher.input_raw(&[testnet as u8]);
+        ValidationContext::from(hasher.finish())
+    }
+}
+
+/// Storage for the validation outcomes, consulted and populated by the
+/// [`super::Validator`].
+///
+/// Only valid outcomes which do not depend on the consignment, the witness
+/// resolver or the chain tip are stored: the validator never passes statuses
+/// with failures to the store, nor the statuses of state transitions whose
+/// witnesses are not buried under the finality depth.
+pub trait ValidationStore {
+    /// Returns status of the operation validation against the contract schema
+    /// and scripts, if it was previously stored.
+    fn op_status(&self, context: ValidationContext, opid: OpId) -> Option<Status>;
+
+    /// Saves status of the operation validation against the contract schema
+    /// and scripts.
+    fn store_op_status(&mut self, context: ValidationContext, opid: OpId, status: &Status);
+
+    /// Returns status of the seal closing validation for the bundle by a given
+    /// witness, if it was previously stored.
+    fn witness_status(
+        &self,
+        context: ValidationContext,
+        witness_id: XWitnessId,
+        bundle_id: BundleId,
+    ) -> Option<Status>;
+
+    /// Saves status of the seal closing validation for the bundle by a given
+    /// witness.
+    fn store_witness_status(
+        &mut self,
+        context: ValidationContext,
+        witness_id: XWitnessId,
+        bundle_id: BundleId,
+        status: &Status,
+    );
+}
+
+/// In-memory implementation of the [`ValidationStore`].
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct MemValidationStore {
+    ops: BTreeMap<(ValidationContext, OpId), Status>,
+    witnesses: BTreeMap<(ValidationContext, XWitnessId, BundleId), Status>,
+}
+
+impl MemValidationStore {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn len(&self) -> usize { self.ops.len() + self.witnesses.len() }
+
+    pub fn is_empty(&self) -> bool { self.ops.is_empty() && self.witnesses.is_empty() }
+
+    pub fn clear(&mut self) {
+        self.ops.clear();
+        self.witnesses.clear();
+    }
+}
+
+impl ValidationStore for MemValidationStore {
+    fn op_status(&self, context: ValidationContext, opid: OpId) -> Option<Status> {
+        self.ops.get(&(context, opid)).cloned()
+    }
+
+    fn store_op_status(&mut self, context: ValidationContext, opid: OpId, status: &Status) {
+        self.ops.insert((context, opid), status.clone());
+    }
+
+    fn witness_status(
+        &self,
+        context: ValidationContext,
+        witness_id: XWitnessId,
+        bundle_id: BundleId,
+    ) -> Option<Status> {
+        self.witnesses
+            .get(&(context, witness_id, bundle_id))
+            .cloned()
+    }
+
+    fn store_witness_status(
+        &mut self,
+        context: ValidationContext,
+        witness_id: XWitnessId,
+        bundle_id: BundleId,
+        status: &Status,
+    ) {
+        self.witnesses
+            .insert((context, witness_id, bundle_id), status.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::validation::Warning;
+
+    #[test]
+    fn context_commits_to_network() {
+        let contract_id = ContractId::from([1u8; 32]);
+        let schema_id = SchemaId::from([2u8; 32]);
+        assert_ne!(
+            ValidationContext::with(contract_id, schema_id, true),
+            ValidationContext::with(contract_id, schema_id, false)
+        );
+    }
+
+    #[test]
+    fn mem_store_roundtrip() {
+        let context = ValidationContext::with([1u8; 32].into(), [2u8; 32].into(), false);
+        let opid = OpId::from([3u8; 32]);
+        let mut status = Status::new();
+        status.add_warning(Warning::Custom("unknown extension".to_owned()));
+
+        let mut store = MemValidationStore::new();
+        assert_eq!(store.op_status(context, opid), None);
+        store.store_op_status(context, opid, &status);
+        assert_eq!(store.op_status(context, opid), Some(status));
+
+        let other = ValidationContext::with([1u8; 32].into(), [2u8; 32].into(), true);
+        assert_eq!(store.op_status(other, opid), None);
+        assert_eq!(store.len(), 1);
+    }
+}
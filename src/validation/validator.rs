@@ -20,21 +20,40 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::cell::RefCell;
-use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
+use std::iter;
+use std::time::Duration;
 
+use aluvm::library::LibId;
 use bp::dbc::Anchor;
 use bp::seals::txout::{CloseMethod, TxoSeal, Witness};
 use bp::{dbc, Outpoint};
 use commit_verify::mpc;
 use single_use_seals::SealWitness;
+use strict_types::{SemId, TypeSystem};
 
-use super::status::{Failure, Warning};
-use super::{CheckedConsignment, ConsignmentApi, Status, Validity};
+use super::cancel::StopConditions;
+use super::prefetch::consignment_witnesses;
+use super::resolver_cache::DEFAULT_CLOCK;
+use super::standard::reject_non_standard;
+use super::status::{Failure, Info, Warning};
+use super::{
+    check_standardness, missing_libs, verify_op_attachments, verify_with_plugin,
+    AttachmentVerifier, CacheClock, CancelToken, CheckedConsignment, CloseMethodRegistry,
+    ConsignmentApi, ConsignmentDag, DagWalker, LocalPolicy, ParentRef, PolicyViolation, SpentState,
+    Status, ValidationConfig, ValidationContext, ValidationMode, ValidationStore, Validity,
+    WitnessHint, WitnessMetrics, CACHE_FINAL_DEPTH,
+};
+use crate::schema::{GlobalStateType, PresentationRules, StateKey, StateRegistry, StateVersion};
+use crate::vm::RgbIsa;
 use crate::{
-    AltLayer1, BundleId, ContractId, DbcProof, EAnchor, Layer1, OpId, OpRef, OpType, Operation,
-    Opout, Schema, SchemaId, TransitionBundle, TypedAssigns, XChain, XOutpoint, XOutputSeal,
-    XWitnessId, XWitnessTx,
+    AltLayer1, BundleId, ChainNet, CloseMethodSet, ContractId, ContractIdVersion, DbcProof,
+    DisclosureLevel, EAnchor, FinalityDepth, Genesis, Layer1, OpFeatures, OpId, OpRef, OpType,
+    Operation, Opout, OracleError, OracleFeed, OracleTracker, Schema, SchemaId, SchemaUpgrade,
+    SealUsage, SupplyError, SupplyTracker, Transition, TransitionBundle, TypedAssigns,
+    ValencyTracker, WitnessAnchor, WitnessConstraints, WitnessOrd, WitnessSubstitution, XChain,
+    XOutpoint, XOutputSeal, XWitnessId, XWitnessTx,
 };
 
 #[derive(Clone, Debug, Display, Error, From)]
@@ -52,10 +71,295 @@ pub trait ResolveWitness {
         &self,
         witness_id: XWitnessId,
     ) -> Result<XWitnessTx, WitnessResolverError>;
+
+    /// Resolves the witness transaction using the hint on its position
+    /// provided by the consignment.
+    ///
+    /// Resolvers may fetch the transaction from the hinted block first, but
+    /// must never trust the hint and fall back to
+    /// [`ResolveWitness::resolve_pub_witness`] if the transaction is not
+    /// there. Resolvers not supporting hints ignore them.
+    fn resolve_pub_witness_hinted(
+        &self,
+        witness_id: XWitnessId,
+        _hint: WitnessHint,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        self.resolve_pub_witness(witness_id)
+    }
+
+    /// Resolves position of the witness transaction in the blockchain, which
+    /// is made available to the contract scripts.
+    ///
+    /// Resolvers not supporting this return [`WitnessResolverError::Unknown`],
+    /// in which case scripts querying the witness height fail.
+    fn resolve_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        Err(WitnessResolverError::Unknown(witness_id))
+    }
+
+    /// Resolves position of the witness transaction using the hint provided
+    /// by the consignment.
+    ///
+    /// Resolvers may check the inclusion of the witness into the hinted block
+    /// first, avoiding the full lookup, but must never trust the hint and
+    /// fall back to [`ResolveWitness::resolve_witness_ord`] if the check
+    /// fails. Resolvers not supporting hints ignore them.
+    fn resolve_witness_ord_hinted(
+        &self,
+        witness_id: XWitnessId,
+        _hint: WitnessHint,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        self.resolve_witness_ord(witness_id)
+    }
+
+    /// Resolves height of the chain tip for the layer 1, which is used to
+    /// detect witnesses within the finality depth of the contract.
+    ///
+    /// Resolvers not supporting this return `None`, in which case all
+    /// witnesses of contracts declaring finality depth are provisional.
+    fn resolve_tip_height(&self, _layer1: Layer1) -> Option<u32> { None }
+
+    /// Resolves a batch of witness transactions at once, allowing resolvers
+    /// working over remote APIs to issue a single batched request.
+    ///
+    /// The validator calls this method with all witnesses of the consignment
+    /// and the hints it provides for them (see
+    /// [`ResolveWitness::resolve_pub_witness_hinted`]) before validating their
+    /// commitments, and resolves witnesses missing in the returned map one by
+    /// one. Resolvers not supporting batch requests return an empty map.
+    fn resolve_pub_witnesses(
+        &self,
+        _witnesses: &BTreeMap<XWitnessId, Option<WitnessHint>>,
+    ) -> BTreeMap<XWitnessId, Result<XWitnessTx, WitnessResolverError>> {
+        empty!()
+    }
+
+    /// Resolves positions of a batch of witness transactions at once, using
+    /// the hints provided by the consignment (see
+    /// [`ResolveWitness::resolve_witness_ord_hinted`]).
+    ///
+    /// Works like [`ResolveWitness::resolve_pub_witnesses`]: resolvers not
+    /// supporting batch requests return an empty map.
+    fn resolve_witness_ords(
+        &self,
+        _witnesses: &BTreeMap<XWitnessId, Option<WitnessHint>>,
+    ) -> BTreeMap<XWitnessId, Result<WitnessOrd, WitnessResolverError>> {
+        empty!()
+    }
+}
+
+/// Resolver shared by the validations of a batch of consignments, which
+/// resolves each witness only once.
+///
+/// Resolver errors are not shared: a witness which failed to resolve is
+/// resolved again for the next consignment, since the failure may be
+/// transient.
+struct BatchResolver<'resolver, R: ResolveWitness> {
+    inner: &'resolver R,
+    txes: RefCell<BTreeMap<XWitnessId, XWitnessTx>>,
+    ords: RefCell<BTreeMap<XWitnessId, WitnessOrd>>,
+}
+
+impl<'resolver, R: ResolveWitness> BatchResolver<'resolver, R> {
+    fn new(inner: &'resolver R) -> Self {
+        BatchResolver {
+            inner,
+            txes: empty!(),
+            ords: empty!(),
+        }
+    }
+
+    fn resolve_tx(
+        &self,
+        witness_id: XWitnessId,
+        resolve: impl FnOnce() -> Result<XWitnessTx, WitnessResolverError>,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        if let Some(tx) = self.txes.borrow().get(&witness_id) {
+            return Ok(tx.clone());
+        }
+        let tx = resolve()?;
+        self.txes.borrow_mut().insert(witness_id, tx.clone());
+        Ok(tx)
+    }
+
+    fn resolve_ord(
+        &self,
+        witness_id: XWitnessId,
+        resolve: impl FnOnce() -> Result<WitnessOrd, WitnessResolverError>,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        if let Some(ord) = self.ords.borrow().get(&witness_id) {
+            return Ok(*ord);
+        }
+        let ord = resolve()?;
+        self.ords.borrow_mut().insert(witness_id, ord);
+        Ok(ord)
+    }
+}
+
+impl<'resolver, R: ResolveWitness> ResolveWitness for BatchResolver<'resolver, R> {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        self.resolve_tx(witness_id, || self.inner.resolve_pub_witness(witness_id))
+    }
+
+    fn resolve_pub_witness_hinted(
+        &self,
+        witness_id: XWitnessId,
+        hint: WitnessHint,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        self.resolve_tx(witness_id, || self.inner.resolve_pub_witness_hinted(witness_id, hint))
+    }
+
+    fn resolve_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        self.resolve_ord(witness_id, || self.inner.resolve_witness_ord(witness_id))
+    }
+
+    fn resolve_witness_ord_hinted(
+        &self,
+        witness_id: XWitnessId,
+        hint: WitnessHint,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        self.resolve_ord(witness_id, || self.inner.resolve_witness_ord_hinted(witness_id, hint))
+    }
+
+    fn resolve_tip_height(&self, layer1: Layer1) -> Option<u32> {
+        self.inner.resolve_tip_height(layer1)
+    }
+
+    fn resolve_pub_witnesses(
+        &self,
+        witnesses: &BTreeMap<XWitnessId, Option<WitnessHint>>,
+    ) -> BTreeMap<XWitnessId, Result<XWitnessTx, WitnessResolverError>> {
+        let missing = witnesses
+            .iter()
+            .filter(|(witness_id, _)| !self.txes.borrow().contains_key(witness_id))
+            .map(|(witness_id, hint)| (*witness_id, *hint))
+            .collect::<BTreeMap<_, _>>();
+        let mut resolved = if missing.is_empty() {
+            BTreeMap::new()
+        } else {
+            self.inner.resolve_pub_witnesses(&missing)
+        };
+        let mut txes = self.txes.borrow_mut();
+        for (witness_id, res) in &resolved {
+            if let Ok(tx) = res {
+                txes.insert(*witness_id, tx.clone());
+            }
+        }
+        for witness_id in witnesses.keys() {
+            if let Some(tx) = txes.get(witness_id) {
+                resolved.insert(*witness_id, Ok(tx.clone()));
+            }
+        }
+        resolved.retain(|witness_id, _| witnesses.contains_key(witness_id));
+        resolved
+    }
+
+    fn resolve_witness_ords(
+        &self,
+        witnesses: &BTreeMap<XWitnessId, Option<WitnessHint>>,
+    ) -> BTreeMap<XWitnessId, Result<WitnessOrd, WitnessResolverError>> {
+        let missing = witnesses
+            .iter()
+            .filter(|(witness_id, _)| !self.ords.borrow().contains_key(witness_id))
+            .map(|(witness_id, hint)| (*witness_id, *hint))
+            .collect::<BTreeMap<_, _>>();
+        let mut resolved = if missing.is_empty() {
+            BTreeMap::new()
+        } else {
+            self.inner.resolve_witness_ords(&missing)
+        };
+        let mut ords = self.ords.borrow_mut();
+        for (witness_id, res) in &resolved {
+            if let Ok(ord) = res {
+                ords.insert(*witness_id, *ord);
+            }
+        }
+        for witness_id in witnesses.keys() {
+            if let Some(ord) = ords.get(witness_id) {
+                resolved.insert(*witness_id, Ok(*ord));
+            }
+        }
+        resolved.retain(|witness_id, _| witnesses.contains_key(witness_id));
+        resolved
+    }
+}
+
+/// Outcomes shared by the validations of a batch of consignments.
+#[derive(Default)]
+struct BatchCache {
+    /// Results of the schema verification against the type system.
+    schemas: RefCell<BTreeMap<SchemaId, (TypeSystem, Status)>>,
+    /// Results of the library code analysis: whether the library reads the
+    /// witness position.
+    libs: RefCell<BTreeMap<LibId, bool>>,
+}
+
+/// Optional extensions to the validation procedure provided by the software
+/// running the validation.
+#[derive(Default)]
+pub struct ValidationHooks<'hooks> {
+    /// Storage of validation outcomes, which is consulted and populated by the
+    /// validator.
+    pub store: Option<&'hooks mut dyn ValidationStore>,
+    /// Source of attachment content used to verify attachments against their
+    /// declarations.
+    pub attachments: Option<&'hooks dyn AttachmentVerifier>,
+    /// Asset presentation rules applied to the contract genesis.
+    pub presentation: Option<&'hooks PresentationRules>,
+    /// Disclosure level the consignment is expected to be constructed at.
+    /// With [`DisclosureLevel::AmountsConcealed`] each concealed fungible
+    /// assignment is additionally reported as uncheckable. The disclosure
+    /// level never affects the validity: concealed fungible state lacking
+    /// valid range proofs always fails the validation.
+    pub disclosure: DisclosureLevel,
+    /// Ids of the contracts which were already validated by the caller. All
+    /// dependencies declared by the contract genesis must be present here;
+    /// see [`crate::DependencyGraph`] for the validation order.
+    pub validated_contracts: Option<&'hooks BTreeSet<ContractId>>,
+    /// Local policies applied to each validated operation. Policy violations
+    /// are reported separately from the consensus failures.
+    pub policies: Vec<&'hooks dyn LocalPolicy>,
+    /// Plugins implementing seal close methods which have no built-in
+    /// verifiers.
+    pub close_method_plugins: Option<&'hooks CloseMethodRegistry<'hooks>>,
+    /// Outpoints used by other contracts known to the software; reuse of
+    /// them by the validated contract is reported with warnings.
+    pub seal_usage: Option<&'hooks SealUsage>,
+    /// Network the software operates on. If provided, contracts with ids
+    /// salted for a different network are rejected, and contracts with the
+    /// legacy non-salted ids are reported with a warning.
+    pub chain_net: Option<ChainNet>,
+    /// Operation features supported by the software, including the ones not
+    /// known to the library. Defaults to [`OpFeatures::KNOWN`].
+    pub supported_features: Option<OpFeatures>,
+    /// Strictness of the validation and severity adjustments applied to the
+    /// validation status, like locally rejecting consignments with selected
+    /// warnings.
+    pub config: ValidationConfig,
+    /// Token allowing to stop the validation from another thread. Stopped
+    /// validation returns the status collected so far, which is always
+    /// invalid.
+    pub cancel: Option<CancelToken>,
+    /// Time, measured by the [`ValidationHooks::clock`], after which the
+    /// validation is stopped like with the [`ValidationHooks::cancel`] token.
+    pub deadline: Option<Duration>,
+    /// Clock measuring the [`ValidationHooks::deadline`]. Defaults to the
+    /// system time since the Unix epoch, and is required for the deadline to
+    /// be checked on WebAssembly targets.
+    pub clock: Option<CacheClock>,
 }
 
 pub struct Validator<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness> {
     consignment: CheckedConsignment<'consignment, C>,
+    dag: ConsignmentDag,
 
     status: RefCell<Status>,
 
@@ -63,17 +367,51 @@ pub struct Validator<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitne
     genesis_id: OpId,
     contract_id: ContractId,
     layers1: BTreeSet<Layer1>,
+    close_methods: CloseMethodSet,
+    witness_constraints: WitnessConstraints,
+    dependencies: BTreeSet<ContractId>,
+    contract_id_version: ContractIdVersion,
+    reserves_types: bool,
+    supply: RefCell<SupplyTracker>,
+    oracle_feeds: BTreeSet<OracleFeed>,
+    finality: FinalityDepth,
+    valencies: RefCell<ValencyTracker>,
 
     validated_op_seals: RefCell<BTreeSet<OpId>>,
     validated_op_state: RefCell<BTreeSet<OpId>>,
+    replaced_witnesses: RefCell<BTreeMap<XWitnessId, XWitnessId>>,
+    hint_mismatches: RefCell<BTreeMap<XWitnessId, u32>>,
+    prefetched_txes: BTreeMap<XWitnessId, Result<XWitnessTx, WitnessResolverError>>,
+    prefetched_ords: BTreeMap<XWitnessId, Result<WitnessOrd, WitnessResolverError>>,
+    upgrades: BTreeMap<OpId, Vec<SchemaUpgrade>>,
+    state_registry: StateRegistry,
+    witness_scripts: Cell<Option<bool>>,
 
     resolver: &'resolver R,
+    store: Option<RefCell<&'resolver mut dyn ValidationStore>>,
+    attachments: Option<&'resolver dyn AttachmentVerifier>,
+    presentation: Option<&'resolver PresentationRules>,
+    disclosure: DisclosureLevel,
+    validated_contracts: Option<&'resolver BTreeSet<ContractId>>,
+    policies: Vec<&'resolver dyn LocalPolicy>,
+    close_method_plugins: Option<&'resolver CloseMethodRegistry<'resolver>>,
+    batch: Option<&'resolver BatchCache>,
+    seal_usage: Option<&'resolver SealUsage>,
+    mode: ValidationMode,
+    chain_net: Option<ChainNet>,
+    supported_features: OpFeatures,
+    stop: StopConditions,
+    interrupted: Cell<bool>,
+    known_ords: Option<BTreeMap<XWitnessId, WitnessOrd>>,
+    context: ValidationContext,
+    #[cfg(feature = "parallel")]
+    parallel: bool,
 }
 
 impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
     Validator<'consignment, 'resolver, C, R>
 {
-    fn init(consignment: &'consignment C, resolver: &'resolver R) -> Self {
+    fn init(consignment: &'consignment C, resolver: &'resolver R, testnet: bool) -> Self {
         // We use validation status object to store all detected failures and
         // warnings
         let mut status = Status::default();
@@ -115,19 +453,126 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
         let validated_op_state = RefCell::new(BTreeSet::<OpId>::new());
         let validated_op_seals = RefCell::new(BTreeSet::<OpId>::new());
 
+        // Contract parameters are declared with the reserved types only if the
+        // schema opts into them; otherwise these are ordinary schema types
+        let reserves_types = consignment.schema().reserves_types();
+        let ordinary;
+        let genesis = if reserves_types {
+            genesis
+        } else {
+            ordinary = strip_reserved(genesis);
+            &ordinary
+        };
+        let close_methods = genesis.close_methods().unwrap_or_else(|| {
+            status.add_failure(Failure::GenesisCloseMethodsInvalid);
+            CloseMethodSet::ALL
+        });
+        let witness_constraints = consignment.schema().witness_constraints;
+        let dependencies = genesis.dependencies().unwrap_or_else(|| {
+            status.add_failure(Failure::GenesisDependenciesInvalid);
+            empty!()
+        });
+        let contract_id_version = genesis.contract_id_version().unwrap_or_else(|| {
+            status.add_failure(Failure::GenesisChainSaltInvalid);
+            ContractIdVersion::V0
+        });
+        let supply = SupplyTracker::from_genesis(genesis).unwrap_or_else(|| {
+            status.add_failure(Failure::GenesisSupplyCapsInvalid);
+            SupplyTracker::default()
+        });
+        let oracle_feeds = genesis.oracle_feeds().unwrap_or_else(|| {
+            status.add_failure(Failure::GenesisOracleFeedsInvalid);
+            empty!()
+        });
+        let finality = genesis.finality_depth().unwrap_or_else(|| {
+            status.add_failure(Failure::GenesisFinalityDepthInvalid);
+            FinalityDepth::default()
+        });
+        let valencies = ValencyTracker::from_genesis(genesis).unwrap_or_else(|| {
+            status.add_failure(Failure::GenesisValencyLimitsInvalid);
+            ValencyTracker::default()
+        });
+        let state_registry = genesis
+            .state_formats()
+            .and_then(|formats| StateRegistry::from_formats(formats, consignment.types()).ok())
+            .unwrap_or_else(|| {
+                status.add_failure(Failure::GenesisStateFormatsInvalid);
+                StateRegistry::default()
+            });
+        if let Some(Err(err)) = genesis.terms() {
+            status.add_failure(Failure::GenesisTermsInvalid(err));
+        }
+        match genesis.declared_template_id() {
+            None => {
+                status.add_failure(Failure::GenesisTemplateInvalid);
+            }
+            Some(Some(declared)) => {
+                let actual = genesis.template_id();
+                if declared == actual {
+                    status.add_info(Info::GenesisTemplateBound(actual));
+                } else {
+                    status.add_failure(Failure::GenesisTemplateMismatch(declared, actual));
+                }
+            }
+            Some(None) => {}
+        }
+
         let mut layers1 = bset! { Layer1::Bitcoin };
         layers1.extend(genesis.alt_layers1.iter().map(AltLayer1::layer1));
 
+        let context = ValidationContext::with(contract_id, schema_id, testnet);
+        let dag = ConsignmentDag::with(&consignment);
+        let upgrades = if reserves_types {
+            schema_upgrades(&consignment, &dag, &mut status)
+        } else {
+            empty!()
+        };
+
         Self {
             consignment,
+            dag,
             status: RefCell::new(status),
             schema_id,
             genesis_id,
             contract_id,
             layers1,
+            close_methods,
+            witness_constraints,
+            dependencies,
+            contract_id_version,
+            reserves_types,
+            supply: RefCell::new(supply),
+            oracle_feeds,
+            finality,
+            valencies: RefCell::new(valencies),
             validated_op_state,
             validated_op_seals,
+            replaced_witnesses: RefCell::new(bmap! {}),
+            hint_mismatches: RefCell::new(bmap! {}),
+            prefetched_txes: empty!(),
+            prefetched_ords: empty!(),
+            upgrades,
+            state_registry,
+            witness_scripts: Cell::new(None),
             resolver,
+            store: None,
+            attachments: None,
+            presentation: None,
+            disclosure: DisclosureLevel::Full,
+            validated_contracts: None,
+            policies: vec![],
+            close_method_plugins: None,
+            batch: None,
+            seal_usage: None,
+            mode: ValidationMode::Consensus,
+            chain_net: None,
+            supported_features: OpFeatures::KNOWN,
+            stop: StopConditions::default(),
+            interrupted: Cell::new(false),
+            known_ords: None,
+            context,
+            #[cfg(feature = "parallel")]
+            parallel: false,
         }
     }
 
@@ -141,40 +586,478 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
     /// rest of the consignment data. This can help it debugging and
     /// detecting all problems with the consignment.
     pub fn validate(consignment: &'consignment C, resolver: &'resolver R, testnet: bool) -> Status {
-        let mut validator = Validator::init(consignment, resolver);
+        let validator = Validator::init(consignment, resolver, testnet);
+        validator.run(consignment, testnet)
+    }
+
+    /// Validation procedure which works like [`Validator::validate`], but
+    /// validates commitments of the consignment bundles concurrently.
+    #[cfg(feature = "parallel")]
+    pub(super) fn validate_concurrently(
+        consignment: &'consignment C,
+        resolver: &'resolver R,
+        testnet: bool,
+    ) -> Status {
+        let mut validator = Validator::init(consignment, resolver, testnet);
+        validator.parallel = true;
+        validator.run(consignment, testnet)
+    }
+
+    /// Validation procedure which works like [`Validator::validate`], but
+    /// consults the provided validation store for the outcomes of operations
+    /// and witnesses validated before, and saves outcomes of all newly
+    /// performed validations into it.
+    pub fn validate_with_store(
+        consignment: &'consignment C,
+        resolver: &'resolver R,
+        store: &'resolver mut dyn ValidationStore,
+        testnet: bool,
+    ) -> Status {
+        let hooks = ValidationHooks {
+            store: Some(store),
+            ..default!()
+        };
+        Self::validate_with_hooks(consignment, resolver, hooks, testnet)
+    }
+
+    /// Validation procedure which works like [`Validator::validate`], using
+    /// additionally the provided validation hooks.
+    pub fn validate_with_hooks(
+        consignment: &'consignment C,
+        resolver: &'resolver R,
+        hooks: ValidationHooks<'resolver>,
+        testnet: bool,
+    ) -> Status {
+        let mut validator = Validator::init(consignment, resolver, testnet);
+        validator.store = hooks.store.map(RefCell::new);
+        validator.attachments = hooks.attachments;
+        validator.presentation = hooks.presentation;
+        validator.disclosure = hooks.disclosure;
+        validator.validated_contracts = hooks.validated_contracts;
+        validator.policies = hooks.policies;
+        validator.close_method_plugins = hooks.close_method_plugins;
+        validator.seal_usage = hooks.seal_usage;
+        validator.mode = hooks.config.mode;
+        validator.chain_net = hooks.chain_net;
+        validator.supported_features = hooks.supported_features.unwrap_or(OpFeatures::KNOWN);
+        validator.stop = StopConditions {
+            cancel: hooks.cancel,
+            deadline: hooks.deadline,
+            clock: hooks.clock.or(DEFAULT_CLOCK),
+        };
+        let mut status = validator.run(consignment, testnet);
+        hooks.config.apply(&mut status);
+        status
+    }
+
+    /// Validates a batch of consignments, returning validation status for
+    /// each of them in the same order.
+    ///
+    /// Works like [`Validator::validate`] for each of the consignments, but
+    /// resolves each witness only once for the whole batch and reuses results
+    /// of the schema verification between consignments of the same schema and
+    /// type system. Intended for services processing many small consignments
+    /// at once, which often share witness transactions and schemata.
+    pub fn validate_batch(
+        consignments: impl IntoIterator<Item = &'consignment C>,
+        resolver: &'resolver R,
+        testnet: bool,
+    ) -> Vec<Status> {
+        let resolver = BatchResolver::new(resolver);
+        let cache = BatchCache::default();
+        consignments
+            .into_iter()
+            .map(|consignment| {
+                let mut validator = Validator::init(consignment, &resolver, testnet);
+                validator.batch = Some(&cache);
+                validator.run(consignment, testnet)
+            })
+            .collect()
+    }
+
+    /// Revalidates an already validated contract after a chain reorg,
+    /// returning the updated validation status.
+    ///
+    /// The procedure re-resolves the witnesses, re-selects the alternative
+    /// witnesses and re-checks seal closings, witness ordering and finality.
+    /// Since the schema selection and the scripts may depend on the witness
+    /// position, the state of state transitions is re-validated against the
+    /// schema if their witness position differs from the one in `known_ords`,
+    /// which must contain the positions of the witnesses at the time of the
+    /// previous validation. The state of other operations is assumed to be
+    /// valid, thus the procedure must be used only for consignments which were
+    /// fully validated with [`Validator::validate`] before.
+    pub fn revalidate_witnesses(
+        consignment: &'consignment C,
+        resolver: &'resolver R,
+        testnet: bool,
+        known_ords: BTreeMap<XWitnessId, WitnessOrd>,
+    ) -> Status {
+        let mut validator = Validator::init(consignment, resolver, testnet);
+        validator.known_ords = Some(known_ords);
+        validator.run(consignment, testnet)
+    }
+
+    fn run(mut self, consignment: &'consignment C, testnet: bool) -> Status {
+        trace_span!("rgb::validate", contract_id = self.contract_id);
         // If the network mismatches there is no point in validating the contract since
         // all witness transactions will be missed.
-        if testnet != validator.consignment.genesis().testnet {
-            validator
-                .status
+        if testnet != self.consignment.genesis().testnet {
+            self.status
                 .borrow_mut()
                 .add_failure(Failure::NetworkMismatch(testnet));
-            return validator.status.into_inner();
+            return self.status.into_inner();
         }
+        self.validate_chain_net();
 
-        validator.validate_schema(consignment.schema());
+        self.validate_schema(consignment.schema());
         // We must return here, since if the schema is not valid there is no reason to
         // validate contract nodes against it: it will produce a plenty of errors.
-        if validator.status.borrow().validity() == Validity::Invalid {
-            return validator.status.into_inner();
+        if self.status.borrow().validity() == Validity::Invalid {
+            return self.status.into_inner();
         }
 
-        validator.validate_commitments();
+        self.validate_dependencies();
+        self.validate_issuer();
+
+        self.prefetch_witnesses(consignment);
+        if self.interrupted() {
+            return self.status.into_inner();
+        }
+        self.validate_commitments();
         // We must return here, since if there were no proper commitments, it is
         // pointless to validate the contract state.
-        if validator.status.borrow().validity() == Validity::Invalid {
-            return validator.status.into_inner();
+        if self.status.borrow().validity() == Validity::Invalid {
+            return self.status.into_inner();
         }
 
-        validator.validate_logic();
+        self.validate_logic();
+        if self.interrupted() {
+            return self.status.into_inner();
+        }
+        self.validate_oracle_feeds();
+        self.validate_seal_reuse();
+        self.validate_finality();
+        for (witness_id, height) in self.hint_mismatches.take() {
+            self.status
+                .borrow_mut()
+                .add_warning(Warning::WitnessHintMismatch(witness_id, height));
+        }
+        for (ty, remaining) in self.supply.borrow().remaining_supply() {
+            self.status
+                .borrow_mut()
+                .add_info(Info::SupplyRemaining(ty, remaining));
+        }
         // Done. Returning status report with all possible failures, issues, warnings
         // and notifications about transactions we were unable to obtain.
-        validator.status.into_inner()
+        self.status.into_inner()
+    }
+
+    /// Checks whether the validation must be stopped, reporting the
+    /// interruption into the status the first time it is detected.
+    fn interrupted(&self) -> bool {
+        if self.interrupted.get() {
+            return true;
+        }
+        let Some(reason) = self.stop.check() else {
+            return false;
+        };
+        self.interrupted.set(true);
+        self.status
+            .borrow_mut()
+            .add_failure(Failure::ValidationInterrupted(
+                reason,
+                self.validated_op_seals.borrow().len(),
+                self.validated_op_state.borrow().len(),
+            ));
+        true
     }
 
     // *** PART I: Schema validation
     fn validate_schema(&mut self, schema: &Schema) {
-        *self.status.borrow_mut() += schema.verify(self.consignment.types());
+        trace_span!("rgb::validate_schema", schema_id = schema.schema_id());
+        // Libraries depend on the consignment and not only on the schema, so
+        // they are checked even if the schema was verified before
+        self.validate_libs(schema);
+        let types = self.consignment.types();
+        let Some(cache) = self.batch else {
+            *self.status.borrow_mut() += schema.verify(types);
+            return;
+        };
+        let schema_id = schema.schema_id();
+        let cached = cache
+            .schemas
+            .borrow()
+            .get(&schema_id)
+            .filter(|(cached_types, _)| cached_types == types)
+            .map(|(_, status)| status.clone());
+        let status = cached.unwrap_or_else(|| {
+            let status = schema.verify(types);
+            cache
+                .schemas
+                .borrow_mut()
+                .insert(schema_id, (types.clone(), status.clone()));
+            status
+        });
+        *self.status.borrow_mut() += status;
+    }
+
+    /// Checks that the consignment provides all libraries required to run the
+    /// scripts of the validated operations, including the libraries called by
+    /// them: validators of the genesis, of the used state transition and state
+    /// extension types (under the contract schema and its successors), and
+    /// lock scripts of the spent assignments. Libraries of the operation types
+    /// which are not used by the consignment are not required.
+    fn validate_libs(&self, schema: &Schema) {
+        let successors = self
+            .upgrades
+            .values()
+            .flatten()
+            .map(|upgrade| upgrade.successor)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|schema_id| self.consignment.schema_successor(schema_id))
+            .collect::<Vec<_>>();
+        let mut sites = schema.genesis.validator.into_iter().collect::<Vec<_>>();
+        for opid in self.dag.operations() {
+            match self.consignment.operation(opid) {
+                None | Some(OpRef::Genesis(_)) => {}
+                Some(OpRef::Transition(transition)) => {
+                    let ty = transition.transition_type;
+                    sites.extend(
+                        iter::once(schema)
+                            .chain(successors.iter().copied())
+                            .filter_map(|schema| schema.transitions.get(&ty))
+                            .filter_map(|transition_schema| transition_schema.validator),
+                    );
+                    if !schema.reserves_types() {
+                        continue;
+                    }
+                    for input in &transition.inputs {
+                        let Opout {
+                            op: prev_id,
+                            ty,
+                            no,
+                        } = input.prev_out;
+                        let Some(prev_op) = self.consignment.operation(prev_id) else {
+                            continue;
+                        };
+                        sites.extend(
+                            prev_op
+                                .metadata()
+                                .assignment_locks()
+                                .and_then(|locks| locks.lock(ty, no)),
+                        );
+                    }
+                }
+                Some(OpRef::Extension(extension)) => {
+                    sites.extend(
+                        schema
+                            .extensions
+                            .get(&extension.extension_type)
+                            .and_then(|extension_schema| extension_schema.validator),
+                    );
+                }
+            }
+        }
+        let libs = sites.into_iter().map(|site| site.lib);
+        for lib_id in missing_libs(libs, self.consignment.scripts()) {
+            self.status
+                .borrow_mut()
+                .add_failure(Failure::SchemaLibUnknown(lib_id));
+        }
+    }
+
+    /// Checks that the contract id is salted for the network the validating
+    /// software operates on.
+    fn validate_chain_net(&self) {
+        let Some(expected) = self.chain_net else {
+            return;
+        };
+        let mut status = self.status.borrow_mut();
+        match self.contract_id_version {
+            ContractIdVersion::V0 => {
+                status.add_warning(Warning::ContractIdUnsalted(self.contract_id));
+            }
+            ContractIdVersion::V1(actual) if actual != expected => {
+                status.add_failure(Failure::ChainNetMismatch { expected, actual });
+            }
+            ContractIdVersion::V1(_) => {}
+        }
+    }
+
+    /// Checks that all contracts the contract depends on were validated
+    /// before it.
+    fn validate_dependencies(&self) {
+        for contract_id in &self.dependencies {
+            if !self
+                .validated_contracts
+                .map(|validated| validated.contains(contract_id))
+                .unwrap_or_default()
+            {
+                self.status
+                    .borrow_mut()
+                    .add_failure(Failure::DependencyUnvalidated(*contract_id));
+            }
+        }
+    }
+
+    /// Verifies issuer signature over the genesis, if provided by the
+    /// consignment, reporting the signer key.
+    fn validate_issuer(&self) {
+        let Some(sig) = self.consignment.issuer_signature() else {
+            return;
+        };
+        let mut status = self.status.borrow_mut();
+        match self.consignment.genesis().verify_issuer(&sig) {
+            Ok(key) => status.add_info(Info::IssuerSigned(key)),
+            Err(err) => status.add_warning(Warning::IssuerSignatureInvalid(err)),
+        };
+    }
+
+    /// Checks that the attestations and rotations of each oracle feed are
+    /// signed by the keys of the oracle set current at their position in the
+    /// consensus order of the validated operations.
+    fn validate_oracle_feeds(&self) {
+        if self.oracle_feeds.is_empty() {
+            return;
+        }
+        let validated = self.validated_op_state.borrow().clone();
+        for feed in &self.oracle_feeds {
+            let mut ops = Vec::new();
+            for opid in &validated {
+                let Some(op) = self.consignment.operation(*opid) else {
+                    continue;
+                };
+                let globals = op.globals();
+                let ty = if globals.contains_key(&feed.attestations) {
+                    feed.attestations
+                } else if globals.contains_key(&feed.oracle_set) {
+                    feed.oracle_set
+                } else {
+                    continue;
+                };
+                let anchor = match op {
+                    OpRef::Genesis(_) => Some(None),
+                    OpRef::Transition(_) => self.op_witness_id(*opid).and_then(|witness_id| {
+                        let witness_ord = self.resolve_witness_ord(witness_id).ok()?;
+                        Some(Some(WitnessAnchor {
+                            witness_ord,
+                            witness_id,
+                        }))
+                    }),
+                    // State extensions have no witness defining their position
+                    OpRef::Extension(_) => None,
+                };
+                match anchor {
+                    Some(anchor) => ops.push((anchor, *opid, op)),
+                    None => {
+                        self.status
+                            .borrow_mut()
+                            .add_failure(Failure::OracleStateInvalid(
+                                *opid,
+                                OracleError::UnknownPosition(ty),
+                            ));
+                    }
+                }
+            }
+            ops.sort_by_key(|(anchor, opid, _)| (*anchor, *opid));
+            let mut tracker = OracleTracker::new(*feed, self.contract_id);
+            for (_, opid, op) in ops {
+                if let Err(err) = tracker.push_op(op.globals()) {
+                    self.status
+                        .borrow_mut()
+                        .add_failure(Failure::OracleStateInvalid(opid, err));
+                }
+            }
+        }
+    }
+
+    /// Reports outpoints allocated by the contract operations which hold
+    /// allocations of other known contracts.
+    fn validate_seal_reuse(&self) {
+        let Some(usage) = self.seal_usage else {
+            return;
+        };
+        let mut reused = BTreeSet::new();
+        for opid in self.dag.operations() {
+            let Some(op) = self.consignment.operation(opid) else {
+                continue;
+            };
+            let witness_id = self.op_witness_id(opid);
+            for typed in op.assignments().flat().values() {
+                for no in 0..typed.len_u16() {
+                    let Ok(Some(seal)) = typed.revealed_seal_at(no) else {
+                        continue;
+                    };
+                    let seal = match witness_id {
+                        Some(witness_id) => seal.try_to_output_seal(witness_id).ok(),
+                        None => seal.to_output_seal(),
+                    };
+                    let Some(outpoint) = seal.map(XOutpoint::from) else {
+                        continue;
+                    };
+                    for contract_id in usage.other_contracts_on(outpoint, self.contract_id) {
+                        reused.insert((outpoint, contract_id));
+                    }
+                }
+            }
+        }
+        let mut status = self.status.borrow_mut();
+        for (outpoint, contract_id) in reused {
+            status.add_warning(Warning::SealReused(outpoint, contract_id));
+        }
+    }
+
+    /// Returns schemata which rules apply to the operation with the witness
+    /// at the given position.
+    ///
+    /// Only state transitions descending from the schema upgrade extensions
+    /// are subject to the upgrades (see [`SchemaUpgrade`]). Mined transitions
+    /// are validated against the schema active at their height; transitions
+    /// which are not mined yet must be valid under the contract schema and
+    /// the successor schemata of all upgrades in their history. Fails if the
+    /// witness position is unknown, if upgrades in the history activate at the
+    /// same height, or if the consignment doesn't provide a successor schema.
+    fn op_schemata(
+        &self,
+        operation: &OpRef,
+        witness: Option<WitnessOrd>,
+    ) -> Result<Vec<&Schema>, Failure> {
+        let opid = operation.id();
+        let schema = self.consignment.schema();
+        let upgrades = match self.upgrades.get(&opid) {
+            Some(upgrades) if operation.op_type() == OpType::StateTransition => upgrades,
+            _ => return Ok(vec![schema]),
+        };
+        if upgrades
+            .windows(2)
+            .any(|pair| pair[0].activation_height == pair[1].activation_height)
+        {
+            return Err(Failure::SchemaUpgradeConflict(opid));
+        }
+        let successor = |upgrade: &SchemaUpgrade| {
+            self.consignment
+                .schema_successor(upgrade.successor)
+                .ok_or(Failure::SchemaUpgradeUnavailable(opid, upgrade.successor))
+        };
+        match witness {
+            Some(WitnessOrd::OnChain(pos)) => {
+                let height = pos.height().get();
+                match upgrades
+                    .iter()
+                    .rev()
+                    .find(|upgrade| upgrade.is_active_at(height))
+                {
+                    None => Ok(vec![schema]),
+                    Some(upgrade) => successor(upgrade).map(|schema| vec![schema]),
+                }
+            }
+            Some(WitnessOrd::OffChain) => iter::once(Ok(schema))
+                .chain(upgrades.iter().map(successor))
+                .collect(),
+            None => Err(Failure::SchemaUpgradeUndetermined(opid)),
+        }
     }
 
     // *** PART II: Validating business logic
@@ -198,7 +1081,11 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
 
         // [VALIDATION]: Validate genesis
         *self.status.borrow_mut() +=
-            schema.validate_state(&self.consignment, OpRef::Genesis(self.consignment.genesis()));
+            self.validate_op_state(OpRef::Genesis(self.consignment.genesis()));
+        self.validate_attachments(OpRef::Genesis(self.consignment.genesis()));
+        if let Some(rules) = self.presentation {
+            *self.status.borrow_mut() += rules.validate(self.consignment.genesis());
+        }
         self.validated_op_state.borrow_mut().insert(self.genesis_id);
 
         // [VALIDATION]: Iterating over each endpoint, reconstructing operation
@@ -207,6 +1094,9 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
         // treat it as a superposition of subgraphs, one for each endpoint; and validate
         // them independently.
         for (bundle_id, _) in self.consignment.terminals() {
+            if self.interrupted() {
+                return;
+            }
             let Some(bundle) = self.consignment.bundle(bundle_id) else {
                 // We already checked and errored here during the terminal validation, so just
                 // skipping.
@@ -219,15 +1109,13 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
     }
 
     fn validate_logic_on_route(&self, opid: OpId) {
-        let schema = self.consignment.schema();
-        let Some(OpRef::Transition(transition)) = self.consignment.operation(opid) else {
+        trace_span!("rgb::validate_route", opid = opid);
+        let Some(OpRef::Transition(_)) = self.consignment.operation(opid) else {
             panic!("provided {opid} is absent");
         };
 
-        let mut queue: VecDeque<OpRef> = VecDeque::new();
-
         // Instead of constructing complex graph structures or using a recursions we
-        // utilize queue to keep the track of the upstream (ancestor) nodes and make
+        // utilize walker to keep the track of the upstream (ancestor) nodes and make
         // sure that ve have validated each one of them up to genesis. The graph is
         // valid when each of its nodes and each of its edges is valid, i.e. when all
         // individual nodes has passed validation against the schema (we track
@@ -235,9 +1123,15 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
         // change to a given operation is valid against the schema + committed
         // into bitcoin transaction graph with proper anchor. That is what we are
         // checking in the code below:
-        queue.push_back(OpRef::Transition(transition));
-        while let Some(operation) = queue.pop_front() {
-            let opid = operation.id();
+        let mut walker = DagWalker::new(&self.consignment, [opid]);
+        while let Some((opid, operation)) = walker.next_op() {
+            if self.interrupted() {
+                return;
+            }
+            let Some(operation) = operation else {
+                // The walker doesn't descend to the parents absent from the consignment
+                unreachable!("walked operation {opid} is absent");
+            };
 
             if operation.contract_id() != self.contract_id {
                 self.status
@@ -254,204 +1148,662 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
                     .add_failure(Failure::SealsUnvalidated(opid));
             }
             // [VALIDATION]: Verify operation against the schema and scripts
-            if self.validated_op_state.borrow_mut().insert(opid) {
-                *self.status.borrow_mut() += schema.validate_state(&self.consignment, operation);
+            if !self.validated_op_state.borrow_mut().insert(opid) {
+                // The ancestors of the operation were queued on its first visit, including
+                // the visits from the routes of other terminals, thus the whole history
+                // behind it is already validated (or is being validated).
+                continue;
             }
+            *self.status.borrow_mut() += self.validate_op_state(operation);
+            self.validate_attachments(operation);
 
-            match operation {
-                OpRef::Genesis(_) => {
-                    // nothing to add to the queue here
-                }
-                OpRef::Transition(transition) => {
-                    // Now, we must collect all parent nodes and add them to the verification queue
-                    let parent_nodes = transition.inputs.iter().filter_map(|input| {
-                        self.consignment.operation(input.prev_out.op).or_else(|| {
-                            self.status
-                                .borrow_mut()
-                                .add_failure(Failure::OperationAbsent(input.prev_out.op));
-                            None
-                        })
-                    });
-
-                    queue.extend(parent_nodes);
-                }
-                OpRef::Extension(extension) => {
-                    for (valency, prev_id) in &extension.redeemed {
-                        let Some(prev_op) = self.consignment.operation(*prev_id) else {
-                            self.status
-                                .borrow_mut()
-                                .add_failure(Failure::ValencyNoParent {
-                                    opid,
-                                    prev_id: *prev_id,
-                                    valency: *valency,
-                                });
-                            continue;
-                        };
-
-                        if !prev_op.valencies().contains(valency) {
+            // Now, we must collect all parent nodes and add them to the verification queue
+            for (parent, prev_op) in walker.descend(&operation) {
+                match (parent, prev_op) {
+                    (ParentRef::Input(prev_out), None) => {
+                        self.status
+                            .borrow_mut()
+                            .add_failure(Failure::OperationAbsent(prev_out.op));
+                    }
+                    // [VALIDATION]: Spent state provided by the consignment, which was used
+                    //               to validate the transition state, must match the
+                    //               ancestor assignment
+                    (ParentRef::Input(prev_out), Some(prev_op)) => {
+                        self.validate_spent_state(opid, prev_out, &prev_op)
+                    }
+                    (ParentRef::Valency(valency, prev_id), None) => {
+                        self.status
+                            .borrow_mut()
+                            .add_failure(Failure::ValencyNoParent {
+                                opid,
+                                prev_id,
+                                valency,
+                            });
+                    }
+                    (ParentRef::Valency(valency, prev_id), Some(prev_op)) => {
+                        if !prev_op.valencies().contains(&valency) {
                             self.status
                                 .borrow_mut()
                                 .add_failure(Failure::NoPrevValency {
                                     opid,
-                                    prev_id: *prev_id,
-                                    valency: *valency,
+                                    prev_id,
+                                    valency,
                                 });
-                            continue;
                         }
-
-                        queue.push_back(prev_op);
                     }
                 }
             }
         }
     }
 
-    // *** PART III: Validating single-use-seals
-    fn validate_commitments(&mut self) {
-        for bundle_id in self.consignment.bundle_ids() {
-            let Some(bundle) = self.consignment.bundle(bundle_id) else {
-                self.status
+    /// Checks that the assignment spent by the transition input, if provided by
+    /// the consignment, matches the assignment of the ancestor operation.
+    fn validate_spent_state(&self, opid: OpId, prev_out: Opout, prev_op: &OpRef) {
+        let Some(spent) = self.consignment.spent_state(prev_out) else {
+            return;
+        };
+        let ancestor = prev_op
+            .assignments_by_type(prev_out.ty)
+            .and_then(|assigns| SpentState::from_assigns(&assigns, prev_out.no));
+        if ancestor.as_ref() != Some(&spent) {
+            self.status
+                .borrow_mut()
+                .add_failure(Failure::SpentStateMismatch(opid, prev_out));
+        }
+    }
+
+    /// Validates the operation against the rules of the schema applying to
+    /// it, storing the result in the validation store.
+    fn validate_op_scripts(&self, operation: OpRef) -> Status {
+        let opid = operation.id();
+        let witness = self.resolve_op_witness_ord(&operation);
+        let schemata = match self.op_schemata(&operation, witness) {
+            Ok(schemata) => schemata,
+            Err(failure) => return Status::with_failure(failure),
+        };
+        let mut status = Status::new();
+        for schema in schemata {
+            status += schema.validate_state(&self.consignment, operation, witness);
+            if !status.failures.is_empty() {
+                break;
+            }
+        }
+        // Standardness depends only on the operation itself, so its warnings are
+        // persisted together with the script outcomes
+        for issue in check_standardness(operation) {
+            status.add_warning(Warning::NonStandard(opid, issue));
+        }
+        // Scripts may depend on the witness position, and failures may come from
+        // the resolver being unavailable, so only successful validation of operations
+        // with settled witnesses is persisted. Results of scripts reading the witness
+        // position depend on the resolver and are never persisted.
+        if status.failures.is_empty() &&
+            !self.witness_scripts() &&
+            self.is_witness_settled(&operation, witness)
+        {
+            if let Some(store) = &self.store {
+                store
                     .borrow_mut()
-                    .add_failure(Failure::BundleAbsent(bundle_id));
-                continue;
-            };
+                    .store_op_status(self.context, opid, &status);
+            }
+        }
+        status
+    }
+
+    /// Detects whether any of the consignment scripts reads the witness
+    /// position. Code analysis of the libraries is shared between the
+    /// consignments of a batch.
+    fn witness_scripts(&self) -> bool {
+        if let Some(witness_scripts) = self.witness_scripts.get() {
+            return witness_scripts;
+        }
+        let mut libs = self.consignment.scripts().values();
+        let witness_scripts = match self.batch {
+            None => libs.any(RgbIsa::reads_witness_position),
+            // Libraries are looked up by their actual id and not by the consignment key,
+            // since a consignment may provide a library under an id of another one
+            Some(cache) => libs.any(|lib| {
+                *cache
+                    .libs
+                    .borrow_mut()
+                    .entry(lib.id())
+                    .or_insert_with(|| RgbIsa::reads_witness_position(lib))
+            }),
+        };
+        self.witness_scripts.set(Some(witness_scripts));
+        witness_scripts
+    }
+
+    /// Detects whether the operation was validated before a reorg with its
+    /// witness at the same position, when revalidating after the reorg.
+    fn is_witness_unmoved(&self, operation: &OpRef) -> bool {
+        let Some(known_ords) = &self.known_ords else {
+            return false;
+        };
+        if operation.op_type() != OpType::StateTransition {
+            return true;
+        }
+        let Some(witness_id) = self.op_witness_id(operation.id()) else {
+            return false;
+        };
+        let witness = self.resolve_op_witness_ord(operation);
+        witness.is_some() && known_ords.get(&witness_id).copied() == witness
+    }
+
+    /// Detects whether the position of the operation witness can't be changed
+    /// by a reorg anymore. Operations without witnesses are always settled;
+    /// transitions with unresolved witnesses are never.
+    fn is_witness_settled(&self, operation: &OpRef, witness: Option<WitnessOrd>) -> bool {
+        if operation.op_type() != OpType::StateTransition {
+            return true;
+        }
+        let (Some(witness_ord), Some(witness_id)) = (witness, self.op_witness_id(operation.id()))
+        else {
+            return false;
+        };
+        let tip_height = self.resolver.resolve_tip_height(witness_id.layer1());
+        self.finality
+            .max(FinalityDepth::new(CACHE_FINAL_DEPTH))
+            .is_final(witness_ord, tip_height)
+    }
+
+    fn validate_op_state(&self, operation: OpRef) -> Status {
+        let opid = operation.id();
+        trace_span!("rgb::validate_op", opid = opid);
+        let mut status = match self
+            .store
+            .as_ref()
+            .and_then(|store| store.borrow().op_status(self.context, opid))
+        {
+            Some(status) => status,
+            // Revalidation after a reorg relies on the state validated before unless the
+            // operation witness has moved
+            None if self.is_witness_unmoved(&operation) => Status::default(),
+            None => self.validate_op_scripts(operation),
+        };
+        if self.disclosure.conceals_amounts() {
+            report_concealed_amounts(&mut status, &operation);
+        }
+        if !self.state_registry.is_empty() {
+            accept_older_state(
+                &mut status,
+                &self.state_registry,
+                &operation,
+                self.consignment.types(),
+            );
+        }
+        for policy in &self.policies {
+            if let Err(reason) = policy.check(self.contract_id, operation) {
+                status
+                    .policy_violations
+                    .push(PolicyViolation { opid, reason });
+            }
+        }
+        if self.mode == ValidationMode::Standard {
+            reject_non_standard(&mut status);
+        }
+        if let Some(features) = operation
+            .metadata()
+            .op_features()
+            .filter(|_| self.reserves_types)
+        {
+            let unsupported = features.unsupported(self.supported_features);
+            if !unsupported.is_empty() {
+                status.add_failure(Failure::OpFeaturesUnsupported(opid, unsupported));
+            }
+        }
+        let prev_value = |opout: Opout| {
+            let prev_state = self
+                .consignment
+                .operation(opout.op)?
+                .assignments_by_type(opout.ty)?;
+            Some(
+                prev_state
+                    .as_fungible_state_at(opout.no)
+                    .ok()??
+                    .value
+                    .as_u64(),
+            )
+        };
+        match self.supply.borrow_mut().register(operation, prev_value) {
+            Ok(()) => {}
+            Err(SupplyError::CapExceeded { ty, cap, .. }) => {
+                status.add_failure(Failure::SupplyCapExceeded(opid, ty, cap));
+            }
+            Err(SupplyError::Uncheckable { ty, .. }) => {
+                status.add_failure(Failure::SupplyUncheckable(opid, ty));
+            }
+        }
+        let is_genesis = operation.op_type() == OpType::Genesis;
+        if self.reserves_types &&
+            !is_genesis &&
+            operation.globals().contains_key(&GlobalStateType::TERMS)
+        {
+            status.add_failure(Failure::TermsRedefined(opid));
+        }
+        for feed in &self.oracle_feeds {
+            if let Err(err) = feed.check_op(self.contract_id, is_genesis, operation.globals()) {
+                status.add_failure(Failure::OracleStateInvalid(opid, err));
+            }
+        }
+        match operation {
+            OpRef::Genesis(_) => {}
+            OpRef::Transition(transition) => self.validate_valency_expiry(transition, &mut status),
+            OpRef::Extension(extension) => {
+                if let Err(err) = self.valencies.borrow_mut().register(extension) {
+                    status.add_failure(Failure::ValencyLimitViolated(opid, err));
+                }
+            }
+        }
+        status
+    }
+
+    /// Checks that the transition doesn't spend state of extensions which
+    /// redeem expired valencies.
+    fn validate_valency_expiry(&self, transition: &Transition, status: &mut Status) {
+        let valencies = self.valencies.borrow();
+        let expiring = transition
+            .inputs
+            .iter()
+            .filter_map(|input| match self.consignment.operation(input.prev_out.op) {
+                Some(OpRef::Extension(extension)) => Some((extension.id(), extension)),
+                _ => None,
+            })
+            .filter(|(_, extension)| {
+                extension.redeemed.keys().any(|valency| {
+                    valencies
+                        .limit(*valency)
+                        .is_some_and(|limit| limit.expiry.is_some())
+                })
+            })
+            .collect::<BTreeMap<_, _>>();
+        if expiring.is_empty() {
+            return;
+        }
+        // Witnesses which are not mined yet are checked once they get mined
+        let Some(WitnessOrd::OnChain(pos)) =
+            self.resolve_op_witness_ord(&OpRef::Transition(transition))
+        else {
+            return;
+        };
+        for extension in expiring.values() {
+            if let Err(err) = valencies.check_expiry(extension, pos.height().get()) {
+                status.add_failure(Failure::ValencyLimitViolated(transition.id(), err));
+            }
+        }
+    }
+
+    /// Resolves position of the witness for a state transition; other
+    /// operations do not have witnesses.
+    fn resolve_op_witness_ord(&self, operation: &OpRef) -> Option<WitnessOrd> {
+        if operation.op_type() != OpType::StateTransition {
+            return None;
+        }
+        let witness_id = self.op_witness_id(operation.id())?;
+        self.resolve_witness_ord(witness_id).ok()
+    }
+
+    /// Resolves position of the witness, passing the hint provided by the
+    /// consignment to the resolver. Hints not matching the resolved position
+    /// are reported with a warning at the end of the validation.
+    fn resolve_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        let hint = self.consignment.witness_hint(witness_id);
+        let ord = match (self.prefetched_ords.get(&witness_id), hint) {
+            (Some(res), _) => res.clone()?,
+            (None, None) => self.resolver.resolve_witness_ord(witness_id)?,
+            (None, Some(hint)) => self.resolver.resolve_witness_ord_hinted(witness_id, hint)?,
+        };
+        if let Some(hint) = hint {
+            if !matches!(ord, WitnessOrd::OnChain(pos) if pos.height().get() == hint.height) {
+                // Status may be borrowed by the caller, so the mismatches are reported at the
+                // end of the validation
+                self.hint_mismatches
+                    .borrow_mut()
+                    .insert(witness_id, hint.height);
+            }
+        }
+        Ok(ord)
+    }
+
+    /// Resolves the witness transaction, using the result of the batch
+    /// resolution if the resolver has provided it, and passing the hint
+    /// provided by the consignment to the resolver otherwise.
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        match (self.prefetched_txes.get(&witness_id), self.consignment.witness_hint(witness_id)) {
+            (Some(res), _) => res.clone(),
+            (None, None) => self.resolver.resolve_pub_witness(witness_id),
+            (None, Some(hint)) => self.resolver.resolve_pub_witness_hinted(witness_id, hint),
+        }
+    }
+
+    /// Passes all witnesses of the consignment to the resolver at once,
+    /// allowing it to resolve them with batch requests.
+    fn prefetch_witnesses(&mut self, consignment: &C) {
+        let witnesses = consignment_witnesses(consignment);
+        if witnesses.is_empty() {
+            return;
+        }
+        self.prefetched_txes = self.resolver.resolve_pub_witnesses(&witnesses);
+        self.prefetched_ords = self.resolver.resolve_witness_ords(&witnesses);
+    }
+
+    /// Reports witnesses which are not buried under the finality depth
+    /// declared by the contract genesis.
+    ///
+    /// Positions are resolved for the witnesses of all the validated
+    /// operations, including the ones which validation outcome was taken
+    /// from the validation store or cache. Witnesses with unknown positions
+    /// are provisional.
+    fn validate_finality(&self) {
+        if self.finality == FinalityDepth::default() {
+            return;
+        }
+        let witness_ids = self
+            .validated_op_state
+            .borrow()
+            .iter()
+            .filter_map(|opid| self.op_witness_id(*opid))
+            .collect::<BTreeSet<_>>();
+        for witness_id in witness_ids {
+            let tip_height = self.resolver.resolve_tip_height(witness_id.layer1());
+            let is_final = self
+                .resolve_witness_ord(witness_id)
+                .is_ok_and(|ord| self.finality.is_final(ord, tip_height));
+            if !is_final {
+                self.status
+                    .borrow_mut()
+                    .add_info(Info::WitnessProvisional(witness_id));
+            }
+        }
+    }
+
+    fn validate_attachments(&self, operation: OpRef) {
+        if let Some(verifier) = self.attachments {
+            *self.status.borrow_mut() += verify_op_attachments(verifier, &operation);
+        }
+    }
+
+    // *** PART III: Validating single-use-seals
+    fn validate_commitments(&mut self) {
+        trace_span!("rgb::validate_commitments");
+        // [VALIDATION]: If bundles have alternative witnesses, we use the ones
+        //               which were mined. This is done before the seal
+        //               validation, since seals defined relative to the
+        //               witness must refer to the selected witness.
+        let mut anchors = BTreeMap::new();
+        for bundle_id in self.consignment.bundle_ids() {
             let Some((witness_id, anchor)) = self.consignment.anchor(bundle_id) else {
+                continue;
+            };
+            let selected = self.select_alt_anchor(bundle_id, witness_id, anchor);
+            if selected.0 != witness_id {
+                self.replaced_witnesses
+                    .borrow_mut()
+                    .insert(witness_id, selected.0);
+            }
+            anchors.insert(bundle_id, selected);
+        }
+
+        let mut checks = vec![];
+        for bundle_id in self.consignment.bundle_ids() {
+            if self.interrupted() {
+                break;
+            }
+            let Some(bundle) = self.consignment.bundle(bundle_id) else {
+                self.status
+                    .borrow_mut()
+                    .add_failure(Failure::BundleAbsent(bundle_id));
+                continue;
+            };
+            let Some((witness_id, anchor)) = anchors.get(&bundle_id).copied() else {
                 self.status
                     .borrow_mut()
                     .add_failure(Failure::AnchorAbsent(bundle_id));
                 continue;
             };
 
+            // [VALIDATION]: We validate that the bundle uses close method allowed by genesis
+            if !self.close_methods.contains(bundle.close_method) {
+                self.status
+                    .borrow_mut()
+                    .add_failure(Failure::CloseMethodForbidden(bundle_id, bundle.close_method));
+            }
+
+            // [VALIDATION]: We validate that re-anchoring of the bundle to a new witness, if
+            //               any, doesn't break the seals defined by the replaced witness
+            if let Some(substitution) = self.consignment.witness_substitution(bundle_id) {
+                self.validate_substitution(bundle_id, witness_id, substitution);
+            }
+
             // [VALIDATION]: We validate that the seals were properly defined on BP-type layers
             let (seals, input_map) = self.validate_seal_definitions(witness_id.layer1(), bundle);
 
-            // [VALIDATION]: We validate that the seals were properly closed on BP-type layers
-            let Some(witness_tx) = self.validate_seal_commitments(
-                &seals,
+            if let Some(status) = self.store.as_ref().and_then(|store| {
+                store
+                    .borrow()
+                    .witness_status(self.context, witness_id, bundle_id)
+            }) {
+                *self.status.borrow_mut() += status;
+                continue;
+            }
+            checks.push(BundleCheck {
                 bundle_id,
+                bundle,
                 witness_id,
-                bundle.close_method,
                 anchor,
-            ) else {
-                continue;
-            };
+                seals,
+                input_map,
+                witness_tx: self.resolve_pub_witness(witness_id),
+            });
+        }
+
+        // Commitments of each bundle are validated independently from other bundles,
+        // and their failures are collected separately, such that they can be
+        // saved into the validation store
+        let checker = CommitmentChecker {
+            contract_id: self.contract_id,
+            witness_constraints: self.witness_constraints,
+        };
+        #[cfg(feature = "parallel")]
+        let statuses = if self.parallel {
+            checker.check_parallel(&checks, self.close_method_plugins)
+        } else {
+            checker.check_all(&checks, self.close_method_plugins)
+        };
+        #[cfg(not(feature = "parallel"))]
+        let statuses = checker.check_all(&checks, self.close_method_plugins);
 
-            // [VALIDATION]: We validate bundle commitments to the input map
-            self.validate_bundle_commitments(bundle_id, bundle, witness_tx, input_map);
+        for (check, status) in checks.into_iter().zip(statuses) {
+            if let Some(store) = &self.store {
+                // Absence of the witness is not a property of the consignment, but of the
+                // resolver, and failures may come from the consignment data not committed by
+                // the witness and bundle ids, so only valid verdicts are persisted
+                if status.failures.is_empty() && status.absent_pub_witnesses.is_empty() {
+                    store.borrow_mut().store_witness_status(
+                        self.context,
+                        check.witness_id,
+                        check.bundle_id,
+                        &status,
+                    );
+                }
+            }
+            *self.status.borrow_mut() += status;
+            if let Ok(witness_tx) = &check.witness_tx {
+                self.check_witness_metrics(check.bundle, check.witness_id, witness_tx);
+            }
         }
     }
 
-    /// Validates that the transition bundle is internally consistent: inputs of
-    /// its state transitions correspond to the way how they are committed
-    /// in the input map of the bundle; and these inputs are real inputs of
-    /// the transaction.
-    fn validate_bundle_commitments(
+    /// Records size and weight of the witness transaction and checks them
+    /// against the local policies.
+    ///
+    /// Witnesses which validation status was taken from the validation store
+    /// are not resolved, and their metrics are not reported.
+    fn check_witness_metrics(
         &self,
-        bundle_id: BundleId,
         bundle: &TransitionBundle,
-        pub_witness: XWitnessTx,
-        input_map: BTreeMap<OpId, BTreeSet<XOutpoint>>,
+        witness_id: XWitnessId,
+        witness_tx: &XWitnessTx,
     ) {
-        let witness_id = pub_witness.witness_id();
-        for (vin, opid) in &bundle.input_map {
-            let Some(outpoints) = input_map.get(opid) else {
-                self.status
-                    .borrow_mut()
-                    .add_failure(Failure::BundleExtraTransition(bundle_id, *opid));
+        let metrics = WitnessMetrics::with(witness_id, witness_tx.as_reduced_unsafe());
+        let mut status = self.status.borrow_mut();
+        status.add_witness_metrics(metrics);
+        for policy in &self.policies {
+            if let Err(reason) = policy.check_witness(self.contract_id, metrics) {
+                for opid in bundle.known_transitions.keys() {
+                    status.policy_violations.push(PolicyViolation {
+                        opid: *opid,
+                        reason: reason.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Returns witness id of the operation, taking into account selection of
+    /// the alternative witnesses.
+    fn op_witness_id(&self, opid: OpId) -> Option<XWitnessId> {
+        let witness_id = self.consignment.op_witness_id(opid)?;
+        Some(
+            self.replaced_witnesses
+                .borrow()
+                .get(&witness_id)
+                .copied()
+                .unwrap_or(witness_id),
+        )
+    }
+
+    /// Selects the witness for a bundle among the alternative witnesses
+    /// provided by the consignment.
+    ///
+    /// Each alternative must replace the main witness in the same way as
+    /// required for the [`WitnessSubstitution`]. If one of the witnesses is
+    /// mined, it is selected and the rest are marked void; otherwise the main
+    /// witness is used.
+    ///
+    /// The main witness is usually unknown to the resolver once one of its
+    /// alternatives is mined, thus the consignment should provide it with
+    /// [`ConsignmentApi::replaced_witness`]. If the main witness can't be
+    /// obtained, all alternatives are invalid, since they can't be checked.
+    fn select_alt_anchor<'anchor>(
+        &'anchor self,
+        bundle_id: BundleId,
+        witness_id: XWitnessId,
+        anchor: &'anchor EAnchor,
+    ) -> (XWitnessId, &'anchor EAnchor) {
+        let alternatives = self.consignment.alt_anchors(bundle_id);
+        if alternatives.is_empty() {
+            return (witness_id, anchor);
+        }
+        let main_tx = self
+            .consignment
+            .replaced_witness(witness_id)
+            .or_else(|| self.resolve_pub_witness(witness_id).ok());
+
+        let mut candidates = vec![(witness_id, anchor)];
+        for (alt_id, alt_anchor) in alternatives {
+            if alt_id == witness_id {
+                continue;
+            }
+            // Alternatives which were never broadcasted can't be checked and can't be
+            // mined, so we skip them
+            let Ok(alt_tx) = self.resolve_pub_witness(alt_id) else {
                 continue;
             };
-            let layer1 = pub_witness.layer1();
-            let pub_witness = pub_witness.as_reduced_unsafe();
-            let Some(input) = pub_witness.inputs.get(vin.to_usize()) else {
+            let Some(main_tx) = &main_tx else {
                 self.status
                     .borrow_mut()
-                    .add_failure(Failure::BundleInvalidInput(bundle_id, *opid, witness_id));
+                    .add_failure(Failure::WitnessAlternativeInvalid(
+                        bundle_id,
+                        alt_id,
+                        s!("main witness transaction is unknown"),
+                    ));
                 continue;
             };
-            if !outpoints.contains(&XChain::with(layer1, input.prev_output)) {
+            let substitution = WitnessSubstitution::new(bundle_id, witness_id, alt_id);
+            if let Err(err) = substitution.verify(main_tx, &alt_tx) {
                 self.status
                     .borrow_mut()
-                    .add_failure(Failure::BundleInvalidCommitment(
-                        bundle_id, *vin, witness_id, *opid,
+                    .add_failure(Failure::WitnessAlternativeInvalid(
+                        bundle_id,
+                        alt_id,
+                        err.to_string(),
                     ));
+                continue;
             }
+            candidates.push((alt_id, alt_anchor));
         }
-    }
 
-    /// Bitcoin- and liquid-specific commitment validation using deterministic
-    /// bitcoin commitments with opret and tapret schema.
-    fn validate_seal_commitments(
-        &self,
-        seals: impl AsRef<[XOutputSeal]>,
-        bundle_id: BundleId,
-        witness_id: XWitnessId,
-        close_method: CloseMethod,
-        anchor: &EAnchor,
-    ) -> Option<XWitnessTx> {
-        // Check that the anchor is committed into a transaction spending all the
-        // transition inputs.
-        // Here the method can do SPV proof instead of querying the indexer. The SPV
-        // proofs can be part of the consignments, but do not require .
-        match self.resolver.resolve_pub_witness(witness_id) {
-            Err(_) => {
-                // We wre unable to retrieve corresponding transaction, so can't check.
-                // Reporting this incident and continuing further. Why this happens? No
-                // connection to Bitcoin Core, Electrum or other backend etc. So this is not a
-                // failure in a strict sense, however we can't be sure that the consignment is
-                // valid. That's why we keep the track of such information in a separate place
-                // (`unresolved_txids` field of the validation status object).
+        let mined = candidates
+            .iter()
+            .filter(|(id, _)| matches!(self.resolve_witness_ord(*id), Ok(WitnessOrd::OnChain(_))))
+            .copied()
+            .collect::<Vec<_>>();
+        let selected = match mined.as_slice() {
+            [] => return (witness_id, anchor),
+            [selected] => *selected,
+            _ => {
                 self.status
                     .borrow_mut()
-                    .absent_pub_witnesses
-                    .push(witness_id);
-                // This also can mean that there is no known transaction with the id provided by
-                // the anchor, i.e. consignment is invalid. We are proceeding with further
-                // validation in order to detect the rest of problems (and reporting the
-                // failure!)
+                    .add_failure(Failure::WitnessAlternativesMined(bundle_id));
+                return (witness_id, anchor);
+            }
+        };
+        for (id, _) in candidates {
+            if id != selected.0 {
                 self.status
                     .borrow_mut()
-                    .add_failure(Failure::SealNoWitnessTx(witness_id));
-                None
+                    .add_info(Info::WitnessVoid(bundle_id, id));
             }
-            Ok(pub_witness) => {
-                let seals = seals.as_ref();
-                for seal in seals.iter().filter(|seal| seal.method() != close_method) {
-                    self.status
-                        .borrow_mut()
-                        .add_failure(Failure::SealInvalidMethod(bundle_id, *seal));
-                }
-                match (close_method, anchor.clone()) {
-                    (
-                        CloseMethod::TapretFirst,
-                        EAnchor {
-                            mpc_proof,
-                            dbc_proof: DbcProof::Tapret(tapret),
-                            ..
-                        },
-                    ) => {
-                        let witness = pub_witness.clone().map(|tx| Witness::with(tx, tapret));
-                        self.validate_seal_closing(seals, bundle_id, witness, mpc_proof)
-                    }
-                    (
-                        CloseMethod::OpretFirst,
-                        EAnchor {
-                            mpc_proof,
-                            dbc_proof: DbcProof::Opret(opret),
-                            ..
-                        },
-                    ) => {
-                        let witness = pub_witness.clone().map(|tx| Witness::with(tx, opret));
-                        self.validate_seal_closing(seals, bundle_id, witness, mpc_proof)
-                    }
-                    (_, _) => {
-                        self.status
-                            .borrow_mut()
-                            .add_failure(Failure::AnchorMethodMismatch(bundle_id));
-                    }
-                }
+        }
+        selected
+    }
 
-                Some(pub_witness)
-            }
+    /// Validates witness substitution proof for a re-anchored bundle against
+    /// the replaced and replacement witness transactions.
+    fn validate_substitution(
+        &self,
+        bundle_id: BundleId,
+        witness_id: XWitnessId,
+        substitution: WitnessSubstitution,
+    ) {
+        if substitution.replacement != witness_id {
+            self.status
+                .borrow_mut()
+                .add_failure(Failure::WitnessSubstitutionMismatch(
+                    bundle_id,
+                    witness_id,
+                    substitution.replacement,
+                ));
+            return;
+        }
+        // Replaced transaction is usually unknown to the resolver, thus the proof
+        // provided by the consignment takes precedence
+        let Some(replaced_tx) = self
+            .consignment
+            .replaced_witness(substitution.replaced)
+            .or_else(|| self.resolve_pub_witness(substitution.replaced).ok())
+        else {
+            let mut status = self.status.borrow_mut();
+            status.absent_pub_witnesses.push(substitution.replaced);
+            status.add_failure(Failure::SealNoWitnessTx(substitution.replaced));
+            return;
+        };
+        // Absence of the replacement witness is reported during the seal closing
+        // validation
+        let Ok(replacement_tx) = self.resolve_pub_witness(witness_id) else {
+            return;
+        };
+        if let Err(err) = substitution.verify(&replaced_tx, &replacement_tx) {
+            self.status
+                .borrow_mut()
+                .add_failure(Failure::WitnessSubstitutionInvalid(bundle_id, err.to_string()));
         }
     }
 
@@ -530,7 +1882,7 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
                 }
 
                 let seal = if prev_op.op_type() == OpType::StateTransition {
-                    let Some(witness_id) = self.consignment.op_witness_id(op) else {
+                    let Some(witness_id) = self.op_witness_id(op) else {
                         self.status
                             .borrow_mut()
                             .add_failure(Failure::OperationAbsent(op));
@@ -563,25 +1915,248 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
         }
         (seals, input_map)
     }
+}
 
-    /// Single-use-seal closing validation.
-    ///
-    /// Checks that the set of seals is closed over the message, which is
-    /// multi-protocol commitment, by utilizing witness, consisting of
-    /// transaction with deterministic bitcoin commitments (defined by
-    /// generic type `Dbc`) and extra-transaction data, which are taken from
-    /// anchor's DBC proof.
+/// Bundle prepared for the validation of its commitments to the witness
+/// transaction, which doesn't depend on the validation of other bundles.
+pub(super) struct BundleCheck<'data> {
+    bundle_id: BundleId,
+    bundle: &'data TransitionBundle,
+    witness_id: XWitnessId,
+    anchor: &'data EAnchor,
+    seals: Vec<XOutputSeal>,
+    input_map: BTreeMap<OpId, BTreeSet<XOutpoint>>,
+    witness_tx: Result<XWitnessTx, WitnessResolverError>,
+}
+
+impl<'data> BundleCheck<'data> {
+    /// Detects whether the bundle seals are closed with one of the close
+    /// methods verified by the validator itself, without plugins.
+    #[cfg(feature = "parallel")]
+    pub(super) fn is_builtin_method(&self) -> bool {
+        matches!(
+            (self.bundle.close_method, &self.anchor.dbc_proof),
+            (CloseMethod::TapretFirst, DbcProof::Tapret(_)) |
+                (CloseMethod::OpretFirst, DbcProof::Opret(_))
+        )
+    }
+}
+
+/// Validation of the bundle commitments to their witness transactions.
+///
+/// The checker holds only the contract data, thus with the `parallel` feature
+/// the bundles may be validated concurrently, with the exception of the
+/// bundles closed with close method plugins.
+#[derive(Copy, Clone, Debug)]
+pub(super) struct CommitmentChecker {
+    contract_id: ContractId,
+    witness_constraints: WitnessConstraints,
+}
+
+impl CommitmentChecker {
+    /// Validates commitments of the bundles one by one, returning their
+    /// statuses in the order of the bundles.
+    fn check_all(
+        &self,
+        checks: &[BundleCheck],
+        plugins: Option<&CloseMethodRegistry>,
+    ) -> Vec<Status> {
+        checks
+            .iter()
+            .map(|check| self.check(check, plugins))
+            .collect()
+    }
+
+    /// Validates commitments of a single bundle.
+    pub(super) fn check(
+        &self,
+        check: &BundleCheck,
+        plugins: Option<&CloseMethodRegistry>,
+    ) -> Status {
+        let mut status = Status::new();
+        // [VALIDATION]: We validate that the seals were properly closed on BP-type layers
+        let Some(witness_tx) = self.validate_seal_commitments(&mut status, check, plugins) else {
+            return status;
+        };
+        // [VALIDATION]: We validate bundle commitments to the input map
+        self.validate_bundle_commitments(&mut status, check, witness_tx);
+        status
+    }
+
+    /// Validates that the transition bundle is internally consistent: inputs of
+    /// its state transitions correspond to the way how they are committed
+    /// in the input map of the bundle; and these inputs are real inputs of
+    /// the transaction.
     ///
-    /// Additionally, checks that the provided message contains commitment to
-    /// the bundle under the current contract.
-    fn validate_seal_closing<'seal, Seal: 'seal, Dbc: dbc::Proof>(
+    /// The checks are scoped to the inputs mapped by the bundle: witness
+    /// transaction may have other inputs controlled by unrelated parties,
+    /// closing seals of other contracts (for instance in coinjoin or payjoin
+    /// transactions), which are not a concern of this bundle.
+    fn validate_bundle_commitments(
         &self,
-        seals: impl IntoIterator<Item = &'seal Seal>,
-        bundle_id: BundleId,
-        witness: XChain<Witness<Dbc>>,
-        mpc_proof: mpc::MerkleProof,
-    ) where
-        XChain<Witness<Dbc>>: SealWitness<Seal, Message = mpc::Commitment>,
+        status: &mut Status,
+        check: &BundleCheck,
+        pub_witness: &XWitnessTx,
+    ) {
+        let BundleCheck {
+            bundle_id,
+            bundle,
+            ref input_map,
+            ..
+        } = *check;
+        let witness_id = pub_witness.witness_id();
+        trace_span!("rgb::validate_anchor", bundle_id = bundle_id, witness_id = witness_id);
+        let layer1 = pub_witness.layer1();
+        let pub_witness = pub_witness.as_reduced_unsafe();
+        for (vin, opid) in &bundle.input_map {
+            let Some(outpoints) = input_map.get(opid) else {
+                status.add_failure(Failure::BundleExtraTransition(bundle_id, *opid));
+                continue;
+            };
+            let Some(input) = pub_witness.inputs.get(vin.to_usize()) else {
+                status.add_failure(Failure::BundleInvalidInput(bundle_id, *opid, witness_id));
+                continue;
+            };
+            if !outpoints.contains(&XChain::with(layer1, input.prev_output)) {
+                status.add_failure(Failure::BundleInvalidCommitment(
+                    bundle_id, *vin, witness_id, *opid,
+                ));
+            }
+        }
+
+        // [VALIDATION]: Witness inputs closing the bundle seals and the witness locktime must
+        //               satisfy constraints declared by the contract schema
+        let vins = bundle.input_map.keys().copied();
+        for vin in self.witness_constraints.violating_inputs(pub_witness, vins) {
+            let sequence = pub_witness.inputs[vin.to_usize()]
+                .sequence
+                .to_consensus_u32();
+            status.add_failure(Failure::WitnessSequenceForbidden(
+                bundle_id, witness_id, vin, sequence,
+            ));
+        }
+        if !self
+            .witness_constraints
+            .accepts_locktime(pub_witness.lock_time.to_consensus_u32())
+        {
+            status.add_failure(Failure::WitnessLocktimeAbsent(bundle_id, witness_id));
+        }
+    }
+
+    /// Bitcoin- and liquid-specific commitment validation using deterministic
+    /// bitcoin commitments with opret and tapret schema.
+    fn validate_seal_commitments<'check>(
+        &self,
+        status: &mut Status,
+        check: &'check BundleCheck,
+        plugins: Option<&CloseMethodRegistry>,
+    ) -> Option<&'check XWitnessTx> {
+        let BundleCheck {
+            bundle_id,
+            witness_id,
+            anchor,
+            ref seals,
+            ..
+        } = *check;
+        let close_method = check.bundle.close_method;
+        // Check that the anchor is committed into a transaction spending all the
+        // transition inputs.
+        // Here the method can do SPV proof instead of querying the indexer. The SPV
+        // proofs can be part of the consignments, but do not require .
+        match &check.witness_tx {
+            Err(_) => {
+                // We wre unable to retrieve corresponding transaction, so can't check.
+                // Reporting this incident and continuing further. Why this happens? No
+                // connection to Bitcoin Core, Electrum or other backend etc. So this is not a
+                // failure in a strict sense, however we can't be sure that the consignment is
+                // valid. That's why we keep the track of such information in a separate place
+                // (`unresolved_txids` field of the validation status object).
+                status.absent_pub_witnesses.push(witness_id);
+                // This also can mean that there is no known transaction with the id provided by
+                // the anchor, i.e. consignment is invalid. We are proceeding with further
+                // validation in order to detect the rest of problems (and reporting the
+                // failure!)
+                status.add_failure(Failure::SealNoWitnessTx(witness_id));
+                None
+            }
+            Ok(pub_witness) => {
+                for seal in seals.iter().filter(|seal| seal.method() != close_method) {
+                    status.add_failure(Failure::SealInvalidMethod(bundle_id, *seal));
+                }
+                match (close_method, anchor.clone()) {
+                    (
+                        CloseMethod::TapretFirst,
+                        EAnchor {
+                            mpc_proof,
+                            dbc_proof: DbcProof::Tapret(tapret),
+                            ..
+                        },
+                    ) => {
+                        let witness = pub_witness.clone().map(|tx| Witness::with(tx, tapret));
+                        self.validate_seal_closing(status, seals, bundle_id, witness, mpc_proof)
+                    }
+                    (
+                        CloseMethod::OpretFirst,
+                        EAnchor {
+                            mpc_proof,
+                            dbc_proof: DbcProof::Opret(opret),
+                            ..
+                        },
+                    ) => {
+                        let witness = pub_witness.clone().map(|tx| Witness::with(tx, opret));
+                        self.validate_seal_closing(status, seals, bundle_id, witness, mpc_proof)
+                    }
+                    // Plugins are registered only for the close methods without built-in
+                    // verifiers
+                    (_, _) => {
+                        if let Some(plugin) =
+                            plugins.and_then(|registry| registry.plugin(close_method))
+                        {
+                            let message = mpc::Message::from(bundle_id);
+                            if let Err(err) = verify_with_plugin(
+                                plugin,
+                                anchor,
+                                self.contract_id.into(),
+                                message,
+                                pub_witness.as_reduced_unsafe(),
+                                seals.iter().map(XChain::as_reduced_unsafe),
+                            ) {
+                                status.add_failure(Failure::SealsInvalid(
+                                    bundle_id,
+                                    witness_id,
+                                    err.to_string(),
+                                ));
+                            }
+                        } else {
+                            status.add_failure(Failure::AnchorMethodMismatch(bundle_id));
+                        }
+                    }
+                }
+
+                Some(pub_witness)
+            }
+        }
+    }
+
+    /// Single-use-seal closing validation.
+    ///
+    /// Checks that the set of seals is closed over the message, which is
+    /// multi-protocol commitment, by utilizing witness, consisting of
+    /// transaction with deterministic bitcoin commitments (defined by
+    /// generic type `Dbc`) and extra-transaction data, which are taken from
+    /// anchor's DBC proof.
+    ///
+    /// Additionally, checks that the provided message contains commitment to
+    /// the bundle under the current contract.
+    fn validate_seal_closing<'seal, Seal: 'seal, Dbc: dbc::Proof>(
+        &self,
+        status: &mut Status,
+        seals: impl IntoIterator<Item = &'seal Seal>,
+        bundle_id: BundleId,
+        witness: XChain<Witness<Dbc>>,
+        mpc_proof: mpc::MerkleProof,
+    ) where
+        XChain<Witness<Dbc>>: SealWitness<Seal, Message = mpc::Commitment>,
     {
         let message = mpc::Message::from(bundle_id);
         let witness_id = witness.witness_id();
@@ -591,16 +2166,14 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
             Err(err) => {
                 // The operation is not committed to bitcoin transaction graph!
                 // Ultimate failure. But continuing to detect the rest (after reporting it).
-                self.status
-                    .borrow_mut()
-                    .add_failure(Failure::MpcInvalid(bundle_id, witness_id, err));
+                status.add_failure(Failure::MpcInvalid(bundle_id, witness_id, err));
             }
             Ok(commitment) => {
                 // [VALIDATION]: CHECKING SINGLE-USE-SEALS
                 witness
                     .verify_many_seals(seals, &commitment)
                     .map_err(|err| {
-                        self.status.borrow_mut().add_failure(Failure::SealsInvalid(
+                        status.add_failure(Failure::SealsInvalid(
                             bundle_id,
                             witness_id,
                             err.to_string(),
@@ -611,3 +2184,1497 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
         }
     }
 }
+
+/// Reports assignment types of the operation which have concealed fungible
+/// state. The report doesn't affect failures of the range proof validation.
+fn report_concealed_amounts(status: &mut Status, operation: &OpRef) {
+    let opid = operation.id();
+    for (ty, assigns) in operation.assignments().flat().iter() {
+        if assigns
+            .as_fungible()
+            .iter()
+            .any(|assign| assign.as_revealed_state().is_none())
+        {
+            status.add_info(Info::UncheckableConfidentialState(opid, *ty));
+        }
+    }
+}
+
+/// Converts failures caused by state values encoded with older minor versions
+/// of the state formats committed by the genesis into information notes.
+fn accept_older_state(
+    status: &mut Status,
+    registry: &StateRegistry,
+    operation: &OpRef,
+    types: &TypeSystem,
+) {
+    let failures = status.failures.drain(..).collect::<Vec<_>>();
+    for failure in failures {
+        let info = match failure {
+            Failure::SchemaInvalidGlobalValue(opid, ty, sem_id) => {
+                let values = operation
+                    .globals()
+                    .get(&ty)
+                    .into_iter()
+                    .flat_map(|v| v.iter());
+                read_older(registry, StateKey::Global(ty), sem_id, types, values)
+                    .map(|version| Info::GlobalStateOlderVersion(opid, ty, version))
+            }
+            Failure::SchemaInvalidOwnedValue(opid, ty, sem_id) => {
+                let assigns = operation.assignments_by_type(ty);
+                let values = assigns
+                    .iter()
+                    .flat_map(|assigns| assigns.as_structured())
+                    .filter_map(|assign| assign.as_revealed_state())
+                    .map(|data| &data.value);
+                read_older(registry, StateKey::Owned(ty), sem_id, types, values)
+                    .map(|version| Info::OwnedStateOlderVersion(opid, ty, version))
+            }
+            _ => None,
+        };
+        match info {
+            Some(info) if status.info.contains(&info) => {}
+            Some(info) => status.info.push(info),
+            None => status.failures.push(failure),
+        }
+    }
+}
+
+/// Returns the oldest version of the state format used by the values, if all
+/// of them can be read under the format `sem_id` or its older minor versions.
+fn read_older<Data: AsRef<[u8]>>(
+    registry: &StateRegistry,
+    key: StateKey,
+    sem_id: SemId,
+    types: &TypeSystem,
+    values: impl IntoIterator<Item = Data>,
+) -> Option<StateVersion> {
+    let mut oldest = None::<StateVersion>;
+    for data in values {
+        if types.strict_deserialize_type(sem_id, data.as_ref()).is_ok() {
+            continue;
+        }
+        let version = registry.read_older(key, sem_id, types, data)?;
+        oldest = Some(oldest.map_or(version, |oldest| oldest.min(version)));
+    }
+    oldest
+}
+
+/// Removes metadata and global state of the types reserved by the consensus
+/// from the genesis of a contract which schema doesn't opt into them.
+/// Collects schema upgrades performed by the state extensions of the
+/// consignment, returning for each operation the upgrades in its history,
+/// ordered by their activation height. Operations other than state extensions
+/// can't upgrade the contract.
+fn schema_upgrades(
+    consignment: &impl ConsignmentApi,
+    dag: &ConsignmentDag,
+    status: &mut Status,
+) -> BTreeMap<OpId, Vec<SchemaUpgrade>> {
+    let mut upgrades = BTreeMap::<OpId, Vec<SchemaUpgrade>>::new();
+    for opid in dag.operations() {
+        let Some(op) = consignment.operation(opid) else {
+            continue;
+        };
+        let upgrade = match op.metadata().schema_upgrade() {
+            Some(None) => continue,
+            Some(Some(upgrade)) if op.op_type() == OpType::StateExtension => upgrade,
+            _ => {
+                status.add_failure(Failure::SchemaUpgradeInvalid(opid));
+                continue;
+            }
+        };
+        for descendant in dag.descendants(opid) {
+            upgrades.entry(descendant).or_default().push(upgrade);
+        }
+    }
+    for history in upgrades.values_mut() {
+        history.sort_by_key(|upgrade| (upgrade.activation_height, upgrade.successor));
+        history.dedup();
+    }
+    upgrades
+}
+
+fn strip_reserved(genesis: &Genesis) -> Genesis {
+    let mut genesis = genesis.clone();
+    let meta_types = genesis
+        .metadata
+        .keys()
+        .copied()
+        .filter(|ty| ty.is_reserved())
+        .collect::<Vec<_>>();
+    for ty in meta_types {
+        genesis.metadata.remove(&ty).expect("metadata may be empty");
+    }
+    let global_types = genesis
+        .globals
+        .keys()
+        .copied()
+        .filter(|ty| ty.is_reserved())
+        .collect::<Vec<_>>();
+    for ty in global_types {
+        genesis
+            .globals
+            .remove(&ty)
+            .expect("global state may be empty");
+    }
+    genesis
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::RangeInclusive;
+
+    use aluvm::isa::{ControlFlowOp, Instr};
+    use aluvm::library::{Lib, LibSite};
+    use amplify::confinement::{Confined, SmallBlob};
+    use amplify::Wrapper;
+    use strict_encoding::StrictDumb;
+    use strict_types::typelib::LibBuilder;
+    use strict_types::SystemBuilder;
+
+    use super::*;
+    use crate::validation::fixtures::{TestChain, TestConsignment};
+    use crate::validation::{Interruption, MemValidationStore};
+    use crate::{
+        dependencies_meta, finality_depth_meta, oracle_feeds_meta, schema, supply_caps_meta,
+        template_id_meta, valency_limits_meta, AssetTag, AssetTags, Assign, AssignFungible,
+        AssignmentType, Assignments, DataState, Extension, ExtensionSchema, ExtensionType,
+        GenesisTemplateId, GraphSeal, Input, InputMap, Inputs, MetaValue, OwnedStateSchema,
+        Redemptions, RevealedValue, SchemaFlags, SecretSeal, TermsError, TransitionSchema,
+        TransitionType, TypedAssigns, Valencies, ValencyError, ValencyLimit, ValencyType, Vin,
+        WitnessPos,
+    };
+
+    const LIB_NAME_TEST: &str = "RGBTest";
+    const TY: AssignmentType = AssignmentType::with(4000);
+    const TRANSITION_TY: TransitionType = TransitionType::with(1);
+
+    /// Layout of a single entry of the [`schema::MetaType::SUPPLY_CAPS`]
+    /// metadata.
+    #[derive(Clone, Debug)]
+    #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+    #[strict_type(lib = LIB_NAME_TEST)]
+    struct SupplyCap {
+        ty: u16,
+        cap: u64,
+    }
+
+    fn capped_contract(issued: &[u64], concealed: bool, flags: SchemaFlags) -> TestConsignment {
+        let lib = LibBuilder::new(libname!(LIB_NAME_TEST), [])
+            .transpile::<SupplyCap>()
+            .compile()
+            .unwrap();
+        let sys = SystemBuilder::new()
+            .import(lib)
+            .unwrap()
+            .finalize()
+            .unwrap();
+        let sem_id = *sys.resolve("RGBTest.SupplyCap").unwrap();
+
+        let mut schema = Schema::strict_dumb();
+        schema.flags = flags;
+        schema.meta_types = Confined::from_iter_unsafe([(schema::MetaType::SUPPLY_CAPS, sem_id)]);
+        schema.owned_types =
+            Confined::from_iter_unsafe([(TY, OwnedStateSchema::Fungible(default!()))]);
+        schema.genesis.metadata = Confined::from_iter_unsafe([schema::MetaType::SUPPLY_CAPS]);
+        schema.genesis.assignments =
+            Confined::from_iter_unsafe([(TY, schema::Occurrences::OnceOrMore)]);
+
+        let mut chain = TestChain::regtest();
+        let tag = AssetTag::strict_dumb();
+        let allocations = issued
+            .iter()
+            .map(|amount| {
+                let assign = Assign::revealed(
+                    chain.genesis_seal(),
+                    RevealedValue::new_random_blinding(*amount, tag),
+                );
+                if concealed {
+                    assign.conceal_state()
+                } else {
+                    assign
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut genesis = Genesis::strict_dumb();
+        genesis.schema_id = schema.schema_id();
+        genesis.testnet = chain.testnet();
+        genesis
+            .metadata
+            .add_value(
+                schema::MetaType::SUPPLY_CAPS,
+                supply_caps_meta(&bmap! { TY => 100 }).unwrap(),
+            )
+            .unwrap();
+        genesis.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            TY,
+            TypedAssigns::Fungible(Confined::try_from(allocations).unwrap()),
+        )]));
+        TestConsignment::new(schema, sys.as_types().clone(), genesis)
+    }
+
+    #[test]
+    fn supply_caps() {
+        let chain = TestChain::regtest();
+
+        let reserved = SchemaFlags::RESERVED_TYPES;
+        let consignment = capped_contract(&[25, 35], false, reserved);
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(!status.failures.iter().any(|failure| matches!(
+            failure,
+            Failure::SupplyCapExceeded(..) | Failure::SupplyUncheckable(..)
+        )));
+        assert_eq!(status.remaining_supply(TY), Some(40));
+
+        let consignment = capped_contract(&[60, 41], false, reserved);
+        let genesis_id = consignment.genesis.id();
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(status
+            .failures
+            .contains(&Failure::SupplyCapExceeded(genesis_id, TY, 100)));
+        assert_eq!(status.remaining_supply(TY), Some(0));
+
+        let consignment = capped_contract(&[60], true, reserved);
+        let genesis_id = consignment.genesis.id();
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(status
+            .failures
+            .contains(&Failure::SupplyUncheckable(genesis_id, TY)));
+        assert_eq!(status.remaining_supply(TY), Some(100));
+    }
+
+    #[test]
+    fn reserved_types_opt_in() {
+        let chain = TestChain::regtest();
+        let consignment = capped_contract(&[60, 41], false, SchemaFlags::default());
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(!status.failures.contains(&Failure::SupplyCapExceeded(
+            consignment.genesis.id(),
+            TY,
+            100
+        )));
+        assert_eq!(status.remaining_supply(TY), None);
+    }
+
+    /// Failures of the validation except the ones reporting metadata types
+    /// unknown to the schema: test schemas don't declare the reserved
+    /// metadata types used by the test contracts.
+    fn failures_except_meta(status: &Status) -> Vec<&Failure> {
+        status
+            .failures
+            .iter()
+            .filter(|failure| !matches!(failure, Failure::SchemaUnknownMetaType(..)))
+            .collect()
+    }
+
+    /// Contract with a single state transition spending the genesis
+    /// allocation, anchored to the chain. Returns the consignment, the id of
+    /// the transition and the spent allocation.
+    fn spending_contract(
+        chain: &mut TestChain,
+        flags: SchemaFlags,
+        metadata: impl IntoIterator<Item = (schema::MetaType, MetaValue)>,
+    ) -> (TestConsignment, OpId, Opout, AssignFungible<GraphSeal>) {
+        let mut schema = Schema::strict_dumb();
+        schema.flags = flags;
+        let mut genesis = Genesis::strict_dumb();
+        for (ty, value) in metadata {
+            genesis.metadata.add_value(ty, value).unwrap();
+        }
+        schema_contract(chain, schema, genesis)
+    }
+
+    fn schema_contract(
+        chain: &mut TestChain,
+        schema: Schema,
+        genesis: Genesis,
+    ) -> (TestConsignment, OpId, Opout, AssignFungible<GraphSeal>) {
+        let transfer = Transfer::new(chain, schema, genesis, 0);
+        let (witness_id, anchor) =
+            chain.commit(transfer.contract_id(), transfer.bundle.bundle_id(), [transfer.spent], 1);
+        chain.mine(witness_id);
+        transfer.anchor(witness_id, anchor)
+    }
+
+    /// Contract with a transfer bundle which is not anchored yet.
+    struct Transfer {
+        consignment: TestConsignment,
+        bundle: TransitionBundle,
+        spent: Outpoint,
+        opid: OpId,
+        opout: Opout,
+        allocation: AssignFungible<GraphSeal>,
+        terminal: XChain<SecretSeal>,
+    }
+
+    impl Transfer {
+        /// Constructs contract transferring its genesis allocation with the
+        /// witness input `vin` to the witness output `vin + 1`. Metadata and
+        /// global state of the contract are taken from the provided genesis.
+        fn new(chain: &mut TestChain, mut schema: Schema, mut genesis: Genesis, vin: u32) -> Self {
+            let tag = AssetTag::strict_dumb();
+            schema.owned_types =
+                Confined::from_iter_unsafe([(TY, OwnedStateSchema::Fungible(default!()))]);
+            schema.genesis.assignments =
+                Confined::from_iter_unsafe([(TY, schema::Occurrences::Once)]);
+            schema.transitions = Confined::from_iter_unsafe([(TRANSITION_TY, TransitionSchema {
+                inputs: Confined::from_iter_unsafe([(TY, schema::Occurrences::Once)]),
+                assignments: Confined::from_iter_unsafe([(TY, schema::Occurrences::Once)]),
+                ..default!()
+            })]);
+
+            let seal = chain.genesis_seal();
+            let allocation = Assign::revealed(seal, RevealedValue::new_random_blinding(100, tag));
+            genesis.schema_id = schema.schema_id();
+            genesis.testnet = chain.testnet();
+            genesis.asset_tags = AssetTags::from_inner(Confined::from_iter_unsafe([(TY, tag)]));
+            genesis.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+                TY,
+                TypedAssigns::Fungible(Confined::from_iter_unsafe([allocation.clone()])),
+            )]));
+            let opout = Opout::new(genesis.id(), TY, 0);
+
+            let output = Assign::revealed(
+                chain.graph_seal(vin + 1),
+                RevealedValue::new_random_blinding(100, tag),
+            );
+            let mut transition = Transition::strict_dumb();
+            transition.contract_id = genesis.contract_id();
+            transition.transition_type = TRANSITION_TY;
+            transition.inputs = Inputs::try_from_inputs([Input::with(opout)]).unwrap();
+            transition.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+                TY,
+                TypedAssigns::Fungible(Confined::from_iter_unsafe([output.clone()])),
+            )]));
+            let opid = transition.id();
+            let bundle = TransitionBundle {
+                close_method: CloseMethod::OpretFirst,
+                input_map: InputMap::with(Vin::from(vin), opid),
+                known_transitions: Confined::with((opid, transition)),
+            };
+            Transfer {
+                consignment: TestConsignment::new(schema, TypeSystem::default(), genesis),
+                bundle,
+                spent: *seal.to_outpoint().as_reduced_unsafe(),
+                opid,
+                opout,
+                allocation: allocation.transmutate_seals(),
+                terminal: output.to_confidential_seal(),
+            }
+        }
+
+        fn contract_id(&self) -> ContractId { self.consignment.genesis.contract_id() }
+
+        fn anchor(
+            mut self,
+            witness_id: XWitnessId,
+            anchor: EAnchor,
+        ) -> (TestConsignment, OpId, Opout, AssignFungible<GraphSeal>) {
+            let bundle_id = self.consignment.add_bundle(self.bundle, witness_id, anchor);
+            self.consignment.terminals.push((bundle_id, self.terminal));
+            (self.consignment, self.opid, self.opout, self.allocation)
+        }
+    }
+
+    #[test]
+    fn coinjoin() {
+        // Two unrelated parties transfer assets of different contracts in the same
+        // witness, which also spends an output of a third party
+        let mut chain = TestChain::regtest();
+        let first = Transfer::new(&mut chain, Schema::strict_dumb(), Genesis::strict_dumb(), 0);
+        let second = Transfer::new(&mut chain, Schema::strict_dumb(), Genesis::strict_dumb(), 1);
+        let foreign = chain.outpoint();
+        let (witness_id, mut anchors) = chain.commit_many(
+            [
+                (first.contract_id(), first.bundle.bundle_id()),
+                (second.contract_id(), second.bundle.bundle_id()),
+            ],
+            [first.spent, second.spent, foreign],
+            3,
+        );
+        chain.mine(witness_id);
+
+        for transfer in [first, second] {
+            let anchor = anchors.remove(&transfer.contract_id()).unwrap();
+            let (consignment, ..) = transfer.anchor(witness_id, anchor);
+            let status = Validator::validate(&consignment, &chain, chain.testnet());
+            assert!(status.failures.is_empty(), "{status}");
+        }
+    }
+
+    #[test]
+    fn witness_constraints() {
+        // Test chain witnesses signal RBF and have zero locktime
+        let mut chain = TestChain::regtest();
+        let mut schema = Schema::strict_dumb();
+        schema.witness_constraints = WitnessConstraints::RBF;
+        let (consignment, ..) = schema_contract(&mut chain, schema, Genesis::strict_dumb());
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(!status.failures.iter().any(|failure| matches!(
+            failure,
+            Failure::WitnessSequenceForbidden(..) | Failure::WitnessLocktimeAbsent(..)
+        )));
+
+        let mut schema = Schema::strict_dumb();
+        schema.witness_constraints = WitnessConstraints::with_sequence(0..=0xFFFF_FFFC, false);
+        let (consignment, opid, ..) = schema_contract(&mut chain, schema, Genesis::strict_dumb());
+        let witness_id = consignment.op_witness_id(opid).unwrap();
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(status.failures.iter().any(|failure| matches!(
+            failure,
+            Failure::WitnessSequenceForbidden(_, id, vin, 0xFFFF_FFFD)
+                if *id == witness_id && *vin == Vin::from(0u32)
+        )));
+        assert!(!status
+            .failures
+            .iter()
+            .any(|failure| matches!(failure, Failure::WitnessLocktimeAbsent(..))));
+
+        let mut schema = Schema::strict_dumb();
+        schema.witness_constraints = WitnessConstraints::with_sequence(0..=u32::MAX, true);
+        let (consignment, opid, ..) = schema_contract(&mut chain, schema, Genesis::strict_dumb());
+        let witness_id = consignment.op_witness_id(opid).unwrap();
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(status.failures.iter().any(|failure| matches!(
+            failure,
+            Failure::WitnessLocktimeAbsent(_, id) if *id == witness_id
+        )));
+
+        let mut schema = Schema::strict_dumb();
+        schema.witness_constraints =
+            WitnessConstraints::with_sequence(RangeInclusive::new(2, 1), false);
+        let (consignment, ..) = schema_contract(&mut chain, schema, Genesis::strict_dumb());
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(status
+            .failures
+            .contains(&Failure::SchemaWitnessConstraintsInvalid));
+    }
+
+    #[test]
+    fn spent_states() {
+        let mut chain = TestChain::regtest();
+        let (mut consignment, opid, opout, allocation) =
+            spending_contract(&mut chain, SchemaFlags::default(), []);
+
+        consignment
+            .spent_states
+            .insert(opout, SpentState::Fungible(allocation.clone()));
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(
+            !status
+                .failures
+                .contains(&Failure::SpentStateMismatch(opid, opout)),
+            "{status}"
+        );
+
+        let seal = allocation.revealed_seal().unwrap();
+        let forged = Assign::revealed(
+            seal,
+            RevealedValue::new_random_blinding(1000, AssetTag::strict_dumb()),
+        );
+        consignment
+            .spent_states
+            .insert(opout, SpentState::Fungible(forged));
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(status
+            .failures
+            .contains(&Failure::SpentStateMismatch(opid, opout)));
+    }
+
+    #[test]
+    fn provisional_witnesses() {
+        let mut chain = TestChain::regtest();
+        let depth = finality_depth_meta(FinalityDepth::new(6)).unwrap();
+        let (consignment, opid, ..) =
+            spending_contract(&mut chain, SchemaFlags::RESERVED_TYPES, [(
+                schema::MetaType::FINALITY_DEPTH,
+                depth,
+            )]);
+        let witness_id = consignment.op_witness_id(opid).unwrap();
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(status.info.contains(&Info::WitnessProvisional(witness_id)));
+
+        // Witnesses of the operations which outcome is taken from the validation store
+        // are reported as well
+        let mut store = MemValidationStore::new();
+        let genesis = &consignment.genesis;
+        let context =
+            ValidationContext::with(genesis.contract_id(), genesis.schema_id, chain.testnet());
+        store.store_op_status(context, opid, &Status::new());
+        let status =
+            Validator::validate_with_store(&consignment, &chain, &mut store, chain.testnet());
+        assert!(status.info.contains(&Info::WitnessProvisional(witness_id)));
+
+        for _ in 0..6 {
+            let funding = chain.outpoint();
+            let (witness_id, _) =
+                chain.commit(genesis.contract_id(), BundleId::from([0; 32]), [funding], 0);
+            chain.mine(witness_id);
+        }
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(!status.info.contains(&Info::WitnessProvisional(witness_id)));
+    }
+
+    /// Resolver recording the hints it was called with.
+    struct HintRecorder<'chain> {
+        chain: &'chain TestChain,
+        hinted: RefCell<Vec<(XWitnessId, WitnessHint)>>,
+        unhinted: RefCell<Vec<XWitnessId>>,
+    }
+
+    impl<'chain> HintRecorder<'chain> {
+        fn new(chain: &'chain TestChain) -> Self {
+            HintRecorder {
+                chain,
+                hinted: empty!(),
+                unhinted: empty!(),
+            }
+        }
+    }
+
+    impl<'chain> ResolveWitness for HintRecorder<'chain> {
+        fn resolve_pub_witness(
+            &self,
+            witness_id: XWitnessId,
+        ) -> Result<XWitnessTx, WitnessResolverError> {
+            self.unhinted.borrow_mut().push(witness_id);
+            self.chain.resolve_pub_witness(witness_id)
+        }
+
+        fn resolve_pub_witness_hinted(
+            &self,
+            witness_id: XWitnessId,
+            hint: WitnessHint,
+        ) -> Result<XWitnessTx, WitnessResolverError> {
+            self.hinted.borrow_mut().push((witness_id, hint));
+            self.chain.resolve_pub_witness(witness_id)
+        }
+
+        fn resolve_witness_ord(
+            &self,
+            witness_id: XWitnessId,
+        ) -> Result<WitnessOrd, WitnessResolverError> {
+            self.unhinted.borrow_mut().push(witness_id);
+            self.chain.resolve_witness_ord(witness_id)
+        }
+
+        fn resolve_witness_ord_hinted(
+            &self,
+            witness_id: XWitnessId,
+            hint: WitnessHint,
+        ) -> Result<WitnessOrd, WitnessResolverError> {
+            self.hinted.borrow_mut().push((witness_id, hint));
+            self.chain.resolve_witness_ord(witness_id)
+        }
+
+        fn resolve_tip_height(&self, layer1: Layer1) -> Option<u32> {
+            self.chain.resolve_tip_height(layer1)
+        }
+    }
+
+    #[test]
+    fn witness_hints() {
+        let mut chain = TestChain::regtest();
+        let depth = finality_depth_meta(FinalityDepth::new(6)).unwrap();
+        let (mut consignment, opid, ..) = spending_contract(
+            &mut chain,
+            SchemaFlags::RESERVED_TYPES,
+            [(schema::MetaType::FINALITY_DEPTH, depth)],
+        );
+        let witness_id = consignment.op_witness_id(opid).unwrap();
+        let Ok(WitnessOrd::OnChain(pos)) = chain.resolve_witness_ord(witness_id) else {
+            panic!("witness is mined");
+        };
+        let hint = WitnessHint {
+            height: pos.height().get(),
+            block_hash: None,
+        };
+        consignment.hints.insert(witness_id, hint);
+
+        // Both the witness transaction and its position are resolved with the hint
+        let resolver = HintRecorder::new(&chain);
+        let status = Validator::validate(&consignment, &resolver, chain.testnet());
+        assert!(failures_except_meta(&status).is_empty(), "{status}");
+        assert!(resolver.unhinted.borrow().is_empty());
+        assert!(resolver.hinted.borrow().len() >= 2);
+        assert!(resolver
+            .hinted
+            .borrow()
+            .iter()
+            .all(|entry| *entry == (witness_id, hint)));
+        assert!(!status
+            .warnings
+            .iter()
+            .any(|warning| matches!(warning, Warning::WitnessHintMismatch(..))));
+
+        // Hints are never trusted: the position is taken from the resolver
+        let wrong = WitnessHint {
+            height: hint.height + 10,
+            block_hash: None,
+        };
+        consignment.hints.insert(witness_id, wrong);
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(failures_except_meta(&status).is_empty(), "{status}");
+        assert!(status
+            .warnings
+            .contains(&Warning::WitnessHintMismatch(witness_id, wrong.height)));
+        assert!(status.info.contains(&Info::WitnessProvisional(witness_id)));
+    }
+
+    /// Resolver failing to resolve witness transactions on the first attempt.
+    struct FlakyResolver<'chain> {
+        chain: &'chain TestChain,
+        calls: Cell<usize>,
+    }
+
+    impl<'chain> ResolveWitness for FlakyResolver<'chain> {
+        fn resolve_pub_witness(
+            &self,
+            witness_id: XWitnessId,
+        ) -> Result<XWitnessTx, WitnessResolverError> {
+            self.calls.set(self.calls.get() + 1);
+            if self.calls.get() == 1 {
+                return Err(WitnessResolverError::Other(witness_id, s!("timeout")));
+            }
+            self.chain.resolve_pub_witness(witness_id)
+        }
+
+        fn resolve_witness_ord(
+            &self,
+            witness_id: XWitnessId,
+        ) -> Result<WitnessOrd, WitnessResolverError> {
+            self.chain.resolve_witness_ord(witness_id)
+        }
+    }
+
+    #[test]
+    fn batch_resolver() {
+        let mut chain = TestChain::regtest();
+        let (consignment, opid, ..) = spending_contract(&mut chain, SchemaFlags::default(), []);
+        let witness_id = consignment.op_witness_id(opid).unwrap();
+        let flaky = FlakyResolver {
+            chain: &chain,
+            calls: Cell::new(0),
+        };
+        let resolver = BatchResolver::new(&flaky);
+
+        // Errors are not reused by the other consignments of the batch
+        assert!(resolver.resolve_pub_witness(witness_id).is_err());
+        let tx = resolver.resolve_pub_witness(witness_id).unwrap();
+        assert_eq!(resolver.resolve_pub_witness(witness_id).unwrap(), tx);
+        assert_eq!(flaky.calls.get(), 2);
+
+        let resolved = resolver.resolve_pub_witnesses(&bmap! { witness_id => None });
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[&witness_id].as_ref().ok(), Some(&tx));
+        assert_eq!(flaky.calls.get(), 2);
+    }
+
+    #[test]
+    fn batch_validation() {
+        let mut chain = TestChain::regtest();
+        let (consignment, opid, ..) = spending_contract(&mut chain, SchemaFlags::default(), []);
+        let witness_id = consignment.op_witness_id(opid).unwrap();
+        let flaky = FlakyResolver {
+            chain: &chain,
+            calls: Cell::new(0),
+        };
+
+        // Witness which failed to resolve for the first consignment is resolved again
+        // for the second one
+        let statuses =
+            Validator::validate_batch([&consignment, &consignment], &flaky, chain.testnet());
+        assert_eq!(statuses.len(), 2);
+        assert!(statuses[0]
+            .failures
+            .contains(&Failure::SealNoWitnessTx(witness_id)));
+        assert_eq!(statuses[1], Validator::validate(&consignment, &chain, chain.testnet()));
+        assert_eq!(flaky.calls.get(), 2);
+    }
+
+    #[test]
+    fn replaced_witness() {
+        let mut chain = TestChain::regtest();
+        let (mut consignment, ..) = spending_contract(&mut chain, SchemaFlags::default(), []);
+        let contract_id = consignment.genesis.contract_id();
+        let (bundle_id, (witness_id, _)) = consignment.anchors.pop_first().unwrap();
+        let witness_tx = chain.resolve_pub_witness(witness_id).unwrap();
+        let spent = witness_tx.as_reduced_unsafe().inputs[0].prev_output;
+        chain.drop_witness(witness_id);
+
+        // RBF replacement: commits to the same bundle, preserving the outputs, but adds
+        // a fee input
+        let (replaced, _) = chain.commit(contract_id, bundle_id, [spent], 1);
+        let funding = chain.outpoint();
+        let (replacement, anchor) = chain.commit(contract_id, bundle_id, [spent, funding], 1);
+        let replaced_tx = chain.resolve_pub_witness(replaced).unwrap();
+        chain.drop_witness(replaced);
+        chain.mine(replacement);
+        consignment.anchors.insert(bundle_id, (replacement, anchor));
+        consignment
+            .substitutions
+            .insert(bundle_id, WitnessSubstitution::new(bundle_id, replaced, replacement));
+
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(
+            status
+                .failures
+                .contains(&Failure::SealNoWitnessTx(replaced)),
+            "{status}"
+        );
+
+        consignment.replaced_witnesses.insert(replaced, replaced_tx);
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(!status.failures.iter().any(|failure| matches!(
+            failure,
+            Failure::SealNoWitnessTx(..) | Failure::WitnessSubstitutionInvalid(..)
+        )));
+        assert!(status.absent_pub_witnesses.is_empty(), "{status}");
+
+        // Transaction provided by the consignment must have the replaced witness id
+        consignment.replaced_witnesses.insert(replaced, witness_tx);
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(
+            status
+                .failures
+                .contains(&Failure::SealNoWitnessTx(replaced)),
+            "{status}"
+        );
+
+        // Substitution must replace the witness the bundle is anchored to
+        consignment
+            .substitutions
+            .insert(bundle_id, WitnessSubstitution::new(bundle_id, replacement, replaced));
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(
+            status
+                .failures
+                .contains(&Failure::WitnessSubstitutionMismatch(bundle_id, replacement, replaced)),
+            "{status}"
+        );
+    }
+
+    #[test]
+    fn alt_witnesses() {
+        let mut chain = TestChain::regtest();
+        let (mut consignment, ..) = spending_contract(&mut chain, SchemaFlags::default(), []);
+        let contract_id = consignment.genesis.contract_id();
+        let (bundle_id, (witness_id, _)) = consignment.anchors.pop_first().unwrap();
+        let witness_tx = chain.resolve_pub_witness(witness_id).unwrap();
+        let spent = witness_tx.as_reduced_unsafe().inputs[0].prev_output;
+        chain.drop_witness(witness_id);
+
+        // Main witness is replaced by the mined alternative and is unknown to the chain
+        let (main, main_anchor) = chain.commit(contract_id, bundle_id, [spent], 1);
+        let funding = chain.outpoint();
+        let (alt, alt_anchor) = chain.commit(contract_id, bundle_id, [spent, funding], 1);
+        // Alternatives are committed at the same height, so their commitment outputs
+        // match
+        let funding = chain.outpoint();
+        let (other, other_anchor) = chain.commit(contract_id, bundle_id, [spent, funding], 1);
+        let main_tx = chain.resolve_pub_witness(main).unwrap();
+        chain.drop_witness(main);
+        chain.mine(alt);
+        consignment.anchors.insert(bundle_id, (main, main_anchor));
+        consignment
+            .alt_anchors
+            .insert(bundle_id, vec![(alt, alt_anchor.clone())]);
+
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(
+            status.failures.iter().any(|failure| matches!(
+                failure,
+                Failure::WitnessAlternativeInvalid(id, witness_id, _)
+                    if *id == bundle_id && *witness_id == alt
+            )),
+            "{status}"
+        );
+
+        consignment.replaced_witnesses.insert(main, main_tx);
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(
+            !status
+                .failures
+                .iter()
+                .any(|failure| matches!(failure, Failure::WitnessAlternativeInvalid(..))),
+            "{status}"
+        );
+        assert!(status.info.contains(&Info::WitnessVoid(bundle_id, main)), "{status}");
+
+        // Alternatives are mutually exclusive, thus at most one of them may be mined
+        chain.mine(other);
+        consignment
+            .alt_anchors
+            .insert(bundle_id, vec![(alt, alt_anchor), (other, other_anchor)]);
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(
+            status
+                .failures
+                .contains(&Failure::WitnessAlternativesMined(bundle_id)),
+            "{status}"
+        );
+    }
+
+    #[test]
+    fn required_libs() {
+        let chain = TestChain::regtest();
+        let succ = |lib: &Lib| LibSite::with(0, lib.id());
+        let missing =
+            Lib::assemble::<Instr<RgbIsa>>(&[Instr::ControlFlow(ControlFlowOp::Ret)]).unwrap();
+        let unused =
+            Lib::assemble::<Instr<RgbIsa>>(&[Instr::ControlFlow(ControlFlowOp::Fail)]).unwrap();
+        let caller = Lib::assemble::<Instr<RgbIsa>>(&[Instr::ControlFlow(ControlFlowOp::Call(
+            succ(&missing),
+        ))])
+        .unwrap();
+
+        let mut consignment = capped_contract(&[25], false, SchemaFlags::default());
+        let transition_schema = TransitionSchema {
+            validator: Some(succ(&unused)),
+            ..default!()
+        };
+        consignment.schema.transitions =
+            Confined::from_iter_unsafe([(TRANSITION_TY, transition_schema)]);
+        consignment.schema.genesis.validator = Some(succ(&caller));
+        consignment.genesis.schema_id = consignment.schema.schema_id();
+        consignment.scripts = Confined::from_iter_unsafe([(caller.id(), caller.clone())]);
+
+        // Schema acceptance requires all libraries, including the ones called by other
+        // libraries
+        let failures = consignment
+            .schema
+            .verify_libs(&consignment.scripts)
+            .failures;
+        assert_eq!(failures.len(), 2);
+        assert!(failures.contains(&Failure::SchemaLibUnknown(missing.id())));
+        assert!(failures.contains(&Failure::SchemaLibUnknown(unused.id())));
+
+        // Validation requires only the libraries of the used operation types
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(
+            status
+                .failures
+                .contains(&Failure::SchemaLibUnknown(missing.id())),
+            "{status}"
+        );
+        assert!(
+            !status
+                .failures
+                .contains(&Failure::SchemaLibUnknown(unused.id())),
+            "{status}"
+        );
+        assert!(
+            !status
+                .failures
+                .contains(&Failure::SchemaLibUnknown(caller.id())),
+            "{status}"
+        );
+
+        consignment.scripts.insert(missing.id(), missing).unwrap();
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(
+            !status
+                .failures
+                .iter()
+                .any(|failure| matches!(failure, Failure::SchemaLibUnknown(_))),
+            "{status}"
+        );
+    }
+
+    #[test]
+    fn genesis_metadata() {
+        let mut chain = TestChain::regtest();
+        let malformed = MetaValue::from(Confined::try_from(vec![0xFF]).unwrap());
+        type Case = (schema::MetaType, fn(OpId) -> Failure);
+        let cases: [Case; 12] = [
+            (schema::MetaType::CLOSE_METHODS, |_| Failure::GenesisCloseMethodsInvalid),
+            (schema::MetaType::DEPENDENCIES, |_| Failure::GenesisDependenciesInvalid),
+            (schema::MetaType::CHAIN_SALT, |_| Failure::GenesisChainSaltInvalid),
+            (schema::MetaType::SUPPLY_CAPS, |_| Failure::GenesisSupplyCapsInvalid),
+            (schema::MetaType::ORACLE_FEEDS, |_| Failure::GenesisOracleFeedsInvalid),
+            (schema::MetaType::FINALITY_DEPTH, |_| Failure::GenesisFinalityDepthInvalid),
+            (schema::MetaType::VALENCY_LIMITS, |_| Failure::GenesisValencyLimitsInvalid),
+            (schema::MetaType::SCHEMA_UPGRADE, Failure::SchemaUpgradeInvalid),
+            (schema::MetaType::STATE_FORMATS, |_| Failure::GenesisStateFormatsInvalid),
+            (schema::MetaType::GENESIS_TEMPLATE, |_| Failure::GenesisTemplateInvalid),
+            (schema::MetaType::ASSIGNMENT_LOCKS, Failure::AssignmentLocksInvalid),
+            (schema::MetaType::OP_FEATURES, Failure::OpFeaturesInvalid),
+        ];
+        for (ty, failure) in cases {
+            let (consignment, ..) = spending_contract(&mut chain, SchemaFlags::RESERVED_TYPES, [(
+                ty,
+                malformed.clone(),
+            )]);
+            let failure = failure(consignment.genesis.id());
+            let status = Validator::validate(&consignment, &chain, chain.testnet());
+            assert!(status.failures.contains(&failure), "{ty:?}: {status}");
+        }
+
+        // Reserved metadata types are ordinary types unless the schema opts into them
+        let (consignment, ..) = spending_contract(&mut chain, SchemaFlags::default(), [(
+            schema::MetaType::CLOSE_METHODS,
+            malformed,
+        )]);
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(!status
+            .failures
+            .contains(&Failure::GenesisCloseMethodsInvalid));
+    }
+
+    #[test]
+    fn op_features() {
+        let mut chain = TestChain::regtest();
+        let features = OpFeatures::from_bits(0x0001);
+        let (consignment, ..) = spending_contract(&mut chain, SchemaFlags::RESERVED_TYPES, [(
+            schema::MetaType::OP_FEATURES,
+            features.to_meta(),
+        )]);
+        let genesis_id = consignment.genesis.id();
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(
+            status
+                .failures
+                .contains(&Failure::OpFeaturesUnsupported(genesis_id, features)),
+            "{status}"
+        );
+
+        let hooks = ValidationHooks {
+            supported_features: Some(features),
+            ..default!()
+        };
+        let status = Validator::validate_with_hooks(&consignment, &chain, hooks, chain.testnet());
+        assert!(
+            !status
+                .failures
+                .iter()
+                .any(|failure| matches!(failure, Failure::OpFeaturesUnsupported(..))),
+            "{status}"
+        );
+    }
+
+    #[test]
+    fn genesis_template() {
+        let mut chain = TestChain::regtest();
+        let declared = GenesisTemplateId::from([1; 32]);
+        let (consignment, ..) = spending_contract(&mut chain, SchemaFlags::RESERVED_TYPES, [(
+            schema::MetaType::GENESIS_TEMPLATE,
+            template_id_meta(declared),
+        )]);
+        let actual = consignment.genesis.template_id();
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(status
+            .failures
+            .contains(&Failure::GenesisTemplateMismatch(declared, actual)));
+    }
+
+    #[test]
+    fn contract_terms() {
+        let mut chain = TestChain::regtest();
+        let mut schema = Schema::strict_dumb();
+        schema.flags = SchemaFlags::RESERVED_TYPES;
+        let terms = DataState::from(SmallBlob::try_from(b"terms".to_vec()).unwrap());
+        let mut genesis = Genesis::strict_dumb();
+        genesis
+            .globals
+            .add_state(GlobalStateType::TERMS, terms.clone())
+            .unwrap();
+        genesis
+            .globals
+            .add_state(GlobalStateType::TERMS, terms)
+            .unwrap();
+        let (consignment, ..) = schema_contract(&mut chain, schema, genesis);
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(status
+            .failures
+            .contains(&Failure::GenesisTermsInvalid(TermsError::Duplicated)));
+    }
+
+    #[test]
+    fn close_methods() {
+        let mut chain = TestChain::regtest();
+        let methods = |methods: u8| MetaValue::from(Confined::try_from(vec![methods]).unwrap());
+
+        let (consignment, ..) = spending_contract(&mut chain, SchemaFlags::RESERVED_TYPES, [(
+            schema::MetaType::CLOSE_METHODS,
+            methods(CloseMethodSet::OPRET),
+        )]);
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(failures_except_meta(&status).is_empty(), "{status}");
+
+        let (consignment, ..) = spending_contract(&mut chain, SchemaFlags::RESERVED_TYPES, [(
+            schema::MetaType::CLOSE_METHODS,
+            methods(CloseMethodSet::TAPRET),
+        )]);
+        let bundle_id = *consignment.anchors.keys().next().unwrap();
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(
+            status
+                .failures
+                .contains(&Failure::CloseMethodForbidden(bundle_id, CloseMethod::OpretFirst)),
+            "{status}"
+        );
+    }
+
+    #[test]
+    fn chain_net() {
+        let mut chain = TestChain::regtest();
+        let (consignment, ..) = spending_contract(&mut chain, SchemaFlags::RESERVED_TYPES, [(
+            schema::MetaType::CHAIN_SALT,
+            ChainNet::BitcoinRegtest.to_meta(),
+        )]);
+        let validate = |chain_net| {
+            let hooks = ValidationHooks {
+                chain_net: Some(chain_net),
+                ..default!()
+            };
+            Validator::validate_with_hooks(&consignment, &chain, hooks, chain.testnet())
+        };
+        let status = validate(ChainNet::BitcoinRegtest);
+        assert!(failures_except_meta(&status).is_empty(), "{status}");
+        let status = validate(ChainNet::BitcoinSignet);
+        assert!(status.failures.contains(&Failure::ChainNetMismatch {
+            expected: ChainNet::BitcoinSignet,
+            actual: ChainNet::BitcoinRegtest,
+        }));
+
+        // Contracts with legacy ids are accepted on any network
+        let (consignment, ..) = spending_contract(&mut chain, SchemaFlags::RESERVED_TYPES, []);
+        let hooks = ValidationHooks {
+            chain_net: Some(ChainNet::BitcoinSignet),
+            ..default!()
+        };
+        let status = Validator::validate_with_hooks(&consignment, &chain, hooks, chain.testnet());
+        assert!(failures_except_meta(&status).is_empty(), "{status}");
+        assert!(status
+            .warnings
+            .contains(&Warning::ContractIdUnsalted(consignment.genesis.contract_id())));
+    }
+
+    #[test]
+    fn dependencies() {
+        let mut chain = TestChain::regtest();
+        let dependency = ContractId::from([0xA1; 32]);
+        let (consignment, ..) = spending_contract(&mut chain, SchemaFlags::RESERVED_TYPES, [(
+            schema::MetaType::DEPENDENCIES,
+            dependencies_meta(&bset! { dependency }).unwrap(),
+        )]);
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(status
+            .failures
+            .contains(&Failure::DependencyUnvalidated(dependency)));
+
+        let validated = bset! { dependency };
+        let hooks = ValidationHooks {
+            validated_contracts: Some(&validated),
+            ..default!()
+        };
+        let status = Validator::validate_with_hooks(&consignment, &chain, hooks, chain.testnet());
+        assert!(failures_except_meta(&status).is_empty(), "{status}");
+    }
+
+    /// Resolver which is unaware of the chain tip.
+    struct Tipless<'chain>(&'chain TestChain);
+
+    impl<'chain> ResolveWitness for Tipless<'chain> {
+        fn resolve_pub_witness(
+            &self,
+            witness_id: XWitnessId,
+        ) -> Result<XWitnessTx, WitnessResolverError> {
+            self.0.resolve_pub_witness(witness_id)
+        }
+
+        fn resolve_witness_ord(
+            &self,
+            witness_id: XWitnessId,
+        ) -> Result<WitnessOrd, WitnessResolverError> {
+            self.0.resolve_witness_ord(witness_id)
+        }
+    }
+
+    /// Contract with a chain of state extensions performing the schema
+    /// upgrades, each redeeming the valency of the previous one, and a state
+    /// transition spending the state assigned by the last extension. The
+    /// transition witness is not mined.
+    fn upgraded_contract(
+        chain: &mut TestChain,
+        upgrades: &[SchemaUpgrade],
+    ) -> (TestConsignment, OpId) {
+        let valency = ValencyType::with(1);
+        let extension_ty = ExtensionType::with(1);
+        let tag = AssetTag::strict_dumb();
+        let mut schema = Schema::strict_dumb();
+        schema.flags = SchemaFlags::RESERVED_TYPES;
+        schema.owned_types =
+            Confined::from_iter_unsafe([(TY, OwnedStateSchema::Fungible(default!()))]);
+        schema.valency_types = Confined::from_iter_unsafe([valency]);
+        schema.genesis.valencies = Confined::from_iter_unsafe([valency]);
+        schema.extensions = Confined::from_iter_unsafe([(extension_ty, ExtensionSchema {
+            redeems: Confined::from_iter_unsafe([valency]),
+            valencies: Confined::from_iter_unsafe([valency]),
+            assignments: Confined::from_iter_unsafe([(TY, schema::Occurrences::NoneOrOnce)]),
+            ..default!()
+        })]);
+        schema.transitions = Confined::from_iter_unsafe([(TRANSITION_TY, TransitionSchema {
+            inputs: Confined::from_iter_unsafe([(TY, schema::Occurrences::Once)]),
+            assignments: Confined::from_iter_unsafe([(TY, schema::Occurrences::Once)]),
+            ..default!()
+        })]);
+
+        let mut genesis = Genesis::strict_dumb();
+        genesis.schema_id = schema.schema_id();
+        genesis.testnet = chain.testnet();
+        genesis.asset_tags = AssetTags::from_inner(Confined::from_iter_unsafe([(TY, tag)]));
+        genesis.valencies = Valencies::from_inner(Confined::from_iter_unsafe([valency]));
+        let contract_id = genesis.contract_id();
+        let mut prev_id = genesis.id();
+        let mut consignment = TestConsignment::new(schema, TypeSystem::default(), genesis);
+
+        let seal = chain.genesis_seal();
+        for (no, upgrade) in upgrades.iter().enumerate() {
+            let mut extension = Extension::strict_dumb();
+            extension.contract_id = contract_id;
+            extension.extension_type = extension_ty;
+            extension
+                .metadata
+                .add_value(schema::MetaType::SCHEMA_UPGRADE, upgrade.to_meta())
+                .unwrap();
+            extension.redeemed.insert(valency, prev_id).unwrap();
+            extension.valencies = Valencies::from_inner(Confined::from_iter_unsafe([valency]));
+            if no + 1 == upgrades.len() {
+                let allocation =
+                    Assign::revealed(seal, RevealedValue::new_random_blinding(100, tag));
+                extension.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+                    TY,
+                    TypedAssigns::Fungible(Confined::from_iter_unsafe([allocation])),
+                )]));
+            }
+            prev_id = extension.id();
+            consignment.extensions.insert(prev_id, extension);
+        }
+
+        let output =
+            Assign::revealed(chain.graph_seal(1), RevealedValue::new_random_blinding(100, tag));
+        let mut transition = Transition::strict_dumb();
+        transition.contract_id = contract_id;
+        transition.transition_type = TRANSITION_TY;
+        transition.inputs =
+            Inputs::try_from_inputs([Input::with(Opout::new(prev_id, TY, 0))]).unwrap();
+        transition.assignments = Assignments::from_inner(Confined::from_iter_unsafe([(
+            TY,
+            TypedAssigns::Fungible(Confined::from_iter_unsafe([output.clone()])),
+        )]));
+        let opid = transition.id();
+        let bundle = TransitionBundle {
+            close_method: CloseMethod::OpretFirst,
+            input_map: InputMap::with(Vin::from(0u32), opid),
+            known_transitions: Confined::with((opid, transition)),
+        };
+        let spent = *seal.to_outpoint().as_reduced_unsafe();
+        let (witness_id, anchor) = chain.commit(contract_id, bundle.bundle_id(), [spent], 1);
+        let bundle_id = consignment.add_bundle(bundle, witness_id, anchor);
+        consignment
+            .terminals
+            .push((bundle_id, output.to_confidential_seal()));
+        (consignment, opid)
+    }
+
+    #[test]
+    fn schema_upgrades() {
+        let mut chain = TestChain::regtest();
+        // Successor schema doesn't define the transition type, so the transition is
+        // valid only under the contract schema
+        let successor = Schema::strict_dumb();
+        let successor_id = successor.schema_id();
+        let upgrade = SchemaUpgrade::new(successor_id, 10);
+        let (mut consignment, opid) = upgraded_contract(&mut chain, &[upgrade]);
+        let transition = consignment.operation(opid).unwrap();
+        let at = |height| Some(WitnessOrd::OnChain(WitnessPos::new(height, 1231006505).unwrap()));
+
+        let validator = Validator::init(&consignment, &chain, chain.testnet());
+        assert_eq!(
+            validator.op_schemata(&transition, Some(WitnessOrd::OffChain)),
+            Err(Failure::SchemaUpgradeUnavailable(opid, successor_id))
+        );
+        assert_eq!(
+            validator.op_schemata(&transition, None),
+            Err(Failure::SchemaUpgradeUndetermined(opid))
+        );
+
+        consignment.successors.insert(successor_id, successor);
+        let transition = consignment.operation(opid).unwrap();
+        let validator = Validator::init(&consignment, &chain, chain.testnet());
+        let schema_ids = |witness| {
+            validator
+                .op_schemata(&transition, witness)
+                .unwrap()
+                .into_iter()
+                .map(Schema::schema_id)
+                .collect::<Vec<_>>()
+        };
+        let contract_schema_id = consignment.schema.schema_id();
+        assert_eq!(schema_ids(at(9)), vec![contract_schema_id]);
+        assert_eq!(schema_ids(at(10)), vec![successor_id]);
+        assert_eq!(schema_ids(Some(WitnessOrd::OffChain)), vec![contract_schema_id, successor_id]);
+
+        // Validity of the unmined transition doesn't depend on the chain tip known to
+        // the resolver: it must be valid under the successor schema as well
+        for status in [
+            Validator::validate(&consignment, &chain, chain.testnet()),
+            Validator::validate(&consignment, &Tipless(&chain), chain.testnet()),
+        ] {
+            assert!(
+                status
+                    .failures
+                    .contains(&Failure::SchemaUnknownTransitionType(opid, TRANSITION_TY)),
+                "{status}"
+            );
+            assert!(!status
+                .failures
+                .contains(&Failure::SchemaUpgradeUndetermined(opid)));
+        }
+
+        // Once mined before the activation height, the transition is valid
+        let witness_id = consignment.op_witness_id(opid).unwrap();
+        chain.mine(witness_id);
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(failures_except_meta(&status).is_empty(), "{status}");
+
+        // Upgrades in the history activating at the same height conflict
+        let other = SchemaUpgrade::new(SchemaId::from([0xA1; 32]), 10);
+        let (consignment, opid) = upgraded_contract(&mut chain, &[upgrade, other]);
+        let validator = Validator::init(&consignment, &chain, chain.testnet());
+        let transition = consignment.operation(opid).unwrap();
+        assert_eq!(
+            validator.op_schemata(&transition, Some(WitnessOrd::OffChain)),
+            Err(Failure::SchemaUpgradeConflict(opid))
+        );
+
+        // Only state extensions may upgrade the contract
+        let (consignment, ..) = spending_contract(&mut chain, SchemaFlags::RESERVED_TYPES, [(
+            schema::MetaType::SCHEMA_UPGRADE,
+            upgrade.to_meta(),
+        )]);
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(status
+            .failures
+            .contains(&Failure::SchemaUpgradeInvalid(consignment.genesis.id())));
+    }
+
+    #[test]
+    fn cancellation() {
+        let mut chain = TestChain::regtest();
+        let (consignment, ..) = spending_contract(&mut chain, SchemaFlags::default(), []);
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let hooks = ValidationHooks {
+            cancel: Some(cancel),
+            ..default!()
+        };
+        let status = Validator::validate_with_hooks(&consignment, &chain, hooks, chain.testnet());
+        assert!(status.failures.iter().any(|failure| matches!(
+            failure,
+            Failure::ValidationInterrupted(Interruption::Cancelled, ..)
+        )));
+    }
+
+    #[test]
+    fn valency_limits() {
+        let chain = TestChain::regtest();
+        let valency = ValencyType::with(1);
+        let extension_schema = ExtensionSchema {
+            redeems: Confined::from_iter_unsafe([valency]),
+            ..default!()
+        };
+        let mut schema = Schema::strict_dumb();
+        schema.flags = SchemaFlags::RESERVED_TYPES;
+        schema.valency_types = Confined::from_iter_unsafe([valency]);
+        schema.genesis.valencies = Confined::from_iter_unsafe([valency]);
+        schema.extensions = Confined::try_from_iter([
+            (ExtensionType::with(1), extension_schema.clone()),
+            (ExtensionType::with(2), extension_schema),
+        ])
+        .unwrap();
+
+        let limit = ValencyLimit {
+            redemptions: Redemptions::ONCE,
+            expiry: None,
+        };
+        let mut genesis = Genesis::strict_dumb();
+        genesis.schema_id = schema.schema_id();
+        genesis.testnet = chain.testnet();
+        genesis
+            .metadata
+            .add_value(
+                schema::MetaType::VALENCY_LIMITS,
+                valency_limits_meta(&bmap! { valency => limit }).unwrap(),
+            )
+            .unwrap();
+        genesis.valencies = Valencies::from_inner(Confined::from_iter_unsafe([valency]));
+        let genesis_id = genesis.id();
+        let contract_id = genesis.contract_id();
+        let mut consignment = TestConsignment::new(schema, TypeSystem::default(), genesis);
+
+        // Both extensions are known to the validator, so the second redemption is seen
+        let mut extensions = vec![];
+        for ty in [1, 2] {
+            let mut extension = Extension::strict_dumb();
+            extension.contract_id = contract_id;
+            extension.extension_type = ExtensionType::with(ty);
+            extension.redeemed.insert(valency, genesis_id).unwrap();
+            consignment
+                .extensions
+                .insert(extension.id(), extension.clone());
+            extensions.push(extension);
+        }
+        // Extensions without descendant transitions aren't reachable from the
+        // consignment terminals, so their state is validated directly
+        let validator = Validator::init(&consignment, &chain, chain.testnet());
+        let mut status = Status::new();
+        for extension in &extensions {
+            status += validator.validate_op_state(OpRef::Extension(extension));
+        }
+        assert!(
+            status.failures.iter().any(|failure| matches!(
+                failure,
+                Failure::ValencyLimitViolated(_, ValencyError::RedemptionsExceeded {
+                    prev_id,
+                    max: 1,
+                    ..
+                }) if *prev_id == genesis_id
+            )),
+            "{status}"
+        );
+    }
+
+    #[test]
+    fn oracle_state() {
+        let mut chain = TestChain::regtest();
+        let feed = OracleFeed {
+            attestations: GlobalStateType::with(2000),
+            oracle_set: GlobalStateType::with(2001),
+        };
+        let mut schema = Schema::strict_dumb();
+        schema.flags = SchemaFlags::RESERVED_TYPES;
+        let mut genesis = Genesis::strict_dumb();
+        genesis
+            .metadata
+            .add_value(schema::MetaType::ORACLE_FEEDS, oracle_feeds_meta(&bset! { feed }).unwrap())
+            .unwrap();
+        genesis
+            .globals
+            .add_state(feed.oracle_set, DataState::from(SmallBlob::try_from(vec![0xFF]).unwrap()))
+            .unwrap();
+        let (consignment, ..) = schema_contract(&mut chain, schema, genesis);
+        let genesis_id = consignment.genesis.id();
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(
+            status.failures.iter().any(|failure| matches!(
+                failure,
+                Failure::OracleStateInvalid(opid, _) if *opid == genesis_id
+            )),
+            "{status}"
+        );
+    }
+
+    #[test]
+    fn presentation() {
+        let mut chain = TestChain::regtest();
+        let [ticker, name, precision] = [2000, 2001, 2002].map(GlobalStateType::with);
+        let data = |bytes: &[u8]| DataState::from(SmallBlob::try_from(bytes.to_vec()).unwrap());
+        let mut genesis = Genesis::strict_dumb();
+        genesis.globals.add_state(ticker, data(b"\x03btc")).unwrap();
+        genesis.globals.add_state(name, data(b"\x00")).unwrap();
+        genesis.globals.add_state(precision, data(&[19])).unwrap();
+        let (consignment, ..) = schema_contract(&mut chain, Schema::strict_dumb(), genesis);
+        let genesis_id = consignment.genesis.id();
+
+        let rules = PresentationRules {
+            ticker: Some(ticker),
+            name: Some(name),
+            precision: Some(precision),
+            ..PresentationRules::with(consignment.genesis.schema_id)
+        };
+        let hooks = ValidationHooks {
+            presentation: Some(&rules),
+            ..default!()
+        };
+        let status = Validator::validate_with_hooks(&consignment, &chain, hooks, chain.testnet());
+        assert!(status
+            .failures
+            .contains(&Failure::PresentationInvalidTicker(genesis_id, ticker)));
+        assert!(status
+            .failures
+            .contains(&Failure::PresentationInvalidName(genesis_id, name)));
+        assert!(status
+            .failures
+            .contains(&Failure::PresentationInvalidPrecision(genesis_id, precision)));
+    }
+
+    #[test]
+    fn schema_names() {
+        let mut chain = TestChain::regtest();
+        let mut schema = Schema::strict_dumb();
+        schema
+            .names
+            .name_transition("issue", TransitionType::with(2))
+            .unwrap();
+        let (consignment, ..) = schema_contract(&mut chain, schema, Genesis::strict_dumb());
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert!(status
+            .failures
+            .contains(&Failure::SchemaNameUnknownType(s!("issue"))));
+    }
+
+    /// Policy rejecting witnesses heavier than the given weight.
+    struct MaxWeight(u32);
+
+    impl LocalPolicy for MaxWeight {
+        fn check(&self, _: ContractId, _: OpRef) -> Result<(), String> { Ok(()) }
+
+        fn check_witness(&self, _: ContractId, metrics: WitnessMetrics) -> Result<(), String> {
+            if metrics.weight > self.0 {
+                return Err(format!("{metrics} exceeds {} weight units", self.0));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn witness_metrics() {
+        let mut chain = TestChain::regtest();
+        let (consignment, opid, ..) =
+            schema_contract(&mut chain, Schema::strict_dumb(), Genesis::strict_dumb());
+        let witness_id = consignment.op_witness_id(opid).unwrap();
+        let witness_tx = chain.resolve_pub_witness(witness_id).unwrap();
+        let metrics = WitnessMetrics::with(witness_id, witness_tx.as_reduced_unsafe());
+        // Test chain witnesses have no segwit data
+        assert_eq!(metrics.weight, metrics.size * 4);
+        assert_eq!(metrics.vsize(), metrics.size);
+
+        let status = Validator::validate(&consignment, &chain, chain.testnet());
+        assert_eq!(status.witness_metrics, vec![metrics]);
+        assert!(status.policy_violations.is_empty());
+
+        let policy = MaxWeight(metrics.weight);
+        let hooks = ValidationHooks {
+            policies: vec![&policy],
+            ..default!()
+        };
+        let status = Validator::validate_with_hooks(&consignment, &chain, hooks, chain.testnet());
+        assert!(status.policy_violations.is_empty(), "{status}");
+
+        let policy = MaxWeight(metrics.weight - 1);
+        let hooks = ValidationHooks {
+            policies: vec![&policy],
+            ..default!()
+        };
+        let status = Validator::validate_with_hooks(&consignment, &chain, hooks, chain.testnet());
+        assert_eq!(status.witness_metrics, vec![metrics]);
+        assert_eq!(status.policy_violations, vec![PolicyViolation {
+            opid,
+            reason: format!("{metrics} exceeds {} weight units", metrics.weight - 1),
+        }]);
+        assert_eq!(status.validity(), Validity::LocallyRejected);
+    }
+}
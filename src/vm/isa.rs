@@ -25,12 +25,12 @@ use std::ops::RangeInclusive;
 
 use aluvm::isa;
 use aluvm::isa::{Bytecode, BytecodeError, ExecStep, InstructionSet};
-use aluvm::library::{CodeEofError, IsaSeg, LibSite, Read, Write};
+use aluvm::library::{CodeEofError, IsaSeg, Lib, LibSite, Read, Write};
 use aluvm::reg::{CoreRegs, Reg};
 
 use super::{ContractOp, TimechainOp};
 use crate::validation::OpInfo;
-use crate::vm::opcodes::{INSTR_RGBISA_FROM, INSTR_RGBISA_TO};
+use crate::vm::opcodes::{INSTR_RGBISA_FROM, INSTR_RGBISA_TO, INSTR_RGBX_FROM, INSTR_RGBX_TO};
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
 #[display(inner)]
@@ -45,10 +45,25 @@ pub enum RgbIsa {
     Fail(u8),
 }
 
+impl RgbIsa {
+    /// Detects whether the library code reads the position of the witness of
+    /// the validated operation (see [`ContractOp::reads_witness_position`]).
+    /// Libraries which can't be disassembled are assumed to read it.
+    pub fn reads_witness_position(lib: &Lib) -> bool {
+        let Ok(code) = lib.disassemble::<isa::Instr<RgbIsa>>() else {
+            return true;
+        };
+        code.iter().any(|instr| match instr {
+            isa::Instr::ExtensionCodes(RgbIsa::Contract(op)) => op.reads_witness_position(),
+            _ => false,
+        })
+    }
+}
+
 impl InstructionSet for RgbIsa {
     type Context<'ctx> = OpInfo<'ctx>;
 
-    fn isa_ids() -> IsaSeg { IsaSeg::with("RGB") }
+    fn isa_ids() -> IsaSeg { IsaSeg::with("RGB RGBX") }
 
     fn src_regs(&self) -> BTreeSet<Reg> {
         match self {
@@ -116,6 +131,7 @@ impl Bytecode for RgbIsa {
             instr if ContractOp::instr_range().contains(&instr) => {
                 RgbIsa::Contract(ContractOp::decode(reader)?)
             }
+            INSTR_RGBX_FROM..=INSTR_RGBX_TO => RgbIsa::Contract(ContractOp::decode(reader)?),
             instr if TimechainOp::instr_range().contains(&instr) => {
                 RgbIsa::Timechain(TimechainOp::decode(reader)?)
             }
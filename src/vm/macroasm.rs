@@ -39,8 +39,14 @@ macro_rules! isa_instr {
     (cng $t:ident,a8[$a_idx:literal]) => {{ RgbIsa::Contract(ContractOp::CnG($t, Reg32::from(u5::with($a_idx)))) }};
     (cnc $t:ident,a16[$a_idx:literal]) => {{ RgbIsa::Contract(ContractOp::CnC($t, Reg32::from(u5::with($a_idx)))) }};
     (ldm $t:ident,s16[$s_idx:literal]) => {{ RgbIsa::Contract(ContractOp::LdM($t, RegS::from($s_idx))) }};
+    (cnr a16[$a_idx:literal]) => {{ RgbIsa::Contract(ContractOp::CnR(Reg32::from(u5::with($a_idx)))) }};
+    (rdv $t:ident) => {{ RgbIsa::Contract(ContractOp::Rdv($t)) }};
+    (wth a32[$a_idx:literal]) => {{ RgbIsa::Contract(ContractOp::WtH(Reg32::from(u5::with($a_idx)))) }};
+    (wtt a64[$a_idx:literal]) => {{ RgbIsa::Contract(ContractOp::WtT(Reg32::from(u5::with($a_idx)))) }};
     (ldg $t:ident,a8[$a_idx:literal],s16[$s_idx:literal]) => {{ RgbIsa::Contract(ContractOp::LdG($t, Reg16::from(u4::with($a_idx)), RegS::from($s_idx))) }};
     (ldp $t:ident,a16[$a_idx:literal],s16[$s_idx:literal]) => {{ RgbIsa::Contract(ContractOp::LdP($t, Reg16::from(u4::with($a_idx)), RegS::from($s_idx))) }};
     (lds $t:ident,a16[$a_idx:literal],s16[$s_idx:literal]) => {{ RgbIsa::Contract(ContractOp::LdS($t, Reg16::from(u4::with($a_idx)), RegS::from($s_idx))) }};
+    (ora $t:ident,s16[$set_idx:literal],s16[$s_idx:literal]) => {{ RgbIsa::Contract(ContractOp::OrA($t, RegS::from($set_idx), RegS::from($s_idx))) }};
+    (ors $t:ident,s16[$set_idx:literal],s16[$s_idx:literal]) => {{ RgbIsa::Contract(ContractOp::OrS($t, RegS::from($set_idx), RegS::from($s_idx))) }};
     ($op:ident $($tt:tt)+) => {{ compile_error!(concat!("unknown RGB assembly opcode `", stringify!($op), "`")) }};
 }
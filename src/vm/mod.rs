@@ -28,6 +28,7 @@ pub mod opcodes;
 mod isa;
 mod op_contract;
 mod op_timechain;
+mod rules;
 #[macro_use]
 mod macroasm;
 
@@ -35,3 +36,4 @@ pub use aluvm::aluasm_isa;
 pub use isa::RgbIsa;
 pub use op_contract::ContractOp;
 pub use op_timechain::TimechainOp;
+pub use rules::{compile_rules, RuleError, RuleSet};
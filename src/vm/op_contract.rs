@@ -35,10 +35,13 @@ use commit_verify::CommitVerify;
 use super::opcodes::*;
 use crate::validation::OpInfo;
 use crate::{
-    Assign, AssignmentType, BlindingFactor, GlobalStateType, MetaType, PedersenCommitment,
-    RevealedValue, TypedAssigns,
+    Assign, AssignmentType, BlindingFactor, GlobalStateType, MetaType, OracleAttestation,
+    OracleSet, PedersenCommitment, RevealedValue, TypedAssigns, ValencyType, WitnessOrd,
 };
 
+/// Contract instructions of the `RGB` ISA extension, together with the contract
+/// introspection instructions `cnr`, `rdv`, `wth`, `wtt`, `ora` and `ors` of
+/// the `RGBX` ISA extension (see [`super::opcodes`] for their codes).
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
 pub enum ContractOp {
     /// Counts number of inputs (previous state entries) of the provided type
@@ -119,6 +122,66 @@ pub enum ContractOp {
     #[display("ldm     {0},{1}")]
     LdM(MetaType, RegS),
 
+    /// Counts number of valencies redeemed by the current operation and puts
+    /// the number to the destination `a16` register. For operations other
+    /// than state extensions the number is always zero.
+    #[display("cnr     a16{0}")]
+    CnR(Reg32),
+
+    /// Verifies that the current operation redeems valency of the provided
+    /// type.
+    ///
+    /// If verification succeeds, doesn't change `st0` value; otherwise sets it
+    /// to `false` and stops execution.
+    #[display("rdv     {0}")]
+    Rdv(ValencyType),
+
+    /// Puts block height of the current operation witness into the destination
+    /// `a32` register. If the witness is not yet mined the register is set to
+    /// `None`.
+    ///
+    /// Fails and sets `st0` to `false` if the operation has no witness
+    /// (genesis and state extensions) or the witness position is not known to
+    /// the validator.
+    ///
+    /// Validation results of the contracts using the instruction are never
+    /// persisted by the validator.
+    #[display("wth     a32{0}")]
+    WtH(Reg32),
+
+    /// Puts mining timestamp of the current operation witness block into the
+    /// destination `a64` register. If the witness is not yet mined the
+    /// register is set to `None`.
+    ///
+    /// Fails and sets `st0` to `false` if the operation has no witness
+    /// (genesis and state extensions) or the witness position is not known to
+    /// the validator.
+    ///
+    /// Validation results of the contracts using the instruction are never
+    /// persisted by the validator.
+    #[display("wtt     a64{0}")]
+    WtT(Reg32),
+
+    /// Verifies that the oracle attestation of the global state type from the
+    /// first argument, read from the `s16` register in the third argument, is
+    /// signed by a key from the oracle set read from the `s16` register in
+    /// the second argument (see [`crate::OracleFeed`]).
+    ///
+    /// If verification succeeds, doesn't change `st0` value; otherwise sets it
+    /// to `false` and stops execution.
+    #[display("ora     {0},{1},{2}")]
+    OrA(GlobalStateType, RegS, RegS),
+
+    /// Verifies that the oracle set of the global state type from the first
+    /// argument, read from the `s16` register in the third argument, is a
+    /// rotation signed by a key from the oracle set read from the `s16`
+    /// register in the second argument (see [`crate::OracleFeed`]).
+    ///
+    /// If verification succeeds, doesn't change `st0` value; otherwise sets it
+    /// to `false` and stops execution.
+    #[display("ors     {0},{1},{2}")]
+    OrS(GlobalStateType, RegS, RegS),
+
     /// Verify sum of pedersen commitments from inputs and outputs.
     ///
     /// The only argument specifies owned state type for the sum operation. If
@@ -164,10 +227,19 @@ pub enum ContractOp {
     Fail(u8),
 }
 
+impl ContractOp {
+    /// Detects whether the instruction reads the position of the witness of
+    /// the validated operation, which is provided by the witness resolver and
+    /// may change with a chain reorg.
+    pub fn reads_witness_position(&self) -> bool {
+        matches!(self, ContractOp::WtH(_) | ContractOp::WtT(_))
+    }
+}
+
 impl InstructionSet for ContractOp {
     type Context<'ctx> = OpInfo<'ctx>;
 
-    fn isa_ids() -> IsaSeg { IsaSeg::with("RGB") }
+    fn isa_ids() -> IsaSeg { IsaSeg::with("RGB RGBX") }
 
     fn src_regs(&self) -> BTreeSet<Reg> {
         match self {
@@ -176,11 +248,18 @@ impl InstructionSet for ContractOp {
             ContractOp::LdS(_, reg, _) => bset![Reg::A(RegA::A16, (*reg).into())],
             ContractOp::LdG(_, reg, _) => bset![Reg::A(RegA::A8, (*reg).into())],
             ContractOp::LdC(_, reg, _) => bset![Reg::A(RegA::A32, (*reg).into())],
+            ContractOp::OrA(_, set, item) | ContractOp::OrS(_, set, item) => {
+                bset![Reg::S(*set), Reg::S(*item)]
+            }
 
             ContractOp::CnP(_, _) |
             ContractOp::CnS(_, _) |
             ContractOp::CnG(_, _) |
             ContractOp::CnC(_, _) |
+            ContractOp::CnR(_) |
+            ContractOp::Rdv(_) |
+            ContractOp::WtH(_) |
+            ContractOp::WtT(_) |
             ContractOp::LdM(_, _) => bset![],
             ContractOp::Pcvs(_) => bset![],
             ContractOp::Pcas(_) | ContractOp::Pcps(_) => bset![Reg::A(RegA::A64, Reg32::Reg0)],
@@ -193,7 +272,10 @@ impl InstructionSet for ContractOp {
             ContractOp::CnG(_, reg) => {
                 bset![Reg::A(RegA::A8, *reg)]
             }
-            ContractOp::CnP(_, reg) | ContractOp::CnS(_, reg) | ContractOp::CnC(_, reg) => {
+            ContractOp::CnP(_, reg) |
+            ContractOp::CnS(_, reg) |
+            ContractOp::CnC(_, reg) |
+            ContractOp::CnR(reg) => {
                 bset![Reg::A(RegA::A16, *reg)]
             }
             ContractOp::LdF(_, _, reg) => {
@@ -209,6 +291,13 @@ impl InstructionSet for ContractOp {
             ContractOp::Pcvs(_) | ContractOp::Pcas(_) | ContractOp::Pcps(_) => {
                 bset![]
             }
+            ContractOp::WtH(reg) => {
+                bset![Reg::A(RegA::A32, *reg)]
+            }
+            ContractOp::WtT(reg) => {
+                bset![Reg::A(RegA::A64, *reg)]
+            }
+            ContractOp::Rdv(_) | ContractOp::OrA(..) | ContractOp::OrS(..) => bset![],
             ContractOp::Fail(_) => bset![],
         }
     }
@@ -218,7 +307,11 @@ impl InstructionSet for ContractOp {
             ContractOp::CnP(_, _) |
             ContractOp::CnS(_, _) |
             ContractOp::CnG(_, _) |
-            ContractOp::CnC(_, _) => 2,
+            ContractOp::CnC(_, _) |
+            ContractOp::CnR(_) |
+            ContractOp::Rdv(_) |
+            ContractOp::WtH(_) |
+            ContractOp::WtT(_) => 2,
             ContractOp::LdP(_, _, _) |
             ContractOp::LdS(_, _, _) |
             ContractOp::LdF(_, _, _) |
@@ -227,6 +320,7 @@ impl InstructionSet for ContractOp {
             ContractOp::LdM(_, _) => 6,
             ContractOp::Pcvs(_) => 1024,
             ContractOp::Pcas(_) | ContractOp::Pcps(_) => 512,
+            ContractOp::OrA(..) | ContractOp::OrS(..) => 256,
             ContractOp::Fail(_) => u64::MAX,
         }
     }
@@ -364,6 +458,68 @@ impl InstructionSet for ContractOp {
                 };
                 regs.set_s(*reg, Some(meta.to_inner()));
             }
+            ContractOp::CnR(reg) => {
+                regs.set_n(RegA::A16, *reg, Some(context.redeemed.len() as u16));
+            }
+            ContractOp::Rdv(valency) => {
+                if !context.redeemed.contains(valency) {
+                    fail!()
+                }
+            }
+            ContractOp::WtH(reg) => {
+                let Some(witness) = context.witness else {
+                    fail!()
+                };
+                let height = match witness {
+                    WitnessOrd::OnChain(pos) => Some(pos.height().get()),
+                    WitnessOrd::OffChain => None,
+                };
+                regs.set_n(RegA::A32, *reg, height);
+            }
+            ContractOp::WtT(reg) => {
+                let Some(witness) = context.witness else {
+                    fail!()
+                };
+                // Witness positions can't have timestamps before the genesis
+                // block, so the value is always positive
+                let timestamp = witness.timestamp().map(|ts| ts as u64);
+                regs.set_n(RegA::A64, *reg, timestamp);
+            }
+            ContractOp::OrA(state_type, reg_set, reg_item) => {
+                let (Some(set), Some(item)) = (regs.get_s(*reg_set), regs.get_s(*reg_item)) else {
+                    fail!()
+                };
+                let Ok(set) = OracleSet::from_slice(set.as_ref(), *state_type) else {
+                    fail!()
+                };
+                let Ok(attestation) = OracleAttestation::from_slice(item.as_ref(), *state_type)
+                else {
+                    fail!()
+                };
+                if set
+                    .verify_attestation(&attestation, context.contract_id, *state_type)
+                    .is_err()
+                {
+                    fail!()
+                }
+            }
+            ContractOp::OrS(state_type, reg_set, reg_item) => {
+                let (Some(set), Some(item)) = (regs.get_s(*reg_set), regs.get_s(*reg_item)) else {
+                    fail!()
+                };
+                let (Ok(set), Ok(next)) = (
+                    OracleSet::from_slice(set.as_ref(), *state_type),
+                    OracleSet::from_slice(item.as_ref(), *state_type),
+                ) else {
+                    fail!()
+                };
+                if set
+                    .verify_rotation(&next, context.contract_id, *state_type)
+                    .is_err()
+                {
+                    fail!()
+                }
+            }
 
             ContractOp::Pcvs(state_type) => {
                 let inputs = load_inputs!(state_type);
@@ -445,6 +601,12 @@ impl Bytecode for ContractOp {
             ContractOp::LdF(_, _, _) => INSTR_LDF,
             ContractOp::LdC(_, _, _) => INSTR_LDC,
             ContractOp::LdM(_, _) => INSTR_LDM,
+            ContractOp::CnR(_) => INSTR_CNR,
+            ContractOp::Rdv(_) => INSTR_RDV,
+            ContractOp::WtH(_) => INSTR_WTH,
+            ContractOp::WtT(_) => INSTR_WTT,
+            ContractOp::OrA(..) => INSTR_ORA,
+            ContractOp::OrS(..) => INSTR_ORS,
 
             ContractOp::Pcvs(_) => INSTR_PCVS,
             ContractOp::Pcas(_) => INSTR_PCAS,
@@ -507,6 +669,21 @@ impl Bytecode for ContractOp {
                 writer.write_u4(reg)?;
                 writer.write_u4(u4::ZERO)?;
             }
+            ContractOp::CnR(reg) => {
+                writer.write_u5(reg)?;
+                writer.write_u3(u3::ZERO)?;
+            }
+            ContractOp::Rdv(valency) => writer.write_u16(*valency)?,
+            ContractOp::WtH(reg) | ContractOp::WtT(reg) => {
+                writer.write_u5(reg)?;
+                writer.write_u3(u3::ZERO)?;
+            }
+            ContractOp::OrA(state_type, reg_set, reg_item) |
+            ContractOp::OrS(state_type, reg_set, reg_item) => {
+                writer.write_u16(*state_type)?;
+                writer.write_u4(reg_set)?;
+                writer.write_u4(reg_item)?;
+            }
 
             ContractOp::Pcvs(state_type) => writer.write_u16(*state_type)?,
             ContractOp::Pcas(owned_type) => writer.write_u16(*owned_type)?,
@@ -574,6 +751,32 @@ impl Bytecode for ContractOp {
                 reader.read_u4()?; // Discard garbage bits
                 i
             }
+            INSTR_CNR => {
+                let i = Self::CnR(reader.read_u5()?.into());
+                reader.read_u3()?; // Discard garbage bits
+                i
+            }
+            INSTR_RDV => Self::Rdv(reader.read_u16()?.into()),
+            INSTR_WTH => {
+                let i = Self::WtH(reader.read_u5()?.into());
+                reader.read_u3()?; // Discard garbage bits
+                i
+            }
+            INSTR_WTT => {
+                let i = Self::WtT(reader.read_u5()?.into());
+                reader.read_u3()?; // Discard garbage bits
+                i
+            }
+            INSTR_ORA => Self::OrA(
+                reader.read_u16()?.into(),
+                reader.read_u4()?.into(),
+                reader.read_u4()?.into(),
+            ),
+            INSTR_ORS => Self::OrS(
+                reader.read_u16()?.into(),
+                reader.read_u4()?.into(),
+                reader.read_u4()?.into(),
+            ),
 
             INSTR_PCVS => Self::Pcvs(reader.read_u16()?.into()),
             INSTR_PCAS => Self::Pcas(reader.read_u16()?.into()),
@@ -586,13 +789,19 @@ impl Bytecode for ContractOp {
 
 #[cfg(test)]
 mod test {
+    use aluvm::data::ByteStr;
     use aluvm::isa::Instr;
     use aluvm::library::Lib;
+    use aluvm::Vm;
     use amplify::hex::ToHex;
-    use strict_encoding::StrictSerialize;
+    use bp::secp256k1::{Keypair, Secp256k1, SecretKey};
+    use strict_encoding::{StrictDumb, StrictSerialize};
 
     use super::*;
     use crate::vm::RgbIsa;
+    use crate::{
+        AssetTags, Assignments, ContractId, Genesis, OpRef, Operation, Valencies, WitnessPos,
+    };
 
     #[test]
     fn encoding() {
@@ -604,7 +813,7 @@ mod test {
 
         assert_eq!(
             alu_id.to_string(),
-            "alu:zI4PtPCR-Eut023!-Hqblf3X-N2J4GZb-TR2ZEsI-vQfhKOU#ruby-sherman-tonight"
+            "alu:joqHks1e-6Ur6TV8-MPWQbal-!rI9Psd-JJn1BX3-S5GnP0w#raymond-global-nancy"
         );
         assert_eq!(alu_lib.code.as_ref().to_hex(), "d0a00f");
         assert_eq!(
@@ -612,8 +821,147 @@ mod test {
                 .to_strict_serialized::<{ usize::MAX }>()
                 .unwrap()
                 .to_hex(),
-            "0303414c55084250444947455354035247420300d0a00f000000"
+            "0403414c550842504449474553540352474204524742580300d0a00f000000"
         );
         assert_eq!(alu_lib.disassemble::<Instr<RgbIsa>>().unwrap(), code);
     }
+
+    #[test]
+    fn witness_timestamp_encoding() {
+        let code = [Instr::ExtensionCodes(RgbIsa::Contract(ContractOp::WtT(Reg32::Reg0)))];
+        let alu_lib = Lib::assemble(&code).unwrap();
+        assert_eq!(alu_lib.code.as_ref().to_hex(), "e300");
+        assert_eq!(alu_lib.disassemble::<Instr<RgbIsa>>().unwrap(), code);
+    }
+
+    #[test]
+    fn witness_position() {
+        let genesis = Genesis::strict_dumb();
+        let op = OpRef::Genesis(&genesis);
+        let prev_state = Assignments::default();
+        let redeemed = Valencies::default();
+        let asset_tags = AssetTags::default();
+        let run = |instr: ContractOp, reg: RegA, witness: Option<WitnessOrd>| {
+            let info = OpInfo::with(
+                ContractId::from([0xA1; 32]),
+                genesis.id(),
+                &op,
+                &prev_state,
+                &redeemed,
+                &asset_tags,
+                witness,
+            );
+            let lib = Lib::assemble(&[Instr::ExtensionCodes(RgbIsa::Contract(instr))]).unwrap();
+            let mut vm = Vm::<Instr<RgbIsa>>::new();
+            let ok = vm.exec(LibSite::with(0, lib.id()), |_| Some(&lib), &info);
+            (ok, (*vm.registers.get_n(reg, Reg32::Reg0)).map(u64::from))
+        };
+
+        let mined = WitnessOrd::OnChain(WitnessPos::new(840_000, 1_713_571_767).unwrap());
+        let wth = ContractOp::WtH(Reg32::Reg0);
+        let wtt = ContractOp::WtT(Reg32::Reg0);
+        assert_eq!(run(wth, RegA::A32, Some(mined)), (true, Some(840_000)));
+        assert_eq!(run(wtt, RegA::A64, Some(mined)), (true, Some(1_713_571_767)));
+        assert_eq!(run(wth, RegA::A32, Some(WitnessOrd::OffChain)), (true, None));
+        assert_eq!(run(wtt, RegA::A64, Some(WitnessOrd::OffChain)), (true, None));
+        assert_eq!(run(wth, RegA::A32, None), (false, None));
+        assert_eq!(run(wtt, RegA::A64, None), (false, None));
+
+        let lib = Lib::assemble(&[Instr::ExtensionCodes(RgbIsa::Contract(wtt))]).unwrap();
+        assert!(wtt.reads_witness_position());
+        assert!(RgbIsa::reads_witness_position(&lib));
+        let pcvs = ContractOp::Pcvs(AssignmentType::from(4000));
+        let lib = Lib::assemble(&[Instr::ExtensionCodes(RgbIsa::Contract(pcvs))]).unwrap();
+        assert!(!RgbIsa::reads_witness_position(&lib));
+    }
+
+    #[test]
+    fn redeemed_valencies() {
+        let code = [
+            Instr::ExtensionCodes(RgbIsa::Contract(ContractOp::CnR(Reg32::Reg1))),
+            Instr::ExtensionCodes(RgbIsa::Contract(ContractOp::Rdv(ValencyType::with(7)))),
+        ];
+        let lib = Lib::assemble(&code).unwrap();
+        assert_eq!(lib.disassemble::<Instr<RgbIsa>>().unwrap(), code);
+
+        let genesis = Genesis::strict_dumb();
+        let op = OpRef::Genesis(&genesis);
+        let prev_state = Assignments::default();
+        let asset_tags = AssetTags::default();
+        let run = |instr: ContractOp, redeemed: &Valencies| {
+            let info = OpInfo::with(
+                ContractId::from([0xA1; 32]),
+                genesis.id(),
+                &op,
+                &prev_state,
+                redeemed,
+                &asset_tags,
+                None,
+            );
+            let lib = Lib::assemble(&[Instr::ExtensionCodes(RgbIsa::Contract(instr))]).unwrap();
+            let mut vm = Vm::<Instr<RgbIsa>>::new();
+            let ok = vm.exec(LibSite::with(0, lib.id()), |_| Some(&lib), &info);
+            (ok, (*vm.registers.get_n(RegA::A16, Reg32::Reg0)).map(u64::from))
+        };
+
+        let mut redeemed = Valencies::default();
+        let cnr = ContractOp::CnR(Reg32::Reg0);
+        assert_eq!(run(cnr, &redeemed), (true, Some(0)));
+        assert!(!run(ContractOp::Rdv(ValencyType::with(7)), &redeemed).0);
+
+        redeemed.push(ValencyType::with(7)).unwrap();
+        redeemed.push(ValencyType::with(8)).unwrap();
+        assert_eq!(run(cnr, &redeemed), (true, Some(2)));
+        assert!(run(ContractOp::Rdv(ValencyType::with(7)), &redeemed).0);
+        assert!(!run(ContractOp::Rdv(ValencyType::with(9)), &redeemed).0);
+    }
+
+    #[test]
+    fn oracle_verification() {
+        let secp = Secp256k1::new();
+        let keypair = |byte: u8| {
+            Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[byte; 32]).unwrap())
+        };
+        let (first, second) = (keypair(0x11), keypair(0x22));
+        let contract_id = ContractId::from([0xA1; 32]);
+        let (attestations, oracle_set) = (GlobalStateType::with(10), GlobalStateType::with(11));
+
+        let genesis = Genesis::strict_dumb();
+        let op = OpRef::Genesis(&genesis);
+        let prev_state = Assignments::default();
+        let redeemed = Valencies::default();
+        let asset_tags = AssetTags::default();
+        let info =
+            OpInfo::with(contract_id, genesis.id(), &op, &prev_state, &redeemed, &asset_tags, None);
+        let run = |instr: ContractOp, set: &OracleSet, item: Vec<u8>| {
+            let lib = Lib::assemble(&[Instr::ExtensionCodes(RgbIsa::Contract(instr))]).unwrap();
+            let mut vm = Vm::<Instr<RgbIsa>>::new();
+            vm.registers
+                .set_s(RegS::from(0), Some(ByteStr::with(set.to_data().as_slice())));
+            vm.registers.set_s(RegS::from(1), Some(ByteStr::with(item)));
+            vm.exec(LibSite::with(0, lib.id()), |_| Some(&lib), &info)
+        };
+
+        let initial = OracleSet::initial([first.x_only_public_key().0]).unwrap();
+        let keys = [second.x_only_public_key().0];
+        let rotated = OracleSet::rotate(keys, contract_id, oracle_set, &first).unwrap();
+        let ors = ContractOp::OrS(oracle_set, RegS::from(0), RegS::from(1));
+        let rotation = rotated.to_data().as_slice().to_vec();
+        assert!(run(ors, &initial, rotation.clone()));
+        assert!(!run(ors, &rotated, rotation));
+
+        let ora = ContractOp::OrA(attestations, RegS::from(0), RegS::from(1));
+        let attestation =
+            OracleAttestation::sign(b"price:42".to_vec(), contract_id, attestations, &second);
+        let data = attestation.to_data().unwrap().as_slice().to_vec();
+        assert!(run(ora, &rotated, data.clone()));
+        assert!(!run(ora, &initial, data));
+        let forged =
+            OracleAttestation::sign(b"price:42".to_vec(), contract_id, oracle_set, &second);
+        assert!(!run(ora, &rotated, forged.to_data().unwrap().as_slice().to_vec()));
+
+        let code = [Instr::ExtensionCodes(RgbIsa::Contract(ora))];
+        let alu_lib = Lib::assemble(&code).unwrap();
+        assert_eq!(alu_lib.disassemble::<Instr<RgbIsa>>().unwrap(), code);
+    }
 }
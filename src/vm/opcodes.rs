@@ -58,3 +58,19 @@ pub const INSTR_TIMECHAIN_TO: u8 = 0b11_011_111;
 // Reserved 0b11_011_101
 // Reserved 0b11_011_110
 // Reserved 0b11_011_111
+
+// CONTRACT INTROSPECTION (`RGBX` ISA extension):
+// The instructions are allocated outside of the contract range, such that
+// the VMs supporting only the `RGB` ISA extension fail on them instead of
+// executing a different instruction. The range is reserved for the
+// instructions of the `RGBX` extension.
+pub const INSTR_CNR: u8 = 0b11_100_000;
+pub const INSTR_RDV: u8 = 0b11_100_001;
+pub const INSTR_WTH: u8 = 0b11_100_010;
+pub const INSTR_WTT: u8 = 0b11_100_011;
+pub const INSTR_ORA: u8 = 0b11_100_100;
+pub const INSTR_ORS: u8 = 0b11_100_101;
+// Reserved 0b11_100_110
+// Reserved 0b11_100_111
+pub const INSTR_RGBX_FROM: u8 = 0b11_100_000;
+pub const INSTR_RGBX_TO: u8 = 0b11_100_111;
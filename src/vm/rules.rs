@@ -0,0 +1,588 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2024 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compiler for declarative validation rules.
+//!
+//! Simple consensus conditions, like "the sum of the issued amounts must not
+//! exceed the cap stored in metadata", can be authored as readable rule
+//! expressions and compiled into AluVM bytecode when the schema is built:
+//!
+//! ```text
+//! sum(2000) <= meta(1) && count_out(2000) > 0 && height >= 800000
+//! ```
+//!
+//! Compiled rules can be used as the genesis validation script, checking
+//! issuance-time invariants like `sum(2000) == global(2010)` (issued amounts
+//! match the declared supply).
+//!
+//! A rule set is a conjunction (`&&`) of comparisons (`==`, `!=`, `<`, `<=`,
+//! `>`, `>=`) between two expressions. Expressions add (`+`) or subtract (`-`)
+//! unsigned 64-bit operands:
+//!
+//! - integer literals;
+//! - `sum(T)`: sum of the owned fungible state of type `T` assigned by the
+//!   operation; zero if there are no such assignments;
+//! - `count_in(T)`, `count_out(T)`: number of inputs or outputs of the owned
+//!   state type `T`;
+//! - `count_global(T)`: number of global state items of type `T` defined by the
+//!   operation;
+//! - `meta(T)`: first eight bytes of the metadata of type `T`, read as a
+//!   little-endian integer;
+//! - `global(T)`: first eight bytes of the first global state item of type `T`
+//!   defined by the operation, read as a little-endian integer;
+//! - `height`, `time`: block height and timestamp of the operation witness.
+//!
+//! Compilation is deterministic: the same rule string always produces the
+//! same library, and hence the same library id. Any operand which can't be
+//! computed (concealed state, absent metadata, arithmetic overflow or
+//! underflow, unmined witness) makes the comparison using it fail. Witness
+//! operands are not available to genesis and state extensions, which always
+//! fail rules using them.
+
+use std::str::FromStr;
+
+use aluvm::data::{MaybeNumber, Number, Step};
+use aluvm::isa::{
+    ArithmeticOp, BytesOp, CmpOp, ControlFlowOp, Instr, IntFlags, MoveOp, NoneEqFlag, PutOp,
+    SignFlag,
+};
+use aluvm::library::{Lib, LibSite};
+use aluvm::reg::{Reg16, Reg32, RegA, RegAR, RegS};
+use amplify::num::{u4, u5};
+
+use super::{ContractOp, RgbIsa};
+use crate::{AssignmentType, GlobalStateType, MetaType};
+
+/// Errors parsing or compiling a rule expression.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum RuleError {
+    /// rule expression is empty.
+    Empty,
+
+    /// unexpected character '{0}' at position {1} of the rule expression.
+    UnexpectedChar(char, usize),
+
+    /// unexpected '{0}' at position {1} of the rule expression.
+    UnexpectedToken(String, usize),
+
+    /// rule expression ends unexpectedly.
+    UnexpectedEnd,
+
+    /// unknown rule operand '{0}' at position {1}.
+    UnknownOperand(String, usize),
+
+    /// integer literal '{0}' at position {1} doesn't fit into 64 bits.
+    InvalidNumber(String, usize),
+
+    /// state type {0} at position {1} doesn't fit into 16 bits.
+    InvalidType(u64, usize),
+
+    /// compiled rule exceeds the maximum size of an AluVM library.
+    CodeTooLarge,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+enum Token {
+    Ident(String),
+    Int(String),
+    Open,
+    Close,
+    Plus,
+    Minus,
+    And,
+    Rel(Relation),
+}
+
+impl Token {
+    fn lexeme(&self) -> String {
+        match self {
+            Token::Ident(s) | Token::Int(s) => s.clone(),
+            Token::Open => s!("("),
+            Token::Close => s!(")"),
+            Token::Plus => s!("+"),
+            Token::Minus => s!("-"),
+            Token::And => s!("&&"),
+            Token::Rel(rel) => rel.to_string(),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<(Token, usize)>, RuleError> {
+    let mut tokens = vec![];
+    let mut chars = s.char_indices().peekable();
+    while let Some((pos, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '&' if chars.next_if(|(_, c)| *c == '&').is_some() => Token::And,
+            '=' if chars.next_if(|(_, c)| *c == '=').is_some() => Token::Rel(Relation::Eq),
+            '!' if chars.next_if(|(_, c)| *c == '=').is_some() => Token::Rel(Relation::Ne),
+            '<' if chars.next_if(|(_, c)| *c == '=').is_some() => Token::Rel(Relation::Le),
+            '>' if chars.next_if(|(_, c)| *c == '=').is_some() => Token::Rel(Relation::Ge),
+            '<' => Token::Rel(Relation::Lt),
+            '>' => Token::Rel(Relation::Gt),
+            c if c.is_ascii_digit() || c.is_ascii_lowercase() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some((_, c)) =
+                    chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+                {
+                    word.push(c);
+                }
+                if c.is_ascii_digit() {
+                    Token::Int(word)
+                } else {
+                    Token::Ident(word)
+                }
+            }
+            c => return Err(RuleError::UnexpectedChar(c, pos)),
+        };
+        tokens.push((token, pos));
+    }
+    Ok(tokens)
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display)]
+enum Relation {
+    #[display("==")]
+    Eq,
+    #[display("!=")]
+    Ne,
+    #[display("<")]
+    Lt,
+    #[display("<=")]
+    Le,
+    #[display(">")]
+    Gt,
+    #[display(">=")]
+    Ge,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Operand {
+    Literal(u64),
+    Sum(AssignmentType),
+    CountIn(AssignmentType),
+    CountOut(AssignmentType),
+    CountGlobal(GlobalStateType),
+    Meta(MetaType),
+    Global(GlobalStateType),
+    Height,
+    Time,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Expr {
+    first: Operand,
+    /// Subsequent operands with a flag indicating subtraction.
+    rest: Vec<(bool, Operand)>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Rule {
+    lhs: Expr,
+    relation: Relation,
+    rhs: Expr,
+}
+
+/// Parsed conjunction of validation rules, ready to be compiled into an AluVM
+/// library with [`RuleSet::compile`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct RuleSet(Vec<Rule>);
+
+impl FromStr for RuleSet {
+    type Err = RuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        if tokens.is_empty() {
+            return Err(RuleError::Empty);
+        }
+        let mut parser = Parser { tokens, pos: 0 };
+        let mut rules = vec![parser.rule()?];
+        while parser.next_if(&Token::And) {
+            rules.push(parser.rule()?);
+        }
+        if let Some((token, pos)) = parser.tokens.get(parser.pos) {
+            return Err(RuleError::UnexpectedToken(token.lexeme(), *pos));
+        }
+        Ok(RuleSet(rules))
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn advance(&mut self) -> Result<(Token, usize), RuleError> {
+        let next = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or(RuleError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(next)
+    }
+
+    fn next_if(&mut self, expected: &Token) -> bool {
+        if matches!(self.tokens.get(self.pos), Some((token, _)) if token == expected) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), RuleError> {
+        match self.advance()? {
+            (token, _) if token == expected => Ok(()),
+            (token, pos) => Err(RuleError::UnexpectedToken(token.lexeme(), pos)),
+        }
+    }
+
+    fn rule(&mut self) -> Result<Rule, RuleError> {
+        let lhs = self.expr()?;
+        let relation = match self.advance()? {
+            (Token::Rel(relation), _) => relation,
+            (token, pos) => return Err(RuleError::UnexpectedToken(token.lexeme(), pos)),
+        };
+        let rhs = self.expr()?;
+        Ok(Rule { lhs, relation, rhs })
+    }
+
+    fn expr(&mut self) -> Result<Expr, RuleError> {
+        let first = self.operand()?;
+        let mut rest = vec![];
+        loop {
+            if self.next_if(&Token::Plus) {
+                rest.push((false, self.operand()?));
+            } else if self.next_if(&Token::Minus) {
+                rest.push((true, self.operand()?));
+            } else {
+                break;
+            }
+        }
+        Ok(Expr { first, rest })
+    }
+
+    fn operand(&mut self) -> Result<Operand, RuleError> {
+        let (token, pos) = self.advance()?;
+        let name = match token {
+            Token::Int(s) => return parse_int(&s, pos).map(Operand::Literal),
+            Token::Ident(name) => name,
+            token => return Err(RuleError::UnexpectedToken(token.lexeme(), pos)),
+        };
+        Ok(match name.as_str() {
+            "height" => Operand::Height,
+            "time" => Operand::Time,
+            "sum" => Operand::Sum(AssignmentType::with(self.type_arg()?)),
+            "count_in" => Operand::CountIn(AssignmentType::with(self.type_arg()?)),
+            "count_out" => Operand::CountOut(AssignmentType::with(self.type_arg()?)),
+            "count_global" => Operand::CountGlobal(GlobalStateType::with(self.type_arg()?)),
+            "meta" => Operand::Meta(MetaType::with(self.type_arg()?)),
+            "global" => Operand::Global(GlobalStateType::with(self.type_arg()?)),
+            _ => return Err(RuleError::UnknownOperand(name, pos)),
+        })
+    }
+
+    fn type_arg(&mut self) -> Result<u16, RuleError> {
+        self.expect(Token::Open)?;
+        let ty = match self.advance()? {
+            (Token::Int(s), pos) => {
+                let ty = parse_int(&s, pos)?;
+                u16::try_from(ty).map_err(|_| RuleError::InvalidType(ty, pos))?
+            }
+            (token, pos) => return Err(RuleError::UnexpectedToken(token.lexeme(), pos)),
+        };
+        self.expect(Token::Close)?;
+        Ok(ty)
+    }
+}
+
+fn parse_int(s: &str, pos: usize) -> Result<u64, RuleError> {
+    u64::from_str(s).map_err(|_| RuleError::InvalidNumber(s.to_owned(), pos))
+}
+
+// Register allocation. Each side of a comparison is computed into its own
+// pair of `a64` registers: the accumulator and a spare one for the next
+// operand.
+const LHS: (u8, u8) = (0, 1);
+const RHS: (u8, u8) = (2, 3);
+// Scratch registers used while loading operands.
+const A64_ITEM: u8 = 7;
+const A_COUNT: u8 = 0;
+const A16_INDEX: u8 = 1;
+const S16_DATA: u8 = 0;
+
+const CHECKED: IntFlags = IntFlags {
+    signed: false,
+    wrap: false,
+};
+
+fn a16(idx: u8) -> Reg16 { Reg16::from(u4::with(idx)) }
+fn a32(idx: u8) -> Reg32 { Reg32::from(u5::with(idx)) }
+fn contract(op: ContractOp) -> Instr<RgbIsa> { Instr::ExtensionCodes(RgbIsa::Contract(op)) }
+fn put_a(reg: RegA, idx: u8, val: Number) -> Instr<RgbIsa> {
+    Instr::Put(PutOp::PutA(reg, a32(idx), Box::new(MaybeNumber::from(val))))
+}
+
+#[derive(Default)]
+struct Emitter {
+    code: Vec<Instr<RgbIsa>>,
+}
+
+impl Emitter {
+    fn push(&mut self, instr: Instr<RgbIsa>) -> usize {
+        self.code.push(instr);
+        self.code.len() - 1
+    }
+
+    /// Byte offset of the next instruction in the library code.
+    fn offset(&self) -> Result<u16, RuleError> {
+        let lib = Lib::assemble(&self.code).map_err(|_| RuleError::CodeTooLarge)?;
+        u16::try_from(lib.code.as_ref().len()).map_err(|_| RuleError::CodeTooLarge)
+    }
+
+    /// Sets target of a previously emitted jump instruction. Jumps have
+    /// fixed-size encoding, so patching doesn't change any offsets.
+    fn patch(&mut self, at: usize, target: u16) {
+        let jump = match self.code[at] {
+            Instr::ControlFlow(ControlFlowOp::Jif(_)) => ControlFlowOp::Jif(target),
+            Instr::ControlFlow(ControlFlowOp::Jmp(_)) => ControlFlowOp::Jmp(target),
+            _ => unreachable!("patching non-jump instruction"),
+        };
+        self.code[at] = Instr::ControlFlow(jump);
+    }
+
+    /// Puts the value of the operand into `a64[dst]`.
+    fn operand(&mut self, operand: Operand, dst: u8) -> Result<(), RuleError> {
+        match operand {
+            Operand::Literal(val) => {
+                self.push(put_a(RegA::A64, dst, Number::from(val)));
+            }
+            Operand::Sum(ty) => self.sum(ty, dst)?,
+            Operand::CountIn(ty) => {
+                self.push(contract(ContractOp::CnP(ty, a32(A_COUNT))));
+                self.widen(RegA::A16, A_COUNT, dst);
+            }
+            Operand::CountOut(ty) => {
+                self.push(contract(ContractOp::CnS(ty, a32(A_COUNT))));
+                self.widen(RegA::A16, A_COUNT, dst);
+            }
+            Operand::CountGlobal(ty) => {
+                self.push(contract(ContractOp::CnG(ty, a32(A_COUNT))));
+                self.widen(RegA::A8, A_COUNT, dst);
+            }
+            Operand::Meta(ty) => {
+                self.push(contract(ContractOp::LdM(ty, RegS::from(S16_DATA))));
+                self.extract(dst);
+            }
+            Operand::Global(ty) => {
+                self.push(put_a(RegA::A8, A16_INDEX, Number::from(0u8)));
+                self.push(contract(ContractOp::LdG(ty, a16(A16_INDEX), RegS::from(S16_DATA))));
+                self.extract(dst);
+            }
+            Operand::Height => {
+                self.push(contract(ContractOp::WtH(a32(0))));
+                self.push(Instr::Move(MoveOp::CpyA(RegA::A32, a32(0), RegA::A64, a32(dst))));
+            }
+            Operand::Time => {
+                self.push(contract(ContractOp::WtT(a32(dst))));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the first eight bytes of the data loaded into the scratch `s16`
+    /// register into `a64[dst]`.
+    fn extract(&mut self, dst: u8) {
+        self.push(put_a(RegA::A16, A16_INDEX, Number::from(0u16)));
+        self.push(Instr::Bytes(BytesOp::Extr(
+            RegS::from(S16_DATA),
+            RegAR::A(RegA::A64),
+            a16(dst),
+            a16(A16_INDEX),
+        )));
+    }
+
+    /// Zero-extends a counter into `a64[dst]`, treating absent state as zero.
+    fn widen(&mut self, reg: RegA, idx: u8, dst: u8) {
+        let zero = match reg {
+            RegA::A8 => Number::from(0u8),
+            _ => Number::from(0u16),
+        };
+        self.push(Instr::Put(PutOp::PutIfA(reg, a32(idx), Box::new(MaybeNumber::from(zero)))));
+        self.push(Instr::Move(MoveOp::CpyA(reg, a32(idx), RegA::A64, a32(dst))));
+    }
+
+    /// Emits a loop summing all owned fungible state of the given type into
+    /// `a64[dst]`.
+    fn sum(&mut self, ty: AssignmentType, dst: u8) -> Result<(), RuleError> {
+        self.push(contract(ContractOp::CnS(ty, a32(A_COUNT))));
+        self.push(put_a(RegA::A16, A16_INDEX, Number::from(0u16)));
+        self.push(put_a(RegA::A64, dst, Number::from(0u64)));
+        let start = self.offset()?;
+        self.push(Instr::Cmp(CmpOp::LtA(
+            SignFlag::Unsigned,
+            RegA::A16,
+            a32(A16_INDEX),
+            a32(A_COUNT),
+        )));
+        let to_body = self.push(Instr::ControlFlow(ControlFlowOp::Jif(0)));
+        let to_end = self.push(Instr::ControlFlow(ControlFlowOp::Jmp(0)));
+        let body = self.offset()?;
+        self.patch(to_body, body);
+        self.push(contract(ContractOp::LdF(ty, a16(A16_INDEX), a16(A64_ITEM))));
+        self.push(Instr::Arithmetic(ArithmeticOp::AddA(
+            CHECKED,
+            RegA::A64,
+            a32(A64_ITEM),
+            a32(dst),
+        )));
+        self.push(Instr::Arithmetic(ArithmeticOp::Stp(RegA::A16, a32(A16_INDEX), Step::with(1))));
+        self.push(Instr::ControlFlow(ControlFlowOp::Jmp(start)));
+        let end = self.offset()?;
+        self.patch(to_end, end);
+        Ok(())
+    }
+
+    /// Computes the expression, returning the index of the `a64` register
+    /// holding the result.
+    fn expr(&mut self, expr: &Expr, (mut acc, mut spare): (u8, u8)) -> Result<u8, RuleError> {
+        self.operand(expr.first, acc)?;
+        for (sub, operand) in &expr.rest {
+            self.operand(*operand, spare)?;
+            // Arithmetic operations put the result into the second register.
+            if *sub {
+                self.push(Instr::Arithmetic(ArithmeticOp::SubA(
+                    CHECKED,
+                    RegA::A64,
+                    a32(acc),
+                    a32(spare),
+                )));
+                (acc, spare) = (spare, acc);
+            } else {
+                self.push(Instr::Arithmetic(ArithmeticOp::AddA(
+                    CHECKED,
+                    RegA::A64,
+                    a32(spare),
+                    a32(acc),
+                )));
+            }
+        }
+        Ok(acc)
+    }
+
+    /// Emits a rule which terminates the program with a failure unless the
+    /// comparison holds.
+    fn rule(&mut self, rule: &Rule) -> Result<(), RuleError> {
+        let lhs = self.expr(&rule.lhs, LHS)?;
+        let rhs = self.expr(&rule.rhs, RHS)?;
+        let (lhs, rhs) = (a32(lhs), a32(rhs));
+        let lt = CmpOp::LtA(SignFlag::Unsigned, RegA::A64, lhs, rhs);
+        let gt = CmpOp::GtA(SignFlag::Unsigned, RegA::A64, lhs, rhs);
+        let eq = CmpOp::EqA(NoneEqFlag::NonEqual, RegA::A64, lhs, rhs);
+        // Each relation is expressed as a disjunction of strict comparisons,
+        // which never hold for `None` operands.
+        let checks = match rule.relation {
+            Relation::Eq => vec![eq],
+            Relation::Ne => vec![lt, gt],
+            Relation::Lt => vec![lt],
+            Relation::Le => vec![lt, eq],
+            Relation::Gt => vec![gt],
+            Relation::Ge => vec![gt, eq],
+        };
+        let mut jumps = vec![];
+        for check in checks {
+            self.push(Instr::Cmp(check));
+            jumps.push(self.push(Instr::ControlFlow(ControlFlowOp::Jif(0))));
+        }
+        self.push(Instr::ControlFlow(ControlFlowOp::Fail));
+        let next = self.offset()?;
+        for jump in jumps {
+            self.patch(jump, next);
+        }
+        Ok(())
+    }
+}
+
+impl RuleSet {
+    /// Compiles the rules into an AluVM library. The script must be invoked
+    /// from the [`RuleSet::entry_point`] of the returned library.
+    pub fn compile(&self) -> Result<Lib, RuleError> {
+        let mut emitter = Emitter::default();
+        for rule in &self.0 {
+            emitter.rule(rule)?;
+        }
+        emitter.push(Instr::ControlFlow(ControlFlowOp::Ret));
+        Lib::assemble(&emitter.code).map_err(|_| RuleError::CodeTooLarge)
+    }
+
+    /// Returns the entry point of a library compiled from a rule set, to be
+    /// used as an operation validator in the schema.
+    pub fn entry_point(lib: &Lib) -> LibSite { LibSite::with(0, lib.id()) }
+}
+
+/// Parses and compiles rule expression into an AluVM library.
+pub fn compile_rules(rules: &str) -> Result<Lib, RuleError> { RuleSet::from_str(rules)?.compile() }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn compile() {
+        let src = "sum(2000) - 5 <= meta(1) && count_out(2000) != 0 && height >= 800000";
+        let lib = compile_rules(src).unwrap();
+        assert_eq!(lib, compile_rules(src).unwrap());
+        let code = lib.disassemble::<Instr<RgbIsa>>().unwrap();
+        assert_eq!(code.last(), Some(&Instr::ControlFlow(ControlFlowOp::Ret)));
+        assert_eq!(
+            code.iter()
+                .filter(|instr| **instr == Instr::ControlFlow(ControlFlowOp::Fail))
+                .count(),
+            3
+        );
+
+        let issuance = compile_rules("sum(2000) == global(2010)").unwrap();
+        let code = issuance.disassemble::<Instr<RgbIsa>>().unwrap();
+        assert!(code.contains(&contract(ContractOp::LdG(
+            GlobalStateType::with(2010),
+            a16(A16_INDEX),
+            RegS::from(S16_DATA)
+        ))));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(compile_rules(" "), Err(RuleError::Empty));
+        assert_eq!(compile_rules("sum(1) <"), Err(RuleError::UnexpectedEnd));
+        assert_eq!(compile_rules("sum(1) = 2"), Err(RuleError::UnexpectedChar('=', 7)));
+        assert_eq!(compile_rules("supply(1) > 0"), Err(RuleError::UnknownOperand(s!("supply"), 0)));
+        assert_eq!(compile_rules("meta(70000) > 0"), Err(RuleError::InvalidType(70000, 5)));
+        assert_eq!(compile_rules("1 < 2 3"), Err(RuleError::UnexpectedToken(s!("3"), 6)));
+    }
+}